@@ -1,3 +1,12 @@
+// Building for the browser (the `wasm` feature, see src/wasm.rs) also
+// compiles the C of tree-sitter and the markdown grammar for
+// wasm32-unknown-unknown, which needs a clang that can target it:
+//
+//     CC_wasm32_unknown_unknown=clang AR_wasm32_unknown_unknown=llvm-ar \
+//         cargo rustc --lib --release --target wasm32-unknown-unknown \
+//         --no-default-features --features wasm --crate-type cdylib
+//
+// tree-sitter brings the bits of libc the C needs for that target itself.
 fn main() {
     #[cfg(debug_assertions)]
     {
@@ -0,0 +1,53 @@
+use std::time::Instant;
+
+use mdvalidate::{Validator, mdschema::validation::validator::ValidateOptions};
+
+/// Compare serial and parallel validation of a large document with many
+/// independent top-level sections.
+///
+/// Run with `cargo run --release --example parallel`.
+fn main() {
+    let mut schema = String::new();
+    let mut input = String::new();
+    for i in 0..5000 {
+        schema.push_str(&format!(
+            "# Endpoint {i}\n\nReturns `returns_{i}:/\\w+/`\n\n- `param_{i}:/\\w+/`{{1,}}\n\n"
+        ));
+        input.push_str(&format!(
+            "# Endpoint {i}\n\nReturns Value{i}\n\n- first\n- second\n- third\n\n"
+        ));
+    }
+
+    let time = |options: ValidateOptions| {
+        let start = Instant::now();
+        let mut validator = Validator::new_complete(&schema, &input)
+            .expect("Failed to create validator")
+            .with_options(options);
+        validator.validate();
+        let (errors, matches) = validator.report();
+        let errors: Vec<_> = errors.cloned().collect();
+        (start.elapsed(), errors, matches.clone())
+    };
+
+    let (serial_time, serial_errors, serial_matches) = time(ValidateOptions::default());
+    println!("serial:      {:?}", serial_time);
+
+    for parallel_threads in [Some(2), Some(4), None] {
+        let (parallel_time, parallel_errors, parallel_matches) = time(ValidateOptions {
+            parallel: true,
+            parallel_threads,
+            ..Default::default()
+        });
+
+        assert_eq!(serial_errors, parallel_errors);
+        assert_eq!(serial_matches, parallel_matches);
+
+        let threads = parallel_threads.map_or("all".to_string(), |count| count.to_string());
+        println!(
+            "{:>3} threads: {:?} ({:.2}x)",
+            threads,
+            parallel_time,
+            serial_time.as_secs_f64() / parallel_time.as_secs_f64()
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use mdvalidate::{Validator, mdschema::validation::errors::pretty_print_error};
+use mdvalidate::{mdschema::validation::errors::pretty_print_error, validate_str};
 
 fn main() {
     // Define a simple schema: a heading with a name and a list
@@ -20,22 +20,16 @@ fn main() {
 - Banana
 "#;
 
-    // Create a validator and validate the input
-    let mut validator = Validator::new_complete(schema, input).expect("Failed to create validator");
-    validator.validate();
+    // Validate the input in one go
+    let validation = validate_str(schema, input).expect("Failed to create validator");
 
-    // Check for errors
-    let (errors, matches) = validator.report();
-
-    let error_vec: Vec<_> = errors.collect();
-
-    if error_vec.is_empty() {
+    if validation.is_valid {
         println!("✓ Validation successful!");
-        println!("Matches: {:?}", matches);
+        println!("Matches: {:?}", validation.matches);
     } else {
-        println!("✗ Validation failed with {} error(s):", error_vec.len());
-        for error in error_vec {
-            let pretty_print = pretty_print_error(error, &validator, "example.md")
+        println!("✗ Validation failed with {} error(s):", validation.errors.len());
+        for error in &validation.errors {
+            let pretty_print = pretty_print_error(error, &validation.validator, "example.md")
                 .expect("Failed to pretty print error");
             println!("{}", pretty_print);
         }
@@ -0,0 +1,32 @@
+//! Validate random inputs against a fixed schema.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mdvalidate::validate_str;
+
+const SCHEMA: &str = r#"# `title:/\w+/`
+
+Some *`style:/\w+/`* and **strong** text with `code:/\w+/`!.
+
+## Items
+
+- `item:/\w+/`{1,}
+  - `nested:/\w+/`{,}
+
+| Name | Value |
+| ---- | ----- |
+| `name:/\w+/` | `value:/\d+/` |{,}
+
+> `quote:/.*/`
+
+[`link_text:/.*/`](`url:/.*/`)
+
+```rust
+fn main() {}
+```
+"#;
+
+fuzz_target!(|data: &[u8]| {
+    let input = String::from_utf8_lossy(data);
+    let _ = validate_str(SCHEMA, &input);
+});
@@ -0,0 +1,33 @@
+//! Validate a fixed input against random schemas.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mdvalidate::validate_str;
+
+const INPUT: &str = r#"# Project
+
+Some *emphasized* and **strong** text with `code`.
+
+## Items
+
+- first
+- second
+  - nested
+
+| Name | Value |
+| ---- | ----- |
+| a    | 1     |
+
+> A quote
+
+[a link](https://example.com)
+
+```rust
+fn main() {}
+```
+"#;
+
+fuzz_target!(|data: &[u8]| {
+    let schema = String::from_utf8_lossy(data);
+    let _ = validate_str(&schema, INPUT);
+});
@@ -0,0 +1,66 @@
+#ifndef MDVALIDATE_H
+#define MDVALIDATE_H
+
+/* Generated with cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The outcome of validating an input against a schema.
+//
+// Opaque to C. Get at its contents with the `mdv_result_*` accessors.
+typedef struct MdvResult MdvResult;
+
+// Validate an input against a schema.
+//
+// Never returns null. Anything that stops the input from being validated,
+// like a null or non UTF-8 argument, or a panic, is reported as an error in
+// the result.
+//
+// # Safety
+//
+// `schema` and `input` must each be null or point to a NUL terminated string
+// that stays valid for the duration of the call.
+MdvResult *mdv_validate(const char *schema, const char *input);
+
+// Everything the matchers of the schema captured, as a JSON object.
+//
+// The string belongs to the result. Returns null if `result` is null.
+//
+// # Safety
+//
+// `result` must be null or a result from `mdv_validate` that hasn't been
+// freed yet.
+const char *mdv_result_matches_json(const MdvResult *result);
+
+// The errors in the input, as a JSON document with an `errors` array like
+// `mdv --error-format json` prints.
+//
+// The string belongs to the result. Returns null if `result` is null.
+//
+// # Safety
+//
+// `result` must be null or a result from `mdv_validate` that hasn't been
+// freed yet.
+const char *mdv_result_errors_json(const MdvResult *result);
+
+// Whether the input conforms to the schema. False if `result` is null.
+//
+// # Safety
+//
+// `result` must be null or a result from `mdv_validate` that hasn't been
+// freed yet.
+bool mdv_result_is_valid(const MdvResult *result);
+
+// Free a result, and the strings its accessors returned. Does nothing if
+// `result` is null.
+//
+// # Safety
+//
+// `result` must be null or a result from `mdv_validate` that hasn't been
+// freed yet.
+void mdv_result_free(MdvResult *result);
+
+#endif  /* MDVALIDATE_H */
@@ -1,14 +1,170 @@
 use crate::mdschema::validation::{
     errors::{
-        ParserError, PrettyPrintError, ValidationError, debug_print_error, pretty_print_error,
+        ParserError, PrettyPrintError, ValidationError, debug_print_error,
+        pretty_print_error_context, pretty_print_error_tree_diff, pretty_print_error_with_color,
     },
-    validator::{Validator, ValidatorState},
+    json_report::ValidatorReport,
+    match_stream::{MatchUpdate, done_record},
+    progress::{Progress, ProgressHook},
+    stats::ValidationStats,
+    validate::{
+        ReadInputError, Validation, validate_reader, validate_reader_streaming_matches,
+        validate_reader_with_progress,
+    },
+    validator::{ValidateOptions, Validator, ValidatorState},
 };
+use crate::path_or_stdio::STDIO_FILEPATH;
 use colored::Colorize;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tabled::{Table, Tabled, settings::Style};
+
+/// How many characters wide the progress bar is.
+const PROGRESS_BAR_WIDTH: usize = 40;
+
+/// How errors are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ErrorFormat {
+    /// Human readable reports, with the offending source highlighted.
+    #[default]
+    Pretty,
+    /// A single JSON document with an `errors` array (see `ValidatorReport`).
+    Json,
+    /// GitHub Actions workflow commands, which annotate the lines of the
+    /// errors in pull requests (see `github_annotations`).
+    Github,
+}
+
+/// Reports written to the output in place of the matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// A standalone HTML page with the input and its errors highlighted (see
+    /// `ValidatorReport::to_html`).
+    Html,
+}
+
+/// When to color output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Color output when it goes to a terminal and `NO_COLOR` isn't set.
+    #[default]
+    Auto,
+    /// Always color output.
+    Always,
+    /// Never color output.
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color output.
+    ///
+    /// # Arguments
+    ///
+    /// * `no_color`: Whether the `NO_COLOR` environment variable is set.
+    /// * `is_terminal`: Whether output goes to a terminal.
+    pub fn should_color(self, no_color: bool, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => !no_color && is_terminal,
+        }
+    }
+}
+
+/// What `mdv` exits with, from best to worst, so the worst of several
+/// outcomes is their maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExitStatus {
+    /// The input is valid (0).
+    Valid,
+    /// The input doesn't follow the schema (1).
+    Invalid,
+    /// The schema itself is broken, like an invalid regex or a schema that
+    /// can't be parsed (2).
+    SchemaError,
+    /// Reading the input or schema, or writing the output, failed (3).
+    IoError,
+}
 
-static DEFAULT_BUFFER_SIZE: usize = 2048;
+impl ExitStatus {
+    /// The process exit code.
+    pub fn code(self) -> i32 {
+        match self {
+            ExitStatus::Valid => 0,
+            ExitStatus::Invalid => 1,
+            ExitStatus::SchemaError => 2,
+            ExitStatus::IoError => 3,
+        }
+    }
+
+    /// The worst status of any of the errors, or `Valid` if there are none.
+    pub fn from_errors<'a>(errors: impl IntoIterator<Item = &'a ValidationError>) -> Self {
+        errors
+            .into_iter()
+            .map(ExitStatus::from)
+            .max()
+            .unwrap_or(ExitStatus::Valid)
+    }
+}
+
+impl From<&ValidationError> for ExitStatus {
+    fn from(error: &ValidationError) -> Self {
+        match error {
+            ValidationError::SchemaViolation(_) => ExitStatus::Invalid,
+            ValidationError::SchemaError(_)
+            | ValidationError::ValidatorCreationFailed
+            | ValidationError::InternalInvariantViolated(_) => ExitStatus::SchemaError,
+            ValidationError::IoError(_)
+            | ValidationError::ParserError(ParserError::ReadInputFailed(_))
+            | ValidationError::ParserError(ParserError::ReadAfterEOF) => ExitStatus::IoError,
+            ValidationError::ParserError(_) => ExitStatus::SchemaError,
+        }
+    }
+}
+
+impl From<&ProcessingError> for ExitStatus {
+    fn from(error: &ProcessingError) -> Self {
+        match error {
+            ProcessingError::Validation(error) => error.into(),
+            ProcessingError::ValidatorCreationFailed => ExitStatus::SchemaError,
+            ProcessingError::ReadInputFailed(_)
+            | ProcessingError::PrettyPrint(_)
+            | ProcessingError::Io(_)
+            | ProcessingError::Utf8(_)
+            | ProcessingError::InvalidUtf8(_) => ExitStatus::IoError,
+        }
+    }
+}
+
+/// Options that change how `process_stdio` validates an input and reports
+/// what it found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CliOptions {
+    /// Options that change how the input is validated, including whether to
+    /// stop at the first error.
+    pub validate: ValidateOptions,
+    /// Don't print the matches, the success message or warnings.
+    pub quiet: bool,
+    /// Follow each error report with the trees around it and the error's
+    /// debug representation.
+    pub debug_mode: bool,
+    /// How errors are printed.
+    pub error_format: ErrorFormat,
+    /// How many bytes the input has, to draw a progress bar towards. Without
+    /// it no progress bar is drawn.
+    pub progress_total_bytes: Option<usize>,
+    /// Whether to color reports.
+    pub color: bool,
+    /// Write each match to the output as a line of JSON as soon as it is
+    /// found (see `match_stream`).
+    pub stream_output: bool,
+    /// Always write the matches to the output, even when validation fails.
+    pub matches_only: bool,
+    /// A report to write to the output in place of the matches.
+    pub report_format: Option<ReportFormat>,
+}
 
 #[derive(Debug)]
 pub enum ProcessingError {
@@ -18,11 +174,17 @@ pub enum ProcessingError {
     PrettyPrint(PrettyPrintError),
     Io(std::io::Error),
     Utf8(std::str::Utf8Error),
+    /// The input contained bytes that aren't valid UTF-8, starting at this
+    /// byte offset.
+    InvalidUtf8(usize),
 }
 
 #[derive(Debug)]
 pub struct ProcessingResult {
     pub errors: Vec<ValidationError>,
+    /// Errors a suppression comment in the input or a severity override
+    /// turned into warnings.
+    pub suppressed_errors: Vec<ValidationError>,
     pub matches: Value,
     pub validator: Validator,
     pub input_str: String,
@@ -49,6 +211,9 @@ impl std::fmt::Display for ProcessingError {
             ProcessingError::PrettyPrint(e) => write!(f, "Pretty print error: {:?}", e),
             ProcessingError::Io(e) => write!(f, "IO error: {}", e),
             ProcessingError::Utf8(e) => write!(f, "UTF-8 error: {}", e),
+            ProcessingError::InvalidUtf8(offset) => {
+                write!(f, "Input is not valid UTF-8 (invalid byte at offset {})", offset)
+            }
         }
     }
 }
@@ -62,6 +227,7 @@ impl std::error::Error for ProcessingError {
             ProcessingError::PrettyPrint(_) => None,
             ProcessingError::Io(e) => Some(e),
             ProcessingError::Utf8(e) => Some(e),
+            ProcessingError::InvalidUtf8(_) => None,
         }
     }
 }
@@ -72,6 +238,17 @@ impl From<ValidationError> for ProcessingError {
     }
 }
 
+impl From<ReadInputError> for ProcessingError {
+    fn from(error: ReadInputError) -> Self {
+        match error {
+            ReadInputError::Validation(error) => ProcessingError::Validation(error),
+            ReadInputError::Io(error) => ProcessingError::Io(error),
+            ReadInputError::Utf8(error) => ProcessingError::Utf8(error),
+            ReadInputError::InvalidUtf8(offset) => ProcessingError::InvalidUtf8(offset),
+        }
+    }
+}
+
 impl From<ParserError> for ProcessingError {
     fn from(error: ParserError) -> Self {
         ProcessingError::Validation(ValidationError::ParserError(error))
@@ -88,44 +265,54 @@ impl ProcessingResult {
     pub fn process<R: Read>(
         schema_str: &str,
         input: &mut R,
-        fast_fail: bool,
+        options: &ValidateOptions,
     ) -> Result<ProcessingResult, ProcessingError> {
-        let buffer_size = get_buffer_size();
-
-        let mut input_str = String::new();
-        let mut buffer = vec![0; buffer_size];
-
-        let mut validator = Validator::new_incomplete(schema_str, input_str.as_str())
-            .ok_or(ValidationError::ValidatorCreationFailed)?;
-
-        loop {
-            let bytes_read = input.read(&mut buffer)?;
-
-            // If we're done reading, mark EOF
-            if bytes_read == 0 {
-                validator.read_final_input(&input_str)?;
-                validator.validate();
-
-                break;
-            }
-
-            let new_text = std::str::from_utf8(&buffer[..bytes_read])?;
-            input_str.push_str(new_text);
-
-            validator.read_more_input(&input_str)?;
-            validator.validate();
+        Self::process_with_progress(schema_str, input, options, None, None)
+    }
 
-            // Check for fast-fail AFTER validation
-            if fast_fail && validator.errors_so_far().count() > 0 {
-                break;
+    /// Like `process`, drawing a progress bar on stderr if we know how many
+    /// bytes the input has, and calling `on_match` with every match as soon
+    /// as it is found, if given.
+    pub fn process_with_progress<R: Read>(
+        schema_str: &str,
+        input: &mut R,
+        options: &ValidateOptions,
+        progress_total_bytes: Option<usize>,
+        on_match: Option<&mut dyn FnMut(MatchUpdate)>,
+    ) -> Result<ProcessingResult, ProcessingError> {
+        let progress =
+            progress_total_bytes.map(|total_bytes| (progress_bar_hook(), Some(total_bytes)));
+        let draws_progress_bar = progress.is_some();
+        let validation = match (on_match, progress) {
+            (Some(on_match), progress) => {
+                validate_reader_streaming_matches(schema_str, input, options, progress, on_match)
             }
+            (None, Some((progress_hook, total_bytes))) => validate_reader_with_progress(
+                schema_str,
+                input,
+                options,
+                progress_hook,
+                total_bytes,
+            ),
+            (None, None) => validate_reader(schema_str, input, options),
+        };
+        if draws_progress_bar {
+            clear_progress_bar();
         }
+        let validation = validation?;
 
-        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
-        let matches = validator.matches_so_far().clone();
+        let Validation {
+            errors,
+            suppressed_errors,
+            matches,
+            validator,
+            ..
+        } = validation;
+        let input_str = validator.last_input_str().to_string();
 
         Ok(ProcessingResult {
             errors,
+            suppressed_errors,
             matches,
             validator,
             input_str,
@@ -138,24 +325,95 @@ pub fn process_stdio<R: Read, W: Write>(
     input: &mut R,
     output: &mut Option<&mut W>,
     filename: &str,
-    fast_fail: bool,
-    quiet: bool,
-    debug_mode: bool,
-) -> Result<((Vec<ValidationError>, Value), bool), ProcessingError> {
+    schema_filename: &str,
+    options: &CliOptions,
+) -> Result<((Vec<ValidationError>, Value, Vec<ValidationError>), bool), ProcessingError> {
+    let CliOptions {
+        validate: _,
+        quiet,
+        debug_mode,
+        error_format,
+        progress_total_bytes,
+        color,
+        stream_output,
+        matches_only,
+        report_format,
+    } = *options;
+    let mut stream_error = None;
+    let mut write_match = |update: MatchUpdate| {
+        if stream_error.is_none() {
+            stream_error = write_json_line(output, &update.to_json()).err();
+        }
+    };
     let ProcessingResult {
         errors,
+        suppressed_errors,
         matches,
         validator,
         input_str: _input_str,
-    } = ProcessingResult::process(schema_str, input, fast_fail)?;
+    } = ProcessingResult::process_with_progress(
+        schema_str,
+        input,
+        &options.validate,
+        progress_total_bytes,
+        stream_output.then_some(&mut write_match as &mut dyn FnMut(MatchUpdate)),
+    )?;
+    if let Some(error) = stream_error {
+        return Err(error.into());
+    }
+    if stream_output {
+        write_json_line(output, &done_record(errors.is_empty()))?;
+    }
+
+    if error_format != ErrorFormat::Pretty {
+        let report = ValidatorReport::new(&validator);
+        match error_format {
+            ErrorFormat::Json => println!("{}", report.with_filename(filename).to_json()),
+            // Input from stdin isn't a file the annotations could point into
+            ErrorFormat::Github if filename == STDIO_FILEPATH => {
+                print!("{}", report.to_github_annotations())
+            }
+            ErrorFormat::Github => {
+                print!("{}", report.with_filename(filename).to_github_annotations())
+            }
+            ErrorFormat::Pretty => {}
+        }
+        if errors.is_empty()
+            && let Some(out) = output
+        {
+            writeln!(out, "{}", matches)?;
+        }
+        let errored = !errors.is_empty();
+        return Ok(((errors, matches, suppressed_errors), errored));
+    }
+
+    if let Some(ReportFormat::Html) = report_format {
+        let html = ValidatorReport::new(&validator)
+            .with_filename(filename)
+            .to_html();
+        match output {
+            Some(out) => write!(out, "{}", html)?,
+            None => print!("{}", html),
+        }
+    }
 
     let mut errored = false;
-    if errors.is_empty() {
+    if matches_only {
+        // Whatever was matched before the errors, for the caller to salvage
+        write_json_line(output, &matches)?;
+    } else if errors.is_empty() && report_format.is_none() {
         match (output, quiet) {
+            // The matches were written as they were found
+            _ if stream_output => {}
             (None, false) => {
+                let stats = ValidationStats::from_validator(&validator);
                 println!(
                     "{}",
-                    format!("File {} validated successfully! No errors found.", filename).green()
+                    format!(
+                        "File {} validated successfully! No errors found. {}.",
+                        filename, stats
+                    )
+                    .green()
                 );
             }
             (Some(out), false) => {
@@ -163,39 +421,176 @@ pub fn process_stdio<R: Read, W: Write>(
             }
             _ => {}
         }
-    } else {
-        for error in &errors {
-            let error_output = if debug_mode {
-                debug_print_error(error)
-            } else {
-                pretty_print_error(error, &validator, filename)?
-            };
-            eprintln!("{}", error_output);
-            errored = true;
+    }
+    for error in &errors {
+        eprintln!(
+            "{}",
+            pretty_print_error_with_color(error, &validator, filename, color)?
+        );
+        eprint!(
+            "{}",
+            pretty_print_error_context(error, &validator, filename, schema_filename, color)
+        );
+        if debug_mode {
+            if let Some(tree_diff) = pretty_print_error_tree_diff(error, &validator) {
+                eprintln!("{}", tree_diff);
+            }
+            eprintln!("{}", debug_print_error(error));
         }
+        errored = true;
+    }
+    if !quiet && errored {
+        let stats = ValidationStats::from_validator(&validator);
+        eprintln!(
+            "{}",
+            format!("{} error(s) found. {}.", errors.len(), stats).red()
+        );
     }
 
-    Ok(((errors, matches), errored))
+    if !quiet && !suppressed_errors.is_empty() {
+        for error in &suppressed_errors {
+            eprintln!(
+                "{}",
+                format!("warning[{}]: {}", error.code(), error).yellow()
+            );
+        }
+        eprintln!(
+            "{}",
+            format!("{} error(s) reported as warnings", suppressed_errors.len()).yellow()
+        );
+    }
+
+    Ok(((errors, matches, suppressed_errors), errored))
 }
 
-fn get_buffer_size() -> usize {
-    std::env::var("BUFFER_SIZE")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(DEFAULT_BUFFER_SIZE)
+/// Write a JSON Lines record to the output, or stdout if there is none, and
+/// flush it so that it can be read right away.
+fn write_json_line<W: Write>(output: &mut Option<&mut W>, record: &Value) -> std::io::Result<()> {
+    match output {
+        Some(out) => {
+            writeln!(out, "{}", record)?;
+            out.flush()
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            writeln!(stdout, "{}", record)?;
+            stdout.flush()
+        }
+    }
+}
+
+/// How validating one of several inputs went, a row of the summary printed
+/// after validating them all (see `summary_table`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputSummary {
+    /// The path of the input.
+    pub file: String,
+    /// How many errors the input has.
+    pub errors: usize,
+    /// How many errors were reported as warnings.
+    pub warnings: usize,
+    /// What `mdv` would exit with for this input alone.
+    pub status: ExitStatus,
+}
+
+/// A table of how validating each input went, with the number of errors
+/// and warnings of each and whether it's valid.
+pub fn summary_table(summaries: &[InputSummary]) -> String {
+    #[derive(Tabled)]
+    struct Row<'a> {
+        #[tabled(rename = "File")]
+        file: &'a str,
+        #[tabled(rename = "Errors")]
+        errors: usize,
+        #[tabled(rename = "Warnings")]
+        warnings: usize,
+        #[tabled(rename = "Valid")]
+        valid: &'static str,
+    }
+
+    let rows = summaries.iter().map(|summary| Row {
+        file: &summary.file,
+        errors: summary.errors,
+        warnings: summary.warnings,
+        valid: if summary.status == ExitStatus::Valid {
+            "yes"
+        } else {
+            "no"
+        },
+    });
+
+    Table::new(rows).with(Style::sharp()).to_string()
+}
+
+/// Where to write the output of each input, with `--output-dir`: a file in
+/// the output directory named after the input without its extension, like
+/// `out/intro.json` for `docs/intro.md`.
+///
+/// # Errors
+///
+/// If two inputs would write the same file, like `a/intro.md` and
+/// `b/intro.md`.
+pub fn output_paths(
+    inputs: &[PathBuf],
+    output_dir: &Path,
+    extension: &str,
+) -> Result<Vec<PathBuf>, String> {
+    let mut written_by: HashMap<PathBuf, &Path> = HashMap::new();
+    inputs
+        .iter()
+        .map(|input| {
+            let mut name = input.file_stem().unwrap_or(input.as_os_str()).to_owned();
+            name.push(".");
+            name.push(extension);
+            let output = output_dir.join(name);
+            match written_by.insert(output.clone(), input) {
+                Some(other) => Err(format!(
+                    "Inputs '{}' and '{}' would both write '{}'",
+                    other.display(),
+                    input.display(),
+                    output.display()
+                )),
+                None => Ok(output),
+            }
+        })
+        .collect()
+}
+
+/// A progress hook that draws a progress bar on stderr, like
+/// `[##########          ]  50% (3 errors)`.
+fn progress_bar_hook() -> ProgressHook {
+    Box::new(|progress: Progress| {
+        let Some(total_bytes) = progress.total_bytes.filter(|total_bytes| *total_bytes > 0) else {
+            return;
+        };
+        let percent = (progress.bytes_processed * 100 / total_bytes).min(100);
+        let filled = percent * PROGRESS_BAR_WIDTH / 100;
+
+        eprint!(
+            "\r[{}{}] {:>3}% ({} errors)",
+            "#".repeat(filled),
+            " ".repeat(PROGRESS_BAR_WIDTH - filled),
+            percent,
+            progress.errors
+        );
+        let _ = std::io::stderr().flush();
+    })
+}
+
+/// Erase the progress bar, so that the report starts on a clean line.
+fn clear_progress_bar() {
+    eprint!("\r\x1B[2K");
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mdschema::validation::errors::SchemaViolationError;
+    use crate::mdschema::validation::validate::validate_str_with_options;
     use std::io::{self, Cursor, Read};
 
-    fn run_validation<R: Read>(
-        schema: &String,
-        mut input: R,
-        fast_fail: bool,
-    ) -> (Vec<ValidationError>, Value) {
-        let result = ProcessingResult::process(schema, &mut input, fast_fail)
+    fn run_validation<R: Read>(schema: &String, mut input: R) -> (Vec<ValidationError>, Value) {
+        let result = ProcessingResult::process(schema, &mut input, &ValidateOptions::default())
             .expect("Validation should complete without errors");
 
         (result.errors, result.matches)
@@ -266,7 +661,7 @@ mod tests {
         let input_data = "# Hi there!";
         let reader = Cursor::new(input_data.as_bytes());
 
-        let (errors, _) = run_validation(&schema_str, reader, false);
+        let (errors, _) = run_validation(&schema_str, reader);
         assert_eq!(errors, vec![]);
     }
 
@@ -277,7 +672,7 @@ mod tests {
         let cursor = Cursor::new(input_data.as_bytes());
         let reader = LimitedReader::new(cursor, 2);
 
-        let (errors, _) = run_validation(&schema_str, reader, false);
+        let (errors, _) = run_validation(&schema_str, reader);
         assert!(
             errors.is_empty(),
             "Should have no errors for matching content"
@@ -291,7 +686,7 @@ mod tests {
         let cursor = Cursor::new(input_data.as_bytes());
         let reader = LimitedReader::new(cursor, 1000);
 
-        let (errors, _) = run_validation(&schema_str, reader, false);
+        let (errors, _) = run_validation(&schema_str, reader);
         assert!(
             errors.is_empty(),
             "Should have no errors for matching content"
@@ -318,7 +713,7 @@ mod tests {
         let cursor = Cursor::new(input_data.as_bytes());
         let reader = LimitedReader::new(cursor, 4);
 
-        let (errors, _) = run_validation(&schema_str.into(), reader, false);
+        let (errors, _) = run_validation(&schema_str.into(), reader);
         assert!(
             errors.is_empty(),
             "should have no errors but found: {:?}",
@@ -356,7 +751,7 @@ This is a shopping list:
         let cursor = Cursor::new(input_data.as_bytes());
         let reader = LimitedReader::new(cursor, 4);
 
-        let (errors, _) = run_validation(&schema_str, reader, false);
+        let (errors, _) = run_validation(&schema_str, reader);
         assert!(
             errors.is_empty(),
             "should have no errors but found: {:?}",
@@ -386,7 +781,7 @@ This is a test"#;
             let cursor = Cursor::new(input_data.as_bytes());
             let reader = LimitedReader::new(cursor, cursor_size);
 
-            let (errors, matches) = run_validation(&schema_str, reader, false);
+            let (errors, matches) = run_validation(&schema_str, reader);
             assert_eq!(
                 errors.len(),
                 1,
@@ -397,6 +792,88 @@ This is a test"#;
         }
     }
 
+    #[test]
+    fn test_bom_prefixed_input_is_stripped() {
+        let schema_str = "# Hi `name:/[A-Za-z]+/`".to_string();
+        let input_data = "\u{FEFF}# Hi Wolf";
+
+        // Also split the BOM itself across reads
+        for cursor_size in [1, 2, 1000] {
+            let cursor = Cursor::new(input_data.as_bytes());
+            let reader = LimitedReader::new(cursor, cursor_size);
+
+            let (errors, matches) = run_validation(&schema_str, reader);
+            assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+            assert_eq!(matches, serde_json::json!({"name": "Wolf"}));
+        }
+    }
+
+    #[test]
+    fn test_multi_byte_character_next_to_matcher() {
+        let schema_str = "😀 Hi `name:/[A-Za-z]+/`😀".to_string();
+
+        // Every read size splits the emojis somewhere
+        for cursor_size in 1..=5 {
+            let cursor = Cursor::new("😀 Hi Wolf😀".as_bytes());
+            let (errors, matches) =
+                run_validation(&schema_str, LimitedReader::new(cursor, cursor_size));
+            assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+            assert_eq!(matches, serde_json::json!({"name": "Wolf"}));
+
+            // A shorter multi-byte character where the schema has the emoji
+            // used to slice the schema text in the middle of the emoji
+            let cursor = Cursor::new("😀 Hi Wolfé".as_bytes());
+            let (errors, _) = run_validation(&schema_str, LimitedReader::new(cursor, cursor_size));
+            assert!(!errors.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_invalid_utf8_input_is_an_error() {
+        let schema_str = "# Hi `name`".to_string();
+        let mut input_data = b"# Hi ".to_vec();
+        input_data.extend_from_slice(&[0xff, 0xfe]);
+
+        let cursor = Cursor::new(input_data);
+        let mut reader = LimitedReader::new(cursor, 2);
+        let result =
+            ProcessingResult::process(&schema_str, &mut reader, &ValidateOptions::default());
+        assert!(matches!(result, Err(ProcessingError::InvalidUtf8(5))));
+
+        // A character cut off by the end of the input
+        let mut cursor = Cursor::new("# Hi 😀".as_bytes()[..7].to_vec());
+        let result =
+            ProcessingResult::process(&schema_str, &mut cursor, &ValidateOptions::default());
+        assert!(matches!(result, Err(ProcessingError::InvalidUtf8(5))));
+    }
+
+    #[test]
+    fn test_invalid_utf8_in_the_middle_of_input_is_an_error() {
+        let schema_str = "# Hi `name`\n\nSome text\n".to_string();
+
+        // Latin-1 for "Café", and a lead byte that isn't followed by the rest
+        // of its character, which some reads only see the start of
+        for input_data in [
+            &b"# Hi Caf\xe9\n\nSome text\n"[..],
+            b"# Hi Caf\xc3(\n\nSome text\n",
+        ] {
+            for cursor_size in 1..=4 {
+                let cursor = Cursor::new(input_data.to_vec());
+                let mut reader = LimitedReader::new(cursor, cursor_size);
+                let result = ProcessingResult::process(
+                    &schema_str,
+                    &mut reader,
+                    &ValidateOptions::default(),
+                );
+                assert!(
+                    matches!(result, Err(ProcessingError::InvalidUtf8(8))),
+                    "Reading {} bytes at a time",
+                    cursor_size
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_process_stdio_with_fake_writer_gets_json_output() {
         let schema_str = "# Hi `name:/[A-Za-z]+/`".to_string();
@@ -411,9 +888,8 @@ This is a test"#;
             &mut reader,
             &mut output_option,
             "test.md",
-            false,
-            false,
-            false,
+            "test.mds",
+            &CliOptions::default(),
         )
         .unwrap();
 
@@ -427,4 +903,282 @@ This is a test"#;
             "Output JSON should match expected matches"
         );
     }
+
+    #[test]
+    fn test_process_stdio_output_is_byte_stable() {
+        let schema_str = "# Hi `zeta:/\\w+/`\n\n- `alpha:/\\w+/`{1,}\n\nBy `mid:/\\w+/`\n";
+        let input_data = "# Hi Wolf\n\n- one\n- two\n\nBy Alice\n";
+
+        let run = |options: &ValidateOptions| {
+            let cursor = Cursor::new(input_data.as_bytes());
+            let mut reader = LimitedReader::new(cursor, 4);
+            let mut output: Vec<u8> = Vec::new();
+            let mut output_option: Option<&mut Vec<u8>> = Some(&mut output);
+            let (_, errored) = process_stdio(
+                schema_str,
+                &mut reader,
+                &mut output_option,
+                "test.md",
+                "test.mds",
+                &CliOptions {
+                    validate: options.clone(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            assert!(!errored);
+            output
+        };
+
+        let output = run(&ValidateOptions::default());
+        assert_eq!(output, run(&ValidateOptions::default()));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"zeta\":\"Wolf\",\"alpha\":[\"one\",\"two\"],\"mid\":\"Alice\"}\n"
+        );
+
+        let sorted = ValidateOptions {
+            sort_keys: true,
+            ..Default::default()
+        };
+        let output = run(&sorted);
+        assert_eq!(output, run(&sorted));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"alpha\":[\"one\",\"two\"],\"mid\":\"Alice\",\"zeta\":\"Wolf\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_process_stdio_matches_only_writes_partial_matches() {
+        let schema_str = "# Hi `name:/[A-Za-z]+/`\n\nAge: `age:/\\d+/`\n".to_string();
+        let input_data = "# Hi Wolf\n\nAge: unknown\n";
+
+        let cursor = Cursor::new(input_data.as_bytes());
+        let mut reader = LimitedReader::new(cursor, 4);
+        let mut output: Vec<u8> = Vec::new();
+        let mut output_option: Option<&mut Vec<u8>> = Some(&mut output);
+        let ((errors, _, _), errored) = process_stdio(
+            &schema_str,
+            &mut reader,
+            &mut output_option,
+            "test.md",
+            "test.mds",
+            &CliOptions {
+                matches_only: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert!(errored);
+        assert_eq!(ExitStatus::from_errors(&errors), ExitStatus::Invalid);
+        assert_eq!(String::from_utf8(output).unwrap(), "{\"name\":\"Wolf\"}\n");
+    }
+
+    #[test]
+    fn test_process_stdio_writes_html_report() {
+        let schema_str = "# Hi `name:/[A-Za-z]+/`\n".to_string();
+        let input_data = "# Hi 42\n";
+
+        let mut reader = Cursor::new(input_data.as_bytes());
+        let mut output: Vec<u8> = Vec::new();
+        let mut output_option: Option<&mut Vec<u8>> = Some(&mut output);
+        let (_, errored) = process_stdio(
+            &schema_str,
+            &mut reader,
+            &mut output_option,
+            "test.md",
+            "test.mds",
+            &CliOptions {
+                quiet: true,
+                report_format: Some(ReportFormat::Html),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert!(errored);
+        let output_str = String::from_utf8(output).unwrap();
+        assert!(output_str.starts_with("<!DOCTYPE html>"));
+        assert!(output_str.contains("<title>Validation of test.md</title>"));
+    }
+
+    #[test]
+    fn test_exit_status() {
+        let violation = ValidationError::SchemaViolation(SchemaViolationError::NodeTypeMismatch {
+            schema_index: 0,
+            input_index: 0,
+            expected: "heading".to_string(),
+            actual: "paragraph".to_string(),
+        });
+        let io_error = ValidationError::IoError("disk on fire".to_string());
+
+        assert_eq!(ExitStatus::from_errors(&[]), ExitStatus::Valid);
+        assert_eq!(ExitStatus::from_errors(&[violation.clone()]), ExitStatus::Invalid);
+        assert_eq!(ExitStatus::from_errors(&[io_error, violation]), ExitStatus::IoError);
+        assert_eq!(
+            ExitStatus::from(&ProcessingError::InvalidUtf8(3)),
+            ExitStatus::IoError
+        );
+        assert_eq!(
+            ExitStatus::from(&ProcessingError::ValidatorCreationFailed),
+            ExitStatus::SchemaError
+        );
+        assert_eq!(ExitStatus::SchemaError.code(), 2);
+    }
+
+    #[test]
+    fn test_output_paths_are_named_after_inputs() {
+        let inputs = [
+            PathBuf::from("docs/intro.md"),
+            PathBuf::from("docs/v1.2.md"),
+        ];
+        assert_eq!(
+            output_paths(&inputs, Path::new("out"), "json"),
+            Ok(vec![
+                PathBuf::from("out/intro.json"),
+                PathBuf::from("out/v1.2.json")
+            ])
+        );
+
+        let inputs = [PathBuf::from("a/intro.md"), PathBuf::from("b/intro.md")];
+        let error = output_paths(&inputs, Path::new("out"), "json").unwrap_err();
+        assert!(error.contains("'a/intro.md' and 'b/intro.md'"), "{}", error);
+    }
+
+    #[test]
+    fn test_summary_table() {
+        let table = summary_table(&[
+            InputSummary {
+                file: "docs/a.md".to_string(),
+                errors: 0,
+                warnings: 1,
+                status: ExitStatus::Valid,
+            },
+            InputSummary {
+                file: "docs/b.md".to_string(),
+                errors: 2,
+                warnings: 0,
+                status: ExitStatus::Invalid,
+            },
+        ]);
+
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(
+            lines[1].contains("File") && lines[1].contains("Valid"),
+            "{}",
+            table
+        );
+        let row = |file: &str| *lines.iter().find(|line| line.contains(file)).unwrap();
+        assert!(row("docs/a.md").contains("yes"), "{}", table);
+        assert!(row("docs/b.md").contains(" 2 "), "{}", table);
+        assert!(row("docs/b.md").contains("no"), "{}", table);
+    }
+
+    #[test]
+    fn test_color_choice() {
+        assert!(ColorChoice::Always.should_color(true, false));
+        assert!(!ColorChoice::Never.should_color(false, true));
+
+        assert!(ColorChoice::Auto.should_color(false, true));
+        assert!(!ColorChoice::Auto.should_color(false, false));
+        assert!(!ColorChoice::Auto.should_color(true, true));
+    }
+
+    #[test]
+    fn test_process_stdio_streams_matches_as_json_lines() {
+        let schema_str = "# Hi `name:/[A-Za-z]+/`\n\nBy `author:/\\w+/`\n".to_string();
+        let input_data = "# Hi Wolf\n\nBy Ada\n";
+
+        let cursor = Cursor::new(input_data.as_bytes());
+        let mut reader = LimitedReader::new(cursor, 4);
+        let mut output: Vec<u8> = Vec::new();
+        let mut output_option: Option<&mut Vec<u8>> = Some(&mut output);
+        let (_, errored) = process_stdio(
+            &schema_str,
+            &mut reader,
+            &mut output_option,
+            "test.md",
+            "test.mds",
+            &CliOptions {
+                stream_output: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(!errored);
+
+        let output_str = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output_str.lines().collect::<Vec<_>>(),
+            vec![
+                r#"{"event":"match","path":"name","value":"Wolf"}"#,
+                r#"{"event":"match","path":"author","value":"Ada"}"#,
+                r#"{"event":"done","valid":true}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cli_options_default() {
+        let options = CliOptions::default();
+        assert_eq!(options.validate, ValidateOptions::default());
+        assert!(!options.validate.fast_fail);
+        assert!(!options.quiet && !options.debug_mode && !options.color);
+        assert!(!options.stream_output && !options.matches_only);
+        assert_eq!(options.error_format, ErrorFormat::Pretty);
+        assert_eq!(options.progress_total_bytes, None);
+        assert_eq!(options.report_format, None);
+    }
+
+    #[test]
+    fn test_process_stdio_validates_like_validate_str() {
+        let schema_str = "# Hi `name:/[A-Za-z]+/`\n\nAge: `age:/\\d+/`\n\nBye\n";
+        let run = |input_data: &str, options: &CliOptions| {
+            let mut reader = Cursor::new(input_data.as_bytes());
+            process_stdio(
+                schema_str,
+                &mut reader,
+                &mut None::<&mut Vec<u8>>,
+                "test.md",
+                "test.mds",
+                options,
+            )
+            .unwrap()
+        };
+        let options = CliOptions {
+            validate: ValidateOptions {
+                sort_keys: true,
+                ..Default::default()
+            },
+            quiet: true,
+            ..Default::default()
+        };
+
+        for input_data in [
+            "# Hi Wolf\n\nAge: 3\n\nBye\n",
+            "# Hi 42\n\nAge: old\n\nBye\n",
+        ] {
+            let ((errors, matches, _), errored) = run(input_data, &options);
+            let validation =
+                validate_str_with_options(schema_str, input_data, &options.validate).unwrap();
+            assert_eq!(errored, !validation.errors.is_empty());
+            assert_eq!(errors, validation.errors);
+            assert_eq!(matches, validation.matches);
+        }
+
+        // Whether to stop at the first error comes with the validate options
+        let fast_fail = CliOptions {
+            validate: ValidateOptions {
+                fast_fail: true,
+                ..options.validate.clone()
+            },
+            ..options.clone()
+        };
+        let ((errors, _, _), _) = run("# Hi 42\n\nAge: old\n\nBye\n", &options);
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        let ((errors, _, _), _) = run("# Hi 42\n\nAge: old\n\nBye\n", &fast_fail);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+    }
 }
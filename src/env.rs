@@ -5,6 +5,8 @@
 
 use serde::Deserialize;
 
+use crate::mdschema::validation::matchers::matcher::RegexLimits;
+
 /// Environment configuration for the application.
 ///
 /// All fields are optional.
@@ -13,12 +15,41 @@ use serde::Deserialize;
 pub struct EnvConfig {
     /// Enable debug mode for error output.
     ///
-    /// When enabled, errors are printed using simple Debug formatting
-    /// instead of pretty-printed Ariadne reports.
+    /// When enabled, each pretty-printed Ariadne report is followed by a
+    /// side-by-side rendering of the schema and input trees around the error,
+    /// and the raw Debug formatting of the error. Same as passing `--debug`.
     ///
     /// Set via: `DEV_DEBUG=1` or `DEV_DEBUG=true`
     #[serde(default)]
     pub dev_debug: bool,
+
+    /// Disable colored output when set to anything but an empty string,
+    /// following <https://no-color.org>. `--color always` still wins.
+    ///
+    /// Set via: `NO_COLOR=1`
+    #[serde(default)]
+    pub no_color: Option<String>,
+
+    /// Print how long each kind of validator took after validating. Same as
+    /// passing `--profile`.
+    ///
+    /// Set via: `MDV_PROFILE=1` or `MDV_PROFILE=true`
+    #[serde(default)]
+    pub mdv_profile: bool,
+
+    /// The maximum size, in bytes, of a compiled matcher regex (see
+    /// `RegexLimits::size_limit`).
+    ///
+    /// Set via: `MDV_REGEX_SIZE_LIMIT=4194304`
+    #[serde(default)]
+    pub mdv_regex_size_limit: Option<usize>,
+
+    /// The maximum size, in bytes, of the cache a matcher regex builds up
+    /// while matching (see `RegexLimits::dfa_size_limit`).
+    ///
+    /// Set via: `MDV_REGEX_DFA_SIZE_LIMIT=4194304`
+    #[serde(default)]
+    pub mdv_regex_dfa_size_limit: Option<usize>,
 }
 
 
@@ -35,6 +66,28 @@ impl EnvConfig {
     pub fn is_debug_mode(&self) -> bool {
         self.dev_debug
     }
+
+    /// Check if profiling is enabled.
+    pub fn is_profile_mode(&self) -> bool {
+        self.mdv_profile
+    }
+
+    /// The limits on matcher regexes, with the defaults for any that
+    /// aren't set.
+    pub fn regex_limits(&self) -> RegexLimits {
+        let defaults = RegexLimits::default();
+        RegexLimits {
+            size_limit: self.mdv_regex_size_limit.unwrap_or(defaults.size_limit),
+            dfa_size_limit: self
+                .mdv_regex_dfa_size_limit
+                .unwrap_or(defaults.dfa_size_limit),
+        }
+    }
+
+    /// Check if colored output is disabled.
+    pub fn is_no_color(&self) -> bool {
+        self.no_color.as_deref().is_some_and(|value| !value.is_empty())
+    }
 }
 
 #[cfg(test)]
@@ -53,4 +106,38 @@ mod tests {
             env::remove_var("DEV_DEBUG");
         }
     }
+
+    #[test]
+    fn test_env_config_no_color() {
+        assert!(!EnvConfig::default().is_no_color());
+
+        let config = EnvConfig {
+            no_color: Some("1".to_string()),
+            ..Default::default()
+        };
+        assert!(config.is_no_color());
+
+        let config = EnvConfig {
+            no_color: Some(String::new()),
+            ..Default::default()
+        };
+        assert!(!config.is_no_color());
+    }
+
+    #[test]
+    fn test_env_config_regex_limits() {
+        assert_eq!(EnvConfig::default().regex_limits(), RegexLimits::default());
+
+        let config = EnvConfig {
+            mdv_regex_size_limit: Some(4096),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.regex_limits(),
+            RegexLimits {
+                size_limit: 4096,
+                ..Default::default()
+            }
+        );
+    }
 }
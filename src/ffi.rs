@@ -0,0 +1,193 @@
+//! A C interface to validation, for calling mdvalidate from other languages
+//! (like Python or Node) without shelling out to `mdv`.
+//!
+//! Only built with the `ffi` feature. The header for it is
+//! `include/mdvalidate.h`, generated with `cbindgen` (see `cbindgen.toml`).
+//!
+//! All strings are UTF-8 and NUL terminated. A result owns the JSON strings
+//! its accessors return, so they stay valid until the result is freed with
+//! `mdv_result_free`.
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+use serde_json::{Value, json};
+
+use crate::mdschema::validation::errors::ValidationError;
+use crate::mdschema::validation::json_report::ValidatorReport;
+use crate::mdschema::validation::validate::validate_str;
+
+/// The outcome of validating an input against a schema.
+///
+/// Opaque to C. Get at its contents with the `mdv_result_*` accessors.
+pub struct MdvResult {
+    is_valid: bool,
+    matches_json: CString,
+    errors_json: CString,
+}
+
+impl MdvResult {
+    fn new(is_valid: bool, matches: &Value, errors: &Value) -> Self {
+        Self {
+            is_valid,
+            matches_json: to_c_string(matches),
+            errors_json: to_c_string(errors),
+        }
+    }
+
+    /// A result for input we couldn't validate at all, with a single error
+    /// of the given kind.
+    fn failed(kind: &str, message: &str) -> Self {
+        Self::new(
+            false,
+            &json!({}),
+            &json!({
+                "errors": [{
+                    "code": null,
+                    "kind": kind,
+                    "severity": "error",
+                    "suppressed": false,
+                    "message": message,
+                }]
+            }),
+        )
+    }
+}
+
+/// Validate an input against a schema.
+///
+/// Never returns null. Anything that stops the input from being validated,
+/// like a null or non UTF-8 argument, or a panic, is reported as an error in
+/// the result.
+///
+/// # Safety
+///
+/// `schema` and `input` must each be null or point to a NUL terminated string
+/// that stays valid for the duration of the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mdv_validate(
+    schema: *const c_char,
+    input: *const c_char,
+) -> *mut MdvResult {
+    // SAFETY: The caller promises the pointers are null or valid strings.
+    let (schema, input) = match unsafe { (str_arg(schema, "schema"), str_arg(input, "input")) } {
+        (Ok(schema), Ok(input)) => (schema, input),
+        (Err(message), _) | (_, Err(message)) => {
+            return Box::into_raw(Box::new(MdvResult::failed("invalid_argument", &message)));
+        }
+    };
+
+    // Unwinding across the FFI boundary is undefined behavior
+    let result = catch_unwind(AssertUnwindSafe(|| validate(schema, input))).unwrap_or_else(
+        |panic| {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            MdvResult::failed("panic", &format!("Validation panicked: {}", message))
+        },
+    );
+
+    Box::into_raw(Box::new(result))
+}
+
+/// Everything the matchers of the schema captured, as a JSON object.
+///
+/// The string belongs to the result. Returns null if `result` is null.
+///
+/// # Safety
+///
+/// `result` must be null or a result from `mdv_validate` that hasn't been
+/// freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mdv_result_matches_json(result: *const MdvResult) -> *const c_char {
+    // SAFETY: The caller promises the result is null or still alive.
+    unsafe { result.as_ref() }.map_or(std::ptr::null(), |result| result.matches_json.as_ptr())
+}
+
+/// The errors in the input, as a JSON document with an `errors` array like
+/// `mdv --error-format json` prints.
+///
+/// The string belongs to the result. Returns null if `result` is null.
+///
+/// # Safety
+///
+/// `result` must be null or a result from `mdv_validate` that hasn't been
+/// freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mdv_result_errors_json(result: *const MdvResult) -> *const c_char {
+    // SAFETY: The caller promises the result is null or still alive.
+    unsafe { result.as_ref() }.map_or(std::ptr::null(), |result| result.errors_json.as_ptr())
+}
+
+/// Whether the input conforms to the schema. False if `result` is null.
+///
+/// # Safety
+///
+/// `result` must be null or a result from `mdv_validate` that hasn't been
+/// freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mdv_result_is_valid(result: *const MdvResult) -> bool {
+    // SAFETY: The caller promises the result is null or still alive.
+    unsafe { result.as_ref() }.is_some_and(|result| result.is_valid)
+}
+
+/// Free a result, and the strings its accessors returned. Does nothing if
+/// `result` is null.
+///
+/// # Safety
+///
+/// `result` must be null or a result from `mdv_validate` that hasn't been
+/// freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mdv_result_free(result: *mut MdvResult) {
+    if !result.is_null() {
+        // SAFETY: The result came from `Box::into_raw` in `mdv_validate`.
+        drop(unsafe { Box::from_raw(result) });
+    }
+}
+
+fn validate(schema: &str, input: &str) -> MdvResult {
+    match validate_str(schema, input) {
+        Ok(validation) => MdvResult::new(
+            validation.is_valid,
+            &validation.matches,
+            &ValidatorReport::new(&validation.validator).to_json(),
+        ),
+        Err(error) => MdvResult::new(false, &json!({}), &error_report(&error)),
+    }
+}
+
+/// A report for an error we got without a validator to locate it with.
+fn error_report(error: &ValidationError) -> Value {
+    json!({
+        "errors": [{
+            "code": error.code(),
+            "kind": error.kind(),
+            "severity": "error",
+            "suppressed": false,
+            "message": error.to_string(),
+        }]
+    })
+}
+
+/// Borrow a string argument.
+///
+/// # Safety
+///
+/// `ptr` must be null or point to a NUL terminated string that outlives `'a`.
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, String> {
+    if ptr.is_null() {
+        return Err(format!("The {} is null", name));
+    }
+
+    // SAFETY: Checked for null above, and the caller promises the rest.
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|error| format!("The {} is not valid UTF-8: {}", name, error))
+}
+
+fn to_c_string(value: &Value) -> CString {
+    // JSON escapes control characters, so it can't contain a NUL byte
+    CString::new(value.to_string()).expect("JSON never contains NUL bytes")
+}
@@ -1,3 +1,12 @@
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod mdschema;
+pub mod prelude;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use mdschema::Validator;
+pub use mdschema::validation::validate::{
+    ReadInputError, Validation, validate_reader, validate_reader_with_progress, validate_str,
+    validate_str_with_options,
+};
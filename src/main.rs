@@ -1,5 +1,6 @@
-use clap::Parser;
-use std::io::{BufReader, Read, Write};
+use clap::{Parser, Subcommand};
+use std::io::{BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use tracing_subscriber::EnvFilter;
 
@@ -7,27 +8,218 @@ pub mod cmd;
 pub mod env;
 pub mod mdschema;
 mod path_or_stdio;
+mod watch;
 
-use crate::cmd::process_stdio;
+use crate::cmd::{
+    CliOptions, ColorChoice, ErrorFormat, ExitStatus, InputSummary, ProcessingError, ReportFormat,
+    output_paths, process_stdio, summary_table,
+};
 use crate::env::EnvConfig;
+use crate::mdschema::validation::error_codes::explain;
+use crate::mdschema::validation::generate::generate_sample;
+use crate::mdschema::validation::includes::resolve_includes;
+use crate::mdschema::validation::lint::{LintSeverity, lint_schema};
+use crate::mdschema::validation::profile::{is_profiling, set_profiling, take_profile};
+use crate::mdschema::validation::selection::HeadingPath;
+use crate::mdschema::validation::severity::WarnRule;
+use crate::mdschema::validation::validate::strip_bom;
+use crate::mdschema::validation::validator::{
+    DEFAULT_MAX_ERRORS_PER_CONTAINER, DEFAULT_MAX_NESTING_DEPTH, DuplicateIdPolicy,
+    TrailingContent, ValidateOptions, Validator,
+};
 use crate::path_or_stdio::PathOrStdio;
+use crate::watch::watch_files;
 use colored::Colorize;
 
 #[derive(Parser, Debug)]
-#[command(version, about = "Validate MDS files against a schema")]
+#[command(
+    version,
+    about = "Validate MDS files against a schema",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Schema file (typically your .mds file)
-    schema: String,
-    /// Input Markdown file or "-" for stdin
-    input: String,
-    /// Output JSON file for discovered matches or "-" for stdout
-    output: Option<String>,
+    #[arg(required = true)]
+    schema: Option<String>,
+    /// Input Markdown file or "-" for stdin, optionally followed by an output
+    /// JSON file for discovered matches or "-" for stdout. With --output-dir,
+    /// every path is an input
+    #[arg(required = true, value_name = "INPUT")]
+    inputs: Vec<String>,
+    /// Directory to write the matches of each input to, as <stem>.json, so
+    /// that several inputs can be validated against the schema at once
+    #[arg(long, value_name = "DIR", conflicts_with = "watch")]
+    output_dir: Option<PathBuf>,
     /// Whether to stop validation on the first error encountered
     #[arg(short, long)]
     fast_fail: bool,
+    /// Whether to refuse to validate against a schema with problems, like an
+    /// invalid matcher, instead of printing them before validating
+    #[arg(long)]
+    strict_schema: bool,
+    /// The most errors to report for the text, code and emphasis of a single
+    /// paragraph or heading
+    #[arg(long, value_name = "COUNT", default_value_t = DEFAULT_MAX_ERRORS_PER_CONTAINER)]
+    max_errors_per_container: usize,
+    /// How deep validation may go into nested lists, quotes and emphasis
+    /// before reporting the input as nested too deeply
+    #[arg(long, value_name = "DEPTH", default_value_t = DEFAULT_MAX_NESTING_DEPTH)]
+    max_nesting_depth: usize,
     /// Whether to suppress non-error output
     #[arg(short, long)]
     quiet: bool,
+    /// Whether to validate independent top-level sections in parallel
+    #[arg(long)]
+    parallel: bool,
+    /// How many threads to validate in parallel on. Implies --parallel
+    #[arg(long, value_name = "COUNT")]
+    threads: Option<usize>,
+    /// Whether to drop the start of a streamed input once it has been
+    /// validated, so huge inputs are validated in bounded memory
+    #[arg(long)]
+    bounded_memory: bool,
+    /// Whether to match HTML comments in the input against the schema instead
+    /// of skipping them
+    #[arg(long)]
+    strict_comments: bool,
+    /// Whether to skip inline HTML in the input, like "<br>", that the schema
+    /// doesn't have
+    #[arg(long)]
+    ignore_inline_html: bool,
+    /// Whether to skip HTML blocks in the input instead of matching them
+    /// against the schema
+    #[arg(long)]
+    ignore_html_blocks: bool,
+    /// Whether to compare the ends of lines exactly, instead of reading
+    /// "\r\n" as "\n" and ignoring whitespace at the end of lines
+    #[arg(long)]
+    strict_line_endings: bool,
+    /// Whether to output each captured value with its source location
+    #[arg(long)]
+    spans: bool,
+    /// Whether to output repeated list matches as objects with a "value" and
+    /// the "children" captured in their nested list
+    #[arg(long)]
+    structured_matches: bool,
+    /// Whether to output every heading of the input, with its anchor, under
+    /// "__headings"
+    #[arg(long)]
+    heading_slugs: bool,
+    /// Whether to sort the keys of the matches JSON lexicographically instead
+    /// of in the order their matchers appear in the schema
+    #[arg(long)]
+    sorted_keys: bool,
+    /// Whether to put the version the schema declares, like
+    /// `<!-- mds-version: 2 -->`, in the matches JSON under "$schema_version"
+    #[arg(long)]
+    embed_version: bool,
+    /// Whether to report errors even where a
+    /// `<!-- mdvalidate-disable-next-block -->` comment suppresses them
+    #[arg(long)]
+    ignore_suppressions: bool,
+    /// Report errors with this code, like MDV003, or of this kind, like
+    /// "node_content_mismatch" or "node_content_mismatch:suffix", as warnings
+    /// that don't make validation fail. Can be given more than once
+    #[arg(long, value_name = "RULE")]
+    warn: Vec<WarnRule>,
+    /// What to do when more than one matcher captures a value under the same
+    /// id: keep the last value, collect the values into an array, or fail
+    #[arg(long, value_enum, default_value_t = DuplicateIdPolicy::Collect)]
+    on_duplicate_id: DuplicateIdPolicy,
+    /// Whether to check that ordered lists are numbered like the schema and
+    /// bullet lists use the schema's bullets
+    #[arg(long)]
+    enforce_ordered_list_numbers: bool,
+    /// Whether to report a loose list, with blank lines between its items,
+    /// where the schema has a tight one and the other way around
+    #[arg(long)]
+    strict_list_tightness: bool,
+    /// Whether to report a setext heading where the schema has an ATX one and
+    /// the other way around, and compare the closing hashes of ATX headings
+    #[arg(long)]
+    strict_heading_form: bool,
+    /// What to do with blocks of the input after the end of the schema:
+    /// "forbid" them, "allow" them, or "capture:KEY" their markdown under KEY
+    #[arg(long, value_name = "POLICY", default_value = "forbid")]
+    trailing_content: TrailingContent,
+    /// Only validate the section of the input under this heading path, like
+    /// "API > Authentication", as if it were the whole document. Headings
+    /// may use * and ? wildcards
+    #[arg(long, value_name = "HEADINGS")]
+    select: Option<HeadingPath>,
+    /// Whether to follow each error with a side-by-side rendering of the
+    /// schema and input trees around it (also enabled by DEV_DEBUG)
+    #[arg(long)]
+    debug: bool,
+    /// How to print errors: "pretty" reports, a single JSON document with
+    /// the location of every error, or "github" workflow commands that
+    /// annotate the errors in pull requests
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Pretty)]
+    error_format: ErrorFormat,
+    /// When to color output: "auto" colors it when it goes to a terminal and
+    /// NO_COLOR isn't set
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+    /// Whether to write each match to the output as soon as it is found, as
+    /// JSON Lines like {"event":"match","path":"item[2]","value":"..."},
+    /// ending with {"event":"done","valid":true}
+    #[arg(long, conflicts_with = "error_format")]
+    stream_output: bool,
+    /// Whether to write only the matches JSON to stdout (or the output file),
+    /// even when validation fails, with errors going to stderr. Exits with 0
+    /// if the input is valid, 1 if it has errors, 2 if the schema is broken
+    /// and 3 if reading or writing failed
+    #[arg(long, conflicts_with_all = ["error_format", "stream_output"])]
+    matches_only: bool,
+    /// Which report to write to the output instead of the matches: "html"
+    /// for a standalone page with the input and its errors highlighted
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        conflicts_with_all = ["error_format", "stream_output", "matches_only"]
+    )]
+    report_format: Option<ReportFormat>,
+    /// Whether to show a progress bar on stderr while validating an input
+    /// file
+    #[arg(long)]
+    progress: bool,
+    /// Whether to validate again whenever the schema or input file changes,
+    /// until Ctrl-C is pressed
+    #[arg(short, long)]
+    watch: bool,
+    /// Whether to print how often each kind of validator ran and how long it
+    /// took to stderr after validating (also enabled by MDV_PROFILE)
+    #[arg(long)]
+    profile: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Describe an error code, like MDV002, with an example and how to fix it
+    Explain { code: String },
+    /// Check a schema for problems, like invalid or duplicate matchers,
+    /// without validating any input
+    Lint {
+        /// Schema file (typically your .mds file) or "-" for stdin
+        schema: String,
+    },
+    /// Write a sample document that a schema describes, with each matcher
+    /// replaced by a placeholder like <name>
+    Generate {
+        /// Schema file (typically your .mds file) or "-" for stdin
+        schema: String,
+        /// Output Markdown file or "-" for stdout
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Whether to fill in matchers with values they accept instead of
+        /// placeholders, so the sample validates against the schema
+        #[arg(long)]
+        fake: bool,
+    },
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -49,46 +241,465 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Load environment configuration
     let env_config = EnvConfig::load();
+    let debug_mode = args.debug || env_config.is_debug_mode();
+    set_profiling(args.profile || env_config.is_profile_mode());
 
-    let schema_src = PathOrStdio::from(args.schema);
-    let schema_src = schema_src.reader().map_err(|e| format!(
-            "Failed to open schema file '{}': {}",
-            schema_src.filepath(),
-            e
-        ))?;
-    let mut schema_str = String::new();
-    BufReader::new(schema_src).read_to_string(&mut schema_str)?;
+    // Reports go to stderr and matches to stdout, so both have to be terminals
+    let color = args.color.should_color(
+        env_config.is_no_color(),
+        std::io::stdout().is_terminal() && std::io::stderr().is_terminal(),
+    );
+    colored::control::set_override(color);
 
-    let input = PathOrStdio::from(args.input);
-    let mut input_reader = input.reader()?;
-
-    let output_writer: &mut Option<&mut Box<dyn Write>> = match args.output {
-        Some(ref output_path) => {
-            let output_pos = PathOrStdio::from(output_path.clone());
-            &mut Some(&mut output_pos.writer()?)
+    if let Some(Command::Explain { code }) = &args.command {
+        let explanation =
+            explain(code).ok_or_else(|| format!("Unknown error code '{}'", code))?;
+        println!("{}", explanation);
+        return Ok(());
+    }
+    if let Some(Command::Lint { schema }) = &args.command {
+        if lint(&PathOrStdio::from(schema.clone()))? {
+            exit(1)
         }
-        None => &mut None,
+        return Ok(());
+    }
+    if let Some(Command::Generate {
+        schema,
+        output,
+        fake,
+    }) = &args.command
+    {
+        let output = PathOrStdio::from(output.clone().unwrap_or_else(|| "-".to_string()));
+        generate(&PathOrStdio::from(schema.clone()), &output, *fake)?;
+        return Ok(());
+    }
+    let Some(schema) = args.schema.clone() else {
+        unreachable!("clap requires a schema and input unless there's a subcommand");
     };
+    let schema_path = PathOrStdio::from(schema);
 
-    match process_stdio(
-        &schema_str,
-        &mut input_reader,
-        output_writer,
-        input.filepath(),
-        args.fast_fail,
-        args.quiet,
-        env_config.is_debug_mode(),
+    if let Some(output_dir) = &args.output_dir {
+        return match validate_all(&args, &env_config, &schema_path, output_dir, debug_mode, color) {
+            Err(err) => {
+                report_error(&args, err.as_ref());
+                exit(exit_status_of(err.as_ref()).code())
+            }
+            Ok(ExitStatus::Valid) => Ok(()),
+            Ok(status) => exit(status.code()),
+        };
+    }
+
+    let (input_path, output_path) = match args.inputs.as_slice() {
+        [input] => (PathOrStdio::from(input.clone()), None),
+        [input, output] => (
+            PathOrStdio::from(input.clone()),
+            Some(PathOrStdio::from(output.clone())),
+        ),
+        _ => return Err("Pass --output-dir to validate more than one input".into()),
+    };
+    let output_path = output_path.as_ref();
+
+    if args.watch {
+        let (PathOrStdio::Path(schema_file), PathOrStdio::Path(input_file)) =
+            (&schema_path, &input_path)
+        else {
+            return Err("--watch needs a schema file and an input file, not stdin".into());
+        };
+
+        watch_files(&[schema_file.clone(), input_file.clone()], || {
+            // Errors are shown until the next change instead of ending the watch
+            if let Err(err) = validate_once(
+                &args,
+                &env_config,
+                &schema_path,
+                &input_path,
+                output_path,
+                debug_mode,
+                color,
+            ) {
+                println!("{}", format!("Error! {}", err).red());
+            }
+        })?;
+        return Ok(());
+    }
+
+    match validate_once(
+        &args,
+        &env_config,
+        &schema_path,
+        &input_path,
+        output_path,
+        debug_mode,
+        color,
     ) {
         Err(err) => {
-            println!("{}", format!("Error! {}", err).red());
-            return Err(Box::new(err));
+            report_error(&args, err.as_ref());
+            exit(exit_status_of(err.as_ref()).code())
+        }
+        Ok(ExitStatus::Valid) => Ok(()),
+        Ok(status) => exit(status.code()),
+    }
+}
+
+/// An error loading a schema, with what `mdv` should exit with because of it.
+#[derive(Debug)]
+struct LoadSchemaError {
+    status: ExitStatus,
+    message: String,
+}
+
+impl std::fmt::Display for LoadSchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for LoadSchemaError {}
+
+/// What `mdv` should exit with after an error that stopped it validating.
+///
+/// Errors we don't know more about come from opening the input or output.
+fn exit_status_of(err: &(dyn std::error::Error + 'static)) -> ExitStatus {
+    if let Some(err) = err.downcast_ref::<LoadSchemaError>() {
+        err.status
+    } else if let Some(err) = err.downcast_ref::<ProcessingError>() {
+        err.into()
+    } else {
+        ExitStatus::IoError
+    }
+}
+
+/// Print an error that stopped `mdv` validating an input. It goes to stderr
+/// if stdout is only for matches.
+fn report_error(args: &Args, err: &dyn std::error::Error) {
+    let message = format!("Error! {}", err).red();
+    if args.matches_only {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+/// Read the schema, validate the input against it and print the report.
+///
+/// Everything is opened afresh, so that in watch mode each run sees the
+/// latest version of the files.
+///
+/// # Returns
+///
+/// What `mdv` should exit with, given the errors in the input.
+fn validate_once(
+    args: &Args,
+    env_config: &EnvConfig,
+    schema_path: &PathOrStdio,
+    input_path: &PathOrStdio,
+    output_path: Option<&PathOrStdio>,
+    debug_mode: bool,
+    color: bool,
+) -> Result<ExitStatus, Box<dyn std::error::Error>> {
+    let schema_str = load_schema(schema_path)?;
+    check_schema(args, &schema_str, schema_path)?;
+    let summary = validate_input(
+        args,
+        env_config,
+        &schema_str,
+        schema_path,
+        input_path,
+        output_path,
+        debug_mode,
+        color,
+    )?;
+
+    Ok(summary.status)
+}
+
+/// Validate each input against the schema, writing its matches to
+/// `<output_dir>/<stem>.json`, and print a table of how each went.
+///
+/// The schema is read once for all of them. Inputs are validated in order of
+/// their paths, so the results are in the same order however the shell
+/// expanded them. An input that can't be read doesn't stop the others.
+///
+/// # Returns
+///
+/// What `mdv` should exit with, the worst of what it would for each input.
+fn validate_all(
+    args: &Args,
+    env_config: &EnvConfig,
+    schema_path: &PathOrStdio,
+    output_dir: &Path,
+    debug_mode: bool,
+    color: bool,
+) -> Result<ExitStatus, Box<dyn std::error::Error>> {
+    let mut input_files: Vec<PathBuf> = args.inputs.iter().map(PathBuf::from).collect();
+    if input_files.iter().any(|input| input.as_os_str() == "-") {
+        return Err("--output-dir needs input files, not stdin".into());
+    }
+    input_files.sort();
+    input_files.dedup();
+
+    let extension = match args.report_format {
+        Some(ReportFormat::Html) => "html",
+        None => "json",
+    };
+    let output_files = output_paths(&input_files, output_dir, extension)?;
+
+    let schema_str = load_schema(schema_path)?;
+    check_schema(args, &schema_str, schema_path)?;
+    std::fs::create_dir_all(output_dir)?;
+
+    let mut summaries = Vec::new();
+    for (input_file, output_file) in input_files.into_iter().zip(output_files) {
+        let input_path = PathOrStdio::Path(input_file);
+        let summary = validate_input(
+            args,
+            env_config,
+            &schema_str,
+            schema_path,
+            &input_path,
+            Some(&PathOrStdio::Path(output_file)),
+            debug_mode,
+            color,
+        )
+        .unwrap_or_else(|err| {
+            report_error(args, err.as_ref());
+            InputSummary {
+                file: input_path.filepath().to_string(),
+                errors: 1,
+                warnings: 0,
+                status: exit_status_of(err.as_ref()),
+            }
+        });
+        summaries.push(summary);
+    }
+
+    // Stdout is for the errors as JSON or annotations with --error-format
+    let table = summary_table(&summaries);
+    if args.error_format != ErrorFormat::Pretty {
+        eprintln!("{}", table);
+    } else {
+        println!("{}", table);
+    }
+
+    Ok(summaries
+        .iter()
+        .map(|summary| summary.status)
+        .max()
+        .unwrap_or(ExitStatus::Valid))
+}
+
+/// Validate an input against a schema we've already read, and print the
+/// report.
+///
+/// # Returns
+///
+/// How many errors and warnings the input has, and what `mdv` should exit
+/// with because of them.
+fn validate_input(
+    args: &Args,
+    env_config: &EnvConfig,
+    schema_str: &str,
+    schema_path: &PathOrStdio,
+    input_path: &PathOrStdio,
+    output_path: Option<&PathOrStdio>,
+    debug_mode: bool,
+    color: bool,
+) -> Result<InputSummary, Box<dyn std::error::Error>> {
+    let mut input_reader = input_path.reader()?;
+    // Without the size of the input there's nothing to show progress towards
+    let progress_total_bytes = match input_path {
+        PathOrStdio::Path(path) if args.progress => {
+            std::fs::metadata(path).ok().map(|metadata| metadata.len() as usize)
         }
-        Ok((_, errored)) => {
-            if errored {
-                exit(1)
+        _ => None,
+    };
+
+    let mut output_writer: Option<Box<dyn Write>> = match output_path {
+        Some(output_path) => Some(output_path.writer()?),
+        None => None,
+    };
+
+    let processed = process_stdio(
+        schema_str,
+        &mut input_reader,
+        &mut output_writer.as_mut(),
+        input_path.filepath(),
+        schema_path.filepath(),
+        &CliOptions {
+            validate: ValidateOptions {
+                fast_fail: args.fast_fail,
+                max_errors_per_container: args.max_errors_per_container,
+                max_nesting_depth: args.max_nesting_depth,
+                parallel: args.parallel || args.threads.is_some(),
+                parallel_threads: args.threads,
+                bounded_memory: args.bounded_memory,
+                strict_comments: args.strict_comments,
+                ignore_inline_html: args.ignore_inline_html,
+                ignore_html_blocks: args.ignore_html_blocks,
+                strict_line_endings: args.strict_line_endings,
+                include_spans: args.spans,
+                structured_matches: args.structured_matches,
+                capture_heading_slugs: args.heading_slugs,
+                sort_keys: args.sorted_keys,
+                embed_schema_version: args.embed_version,
+                ignore_suppressions: args.ignore_suppressions,
+                severity_overrides: args.warn.iter().copied().collect(),
+                on_duplicate_id: args.on_duplicate_id,
+                enforce_ordered_list_numbers: args.enforce_ordered_list_numbers,
+                strict_list_tightness: args.strict_list_tightness,
+                strict_heading_form: args.strict_heading_form,
+                trailing_content: args.trailing_content.clone(),
+                input_selection: args.select.clone(),
+                regex_limits: env_config.regex_limits(),
+                ..Default::default()
+            },
+            quiet: args.quiet,
+            debug_mode,
+            error_format: args.error_format,
+            progress_total_bytes,
+            color,
+            stream_output: args.stream_output,
+            matches_only: args.matches_only,
+            report_format: args.report_format,
+        },
+    );
+    if is_profiling() {
+        eprint!("{}", take_profile());
+    }
+    let ((errors, _, suppressed_errors), _) = processed?;
+    if let Some(output_writer) = &mut output_writer {
+        output_writer.flush()?;
+    }
+
+    Ok(InputSummary {
+        file: input_path.filepath().to_string(),
+        errors: errors.len(),
+        warnings: suppressed_errors.len(),
+        status: ExitStatus::from_errors(&errors),
+    })
+}
+
+/// Read a whole schema, without its byte order mark.
+fn read_schema(schema_path: &PathOrStdio) -> Result<String, Box<dyn std::error::Error>> {
+    let schema_src = schema_path.reader().map_err(|e| format!(
+            "Failed to open schema file '{}': {}",
+            schema_path.filepath(),
+            e
+        ))?;
+    let mut schema_bytes = Vec::new();
+    BufReader::new(schema_src).read_to_end(&mut schema_bytes)?;
+    let schema_str = String::from_utf8(schema_bytes).map_err(|e| format!(
+            "Schema file '{}' is not valid UTF-8 (invalid byte at offset {})",
+            schema_path.filepath(),
+            e.utf8_error().valid_up_to()
+        ))?;
+
+    Ok(strip_bom(&schema_str).to_string())
+}
+
+/// Read a whole schema and splice in the schemas it includes.
+///
+/// Schemas read from stdin have no path to resolve includes against, so
+/// their includes are left for the validator to report.
+fn load_schema(schema_path: &PathOrStdio) -> Result<String, Box<dyn std::error::Error>> {
+    let schema_str = read_schema(schema_path).map_err(|error| LoadSchemaError {
+        status: ExitStatus::IoError,
+        message: error.to_string(),
+    })?;
+    match schema_path {
+        PathOrStdio::Path(path) => resolve_includes(&schema_str, path).map_err(|error| {
+            LoadSchemaError {
+                status: ExitStatus::SchemaError,
+                message: format!(
+                    "Failed to load schema '{}': {} [{}]",
+                    schema_path.filepath(),
+                    error,
+                    error.code()
+                ),
             }
+            .into()
+        }),
+        PathOrStdio::Stdio => Ok(schema_str),
+    }
+}
+
+/// Print what is wrong with a schema before reading any input, or with
+/// --strict-schema, refuse to validate against it.
+///
+/// The problems are also reported where the input reaches them, so this only
+/// goes to stderr.
+fn check_schema(
+    args: &Args,
+    schema_str: &str,
+    schema_path: &PathOrStdio,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // A schema that doesn't parse fails when we validate against it
+    let Some(validator) = Validator::new_incomplete(schema_str, "") else {
+        return Ok(());
+    };
+    let diagnostics = validator.schema_diagnostics();
+    if diagnostics.is_empty() {
+        return Ok(());
+    }
+
+    let message = format!("{}: {}", schema_path.filepath(), diagnostics);
+    if args.strict_schema {
+        return Err(LoadSchemaError {
+            status: ExitStatus::SchemaError,
+            message,
         }
+        .into());
     }
+    if !args.quiet {
+        eprintln!("{}", message.yellow());
+    }
+    Ok(())
+}
 
+/// Write a sample document generated from a schema.
+fn generate(
+    schema_path: &PathOrStdio,
+    output_path: &PathOrStdio,
+    fake: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let schema_str = load_schema(schema_path)?;
+    let sample = generate_sample(&schema_str, fake)
+        .ok_or_else(|| format!("Failed to parse schema '{}'", schema_path.filepath()))?;
+
+    output_path.writer()?.write_all(sample.as_bytes())?;
     Ok(())
 }
+
+/// Print the problems `lint_schema` finds in a schema.
+///
+/// # Returns
+///
+/// Whether any of the problems are errors.
+fn lint(schema_path: &PathOrStdio) -> Result<bool, Box<dyn std::error::Error>> {
+    let schema_str = load_schema(schema_path)?;
+    let findings = lint_schema(&schema_str)
+        .ok_or_else(|| format!("Failed to parse schema '{}'", schema_path.filepath()))?;
+
+    for finding in &findings {
+        let severity = match finding.severity {
+            LintSeverity::Error => finding.severity.to_string().red(),
+            LintSeverity::Warning => finding.severity.to_string().yellow(),
+        };
+        println!(
+            "{}:{}:{}: {}: {}",
+            schema_path.filepath(),
+            finding.position.line,
+            finding.position.col,
+            severity,
+            finding.issue
+        );
+    }
+
+    if findings.is_empty() {
+        println!("{}", format!("No problems found in '{}'", schema_path.filepath()).green());
+    }
+
+    Ok(findings
+        .iter()
+        .any(|finding| finding.severity == LintSeverity::Error))
+}
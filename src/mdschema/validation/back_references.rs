@@ -0,0 +1,267 @@
+//! Back-references between matchers.
+//!
+//! Sometimes the same value has to appear in more than one place, like a
+//! title that is repeated in a list of fields further down. A matcher
+//! captures it, and a back-reference, a code span with `=` and the id of the
+//! matcher, requires the input to have the same text where it is:
+//!
+//! ```md
+//! # `title:/.+/`
+//!
+//! - Name: `=title`
+//! ```
+//!
+//! A back-reference matches like a bare id matcher, up to the literal text
+//! after it, but captures nothing. What it matched is compared against the
+//! text the matcher captured once all of the matches so far are known, and a
+//! difference is reported as a `SchemaViolationError::BackReferenceMismatch`.
+//! Inside a scope (see `scopes`), the innermost scope around the
+//! back-reference that has the id is used. If the matcher captured nothing,
+//! like an optional matcher that was left out, there is nothing to compare.
+//!
+//! When the schema is loaded, every back-reference has to come after the
+//! matcher it refers to, and that matcher can't repeat, since a back-reference
+//! stands for a single value. Otherwise it is reported as a
+//! `SchemaError::InvalidBackReference`.
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tree_sitter::Tree;
+
+use crate::mdschema::validation::{
+    errors::{SchemaError, SchemaViolationError, ValidationError},
+    match_paths::{match_path, value_at_path},
+    matchers::matcher::Matcher,
+    ts_utils::visit_code_spans,
+};
+
+/// Why a back-reference in a schema can't be resolved.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum BackReferenceError {
+    /// No matcher of the schema captures under the id.
+    UnknownId,
+    /// The matcher that captures under the id comes after the back-reference.
+    BeforeCapture,
+    /// The matcher that captures under the id repeats, so it captures an
+    /// array rather than a value.
+    RepeatedCapture,
+}
+
+impl fmt::Display for BackReferenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BackReferenceError::UnknownId => write!(f, "no matcher captures it"),
+            BackReferenceError::BeforeCapture => {
+                write!(f, "it comes before the matcher that captures it")
+            }
+            BackReferenceError::RepeatedCapture => {
+                write!(f, "the matcher that captures it repeats")
+            }
+        }
+    }
+}
+
+/// Text of the input that a back-reference matched, to compare against what
+/// its id captured.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BackReference {
+    /// The keys of the scope the back-reference is in (see `scopes`),
+    /// outermost first.
+    pub scope: Vec<String>,
+    /// The id the back-reference refers to.
+    pub id: String,
+    /// The text the back-reference matched.
+    pub text: String,
+    pub schema_index: usize,
+    pub input_index: usize,
+}
+
+impl BackReference {
+    /// The error to report if what the id captured isn't the text the
+    /// back-reference matched.
+    ///
+    /// # Arguments
+    ///
+    /// * `matches`: Everything captured so far.
+    /// * `capture_index`: The descendant index of the schema matcher that
+    ///   captures under the id.
+    pub(crate) fn mismatch(
+        &self,
+        matches: &Value,
+        capture_index: usize,
+    ) -> Option<ValidationError> {
+        let captured = find_captured(matches, &self.scope, &self.id)?;
+
+        let expected = captured_text(captured).unwrap_or_else(|| captured.to_string());
+        if expected == self.text {
+            return None;
+        }
+
+        Some(ValidationError::SchemaViolation(
+            SchemaViolationError::BackReferenceMismatch {
+                schema_index: self.schema_index,
+                input_index: self.input_index,
+                id: self.id.clone(),
+                capture_index,
+                expected,
+                actual: self.text.clone(),
+            },
+        ))
+    }
+}
+
+/// What was captured under an id, looking in the innermost scope that has it
+/// first.
+///
+/// # Arguments
+///
+/// * `matches`: Everything captured so far.
+/// * `scope`: The keys of the scope to look in, outermost first.
+/// * `id`: The id to look up, which may be a path (see `match_paths`).
+pub(crate) fn find_captured<'v>(
+    matches: &'v Value,
+    scope: &[String],
+    id: &str,
+) -> Option<&'v Value> {
+    let path = match_path(id);
+    (0..=scope.len()).rev().find_map(|depth| {
+        let scope_matches = scope[..depth]
+            .iter()
+            .try_fold(matches, |value, key| value.get(key))?;
+        value_at_path(scope_matches, &path)
+    })
+}
+
+/// The text of a captured value, which may have been converted to another
+/// type or be reported with its location.
+pub(crate) fn captured_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(_) | Value::Bool(_) => Some(value.to_string()),
+        // Captures with their location, or split into capture groups
+        Value::Object(object) => captured_text(object.get("value").or(object.get("full"))?),
+        _ => None,
+    }
+}
+
+/// An error for every back-reference of a schema that can't be resolved, in
+/// order.
+pub(crate) fn back_reference_errors(schema_tree: &Tree, schema_str: &str) -> Vec<ValidationError> {
+    // Whether each id is captured by a matcher that repeats, and where the
+    // back-references are
+    let mut captures: HashMap<String, bool> = HashMap::new();
+    let mut back_references = Vec::new();
+    visit_code_spans(schema_tree, |cursor| {
+        let Ok(matcher) = Matcher::try_from_schema_cursor(cursor, schema_str) else {
+            return;
+        };
+        if let Some(id) = matcher.id() {
+            captures
+                .entry(id.to_string())
+                .or_insert_with(|| matcher.is_repeated());
+        } else if let Some(id) = matcher.back_reference() {
+            let error = match captures.get(id) {
+                Some(false) => return,
+                Some(true) => BackReferenceError::RepeatedCapture,
+                None => BackReferenceError::UnknownId,
+            };
+            back_references.push((cursor.descendant_index(), id.to_string(), error));
+        }
+    });
+
+    back_references
+        .into_iter()
+        .map(|(schema_index, id, error)| {
+            // Captured after all, just too late
+            let error = match error {
+                BackReferenceError::UnknownId if captures.contains_key(&id) => {
+                    BackReferenceError::BeforeCapture
+                }
+                error => error,
+            };
+            ValidationError::SchemaError(SchemaError::InvalidBackReference {
+                schema_index,
+                id,
+                error,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+    use serde_json::json;
+
+    fn errors(schema: &str) -> Vec<(String, BackReferenceError)> {
+        let tree = parse_markdown(schema).unwrap();
+        back_reference_errors(&tree, schema)
+            .into_iter()
+            .map(|error| match error {
+                ValidationError::SchemaError(SchemaError::InvalidBackReference {
+                    id,
+                    error,
+                    ..
+                }) => (id, error),
+                error => panic!("Unexpected error: {:?}", error),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_back_reference_errors() {
+        assert_eq!(errors("# `title:/.+/`\n\n- Name: `=title`\n"), vec![]);
+        assert_eq!(
+            errors("Name: `=title`\n\n# `title:/.+/`\n"),
+            vec![("title".to_string(), BackReferenceError::BeforeCapture)]
+        );
+        assert_eq!(
+            errors("# `title:/.+/`\n\nName: `=name`\n"),
+            vec![("name".to_string(), BackReferenceError::UnknownId)]
+        );
+        assert_eq!(
+            errors("- `tag:/\\w+/`{1,}\n\nFirst tag: `=tag`\n"),
+            vec![("tag".to_string(), BackReferenceError::RepeatedCapture)]
+        );
+    }
+
+    #[test]
+    fn test_mismatch_looks_in_the_innermost_scope_with_the_id() {
+        let back_reference = BackReference {
+            scope: vec!["config".to_string()],
+            id: "name".to_string(),
+            text: "db".to_string(),
+            schema_index: 5,
+            input_index: 7,
+        };
+
+        let matches = json!({"name": "app", "config": {"name": "db"}});
+        assert_eq!(back_reference.mismatch(&matches, 2), None);
+        let matches = json!({"name": "db", "config": {"host": "localhost"}});
+        assert_eq!(back_reference.mismatch(&matches, 2), None);
+        // Captured with its location
+        let matches = json!({"name": {"value": "db", "start": 3}});
+        assert_eq!(back_reference.mismatch(&matches, 2), None);
+        // Nothing to compare against
+        assert_eq!(back_reference.mismatch(&json!({}), 2), None);
+
+        let matches = json!({"name": "app"});
+        assert_eq!(
+            back_reference.mismatch(&matches, 2),
+            Some(ValidationError::SchemaViolation(
+                SchemaViolationError::BackReferenceMismatch {
+                    schema_index: 5,
+                    input_index: 7,
+                    id: "name".to_string(),
+                    capture_index: 2,
+                    expected: "app".to_string(),
+                    actual: "db".to_string(),
+                }
+            ))
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! Bounded memory validation of streamed input.
+//!
+//! A validator normally holds on to all of the input it has read, and its
+//! tree, since errors point into them. For huge streamed documents, like
+//! generated API references, that's a lot. With
+//! `ValidateOptions::bounded_memory`, the validator drops the top-level blocks
+//! at the start of its input once they have been validated, and parses what's
+//! left on its own, so it only holds on to the blocks it is still working on.
+//!
+//! A block is dropped once
+//! - two more blocks have started after it, so it can't change anymore,
+//! - the schema block it is compared against always matches exactly one
+//!   input block, so it isn't a repeating paragraph or section, a section
+//!   capture or a ruler matcher, and
+//! - it validated without errors (or warnings), defines no link references,
+//!   which later blocks may still use, and has no back-references, which are
+//!   only compared once everything before them has been captured.
+//!
+//! `DroppedInput` remembers what was dropped: how many bytes, lines and tree
+//! nodes came before the kept input, which schema block the first kept block
+//! is compared against, and what the dropped blocks captured. Errors and
+//! spans found in the kept input are moved by it, so that they point into the
+//! whole document, just like they do when nothing is dropped.
+//!
+//! Front matter, scopes and heading slugs need the whole document, so nothing
+//! is dropped for schemas with front matter or scopes, or when capturing
+//! heading slugs.
+use serde::{Deserialize, Serialize};
+use tree_sitter::TreeCursor;
+
+use crate::mdschema::validation::{
+    errors::ValidationError,
+    selection::SelectedSection,
+    spans::shift_spans,
+    ts_types::*,
+    ts_utils::{goto_first_child_skipping_directives, goto_next_sibling_skipping_directives},
+    validator_walker::ValidatorWalker,
+    walkers::{
+        ValidationData, ValidationResult,
+        parallel::matches_one_input_block,
+        validators::{Validator, nodes::NodeVsNodeValidator},
+    },
+};
+
+/// The start of the input that a validator has dropped, and what it found
+/// there.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DroppedInput {
+    /// How many bytes were dropped. The kept input starts this far into the
+    /// whole input, at the start of a line.
+    pub bytes: usize,
+    /// How many lines were dropped.
+    pub lines: usize,
+    /// How many descendants of the input's root were dropped, so the node at
+    /// descendant index `i` of the kept input is at `i + descendants` in the
+    /// whole input.
+    pub descendants: usize,
+    /// The descendant index of the schema block the first kept input block is
+    /// compared against, if anything was dropped.
+    pub schema_start: Option<usize>,
+    /// What the dropped blocks captured, with spans in the whole input.
+    committed: ValidationResult,
+}
+
+impl DroppedInput {
+    /// Whether nothing has been dropped.
+    pub fn is_empty(&self) -> bool {
+        self.schema_start.is_none() && self.bytes == 0 && self.descendants == 0
+    }
+
+    /// The descendant index in the kept input of the node at `index` in the
+    /// whole input. Dropped nodes are given the root.
+    pub fn kept_index(&self, index: usize) -> usize {
+        if index > self.descendants {
+            index - self.descendants
+        } else {
+            0
+        }
+    }
+
+    /// Point an error found in the kept input into the whole input.
+    pub(crate) fn shift_error(&self, error: &mut ValidationError) {
+        error.shift_input_index(self.descendants);
+    }
+
+    /// Put what the dropped blocks captured before what a pass over the kept
+    /// input found, with the spans of its captures moved into the whole input.
+    ///
+    /// Its errors still point into the kept input, since suppressions are
+    /// looked up there (see `shift_error`).
+    pub(crate) fn with_committed(&self, result: ValidationResult) -> ValidationResult {
+        if self.is_empty() {
+            return result;
+        }
+
+        let mut data = result.data().clone();
+        self.shift_data_spans(&mut data);
+        let mut joined = self.committed.clone();
+        joined.join_data(&data);
+        joined.set_farthest_reached_pos(*result.farthest_reached_pos());
+        joined
+    }
+
+    /// Drop the start of the input, up to where a compaction says to cut it.
+    ///
+    /// # Arguments
+    ///
+    /// * `input`: The kept input, which loses its start.
+    /// * `compaction`: The blocks to drop, found in `input`.
+    pub(crate) fn drop_prefix(&mut self, input: &mut String, compaction: Compaction) {
+        let Compaction {
+            cut_byte,
+            kept_index,
+            schema_start,
+            mut data,
+        } = compaction;
+
+        self.shift_data_spans(&mut data);
+        self.committed.join_data(&data);
+
+        self.lines += input[..cut_byte].matches('\n').count();
+        self.bytes += cut_byte;
+        // The kept root stands in for the dropped one
+        self.descendants += kept_index - 1;
+        self.schema_start = Some(schema_start);
+        input.drain(..cut_byte);
+    }
+
+    /// Drop everything but a section of the input, which is validated against
+    /// the whole schema (see `selection`).
+    ///
+    /// # Arguments
+    ///
+    /// * `input`: The whole input, which is cut down to the section.
+    /// * `section`: The section to keep, found in `input`.
+    pub(crate) fn select(&mut self, input: &mut String, section: &SelectedSection) {
+        let range = section.byte_range.clone();
+        self.lines += input[..range.start].matches('\n').count();
+        self.bytes += range.start;
+        // The kept root stands in for everything before the heading
+        self.descendants += section.heading_index - 1;
+        input.truncate(range.end);
+        input.drain(..range.start);
+    }
+
+    fn shift_data_spans(&self, data: &mut ValidationData) {
+        shift_spans(&mut data.value, self.bytes, self.lines);
+        for duplicate in &mut data.duplicates {
+            shift_spans(&mut duplicate.value, self.bytes, self.lines);
+        }
+    }
+}
+
+/// Blocks at the start of the kept input that can be dropped.
+#[derive(Debug)]
+pub(crate) struct Compaction {
+    /// Where the input to keep starts, at the start of the line its first
+    /// node is on.
+    cut_byte: usize,
+    /// The descendant index of the first kept node.
+    kept_index: usize,
+    /// The descendant index of the schema block the first kept block is
+    /// compared against.
+    schema_start: usize,
+    /// What the dropped blocks captured.
+    data: ValidationData,
+}
+
+impl Compaction {
+    /// Where the input to keep starts.
+    pub fn cut_byte(&self) -> usize {
+        self.cut_byte
+    }
+}
+
+/// Find the blocks at the start of the input that can be dropped (see the
+/// module docs), or `None` if there aren't any.
+///
+/// # Arguments
+///
+/// * `walker`: A walker at the roots of the schema and the kept input.
+/// * `schema_start`: The schema block the first kept block is compared
+///   against, if some input was dropped already.
+pub(crate) fn droppable_prefix(
+    walker: &ValidatorWalker,
+    schema_start: Option<usize>,
+) -> Option<Compaction> {
+    let mut schema_cursor = walker.schema_cursor().clone();
+    let mut input_cursor = walker.input_cursor().clone();
+    let has_schema_block = match schema_start {
+        Some(schema_start) => {
+            schema_cursor.goto_descendant(schema_start);
+            true
+        }
+        None => goto_first_child_skipping_directives(&mut schema_cursor, walker.schema_str()),
+    };
+    if !has_schema_block || !input_cursor.goto_first_child() {
+        return None;
+    }
+
+    let mut data = ValidationData::empty();
+    let mut cut = None;
+    'blocks: loop {
+        // Comments are dropped with the block after them
+        while walker.skips_input_node(&input_cursor.node()) {
+            if is_link_reference_definition_node(&input_cursor.node())
+                || !input_cursor.goto_next_sibling()
+            {
+                break 'blocks;
+            }
+        }
+
+        let mut next_input = input_cursor.clone();
+        let mut next_schema = schema_cursor.clone();
+        if !matches_one_input_block(&schema_cursor, walker.schema_str())
+            || defines_link_references(&input_cursor)
+            || !next_input.goto_next_sibling()
+            || !next_input.clone().goto_next_sibling()
+            || !goto_next_sibling_skipping_directives(&mut next_schema, walker.schema_str())
+        {
+            break;
+        }
+
+        let result = NodeVsNodeValidator
+            .validate(&walker.with_cursors(&schema_cursor, &input_cursor), false);
+        let cut_byte = line_start(walker.input_str(), next_input.node().start_byte());
+        if result.has_errors()
+            || !result.back_references().is_empty()
+            || cut_byte < input_cursor.node().end_byte()
+        {
+            break;
+        }
+
+        data.join(result.data());
+        cut = Some((
+            cut_byte,
+            next_input.descendant_index(),
+            next_schema.descendant_index(),
+        ));
+        input_cursor = next_input;
+        schema_cursor = next_schema;
+    }
+
+    let (cut_byte, kept_index, schema_start) = cut?;
+    Some(Compaction {
+        cut_byte,
+        kept_index,
+        schema_start,
+        data,
+    })
+}
+
+/// Whether a block is or has a link reference definition.
+fn defines_link_references(block_cursor: &TreeCursor) -> bool {
+    let mut cursor = block_cursor.clone();
+    let first = cursor.descendant_index();
+    (first..first + block_cursor.node().descendant_count()).any(|index| {
+        cursor.goto_descendant(index);
+        is_link_reference_definition_node(&cursor.node())
+    })
+}
+
+/// Where the line that `byte` is on starts.
+pub(crate) fn line_start(text: &str, byte: usize) -> usize {
+    text[..byte].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    fn compaction(schema: &str, input: &str) -> Option<Compaction> {
+        let schema_tree = parse_markdown(schema).unwrap();
+        let input_tree = parse_markdown(input).unwrap();
+        let walker = ValidatorWalker::new(schema_tree.walk(), schema, input_tree.walk(), input);
+        droppable_prefix(&walker, None)
+    }
+
+    #[test]
+    fn test_drops_validated_blocks_followed_by_two_more() {
+        let schema = "# Title\n\nBy `author:/\\w+/`\n\nOne\n\nTwo\n";
+        let input = "# Title\n\nBy Alice\n\nOne\n\nTw";
+
+        let compaction = compaction(schema, input).unwrap();
+        assert_eq!(&input[compaction.cut_byte..], "One\n\nTw");
+        assert_eq!(
+            compaction.data.value,
+            serde_json::json!({"author": "Alice"})
+        );
+
+        let mut dropped = DroppedInput::default();
+        let mut kept = input.to_string();
+        dropped.drop_prefix(&mut kept, compaction);
+        assert_eq!(kept, "One\n\nTw");
+        assert_eq!((dropped.bytes, dropped.lines), (19, 4));
+        assert!(!dropped.is_empty());
+    }
+
+    #[test]
+    fn test_keeps_blocks_with_errors_and_what_comes_after() {
+        let schema = "# Title\n\nOne\n\nTwo\n\nThree\n";
+        let input = "# Title\n\nOen\n\nTwo\n\nThree\n";
+
+        let compaction = compaction(schema, input).unwrap();
+        assert_eq!(&input[compaction.cut_byte..], "Oen\n\nTwo\n\nThree\n");
+    }
+
+    #[test]
+    fn test_keeps_repeating_paragraphs_and_link_references() {
+        let schema = "`line:/\\w+/`{1,}\n\nEnd\n\nMore\n";
+        let input = "one\n\ntwo\n\nEnd\n\nMore\n";
+        assert!(compaction(schema, input).is_none());
+
+        let schema = "[docs]: https://example.com\n\nOne\n\nTwo\n\nThree\n";
+        assert!(compaction(schema, schema).is_none());
+    }
+}
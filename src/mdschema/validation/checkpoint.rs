@@ -0,0 +1,84 @@
+//! Saving the state of an incremental validator, to carry on elsewhere.
+//!
+//! A validator that is streamed a long input, like a server validating a
+//! generation as it's written, sometimes has to move, like to another worker.
+//! `Validator::checkpoint` saves everything it has found so far in a
+//! `ValidatorCheckpoint`, which can be serialized, and `Validator::resume`
+//! makes a validator from it that carries on as if it had been there all
+//! along:
+//!
+//! ```no_run
+//! use mdvalidate::mdschema::validation::validator::Validator;
+//!
+//! let schema = "# `title:/.+/`\n\nSome text\n";
+//! let mut validator = Validator::new_incomplete(schema, "# Hello\n\nSo").unwrap();
+//! validator.validate();
+//! let saved = serde_json::to_string(&validator.checkpoint()).unwrap();
+//!
+//! // Somewhere else
+//! let checkpoint = serde_json::from_str(&saved).unwrap();
+//! let mut validator = Validator::resume(schema, &checkpoint).unwrap();
+//! validator.append_final_input("me text\n").unwrap();
+//! validator.validate();
+//! ```
+//!
+//! The input read so far is kept in the checkpoint and parsed again when
+//! resuming, since trees can't be serialized. Input that was dropped with
+//! `ValidateOptions::bounded_memory` stays dropped.
+//!
+//! Options, the progress and match hooks and the schema aren't part of the
+//! checkpoint.
+//! The resumed validator is given the same options with `with_options`,
+//! before any more input.
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::mdschema::validation::{
+    bounded_memory::DroppedInput,
+    error_locations::ErrorLocations,
+    errors::ValidationError,
+    match_stream::{MatchPath, NewMatch},
+    node_pos_pair::NodePosPair,
+};
+
+/// The state of a `Validator`, to make an equivalent one from with
+/// `Validator::resume`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorCheckpoint {
+    /// The input read so far, without what was dropped.
+    pub input: String,
+    /// Whether we had received the full input.
+    pub got_eof: bool,
+    /// The descendant index of the schema node validation carries on from.
+    pub schema_index: usize,
+    /// The descendant index of the input node validation carries on from.
+    pub input_index: usize,
+    /// The errors found so far.
+    pub errors: Vec<ValidationError>,
+    /// The warnings found so far, and errors that were turned into them.
+    pub suppressed_errors: Vec<ValidationError>,
+    /// The matches found so far.
+    pub matches: Value,
+    /// Whether the front matter has already been validated.
+    pub frontmatter_validated: bool,
+    /// How long the input will be, if we were told.
+    pub total_input_bytes: Option<usize>,
+    /// The start of the input that was dropped, or isn't in the selected
+    /// section.
+    pub dropped_input: DroppedInput,
+    /// Whether the input was cut down to the selected section.
+    pub input_selected: bool,
+    /// Where the matches found so far are, so they aren't found again.
+    pub journaled_matches: Vec<MatchPath>,
+    /// Matches that weren't drained with `Validator::drain_new_matches` yet.
+    pub new_matches: Vec<NewMatch>,
+    /// The nodes the errors point at, as they were when they were found.
+    pub error_locations: Vec<(ValidationError, ErrorLocations)>,
+}
+
+impl ValidatorCheckpoint {
+    /// Where validation carries on from.
+    pub(crate) fn farthest_reached_pos(&self) -> NodePosPair {
+        NodePosPair::from_pos(self.schema_index, self.input_index)
+    }
+}
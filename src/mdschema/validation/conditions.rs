@@ -0,0 +1,393 @@
+//! Blocks that the input has or doesn't have depending on a captured value.
+//!
+//! A heading or list item that ends with a condition annotation, like
+//!
+//! ```md
+//! # `type:/library|binary/`
+//!
+//! ## API `@when:type=library`
+//! ```
+//!
+//! is only expected when the matcher with the id captured exactly the text
+//! after the `=`. If it did, the block is validated like any other, so the
+//! input has to have it. If it didn't, the input must not have it: the schema
+//! block is skipped, and for a heading the rest of its section too, and an
+//! input block that matches it anyway is reported as
+//! `SchemaViolationError::ConditionNotMet`.
+//!
+//! Like a back-reference (see `back_references`), the matcher has to capture
+//! a single value, and it has to come before the top-level block that the
+//! condition is in, so that it has been captured by the time we get there.
+//! Otherwise the condition is reported as a `SchemaError::InvalidCondition`.
+//! While the input is streamed in, a condition whose id hasn't been captured
+//! yet waits for it. Once we have the whole input, an id that was never
+//! captured, like that of an optional matcher that was left out, doesn't meet
+//! any condition.
+//!
+//! The annotation is stripped from the schema when it is loaded, like a scope
+//! annotation (see `scopes`). A heading with both has its scope annotation
+//! last.
+use std::collections::HashMap;
+use std::ops::Range;
+
+use serde_json::Value;
+use tree_sitter::{Node, Tree};
+
+use crate::mdschema::validation::back_references::{BackReferenceError, captured_text};
+use crate::mdschema::validation::errors::{SchemaError, SchemaViolationError, ValidationError};
+use crate::mdschema::validation::matchers::matcher::Matcher;
+use crate::mdschema::validation::scopes::{start_after_stripping, strip_annotations};
+use crate::mdschema::validation::ts_types::*;
+use crate::mdschema::validation::ts_utils::{get_node_text, visit_code_spans};
+
+/// What a condition annotation starts with, like `` `@when:type=library` ``.
+pub const CONDITION_PREFIX: &str = "@when:";
+
+/// A condition on a heading or list item of a schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    /// The id of the matcher whose capture the condition looks at.
+    pub id: String,
+    /// The text the matcher has to have captured.
+    pub value: String,
+}
+
+impl Condition {
+    /// The condition of a condition annotation, if a code span's text is one.
+    ///
+    /// Ids are made of letters, digits, `_` and `-`, and may have the dots
+    /// of a path (see `match_paths`). The value is everything after the `=`,
+    /// and may be empty.
+    pub fn parse(code_span_text: &str) -> Option<Self> {
+        let (id, value) = code_span_text
+            .trim_matches('`')
+            .trim()
+            .strip_prefix(CONDITION_PREFIX)?
+            .split_once('=')?;
+        let is_id = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '\\'));
+        is_id.then(|| Self {
+            id: id.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    /// Whether the condition is met by what was captured under its id, if
+    /// anything was.
+    ///
+    /// Returns `None` if nothing was captured yet but we don't have all of
+    /// the input, since it may still be.
+    ///
+    /// # Arguments
+    ///
+    /// * `captured`: What was captured under the id of the condition.
+    /// * `got_eof`: Whether we have received the full input document.
+    pub fn is_met(&self, captured: Option<&Value>, got_eof: bool) -> Option<bool> {
+        match captured {
+            Some(captured) => Some(captured_text(captured).as_deref() == Some(&self.value)),
+            None if got_eof => Some(false),
+            None => None,
+        }
+    }
+
+    /// The error for an input block that the schema only expects when the
+    /// condition is met, while it isn't.
+    pub fn not_met_error(
+        &self,
+        captured: Option<&Value>,
+        schema_index: usize,
+        input_index: usize,
+    ) -> ValidationError {
+        ValidationError::SchemaViolation(SchemaViolationError::ConditionNotMet {
+            schema_index,
+            input_index,
+            id: self.id.clone(),
+            expected: self.value.clone(),
+            actual: captured.and_then(captured_text),
+        })
+    }
+}
+
+/// The conditional headings and list items of a schema.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaConditions {
+    /// The conditions, by the start byte of their heading or list item.
+    conditions: HashMap<usize, Condition>,
+    /// The byte ranges of the annotations, with the whitespace before them,
+    /// in order.
+    annotations: Vec<Range<usize>>,
+}
+
+impl SchemaConditions {
+    /// Find the condition annotations of the top-level headings and the list
+    /// items of a schema.
+    pub fn find(schema_tree: &Tree, schema_str: &str) -> Self {
+        let mut conditions = Self::default();
+
+        let mut cursor = schema_tree.walk();
+        loop {
+            let node = cursor.node();
+            if let Some(content) = annotated_content(&node)
+                && let Some(code_span) = content
+                    .named_child(content.named_child_count().saturating_sub(1))
+                    .filter(is_inline_code_node)
+                && let Some(condition) = Condition::parse(get_node_text(&code_span, schema_str))
+                && schema_str[code_span.end_byte()..content.end_byte()]
+                    .trim()
+                    .is_empty()
+            {
+                let start = schema_str[..code_span.start_byte()]
+                    .trim_end_matches([' ', '\t'])
+                    .len()
+                    .max(content.start_byte());
+                conditions.conditions.insert(node.start_byte(), condition);
+                conditions.annotations.push(start..code_span.end_byte());
+            }
+
+            if cursor.goto_first_child() {
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return conditions;
+                }
+            }
+        }
+    }
+
+    /// Remove the condition annotations from a schema, so that its headings
+    /// and list items get compared without them.
+    ///
+    /// Returns the schema without them and its conditions, or `None` if it
+    /// has no condition annotations. The conditions keep the byte ranges the
+    /// annotations had, to move anything else found by its start byte (see
+    /// `SchemaScopes::after_stripping`).
+    pub fn strip(schema_tree: &Tree, schema_str: &str) -> Option<(String, Self)> {
+        let found = Self::find(schema_tree, schema_str);
+        if found.is_empty() {
+            return None;
+        }
+
+        let stripped = strip_annotations(schema_str, &found.annotations);
+        let conditions = found
+            .conditions
+            .iter()
+            .map(|(&start, condition)| {
+                (
+                    start_after_stripping(start, &found.annotations),
+                    condition.clone(),
+                )
+            })
+            .collect();
+        Some((
+            stripped,
+            Self {
+                conditions,
+                annotations: found.annotations,
+            },
+        ))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    /// The byte ranges the annotations had before they were stripped.
+    pub fn annotations(&self) -> &[Range<usize>] {
+        &self.annotations
+    }
+
+    /// The condition of a heading or list item, if it has one.
+    pub fn condition(&self, node: &Node) -> Option<&Condition> {
+        if !is_heading_node(node) && !is_list_item_node(node) {
+            return None;
+        }
+        self.conditions.get(&node.start_byte())
+    }
+
+    /// The errors for conditions whose id isn't captured by a single matcher
+    /// before the top-level block they are in.
+    pub fn errors(&self, schema_tree: &Tree, schema_str: &str) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        if self.is_empty() {
+            return errors;
+        }
+
+        // Where each id is first captured, and whether that matcher repeats
+        let mut captures: HashMap<String, (usize, bool)> = HashMap::new();
+        visit_code_spans(schema_tree, |cursor| {
+            if let Ok(matcher) = Matcher::try_from_schema_cursor(cursor, schema_str)
+                && let Some(id) = matcher.id()
+            {
+                captures
+                    .entry(id.to_string())
+                    .or_insert((cursor.node().start_byte(), matcher.is_repeated()));
+            }
+        });
+
+        let mut cursor = schema_tree.walk();
+        let mut block_start = 0;
+        loop {
+            let node = cursor.node();
+            if node
+                .parent()
+                .is_some_and(|parent| is_document_node(&parent))
+            {
+                block_start = node.start_byte();
+            }
+            if let Some(condition) = self.condition(&node) {
+                let error = match captures.get(&condition.id) {
+                    None => Some(BackReferenceError::UnknownId),
+                    Some((start, _)) if *start >= block_start => {
+                        Some(BackReferenceError::BeforeCapture)
+                    }
+                    Some((_, true)) => Some(BackReferenceError::RepeatedCapture),
+                    Some((_, false)) => None,
+                };
+                if let Some(error) = error {
+                    errors.push(ValidationError::SchemaError(
+                        SchemaError::InvalidCondition {
+                            schema_index: cursor.descendant_index(),
+                            id: condition.id.clone(),
+                            error,
+                        },
+                    ));
+                }
+            }
+
+            if cursor.goto_first_child() {
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return errors;
+                }
+            }
+        }
+    }
+}
+
+/// The node a condition annotation can end, for a top-level heading its
+/// content and for a list item its first paragraph.
+fn annotated_content<'a>(node: &Node<'a>) -> Option<Node<'a>> {
+    if is_heading_node(node)
+        && node
+            .parent()
+            .is_some_and(|parent| is_document_node(&parent))
+    {
+        node.children(&mut node.walk())
+            .find(is_heading_content_node)
+    } else if is_list_item_node(node) {
+        node.children(&mut node.walk())
+            .find(|child| !is_list_marker_node(child))
+            .filter(is_paragraph_node)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    #[test]
+    fn test_parse_condition() {
+        assert_eq!(
+            Condition::parse("`@when:type=library`"),
+            Some(Condition {
+                id: "type".to_string(),
+                value: "library".to_string(),
+            })
+        );
+        assert_eq!(
+            Condition::parse("`@when:kind=a = b`").map(|condition| condition.value),
+            Some("a = b".to_string())
+        );
+        assert_eq!(Condition::parse("`@when:type`"), None);
+        assert_eq!(Condition::parse("`@when:=library`"), None);
+        assert_eq!(Condition::parse("`@scope:type`"), None);
+    }
+
+    #[test]
+    fn test_strip_condition_annotations() {
+        let schema = "# `type:/\\w+/`\n\n## API `@when:type=library`\n\n\
+                      - Docs `@when:type=library`\n- Usage\n";
+        let tree = parse_markdown(schema).unwrap();
+
+        let (stripped, conditions) = SchemaConditions::strip(&tree, schema).unwrap();
+        assert_eq!(stripped, "# `type:/\\w+/`\n\n## API\n\n- Docs\n- Usage\n");
+
+        let tree = parse_markdown(&stripped).unwrap();
+        let root = tree.root_node();
+        let children: Vec<_> = root.children(&mut root.walk()).collect();
+        let items: Vec<_> = children[2].children(&mut children[2].walk()).collect();
+        assert_eq!(
+            conditions
+                .condition(&children[1])
+                .map(|condition| condition.id.as_str()),
+            Some("type")
+        );
+        assert!(conditions.condition(&items[0]).is_some());
+        assert!(conditions.condition(&items[1]).is_none());
+        // The list starts where its first item does
+        assert!(conditions.condition(&children[2]).is_none());
+    }
+
+    #[test]
+    fn test_condition_errors() {
+        fn errors(schema: &str) -> Vec<(String, BackReferenceError)> {
+            let tree = parse_markdown(schema).unwrap();
+            let (stripped, conditions) = SchemaConditions::strip(&tree, schema).unwrap();
+            let tree = parse_markdown(&stripped).unwrap();
+            conditions
+                .errors(&tree, &stripped)
+                .into_iter()
+                .map(|error| match error {
+                    ValidationError::SchemaError(SchemaError::InvalidCondition {
+                        id,
+                        error,
+                        ..
+                    }) => (id, error),
+                    error => panic!("Unexpected error: {:?}", error),
+                })
+                .collect()
+        }
+
+        assert_eq!(
+            errors("# `type:/\\w+/`\n\n## API `@when:type=library`\n"),
+            vec![]
+        );
+        assert_eq!(
+            errors("## API `@when:type=library`\n\n# `type:/\\w+/`\n"),
+            vec![("type".to_string(), BackReferenceError::BeforeCapture)]
+        );
+        // Captured in the same list, which is validated all at once
+        assert_eq!(
+            errors("- `type:/\\w+/`\n- Docs `@when:type=library`\n"),
+            vec![("type".to_string(), BackReferenceError::BeforeCapture)]
+        );
+        assert_eq!(
+            errors("# Title\n\n## API `@when:type=library`\n"),
+            vec![("type".to_string(), BackReferenceError::UnknownId)]
+        );
+        assert_eq!(
+            errors("- `type:/\\w+/`{1,}\n\n## API `@when:type=library`\n"),
+            vec![("type".to_string(), BackReferenceError::RepeatedCapture)]
+        );
+    }
+
+    #[test]
+    fn test_condition_is_met() {
+        let condition = Condition::parse("`@when:type=library`").unwrap();
+        let library = Value::String("library".to_string());
+        let binary = Value::String("binary".to_string());
+
+        assert_eq!(condition.is_met(Some(&library), false), Some(true));
+        assert_eq!(condition.is_met(Some(&binary), true), Some(false));
+        assert_eq!(condition.is_met(None, false), None);
+        assert_eq!(condition.is_met(None, true), Some(false));
+    }
+}
@@ -0,0 +1,194 @@
+//! Word-level diffs of the text an error expected and the text it found.
+//!
+//! When a long paragraph differs from the schema by a word, printing both
+//! in full leaves the reader to spot the difference. A diff shows only the
+//! words that differ, with a few words of context on each side:
+//!
+//! ```text
+//! differs at byte 16: ...quick brown [-fox-]{+cat+} jumps over the...
+//! ```
+//!
+//! Removed text is in `[-...-]` and added text in `{+...+}`, like `wdiff`.
+//! The byte offset is into the found text.
+
+/// How many words of context to show on each side of the difference.
+pub const CONTEXT_WORDS: usize = 3;
+
+/// Strings at least this long are diffed in reports instead of being
+/// printed in full.
+pub const LONG_TEXT_LEN: usize = 40;
+
+/// The ANSI codes we color diffs with. We write them ourselves rather than
+/// going through `colored`, so that `color` is all that decides.
+const REMOVED_STYLE: &str = "\x1b[31m";
+const ADDED_STYLE: &str = "\x1b[32m";
+const RESET_STYLE: &str = "\x1b[0m";
+
+/// The byte offset into `actual` of the first character that differs from
+/// `expected`, or `None` if they are the same.
+pub fn first_diff_offset(expected: &str, actual: &str) -> Option<usize> {
+    let mut expected_chars = expected.chars();
+    for (offset, actual_char) in actual.char_indices() {
+        if expected_chars.next() != Some(actual_char) {
+            return Some(offset);
+        }
+    }
+
+    // One is the start of the other
+    (expected.len() != actual.len()).then_some(actual.len())
+}
+
+/// Render the part of `actual` that differs from `expected`, with a few
+/// words of context, as one line. Returns `None` if they are the same.
+///
+/// # Arguments
+///
+/// * `expected`: The text the schema expected.
+/// * `actual`: The text found in the input.
+/// * `color`: Whether to color removed and added text with ANSI codes.
+pub fn render_diff(expected: &str, actual: &str, color: bool) -> Option<String> {
+    let offset = first_diff_offset(expected, actual)?;
+
+    let expected_tokens = tokens(expected);
+    let actual_tokens = tokens(actual);
+
+    let prefix = expected_tokens
+        .iter()
+        .zip(&actual_tokens)
+        .take_while(|(expected, actual)| expected == actual)
+        .count();
+    let suffix = expected_tokens[prefix..]
+        .iter()
+        .rev()
+        .zip(actual_tokens[prefix..].iter().rev())
+        .take_while(|(expected, actual)| expected == actual)
+        .count();
+
+    let removed = expected_tokens[prefix..expected_tokens.len() - suffix].concat();
+    let added = actual_tokens[prefix..actual_tokens.len() - suffix].concat();
+
+    // A word and the space around it are two tokens
+    let before_start = prefix.saturating_sub(CONTEXT_WORDS * 2);
+    let after_start = actual_tokens.len() - suffix;
+    let after_end = (after_start + CONTEXT_WORDS * 2).min(actual_tokens.len());
+
+    let mut rendered = format!("differs at byte {}: ", offset);
+    let before = actual_tokens[before_start..prefix].concat();
+    if before_start > 0 {
+        rendered.push_str("...");
+        rendered.push_str(before.trim_start());
+    } else {
+        rendered.push_str(&before);
+    }
+    if !removed.is_empty() {
+        push_styled(&mut rendered, &format!("[-{}-]", removed), REMOVED_STYLE, color);
+    }
+    if !added.is_empty() {
+        push_styled(&mut rendered, &format!("{{+{}+}}", added), ADDED_STYLE, color);
+    }
+    let after = actual_tokens[after_start..after_end].concat();
+    if after_end < actual_tokens.len() {
+        rendered.push_str(after.trim_end());
+        rendered.push_str("...");
+    } else {
+        rendered.push_str(&after);
+    }
+
+    Some(rendered)
+}
+
+fn push_styled(rendered: &mut String, text: &str, style: &str, color: bool) {
+    if color {
+        rendered.push_str(style);
+        rendered.push_str(text);
+        rendered.push_str(RESET_STYLE);
+    } else {
+        rendered.push_str(text);
+    }
+}
+
+/// Split text into runs of whitespace and the words between them.
+fn tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut in_whitespace = None;
+    for (offset, c) in text.char_indices() {
+        let is_whitespace = c.is_whitespace();
+        if in_whitespace.is_some_and(|in_whitespace| in_whitespace != is_whitespace) {
+            tokens.push(&text[start..offset]);
+            start = offset;
+        }
+        in_whitespace = Some(is_whitespace);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_strings() {
+        assert_eq!(first_diff_offset("same text", "same text"), None);
+        assert_eq!(render_diff("same text", "same text", false), None);
+        assert_eq!(render_diff("", "", false), None);
+    }
+
+    #[test]
+    fn test_difference_at_start() {
+        assert_eq!(first_diff_offset("The fox ran", "A fox ran"), Some(0));
+        assert_eq!(
+            render_diff("The fox ran", "A fox ran", false).unwrap(),
+            "differs at byte 0: [-The-]{+A+} fox ran"
+        );
+    }
+
+    #[test]
+    fn test_difference_in_middle() {
+        let expected = "The quick brown fox jumps over the lazy dog today";
+        let actual = "The quick brown cat jumps over the lazy dog today";
+
+        assert_eq!(first_diff_offset(expected, actual), Some(16));
+        assert_eq!(
+            render_diff(expected, actual, false).unwrap(),
+            "differs at byte 16: The quick brown [-fox-]{+cat+} jumps over the..."
+        );
+    }
+
+    #[test]
+    fn test_difference_at_end() {
+        let expected = "one two three four five six";
+        let actual = "one two three four five";
+
+        assert_eq!(first_diff_offset(expected, actual), Some(actual.len()));
+        assert_eq!(
+            render_diff(expected, actual, false).unwrap(),
+            "differs at byte 23: ...three four five[- six-]"
+        );
+    }
+
+    #[test]
+    fn test_multi_byte_characters() {
+        let expected = "Café ☕ with Zoë";
+        let actual = "Café ☕ with Zoé";
+
+        assert_eq!(first_diff_offset(expected, actual), Some(17));
+        assert_eq!(
+            render_diff(expected, actual, false).unwrap(),
+            "differs at byte 17: Café ☕ with [-Zoë-]{+Zoé+}"
+        );
+    }
+
+    #[test]
+    fn test_colored_diff() {
+        let rendered = render_diff("a b", "a c", true).unwrap();
+
+        assert_eq!(
+            rendered,
+            "differs at byte 2: a \x1b[31m[-b-]\x1b[0m\x1b[32m{+c+}\x1b[0m"
+        );
+    }
+}
@@ -0,0 +1,916 @@
+//! Longer explanations of error codes.
+//!
+//! Every error has a stable short code (see `ValidationError::code`). This
+//! module describes each code, with a small schema and input that produce
+//! the error where that makes sense, for `mdv explain <code>`.
+use core::fmt;
+
+/// The explanation of one error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorExplanation {
+    pub code: &'static str,
+    /// A short title for the error.
+    pub name: &'static str,
+    /// What went wrong.
+    pub description: &'static str,
+    /// A schema that produces the error together with `input`.
+    pub schema: Option<&'static str>,
+    /// An input that produces the error together with `schema`.
+    pub input: Option<&'static str>,
+    /// How to fix it.
+    pub fix: &'static str,
+}
+
+/// Every error code, in order.
+pub const ERROR_EXPLANATIONS: &[ErrorExplanation] = &[
+    ErrorExplanation {
+        code: "MDV001",
+        name: "Node type mismatch",
+        description: "The input has a different kind of node than the schema at this \
+                      position, like a paragraph where the schema has a heading.",
+        schema: Some("# Title"),
+        input: Some("Title"),
+        fix: "Use the kind of node the schema expects, or change the schema.",
+    },
+    ErrorExplanation {
+        code: "MDV002",
+        name: "Prefix mismatch",
+        description: "The literal text before a matcher doesn't match the input.",
+        schema: Some("Name: `name:/\\w+/`"),
+        input: Some("Nom: Wolf"),
+        fix: "Make the text before the matcher match the schema exactly.",
+    },
+    ErrorExplanation {
+        code: "MDV003",
+        name: "Suffix mismatch",
+        description: "The literal text after a matcher doesn't match the input.",
+        schema: Some("`count:/\\d+/` items"),
+        input: Some("3 things"),
+        fix: "Make the text after the matcher match the schema exactly.",
+    },
+    ErrorExplanation {
+        code: "MDV004",
+        name: "Matcher mismatch",
+        description: "The input doesn't match the pattern of a matcher.",
+        schema: Some("Version `version:/\\d+\\.\\d+/`"),
+        input: Some("Version latest"),
+        fix: "Change the input so that the pattern matches it, or loosen the pattern.",
+    },
+    ErrorExplanation {
+        code: "MDV005",
+        name: "Literal mismatch",
+        description: "Literal text in the schema doesn't match the input exactly.",
+        schema: Some("# Getting Started"),
+        input: Some("# Getting started"),
+        fix: "Match the schema text exactly, or relax the comparison with a \
+              `{compare: ...}` modifier or an `<!-- mds:compare ... -->` directive.",
+    },
+    ErrorExplanation {
+        code: "MDV006",
+        name: "Normalized literal mismatch",
+        description: "Literal text in the schema doesn't match the input, even after \
+                      normalizing both sides.",
+        schema: Some("# Getting Started {compare: case-insensitive}"),
+        input: Some("# Getting Going"),
+        fix: "Match the schema text, up to the normalizations that are turned on.",
+    },
+    ErrorExplanation {
+        code: "MDV007",
+        name: "Value not allowed",
+        description: "The input isn't one of the values an enum matcher allows.",
+        schema: Some("Status: `status:[draft, published]`"),
+        input: Some("Status: archived"),
+        fix: "Use one of the allowed values, or add the value to the enum.",
+    },
+    ErrorExplanation {
+        code: "MDV008",
+        name: "Not enough paragraphs",
+        description: "There are fewer input paragraphs than a repeating paragraph \
+                      matcher requires.",
+        schema: Some("`para`{2,}"),
+        input: Some("Only one paragraph"),
+        fix: "Add paragraphs, or lower the minimum of the matcher.",
+    },
+    ErrorExplanation {
+        code: "MDV009",
+        name: "Non-repeating matcher in list",
+        description: "A list item matcher has no `{min,max}` repetition, so it can't \
+                      say how many items it matches.",
+        schema: Some("- `item:/.+/`"),
+        input: Some("- one\n- two"),
+        fix: "Add repetition to the matcher, like `item:/.+/`{1,}.",
+    },
+    ErrorExplanation {
+        code: "MDV010",
+        name: "Children length mismatch",
+        description: "A node of the input has a different number of children than \
+                      the schema allows.",
+        schema: Some("Hello **world**"),
+        input: Some("Hello world"),
+        fix: "Make the input have the same structure as the schema.",
+    },
+    ErrorExplanation {
+        code: "MDV011",
+        name: "List too deep",
+        description: "The input nests lists deeper than a list matcher with `+`s for \
+                      each level of nesting reaches.",
+        schema: Some("- `item:/.+/`++{1,}"),
+        input: Some("- one\n  - two\n    - three"),
+        fix: "Flatten the input list, add a `+` to the matcher, or describe the nested \
+              list in the schema.",
+    },
+    ErrorExplanation {
+        code: "MDV012",
+        name: "Wrong list item count",
+        description: "The number of items matched by a repeating list matcher is \
+                      outside of its `{min,max}` range.",
+        schema: Some("- `step:/.+/`{2,3}"),
+        input: Some("- one"),
+        fix: "Add or remove list items, or change the range.",
+    },
+    ErrorExplanation {
+        code: "MDV013",
+        name: "Wrong section count",
+        description: "The number of sections matched by a repeating section is \
+                      outside of its `{min,max}` range.",
+        schema: Some("## `version:/v\\d+/`{2,}\n\nNotes"),
+        input: Some("## v1\n\nNotes"),
+        fix: "Add or remove sections, or change the range.",
+    },
+    ErrorExplanation {
+        code: "MDV014",
+        name: "Malformed node structure",
+        description: "The input has nodes the schema doesn't, or is missing nodes \
+                      the schema has.",
+        schema: Some("# Title\n\nBody"),
+        input: Some("# Title"),
+        fix: "Add the missing content, or remove the extra content.",
+    },
+    ErrorExplanation {
+        code: "MDV015",
+        name: "Unresolved link reference",
+        description: "A reference-style link uses a label that no link reference \
+                      definition defines.",
+        schema: None,
+        input: None,
+        fix: "Define the label with a line like `[label]: https://example.com`.",
+    },
+    ErrorExplanation {
+        code: "MDV016",
+        name: "Broken anchor",
+        description: "A schema link points at a fragment, like `#usage`, but no heading \
+                      of the input has that anchor.",
+        schema: Some("See [usage](#usage).\n\n## `title:/.+/`"),
+        input: Some("See [usage](#usage).\n\n## Install"),
+        fix: "Add a heading with that anchor. Anchors are made from heading text by \
+              lowercasing it, dropping punctuation and turning spaces into dashes.",
+    },
+    ErrorExplanation {
+        code: "MDV017",
+        name: "Wrong table row count",
+        description: "The number of table rows matched by a repeated row is outside \
+                      of its `{min,max}` range.",
+        schema: Some("| Item |\n|-|\n| `item:/\\w+/` |{2,}"),
+        input: Some("| Item |\n|-|\n| Apple |"),
+        fix: "Add or remove rows, or change the range.",
+    },
+    ErrorExplanation {
+        code: "MDV018",
+        name: "Captured value has the wrong type",
+        description: "A matcher that declares a type, like `int`, matched text that \
+                      doesn't parse as that type.",
+        schema: Some("Age: `age:int:/[\\d.]+/`"),
+        input: Some("Age: 4.2"),
+        fix: "Change the input, or tighten the matcher's regex so that it only \
+              matches values of its type.",
+    },
+    ErrorExplanation {
+        code: "MDV019",
+        name: "Code block language mismatch",
+        description: "The info string of a code block, usually its language, doesn't \
+                      match the schema.",
+        schema: Some("```json\n{payload}\n```"),
+        input: Some("```yaml\npayload: 1\n```"),
+        fix: "Use the language the schema expects, or match the language with a \
+              matcher like ```` ```{lang:/json|yaml/} ````.",
+    },
+    ErrorExplanation {
+        code: "MDV020",
+        name: "Missing front matter",
+        description: "The schema starts with front matter that has required keys, but \
+                      the input doesn't start with front matter.",
+        schema: Some("---\ntitle: `title:/.+/`\n---\n\n# Post"),
+        input: Some("# Post"),
+        fix: "Start the document with front matter between two `---` lines.",
+    },
+    ErrorExplanation {
+        code: "MDV021",
+        name: "Missing front matter key",
+        description: "The input's front matter doesn't have a key that the schema's \
+                      front matter requires.",
+        schema: Some("---\ntitle: `title:/.+/`\n---"),
+        input: Some("---\nauthor: Wolf\n---"),
+        fix: "Add the key to the front matter, or make it optional in the schema with \
+              a `?` after its matcher.",
+    },
+    ErrorExplanation {
+        code: "MDV022",
+        name: "Unaligned blocks",
+        description: "Top-level blocks of the input stopped lining up with the schema, \
+                      for example because of an extra paragraph, so validation skipped \
+                      them and resumed at the next blocks that do line up.",
+        schema: Some("# Intro\n\nHello\n\n# Usage"),
+        input: Some("# Intro\n\nHello\n\nAn extra paragraph\n\n# Usage"),
+        fix: "Remove the blocks the schema doesn't expect, or add the blocks it's missing.",
+    },
+    ErrorExplanation {
+        code: "MDV023",
+        name: "List marker mismatch",
+        description: "With `--enforce-ordered-list-numbers`, the marker of a list item \
+                      isn't the one the schema expects. Ordered lists are numbered \
+                      like the schema, counting up from the schema's number for the \
+                      items of a repeated matcher, and bullet lists use the schema's \
+                      bullet. Task list items always need the checkbox the schema \
+                      asks for, `[ ]` or `[x]`, where `[?]` accepts either.",
+        schema: Some("1. foo\n2. bar"),
+        input: Some("1. foo\n5. bar"),
+        fix: "Renumber the list, use the same bullet as the schema, or check or \
+              uncheck the task.",
+    },
+    ErrorExplanation {
+        code: "MDV024",
+        name: "Trailing content",
+        description: "The input goes on after everything in the schema has been \
+                      matched. Extra blocks at the end of a document are reported \
+                      once, with how many there are.",
+        schema: Some("# Title\n\nHello"),
+        input: Some("# Title\n\nHello\n\nAn extra paragraph"),
+        fix: "Remove the extra blocks, or pass `--trailing-content allow` to ignore \
+              them or `--trailing-content capture:KEY` to capture them under KEY.",
+    },
+    ErrorExplanation {
+        code: "MDV025",
+        name: "Text not similar enough",
+        description: "Literal text with a `{similar: ...}` modifier is compared by \
+                      how similar it is to the input, ignoring case and extra \
+                      whitespace, and the input differs by more than it allows.",
+        schema: Some("This page describes the API. {similar: 0.9}"),
+        input: Some("This page lists a few endpoints."),
+        fix: "Reword the input to be closer to the schema text, or lower the \
+              threshold of the modifier.",
+    },
+    ErrorExplanation {
+        code: "MDV026",
+        name: "Selected section not found",
+        description: "Only the section under a heading path of the input is \
+                      validated, like with `--select \"API > Authentication\"`, \
+                      but no heading of the input is at that path.",
+        schema: None,
+        input: Some("# API\n\n## Tokens"),
+        fix: "Check the spelling of the headings in the path, or use `*` and \
+              `?` wildcards for the parts that vary.",
+    },
+    ErrorExplanation {
+        code: "MDV027",
+        name: "Ambiguous selection",
+        description: "Only the section under a heading path of the input is \
+                      validated, but more than one heading of the input is at \
+                      that path, so it isn't clear which section to validate.",
+        schema: None,
+        input: Some("# v1\n\n## Usage\n\n# v2\n\n## Usage"),
+        fix: "Add the headings above the section to the path, like \
+              `v2 > Usage`.",
+    },
+    ErrorExplanation {
+        code: "MDV028",
+        name: "Back-reference mismatch",
+        description: "A back-reference, like `=title`, requires the input to repeat \
+                      the text that the matcher with its id captured earlier, but \
+                      the input has different text there.",
+        schema: Some("# `title:/.+/`\n\nName: `=title`"),
+        input: Some("# Widgets\n\nName: Gadgets"),
+        fix: "Change the input so that both places have the same text.",
+    },
+    ErrorExplanation {
+        code: "MDV029",
+        name: "Missing content",
+        description: "The input is empty, or only whitespace, but the schema \
+                      expects blocks. An empty schema only accepts an empty input.",
+        schema: Some("# `title:/.+/`"),
+        input: None,
+        fix: "Write the input the schema describes, starting with its first block.",
+    },
+    ErrorExplanation {
+        code: "MDV030",
+        name: "Condition not met",
+        description: "A heading or list item with a condition, like \
+                      `@when:type=library`, is only expected when the matcher with \
+                      its id captured that text, but the input has the block \
+                      although it captured something else.",
+        schema: Some("# `type:/\\w+/`\n\n## API `@when:type=library`"),
+        input: Some("# binary\n\n## API"),
+        fix: "Remove the block from the input, or change what the id captures.",
+    },
+    ErrorExplanation {
+        code: "MDV031",
+        name: "Nesting too deep",
+        description: "The input nests lists, quotes or emphasis so deeply that \
+                      validating it would go past the maximum depth, like this \
+                      list with `--max-nesting-depth 16`. The limit keeps \
+                      validation from running out of stack, and the nodes past \
+                      it aren't validated.",
+        schema: None,
+        input: Some("- - - - - - - - - - - - - - - - - - - - - - - - - item"),
+        fix: "Nest the input less deeply, or raise the maximum depth with \
+              --max-nesting-depth.",
+    },
+    ErrorExplanation {
+        code: "MDV032",
+        name: "Negated matcher matched",
+        description: "A negated matcher, like `!:/TODO/`, only accepts text that \
+                      its pattern doesn't match, but the input matches it.",
+        schema: Some("Status: `!:/TODO/`"),
+        input: Some("Status: TODO"),
+        fix: "Change the input so that the pattern doesn't match it.",
+    },
+    ErrorExplanation {
+        code: "MDV033",
+        name: "Forbidden content",
+        description: "Text of the input, wherever it is, matches a pattern of an \
+                      `mds-forbid` block of the schema.",
+        schema: Some("# `title:/.+/`\n\n```mds-forbid\nTODO|FIXME\n```"),
+        input: Some("# Release notes\n\nTODO: write these"),
+        fix: "Remove or reword the text that matches the pattern.",
+    },
+    ErrorExplanation {
+        code: "MDV034",
+        name: "Unordered items unmatched",
+        description: "A schema list is marked `<!-- mds:unordered -->`, so its \
+                      items may come in any order, but some of its items have no \
+                      input item of their own left, or some input items match no \
+                      schema item.",
+        schema: Some("<!-- mds:unordered -->\n- apple\n- banana"),
+        input: Some("- banana\n- kiwi"),
+        fix: "Give every schema item an input item of its own, or mark the list \
+              `<!-- mds:unordered+ -->` to allow extra input items.",
+    },
+    ErrorExplanation {
+        code: "MDV101",
+        name: "Multiple matchers in node",
+        description: "A node of the schema has more than one matcher in it, \
+                      mixed with formatting like emphasis or links. Several \
+                      matchers are only allowed between plain text.",
+        schema: Some("`first:/\\w+/` *and* `last:/\\w+/`"),
+        input: None,
+        fix: "Drop the formatting between the matchers, or use a single matcher \
+              for the node.",
+    },
+    ErrorExplanation {
+        code: "MDV102",
+        name: "Repeating matcher in text container",
+        description: "A repeating matcher is used in a paragraph, where repetition \
+                      has no meaning.",
+        schema: Some("Hello `name:/\\w+/`{1,}"),
+        input: None,
+        fix: "Only use `{min,max}` repetition in list items, whole paragraphs or \
+              section headings.",
+    },
+    ErrorExplanation {
+        code: "MDV103",
+        name: "Invalid matcher extras",
+        description: "The extras after a matcher, like `{1,3}` or `!`, can't be parsed.",
+        schema: Some("- `item:/.+/`{3,1}"),
+        input: None,
+        fix: "Fix the extras, like `{1,3}` for between one and three items.",
+    },
+    ErrorExplanation {
+        code: "MDV104",
+        name: "Unclosed matcher",
+        description: "A matcher isn't closed with a backtick.",
+        schema: Some("Hello `name:/\\w+/"),
+        input: None,
+        fix: "Close the matcher, like `name:/\\w+/`.",
+    },
+    ErrorExplanation {
+        code: "MDV105",
+        name: "Matcher error",
+        description: "A matcher can't be built, usually because its pattern isn't a \
+                      valid regex, uses syntax that can't run in linear time, like \
+                      look-around or back-references, or compiles to a regex bigger \
+                      than the regex size limit.",
+        schema: Some("`name:/[a-z/`"),
+        input: None,
+        fix: "Fix the pattern of the matcher, or simplify it. The size limit can be \
+              raised with `MDV_REGEX_SIZE_LIMIT`.",
+    },
+    ErrorExplanation {
+        code: "MDV106",
+        name: "Unbounded repeating matcher must be last",
+        description: "An unbounded repeating matcher is followed by another repeating \
+                      matcher, so we can't tell where one ends and the next starts.",
+        schema: Some("- `a:/test/`{,}\n- `b:/bar/`{,2}"),
+        input: None,
+        fix: "Give every repeating matcher but the last an upper bound, like `{1,3}`.",
+    },
+    ErrorExplanation {
+        code: "MDV107",
+        name: "Invalid UTF-8 in schema",
+        description: "Schema text isn't valid UTF-8.",
+        schema: None,
+        input: None,
+        fix: "Save the schema as UTF-8.",
+    },
+    ErrorExplanation {
+        code: "MDV108",
+        name: "Duplicate matcher id",
+        description: "More than one matcher captured a value under the same id, and \
+                      validation was set to treat that as an error instead of \
+                      collecting the values into an array.",
+        schema: Some("# `title:/\\w+/`\n\nBy `title:/\\w+/`"),
+        input: Some("# Hello\n\nBy Wolf"),
+        fix: "Give each matcher its own id.",
+    },
+    ErrorExplanation {
+        code: "MDV109",
+        name: "Invalid include",
+        description: "An include directive like `include:./person.mds` couldn't be \
+                      resolved. The file may not exist, includes may form a cycle or \
+                      nest too deeply, or the schema wasn't loaded from a file, so \
+                      there's no path to resolve the include against.",
+        schema: Some("# People\n\n`include:./person.mds`"),
+        input: None,
+        fix: "Check the path of the included file, which is relative to the \
+              including schema, and that no schema ends up including itself.",
+    },
+    ErrorExplanation {
+        code: "MDV110",
+        name: "Duplicate scope",
+        description: "Two headings nest the matches of their sections under the \
+                      same keys with a `@scope:name` annotation, so their matches \
+                      would be mixed together.",
+        schema: Some("# Server `@scope:config`\n\n# Client `@scope:config`"),
+        input: None,
+        fix: "Give each scoped heading its own scope name.",
+    },
+    ErrorExplanation {
+        code: "MDV111",
+        name: "Suspicious matcher syntax",
+        description: "A code span looks like a matcher with a small mistake, like a \
+                      missing '/' or a space after the ':', so it is compared \
+                      literally. This is only ever a warning.",
+        schema: Some("`name:/\\w+`"),
+        input: None,
+        fix: "Fix the matcher, or put a `!` after the code span if it's meant to be \
+              literal.",
+    },
+    ErrorExplanation {
+        code: "MDV112",
+        name: "Invalid schema version",
+        description: "The schema starts with a version declaration, but the version \
+                      isn't a positive whole number.",
+        schema: Some("<!-- mds-version: two -->"),
+        input: None,
+        fix: "Declare the version as a whole number from 1, like \
+              `<!-- mds-version: 2 -->`, or remove the declaration to make it version 1.",
+    },
+    ErrorExplanation {
+        code: "MDV113",
+        name: "Invalid back-reference",
+        description: "A back-reference, like `=title`, has to come after a matcher \
+                      that captures a single value under its id. Here no matcher \
+                      captures under the id, the matcher comes after the \
+                      back-reference, or it repeats and so captures an array.",
+        schema: Some("Name: `=title`\n\n# `title:/.+/`"),
+        input: None,
+        fix: "Move the back-reference after the matcher it refers to, or fix its id.",
+    },
+    ErrorExplanation {
+        code: "MDV114",
+        name: "Invalid condition",
+        description: "A condition, like `@when:type=library`, has to come after a \
+                      matcher that captures a single value under its id, outside \
+                      the block the condition is on. Here no matcher captures under \
+                      the id, the matcher doesn't come before that block, or it \
+                      repeats and so captures an array.",
+        schema: Some("## API `@when:type=library`\n\n# `type:/\\w+/`"),
+        input: None,
+        fix: "Move the condition after the matcher it depends on, or fix its id.",
+    },
+    ErrorExplanation {
+        code: "MDV115",
+        name: "Invalid forbidden pattern",
+        description: "A line of an `mds-forbid` block isn't a valid regex, or it is \
+                      too complex to compile.",
+        schema: Some("```mds-forbid\n(TODO\n```"),
+        input: None,
+        fix: "Fix the regex, escaping characters like `(` that are meant literally.",
+    },
+    ErrorExplanation {
+        code: "MDV116",
+        name: "Conflicting match paths",
+        description: "The dots of a matcher id nest its match in objects, so \
+                      `meta.author` captures into `{\"meta\": {\"author\": ...}}`. \
+                      Another matcher in the same scope captures a value right where \
+                      that needs an object, like one with the id `meta`.",
+        schema: Some("# `meta:/\\w+/`\n\nBy `meta.author:/\\w+/`"),
+        input: None,
+        fix: "Rename one of the matchers, or escape a dot that is part of a key with a \
+              backslash, like `v1\\.2`.",
+    },
+    ErrorExplanation {
+        code: "MDV117",
+        name: "Unknown comparison mode",
+        description: "A `{compare: ...}` modifier or `<!-- mds:compare ... -->` \
+                      directive lists a comparison mode that doesn't exist. The modes \
+                      are `case-insensitive`, `collapse-whitespace` and `nfc`.",
+        schema: Some("# Getting started {compare: nfd}"),
+        input: None,
+        fix: "Fix the name of the mode, or remove it.",
+    },
+    ErrorExplanation {
+        code: "MDV201",
+        name: "IO error",
+        description: "The input couldn't be read.",
+        schema: None,
+        input: None,
+        fix: "Check that the input exists and is readable.",
+    },
+    ErrorExplanation {
+        code: "MDV202",
+        name: "Parser error",
+        description: "The input couldn't be parsed or read, for example because it \
+                      isn't valid UTF-8.",
+        schema: None,
+        input: None,
+        fix: "Check that the input is UTF-8 encoded Markdown.",
+    },
+    ErrorExplanation {
+        code: "MDV203",
+        name: "Validator creation failed",
+        description: "The validator couldn't be set up for the schema and input.",
+        schema: None,
+        input: None,
+        fix: "This is a bug, please report it with the schema and input.",
+    },
+    ErrorExplanation {
+        code: "MDV204",
+        name: "Internal invariant violated",
+        description: "The validator got into a state it doesn't expect while \
+                      comparing the schema and input. Validation stops at that \
+                      point instead of crashing.",
+        schema: None,
+        input: None,
+        fix: "This is a bug, please report it with the schema and input.",
+    },
+];
+
+/// Find the explanation of an error code, ignoring case.
+pub fn explain(code: &str) -> Option<&'static ErrorExplanation> {
+    ERROR_EXPLANATIONS
+        .iter()
+        .find(|explanation| explanation.code.eq_ignore_ascii_case(code.trim()))
+}
+
+impl fmt::Display for ErrorExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}: {}", self.code, self.name)?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.description)?;
+
+        for (title, example) in [("Schema", self.schema), ("Input", self.input)] {
+            if let Some(example) = example {
+                writeln!(f)?;
+                writeln!(f, "{}:", title)?;
+                writeln!(f)?;
+                for line in example.lines() {
+                    writeln!(f, "    {}", line)?;
+                }
+            }
+        }
+
+        writeln!(f)?;
+        write!(f, "Fix: {}", self.fix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::mdschema::validation::back_references::BackReferenceError;
+    use crate::mdschema::validation::errors::{
+        ChildrenLengthRange, MalformedStructureKind, NodeContentMismatchKind, ParserError,
+        SchemaError, SchemaViolationError, ValidationError,
+    };
+    use crate::mdschema::validation::includes::IncludeError;
+    use crate::mdschema::validation::matchers::matcher::MatcherError;
+    use crate::mdschema::validation::matchers::matcher_extras::MatcherExtrasError;
+    use crate::mdschema::validation::severity::WarnRule;
+    use crate::mdschema::validation::text_normalization::TextNormalizations;
+
+    /// One error of every kind. Add new variants here, so that they get a code
+    /// and an explanation.
+    fn one_of_each_error() -> Vec<ValidationError> {
+        let violations = vec![
+            SchemaViolationError::NodeTypeMismatch {
+                schema_index: 0,
+                input_index: 0,
+                expected: "atx_heading".into(),
+                actual: "paragraph".into(),
+            },
+            SchemaViolationError::EnumMismatch {
+                schema_index: 0,
+                input_index: 0,
+                allowed: vec![],
+                actual: String::new(),
+            },
+            SchemaViolationError::CapturedValueTypeMismatch {
+                schema_index: 0,
+                input_index: 0,
+                expected: "int".into(),
+                actual: String::new(),
+            },
+            SchemaViolationError::UnresolvedLinkReference {
+                schema_index: 0,
+                input_index: 0,
+                label: String::new(),
+            },
+            SchemaViolationError::BrokenAnchor {
+                schema_index: 0,
+                input_index: 0,
+                anchor: String::new(),
+            },
+            SchemaViolationError::MissingFrontmatter {
+                schema_index: 0,
+                input_index: 0,
+            },
+            SchemaViolationError::MissingFrontmatterKey {
+                schema_index: 0,
+                input_index: 0,
+                key: String::new(),
+            },
+            SchemaViolationError::UnalignedBlocks {
+                schema_index: 0,
+                input_index: 0,
+                skipped_schema: 0,
+                skipped_input: 1,
+            },
+            SchemaViolationError::ListMarkerMismatch {
+                schema_index: 0,
+                input_index: 0,
+                expected: String::new(),
+                actual: String::new(),
+            },
+            SchemaViolationError::TrailingContent {
+                schema_index: 0,
+                input_index: 0,
+                extra_blocks: 1,
+            },
+            SchemaViolationError::SelectionNotFound {
+                schema_index: 0,
+                input_index: 0,
+                selection: String::new(),
+            },
+            SchemaViolationError::AmbiguousSelection {
+                schema_index: 0,
+                input_index: 0,
+                selection: String::new(),
+                lines: vec![],
+            },
+            SchemaViolationError::BackReferenceMismatch {
+                schema_index: 0,
+                input_index: 0,
+                id: String::new(),
+                capture_index: 0,
+                expected: String::new(),
+                actual: String::new(),
+            },
+            SchemaViolationError::MissingContent {
+                schema_index: 0,
+                input_index: 0,
+                expected: String::new(),
+            },
+            SchemaViolationError::ConditionNotMet {
+                schema_index: 0,
+                input_index: 0,
+                id: String::new(),
+                expected: String::new(),
+                actual: None,
+            },
+            SchemaViolationError::NestingTooDeep {
+                schema_index: 0,
+                input_index: 0,
+                max_depth: 0,
+            },
+            SchemaViolationError::ForbiddenContent {
+                schema_index: 0,
+                input_index: 0,
+                pattern: String::new(),
+                actual: String::new(),
+            },
+            SchemaViolationError::UnorderedItemsUnmatched {
+                schema_index: 0,
+                input_index: 0,
+                missing: Vec::new(),
+                unexpected: Vec::new(),
+            },
+            SchemaViolationError::NotEnoughNodesForRepeatingParagraph {
+                schema_index: 0,
+                input_index: 0,
+                expected: ChildrenLengthRange(1, 1),
+                actual: 0,
+            },
+            SchemaViolationError::NonRepeatingMatcherInListContext {
+                schema_index: 0,
+                input_index: 0,
+            },
+            SchemaViolationError::ChildrenLengthMismatch {
+                schema_index: 0,
+                input_index: 0,
+                expected: ChildrenLengthRange(1, 1),
+                actual: 0,
+            },
+            SchemaViolationError::NodeListTooDeep {
+                schema_index: 0,
+                input_index: 0,
+                max_depth: 1,
+            },
+            SchemaViolationError::WrongListCount {
+                schema_index: 0,
+                input_index: 0,
+                min: None,
+                max: None,
+                actual: 0,
+            },
+            SchemaViolationError::WrongSectionCount {
+                schema_index: 0,
+                input_index: 0,
+                min: None,
+                max: None,
+                actual: 0,
+            },
+            SchemaViolationError::WrongRowCount {
+                schema_index: 0,
+                input_index: 0,
+                min: None,
+                max: None,
+                actual: 0,
+            },
+            SchemaViolationError::MalformedNodeStructure {
+                schema_index: 0,
+                input_index: 0,
+                kind: MalformedStructureKind::HadExtraListItem,
+            },
+        ];
+        let content_mismatches = [
+            NodeContentMismatchKind::Prefix,
+            NodeContentMismatchKind::Suffix,
+            NodeContentMismatchKind::Matcher,
+            NodeContentMismatchKind::NegatedMatcher,
+            NodeContentMismatchKind::Literal,
+            NodeContentMismatchKind::NormalizedLiteral(TextNormalizations::default()),
+            NodeContentMismatchKind::InfoString,
+            NodeContentMismatchKind::SimilarLiteral {
+                threshold: 0,
+                similarity: 0,
+            },
+        ]
+        .into_iter()
+        .map(|kind| SchemaViolationError::NodeContentMismatch {
+            schema_index: 0,
+            input_index: 0,
+            expected: String::new(),
+            actual: String::new(),
+            kind,
+        });
+        let schema_errors = vec![
+            SchemaError::MultipleMatchersInNodeChildren {
+                schema_index: 0,
+                received: 2,
+            },
+            SchemaError::RepeatingMatcherInTextContainer { schema_index: 0 },
+            SchemaError::InvalidMatcherExtras {
+                schema_index: 0,
+                error: MatcherExtrasError::MatcherExtrasInvalid,
+            },
+            SchemaError::UnclosedMatcher { schema_index: 0 },
+            SchemaError::MatcherError {
+                error: MatcherError::WasLiteralCode,
+                schema_index: 0,
+            },
+            SchemaError::RepeatingMatcherUnbounded { schema_index: 0 },
+            SchemaError::UTF8Error { schema_index: 0 },
+            SchemaError::DuplicateMatcherId {
+                schema_index: 0,
+                id: String::new(),
+            },
+            SchemaError::InvalidInclude {
+                schema_index: 0,
+                error: IncludeError::NoSchemaPath {
+                    path: Default::default(),
+                },
+            },
+            SchemaError::DuplicateScope {
+                schema_index: 0,
+                path: String::new(),
+            },
+            SchemaError::MatcherSyntaxSuspicious {
+                schema_index: 0,
+                text: String::new(),
+                hint: String::new(),
+                suggestion: String::new(),
+            },
+            SchemaError::InvalidSchemaVersion {
+                schema_index: 0,
+                declared: String::new(),
+            },
+            SchemaError::InvalidBackReference {
+                schema_index: 0,
+                id: String::new(),
+                error: BackReferenceError::UnknownId,
+            },
+            SchemaError::InvalidCondition {
+                schema_index: 0,
+                id: String::new(),
+                error: BackReferenceError::UnknownId,
+            },
+            SchemaError::InvalidForbiddenPattern {
+                schema_index: 0,
+                pattern: String::new(),
+                error: MatcherError::WasLiteralCode,
+            },
+            SchemaError::MatchPathConflict {
+                schema_index: 0,
+                id: String::new(),
+                other_id: String::new(),
+            },
+            SchemaError::UnknownCompareMode {
+                schema_index: 0,
+                mode: String::new(),
+            },
+        ];
+
+        violations
+            .into_iter()
+            .chain(content_mismatches)
+            .map(ValidationError::SchemaViolation)
+            .chain(schema_errors.into_iter().map(ValidationError::SchemaError))
+            .chain([
+                ValidationError::IoError(String::new()),
+                ValidationError::ParserError(ParserError::TreesitterError),
+                ValidationError::ValidatorCreationFailed,
+                ValidationError::InternalInvariantViolated(String::new()),
+            ])
+            .collect()
+    }
+
+    #[test]
+    fn test_every_error_has_a_unique_explained_code() {
+        let errors = one_of_each_error();
+
+        let codes: HashSet<_> = errors.iter().map(ValidationError::code).collect();
+        assert_eq!(codes.len(), errors.len(), "Error codes must be unique");
+
+        for code in &codes {
+            assert!(explain(code).is_some(), "{} has no explanation", code);
+        }
+        assert_eq!(
+            codes.len(),
+            ERROR_EXPLANATIONS.len(),
+            "Every explanation must belong to an error"
+        );
+    }
+
+    #[test]
+    fn test_every_error_kind_can_be_a_warning() {
+        for error in one_of_each_error() {
+            assert!(
+                error.kind().parse::<WarnRule>().is_ok(),
+                "{} can't be made a warning",
+                error.kind()
+            );
+            if let ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                kind,
+                ..
+            }) = &error
+            {
+                let rule = format!("node_content_mismatch:{}", kind.name());
+                assert!(rule.parse::<WarnRule>().is_ok(), "{} isn't a rule", rule);
+            }
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        let explanation = explain("mdv002").unwrap();
+        assert_eq!(explanation.name, "Prefix mismatch");
+
+        let text = explanation.to_string();
+        assert!(text.starts_with("MDV002: Prefix mismatch\n"));
+        assert!(text.contains("Schema:\n\n    Name: `name:/\\w+/`\n"));
+        assert!(text.ends_with("Fix: Make the text before the matcher match the schema exactly."));
+
+        assert!(explain("MDV999").is_none());
+    }
+}
@@ -0,0 +1,114 @@
+//! Where errors point, without the trees.
+//!
+//! Errors point at nodes by their descendant indexes, which mean nothing
+//! without the trees, and streamed input changes the input tree, so an index
+//! from an earlier pass can end up pointing at another node. A `Validator`
+//! looks up the nodes of the errors of every pass while its trees still match
+//! them, and keeps their kind, bytes and the start of their text as
+//! `ErrorLocations` (see `Validator::error_locations`). The errors themselves
+//! keep their indexes.
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::{Node, Tree};
+
+use crate::mdschema::validation::{
+    bounded_memory::DroppedInput,
+    errors::ValidationError,
+    ts_utils::{find_node_by_index, get_node_text},
+};
+
+/// How many characters of a node's text a location keeps.
+pub const SNIPPET_CHARS: usize = 40;
+
+/// A node an error points at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorLocation {
+    /// The descendant index the error has for the node.
+    pub descendant_index: usize,
+    /// The kind of node, like `paragraph`.
+    pub kind: String,
+    /// The bytes of the node, in the whole document.
+    pub byte_range: Range<usize>,
+    /// The first `SNIPPET_CHARS` characters of the node's text.
+    pub snippet: String,
+}
+
+impl ErrorLocation {
+    /// # Arguments
+    ///
+    /// * `node`: The node the error points at.
+    /// * `descendant_index`: The index the error has for it.
+    /// * `src`: The text of the node's tree.
+    /// * `byte_offset`: How far into the whole document `src` starts.
+    pub fn of_node(node: &Node, descendant_index: usize, src: &str, byte_offset: usize) -> Self {
+        let range = node.byte_range();
+        Self {
+            descendant_index,
+            kind: node.kind().to_string(),
+            byte_range: byte_offset + range.start..byte_offset + range.end,
+            snippet: get_node_text(node, src)
+                .chars()
+                .take(SNIPPET_CHARS)
+                .collect(),
+        }
+    }
+}
+
+/// The schema and input nodes an error points at, if it points at any.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorLocations {
+    pub schema: Option<ErrorLocation>,
+    pub input: Option<ErrorLocation>,
+}
+
+impl ErrorLocations {
+    /// Look up the nodes of an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `error`: The error, with its input index into the whole input.
+    /// * `schema`: The schema tree and its text.
+    /// * `input`: The input tree and its text, which is what's left after the
+    ///   start of the input that `dropped_input` says was dropped.
+    /// * `dropped_input`: The start of the input that isn't in the tree.
+    pub(crate) fn resolve(
+        error: &ValidationError,
+        (schema_tree, schema_str): (&Tree, &str),
+        (input_tree, input_str): (&Tree, &str),
+        dropped_input: &DroppedInput,
+    ) -> Self {
+        let (schema_index, input_index) = error.node_indexes();
+        Self {
+            schema: schema_index.map(|index| {
+                let node = find_node_by_index(schema_tree.root_node(), index);
+                ErrorLocation::of_node(&node, index, schema_str, 0)
+            }),
+            input: input_index.map(|index| {
+                let node =
+                    find_node_by_index(input_tree.root_node(), dropped_input.kept_index(index));
+                ErrorLocation::of_node(&node, index, input_str, dropped_input.bytes)
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    #[test]
+    fn test_error_location_of_node() {
+        let input = "A paragraph that goes on for longer than a snippet does\n";
+        let tree = parse_markdown(input).unwrap();
+        let paragraph = tree.root_node().child(0).unwrap();
+
+        let location = ErrorLocation::of_node(&paragraph, 1, input, 100);
+        assert_eq!(location.descendant_index, 1);
+        assert_eq!(location.kind, "paragraph");
+        assert_eq!(location.byte_range.start, 100);
+        assert_eq!(location.snippet, "A paragraph that goes on for longer than");
+        assert_eq!(location.snippet.chars().count(), SNIPPET_CHARS);
+    }
+}
@@ -1,16 +1,23 @@
 use crate::mdschema::validation::{
+    back_references::BackReferenceError,
+    diff::{LONG_TEXT_LEN, render_diff},
+    includes::IncludeError,
     matchers::{
         matcher::*,
         matcher_extras::{MatcherExtras, MatcherExtrasError},
     },
+    snippet::render_snippet,
+    text_normalization::{COMPARE_MODES, TextNormalizations},
     validator::{Validator, ValidatorState},
 };
-use ariadne::{Color, Label, Report, ReportKind, Source};
+use ariadne::{Color, Config, Label, Report, ReportKind, Source};
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::ops::Range;
 use tree_sitter::TreeCursor;
 
 use crate::mdschema::validation::{
-    walkers::utils::pretty_print_cursor_pair,
+    walkers::utils::{pretty_print_cursor_pair, pretty_print_subtree_pair},
     ts_utils::{find_node_by_index, walk_to_root},
 };
 
@@ -27,10 +34,43 @@ macro_rules! trace_cursors {
     }};
 }
 
+/// Report that something the validator relies on doesn't hold.
+///
+/// Given the `result` of a validator, and the cursors it was at if there are
+/// any, the violation is added to it as a
+/// `ValidationError::InternalInvariantViolated` and the result is returned,
+/// so that no schema or input can make validation panic. Otherwise it panics
+/// with the `invariant_violations` feature and is unreachable without it.
 #[macro_export]
 macro_rules! invariant_violation {
-    (result, $schema_cursor:expr, $input_cursor:expr, $message:expr $(, $($args:tt)*)?) => {{
-        $crate::invariant_violation!($schema_cursor, $input_cursor, $message $(, $($args)*)?)
+    (
+        $result:ident, $schema_cursor:expr, $input_cursor:expr, $message:literal
+        $(, $($args:tt)*)?
+    ) => {{
+        let error_msg = $crate::mdschema::validation::errors::invariant_violation_message(
+            Some(($schema_cursor, $input_cursor)),
+            format!($message $(, $($args)*)?),
+            module_path!(),
+        );
+        $result.add_error(
+            $crate::mdschema::validation::errors::ValidationError::InternalInvariantViolated(
+                error_msg,
+            ),
+        );
+        return $result;
+    }};
+    ($result:ident, $message:literal $(, $($args:tt)*)?) => {{
+        let error_msg = $crate::mdschema::validation::errors::invariant_violation_message(
+            None,
+            format!($message $(, $($args)*)?),
+            module_path!(),
+        );
+        $result.add_error(
+            $crate::mdschema::validation::errors::ValidationError::InternalInvariantViolated(
+                error_msg,
+            ),
+        );
+        return $result;
     }};
     ($schema_cursor:expr, $input_cursor:expr, $message:expr $(, $($args:tt)*)?) => {{
         #[cfg(feature = "invariant_violations")]
@@ -125,6 +165,9 @@ impl fmt::Display for ValidationError {
             ValidationError::SchemaError(e) => write!(f, "Schema error: {}", e),
             ValidationError::ParserError(e) => write!(f, "Parser error: {}", e),
             ValidationError::ValidatorCreationFailed => write!(f, "Failed to create validator"),
+            ValidationError::InternalInvariantViolated(e) => {
+                write!(f, "Internal invariant violated: {}", e)
+            }
         }
     }
 }
@@ -133,7 +176,8 @@ impl fmt::Display for ValidationError {
 ///
 /// This enum represents all possible errors that can occur during markdown validation,
 /// from IO issues to schema violations to parser errors.
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum ValidationError {
     /// IO error occurred while reading input.
     IoError(String),
@@ -149,10 +193,74 @@ pub enum ValidationError {
 
     /// Failed to create or initialize the validator.
     ValidatorCreationFailed,
+
+    /// The validator got into a state it doesn't expect. This is always a
+    /// bug in the validator, not in the schema or input.
+    InternalInvariantViolated(String),
+}
+
+impl ValidationError {
+    /// The descendant indexes of the schema and input nodes this error points
+    /// at, if it points at any.
+    pub fn node_indexes(&self) -> (Option<usize>, Option<usize>) {
+        match self {
+            ValidationError::SchemaViolation(e) => {
+                let (schema_index, input_index) = e.node_indexes();
+                (Some(schema_index), Some(input_index))
+            }
+            ValidationError::SchemaError(e) => (Some(e.schema_index()), None),
+            ValidationError::IoError(_)
+            | ValidationError::ParserError(_)
+            | ValidationError::ValidatorCreationFailed
+            | ValidationError::InternalInvariantViolated(_) => (None, None),
+        }
+    }
+
+    /// Point the error at the input node `offset` descendants further on, for
+    /// an error found in input that had `offset` descendants before it
+    /// dropped (see `bounded_memory`). Errors at the root of the input stay
+    /// there, since it's the root of both.
+    pub(crate) fn shift_input_index(&mut self, offset: usize) {
+        if let ValidationError::SchemaViolation(e) = self {
+            let input_index = e.input_index_mut();
+            if *input_index != 0 {
+                *input_index += offset;
+            }
+        }
+    }
+
+    /// The stable short code of this kind of error, like `MDV001`.
+    ///
+    /// Codes never change meaning once assigned, so they can be used to
+    /// filter errors. `mdv explain <code>` describes each of them.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ValidationError::SchemaViolation(e) => e.code(),
+            ValidationError::SchemaError(e) => e.code(),
+            ValidationError::IoError(_) => "MDV201",
+            ValidationError::ParserError(_) => "MDV202",
+            ValidationError::ValidatorCreationFailed => "MDV203",
+            ValidationError::InternalInvariantViolated(_) => "MDV204",
+        }
+    }
+
+    /// A stable snake_case name for this kind of error, like
+    /// `node_content_mismatch`, for tools that consume errors as data.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ValidationError::SchemaViolation(e) => e.kind(),
+            ValidationError::SchemaError(e) => e.kind(),
+            ValidationError::IoError(_) => "io_error",
+            ValidationError::ParserError(_) => "parser_error",
+            ValidationError::ValidatorCreationFailed => "validator_creation_failed",
+            ValidationError::InternalInvariantViolated(_) => "internal_invariant_violated",
+        }
+    }
 }
 
 /// Errors that occur during parsing of input or schema.
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum ParserError {
     /// Attempted to read after already reaching end of file.
     ///
@@ -187,7 +295,8 @@ impl fmt::Display for ParserError {
 /// Errors in the schema definition itself.
 ///
 /// These errors indicate problems with the schema document, not the input being validated.
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum SchemaError {
     /// Node has multiple matchers in its children (only one is allowed).
     MultipleMatchersInNodeChildren {
@@ -260,6 +369,82 @@ pub enum SchemaError {
 
     /// Schema text contains invalid UTF-8 encoding.
     UTF8Error { schema_index: usize },
+
+    /// More than one matcher captured a value under the same id, with
+    /// `DuplicateIdPolicy::Error`. The index is of the second matcher with
+    /// the id, if we could find it.
+    DuplicateMatcherId { schema_index: usize, id: String },
+
+    /// An include directive, like `` `include:./person.mds` ``, couldn't be
+    /// resolved (see `includes::resolve_includes`).
+    InvalidInclude {
+        schema_index: usize,
+        error: IncludeError,
+    },
+
+    /// Two headings scope their matches under the same keys, like two
+    /// `` `@scope:config` `` headings at the same level (see `scopes`). The
+    /// index is of the second heading.
+    DuplicateScope { schema_index: usize, path: String },
+
+    /// A code span looks like a matcher with a small mistake, like
+    /// `` `name:/\w+` ``, so it is compared literally. This is only ever
+    /// reported as a warning. Code spans marked as literal code with `!` are
+    /// left alone.
+    MatcherSyntaxSuspicious {
+        schema_index: usize,
+        /// The text of the code span, backticks included.
+        text: String,
+        /// What looks wrong, like "missing closing '/'".
+        hint: String,
+        /// The matcher it was probably meant to be.
+        suggestion: String,
+    },
+
+    /// The version declared in the schema's preamble, like
+    /// `<!-- mds-version: two -->`, isn't a positive whole number (see
+    /// `preamble`). The preamble is stripped, so the index is of the root.
+    InvalidSchemaVersion { schema_index: usize, declared: String },
+
+    /// A back-reference, like `` `=title` ``, can't be resolved, because no
+    /// earlier matcher captures a single value under its id (see
+    /// `back_references`).
+    InvalidBackReference {
+        schema_index: usize,
+        id: String,
+        error: BackReferenceError,
+    },
+
+    /// A condition, like `` `@when:type=library` ``, can't be decided, for
+    /// the same reasons as a back-reference (see `conditions`).
+    InvalidCondition {
+        schema_index: usize,
+        id: String,
+        error: BackReferenceError,
+    },
+
+    /// A pattern of a forbid block isn't a valid regex (see `forbidden`). The
+    /// block is stripped from the schema, so this points at the top-level node
+    /// after where it was.
+    InvalidForbiddenPattern {
+        schema_index: usize,
+        pattern: String,
+        error: MatcherError,
+    },
+
+    /// A dotted matcher id nests its match under a key that another matcher
+    /// captures a value under, or the other way around, like `meta` and
+    /// `meta.author` (see `match_paths`).
+    MatchPathConflict {
+        schema_index: usize,
+        id: String,
+        other_id: String,
+    },
+
+    /// A `{compare: ...}` modifier or `<!-- mds:compare ... -->` directive
+    /// asks for a comparison mode we don't know, like `nfd` (see
+    /// `text_normalization`).
+    UnknownCompareMode { schema_index: usize, mode: String },
 }
 
 impl fmt::Display for SchemaError {
@@ -285,30 +470,219 @@ impl fmt::Display for SchemaError {
                 write!(f, "Unbounded repeating matcher must be last in sequence")
             }
             SchemaError::UTF8Error { .. } => write!(f, "Invalid UTF-8 in schema"),
+            SchemaError::DuplicateMatcherId { id, .. } => {
+                write!(f, "More than one matcher captured a value for id '{}'", id)
+            }
+            SchemaError::InvalidInclude { error, .. } => write!(f, "Invalid include: {}", error),
+            SchemaError::DuplicateScope { path, .. } => {
+                write!(f, "More than one heading has the scope '{}'", path)
+            }
+            SchemaError::MatcherSyntaxSuspicious {
+                text,
+                hint,
+                suggestion,
+                ..
+            } => write!(
+                f,
+                "{} looks like a matcher, but is compared literally ({}), did you mean `{}`?",
+                text, hint, suggestion
+            ),
+            SchemaError::InvalidSchemaVersion { declared, .. } => write!(
+                f,
+                "Invalid schema version '{}', expected a positive whole number",
+                declared
+            ),
+            SchemaError::InvalidBackReference { id, error, .. } => {
+                write!(f, "Invalid back-reference to '{}': {}", id, error)
+            }
+            SchemaError::InvalidCondition { id, error, .. } => {
+                write!(f, "Invalid condition on '{}': {}", id, error)
+            }
+            SchemaError::InvalidForbiddenPattern { pattern, error, .. } => {
+                write!(f, "Invalid forbidden pattern '{}': {}", pattern, error)
+            }
+            SchemaError::MatchPathConflict { id, other_id, .. } => write!(
+                f,
+                "Matcher ids '{}' and '{}' conflict, since one nests its match under the other",
+                other_id, id
+            ),
+            SchemaError::UnknownCompareMode { mode, .. } => write!(
+                f,
+                "Unknown comparison mode '{}', expected one of: {}",
+                mode,
+                COMPARE_MODES.join(", ")
+            ),
+        }
+    }
+}
+
+impl SchemaError {
+    /// The descendant index of the schema node this error points at.
+    pub fn schema_index(&self) -> usize {
+        match self {
+            SchemaError::MultipleMatchersInNodeChildren { schema_index, .. }
+            | SchemaError::RepeatingMatcherInTextContainer { schema_index }
+            | SchemaError::InvalidMatcherExtras { schema_index, .. }
+            | SchemaError::UnclosedMatcher { schema_index }
+            | SchemaError::MatcherError { schema_index, .. }
+            | SchemaError::RepeatingMatcherUnbounded { schema_index }
+            | SchemaError::UTF8Error { schema_index }
+            | SchemaError::DuplicateMatcherId { schema_index, .. }
+            | SchemaError::InvalidInclude { schema_index, .. }
+            | SchemaError::DuplicateScope { schema_index, .. }
+            | SchemaError::MatcherSyntaxSuspicious { schema_index, .. }
+            | SchemaError::InvalidSchemaVersion { schema_index, .. }
+            | SchemaError::InvalidBackReference { schema_index, .. }
+            | SchemaError::InvalidCondition { schema_index, .. }
+            | SchemaError::InvalidForbiddenPattern { schema_index, .. }
+            | SchemaError::MatchPathConflict { schema_index, .. }
+            | SchemaError::UnknownCompareMode { schema_index, .. } => *schema_index,
+        }
+    }
+
+    /// The stable short code of this kind of error (see
+    /// `ValidationError::code`).
+    pub fn code(&self) -> &'static str {
+        match self {
+            SchemaError::MultipleMatchersInNodeChildren { .. } => "MDV101",
+            SchemaError::RepeatingMatcherInTextContainer { .. } => "MDV102",
+            SchemaError::InvalidMatcherExtras { .. } => "MDV103",
+            SchemaError::UnclosedMatcher { .. } => "MDV104",
+            SchemaError::MatcherError { .. } => "MDV105",
+            SchemaError::RepeatingMatcherUnbounded { .. } => "MDV106",
+            SchemaError::UTF8Error { .. } => "MDV107",
+            SchemaError::DuplicateMatcherId { .. } => "MDV108",
+            SchemaError::InvalidInclude { .. } => "MDV109",
+            SchemaError::DuplicateScope { .. } => "MDV110",
+            SchemaError::MatcherSyntaxSuspicious { .. } => "MDV111",
+            SchemaError::InvalidSchemaVersion { .. } => "MDV112",
+            SchemaError::InvalidBackReference { .. } => "MDV113",
+            SchemaError::InvalidCondition { .. } => "MDV114",
+            SchemaError::InvalidForbiddenPattern { .. } => "MDV115",
+            SchemaError::MatchPathConflict { .. } => "MDV116",
+            SchemaError::UnknownCompareMode { .. } => "MDV117",
+        }
+    }
+
+    /// A stable snake_case name for this kind of error (see
+    /// `ValidationError::kind`).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SchemaError::MultipleMatchersInNodeChildren { .. } => {
+                "multiple_matchers_in_node_children"
+            }
+            SchemaError::RepeatingMatcherInTextContainer { .. } => {
+                "repeating_matcher_in_text_container"
+            }
+            SchemaError::InvalidMatcherExtras { .. } => "invalid_matcher_extras",
+            SchemaError::UnclosedMatcher { .. } => "unclosed_matcher",
+            SchemaError::MatcherError { .. } => "matcher_error",
+            SchemaError::RepeatingMatcherUnbounded { .. } => "repeating_matcher_unbounded",
+            SchemaError::UTF8Error { .. } => "utf8_error",
+            SchemaError::DuplicateMatcherId { .. } => "duplicate_matcher_id",
+            SchemaError::InvalidInclude { .. } => "invalid_include",
+            SchemaError::DuplicateScope { .. } => "duplicate_scope",
+            SchemaError::MatcherSyntaxSuspicious { .. } => "matcher_syntax_suspicious",
+            SchemaError::InvalidSchemaVersion { .. } => "invalid_schema_version",
+            SchemaError::InvalidBackReference { .. } => "invalid_back_reference",
+            SchemaError::InvalidCondition { .. } => "invalid_condition",
+            SchemaError::InvalidForbiddenPattern { .. } => "invalid_forbidden_pattern",
+            SchemaError::MatchPathConflict { .. } => "match_path_conflict",
+            SchemaError::UnknownCompareMode { .. } => "unknown_compare_mode",
         }
     }
 }
 
 /// Represents the kind of mismatch that occurred between expected and actual content in a node.
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum NodeContentMismatchKind {
     /// The suffix following a matcher doesn't match.
     Suffix,
     /// The actual matcher pattern doesn't match.
     Matcher,
+    /// A matcher with a message, like `` `date:@date|Use a date` ``, doesn't
+    /// match. The expected content is its pattern, and the message is what
+    /// we tell the user instead.
+    MatcherMessage(String),
+    /// The named pattern of a matcher, like `@date`, doesn't match. The
+    /// expected content says what the pattern stands for, like "a date".
+    NamedPattern,
+    /// A negated matcher, like `` `!:/TODO/` ``, matches. The expected content
+    /// is its pattern, which the content must not match.
+    NegatedMatcher,
     /// The prefix following a matcher doesn't match.
     Prefix,
     /// A literal piece of content doesn't match.
     Literal,
+    /// A literal piece of content doesn't match, even after normalizing both
+    /// sides. The expected and actual content are the original text.
+    NormalizedLiteral(TextNormalizations),
+    /// The info string (language) of a code block doesn't match.
+    InfoString,
+    /// A literal piece of content with a `{similar: ...}` modifier isn't
+    /// similar enough (see `utils::similarity`). Both are in thousandths.
+    SimilarLiteral { threshold: u16, similarity: u16 },
+}
+
+impl NodeContentMismatchKind {
+    /// A stable snake_case name for this kind of content, like `suffix`, for
+    /// choosing which mismatches to report as warnings (see `severity`).
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeContentMismatchKind::Suffix => "suffix",
+            NodeContentMismatchKind::Matcher | NodeContentMismatchKind::MatcherMessage(_) => {
+                "matcher"
+            }
+            NodeContentMismatchKind::NamedPattern => "named_pattern",
+            NodeContentMismatchKind::NegatedMatcher => "negated_matcher",
+            NodeContentMismatchKind::Prefix => "prefix",
+            NodeContentMismatchKind::Literal => "literal",
+            NodeContentMismatchKind::NormalizedLiteral(_) => "normalized_literal",
+            NodeContentMismatchKind::InfoString => "info_string",
+            NodeContentMismatchKind::SimilarLiteral { .. } => "similar_literal",
+        }
+    }
+
+    /// Whether the expected content is text to compare against, rather than
+    /// a pattern or a description of one.
+    pub fn is_literal(&self) -> bool {
+        !matches!(
+            self,
+            NodeContentMismatchKind::Matcher
+                | NodeContentMismatchKind::MatcherMessage(_)
+                | NodeContentMismatchKind::NamedPattern
+                | NodeContentMismatchKind::NegatedMatcher
+        )
+    }
 }
 
 impl fmt::Display for NodeContentMismatchKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             NodeContentMismatchKind::Suffix => write!(f, "suffix"),
-            NodeContentMismatchKind::Matcher => write!(f, "matcher"),
+            NodeContentMismatchKind::Matcher | NodeContentMismatchKind::MatcherMessage(_) => {
+                write!(f, "matcher")
+            }
+            NodeContentMismatchKind::NamedPattern => write!(f, "named pattern"),
+            NodeContentMismatchKind::NegatedMatcher => write!(f, "negated matcher"),
             NodeContentMismatchKind::Prefix => write!(f, "prefix"),
             NodeContentMismatchKind::Literal => write!(f, "literal"),
+            NodeContentMismatchKind::NormalizedLiteral(normalizations) => {
+                write!(f, "literal (compared {})", normalizations)
+            }
+            NodeContentMismatchKind::InfoString => write!(f, "info string"),
+            NodeContentMismatchKind::SimilarLiteral {
+                threshold,
+                similarity,
+            } => write!(
+                f,
+                "literal ({}.{:03} similar, needs {}.{:03})",
+                similarity / 1000,
+                similarity % 1000,
+                threshold / 1000,
+                threshold % 1000
+            ),
         }
     }
 }
@@ -316,7 +690,8 @@ impl fmt::Display for NodeContentMismatchKind {
 /// Violations where input doesn't match a valid schema.
 ///
 /// These errors indicate that the input document doesn't conform to the schema definition.
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum SchemaViolationError {
     /// Node type doesn't match expected type from schema.
     NodeTypeMismatch {
@@ -340,6 +715,192 @@ pub enum SchemaViolationError {
         kind: NodeContentMismatchKind,
     },
 
+    /// Input text isn't one of the values an enum matcher allows.
+    EnumMismatch {
+        schema_index: usize,
+        input_index: usize,
+        /// The values the enum matcher allows.
+        allowed: Vec<String>,
+        /// Actual content found in input.
+        actual: String,
+    },
+
+    /// A matcher captured text that doesn't parse as the type it declares,
+    /// like `4.2` for `` `age:int:/[\d.]+/` ``.
+    CapturedValueTypeMismatch {
+        schema_index: usize,
+        input_index: usize,
+        /// The type the matcher declares, like `int`.
+        expected: String,
+        /// The captured text.
+        actual: String,
+    },
+
+    /// A reference-style link uses a label that no link reference definition
+    /// defines, like `[docs][missing]`.
+    UnresolvedLinkReference {
+        schema_index: usize,
+        input_index: usize,
+        /// The label, as written in the link.
+        label: String,
+    },
+
+    /// A schema link points at a fragment, like `[Usage](#usage)`, but no
+    /// heading of the input has that anchor.
+    BrokenAnchor {
+        schema_index: usize,
+        input_index: usize,
+        /// The anchor, without the leading `#`.
+        anchor: String,
+    },
+
+    /// The schema starts with front matter that has required keys, but the
+    /// input doesn't start with front matter (see `frontmatter`).
+    MissingFrontmatter {
+        schema_index: usize,
+        input_index: usize,
+    },
+
+    /// A key the schema's front matter requires isn't in the input's.
+    MissingFrontmatterKey {
+        schema_index: usize,
+        input_index: usize,
+        key: String,
+    },
+
+    /// Top-level blocks of the input and schema stopped lining up, so we
+    /// skipped some of them to resume validating at the next pair of blocks
+    /// that do. The indexes are of the first skipped blocks.
+    UnalignedBlocks {
+        schema_index: usize,
+        input_index: usize,
+        /// How many schema blocks had no input to match.
+        skipped_schema: usize,
+        /// How many input blocks the schema didn't expect.
+        skipped_input: usize,
+    },
+
+    /// The marker of a list item isn't the one the schema expects, like `5.`
+    /// where the schema numbers it `2.`, or `*` in a list that uses `-`. Only
+    /// checked with `ValidateOptions::enforce_ordered_list_numbers`.
+    ///
+    /// Task list items are always checked for the checkbox the schema asks
+    /// for, like `[x]` where the input has `[ ]`.
+    ListMarkerMismatch {
+        schema_index: usize,
+        input_index: usize,
+        /// The marker the schema expects, like `2.`.
+        expected: String,
+        /// The marker of the input item, which is empty for a task item
+        /// without a checkbox.
+        actual: String,
+    },
+
+    /// The input has blocks after everything in the schema, and
+    /// `ValidateOptions::trailing_content` forbids them. The indexes are of
+    /// the last schema block and the first extra input block.
+    TrailingContent {
+        schema_index: usize,
+        input_index: usize,
+        /// How many blocks come after the end of the schema.
+        extra_blocks: usize,
+    },
+
+    /// No heading of the input is at the heading path
+    /// `ValidateOptions::input_selection` selects (see `selection`).
+    SelectionNotFound {
+        schema_index: usize,
+        input_index: usize,
+        /// The heading path, like `API > Authentication`.
+        selection: String,
+    },
+
+    /// More than one heading of the input is at the heading path
+    /// `ValidateOptions::input_selection` selects. The input index is of the
+    /// second one.
+    AmbiguousSelection {
+        schema_index: usize,
+        input_index: usize,
+        /// The heading path, like `API > Authentication`.
+        selection: String,
+        /// The lines the headings are on, starting at 1.
+        lines: Vec<usize>,
+    },
+
+    /// The input text at a back-reference, like `` `=title` ``, isn't what
+    /// the matcher it refers to captured (see `back_references`).
+    BackReferenceMismatch {
+        schema_index: usize,
+        input_index: usize,
+        /// The id the back-reference refers to.
+        id: String,
+        /// The descendant index of the schema matcher that captured the value.
+        capture_index: usize,
+        /// The text the matcher captured.
+        expected: String,
+        /// The text at the back-reference.
+        actual: String,
+    },
+
+    /// The input has no blocks, or only whitespace, but the schema expects
+    /// some. The schema index is of its first block, and the input index is
+    /// of the input document.
+    MissingContent {
+        schema_index: usize,
+        input_index: usize,
+        /// The first line of the first block of the schema.
+        expected: String,
+    },
+
+    /// The input has a block whose condition, like `` `@when:type=library` ``,
+    /// isn't met, so the input shouldn't have it (see `conditions`).
+    ConditionNotMet {
+        schema_index: usize,
+        input_index: usize,
+        /// The id the condition looks at.
+        id: String,
+        /// The text the id has to have captured.
+        expected: String,
+        /// The text it captured, if it captured anything.
+        actual: Option<String>,
+    },
+
+    /// The input is nested so deeply that validating it would go deeper than
+    /// `ValidateOptions::max_nesting_depth`, like a list in a list in a list
+    /// and so on, thousands of times. The nodes aren't validated.
+    NestingTooDeep {
+        schema_index: usize,
+        input_index: usize,
+        /// How deep validation may go.
+        max_depth: usize,
+    },
+
+    /// Text of the input matches a pattern that a forbid block of the schema
+    /// forbids anywhere (see `forbidden`). The schema index is of the
+    /// top-level node after where the block was, and the input index of the
+    /// text node.
+    ForbiddenContent {
+        schema_index: usize,
+        input_index: usize,
+        /// The forbidden pattern.
+        pattern: String,
+        /// The text it matched.
+        actual: String,
+    },
+
+    /// The items of an input list can't be paired up with the items of a
+    /// schema list marked `<!-- mds:unordered -->` (see `unordered_lists`).
+    /// The schema index is of the schema list, and the input index of the
+    /// input list.
+    UnorderedItemsUnmatched {
+        schema_index: usize,
+        input_index: usize,
+        /// The text of the schema items no input item is left for.
+        missing: Vec<String>,
+        /// The text of the input items no schema item is left for.
+        unexpected: Vec<String>,
+    },
+
     /// Not enough nodes for a repeating paragraph.
     NotEnoughNodesForRepeatingParagraph {
         schema_index: usize,
@@ -388,6 +949,38 @@ pub enum SchemaViolationError {
         actual: usize,
     },
 
+    /// Wrong number of sections matched a repeating section, like
+    ///
+    /// ```md
+    /// ## `date:/\d{4}-\d{2}-\d{2}/`{1,}
+    /// ```
+    WrongSectionCount {
+        schema_index: usize,
+        input_index: usize,
+        /// Minimum number of sections allowed (None means no minimum).
+        min: Option<usize>,
+        /// Maximum number of sections allowed (None means no maximum).
+        max: Option<usize>,
+        /// Actual number of sections in input.
+        actual: usize,
+    },
+
+    /// Wrong number of table rows matched a repeating row, like
+    ///
+    /// ```md
+    /// | `item:/\w+/` | `price:/\d+/` |{2,}
+    /// ```
+    WrongRowCount {
+        schema_index: usize,
+        input_index: usize,
+        /// Minimum number of rows allowed (None means no minimum).
+        min: Option<usize>,
+        /// Maximum number of rows allowed (None means no maximum).
+        max: Option<usize>,
+        /// Actual number of rows in input.
+        actual: usize,
+    },
+
     /// Malformed node structure.
     MalformedNodeStructure {
         schema_index: usize,
@@ -396,7 +989,7 @@ pub enum SchemaViolationError {
     },
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ChildrenLengthRange(pub usize, pub usize);
 
 impl From<(usize, usize)> for ChildrenLengthRange {
@@ -441,7 +1034,8 @@ impl std::fmt::Display for ChildrenLengthRange {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum MalformedStructureKind {
     MissingListItemContent,
     HadExtraListItem,
@@ -458,6 +1052,21 @@ impl fmt::Display for SchemaViolationError {
             } => {
                 write!(f, "Expected node type '{}', found '{}'", expected, actual)
             }
+            SchemaViolationError::NodeContentMismatch {
+                expected,
+                actual,
+                kind: NodeContentMismatchKind::NamedPattern,
+                ..
+            } => {
+                write!(f, "Expected {}, found '{}'", expected, actual)
+            }
+            SchemaViolationError::NodeContentMismatch {
+                actual,
+                kind: NodeContentMismatchKind::MatcherMessage(message),
+                ..
+            } => {
+                write!(f, "{} (found '{}')", message, actual)
+            }
             SchemaViolationError::NodeContentMismatch {
                 expected,
                 actual,
@@ -466,6 +1075,21 @@ impl fmt::Display for SchemaViolationError {
             } => {
                 write!(f, "Expected {} '{}', found '{}'", kind, expected, actual)
             }
+            SchemaViolationError::CapturedValueTypeMismatch {
+                expected, actual, ..
+            } => {
+                write!(f, "Expected a value of type {}, found '{}'", expected, actual)
+            }
+            SchemaViolationError::EnumMismatch {
+                allowed, actual, ..
+            } => {
+                write!(
+                    f,
+                    "Expected one of: {}; got '{}'",
+                    allowed.join(", "),
+                    actual
+                )
+            }
             SchemaViolationError::NotEnoughNodesForRepeatingParagraph {
                 expected, actual, ..
             } => {
@@ -493,6 +1117,144 @@ impl fmt::Display for SchemaViolationError {
                 };
                 write!(f, "Expected {} items, found {}", range_desc, actual)
             }
+            SchemaViolationError::WrongSectionCount {
+                min, max, actual, ..
+            } => {
+                let range_desc = match (min, max) {
+                    (Some(min_val), Some(max_val)) => format!("{}-{}", min_val, max_val),
+                    (Some(min_val), None) => format!("at least {}", min_val),
+                    (None, Some(max_val)) => format!("at most {}", max_val),
+                    (None, None) => "any number of".to_string(),
+                };
+                write!(f, "Expected {} sections, found {}", range_desc, actual)
+            }
+            SchemaViolationError::WrongRowCount {
+                min, max, actual, ..
+            } => {
+                let range_desc = match (min, max) {
+                    (Some(min_val), Some(max_val)) => format!("{}-{}", min_val, max_val),
+                    (Some(min_val), None) => format!("at least {}", min_val),
+                    (None, Some(max_val)) => format!("at most {}", max_val),
+                    (None, None) => "any number of".to_string(),
+                };
+                write!(f, "Expected {} rows, found {}", range_desc, actual)
+            }
+            SchemaViolationError::UnresolvedLinkReference { label, .. } => {
+                write!(f, "Link reference '{}' has no definition", label)
+            }
+            SchemaViolationError::BrokenAnchor { anchor, .. } => {
+                write!(f, "No heading has the anchor '#{}'", anchor)
+            }
+            SchemaViolationError::MissingFrontmatter { .. } => {
+                write!(f, "Expected the document to start with front matter")
+            }
+            SchemaViolationError::MissingFrontmatterKey { key, .. } => {
+                write!(f, "Front matter is missing the key '{}'", key)
+            }
+            SchemaViolationError::UnalignedBlocks {
+                skipped_schema,
+                skipped_input,
+                ..
+            } => match (skipped_schema, skipped_input) {
+                (0, _) => write!(f, "Found {} the schema doesn't expect", blocks(*skipped_input)),
+                (_, 0) => write!(f, "Missing {} the schema expects", blocks(*skipped_schema)),
+                _ => write!(
+                    f,
+                    "Found {} where the schema expects {}",
+                    blocks(*skipped_input),
+                    blocks(*skipped_schema)
+                ),
+            },
+            SchemaViolationError::ListMarkerMismatch {
+                expected, actual, ..
+            } if actual.is_empty() => write!(f, "Expected list marker '{}', found none", expected),
+            SchemaViolationError::ListMarkerMismatch {
+                expected, actual, ..
+            } => write!(f, "Expected list marker '{}', found '{}'", expected, actual),
+            SchemaViolationError::TrailingContent { extra_blocks, .. } => write!(
+                f,
+                "Found {} after the end of the schema",
+                blocks(*extra_blocks)
+            ),
+            SchemaViolationError::SelectionNotFound { selection, .. } => {
+                write!(f, "No heading of the input is at '{}'", selection)
+            }
+            SchemaViolationError::AmbiguousSelection {
+                selection, lines, ..
+            } => write!(
+                f,
+                "{} headings of the input are at '{}' (lines {}), expected one",
+                lines.len(),
+                selection,
+                lines
+                    .iter()
+                    .map(|line| line.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            SchemaViolationError::BackReferenceMismatch {
+                id,
+                expected,
+                actual,
+                ..
+            } => write!(
+                f,
+                "Expected '{}', the value captured for '{}', found '{}'",
+                expected, id, actual
+            ),
+            SchemaViolationError::MissingContent { expected, .. } => {
+                write!(f, "Expected '{}', but the input is empty", expected)
+            }
+            SchemaViolationError::ConditionNotMet {
+                id,
+                expected,
+                actual,
+                ..
+            } => match actual {
+                Some(actual) => write!(
+                    f,
+                    "Only expected when '{}' is '{}', but it is '{}'",
+                    id, expected, actual
+                ),
+                None => write!(
+                    f,
+                    "Only expected when '{}' is '{}', but nothing was captured for it",
+                    id, expected
+                ),
+            },
+            SchemaViolationError::NestingTooDeep { max_depth, .. } => write!(
+                f,
+                "Nested too deeply to validate, past a depth of {}",
+                max_depth
+            ),
+            SchemaViolationError::ForbiddenContent {
+                pattern, actual, ..
+            } => write!(
+                f,
+                "Found '{}', which matches the forbidden pattern '{}'",
+                actual, pattern
+            ),
+            SchemaViolationError::UnorderedItemsUnmatched {
+                missing,
+                unexpected,
+                ..
+            } => {
+                let quoted = |items: &[String]| {
+                    items
+                        .iter()
+                        .map(|item| format!("'{}'", item))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                let mut parts = Vec::new();
+                if !missing.is_empty() {
+                    parts.push(format!("no input item for {}", quoted(missing)));
+                }
+                if !unexpected.is_empty() {
+                    parts.push(format!("no schema item for {}", quoted(unexpected)));
+                }
+                write!(f, "Unordered list items don't match: {}", parts.join("; "))
+            }
             SchemaViolationError::MalformedNodeStructure { kind, .. } => {
                 write!(f, "Malformed node structure: {:?}", kind)
             }
@@ -500,21 +1262,294 @@ impl fmt::Display for SchemaViolationError {
     }
 }
 
-/// Errors that occur during pretty-printing of validation errors.
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
-pub enum PrettyPrintError {
-    /// Failed to format error message for display.
-    FailedToPrettyPrint(String),
-
-    /// UTF-8 encoding error while formatting.
-    UTF8Error(String),
+/// A count of blocks, like "1 block" or "3 blocks".
+fn blocks(count: usize) -> String {
+    match count {
+        1 => "1 block".to_string(),
+        _ => format!("{} blocks", count),
+    }
 }
 
-impl fmt::Display for PrettyPrintError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl SchemaViolationError {
+    /// The descendant indexes of the schema and input nodes this error points
+    /// at.
+    pub fn node_indexes(&self) -> (usize, usize) {
         match self {
-            PrettyPrintError::FailedToPrettyPrint(msg) => {
-                write!(f, "Failed to format error: {}", msg)
+            SchemaViolationError::NodeTypeMismatch {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::NodeContentMismatch {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::EnumMismatch {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::CapturedValueTypeMismatch {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::UnresolvedLinkReference {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::BrokenAnchor {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::MissingFrontmatter {
+                schema_index,
+                input_index,
+            }
+            | SchemaViolationError::MissingFrontmatterKey {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::UnalignedBlocks {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::ListMarkerMismatch {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::TrailingContent {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::SelectionNotFound {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::AmbiguousSelection {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::BackReferenceMismatch {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::MissingContent {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::ConditionNotMet {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::NestingTooDeep {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::ForbiddenContent {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::UnorderedItemsUnmatched {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::NotEnoughNodesForRepeatingParagraph {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::NonRepeatingMatcherInListContext {
+                schema_index,
+                input_index,
+            }
+            | SchemaViolationError::ChildrenLengthMismatch {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::NodeListTooDeep {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::WrongListCount {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::WrongSectionCount {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::WrongRowCount {
+                schema_index,
+                input_index,
+                ..
+            }
+            | SchemaViolationError::MalformedNodeStructure {
+                schema_index,
+                input_index,
+                ..
+            } => (*schema_index, *input_index),
+        }
+    }
+
+    /// The descendant index of the input node this error points at, to move
+    /// it.
+    fn input_index_mut(&mut self) -> &mut usize {
+        match self {
+            SchemaViolationError::NodeTypeMismatch { input_index, .. }
+            | SchemaViolationError::NodeContentMismatch { input_index, .. }
+            | SchemaViolationError::EnumMismatch { input_index, .. }
+            | SchemaViolationError::CapturedValueTypeMismatch { input_index, .. }
+            | SchemaViolationError::UnresolvedLinkReference { input_index, .. }
+            | SchemaViolationError::BrokenAnchor { input_index, .. }
+            | SchemaViolationError::MissingFrontmatter { input_index, .. }
+            | SchemaViolationError::MissingFrontmatterKey { input_index, .. }
+            | SchemaViolationError::UnalignedBlocks { input_index, .. }
+            | SchemaViolationError::ListMarkerMismatch { input_index, .. }
+            | SchemaViolationError::TrailingContent { input_index, .. }
+            | SchemaViolationError::SelectionNotFound { input_index, .. }
+            | SchemaViolationError::AmbiguousSelection { input_index, .. }
+            | SchemaViolationError::BackReferenceMismatch { input_index, .. }
+            | SchemaViolationError::MissingContent { input_index, .. }
+            | SchemaViolationError::ConditionNotMet { input_index, .. }
+            | SchemaViolationError::NestingTooDeep { input_index, .. }
+            | SchemaViolationError::ForbiddenContent { input_index, .. }
+            | SchemaViolationError::UnorderedItemsUnmatched { input_index, .. }
+            | SchemaViolationError::NotEnoughNodesForRepeatingParagraph { input_index, .. }
+            | SchemaViolationError::NonRepeatingMatcherInListContext { input_index, .. }
+            | SchemaViolationError::ChildrenLengthMismatch { input_index, .. }
+            | SchemaViolationError::NodeListTooDeep { input_index, .. }
+            | SchemaViolationError::WrongListCount { input_index, .. }
+            | SchemaViolationError::WrongSectionCount { input_index, .. }
+            | SchemaViolationError::WrongRowCount { input_index, .. }
+            | SchemaViolationError::MalformedNodeStructure { input_index, .. } => input_index,
+        }
+    }
+
+    /// The stable short code of this kind of error (see
+    /// `ValidationError::code`).
+    ///
+    /// Content mismatches get a code per kind, since they are fixed in
+    /// different ways.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SchemaViolationError::NodeTypeMismatch { .. } => "MDV001",
+            SchemaViolationError::NodeContentMismatch { kind, .. } => match kind {
+                NodeContentMismatchKind::Prefix => "MDV002",
+                NodeContentMismatchKind::Suffix => "MDV003",
+                NodeContentMismatchKind::Matcher
+                | NodeContentMismatchKind::MatcherMessage(_)
+                | NodeContentMismatchKind::NamedPattern => "MDV004",
+                NodeContentMismatchKind::Literal => "MDV005",
+                NodeContentMismatchKind::NormalizedLiteral(_) => "MDV006",
+                NodeContentMismatchKind::InfoString => "MDV019",
+                NodeContentMismatchKind::SimilarLiteral { .. } => "MDV025",
+                NodeContentMismatchKind::NegatedMatcher => "MDV032",
+            },
+            SchemaViolationError::EnumMismatch { .. } => "MDV007",
+            SchemaViolationError::NotEnoughNodesForRepeatingParagraph { .. } => "MDV008",
+            SchemaViolationError::NonRepeatingMatcherInListContext { .. } => "MDV009",
+            SchemaViolationError::ChildrenLengthMismatch { .. } => "MDV010",
+            SchemaViolationError::NodeListTooDeep { .. } => "MDV011",
+            SchemaViolationError::WrongListCount { .. } => "MDV012",
+            SchemaViolationError::WrongSectionCount { .. } => "MDV013",
+            SchemaViolationError::MalformedNodeStructure { .. } => "MDV014",
+            SchemaViolationError::UnresolvedLinkReference { .. } => "MDV015",
+            SchemaViolationError::BrokenAnchor { .. } => "MDV016",
+            SchemaViolationError::WrongRowCount { .. } => "MDV017",
+            SchemaViolationError::CapturedValueTypeMismatch { .. } => "MDV018",
+            SchemaViolationError::MissingFrontmatter { .. } => "MDV020",
+            SchemaViolationError::MissingFrontmatterKey { .. } => "MDV021",
+            SchemaViolationError::UnalignedBlocks { .. } => "MDV022",
+            SchemaViolationError::ListMarkerMismatch { .. } => "MDV023",
+            SchemaViolationError::TrailingContent { .. } => "MDV024",
+            SchemaViolationError::SelectionNotFound { .. } => "MDV026",
+            SchemaViolationError::AmbiguousSelection { .. } => "MDV027",
+            SchemaViolationError::BackReferenceMismatch { .. } => "MDV028",
+            SchemaViolationError::MissingContent { .. } => "MDV029",
+            SchemaViolationError::ConditionNotMet { .. } => "MDV030",
+            SchemaViolationError::NestingTooDeep { .. } => "MDV031",
+            SchemaViolationError::ForbiddenContent { .. } => "MDV033",
+            SchemaViolationError::UnorderedItemsUnmatched { .. } => "MDV034",
+        }
+    }
+
+    /// A stable snake_case name for this kind of error (see
+    /// `ValidationError::kind`).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SchemaViolationError::NodeTypeMismatch { .. } => "node_type_mismatch",
+            SchemaViolationError::NodeContentMismatch { .. } => "node_content_mismatch",
+            SchemaViolationError::EnumMismatch { .. } => "enum_mismatch",
+            SchemaViolationError::CapturedValueTypeMismatch { .. } => {
+                "captured_value_type_mismatch"
+            }
+            SchemaViolationError::UnresolvedLinkReference { .. } => "unresolved_link_reference",
+            SchemaViolationError::BrokenAnchor { .. } => "broken_anchor",
+            SchemaViolationError::MissingFrontmatter { .. } => "missing_frontmatter",
+            SchemaViolationError::MissingFrontmatterKey { .. } => "missing_frontmatter_key",
+            SchemaViolationError::UnalignedBlocks { .. } => "unaligned_blocks",
+            SchemaViolationError::ListMarkerMismatch { .. } => "list_marker_mismatch",
+            SchemaViolationError::TrailingContent { .. } => "trailing_content",
+            SchemaViolationError::SelectionNotFound { .. } => "selection_not_found",
+            SchemaViolationError::AmbiguousSelection { .. } => "ambiguous_selection",
+            SchemaViolationError::BackReferenceMismatch { .. } => "back_reference_mismatch",
+            SchemaViolationError::MissingContent { .. } => "missing_content",
+            SchemaViolationError::ConditionNotMet { .. } => "condition_not_met",
+            SchemaViolationError::NestingTooDeep { .. } => "nesting_too_deep",
+            SchemaViolationError::ForbiddenContent { .. } => "forbidden_content",
+            SchemaViolationError::UnorderedItemsUnmatched { .. } => "unordered_items_unmatched",
+            SchemaViolationError::NotEnoughNodesForRepeatingParagraph { .. } => {
+                "not_enough_nodes_for_repeating_paragraph"
+            }
+            SchemaViolationError::NonRepeatingMatcherInListContext { .. } => {
+                "non_repeating_matcher_in_list_context"
+            }
+            SchemaViolationError::ChildrenLengthMismatch { .. } => "children_length_mismatch",
+            SchemaViolationError::NodeListTooDeep { .. } => "node_list_too_deep",
+            SchemaViolationError::WrongListCount { .. } => "wrong_list_count",
+            SchemaViolationError::WrongSectionCount { .. } => "wrong_section_count",
+            SchemaViolationError::WrongRowCount { .. } => "wrong_row_count",
+            SchemaViolationError::MalformedNodeStructure { .. } => "malformed_node_structure",
+        }
+    }
+}
+
+/// Errors that occur during pretty-printing of validation errors.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum PrettyPrintError {
+    /// Failed to format error message for display.
+    FailedToPrettyPrint(String),
+
+    /// UTF-8 encoding error while formatting.
+    UTF8Error(String),
+}
+
+impl fmt::Display for PrettyPrintError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrettyPrintError::FailedToPrettyPrint(msg) => {
+                write!(f, "Failed to format error: {}", msg)
             }
             PrettyPrintError::UTF8Error(msg) => write!(f, "UTF-8 error during formatting: {}", msg),
         }
@@ -544,12 +1579,123 @@ pub fn pretty_print_error(
     error: &ValidationError,
     validator: &Validator,
     filename: &str,
+) -> Result<String, PrettyPrintError> {
+    pretty_print_error_with_color(error, validator, filename, true)
+}
+
+/// Like `pretty_print_error`, but without ANSI color codes unless `color` is
+/// set, for output that goes to a file or a CI log.
+pub fn pretty_print_error_with_color(
+    error: &ValidationError,
+    validator: &Validator,
+    filename: &str,
+    color: bool,
 ) -> Result<String, PrettyPrintError> {
     let mut buffer = Vec::new();
-    validation_error_to_ariadne(error, validator, filename, &mut buffer)?;
+    let config = Config::default().with_color(color);
+    validation_error_to_ariadne(error, validator, filename, config, color, &mut buffer)?;
     Ok(String::from_utf8_lossy(&buffer).to_string())
 }
 
+/// How many levels above the offending nodes the debug tree diff starts.
+pub const DEBUG_TREE_DIFF_ANCESTOR_LEVELS: usize = 2;
+
+/// Renders the schema and input subtrees around an error side by side, with
+/// the offending nodes highlighted.
+///
+/// This is meant to follow the pretty printed report in debug mode, to make
+/// it easier to see where the schema and input diverged. Returns `None` if the
+/// error doesn't point at any nodes.
+pub fn pretty_print_error_tree_diff(error: &ValidationError, validator: &Validator) -> Option<String> {
+    let (schema_index, input_index) = error.node_indexes();
+    if schema_index.is_none() && input_index.is_none() {
+        return None;
+    }
+
+    Some(pretty_print_subtree_pair(
+        schema_index.map(|index| (validator.schema_tree(), validator.schema_str(), index)),
+        input_index.map(|index| {
+            (
+                validator.input_tree(),
+                validator.last_input_str(),
+                validator.dropped_input().kept_index(index),
+            )
+        }),
+        DEBUG_TREE_DIFF_ANCESTOR_LEVELS,
+    ))
+}
+
+/// Renders snippets of the input and the schema around an error, with a
+/// line of context on each side and the offending text underlined (see
+/// `snippet`).
+///
+/// This is meant to follow the pretty printed report, which only shows the
+/// lines it labels, and none of the schema. Returns an empty string if the
+/// error doesn't point at any nodes.
+///
+/// # Arguments
+///
+/// * `error`: The error to show.
+/// * `validator`: The validator that found the error.
+/// * `filename`: The name of the input.
+/// * `schema_filename`: The name of the schema.
+/// * `color`: Whether to color the snippets with ANSI codes.
+pub fn pretty_print_error_context(
+    error: &ValidationError,
+    validator: &Validator,
+    filename: &str,
+    schema_filename: &str,
+    color: bool,
+) -> String {
+    let (schema_index, input_index) = error.node_indexes();
+
+    let mut context = String::new();
+    if let Some(input_index) = input_index {
+        context.push_str(&render_snippet(
+            validator.last_input_str(),
+            filename,
+            input_error_range(error, validator, input_index),
+            validator.dropped_input().lines,
+            "found here",
+            color,
+        ));
+    }
+    if let Some(schema_index) = schema_index {
+        context.push_str(&render_snippet(
+            validator.schema_str(),
+            schema_filename,
+            find_node_by_index(validator.schema_tree().root_node(), schema_index).byte_range(),
+            0,
+            "expected by the schema",
+            color,
+        ));
+    }
+    context
+}
+
+/// The part of an input node an error is about: the text that didn't match
+/// for errors about the content of a node, or the whole node otherwise.
+fn input_error_range(
+    error: &ValidationError,
+    validator: &Validator,
+    input_index: usize,
+) -> Range<usize> {
+    let range = validator.input_node(input_index).byte_range();
+    let actual = match error {
+        ValidationError::SchemaViolation(
+            SchemaViolationError::NodeContentMismatch { actual, .. }
+            | SchemaViolationError::EnumMismatch { actual, .. }
+            | SchemaViolationError::CapturedValueTypeMismatch { actual, .. },
+        ) if !actual.is_empty() => actual,
+        _ => return range,
+    };
+
+    match validator.last_input_str()[range.clone()].find(actual.as_str()) {
+        Some(offset) => range.start + offset..range.start + offset + actual.len(),
+        None => range,
+    }
+}
+
 /// Prints error using simple Debug formatting without pretty-printing.
 ///
 /// This is for debugging and development when you want to see the raw error
@@ -564,11 +1710,20 @@ fn validation_error_to_ariadne(
     error: &ValidationError,
     validator: &Validator,
     filename: &str,
+    config: Config,
+    color: bool,
     buffer: &mut Vec<u8>,
 ) -> Result<(), PrettyPrintError> {
     let source_content = validator.last_input_str();
     let tree = validator.input_tree();
 
+    // With bounded memory the input we have is only the end of the document.
+    // We pad it with the lines that were dropped rather than offsetting the
+    // line numbers, since ariadne only offsets the one in the header.
+    let line_offset = validator.dropped_input().lines;
+    let span = |range: Range<usize>| (filename, line_offset + range.start..line_offset + range.end);
+    let code = error.code();
+
     let report = match error {
         ValidationError::SchemaViolation(schema_err) => match schema_err {
             SchemaViolationError::NodeTypeMismatch {
@@ -577,13 +1732,15 @@ fn validation_error_to_ariadne(
                 expected,
                 actual,
             } => {
-                let input_node = find_node_by_index(tree.root_node(), *input_index);
+                let input_node = validator.input_node(*input_index);
                 let input_range = input_node.start_byte()..input_node.end_byte();
 
-                Report::build(ReportKind::Error, (filename, input_range.clone()))
+                Report::build(ReportKind::Error, span(input_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
                     .with_message("Node type mismatch")
                     .with_label(
-                        Label::new((filename, input_range))
+                        Label::new(span(input_range))
                             .with_message(
                                 format!("Expected '{}' but found '{}'", expected, actual,),
                             )
@@ -598,16 +1755,91 @@ fn validation_error_to_ariadne(
                 actual,
                 kind,
             } => {
-                let node = find_node_by_index(tree.root_node(), *input_index);
+                let node = validator.input_node(*input_index);
                 let node_range = node.start_byte()..node.end_byte();
 
-                Report::build(ReportKind::Error, (filename, node_range.clone()))
+                // Long texts that differ by a word are hard to compare in full
+                let diff = (kind.is_literal()
+                    && expected.len().max(actual.len()) >= LONG_TEXT_LEN)
+                    .then(|| render_diff(expected, actual, color))
+                    .flatten();
+
+                let mut report = Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
                     .with_message(format!("Node {} mismatch", kind))
                     .with_label(
-                        Label::new((filename, node_range))
+                        Label::new(span(node_range))
+                            .with_message(match (kind, &diff) {
+                                (NodeContentMismatchKind::NamedPattern, _) => {
+                                    format!("Expected {} but found '{}'", expected, actual)
+                                }
+                                (NodeContentMismatchKind::NegatedMatcher, _) => format!(
+                                    "Expected text that doesn't match '{}' but found '{}'",
+                                    expected, actual
+                                ),
+                                (NodeContentMismatchKind::MatcherMessage(message), _) => {
+                                    format!("{} (found '{}')", message, actual)
+                                }
+                                (_, Some(_)) => format!("Text differs from the expected {}", kind),
+                                _ => format!(
+                                    "Expected {} '{}' but found '{}'",
+                                    kind, expected, actual
+                                ),
+                            })
+                            .with_color(Color::Red),
+                    );
+                if let Some(diff) = diff {
+                    report = report.with_note(diff);
+                }
+                // The pattern is only a detail next to the schema's message
+                if let NodeContentMismatchKind::MatcherMessage(_) = kind {
+                    report = report.with_note(format!("Expected matcher '{}'", expected));
+                }
+                report.finish()
+            }
+            SchemaViolationError::CapturedValueTypeMismatch {
+                schema_index: _,
+                input_index,
+                expected,
+                actual,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Captured value has the wrong type")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(format!(
+                                "'{}' matched, but isn't a valid {}",
+                                actual, expected
+                            ))
+                            .with_color(Color::Red),
+                    )
+                    .finish()
+            }
+            SchemaViolationError::EnumMismatch {
+                schema_index: _,
+                input_index,
+                allowed,
+                actual,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Value not allowed")
+                    .with_label(
+                        Label::new(span(node_range))
                             .with_message(format!(
-                                "Expected {} '{}' but found '{}'",
-                                kind, expected, actual
+                                "Expected one of: {}; got '{}'",
+                                allowed.join(", "),
+                                actual
                             ))
                             .with_color(Color::Red),
                     )
@@ -619,13 +1851,15 @@ fn validation_error_to_ariadne(
                 expected,
                 actual,
             } => {
-                let node = find_node_by_index(tree.root_node(), *input_index);
+                let node = validator.input_node(*input_index);
                 let node_range = node.start_byte()..node.end_byte();
 
-                Report::build(ReportKind::Error, (filename, node_range.clone()))
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
                     .with_message("Not enough nodes for repeating paragraph")
                     .with_label(
-                        Label::new((filename, node_range))
+                        Label::new(span(node_range))
                             .with_message(format!(
                                 "Expected {} children but found {}.",
                                 expected, actual
@@ -642,20 +1876,22 @@ fn validation_error_to_ariadne(
                 let schema_content =
                     node_content_by_index(tree.root_node(), *schema_index, source_content)?;
                 let schema_range = schema_node.start_byte()..schema_node.end_byte();
-                let input_node = find_node_by_index(tree.root_node(), *input_index);
+                let input_node = validator.input_node(*input_index);
                 let input_range = input_node.start_byte()..input_node.end_byte();
 
-                Report::build(ReportKind::Error, (filename, input_range.clone()))
+                Report::build(ReportKind::Error, span(input_range.clone()))
+            .with_code(code)
+            .with_config(config)
             .with_message("Non-repeating matcher in repeating context")
             .with_label(
-                Label::new((filename, input_range))
+                Label::new(span(input_range))
                     .with_message(
                         "This input corresponds to a list node in the schema"
                     )
                     .with_color(Color::Blue),
             )
             .with_label(
-                Label::new((filename, schema_range))
+                Label::new(span(schema_range))
                     .with_message(format!(
                         "This matcher is in a list context but is not marked as repeating: '{}'",
                         schema_content
@@ -674,13 +1910,15 @@ You can mark a list node as repeating by adding a '{<min_count>,<max_count>} dir
                 expected,
                 actual,
             } => {
-                let parent = find_node_by_index(tree.root_node(), *input_index);
+                let parent = validator.input_node(*input_index);
                 let parent_range = parent.start_byte()..parent.end_byte();
 
-                let mut report = Report::build(ReportKind::Error, (filename, parent_range.clone()))
+                let mut report = Report::build(ReportKind::Error, span(parent_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
                     .with_message("Children length mismatch")
                     .with_label(
-                        Label::new((filename, parent_range))
+                        Label::new(span(parent_range))
                             .with_message(format!(
                                 "Expected {} children but found {}.",
                                 expected, actual
@@ -702,13 +1940,15 @@ You can mark a list node as repeating by adding a '{<min_count>,<max_count>} dir
                 input_index,
                 max_depth,
             } => {
-                let node = find_node_by_index(tree.root_node(), *input_index);
+                let node = validator.input_node(*input_index);
                 let node_range = node.start_byte()..node.end_byte();
 
-                Report::build(ReportKind::Error, (filename, node_range.clone()))
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
                     .with_message("Nested list exceeds maximum depth")
                     .with_label(
-                        Label::new((filename, node_range))
+                        Label::new(span(node_range))
                             .with_message(format!(
                                 "List nesting exceeds maximum depth of {} level(s).",
                                 max_depth,
@@ -716,14 +1956,9 @@ You can mark a list node as repeating by adding a '{<min_count>,<max_count>} dir
                             .with_color(Color::Red),
                     )
                     .with_help(
-                        "For schemas like:\n\
-                 - `num1:/\\d/`{1,}\n\
-                 \u{20} - `num2:/\\d/`{1,}{1,}\n\
-                 \n\
-                 You may need to adjust the repetition for the first matcher\n\
-                 to allow for the depth of the following ones. For example, you could\n\
-                 make that `num1:/\\d/`{1,}{1,}{1,} to allow for three levels of nesting (the one \
-                 below it, and the two allowed below that).",
+                        "A list matcher like `num:/\\d/`++{1,} reaches one level of nested \
+                 lists for each `+`. Add a `+` to allow another level, or describe the \
+                 nested list in the schema.",
                     )
                     .finish()
             }
@@ -734,7 +1969,7 @@ You can mark a list node as repeating by adding a '{<min_count>,<max_count>} dir
                 max,
                 actual,
             } => {
-                let node = find_node_by_index(tree.root_node(), *input_index);
+                let node = validator.input_node(*input_index);
                 let schema_content =
                     node_content_by_index(tree.root_node(), *schema_index, source_content)?;
                 let node_range = node.start_byte()..node.end_byte();
@@ -753,10 +1988,12 @@ You can mark a list node as repeating by adding a '{<min_count>,<max_count>} dir
                     range_desc, actual, schema_content
                 );
 
-                Report::build(ReportKind::Error, (filename, node_range.clone()))
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
                     .with_message("List item count mismatch")
                     .with_label(
-                        Label::new((filename, node_range))
+                        Label::new(span(node_range))
                             .with_message(message)
                             .with_color(Color::Red),
                     )
@@ -767,56 +2004,467 @@ You can mark a list node as repeating by adding a '{<min_count>,<max_count>} dir
                     )
                     .finish()
             }
-            SchemaViolationError::MalformedNodeStructure {
+            SchemaViolationError::WrongSectionCount {
                 schema_index: _,
                 input_index,
-                kind,
+                min,
+                max,
+                actual,
             } => {
-                let node = find_node_by_index(tree.root_node(), *input_index);
+                let node = validator.input_node(*input_index);
                 let node_range = node.start_byte()..node.end_byte();
 
-                Report::build(ReportKind::Error, (filename, node_range.clone()))
-                    .with_message("Malformed node structure")
+                let range_desc = match (min, max) {
+                    (Some(min_val), Some(max_val)) => {
+                        format!("between {} and {}", min_val, max_val)
+                    }
+                    (Some(min_val), None) => format!("at least {}", min_val),
+                    (None, Some(max_val)) => format!("at most {}", max_val),
+                    (None, None) => "any number of".to_string(),
+                };
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Section count mismatch")
                     .with_label(
-                        Label::new((filename, node_range))
-                            .with_message(format!("Malformed node structure: {:?}", kind))
+                        Label::new(span(node_range))
+                            .with_message(format!(
+                                "Expected {} section(s) but found {}",
+                                range_desc, actual
+                            ))
                             .with_color(Color::Red),
                     )
+                    .with_help(
+                        "A section is a heading and everything after it up to the next \
+                 heading of the same or a higher level.",
+                    )
                     .finish()
             }
-        },
-        ValidationError::SchemaError(schema_err) => {
-            match schema_err {
-                SchemaError::MultipleMatchersInNodeChildren {
-                    schema_index,
-                    received: received_count,
-                } => {
-                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
-                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
-
-                    Report::build(ReportKind::Error, (filename, schema_range.clone()))
-                        .with_message("Multiple matchers in node children")
-                        .with_label(
-                            Label::new((filename, schema_range))
-                                .with_message(format!(
-                                    "{} matchers found in node children (only 1 allowed)",
-                                    received_count
-                                ))
-                                .with_color(Color::Red),
-                        )
-                        .with_help("Only one matcher is allowed per node's children.")
-                        .finish()
-                }
-                SchemaError::RepeatingMatcherInTextContainer { schema_index } => {
-                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
-                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
+            SchemaViolationError::WrongRowCount {
+                schema_index: _,
+                input_index,
+                min,
+                max,
+                actual,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
 
-                    Report::build(ReportKind::Error, (filename, schema_range.clone()))
-                        .with_message("Repeating matcher in text container")
-                        .with_label(
-                            Label::new((filename, schema_range))
-                                .with_message("Repeating matcher cannot be used in a textual container")
-                                .with_color(Color::Red),
+                let range_desc = match (min, max) {
+                    (Some(min_val), Some(max_val)) => {
+                        format!("between {} and {}", min_val, max_val)
+                    }
+                    (Some(min_val), None) => format!("at least {}", min_val),
+                    (None, Some(max_val)) => format!("at most {}", max_val),
+                    (None, None) => "any number of".to_string(),
+                };
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Table row count mismatch")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(format!(
+                                "Expected {} row(s) matching the repeated row but found {}",
+                                range_desc, actual
+                            ))
+                            .with_color(Color::Red),
+                    )
+                    .finish()
+            }
+            SchemaViolationError::UnresolvedLinkReference {
+                schema_index: _,
+                input_index,
+                label,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Unresolved link reference")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(format!("No definition for link label '{}'", label))
+                            .with_color(Color::Red),
+                    )
+                    .with_help("Define the label with a line like `[label]: https://example.com`.")
+                    .finish()
+            }
+            SchemaViolationError::BrokenAnchor {
+                schema_index: _,
+                input_index,
+                anchor,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Broken anchor")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(format!("No heading has the anchor '#{}'", anchor))
+                            .with_color(Color::Red),
+                    )
+                    .with_help(
+                        "Anchors are made from heading text by lowercasing it, dropping \
+                 punctuation and turning spaces into dashes.",
+                    )
+                    .finish()
+            }
+            SchemaViolationError::MissingFrontmatter {
+                schema_index: _,
+                input_index,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.start_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Missing front matter")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message("Expected front matter here")
+                            .with_color(Color::Red),
+                    )
+                    .with_help("Start the document with front matter between two `---` lines.")
+                    .finish()
+            }
+            SchemaViolationError::MissingFrontmatterKey {
+                schema_index: _,
+                input_index,
+                key,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Missing front matter key")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(format!("Front matter has no '{}' key", key))
+                            .with_color(Color::Red),
+                    )
+                    .finish()
+            }
+            SchemaViolationError::UnalignedBlocks {
+                schema_index: _,
+                input_index,
+                skipped_schema: _,
+                skipped_input: _,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Unaligned blocks")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(schema_err.to_string())
+                            .with_color(Color::Red),
+                    )
+                    .with_help(
+                        "Validation skipped these blocks and resumed at the next blocks that \
+                 line up with the schema.",
+                    )
+                    .finish()
+            }
+            SchemaViolationError::ListMarkerMismatch {
+                schema_index: _,
+                input_index,
+                expected,
+                actual: _,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("List marker mismatch")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(schema_err.to_string())
+                            .with_color(Color::Red),
+                    )
+                    .with_help(format!("Use '{}' as this item's marker.", expected))
+                    .finish()
+            }
+            SchemaViolationError::TrailingContent {
+                schema_index: _,
+                input_index,
+                extra_blocks: _,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..source_content.len();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Trailing content")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(schema_err.to_string())
+                            .with_color(Color::Red),
+                    )
+                    .with_help(
+                        "Remove these blocks, or allow them with `--trailing-content allow`.",
+                    )
+                    .finish()
+            }
+            SchemaViolationError::SelectionNotFound {
+                schema_index: _,
+                input_index,
+                selection: _,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.start_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Selected section not found")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(schema_err.to_string())
+                            .with_color(Color::Red),
+                    )
+                    .with_help(
+                        "Headings of a path are separated by '>', like `API > Authentication`, \
+                 and may use `*` and `?` wildcards.",
+                    )
+                    .finish()
+            }
+            SchemaViolationError::AmbiguousSelection {
+                schema_index: _,
+                input_index,
+                selection: _,
+                lines: _,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Ambiguous selection")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(schema_err.to_string())
+                            .with_color(Color::Red),
+                    )
+                    .with_help("Add the headings above it to the path to pick one of them.")
+                    .finish()
+            }
+            SchemaViolationError::BackReferenceMismatch {
+                schema_index: _,
+                input_index,
+                id,
+                capture_index: _,
+                expected: _,
+                actual: _,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Back-reference mismatch")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(schema_err.to_string())
+                            .with_color(Color::Red),
+                    )
+                    .with_help(format!(
+                        "This has to be the same text as the '{}' captured earlier.",
+                        id
+                    ))
+                    .finish()
+            }
+            SchemaViolationError::ConditionNotMet {
+                schema_index: _,
+                input_index,
+                id,
+                expected,
+                actual: _,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Condition not met")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(schema_err.to_string())
+                            .with_color(Color::Red),
+                    )
+                    .with_help(format!(
+                        "Remove this, or change what '{}' captures to '{}'.",
+                        id, expected
+                    ))
+                    .finish()
+            }
+            SchemaViolationError::NestingTooDeep {
+                schema_index: _,
+                input_index,
+                max_depth: _,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Nesting too deep")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(schema_err.to_string())
+                            .with_color(Color::Red),
+                    )
+                    .with_help(
+                        "Nest the input less deeply, or raise the limit with \
+                 --max-nesting-depth.",
+                    )
+                    .finish()
+            }
+            SchemaViolationError::ForbiddenContent {
+                schema_index: _,
+                input_index,
+                pattern,
+                actual: _,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Forbidden content")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(schema_err.to_string())
+                            .with_color(Color::Red),
+                    )
+                    .with_help(format!(
+                        "Reword this, since the schema forbids anything that matches '{}'.",
+                        pattern
+                    ))
+                    .finish()
+            }
+            SchemaViolationError::UnorderedItemsUnmatched {
+                schema_index: _,
+                input_index,
+                missing: _,
+                unexpected: _,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Unordered list items don't match")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(schema_err.to_string())
+                            .with_color(Color::Red),
+                    )
+                    .with_help(
+                        "The items may be in any order, but each schema item needs an \
+                 input item of its own.",
+                    )
+                    .finish()
+            }
+            SchemaViolationError::MissingContent {
+                schema_index: _,
+                input_index: _,
+                expected: _,
+            } => {
+                let node_range = 0..source_content.len();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Missing content")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(schema_err.to_string())
+                            .with_color(Color::Red),
+                    )
+                    .with_help(
+                        "Write the input the schema describes, starting with its first block.",
+                    )
+                    .finish()
+            }
+            SchemaViolationError::MalformedNodeStructure {
+                schema_index: _,
+                input_index,
+                kind,
+            } => {
+                let node = validator.input_node(*input_index);
+                let node_range = node.start_byte()..node.end_byte();
+
+                Report::build(ReportKind::Error, span(node_range.clone()))
+                    .with_code(code)
+                    .with_config(config)
+                    .with_message("Malformed node structure")
+                    .with_label(
+                        Label::new(span(node_range))
+                            .with_message(format!("Malformed node structure: {:?}", kind))
+                            .with_color(Color::Red),
+                    )
+                    .finish()
+            }
+        },
+        ValidationError::SchemaError(schema_err) => {
+            match schema_err {
+                SchemaError::MultipleMatchersInNodeChildren {
+                    schema_index,
+                    received: received_count,
+                } => {
+                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
+                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
+
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
+                        .with_message("Multiple matchers in node children")
+                        .with_label(
+                            Label::new(span(schema_range))
+                                .with_message(format!(
+                                    "{} matchers found in node children (only 1 allowed)",
+                                    received_count
+                                ))
+                                .with_color(Color::Red),
+                        )
+                        .with_help("Only one matcher is allowed per node's children.")
+                        .finish()
+                }
+                SchemaError::RepeatingMatcherInTextContainer { schema_index } => {
+                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
+                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
+
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
+                        .with_message("Repeating matcher in text container")
+                        .with_label(
+                            Label::new(span(schema_range))
+                                .with_message("Repeating matcher cannot be used in a textual container")
+                                .with_color(Color::Red),
                         )
                         .with_help("Text containers like paragraphs and headings cannot contain repeating matchers. Use repetition syntax only with list items.")
                         .finish()
@@ -825,10 +2473,12 @@ You can mark a list node as repeating by adding a '{<min_count>,<max_count>} dir
                     let schema_node = find_node_by_index(tree.root_node(), *schema_index);
                     let schema_range = schema_node.start_byte()..schema_node.end_byte();
 
-                    Report::build(ReportKind::Error, (filename, schema_range.clone()))
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
                         .with_message("Unclosed matcher")
                         .with_label(
-                            Label::new((filename, schema_range))
+                            Label::new(span(schema_range))
                                 .with_message("Matcher is not properly closed")
                                 .with_color(Color::Red),
                         )
@@ -842,10 +2492,12 @@ You can mark a list node as repeating by adding a '{<min_count>,<max_count>} dir
                     let schema_node = find_node_by_index(tree.root_node(), *schema_index);
                     let schema_range = schema_node.start_byte()..schema_node.end_byte();
 
-                    Report::build(ReportKind::Error, (filename, schema_range.clone()))
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
                         .with_message("Matcher error")
                         .with_label(
-                            Label::new((filename, schema_range))
+                            Label::new(span(schema_range))
                                 .with_message(format!("Matcher error: {}", error))
                                 .with_color(Color::Red),
                         )
@@ -855,15 +2507,219 @@ You can mark a list node as repeating by adding a '{<min_count>,<max_count>} dir
                     let schema_node = find_node_by_index(tree.root_node(), *schema_index);
                     let schema_range = schema_node.start_byte()..schema_node.end_byte();
 
-                    Report::build(ReportKind::Error, (filename, schema_range.clone()))
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
                         .with_message("UTF-8 error in schema")
                         .with_label(
-                            Label::new((filename, schema_range))
+                            Label::new(span(schema_range))
                                 .with_message("Schema text at this position is not valid UTF-8")
                                 .with_color(Color::Red),
                         )
                         .finish()
                 }
+                SchemaError::DuplicateMatcherId { schema_index, id } => {
+                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
+                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
+
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
+                        .with_message("Duplicate matcher id")
+                        .with_label(
+                            Label::new(span(schema_range))
+                                .with_message(format!(
+                                    "Another matcher already captured a value for '{}'",
+                                    id
+                                ))
+                                .with_color(Color::Red),
+                        )
+                        .with_help("Give each matcher its own id, or collect the values into an array.")
+                        .finish()
+                }
+                SchemaError::DuplicateScope { schema_index, path } => {
+                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
+                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
+
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
+                        .with_message("Duplicate scope")
+                        .with_label(
+                            Label::new(span(schema_range))
+                                .with_message(format!(
+                                    "An earlier heading already scopes its matches under '{}'",
+                                    path
+                                ))
+                                .with_color(Color::Red),
+                        )
+                        .with_help("Give each scoped heading its own scope name.")
+                        .finish()
+                }
+                SchemaError::MatcherSyntaxSuspicious {
+                    schema_index,
+                    hint,
+                    suggestion,
+                    ..
+                } => {
+                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
+                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
+
+                    Report::build(ReportKind::Warning, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
+                        .with_message("Code span looks like a matcher")
+                        .with_label(
+                            Label::new(span(schema_range))
+                                .with_message(format!(
+                                    "This is compared literally ({}), did you mean `{}`?",
+                                    hint, suggestion
+                                ))
+                                .with_color(Color::Yellow),
+                        )
+                        .with_help("Put a `!` after the code span if it's meant to be literal.")
+                        .finish()
+                }
+                SchemaError::InvalidSchemaVersion { declared, .. } => {
+                    Report::build(ReportKind::Error, span(0..0))
+                        .with_code(code)
+                        .with_config(config)
+                        .with_message(format!("Invalid schema version '{}'", declared))
+                        .with_help(
+                            "Declare the version as a positive whole number, like \
+                             `<!-- mds-version: 2 -->`, on the first line of the schema.",
+                        )
+                        .finish()
+                }
+                SchemaError::InvalidBackReference {
+                    schema_index,
+                    id,
+                    error,
+                } => {
+                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
+                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
+
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
+                        .with_message("Invalid back-reference")
+                        .with_label(
+                            Label::new(span(schema_range))
+                                .with_message(format!("Can't refer to '{}', since {}", id, error))
+                                .with_color(Color::Red),
+                        )
+                        .with_help(
+                            "A back-reference has to come after a matcher that captures a \
+                             single value under its id, like `title:/.+/`.",
+                        )
+                        .finish()
+                }
+                SchemaError::InvalidCondition {
+                    schema_index,
+                    id,
+                    error,
+                } => {
+                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
+                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
+
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
+                        .with_message("Invalid condition")
+                        .with_label(
+                            Label::new(span(schema_range))
+                                .with_message(format!("Can't depend on '{}', since {}", id, error))
+                                .with_color(Color::Red),
+                        )
+                        .with_help(
+                            "A condition has to come after a matcher that captures a single \
+                             value under its id, outside the block the condition is in.",
+                        )
+                        .finish()
+                }
+                SchemaError::InvalidForbiddenPattern {
+                    schema_index,
+                    pattern,
+                    error,
+                } => {
+                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
+                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
+
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
+                        .with_message("Invalid forbidden pattern")
+                        .with_label(
+                            Label::new(span(schema_range))
+                                .with_message(format!("Can't forbid '{}': {}", pattern, error))
+                                .with_color(Color::Red),
+                        )
+                        .with_help(
+                            "Each line of an `mds-forbid` block is a regex, like `TODO|FIXME`.",
+                        )
+                        .finish()
+                }
+                SchemaError::MatchPathConflict {
+                    schema_index,
+                    id,
+                    other_id,
+                } => {
+                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
+                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
+
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
+                        .with_message("Conflicting match paths")
+                        .with_label(
+                            Label::new(span(schema_range))
+                                .with_message(format!("'{}' conflicts with '{}'", id, other_id))
+                                .with_color(Color::Red),
+                        )
+                        .with_help(
+                            "Rename one of the matchers, or escape a dot that is part of a key \
+                             with a backslash, like `v1\\.2`.",
+                        )
+                        .finish()
+                }
+                SchemaError::UnknownCompareMode { schema_index, mode } => {
+                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
+                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
+
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
+                        .with_message("Unknown comparison mode")
+                        .with_label(
+                            Label::new(span(schema_range))
+                                .with_message(format!("Can't compare with '{}'", mode))
+                                .with_color(Color::Red),
+                        )
+                        .with_help(format!(
+                            "The comparison modes are {}.",
+                            COMPARE_MODES.join(", ")
+                        ))
+                        .finish()
+                }
+                SchemaError::InvalidInclude {
+                    schema_index,
+                    error,
+                } => {
+                    let schema_node = find_node_by_index(tree.root_node(), *schema_index);
+                    let schema_range = schema_node.start_byte()..schema_node.end_byte();
+
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
+                        .with_message("Invalid include")
+                        .with_label(
+                            Label::new(span(schema_range))
+                                .with_message(error.to_string())
+                                .with_color(Color::Red),
+                        )
+                        .with_help("Includes are resolved against the path of the schema file.")
+                        .finish()
+                }
                 SchemaError::InvalidMatcherExtras {
                     schema_index,
                     error,
@@ -871,10 +2727,12 @@ You can mark a list node as repeating by adding a '{<min_count>,<max_count>} dir
                     let schema_node = find_node_by_index(tree.root_node(), *schema_index);
                     let schema_range = schema_node.start_byte()..schema_node.end_byte();
 
-                    Report::build(ReportKind::Error, (filename, schema_range.clone()))
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
                         .with_message("Invalid matcher extras")
                         .with_label(
-                            Label::new((filename, schema_range))
+                            Label::new(span(schema_range))
                                 .with_message(format!("Invalid matcher extras: {}", error))
                                 .with_color(Color::Red),
                         )
@@ -884,10 +2742,12 @@ You can mark a list node as repeating by adding a '{<min_count>,<max_count>} dir
                     let schema_node = find_node_by_index(tree.root_node(), *schema_index);
                     let schema_range = schema_node.start_byte()..schema_node.end_byte();
 
-                    Report::build(ReportKind::Error, (filename, schema_range.clone()))
+                    Report::build(ReportKind::Error, span(schema_range.clone()))
+                        .with_code(code)
+                        .with_config(config)
                         .with_message("Unbounded repeating matcher must be last")
                         .with_label(
-                            Label::new((filename, schema_range))
+                            Label::new(span(schema_range))
                                 .with_message(
                                     "This unbounded repeating matcher is followed by other repeating matchers.",
                                )
@@ -906,10 +2766,12 @@ The first matcher has a specific upper bound (3), while the last one can be unbo
         }
         ValidationError::IoError(msg) => {
             let root_range = 0..source_content.len();
-            Report::build(ReportKind::Error, (filename, root_range.clone()))
+            Report::build(ReportKind::Error, span(root_range.clone()))
+                .with_code(code)
+                .with_config(config)
                 .with_message("IO error")
                 .with_label(
-                    Label::new((filename, root_range))
+                    Label::new(span(root_range))
                         .with_message(format!("IO error: {}", msg))
                         .with_color(Color::Red),
                 )
@@ -917,10 +2779,12 @@ The first matcher has a specific upper bound (3), while the last one can be unbo
         }
         ValidationError::ParserError(parser_err) => {
             let root_range = 0..source_content.len();
-            Report::build(ReportKind::Error, (filename, root_range.clone()))
+            Report::build(ReportKind::Error, span(root_range.clone()))
+                .with_code(code)
+                .with_config(config)
                 .with_message("Parser error")
                 .with_label(
-                    Label::new((filename, root_range))
+                    Label::new(span(root_range))
                         .with_message(format!("Parser error: {:?}", parser_err))
                         .with_color(Color::Red),
                 )
@@ -928,19 +2792,36 @@ The first matcher has a specific upper bound (3), while the last one can be unbo
         }
         ValidationError::ValidatorCreationFailed => {
             let root_range = 0..source_content.len();
-            Report::build(ReportKind::Error, (filename, root_range.clone()))
+            Report::build(ReportKind::Error, span(root_range.clone()))
+                .with_code(code)
+                .with_config(config)
                 .with_message("Validator creation failed")
                 .with_label(
-                    Label::new((filename, root_range))
+                    Label::new(span(root_range))
                         .with_message("Failed to create validator")
                         .with_color(Color::Red),
                 )
                 .finish()
         }
+        ValidationError::InternalInvariantViolated(message) => {
+            let root_range = 0..source_content.len();
+            Report::build(ReportKind::Error, span(root_range.clone()))
+                .with_code(code)
+                .with_config(config)
+                .with_message("Internal invariant violated")
+                .with_label(
+                    Label::new(span(root_range))
+                        .with_message(message)
+                        .with_color(Color::Red),
+                )
+                .with_note("This is a bug in mdvalidate, please report it")
+                .finish()
+        }
     };
 
+    let source = Source::from(format!("{}{}", "\n".repeat(line_offset), source_content));
     report
-        .write((filename, Source::from(source_content)), buffer)
+        .write((filename, source), buffer)
         .map_err(|e| PrettyPrintError::from(e.to_string()))?;
 
     Ok(())
@@ -959,6 +2840,7 @@ fn node_content_by_index<'a>(
 #[cfg(test)]
 mod tests {
     use crate::mdschema::validation::ts_utils::new_markdown_parser;
+    use crate::mdschema::validation::validator::ValidateOptions;
 
     use super::*;
 
@@ -972,4 +2854,549 @@ mod tests {
         let heading_content = node_content_by_index(root, 3, source);
         assert_eq!(heading_content.unwrap(), " Heading");
     }
+
+    #[test]
+    fn test_invariant_violation_adds_error_to_result() {
+        use crate::mdschema::validation::walkers::ValidationResult;
+
+        fn violate(schema_cursor: &TreeCursor, input_cursor: &TreeCursor) -> ValidationResult {
+            let mut result = ValidationResult::from_cursors(schema_cursor, input_cursor);
+            crate::invariant_violation!(result, schema_cursor, input_cursor, "broken {}", 1)
+        }
+
+        let source = "# Heading";
+        let tree = new_markdown_parser().parse(source, None).unwrap();
+        let cursor = tree.walk();
+
+        let result = violate(&cursor, &cursor);
+
+        assert_eq!(result.errors().len(), 1);
+        match &result.errors()[0] {
+            ValidationError::InternalInvariantViolated(message) => {
+                assert!(message.contains("broken 1"))
+            }
+            other => panic!("Unexpected error: {:?}", other),
+        }
+        assert_eq!(result.errors()[0].code(), "MDV204");
+    }
+
+    #[test]
+    fn test_pretty_print_error_tree_diff() {
+        let schema = "# Title\n\n- a\n- b\n";
+        let input = "# Title\n\n- a\n- c\n";
+
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        assert_eq!(errors.len(), 1);
+
+        let diff = pretty_print_error_tree_diff(&errors[0], &validator).unwrap();
+        assert!(diff.contains("Schema:"));
+        assert!(diff.contains("Input:"));
+        // One highlighted node on each side
+        assert_eq!(diff.matches(" <--").count(), 2);
+        assert!(diff.contains("\"c"));
+        // We only render the subtree around the error, not the whole document
+        assert!(!diff.contains("Title"));
+    }
+
+    #[test]
+    fn test_pretty_print_error_includes_code() {
+        let mut validator = Validator::new_complete("# Title", "# Titel").unwrap();
+        validator.validate();
+
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code(), "MDV005");
+
+        let pretty = pretty_print_error(&errors[0], &validator, "input.md").unwrap();
+        assert!(pretty.contains("MDV005"), "Expected the code in {}", pretty);
+    }
+
+    #[test]
+    fn test_pretty_print_error_context() {
+        let schema = "# Title\n\nBy `author:/[A-Z][a-z]+/`\n";
+        let input = "# Title\n\nBy wolf\n";
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        assert_eq!(errors.len(), 1);
+
+        let context =
+            pretty_print_error_context(&errors[0], &validator, "input.md", "schema.mds", false);
+        assert!(context.contains(" --> input.md:3:4\n"), "{}", context);
+        assert!(context.contains("3 | By wolf\n  |    ^^^^ found here\n"), "{}", context);
+        assert!(context.contains(" --> schema.mds:3:"), "{}", context);
+
+        assert_eq!(
+            pretty_print_error_context(
+                &ValidationError::ValidatorCreationFailed,
+                &validator,
+                "input.md",
+                "schema.mds",
+                false
+            ),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_pretty_print_error_after_dropping_input() {
+        let schema = "# Log\n\nfirst\n\nsecond\n\nthird\n\nfourth\n";
+        let input = "# Log\n\nfirst\n\nsecond\n\nthird\n\nfouth\n";
+
+        let options = ValidateOptions {
+            bounded_memory: true,
+            ..Default::default()
+        };
+
+        let mut validator = Validator::new_incomplete(schema, "")
+            .unwrap()
+            .with_options(options);
+        for line in input.split_inclusive('\n') {
+            validator.append_more_input(line).unwrap();
+            validator.validate();
+        }
+        validator.append_final_input("").unwrap();
+        validator.validate();
+
+        let mut expected = Validator::new_complete(schema, input).unwrap();
+        expected.validate();
+
+        assert!(validator.dropped_input().lines > 0);
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        let expected_errors: Vec<_> = expected.errors_so_far().cloned().collect();
+        assert_eq!(errors, expected_errors);
+        assert_eq!(errors.len(), 1);
+
+        let pretty = |validator: &Validator| {
+            pretty_print_error_with_color(&errors[0], validator, "input.md", false).unwrap()
+        };
+        let pretty_output = pretty(&validator);
+        assert_eq!(pretty_output, pretty(&expected));
+        assert!(pretty_output.contains("input.md:9:1"), "{}", pretty_output);
+
+        let context = |validator: &Validator| {
+            pretty_print_error_context(&errors[0], validator, "input.md", "schema.mds", false)
+        };
+        let context_output = context(&validator);
+        assert_eq!(context_output, context(&expected));
+        assert!(context_output.contains("9 | fouth\n"), "{}", context_output);
+    }
+
+    #[test]
+    fn test_pretty_print_error_without_color() {
+        let mut validator = Validator::new_complete("# Title", "# Titel").unwrap();
+        validator.validate();
+
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        assert_eq!(errors.len(), 1);
+
+        let plain =
+            pretty_print_error_with_color(&errors[0], &validator, "input.md", false).unwrap();
+        assert!(!plain.contains('\x1b'), "Expected no escape codes in {:?}", plain);
+        assert!(plain.contains("MDV005"));
+
+        let colored =
+            pretty_print_error_with_color(&errors[0], &validator, "input.md", true).unwrap();
+        assert!(colored.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_pretty_print_error_diffs_long_text() {
+        let schema = "The quick brown fox jumps over the lazy dog and runs away\n";
+        let input = "The quick brown cat jumps over the lazy dog and runs away\n";
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        assert_eq!(errors.len(), 1);
+
+        let pretty =
+            pretty_print_error_with_color(&errors[0], &validator, "input.md", false).unwrap();
+        assert!(
+            pretty.contains("differs at byte 16: The quick brown [-fox-]{+cat+} jumps over the..."),
+            "{}",
+            pretty
+        );
+        assert!(!pretty.contains("runs away'"), "{}", pretty);
+    }
+
+    #[test]
+    fn test_pretty_print_error_tree_diff_without_location() {
+        let validator = Validator::new_complete("# Title", "# Title").unwrap();
+
+        assert!(
+            pretty_print_error_tree_diff(&ValidationError::ValidatorCreationFailed, &validator)
+                .is_none()
+        );
+    }
+
+    /// Serialize a value to JSON and back.
+    fn round_trip<T>(value: &T) -> T
+    where
+        T: Serialize + serde::de::DeserializeOwned,
+    {
+        let json = serde_json::to_string(value).unwrap();
+        serde_json::from_str(&json).unwrap_or_else(|e| panic!("{}: {}", e, json))
+    }
+
+    /// An error of every kind, with the errors they wrap.
+    fn error_of_every_kind() -> Vec<ValidationError> {
+        let violations = vec![
+            SchemaViolationError::NodeTypeMismatch {
+                schema_index: 1,
+                input_index: 2,
+                expected: "heading".to_string(),
+                actual: "paragraph".to_string(),
+            },
+            SchemaViolationError::NodeContentMismatch {
+                schema_index: 1,
+                input_index: 2,
+                expected: "Title".to_string(),
+                actual: "title".to_string(),
+                kind: NodeContentMismatchKind::NormalizedLiteral(TextNormalizations {
+                    case_insensitive: false,
+                    collapse_whitespace: true,
+                    unicode_nfc: true,
+                }),
+            },
+            SchemaViolationError::EnumMismatch {
+                schema_index: 1,
+                input_index: 2,
+                allowed: vec!["draft".to_string(), "final".to_string()],
+                actual: "done".to_string(),
+            },
+            SchemaViolationError::CapturedValueTypeMismatch {
+                schema_index: 1,
+                input_index: 2,
+                expected: "int".to_string(),
+                actual: "4.2".to_string(),
+            },
+            SchemaViolationError::UnresolvedLinkReference {
+                schema_index: 1,
+                input_index: 2,
+                label: "missing".to_string(),
+            },
+            SchemaViolationError::BrokenAnchor {
+                schema_index: 1,
+                input_index: 2,
+                anchor: "usage".to_string(),
+            },
+            SchemaViolationError::MissingFrontmatter {
+                schema_index: 1,
+                input_index: 0,
+            },
+            SchemaViolationError::MissingFrontmatterKey {
+                schema_index: 1,
+                input_index: 2,
+                key: "title".to_string(),
+            },
+            SchemaViolationError::UnalignedBlocks {
+                schema_index: 1,
+                input_index: 2,
+                skipped_schema: 0,
+                skipped_input: 3,
+            },
+            SchemaViolationError::ListMarkerMismatch {
+                schema_index: 1,
+                input_index: 2,
+                expected: "2.".to_string(),
+                actual: "5.".to_string(),
+            },
+            SchemaViolationError::TrailingContent {
+                schema_index: 1,
+                input_index: 2,
+                extra_blocks: 4,
+            },
+            SchemaViolationError::SelectionNotFound {
+                schema_index: 0,
+                input_index: 0,
+                selection: "API > Authentication".to_string(),
+            },
+            SchemaViolationError::AmbiguousSelection {
+                schema_index: 0,
+                input_index: 2,
+                selection: "Usage".to_string(),
+                lines: vec![1, 5],
+            },
+            SchemaViolationError::BackReferenceMismatch {
+                schema_index: 1,
+                input_index: 2,
+                id: "title".to_string(),
+                capture_index: 3,
+                expected: "Hello".to_string(),
+                actual: "Goodbye".to_string(),
+            },
+            SchemaViolationError::MissingContent {
+                schema_index: 1,
+                input_index: 0,
+                expected: "# Title".to_string(),
+            },
+            SchemaViolationError::ConditionNotMet {
+                schema_index: 3,
+                input_index: 4,
+                id: "type".to_string(),
+                expected: "library".to_string(),
+                actual: Some("binary".to_string()),
+            },
+            SchemaViolationError::NestingTooDeep {
+                schema_index: 1,
+                input_index: 2,
+                max_depth: 128,
+            },
+            SchemaViolationError::ForbiddenContent {
+                schema_index: 0,
+                input_index: 2,
+                pattern: "TODO".to_string(),
+                actual: "TODO".to_string(),
+            },
+            SchemaViolationError::UnorderedItemsUnmatched {
+                schema_index: 1,
+                input_index: 2,
+                missing: vec!["apple".to_string()],
+                unexpected: vec!["kiwi".to_string()],
+            },
+            SchemaViolationError::NotEnoughNodesForRepeatingParagraph {
+                schema_index: 1,
+                input_index: 2,
+                expected: ChildrenLengthRange(2, 3),
+                actual: 1,
+            },
+            SchemaViolationError::NonRepeatingMatcherInListContext {
+                schema_index: 1,
+                input_index: 2,
+            },
+            SchemaViolationError::ChildrenLengthMismatch {
+                schema_index: 1,
+                input_index: 2,
+                expected: ChildrenLengthRange(1, usize::MAX),
+                actual: 0,
+            },
+            SchemaViolationError::NodeListTooDeep {
+                schema_index: 1,
+                input_index: 2,
+                max_depth: 3,
+            },
+            SchemaViolationError::WrongListCount {
+                schema_index: 1,
+                input_index: 2,
+                min: Some(1),
+                max: None,
+                actual: 0,
+            },
+            SchemaViolationError::WrongSectionCount {
+                schema_index: 1,
+                input_index: 2,
+                min: None,
+                max: Some(2),
+                actual: 3,
+            },
+            SchemaViolationError::WrongRowCount {
+                schema_index: 1,
+                input_index: 2,
+                min: Some(2),
+                max: Some(2),
+                actual: 1,
+            },
+            SchemaViolationError::MalformedNodeStructure {
+                schema_index: 1,
+                input_index: 2,
+                kind: MalformedStructureKind::HadExtraListItem,
+            },
+        ];
+        let schema_errors = vec![
+            SchemaError::MultipleMatchersInNodeChildren {
+                schema_index: 1,
+                received: 2,
+            },
+            SchemaError::RepeatingMatcherInTextContainer { schema_index: 1 },
+            SchemaError::InvalidMatcherExtras {
+                schema_index: 1,
+                error: MatcherExtrasError::ImpossibleRange { min: 3, max: 2 },
+            },
+            SchemaError::UnclosedMatcher { schema_index: 1 },
+            SchemaError::MatcherError {
+                error: MatcherError::MatcherInteriorRegexInvalid("unclosed group".to_string()),
+                schema_index: 1,
+            },
+            SchemaError::RepeatingMatcherUnbounded { schema_index: 1 },
+            SchemaError::UTF8Error { schema_index: 1 },
+            SchemaError::DuplicateMatcherId {
+                schema_index: 1,
+                id: "title".to_string(),
+            },
+            SchemaError::InvalidInclude {
+                schema_index: 1,
+                error: IncludeError::Cycle {
+                    chain: vec!["a.mds".into(), "b.mds".into(), "a.mds".into()],
+                },
+            },
+            SchemaError::DuplicateScope {
+                schema_index: 1,
+                path: "config".to_string(),
+            },
+            SchemaError::MatcherSyntaxSuspicious {
+                schema_index: 1,
+                text: "`name:/\\w+`".to_string(),
+                hint: "missing closing '/'".to_string(),
+                suggestion: "name:/\\w+/".to_string(),
+            },
+            SchemaError::InvalidSchemaVersion {
+                schema_index: 0,
+                declared: "two".to_string(),
+            },
+            SchemaError::InvalidBackReference {
+                schema_index: 1,
+                id: "title".to_string(),
+                error: BackReferenceError::BeforeCapture,
+            },
+            SchemaError::InvalidCondition {
+                schema_index: 1,
+                id: "type".to_string(),
+                error: BackReferenceError::UnknownId,
+            },
+            SchemaError::InvalidForbiddenPattern {
+                schema_index: 0,
+                pattern: "(".to_string(),
+                error: MatcherError::MatcherInteriorRegexInvalid("unclosed group".to_string()),
+            },
+            SchemaError::MatchPathConflict {
+                schema_index: 1,
+                id: "meta.author".to_string(),
+                other_id: "meta".to_string(),
+            },
+            SchemaError::UnknownCompareMode {
+                schema_index: 1,
+                mode: "nfd".to_string(),
+            },
+        ];
+
+        violations
+            .into_iter()
+            .map(ValidationError::SchemaViolation)
+            .chain(schema_errors.into_iter().map(ValidationError::SchemaError))
+            .chain([
+                ValidationError::IoError("No such file".to_string()),
+                ValidationError::ParserError(ParserError::ReadAfterEOF),
+                ValidationError::ValidatorCreationFailed,
+                ValidationError::InternalInvariantViolated("broken".to_string()),
+            ])
+            .collect()
+    }
+
+    #[test]
+    fn test_errors_round_trip_through_json() {
+        let errors = error_of_every_kind();
+        for kind in crate::mdschema::validation::severity::ERROR_KINDS {
+            assert!(
+                errors.iter().any(|error| error.kind() == *kind),
+                "Add a {} error to error_of_every_kind",
+                kind
+            );
+        }
+
+        for error in &errors {
+            assert_eq!(&round_trip(error), error);
+        }
+    }
+
+    #[test]
+    fn test_wrapped_errors_round_trip_through_json() {
+        let kinds = [
+            NodeContentMismatchKind::Suffix,
+            NodeContentMismatchKind::Matcher,
+            NodeContentMismatchKind::MatcherMessage("Use a date".to_string()),
+            NodeContentMismatchKind::NamedPattern,
+            NodeContentMismatchKind::NegatedMatcher,
+            NodeContentMismatchKind::Prefix,
+            NodeContentMismatchKind::Literal,
+            NodeContentMismatchKind::NormalizedLiteral(TextNormalizations::default()),
+            NodeContentMismatchKind::InfoString,
+            NodeContentMismatchKind::SimilarLiteral {
+                threshold: 900,
+                similarity: 818,
+            },
+        ];
+        for kind in &kinds {
+            assert_eq!(&round_trip(kind), kind);
+        }
+
+        let structures = [
+            MalformedStructureKind::MissingListItemContent,
+            MalformedStructureKind::HadExtraListItem,
+            MalformedStructureKind::MismatchingTableCells,
+            MalformedStructureKind::SchemaHasChildInputDoesnt,
+            MalformedStructureKind::InputHasChildSchemaDoesnt,
+        ];
+        for structure in &structures {
+            assert_eq!(&round_trip(structure), structure);
+        }
+
+        let matcher_errors = [
+            MatcherError::MatcherInteriorRegexInvalid("unclosed group".to_string()),
+            MatcherError::MatcherExtrasError(MatcherExtrasError::MatcherExtrasInvalid),
+            MatcherError::MatcherExtrasError(MatcherExtrasError::MixedLiteralAndOthers),
+            MatcherError::MatcherExtrasError(MatcherExtrasError::MixedOptionalAndRange),
+            MatcherError::WasLiteralCode,
+            MatcherError::InvariantViolation("not a code span".to_string()),
+            MatcherError::UnsupportedRegexSyntax("look-around".to_string()),
+            MatcherError::RegexTooComplex { limit: 1000 },
+            MatcherError::InputTooLong {
+                len: 10,
+                max_len: 5,
+            },
+            MatcherError::UnknownValueType("integer".to_string()),
+            MatcherError::UnknownNamedPattern {
+                name: "dat".to_string(),
+                suggestion: Some("date".to_string()),
+            },
+            MatcherError::UnknownRegexFlag('g'),
+        ];
+        for error in &matcher_errors {
+            assert_eq!(&round_trip(error), error);
+        }
+
+        let parser_errors = [
+            ParserError::ReadAfterEOF,
+            ParserError::ReadInputFailed("closed".to_string()),
+            ParserError::TreesitterError,
+            ParserError::ValidatorCreationFailed,
+            ParserError::PrettyPrintFailed("no node".to_string()),
+        ];
+        for error in &parser_errors {
+            assert_eq!(&round_trip(error), error);
+        }
+
+        let include_errors = [
+            IncludeError::NoSchemaPath {
+                path: "a.mds".into(),
+            },
+            IncludeError::ReadFailed {
+                path: "a.mds".into(),
+                error: "No such file".to_string(),
+            },
+            IncludeError::TooDeep {
+                path: "a.mds".into(),
+                max_depth: 8,
+            },
+        ];
+        for error in &include_errors {
+            assert_eq!(&round_trip(error), error);
+        }
+
+        let back_reference_errors = [
+            BackReferenceError::UnknownId,
+            BackReferenceError::BeforeCapture,
+            BackReferenceError::RepeatedCapture,
+        ];
+        for error in &back_reference_errors {
+            assert_eq!(&round_trip(error), error);
+        }
+
+        let error = PrettyPrintError::FailedToPrettyPrint("no node".to_string());
+        assert_eq!(round_trip(&error), error);
+    }
 }
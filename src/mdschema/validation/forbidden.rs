@@ -0,0 +1,251 @@
+//! Text that the input mustn't have anywhere.
+//!
+//! A fenced code block of a schema with the info string `mds-forbid` lists
+//! regexes, one per line, that no text of the input may match, wherever it
+//! is:
+//!
+//! ````md
+//! # `title:/.+/`
+//!
+//! ```mds-forbid
+//! TODO|FIXME
+//! lorem ipsum
+//! ```
+//! ````
+//!
+//! The block is stripped from the schema when it is loaded, so the input
+//! doesn't have to have it, and it is usually put at the end. Once the input
+//! is validated, every text node of the input is searched for the patterns,
+//! and every match is reported as a `SchemaViolationError::ForbiddenContent`.
+//! Unlike matchers, the patterns aren't anchored, so they match anywhere in
+//! the text. A pattern that isn't a valid regex is reported as a
+//! `SchemaError::InvalidForbiddenPattern`. Since the block is gone, both
+//! errors point at the top-level node of the schema right after where it was,
+//! or at the last one if the block was last.
+//!
+//! To forbid text at one place of the input only, use a negated matcher, like
+//! `` `!:/TODO/` `` (see `NEGATED_MATCHER_PREFIX`).
+use std::ops::Range;
+
+use regex::Regex;
+use tree_sitter::Tree;
+
+use crate::mdschema::validation::errors::{SchemaError, SchemaViolationError, ValidationError};
+use crate::mdschema::validation::matchers::matcher::{MatcherError, RegexLimits, build_regex};
+use crate::mdschema::validation::scopes::strip_annotations;
+use crate::mdschema::validation::ts_types::*;
+use crate::mdschema::validation::ts_utils::{CodeblockContents, get_node_text, waiting_at_end};
+
+/// The info string of a fenced code block of forbidden patterns.
+pub const FORBID_INFO_STRING: &str = "mds-forbid";
+
+/// The forbidden patterns of a schema.
+#[derive(Debug, Clone, Default)]
+pub struct ForbiddenPatterns {
+    /// The patterns that compiled, in order, with the block they are in.
+    patterns: Vec<(Regex, usize)>,
+    /// The patterns that didn't, with why not and the block they are in.
+    invalid: Vec<(String, MatcherError, usize)>,
+}
+
+impl ForbiddenPatterns {
+    /// Remove the forbid blocks from the top level of a schema.
+    ///
+    /// Returns the schema without them and their patterns, or `None` if it
+    /// has no forbid blocks. This has to happen before anything else is
+    /// found in the schema by where it starts, since those starts move.
+    pub fn strip(schema_tree: &Tree, schema_str: &str) -> Option<(String, Self)> {
+        let mut forbidden = Self::default();
+        let mut blocks: Vec<Range<usize>> = Vec::new();
+
+        // Which top-level node of the stripped schema comes right after the
+        // block we are at
+        let mut next_kept = 0;
+
+        let mut cursor = schema_tree.walk();
+        if !cursor.goto_first_child() {
+            return None;
+        }
+        loop {
+            if let Ok(Some(contents)) = CodeblockContents::try_from_cursor(&cursor, schema_str)
+                && contents
+                    .lang
+                    .as_ref()
+                    .is_some_and(|(lang, _)| lang.trim() == FORBID_INFO_STRING)
+            {
+                for pattern in contents.code.0.lines().map(str::trim) {
+                    if !pattern.is_empty() {
+                        forbidden.add(pattern, next_kept);
+                    }
+                }
+                blocks.push(cursor.node().byte_range());
+            } else {
+                next_kept += 1;
+            }
+
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+
+        if blocks.is_empty() {
+            return None;
+        }
+        Some((strip_annotations(schema_str, &blocks), forbidden))
+    }
+
+    fn add(&mut self, pattern: &str, block: usize) {
+        match build_regex(pattern, &RegexLimits::default()) {
+            Ok(regex) => self.patterns.push((regex, block)),
+            Err(error) => self.invalid.push((pattern.to_string(), error, block)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty() && self.invalid.is_empty()
+    }
+
+    /// The errors for patterns that aren't valid regexes.
+    pub fn errors(&self, schema_tree: &Tree) -> Vec<ValidationError> {
+        self.invalid
+            .iter()
+            .map(|(pattern, error, block)| {
+                ValidationError::SchemaError(SchemaError::InvalidForbiddenPattern {
+                    schema_index: block_schema_index(schema_tree, *block),
+                    pattern: pattern.clone(),
+                    error: error.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// An error for every match of a forbidden pattern in a text node of the
+    /// input.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema_tree`: The schema tree, without the forbid blocks.
+    /// * `input_tree`: The input tree.
+    /// * `input_str`: The text of the input tree.
+    /// * `got_eof`: Whether we have received the full input document.
+    pub fn find(
+        &self,
+        schema_tree: &Tree,
+        input_tree: &Tree,
+        input_str: &str,
+        got_eof: bool,
+    ) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        if self.patterns.is_empty() {
+            return errors;
+        }
+
+        let mut cursor = input_tree.walk();
+        loop {
+            // Text at the end can still grow into a match, or out of one
+            if is_text_node(&cursor.node()) && !waiting_at_end(got_eof, input_str, &cursor) {
+                let text = get_node_text(&cursor.node(), input_str);
+                for (pattern, block) in &self.patterns {
+                    for found in pattern.find_iter(text).filter(|found| !found.is_empty()) {
+                        errors.push(ValidationError::SchemaViolation(
+                            SchemaViolationError::ForbiddenContent {
+                                schema_index: block_schema_index(schema_tree, *block),
+                                input_index: cursor.descendant_index(),
+                                pattern: pattern.as_str().to_string(),
+                                actual: found.as_str().to_string(),
+                            },
+                        ));
+                    }
+                }
+            }
+
+            if cursor.goto_first_child() {
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return errors;
+                }
+            }
+        }
+    }
+}
+
+/// The descendant index of the top-level node of the schema that comes right
+/// after a stripped forbid block, or of the last one if the block was last.
+///
+/// # Arguments
+///
+/// * `schema_tree`: The schema tree, without the forbid blocks.
+/// * `block`: How many top-level nodes of the schema came before the block.
+fn block_schema_index(schema_tree: &Tree, block: usize) -> usize {
+    let mut cursor = schema_tree.walk();
+    if !cursor.goto_first_child() {
+        return 0;
+    }
+    for _ in 0..block {
+        if !cursor.goto_next_sibling() {
+            break;
+        }
+    }
+    cursor.descendant_index()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    #[test]
+    fn test_strip_forbid_blocks() {
+        let schema = "# `title:/.+/`\n\n```mds-forbid\nTODO|FIXME\n\n(\n```\n";
+        let tree = parse_markdown(schema).unwrap();
+
+        let (stripped, forbidden) = ForbiddenPatterns::strip(&tree, schema).unwrap();
+        assert_eq!(stripped.trim_end(), "# `title:/.+/`");
+        assert_eq!(forbidden.patterns.len(), 1);
+        // The block was last, so the error is at the heading before it
+        let stripped_tree = parse_markdown(&stripped).unwrap();
+        assert!(matches!(
+            forbidden.errors(&stripped_tree).as_slice(),
+            [ValidationError::SchemaError(SchemaError::InvalidForbiddenPattern {
+                schema_index: 1,
+                pattern,
+                ..
+            })] if pattern == "("
+        ));
+
+        // Other code blocks stay
+        let schema = "# Title\n\n```rust\nfn main() {}\n```\n";
+        let tree = parse_markdown(schema).unwrap();
+        assert!(ForbiddenPatterns::strip(&tree, schema).is_none());
+    }
+
+    #[test]
+    fn test_find_forbidden_content() {
+        let schema = "```mds-forbid\nTODO\n```\n\n# Title\n";
+        let tree = parse_markdown(schema).unwrap();
+        let (stripped, forbidden) = ForbiddenPatterns::strip(&tree, schema).unwrap();
+        let schema_tree = parse_markdown(&stripped).unwrap();
+
+        let input = "# TODO\n\nSome text, TODO and TODO\n\nDone\n";
+        let tree = parse_markdown(input).unwrap();
+        let found = forbidden.find(&schema_tree, &tree, input, true);
+        assert_eq!(found.len(), 3);
+        // The block was first, so the errors are at the heading after it
+        assert!(found.iter().all(|error| matches!(
+            error,
+            ValidationError::SchemaViolation(SchemaViolationError::ForbiddenContent {
+                schema_index: 1,
+                actual,
+                ..
+            }) if actual == "TODO"
+        )));
+
+        // The last paragraph may still change
+        let input = "# Title\n\nTODO";
+        let tree = parse_markdown(input).unwrap();
+        assert!(forbidden.find(&schema_tree, &tree, input, false).is_empty());
+        assert_eq!(forbidden.find(&schema_tree, &tree, input, true).len(), 1);
+    }
+}
@@ -0,0 +1,440 @@
+//! Validating the YAML front matter at the start of a document.
+//!
+//! A schema that starts with front matter requires the input to start with
+//! front matter too. Each `key: value` line of the schema's front matter
+//! requires the input's front matter to have that key, and the value can be
+//! a matcher that captures it:
+//!
+//! ```md
+//! ---
+//! title: `title:/.+/`
+//! draft: `draft:bool:/true|false/`?
+//! layout: post
+//! ---
+//! ```
+//!
+//! Keys with a literal value, like `layout: post`, must have exactly that
+//! value, and keys whose matcher is optional (`?`) may be left out.
+//!
+//! Depending on the grammar, front matter is parsed as a thematic break and
+//! a setext heading or as a node of its own, so we find it by the `---` lines
+//! that delimit it instead, and compare it line by line. The nodes it covers
+//! are skipped when comparing the rest of the trees.
+use std::ops::Range;
+
+use tree_sitter::{Node, Tree};
+
+use crate::invariant_violation;
+use crate::mdschema::validation::{
+    errors::{NodeContentMismatchKind, SchemaError, SchemaViolationError, ValidationError},
+    matchers::matcher::{Matcher, MatcherError, RegexLimits},
+    spans::{LineStarts, captured_value, subslice_offset},
+    ts_utils::descendant_index_spanning,
+    validator::ValidateOptions,
+    walkers::ValidationResult,
+};
+
+/// The line that opens and closes front matter.
+pub const FRONTMATTER_DELIMITER: &str = "---";
+
+/// A `key: value` line of front matter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrontmatterEntry<'a> {
+    pub key: &'a str,
+    /// The value, without surrounding whitespace or quotes.
+    pub value: &'a str,
+    /// Where the value starts in the document.
+    pub value_start: usize,
+    /// The byte range of the line, without its line ending.
+    pub line: Range<usize>,
+}
+
+/// The byte range of the front matter a document starts with, from the start
+/// of its opening `---` line to the end of its closing one.
+///
+/// Returns `None` if the document doesn't start with front matter, or if it
+/// hasn't been closed (yet).
+pub fn frontmatter_range(src: &str) -> Option<Range<usize>> {
+    let mut lines = line_ranges(src);
+    let opening = lines.next()?;
+    if !is_delimiter(&src[opening]) {
+        return None;
+    }
+
+    lines
+        .find(|line| is_delimiter(&src[line.clone()]))
+        .map(|closing| 0..closing.end)
+}
+
+/// Whether streamed input could still turn out to start with front matter
+/// that we don't have all of yet.
+pub fn awaiting_frontmatter(src: &str) -> bool {
+    if frontmatter_range(src).is_some() {
+        return false;
+    }
+    match src.split_once('\n') {
+        Some((opening, _)) => is_delimiter(opening),
+        None => FRONTMATTER_DELIMITER.starts_with(src.trim_end()),
+    }
+}
+
+/// Whether a node is part of a document's front matter.
+pub fn is_frontmatter_node(node: &Node, src: &str) -> bool {
+    src.starts_with(FRONTMATTER_DELIMITER)
+        && node.start_byte() < node.end_byte()
+        && frontmatter_range(src).is_some_and(|range| node.end_byte() <= range.end)
+}
+
+/// The `key: value` lines of front matter, in order.
+///
+/// Indented lines, which belong to the value of the key above them, comments
+/// and blank lines are left out.
+pub fn frontmatter_entries(src: &str, range: Range<usize>) -> Vec<FrontmatterEntry<'_>> {
+    line_ranges(src)
+        .skip(1)
+        .take_while(|line| line.end <= range.end && !is_delimiter(&src[line.clone()]))
+        .filter_map(|line| {
+            let text = src[line.clone()].trim_end_matches(['\n', '\r']);
+            if text.starts_with([' ', '\t', '#']) {
+                return None;
+            }
+
+            let (key, value) = text.split_once(':')?;
+            let key = key.trim();
+            let trimmed = value.trim();
+            let unquoted = unquote(trimmed);
+            // After the colon, the whitespace after it and the opening quote
+            let value_start = line.start + text.len() - value.trim_start().len()
+                + (trimmed.len() - unquoted.len()) / 2;
+            (!key.is_empty()).then(|| FrontmatterEntry {
+                key,
+                value: unquoted,
+                value_start,
+                line: line.start..line.start + text.len(),
+            })
+        })
+        .collect()
+}
+
+/// Validate the input's front matter against the schema's.
+///
+/// # Returns
+///
+/// `None` if the schema has no front matter. Otherwise the errors and matches
+/// of the front matter. An input without front matter gets a single
+/// `MissingFrontmatter` error, unless every key of the schema is optional.
+pub fn validate_frontmatter(
+    schema_tree: &Tree,
+    schema_str: &str,
+    input_tree: &Tree,
+    input_str: &str,
+    options: &ValidateOptions,
+    input_line_starts: Option<&LineStarts>,
+) -> Option<ValidationResult> {
+    let schema_range = frontmatter_range(schema_str)?;
+    let schema_entries = frontmatter_entries(schema_str, schema_range.clone());
+    let mut result = ValidationResult::from_descendant_indexes(0, 0);
+
+    let Some(input_range) = frontmatter_range(input_str) else {
+        if !schema_entries
+            .iter()
+            .all(|entry| is_optional_entry(entry, &options.regex_limits))
+        {
+            result.add_error(ValidationError::SchemaViolation(
+                SchemaViolationError::MissingFrontmatter {
+                    schema_index: descendant_index_spanning(schema_tree, schema_range),
+                    input_index: first_top_level_index(input_tree),
+                },
+            ));
+        }
+        return Some(result);
+    };
+    let input_entries = frontmatter_entries(input_str, input_range.clone());
+
+    for schema_entry in &schema_entries {
+        let schema_index = descendant_index_spanning(schema_tree, schema_entry.line.clone());
+        let matcher = match schema_value_matcher(schema_entry.value, &options.regex_limits) {
+            Some(Ok(matcher)) => Some(matcher),
+            Some(Err(error)) => {
+                result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                    error,
+                    schema_index,
+                }));
+                continue;
+            }
+            None => None,
+        };
+
+        let Some(input_entry) = input_entries
+            .iter()
+            .find(|input_entry| input_entry.key == schema_entry.key)
+        else {
+            if !matcher.as_ref().is_some_and(Matcher::is_optional) {
+                result.add_error(ValidationError::SchemaViolation(
+                    SchemaViolationError::MissingFrontmatterKey {
+                        schema_index,
+                        input_index: descendant_index_spanning(input_tree, input_range.clone()),
+                        key: schema_entry.key.to_string(),
+                    },
+                ));
+            }
+            continue;
+        };
+        let input_index = descendant_index_spanning(input_tree, input_entry.line.clone());
+
+        match matcher {
+            Some(matcher) => result.join_data(
+                compare_value_matcher(
+                    &matcher,
+                    input_entry,
+                    input_str,
+                    schema_index,
+                    input_index,
+                    options,
+                    input_line_starts,
+                )
+                .data(),
+            ),
+            None => {
+                let expected = literal_value(schema_entry.value);
+                if input_entry.value != expected {
+                    result.add_error(ValidationError::SchemaViolation(
+                        SchemaViolationError::NodeContentMismatch {
+                            schema_index,
+                            input_index,
+                            expected: expected.to_string(),
+                            actual: input_entry.value.to_string(),
+                            kind: NodeContentMismatchKind::Literal,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+
+    Some(result)
+}
+
+/// Match a front matter value against a matcher, capturing it if it matches.
+///
+/// Anchored matchers have to match the whole value.
+fn compare_value_matcher(
+    matcher: &Matcher,
+    input_entry: &FrontmatterEntry,
+    input_str: &str,
+    schema_index: usize,
+    input_index: usize,
+    options: &ValidateOptions,
+    input_line_starts: Option<&LineStarts>,
+) -> ValidationResult {
+    let mut result = ValidationResult::from_descendant_indexes(schema_index, input_index);
+    let value = input_entry.value;
+
+    match matcher.try_match_str(value, options.max_matcher_input_len) {
+        Ok(Some(matched_str)) if matcher.is_unanchored() || matched_str.len() == value.len() => {
+            let Some(offset) = subslice_offset(value, matched_str) else {
+                invariant_violation!(result, "a match should be a slice of the text it is in");
+            };
+            let start = input_entry.value_start + offset;
+            result.set_matcher_match(
+                matcher,
+                captured_value(input_str, matched_str, start, input_line_starts),
+                schema_index,
+                input_index,
+            );
+        }
+        Ok(_) => {
+            result.add_error(ValidationError::SchemaViolation(matcher.mismatch_error(
+                schema_index,
+                input_index,
+                value.into(),
+            )));
+        }
+        Err(error) => {
+            result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                error,
+                schema_index,
+            }));
+        }
+    }
+
+    result
+}
+
+/// The matcher a schema front matter value is, if it's a code span like
+/// `` `title:/.+/` ``, followed by its extras. Literal code like `` `post`! ``
+/// isn't a matcher. The matcher's regex is compiled within `limits`.
+fn schema_value_matcher(
+    value: &str,
+    limits: &RegexLimits,
+) -> Option<Result<Matcher, MatcherError>> {
+    let (pattern, extras) = split_code_span(value)?;
+    let extras = (!extras.is_empty()).then_some(extras);
+
+    match Matcher::try_from_pattern_and_suffix_str_with_limits(pattern, extras, limits) {
+        Err(MatcherError::WasLiteralCode) => None,
+        matcher => Some(matcher),
+    }
+}
+
+/// Whether a schema front matter key may be left out of the input, because
+/// its value is an optional matcher like `` `draft:/true|false/`? ``.
+fn is_optional_entry(entry: &FrontmatterEntry, limits: &RegexLimits) -> bool {
+    matches!(schema_value_matcher(entry.value, limits), Some(Ok(matcher)) if matcher.is_optional())
+}
+
+/// The value a schema front matter value that isn't a matcher requires.
+fn literal_value(value: &str) -> &str {
+    match split_code_span(value) {
+        Some((code, _)) => &code[1..code.len() - 1],
+        None => value,
+    }
+}
+
+/// Split a value that starts with a code span into the code span, backticks
+/// included, and what follows it.
+fn split_code_span(value: &str) -> Option<(&str, &str)> {
+    let rest = value.strip_prefix('`')?;
+    let close = rest.find('`')?;
+    Some((&value[..close + 2], value[close + 2..].trim()))
+}
+
+/// Remove the quotes around a quoted YAML scalar, like `"Hello"`.
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(value)
+}
+
+fn is_delimiter(line: &str) -> bool {
+    line.trim_end() == FRONTMATTER_DELIMITER
+}
+
+/// The byte ranges of the lines of a document, line endings included.
+fn line_ranges(src: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    src.split_inclusive('\n').scan(0, |start, line| {
+        let range = *start..*start + line.len();
+        *start = range.end;
+        Some(range)
+    })
+}
+
+/// The descendant index of the first top-level node of a document, or of the
+/// document if it's empty.
+fn first_top_level_index(tree: &Tree) -> usize {
+    let mut cursor = tree.walk();
+    cursor.goto_first_child();
+    cursor.descendant_index()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    const SCHEMA: &str =
+        "---\ntitle: `title:/.+/`\ndraft: `draft:/true|false/`?\nlayout: post\n---\n\n# Hi\n";
+
+    fn validate(schema_str: &str, input_str: &str) -> ValidationResult {
+        validate_frontmatter(
+            &parse_markdown(schema_str).unwrap(),
+            schema_str,
+            &parse_markdown(input_str).unwrap(),
+            input_str,
+            &ValidateOptions::default(),
+            None,
+        )
+        .expect("Expected the schema to have front matter")
+    }
+
+    #[test]
+    fn test_frontmatter_range() {
+        assert_eq!(frontmatter_range("---\na: b\n---\n# Hi\n"), Some(0..13));
+        assert_eq!(frontmatter_range("---\r\na: b\r\n---\r\n"), Some(0..16));
+        assert_eq!(frontmatter_range("---\na: b\n"), None);
+        assert_eq!(frontmatter_range("# Hi\n\n---\n"), None);
+    }
+
+    #[test]
+    fn test_awaiting_frontmatter() {
+        assert!(awaiting_frontmatter(""));
+        assert!(awaiting_frontmatter("--"));
+        assert!(awaiting_frontmatter("---\ntitle: Hi\n"));
+        assert!(!awaiting_frontmatter("---\ntitle: Hi\n---\n"));
+        assert!(!awaiting_frontmatter("# Hi\n"));
+    }
+
+    #[test]
+    fn test_frontmatter_entries() {
+        let src = "---\ntitle: \"Hello: World\"\n# comment\ntags:\n  - a\n---\n";
+        let entries = frontmatter_entries(src, frontmatter_range(src).unwrap());
+
+        let pairs: Vec<_> = entries.iter().map(|entry| (entry.key, entry.value)).collect();
+        assert_eq!(pairs, vec![("title", "Hello: World"), ("tags", "")]);
+        assert_eq!(&src[entries[0].line.clone()], "title: \"Hello: World\"");
+        assert!(src[entries[0].value_start..].starts_with("Hello: World\""));
+    }
+
+    #[test]
+    fn test_frontmatter_is_matched_and_captured() {
+        let result = validate(SCHEMA, "---\nlayout: post\ntitle: Hello\n---\n\n# Hi\n");
+
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+        assert_eq!(result.value(), &json!({"title": "Hello"}));
+    }
+
+    #[test]
+    fn test_frontmatter_mismatches() {
+        let result = validate(SCHEMA, "---\ntitle: Hello\ndraft: maybe\nlayout: page\n---\n");
+
+        assert_eq!(result.errors().len(), 2, "{:?}", result.errors());
+        assert!(matches!(
+            &result.errors()[0],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                kind: NodeContentMismatchKind::Matcher,
+                actual,
+                ..
+            }) if actual == "maybe"
+        ));
+        assert!(matches!(
+            &result.errors()[1],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                kind: NodeContentMismatchKind::Literal,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_missing_frontmatter_key() {
+        let result = validate(SCHEMA, "---\nlayout: post\n---\n");
+
+        // The optional draft key can be left out
+        assert_eq!(result.errors().len(), 1, "{:?}", result.errors());
+        assert!(matches!(
+            &result.errors()[0],
+            ValidationError::SchemaViolation(SchemaViolationError::MissingFrontmatterKey {
+                key,
+                ..
+            }) if key == "title"
+        ));
+    }
+
+    #[test]
+    fn test_missing_frontmatter_is_one_error() {
+        let result = validate(SCHEMA, "# Hi\n");
+
+        assert_eq!(result.errors().len(), 1, "{:?}", result.errors());
+        assert!(matches!(
+            result.errors()[0],
+            ValidationError::SchemaViolation(SchemaViolationError::MissingFrontmatter { .. })
+        ));
+
+        // Nothing is required, so nothing is missing
+        let result = validate("---\ndraft: `draft:/true|false/`?\n---\n", "# Hi\n");
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+    }
+}
@@ -0,0 +1,464 @@
+//! Sample documents generated from a schema.
+//!
+//! `generate_sample` writes out the document a schema describes, to show
+//! authors what it expects. Literal text is copied as it is, schema-only
+//! comments are left out, and each matcher is replaced by its id in angle
+//! brackets:
+//!
+//! ```md
+//! # <title>
+//!
+//! Version <version>
+//! ```
+//!
+//! With `fake`, matchers are replaced by a value they accept instead, like
+//! `2024-01-01` for `` `released:@date` `` or the first choice of an enum, so
+//! the sample validates against its schema. Regex matchers get a value built
+//! from their regex, and fall back to the placeholder when we can't build
+//! one the regex accepts.
+//!
+//! Repeated list items and sections, like `` - `item:/\w+/`{2,} ``, are
+//! written `min` times, or twice if they have no minimum.
+use regex_syntax::hir::{Class, ClassUnicode, Hir, HirKind};
+use tree_sitter::TreeCursor;
+
+use crate::mdschema::validation::conditions::SchemaConditions;
+use crate::mdschema::validation::forbidden::ForbiddenPatterns;
+use crate::mdschema::validation::matchers::matcher::{
+    Matcher, MatcherError, MatcherKind, RegexLimits, is_always_literal_code,
+};
+use crate::mdschema::validation::matchers::matcher_extras::partition_at_special_chars;
+use crate::mdschema::validation::preamble::split_preamble;
+use crate::mdschema::validation::scopes::SchemaScopes;
+use crate::mdschema::validation::ts_types::*;
+use crate::mdschema::validation::ts_utils::{
+    get_node_text, heading_level, is_directive_comment, parse_markdown,
+};
+use crate::mdschema::validation::walkers::helpers::curly_matchers::{
+    extract_id_from_curly_braces, extract_matcher_from_curly_delineated_text,
+};
+use crate::mdschema::validation::walkers::validators::rulers::ruler_matcher;
+use crate::mdschema::validation::walkers::validators::sections::repeated_section_matcher;
+
+/// How many times we write repeated items that have no minimum.
+const DEFAULT_REPEATS: usize = 2;
+
+/// The characters we prefer when a regex accepts one of several, so samples
+/// read like text.
+const PREFERRED_CHARS: &str = "aA0x. ";
+
+/// The fake value of matchers that accept anything.
+const FAKE_TEXT: &str = "example";
+
+/// Generate a sample document from a schema.
+///
+/// Returns `None` if the schema can't be parsed.
+///
+/// # Arguments
+///
+/// * `schema_str`: The schema.
+/// * `fake`: Whether to fill in matchers with values they accept, instead of
+///   placeholders like `<name>`.
+pub fn generate_sample(schema_str: &str, fake: bool) -> Option<String> {
+    let (_, schema_str) = split_preamble(schema_str);
+    let schema_tree = parse_markdown(schema_str)?;
+    let (schema_str, schema_tree) = match ForbiddenPatterns::strip(&schema_tree, schema_str) {
+        Some((stripped, _)) => {
+            let stripped_tree = parse_markdown(&stripped)?;
+            (stripped, stripped_tree)
+        }
+        None => (schema_str.to_string(), schema_tree),
+    };
+    let (schema_str, schema_tree) = match SchemaScopes::strip(&schema_tree, &schema_str) {
+        Some((stripped, _)) => {
+            let stripped_tree = parse_markdown(&stripped)?;
+            (stripped, stripped_tree)
+        }
+        None => (schema_str, schema_tree),
+    };
+    let (schema_str, schema_tree) = match SchemaConditions::strip(&schema_tree, &schema_str) {
+        Some((stripped, _)) => {
+            let stripped_tree = parse_markdown(&stripped)?;
+            (stripped, stripped_tree)
+        }
+        None => (schema_str, schema_tree),
+    };
+
+    let generator = SampleGenerator {
+        schema_str: &schema_str,
+        fake,
+    };
+    Some(generator.render_node(&schema_tree.walk()))
+}
+
+struct SampleGenerator<'a> {
+    schema_str: &'a str,
+    fake: bool,
+}
+
+impl SampleGenerator<'_> {
+    /// Render the node the cursor points at, with its children.
+    fn render_node(&self, cursor: &TreeCursor) -> String {
+        let node = cursor.node();
+        if let Some(rendered) = self.render_special(cursor) {
+            return rendered;
+        }
+
+        let mut rendered = String::new();
+        let mut pos = node.start_byte();
+        let mut child_cursor = cursor.clone();
+        if child_cursor.goto_first_child() {
+            loop {
+                if let Some(count) = self.section_repeats(&child_cursor) {
+                    let level = heading_level(&child_cursor.node()).unwrap_or(0);
+                    let section_start = child_cursor.node().start_byte().max(pos);
+                    rendered.push_str(&self.schema_str[pos..section_start]);
+
+                    let mut section = String::new();
+                    pos = section_start;
+                    loop {
+                        let (piece, end) = self.render_child(&child_cursor, pos);
+                        section.push_str(&piece);
+                        pos = end;
+
+                        let mut next_cursor = child_cursor.clone();
+                        if !next_cursor.goto_next_sibling()
+                            || heading_level(&next_cursor.node()).is_some_and(|next| next <= level)
+                        {
+                            break;
+                        }
+                        child_cursor = next_cursor;
+                    }
+                    rendered.push_str(&repeat_blocks(&section, count));
+                } else {
+                    let (piece, end) = self.render_child(&child_cursor, pos);
+                    rendered.push_str(&piece);
+                    pos = end;
+                }
+
+                if !child_cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+        }
+
+        if pos < node.end_byte() {
+            rendered.push_str(&self.schema_str[pos..node.end_byte()]);
+        }
+        rendered
+    }
+
+    /// Render a child node, with the text between where the previous child
+    /// ended and it. Returns the rendered text and where it ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `cursor`: The cursor pointing at the child.
+    /// * `pos`: The byte the previous child was rendered up to. This is past
+    ///   the start of a text node that begins with the extras of a matcher.
+    fn render_child(&self, cursor: &TreeCursor, pos: usize) -> (String, usize) {
+        let node = cursor.node();
+        if node.end_byte() <= pos {
+            return (String::new(), pos);
+        }
+        // The rest of a text node after the extras of the matcher before it
+        if node.start_byte() < pos {
+            return (
+                self.schema_str[pos..node.end_byte()].to_string(),
+                node.end_byte(),
+            );
+        }
+
+        let mut rendered = self.schema_str[pos..node.start_byte()].to_string();
+        if is_directive_comment(&node, self.schema_str) {
+            return (rendered, node.end_byte());
+        }
+        if let Some((sample, end)) = self.render_code_span(cursor) {
+            rendered.push_str(&sample);
+            return (rendered, end);
+        }
+
+        let block = self.render_node(cursor);
+        match self.item_repeats(cursor) {
+            Some(count) => {
+                // Copies after the first start at the column the item does
+                let line_start = self.schema_str[..node.start_byte()]
+                    .rfind('\n')
+                    .map_or(0, |newline| newline + 1);
+                let indent = &self.schema_str[line_start..node.start_byte()];
+                let indent = if indent.trim().is_empty() { indent } else { "" };
+                let separator = match block.ends_with('\n') {
+                    true => indent.to_string(),
+                    false => format!("\n{}", indent),
+                };
+                rendered.push_str(&vec![block; count].join(&separator));
+            }
+            None => rendered.push_str(&block),
+        }
+        (rendered, node.end_byte())
+    }
+
+    /// Render the nodes that stand for something other than their text: ruler
+    /// matchers and the info strings and bodies of code blocks.
+    fn render_special(&self, cursor: &TreeCursor) -> Option<String> {
+        let node = cursor.node();
+        let text = get_node_text(&node, self.schema_str);
+        let trailing = &text[text.trim_end().len()..];
+
+        if ruler_matcher(cursor, self.schema_str).is_some() {
+            return Some(format!("---{}", trailing));
+        }
+
+        match node.kind() {
+            "info_string" => {
+                let matcher = extract_matcher_from_curly_delineated_text(
+                    text.trim(),
+                    &RegexLimits::default(),
+                )?
+                .ok()?;
+                Some(format!("{}{}", self.sample(&matcher), trailing))
+            }
+            "code_fence_content" => {
+                let id = extract_id_from_curly_braces(text.trim())?;
+                match self.fake {
+                    true => Some(format!("{}{}", FAKE_TEXT, trailing)),
+                    false => Some(format!("<{}>{}", id, trailing)),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Render a code span that is a matcher, or literal code marked with `!`
+    /// or in double backticks.
+    ///
+    /// Returns the rendered text and the byte after the matcher's extras, or
+    /// `None` if the cursor isn't at a code span we render differently from
+    /// its text.
+    fn render_code_span(&self, cursor: &TreeCursor) -> Option<(String, usize)> {
+        let node = cursor.node();
+        if !is_inline_code_node(&node) {
+            return None;
+        }
+
+        // Everything before the text after the extras, including the
+        // backslash of an escape. Code in double backticks has no extras.
+        let extras_len = node
+            .next_sibling()
+            .filter(is_text_node)
+            .filter(|_| !is_always_literal_code(get_node_text(&node, self.schema_str)))
+            .map(|next| get_node_text(&next, self.schema_str))
+            .map_or(0, |text| {
+                partition_at_special_chars(text).map_or(0, |(_, after)| text.len() - after.len())
+            });
+        let end = node.end_byte() + extras_len;
+
+        match Matcher::try_from_schema_cursor(cursor, self.schema_str) {
+            Ok(matcher) => Some((self.sample(&matcher), end)),
+            Err(MatcherError::WasLiteralCode) => {
+                Some((get_node_text(&node, self.schema_str).to_string(), end))
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// How many times to write the section of a repeated heading, if the
+    /// cursor is at one.
+    fn section_repeats(&self, cursor: &TreeCursor) -> Option<usize> {
+        repeated_section_matcher(cursor, self.schema_str).map(|matcher| repeat_count(&matcher))
+    }
+
+    /// How many times to write a list item that starts with a repeated
+    /// matcher, if the cursor is at one.
+    fn item_repeats(&self, cursor: &TreeCursor) -> Option<usize> {
+        if !is_list_item_node(&cursor.node()) {
+            return None;
+        }
+
+        let mut cursor = cursor.clone();
+        cursor.goto_first_child();
+        while !is_paragraph_node(&cursor.node()) {
+            if !cursor.goto_next_sibling() {
+                return None;
+            }
+        }
+        if !cursor.goto_first_child() || !is_inline_code_node(&cursor.node()) {
+            return None;
+        }
+
+        Matcher::try_from_schema_cursor(&cursor, self.schema_str)
+            .ok()
+            .filter(Matcher::is_repeated)
+            .map(|matcher| repeat_count(&matcher))
+    }
+
+    /// What to write in place of a matcher.
+    fn sample(&self, matcher: &Matcher) -> String {
+        // A back-reference stands for the same value as the matcher it refers to
+        let id = matcher.id().or(matcher.back_reference());
+        let placeholder = format!("<{}>", id.unwrap_or("value"));
+        if !self.fake && id.is_some() {
+            return placeholder;
+        }
+
+        fake_value(matcher)
+            .filter(|value| {
+                !value.is_empty()
+                    && !value.contains('\n')
+                    && matcher.match_str(value) == Some(value.as_str())
+            })
+            .unwrap_or(placeholder)
+    }
+}
+
+/// How many times to write a repeated matcher's item.
+fn repeat_count(matcher: &Matcher) -> usize {
+    let extras = matcher.extras();
+    match extras.min_items() {
+        Some(min_items) if min_items > 0 => min_items,
+        _ => extras
+            .max_items()
+            .map_or(DEFAULT_REPEATS, |max_items| max_items.min(DEFAULT_REPEATS))
+            .max(1),
+    }
+}
+
+/// Write blocks one after another, with a blank line between them.
+fn repeat_blocks(block: &str, count: usize) -> String {
+    let separator = if block.ends_with("\n\n") {
+        ""
+    } else if block.ends_with('\n') {
+        "\n"
+    } else {
+        "\n\n"
+    };
+    vec![block; count].join(separator)
+}
+
+/// A value the matcher accepts, if we can make one up.
+fn fake_value(matcher: &Matcher) -> Option<String> {
+    // Anything its pattern doesn't match will do
+    if matcher.is_negated() {
+        return Some(FAKE_TEXT.to_string()).filter(|text| matcher.match_str(text).is_some());
+    }
+    match matcher.kind() {
+        MatcherKind::Regex { pattern, flags, .. } => {
+            regex_sample(&format!("{}{}", flags.inline_group(), pattern))
+        }
+        MatcherKind::Enum(allowed) => allowed.first().cloned(),
+        MatcherKind::Named { pattern, .. } => Some(pattern.example().to_string()),
+        MatcherKind::All | MatcherKind::Section => Some(FAKE_TEXT.to_string()),
+        // We don't know what the matcher it refers to was filled in with
+        MatcherKind::BackReference(_) => None,
+    }
+}
+
+/// A string the regex matches, built by taking the first choice of every
+/// alternation and repeating everything as few times as it may, but at least
+/// once.
+fn regex_sample(pattern: &str) -> Option<String> {
+    let hir = regex_syntax::parse(pattern).ok()?;
+    let mut sample = String::new();
+    push_hir_sample(&hir, &mut sample)?;
+    Some(sample)
+}
+
+fn push_hir_sample(hir: &Hir, sample: &mut String) -> Option<()> {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => {}
+        HirKind::Literal(literal) => sample.push_str(std::str::from_utf8(&literal.0).ok()?),
+        HirKind::Class(Class::Unicode(class)) => sample.push(class_sample(class)?),
+        HirKind::Class(Class::Bytes(class)) => {
+            let byte = class
+                .ranges()
+                .iter()
+                .flat_map(|range| range.start()..=range.end())
+                .find(u8::is_ascii_graphic)?;
+            sample.push(char::from(byte));
+        }
+        HirKind::Repetition(repetition) => {
+            let count = repetition
+                .min
+                .max(1)
+                .min(repetition.max.unwrap_or(u32::MAX));
+            for _ in 0..count {
+                push_hir_sample(&repetition.sub, sample)?;
+            }
+        }
+        HirKind::Capture(capture) => push_hir_sample(&capture.sub, sample)?,
+        HirKind::Concat(hirs) => {
+            for hir in hirs {
+                push_hir_sample(hir, sample)?;
+            }
+        }
+        HirKind::Alternation(hirs) => push_hir_sample(hirs.first()?, sample)?,
+    }
+    Some(())
+}
+
+/// A character of a class, preferring ones that read like text.
+fn class_sample(class: &ClassUnicode) -> Option<char> {
+    let ranges = class.ranges();
+    PREFERRED_CHARS
+        .chars()
+        .find(|c| {
+            ranges
+                .iter()
+                .any(|range| range.start() <= *c && *c <= range.end())
+        })
+        .or_else(|| {
+            ranges
+                .iter()
+                .flat_map(|range| range.start()..=range.end())
+                .find(|c| !c.is_control())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_placeholders() {
+        let schema =
+            "# `title:/.+/`\n\nVersion `version:@semver` and `code`!\n\n- `item:/\\w+/`{2,}\n";
+
+        assert_eq!(
+            generate_sample(schema, false).unwrap(),
+            "# <title>\n\nVersion <version> and `code`\n\n- <item>\n- <item>\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_fake_values() {
+        let schema = "# `title:/[A-Z][a-z]+/`\n\n`color:[red, green]` on `released:@date`\n";
+
+        assert_eq!(
+            generate_sample(schema, true).unwrap(),
+            "# Aa\n\nred on 2024-01-01\n"
+        );
+    }
+
+    #[test]
+    fn test_regex_sample() {
+        assert_eq!(regex_sample(r"\d{3}-\d{2}").as_deref(), Some("000-00"));
+        assert_eq!(regex_sample(r"(?:cat|dog)s?").as_deref(), Some("cats"));
+        assert_eq!(regex_sample(r"^\w+$").as_deref(), Some("a"));
+        assert_eq!(regex_sample(r"[^a-z]").as_deref(), Some("A"));
+    }
+
+    #[test]
+    fn test_repeat_count() {
+        let counts: Vec<usize> = ["{2,}", "{,}", "{,1}", "{3,3}"]
+            .into_iter()
+            .map(|extras| {
+                let matcher =
+                    Matcher::try_from_pattern_and_suffix_str("`item:/\\w+/`", Some(extras))
+                        .unwrap();
+                repeat_count(&matcher)
+            })
+            .collect();
+
+        assert_eq!(counts, vec![2, 2, 1, 3]);
+    }
+}
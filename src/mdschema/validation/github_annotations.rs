@@ -0,0 +1,165 @@
+//! GitHub Actions annotations.
+//!
+//! `ValidatorReport::to_github_annotations` renders errors as GitHub Actions
+//! workflow commands, one per line, which show up as annotations on the lines
+//! of a pull request that they point at:
+//!
+//! ```text
+//! ::error file=README.md,line=3,col=1,endLine=3,endColumn=11::[MDV005] Expected ...
+//! ```
+//!
+//! Errors a suppression comment turned into warnings are `::warning`s. An
+//! error about a node that spans several lines annotates the first of them,
+//! from where the node starts, with the whole message. Errors that don't point
+//! at a node of the input only name the file, which is the report's filename
+//! or `stdin` if it has none.
+use std::ops::Range;
+
+use crate::mdschema::validation::errors::ValidationError;
+use crate::mdschema::validation::json_report::{ValidatorReport, input_position};
+use crate::mdschema::validation::spans::TextPosition;
+use crate::mdschema::validation::validator::ValidatorState;
+
+/// The file annotations are in when the report has no filename.
+const STDIN_FILENAME: &str = "stdin";
+
+impl ValidatorReport<'_> {
+    /// Every error found so far, followed by the suppressed ones, as GitHub
+    /// Actions workflow commands, each ending with a newline.
+    pub fn to_github_annotations(&self) -> String {
+        let validator = self.validator;
+        let file = self.filename.as_deref().unwrap_or(STDIN_FILENAME);
+        validator
+            .errors_so_far()
+            .map(|error| (error, false))
+            .chain(
+                validator
+                    .suppressed_errors_so_far()
+                    .map(|error| (error, true)),
+            )
+            .map(|(error, suppressed)| {
+                let (_, input_index) = error.node_indexes();
+                let span = input_index.map(|index| {
+                    let range = validator.input_node(index).byte_range();
+                    let range = first_line(validator.last_input_str(), range);
+                    (
+                        input_position(validator, range.start),
+                        input_position(validator, range.end),
+                    )
+                });
+                annotation(error, suppressed, file, span) + "\n"
+            })
+            .collect()
+    }
+}
+
+/// A workflow command annotating the span of the input an error is in, if it
+/// is about a node of the input.
+///
+/// # Arguments
+///
+/// * `error`: The error to annotate.
+/// * `suppressed`: Whether a suppression comment turned the error into a warning.
+/// * `file`: The file the error is in.
+/// * `span`: Where the error starts and ends in the file.
+fn annotation(
+    error: &ValidationError,
+    suppressed: bool,
+    file: &str,
+    span: Option<(TextPosition, TextPosition)>,
+) -> String {
+    let command = if suppressed { "warning" } else { "error" };
+    let mut properties = format!("file={}", escape_property(file));
+    if let Some((start, end)) = span {
+        properties += &format!(
+            ",line={},col={},endLine={},endColumn={}",
+            start.line, start.col, end.line, end.col
+        );
+    }
+    let message = format!("[{}] {}", error.code(), error);
+
+    format!("::{} {}::{}", command, properties, escape_data(&message))
+}
+
+/// The part of a byte range of `src` on the line it starts on.
+fn first_line(src: &str, range: Range<usize>) -> Range<usize> {
+    let start = range.start.min(src.len());
+    let end = range.end.clamp(start, src.len());
+    match src[start..end].find('\n') {
+        Some(newline) => start..start + newline,
+        None => start..end,
+    }
+}
+
+/// Escape the message of a workflow command, which ends at a newline.
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property of a workflow command, which also ends at a comma, and
+/// at the colons that start the message.
+fn escape_property(property: &str) -> String {
+    escape_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::validator::Validator;
+
+    #[test]
+    fn test_annotations_of_errors_on_different_lines() {
+        let schema = "# Title\n\nSome text\n\nMore text\n";
+        let input = "# Title\n\nOther text\n\nLess text\n";
+
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+        let annotations = ValidatorReport::new(&validator)
+            .with_filename("docs/input.md")
+            .to_github_annotations();
+
+        assert_eq!(
+            annotations,
+            "::error file=docs/input.md,line=3,col=1,endLine=3,endColumn=11::\
+             [MDV005] Expected literal 'Some text', found 'Other text'\n\
+             ::error file=docs/input.md,line=5,col=1,endLine=5,endColumn=10::\
+             [MDV005] Expected literal 'More text', found 'Less text'\n"
+        );
+    }
+
+    #[test]
+    fn test_annotations_of_suppressed_errors_are_warnings() {
+        let schema = "# Title\n";
+        let input = "<!-- mdvalidate-disable-next-block -->\n# Titel\n";
+
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+        let annotations = ValidatorReport::new(&validator).to_github_annotations();
+
+        assert_eq!(annotations.lines().count(), 1);
+        assert!(
+            annotations.starts_with("::warning file=stdin,line=2,"),
+            "{}",
+            annotations
+        );
+    }
+
+    #[test]
+    fn test_multi_line_spans_annotate_their_first_line() {
+        let src = "# Title\n\nSome\ntext\n";
+
+        assert_eq!(first_line(src, 9..18), 9..13);
+        assert_eq!(first_line(src, 0..7), 0..7);
+        assert_eq!(first_line(src, 9..40), 9..13);
+    }
+
+    #[test]
+    fn test_workflow_commands_are_escaped() {
+        assert_eq!(escape_data("100%\nsure"), "100%25%0Asure");
+        assert_eq!(escape_property("a:b,c.md"), "a%3Ab%2Cc.md");
+    }
+}
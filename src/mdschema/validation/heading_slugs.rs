@@ -0,0 +1,145 @@
+//! GitHub-style heading anchors.
+//!
+//! GitHub gives every heading an anchor, so that `[Usage](#usage)` links to
+//! the `## Usage` heading. With `ValidateOptions::capture_heading_slugs` the
+//! headings of the input are reported under `__headings` in the matches:
+//!
+//! ```json
+//! {
+//!   "__headings": [
+//!     { "level": 1, "text": "Install", "slug": "install", "line": 1 },
+//!     { "level": 2, "text": "Usage", "slug": "usage", "line": 5 },
+//!     { "level": 2, "text": "Usage", "slug": "usage-1", "line": 9 }
+//!   ]
+//! }
+//! ```
+use std::collections::HashMap;
+
+use serde_json::{Value, json};
+use tree_sitter::TreeCursor;
+
+use crate::mdschema::validation::ts_types::*;
+use crate::mdschema::validation::ts_utils::{
+    ends_at_end, get_node_text, heading_level, walk_to_root,
+};
+
+/// The key headings are reported under in the matches.
+pub const HEADINGS_KEY: &str = "__headings";
+
+/// A heading of the input, with its anchor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadingSlug {
+    pub level: usize,
+    pub text: String,
+    pub slug: String,
+    /// The line the heading is on, starting at 1.
+    pub line: usize,
+}
+
+impl HeadingSlug {
+    pub fn to_value(&self) -> Value {
+        json!({
+            "level": self.level,
+            "text": self.text,
+            "slug": self.slug,
+            "line": self.line,
+        })
+    }
+}
+
+/// Turn heading text into an anchor the way GitHub does: lowercase it, drop
+/// punctuation and turn spaces into dashes.
+pub fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Collect every heading of the document the cursor is in, in order.
+///
+/// Like GitHub, repeated slugs get a `-1`, `-2`, ... suffix. Unless we have
+/// the whole document, a heading that runs to the end of it is left out since
+/// its text may still change.
+///
+/// # Arguments
+///
+/// * `cursor`: Any cursor into the document.
+/// * `src`: The full document (so far).
+/// * `got_eof`: Whether we have received the full input document.
+pub fn collect_heading_slugs(cursor: &TreeCursor, src: &str, got_eof: bool) -> Vec<HeadingSlug> {
+    let mut cursor = cursor.clone();
+    walk_to_root(&mut cursor);
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut headings = vec![];
+
+    let root = cursor.node();
+    for index in 1..root.descendant_count() {
+        cursor.goto_descendant(index);
+        let node = cursor.node();
+        let Some(level) = heading_level(&node) else {
+            continue;
+        };
+        if !got_eof && ends_at_end(&node, src) {
+            break;
+        }
+
+        let text = node
+            .children(&mut node.walk())
+            .find(is_heading_content_node)
+            .map_or("", |content| get_node_text(&content, src).trim());
+
+        let base_slug = slugify(text);
+        let count = seen.entry(base_slug.clone()).or_default();
+        let slug = match *count {
+            0 => base_slug,
+            n => format!("{}-{}", base_slug, n),
+        };
+        *count += 1;
+
+        headings.push(HeadingSlug {
+            level,
+            text: text.to_string(),
+            slug,
+            line: node.start_position().row + 1,
+        });
+    }
+
+    headings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Getting Started"), "getting-started");
+        assert_eq!(slugify("What's new in v2.0?"), "whats-new-in-v20");
+        assert_eq!(slugify("`mdv` --help"), "mdv---help");
+        assert_eq!(slugify("Über_uns"), "über_uns");
+    }
+
+    #[test]
+    fn test_collect_heading_slugs_dedupes() {
+        let src = "# Usage\n\nText\n\n## Usage\n\n## Usage\n";
+        let tree = parse_markdown(src).unwrap();
+
+        let headings = collect_heading_slugs(&tree.walk(), src, true);
+        let slugs: Vec<_> = headings.iter().map(|h| h.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["usage", "usage-1", "usage-2"]);
+        assert_eq!(headings[1].level, 2);
+        assert_eq!(headings[1].line, 5);
+
+        // The last heading may still grow
+        let headings = collect_heading_slugs(&tree.walk(), src, false);
+        assert_eq!(headings.len(), 2);
+    }
+}
@@ -0,0 +1,324 @@
+//! HTML error reports.
+//!
+//! `ValidatorReport::to_html` renders a validation as a standalone HTML page,
+//! for reviewing results outside a terminal, like in a pull request. It has a
+//! summary of the errors, the input with the node of each error highlighted,
+//! and a table of the errors that link to their highlights. Styles are inline,
+//! so the page needs nothing else to display.
+//!
+//! Where the nodes of errors overlap, the highlight that starts first (or,
+//! starting together, ends last) encloses the others. A highlight that runs
+//! past the end of one enclosing it is split in two, so the spans are always
+//! nested, and only its first part is the target of the error's link.
+use std::ops::Range;
+
+use serde_json::Value;
+
+use crate::mdschema::validation::errors::ValidationError;
+use crate::mdschema::validation::json_report::{ValidatorReport, expected_and_actual};
+use crate::mdschema::validation::spans::TextPosition;
+use crate::mdschema::validation::validator::ValidatorState;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; color: #1f2328; }
+.summary { font-weight: bold; }
+.summary.valid { color: #1a7f37; }
+.summary.invalid { color: #cf222e; }
+pre.source { max-height: 32em; overflow: auto; padding: 1em; background: #f6f8fa; border: 1px solid #d0d7de; }
+span.error { background: #ffebe9; border-bottom: 2px solid #cf222e; }
+span.warning { background: #fff8c5; border-bottom: 2px solid #9a6700; }
+span:target { outline: 2px solid #0969da; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #d0d7de; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+td.error { color: #cf222e; }
+td.warning { color: #9a6700; }
+";
+
+/// A range of the input to highlight for an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Highlight {
+    /// The position of the error in the report, which its anchor is named by.
+    index: usize,
+    /// The bytes of the input the error's node spans.
+    range: Range<usize>,
+    /// The class of the highlight, `error` or `warning`.
+    class: &'static str,
+}
+
+impl ValidatorReport<'_> {
+    /// Every error found so far, followed by the suppressed ones, as a
+    /// standalone HTML page with the input they are in.
+    pub fn to_html(&self) -> String {
+        let validator = self.validator;
+        let input_str = validator.last_input_str();
+        let errors: Vec<(&ValidationError, bool)> = validator
+            .errors_so_far()
+            .map(|error| (error, false))
+            .chain(
+                validator
+                    .suppressed_errors_so_far()
+                    .map(|error| (error, true)),
+            )
+            .collect();
+
+        let ranges: Vec<Option<Range<usize>>> = errors
+            .iter()
+            .map(|(error, _)| {
+                let (_, input_index) = error.node_indexes();
+                input_index.map(|index| {
+                    let range = validator.input_node(index).byte_range();
+                    range.start.min(input_str.len())..range.end.min(input_str.len())
+                })
+            })
+            .collect();
+        let highlights: Vec<Highlight> = errors
+            .iter()
+            .zip(&ranges)
+            .enumerate()
+            .filter_map(|(index, ((_, suppressed), range))| {
+                Some(Highlight {
+                    index,
+                    range: range.clone()?,
+                    class: severity(*suppressed),
+                })
+            })
+            .collect();
+
+        let error_count = errors.iter().filter(|(_, suppressed)| !suppressed).count();
+        let warning_count = errors.len() - error_count;
+        let title = match &self.filename {
+            Some(filename) => format!("Validation of {}", filename),
+            None => "Validation report".to_string(),
+        };
+
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str(&format!("<title>{}</title>\n", escape_html(&title)));
+        html.push_str(&format!("<style>\n{}</style>\n", STYLE));
+        html.push_str("</head>\n<body>\n");
+        html.push_str(&format!("<h1>{}</h1>\n", escape_html(&title)));
+        html.push_str(&format!(
+            "<p class=\"summary {}\">{}: {} error(s), {} warning(s)</p>\n",
+            if error_count == 0 { "valid" } else { "invalid" },
+            if error_count == 0 { "Valid" } else { "Invalid" },
+            error_count,
+            warning_count
+        ));
+
+        html.push_str("<pre class=\"source\">");
+        html.push_str(&highlight_source(input_str, &highlights));
+        html.push_str("</pre>\n");
+
+        if !errors.is_empty() {
+            html.push_str("<table>\n<tr><th>Severity</th><th>Code</th><th>Location</th>");
+            html.push_str("<th>Message</th><th>Expected</th><th>Actual</th></tr>\n");
+            for (index, ((error, suppressed), range)) in errors.iter().zip(&ranges).enumerate() {
+                let location = match range {
+                    Some(range) => {
+                        let start = TextPosition::from_byte(input_str, range.start);
+                        format!(
+                            "<a href=\"#error-{}\">{}:{}</a>",
+                            index,
+                            validator.dropped_input().lines + start.line,
+                            start.col
+                        )
+                    }
+                    None => String::new(),
+                };
+                let (expected, actual) = match error {
+                    ValidationError::SchemaViolation(violation) => expected_and_actual(violation)
+                        .map_or((String::new(), String::new()), |(expected, actual)| {
+                            (value_text(&expected), value_text(&actual))
+                        }),
+                    _ => (String::new(), String::new()),
+                };
+
+                html.push_str(&format!(
+                    "<tr><td class=\"{severity}\">{severity}</td><td>{}</td><td>{}</td>\
+                     <td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    error.code(),
+                    location,
+                    escape_html(&error.to_string()),
+                    escape_html(&expected),
+                    escape_html(&actual),
+                    severity = severity(*suppressed),
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+/// The severity an error is reported with.
+fn severity(suppressed: bool) -> &'static str {
+    if suppressed { "warning" } else { "error" }
+}
+
+/// The text of an expected or actual value, without quotes if it's a string.
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Render the source with each highlight in a span, nesting them as the
+/// module docs describe.
+fn highlight_source(src: &str, highlights: &[Highlight]) -> String {
+    let mut ordered: Vec<&Highlight> = highlights.iter().collect();
+    ordered.sort_by_key(|highlight| {
+        (
+            highlight.range.start,
+            std::cmp::Reverse(highlight.range.end),
+            highlight.index,
+        )
+    });
+
+    let mut boundaries: Vec<usize> = ordered
+        .iter()
+        .flat_map(|highlight| [highlight.range.start, highlight.range.end])
+        .chain([0, src.len()])
+        .collect();
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    let mut html = String::new();
+    let mut open: Vec<usize> = Vec::new();
+    let mut anchored = vec![false; ordered.len()];
+    for (i, &pos) in boundaries.iter().enumerate() {
+        let active: Vec<usize> = (0..ordered.len())
+            .filter(|&k| ordered[k].range.contains(&pos))
+            .collect();
+
+        // Close what ends here, and what was opened inside it
+        let kept = open
+            .iter()
+            .zip(&active)
+            .take_while(|(open, active)| open == active)
+            .count();
+        for _ in kept..open.len() {
+            html.push_str("</span>");
+        }
+        open.truncate(kept);
+
+        for k in 0..ordered.len() {
+            if ordered[k].range.is_empty() && ordered[k].range.start == pos {
+                html.push_str(&open_tag(ordered[k], &mut anchored[k]));
+                html.push_str("</span>");
+            }
+        }
+        for &k in &active[kept..] {
+            html.push_str(&open_tag(ordered[k], &mut anchored[k]));
+            open.push(k);
+        }
+
+        if let Some(&next) = boundaries.get(i + 1) {
+            html.push_str(&escape_html(&src[pos..next]));
+        }
+    }
+
+    html
+}
+
+/// The opening tag of a highlight's span. Only its first span is the target
+/// of the error's link.
+fn open_tag(highlight: &Highlight, anchored: &mut bool) -> String {
+    if *anchored {
+        return format!("<span class=\"{}\">", highlight.class);
+    }
+    *anchored = true;
+    format!(
+        "<span class=\"{}\" id=\"error-{}\">",
+        highlight.class, highlight.index
+    )
+}
+
+/// Escape text to put it in HTML, in an element or an attribute.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::validator::Validator;
+
+    fn highlight(index: usize, range: Range<usize>) -> Highlight {
+        Highlight {
+            index,
+            range,
+            class: "error",
+        }
+    }
+
+    #[test]
+    fn test_highlights_nest() {
+        let highlights = [
+            highlight(0, 0..4),
+            highlight(1, 2..6),
+            highlight(2, 1..2),
+            highlight(3, 3..3),
+        ];
+
+        assert_eq!(
+            highlight_source("abcdef", &highlights),
+            "<span class=\"error\" id=\"error-0\">a\
+             <span class=\"error\" id=\"error-2\">b</span>\
+             <span class=\"error\" id=\"error-1\">c\
+             <span class=\"error\" id=\"error-3\"></span>d</span></span>\
+             <span class=\"error\">ef</span>"
+        );
+    }
+
+    #[test]
+    fn test_highlights_escape_source() {
+        assert_eq!(
+            highlight_source("a<b>&c", &[highlight(0, 1..4)]),
+            "a<span class=\"error\" id=\"error-0\">&lt;b&gt;</span>&amp;c"
+        );
+    }
+
+    #[test]
+    fn test_html_report_escapes_scripts() {
+        let schema = "# Title\n\nSome text\n";
+        let input = "# Title\n\nSome <script>alert('hi')</script>\n";
+
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+        let html = ValidatorReport::new(&validator)
+            .with_filename("<doc>.md")
+            .to_html();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;alert(&#39;hi&#39;)&lt;/script&gt;"));
+        assert!(html.contains("<title>Validation of &lt;doc&gt;.md</title>"));
+        assert!(html.contains("<p class=\"summary invalid\">Invalid: "));
+        assert!(html.contains("id=\"error-0\""));
+        assert!(html.contains("<a href=\"#error-0\">3:"));
+    }
+
+    #[test]
+    fn test_html_report_for_valid_input() {
+        let mut validator = Validator::new_complete("# Title\n", "# Title\n").unwrap();
+        validator.validate();
+        let html = ValidatorReport::new(&validator).to_html();
+
+        assert!(html.contains("<p class=\"summary valid\">Valid: 0 error(s), 0 warning(s)</p>"));
+        assert!(!html.contains("<table>"));
+    }
+}
@@ -0,0 +1,310 @@
+//! Splicing other schema files into a schema.
+//!
+//! A paragraph that is nothing but an include directive, like
+//!
+//! ```md
+//! `include:./person.mds`
+//! ```
+//!
+//! is replaced by the contents of that file when the schema is loaded, before
+//! any input is validated. Relative paths are resolved against the directory
+//! of the schema doing the including, and included schemas can include more
+//! schemas in turn, up to `MAX_INCLUDE_DEPTH` deep.
+//!
+//! Resolving includes needs to know where the schema is, so it happens in
+//! `resolve_includes` rather than in `Validator`. A `Validator` made straight
+//! from a schema string reports any includes left in it as
+//! `SchemaError::InvalidInclude`.
+//!
+//! Include directives inside list items or block quotes, or with other text
+//! around them, aren't spliced and are compared literally like any other code
+//! span.
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::Tree;
+
+use crate::mdschema::validation::errors::SchemaError;
+use crate::mdschema::validation::ts_types::*;
+use crate::mdschema::validation::ts_utils::{get_node_text, parse_markdown, visit_code_spans};
+use crate::mdschema::validation::validate::strip_bom;
+
+/// What an include directive starts with, like `` `include:./person.mds` ``.
+pub const INCLUDE_PREFIX: &str = "include:";
+
+/// How deeply includes may nest, counting the including schema's own
+/// includes as the first level.
+pub const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Why an include directive couldn't be resolved.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum IncludeError {
+    /// The schema came from a string rather than a file, so there's nothing
+    /// to resolve the path against.
+    NoSchemaPath { path: PathBuf },
+    /// The included file couldn't be read.
+    ReadFailed { path: PathBuf, error: String },
+    /// The included file is already being included, so including it again
+    /// would never end. The chain starts with the outermost schema and ends
+    /// with the file that was included again.
+    Cycle { chain: Vec<PathBuf> },
+    /// Including the file would nest includes more than `max_depth` deep.
+    TooDeep { path: PathBuf, max_depth: usize },
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IncludeError::NoSchemaPath { path } => write!(
+                f,
+                "Can't include '{}' in a schema that wasn't loaded from a file",
+                path.display()
+            ),
+            IncludeError::ReadFailed { path, error } => write!(
+                f,
+                "Failed to read included schema '{}': {}",
+                path.display(),
+                error
+            ),
+            IncludeError::Cycle { chain } => {
+                let chain: Vec<String> =
+                    chain.iter().map(|path| path.display().to_string()).collect();
+                write!(f, "Include cycle: {}", chain.join(" -> "))
+            }
+            IncludeError::TooDeep { path, max_depth } => write!(
+                f,
+                "Including '{}' nests includes more than {} deep",
+                path.display(),
+                max_depth
+            ),
+        }
+    }
+}
+
+/// An include directive in a schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Include {
+    /// The descendant index of the directive's code span.
+    pub schema_index: usize,
+    /// The byte range of the code span, backticks included.
+    pub range: Range<usize>,
+    /// The path to include, as written.
+    pub path: PathBuf,
+}
+
+/// The path of an include directive, if a code span's text is one.
+pub fn include_path(code_span_text: &str) -> Option<&str> {
+    code_span_text
+        .trim_matches('`')
+        .trim()
+        .strip_prefix(INCLUDE_PREFIX)
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+}
+
+/// The include directives of a schema that get spliced, in order.
+pub fn find_includes(schema_tree: &Tree, schema_str: &str) -> Vec<Include> {
+    let mut includes = Vec::new();
+    visit_code_spans(schema_tree, |cursor| {
+        let node = cursor.node();
+        let text = get_node_text(&node, schema_str);
+        let Some(path) = include_path(text) else {
+            return;
+        };
+
+        let Some(paragraph) = node.parent().filter(is_paragraph_node) else {
+            return;
+        };
+        let alone = get_node_text(&paragraph, schema_str).trim() == text;
+        let nested = std::iter::successors(paragraph.parent(), |node| node.parent())
+            .any(|ancestor| is_list_item_node(&ancestor) || is_quote_node(&ancestor));
+        if alone && !nested {
+            includes.push(Include {
+                schema_index: cursor.descendant_index(),
+                range: node.start_byte()..node.end_byte(),
+                path: PathBuf::from(path),
+            });
+        }
+    });
+    includes
+}
+
+/// Splice the files that a schema includes into it, recursively.
+///
+/// # Arguments
+///
+/// * `schema_str`: The schema.
+/// * `schema_path`: Where the schema was loaded from. Relative include paths
+///   are resolved against its directory.
+///
+/// # Returns
+///
+/// The schema with every include directive replaced by the contents of the
+/// file it names, or a `SchemaError::InvalidInclude` pointing at the
+/// directive in `schema_str` that couldn't be resolved. The error is about
+/// that directive even when the problem is in a file it includes, since
+/// that's the only schema we have a tree for.
+pub fn resolve_includes(schema_str: &str, schema_path: &Path) -> Result<String, SchemaError> {
+    let schema_path = schema_path
+        .canonicalize()
+        .unwrap_or_else(|_| schema_path.to_path_buf());
+    let mut chain = vec![schema_path];
+
+    splice_includes(schema_str, &mut chain)
+        .map_err(|(schema_index, error)| SchemaError::InvalidInclude { schema_index, error })
+}
+
+/// Splice the includes of the schema at the end of `chain`.
+///
+/// # Returns
+///
+/// The spliced schema, or the descendant index of the directive that failed
+/// and why.
+fn splice_includes(
+    schema_str: &str,
+    chain: &mut Vec<PathBuf>,
+) -> Result<String, (usize, IncludeError)> {
+    // The validator reports schemas that don't parse
+    let Some(tree) = parse_markdown(schema_str) else {
+        return Ok(schema_str.to_string());
+    };
+    let base_dir = chain
+        .last()
+        .and_then(|path| path.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let mut spliced = String::with_capacity(schema_str.len());
+    let mut copied_up_to = 0;
+    for include in find_includes(&tree, schema_str) {
+        let included = load_include(&base_dir.join(&include.path), chain)
+            .map_err(|error| (include.schema_index, error))?;
+
+        spliced.push_str(&schema_str[copied_up_to..include.range.start]);
+        spliced.push_str(included.trim_end());
+        copied_up_to = include.range.end;
+    }
+    spliced.push_str(&schema_str[copied_up_to..]);
+
+    Ok(spliced)
+}
+
+/// Read an included schema and splice its own includes.
+fn load_include(path: &Path, chain: &mut Vec<PathBuf>) -> Result<String, IncludeError> {
+    let read_failed = |error: std::io::Error| IncludeError::ReadFailed {
+        path: path.to_path_buf(),
+        error: error.to_string(),
+    };
+
+    let canonical = path.canonicalize().map_err(read_failed)?;
+    if chain.contains(&canonical) {
+        let mut chain = chain.clone();
+        chain.push(canonical);
+        return Err(IncludeError::Cycle { chain });
+    }
+    if chain.len() > MAX_INCLUDE_DEPTH {
+        return Err(IncludeError::TooDeep {
+            path: path.to_path_buf(),
+            max_depth: MAX_INCLUDE_DEPTH,
+        });
+    }
+
+    let contents = std::fs::read_to_string(&canonical).map_err(read_failed)?;
+    chain.push(canonical);
+    let spliced = splice_includes(strip_bom(&contents), chain).map_err(|(_, error)| error);
+    chain.pop();
+
+    spliced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory to write schemas into.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("mdv-includes-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_include_path() {
+        assert_eq!(include_path("`include:./person.mds`"), Some("./person.mds"));
+        assert_eq!(include_path("`include: person.mds `"), Some("person.mds"));
+        assert_eq!(include_path("`include:`"), None);
+        assert_eq!(include_path("`name:/\\w+/`"), None);
+    }
+
+    #[test]
+    fn test_find_includes_only_finds_standalone_directives() {
+        let schema = "# Title\n\n`include:a.mds`\n\nSee `include:b.mds`\n\n- `include:c.mds`\n";
+        let tree = parse_markdown(schema).unwrap();
+
+        let includes = find_includes(&tree, schema);
+        assert_eq!(includes.len(), 1);
+        assert_eq!(includes[0].path, PathBuf::from("a.mds"));
+        assert_eq!(&schema[includes[0].range.clone()], "`include:a.mds`");
+    }
+
+    #[test]
+    fn test_resolve_nested_includes() {
+        let dir = temp_dir("nested");
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        std::fs::write(dir.join("main.mds"), "").unwrap();
+        std::fs::write(
+            dir.join("parts/person.mds"),
+            "## `name:/\\w+/`\n\n`include:./age.mds`\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("parts/age.mds"), "Age: `age:/\\d+/`\n").unwrap();
+
+        let schema = "# People\n\n`include:parts/person.mds`\n\nThe end\n";
+        let resolved = resolve_includes(schema, &dir.join("main.mds")).unwrap();
+        assert_eq!(
+            resolved,
+            "# People\n\n## `name:/\\w+/`\n\nAge: `age:/\\d+/`\n\nThe end\n"
+        );
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let dir = temp_dir("cycle");
+        std::fs::write(dir.join("a.mds"), "`include:b.mds`\n").unwrap();
+        std::fs::write(dir.join("b.mds"), "`include:a.mds`\n").unwrap();
+
+        let error = resolve_includes("`include:a.mds`\n", &dir.join("main.mds")).unwrap_err();
+        let SchemaError::InvalidInclude {
+            error: IncludeError::Cycle { chain },
+            ..
+        } = error
+        else {
+            panic!("Expected an include cycle, got {:?}", error);
+        };
+        let names: Vec<_> = chain
+            .iter()
+            .filter_map(|path| path.file_name()?.to_str())
+            .collect();
+        assert_eq!(names, vec!["main.mds", "a.mds", "b.mds", "a.mds"]);
+    }
+
+    #[test]
+    fn test_missing_include() {
+        let dir = temp_dir("missing");
+
+        let error = resolve_includes("# Hi\n\n`include:nope.mds`\n", &dir.join("main.mds"))
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            SchemaError::InvalidInclude {
+                error: IncludeError::ReadFailed { .. },
+                ..
+            }
+        ));
+    }
+}
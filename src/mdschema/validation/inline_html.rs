@@ -0,0 +1,179 @@
+//! HTML in the input.
+//!
+//! Generated markdown often has a bit of HTML in it, like a `<br>` to break a
+//! line or `<sup>1</sup>` for a footnote, that a schema can't be expected to
+//! predict. With `ValidateOptions::ignore_inline_html` (`--ignore-inline-html`
+//! on the command line), inline HTML in the input that the schema doesn't
+//! have is skipped. The text on either side of it is matched as if the HTML
+//! wasn't there, so with
+//!
+//! ```md
+//! Written by `author:/[\w ]+/`
+//! ```
+//!
+//! the input `Written by <b>Ada</b> Lovelace` captures `Ada Lovelace`, and the
+//! span of the capture points at where it is in the input, tags and all.
+//! Likewise `ValidateOptions::ignore_html_blocks` skips HTML blocks, like a
+//! `<div>` on lines of its own.
+//!
+//! Inline HTML the schema does have is required either way, and compared as
+//! text with the HTML in the input at the same place. So are HTML blocks, as
+//! long as we aren't skipping them.
+use std::borrow::Cow;
+use std::ops::Range;
+
+use tree_sitter::Node;
+
+use crate::mdschema::validation::ts_types::*;
+
+/// The text of a range of a textual container with its inline HTML left out,
+/// that remembers where its bytes came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WithoutInlineHtml<'a> {
+    text: Cow<'a, str>,
+    /// Where each run of text between HTML starts, in this and the original
+    /// text.
+    run_starts: Vec<(usize, usize)>,
+}
+
+impl<'a> WithoutInlineHtml<'a> {
+    /// # Arguments
+    ///
+    /// * `container`: The textual container, like a paragraph.
+    /// * `range`: The bytes of the container to take the text of.
+    /// * `src`: The full document the container is in.
+    pub fn new(container: &Node, range: Range<usize>, src: &'a str) -> Self {
+        let mut text = String::new();
+        let mut run_starts = vec![(0, range.start)];
+        let mut run_start = range.start;
+        for child in container.children(&mut container.walk()) {
+            if !is_inline_html_node(&child)
+                || child.start_byte() < range.start
+                || child.end_byte() > range.end
+            {
+                continue;
+            }
+            text.push_str(&src[run_start..child.start_byte()]);
+            run_start = child.end_byte();
+            run_starts.push((text.len(), run_start));
+        }
+
+        if run_starts.len() == 1 {
+            return Self {
+                text: Cow::Borrowed(&src[range]),
+                run_starts,
+            };
+        }
+
+        // Like the text of any container, it doesn't end in whitespace
+        text.push_str(&src[run_start..range.end]);
+        text.truncate(text.trim_end().len());
+        Self {
+            text: Cow::Owned(text),
+            run_starts,
+        }
+    }
+
+    /// Just the text of a range, for when we don't skip inline HTML.
+    pub fn with_html(range: Range<usize>, src: &'a str) -> Self {
+        Self {
+            run_starts: vec![(0, range.start)],
+            text: Cow::Borrowed(&src[range]),
+        }
+    }
+
+    /// The text without its HTML.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The bytes of the original text that a range of this text came from.
+    ///
+    /// A range that ends where some HTML was left out ends before the HTML,
+    /// and one that starts there starts after it.
+    pub fn original_range(&self, range: Range<usize>) -> Range<usize> {
+        let start = self.original_offset(range.start, |run_start| run_start <= range.start);
+        if range.is_empty() {
+            return start..start;
+        }
+        let end = self.original_offset(range.end, |run_start| run_start < range.end);
+        start..end
+    }
+
+    /// The offset in the original text of an offset in this text, in the last
+    /// run that `in_run` holds for the start of.
+    fn original_offset(&self, offset: usize, in_run: impl Fn(usize) -> bool) -> usize {
+        let run = self
+            .run_starts
+            .partition_point(|(start, _)| in_run(*start))
+            .saturating_sub(1);
+        let (start, original_start) = self.run_starts[run];
+        original_start + offset - start
+    }
+}
+
+/// Whether a textual container has inline HTML among its children.
+pub(crate) fn has_inline_html_child(container: &Node) -> bool {
+    container
+        .children(&mut container.walk())
+        .any(|child| is_inline_html_node(&child))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    /// The text of the first paragraph of `input` without its HTML.
+    fn paragraph_without_html(input: &str) -> WithoutInlineHtml<'_> {
+        let tree = parse_markdown(input).unwrap();
+        let paragraph = tree.root_node().child(0).unwrap();
+        WithoutInlineHtml::new(&paragraph, paragraph.byte_range(), input)
+    }
+
+    #[test]
+    fn test_text_without_inline_html() {
+        let input = "Written by <b>Ada</b> Lovelace";
+        let text = paragraph_without_html(input);
+        assert_eq!(text.as_str(), "Written by Ada Lovelace");
+        assert_eq!(text.run_starts, vec![(0, 0), (11, 14), (14, 21)]);
+
+        let text = paragraph_without_html("Just text\n");
+        assert_eq!(text.as_str().trim_end(), "Just text");
+        assert_eq!(text.run_starts, vec![(0, 0)]);
+
+        // Whitespace before HTML at the end isn't part of the text
+        let text = paragraph_without_html("Line one <br>\n");
+        assert_eq!(text.as_str(), "Line one");
+    }
+
+    #[test]
+    fn test_original_range_around_inline_html() {
+        let input = "Written by <b>Ada</b> Lovelace";
+        let text = paragraph_without_html(input);
+
+        // "Ada Lovelace" spans the closing tag
+        let range = text.original_range(11..23);
+        assert_eq!(range, 14..30);
+        assert_eq!(&input[range], "Ada</b> Lovelace");
+
+        // "Ada" ends before the closing tag and starts after the opening one
+        let range = text.original_range(11..14);
+        assert_eq!(&input[range], "Ada");
+
+        // "Written by " ends before the opening tag
+        let range = text.original_range(0..11);
+        assert_eq!(&input[range], "Written by ");
+
+        // Nothing, where the HTML was
+        assert_eq!(text.original_range(11..11), 14..14);
+    }
+
+    #[test]
+    fn test_original_range_without_inline_html() {
+        let input = "# Title\n\nSome text";
+        let text = WithoutInlineHtml::with_html(9..18, input);
+        assert_eq!(text.as_str(), "Some text");
+        assert_eq!(text.original_range(5..9), 14..18);
+    }
+}
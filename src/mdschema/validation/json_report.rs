@@ -0,0 +1,403 @@
+//! Machine-readable error reports.
+//!
+//! Errors only know the descendant indexes of the nodes they point at, which
+//! mean nothing without the trees. A `ValidatorReport` resolves them into
+//! byte ranges and line/column positions, for editors and other tools:
+//!
+//! ```json
+//! {
+//!   "file": "README.md",
+//!   "schema_version": 1,
+//!   "errors": [
+//!     {
+//!       "code": "MDV005",
+//!       "kind": "node_content_mismatch",
+//!       "severity": "error",
+//!       "suppressed": false,
+//!       "message": "...",
+//!       "expected": "Some text",
+//!       "actual": "Other text",
+//!       "first_diff_offset": 0,
+//!       "input": {
+//!         "start": { "line": 3, "col": 1, "byte": 9 },
+//!         "end": { "line": 3, "col": 11, "byte": 19 },
+//!         "node_kind": "text",
+//!         "snippet": "Other text"
+//!       },
+//!       "schema": {
+//!         "start": { "line": 3, "col": 1, "byte": 9 },
+//!         "end": { "line": 3, "col": 10, "byte": 18 },
+//!         "node_kind": "text",
+//!         "snippet": "Some text"
+//!       }
+//!     }
+//!   ],
+//!   "stats": {
+//!     "sections": 14,
+//!     "sections_matched": 12,
+//!     "sections_with_warnings": 0,
+//!     "extra_input_blocks": 0,
+//!     "fields_captured": 37,
+//!     "fields_expected": 40,
+//!     "fields": [{ "id": "title", "captured": 1, "expected": 1 }, ...]
+//!   }
+//! }
+//! ```
+//!
+//! Errors about literal text that doesn't match also have the byte offset
+//! into `actual` where it first differs from `expected`, as
+//! `first_diff_offset`.
+//!
+//! When a matcher with a message, like `` `date:@date|Use a date` ``, doesn't
+//! match, `message` is the schema's message, and the pattern is only in
+//! `expected`.
+//!
+//! `node_kind` and `snippet` are the kind of the node and the start of its
+//! text, as they were when the error was found (see `error_locations`).
+//!
+//! Errors a suppression comment turned into warnings are included with a
+//! `"warning"` severity and `"suppressed": true`, so they can be audited.
+//! `input` and `schema` are `null` for errors that don't point at a node.
+//! `file` is only there if the report was given a filename.
+//! `schema_version` is the version the schema declares in its preamble (see
+//! `preamble`). `stats` is how many of the schema's sections the input
+//! matched and how many values it has for its matchers (see `stats`).
+//!
+//! A report serializes as this document, so it can be persisted with any
+//! serde format. It borrows its validator, so it can't be deserialized, but
+//! the document it serializes as can be, as a `serde_json::Value`.
+use serde::{Serialize, Serializer};
+use serde_json::{Value, json};
+use tree_sitter::Tree;
+
+use crate::mdschema::validation::diff::first_diff_offset;
+use crate::mdschema::validation::errors::{SchemaViolationError, ValidationError};
+use crate::mdschema::validation::spans::TextPosition;
+use crate::mdschema::validation::stats::ValidationStats;
+use crate::mdschema::validation::ts_utils::find_node_by_index;
+use crate::mdschema::validation::validator::{Validator, ValidatorState};
+
+/// The errors of a validator, ready to be serialized.
+#[derive(Debug, Clone)]
+pub struct ValidatorReport<'a> {
+    pub(crate) validator: &'a Validator,
+    pub(crate) filename: Option<String>,
+}
+
+impl<'a> ValidatorReport<'a> {
+    pub fn new(validator: &'a Validator) -> Self {
+        Self {
+            validator,
+            filename: None,
+        }
+    }
+
+    /// Report the name of the input the errors are in.
+    pub fn with_filename(mut self, filename: &str) -> Self {
+        self.filename = Some(filename.to_string());
+        self
+    }
+
+    /// How much of the schema the input matched so far (see `stats`).
+    pub fn stats(&self) -> ValidationStats {
+        ValidationStats::from_validator(self.validator)
+    }
+
+    /// Every error found so far, followed by the suppressed ones, as a JSON
+    /// document with an `errors` array, the version of the schema, the
+    /// `stats` (and the `file` they're in, if known).
+    pub fn to_json(&self) -> Value {
+        let errors = self
+            .validator
+            .errors_so_far()
+            .map(|error| self.error_to_json(error, false))
+            .chain(
+                self.validator
+                    .suppressed_errors_so_far()
+                    .map(|error| self.error_to_json(error, true)),
+            )
+            .collect::<Vec<_>>();
+
+        let schema_version = self.validator.schema_version();
+        let stats = self.stats();
+        match &self.filename {
+            Some(filename) => json!({
+                "file": filename,
+                "schema_version": schema_version,
+                "errors": errors,
+                "stats": stats,
+            }),
+            None => json!({
+                "schema_version": schema_version,
+                "errors": errors,
+                "stats": stats,
+            }),
+        }
+    }
+
+    /// A single error as JSON, with the locations of its nodes.
+    ///
+    /// # Arguments
+    ///
+    /// * `error`: The error to serialize.
+    /// * `suppressed`: Whether a suppression comment turned the error into a warning.
+    pub fn error_to_json(&self, error: &ValidationError, suppressed: bool) -> Value {
+        let (schema_index, input_index) = error.node_indexes();
+
+        let mut value = json!({
+            "code": error.code(),
+            "kind": error.kind(),
+            "severity": if suppressed { "warning" } else { "error" },
+            "suppressed": suppressed,
+            "message": error.to_string(),
+        });
+
+        if let ValidationError::SchemaViolation(violation) = error
+            && let Some((expected, actual)) = expected_and_actual(violation)
+        {
+            value["expected"] = expected;
+            value["actual"] = actual;
+        }
+        if let ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+            expected,
+            actual,
+            kind,
+            ..
+        }) = error
+            && kind.is_literal()
+            && let Some(offset) = first_diff_offset(expected, actual)
+        {
+            value["first_diff_offset"] = json!(offset);
+        }
+
+        value["input"] =
+            input_index.map_or(Value::Null, |index| input_node_span(self.validator, index));
+        value["schema"] = schema_index.map_or(Value::Null, |index| {
+            node_span(
+                self.validator.schema_tree(),
+                self.validator.schema_str(),
+                index,
+            )
+        });
+        let locations = self.validator.error_locations(error);
+        for (key, location) in [("input", locations.input), ("schema", locations.schema)] {
+            if let (Some(location), Some(span)) = (location, value[key].as_object_mut()) {
+                span.insert("node_kind".to_string(), json!(location.kind));
+                span.insert("snippet".to_string(), json!(location.snippet));
+            }
+        }
+
+        value
+    }
+}
+
+impl Serialize for ValidatorReport<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+/// Reports are equal when they serialize to the same document, even if they
+/// are of different validators.
+impl PartialEq for ValidatorReport<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_json() == other.to_json()
+    }
+}
+
+/// What the schema expected and what the input had, for the errors that
+/// carry both.
+pub(crate) fn expected_and_actual(error: &SchemaViolationError) -> Option<(Value, Value)> {
+    match error {
+        SchemaViolationError::NodeTypeMismatch {
+            expected, actual, ..
+        }
+        | SchemaViolationError::NodeContentMismatch {
+            expected, actual, ..
+        }
+        | SchemaViolationError::CapturedValueTypeMismatch {
+            expected, actual, ..
+        }
+        | SchemaViolationError::ListMarkerMismatch {
+            expected, actual, ..
+        }
+        | SchemaViolationError::BackReferenceMismatch {
+            expected, actual, ..
+        } => Some((json!(expected), json!(actual))),
+        SchemaViolationError::EnumMismatch {
+            allowed, actual, ..
+        } => Some((json!(allowed), json!(actual))),
+        SchemaViolationError::NotEnoughNodesForRepeatingParagraph {
+            expected, actual, ..
+        }
+        | SchemaViolationError::ChildrenLengthMismatch {
+            expected, actual, ..
+        } => Some((json!({ "min": expected.0, "max": expected.1 }), json!(actual))),
+        SchemaViolationError::WrongListCount {
+            min, max, actual, ..
+        }
+        | SchemaViolationError::WrongSectionCount {
+            min, max, actual, ..
+        }
+        | SchemaViolationError::WrongRowCount {
+            min, max, actual, ..
+        } => Some((json!({ "min": min, "max": max }), json!(actual))),
+        SchemaViolationError::UnalignedBlocks {
+            skipped_schema,
+            skipped_input,
+            ..
+        } => Some((json!(skipped_schema), json!(skipped_input))),
+        SchemaViolationError::TrailingContent { extra_blocks, .. } => {
+            Some((json!(0), json!(extra_blocks)))
+        }
+        SchemaViolationError::AmbiguousSelection { lines, .. } => {
+            Some((json!(1), json!(lines.len())))
+        }
+        SchemaViolationError::MissingContent { expected, .. } => Some((json!(expected), json!(""))),
+        SchemaViolationError::ConditionNotMet {
+            expected, actual, ..
+        } => Some((json!(expected), json!(actual))),
+        SchemaViolationError::ForbiddenContent {
+            pattern, actual, ..
+        } => Some((json!(pattern), json!(actual))),
+        SchemaViolationError::UnorderedItemsUnmatched {
+            missing,
+            unexpected,
+            ..
+        } => Some((json!(missing), json!(unexpected))),
+        SchemaViolationError::UnresolvedLinkReference { .. }
+        | SchemaViolationError::BrokenAnchor { .. }
+        | SchemaViolationError::MissingFrontmatter { .. }
+        | SchemaViolationError::MissingFrontmatterKey { .. }
+        | SchemaViolationError::SelectionNotFound { .. }
+        | SchemaViolationError::NonRepeatingMatcherInListContext { .. }
+        | SchemaViolationError::NodeListTooDeep { .. }
+        | SchemaViolationError::NestingTooDeep { .. }
+        | SchemaViolationError::MalformedNodeStructure { .. } => None,
+    }
+}
+
+/// Where the node with a descendant index starts and ends in its document.
+fn node_span(tree: &Tree, src: &str, index: usize) -> Value {
+    let range = find_node_by_index(tree.root_node(), index).byte_range();
+
+    json!({
+        "start": TextPosition::from_byte(src, range.start).to_value(),
+        "end": TextPosition::from_byte(src, range.end).to_value(),
+    })
+}
+
+/// Like `node_span`, for a node of the input at a descendant index of the
+/// whole input, even if the start of the input was dropped (see
+/// `bounded_memory`).
+fn input_node_span(validator: &Validator, index: usize) -> Value {
+    let range = validator.input_node(index).byte_range();
+
+    json!({
+        "start": input_position(validator, range.start).to_value(),
+        "end": input_position(validator, range.end).to_value(),
+    })
+}
+
+/// Where a byte of the input we still have is in the whole input, even if
+/// the start of the input was dropped (see `bounded_memory`).
+pub(crate) fn input_position(validator: &Validator, byte: usize) -> TextPosition {
+    let dropped_input = validator.dropped_input();
+    let position = TextPosition::from_byte(validator.last_input_str(), byte);
+    TextPosition {
+        line: dropped_input.lines + position.line,
+        col: position.col,
+        byte: dropped_input.bytes + position.byte,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_resolves_node_locations() {
+        let schema = "# Title\n\nSome text\n";
+        let input = "# Title\n\nOther text\n";
+
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+        let report = ValidatorReport::new(&validator).to_json();
+
+        let errors = report["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), 1);
+        let error = &errors[0];
+        assert_eq!(error["input"]["start"], json!({ "line": 3, "col": 1, "byte": 9 }));
+        assert_eq!(error["schema"]["start"], json!({ "line": 3, "col": 1, "byte": 9 }));
+        assert_eq!(error["input"]["snippet"], "Other text");
+        assert_eq!(error["schema"]["snippet"], "Some text");
+        assert_eq!(error["input"]["node_kind"], error["schema"]["node_kind"]);
+
+        assert_eq!(error["code"], "MDV005");
+        assert_eq!(error["kind"], "node_content_mismatch");
+        assert_eq!(error["severity"], "error");
+        assert_eq!(error["suppressed"], false);
+
+        assert_eq!(error["expected"], "Some text");
+        assert_eq!(error["actual"], "Other text");
+        assert_eq!(error["first_diff_offset"], 0);
+    }
+
+    #[test]
+    fn test_report_serializes_as_its_json() {
+        let schema = "# Title\n\nSome text\n";
+        let input = "# Title\n\nOther text\n";
+
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+        let report = ValidatorReport::new(&validator).with_filename("input.md");
+
+        let serialized = serde_json::to_string(&report).unwrap();
+        let persisted: Value = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(persisted, report.to_json());
+        assert_eq!(report.clone(), report);
+        assert_ne!(ValidatorReport::new(&validator), report);
+    }
+
+    #[test]
+    fn test_report_marks_suppressed_errors_as_warnings() {
+        let schema = "# Title\n";
+        let input = "<!-- mdvalidate-disable-next-block -->\n# Titel\n";
+
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+        let report = ValidatorReport::new(&validator).to_json();
+
+        let errors = report["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["input"]["start"]["line"], 2);
+        assert_eq!(errors[0]["severity"], "warning");
+        assert_eq!(errors[0]["suppressed"], true);
+    }
+
+    #[test]
+    fn test_report_has_schema_version() {
+        let schema = "<!-- mds-version: 3 -->\n\n# Title\n";
+
+        let mut validator = Validator::new_complete(schema, "# Title\n").unwrap();
+        validator.validate();
+        let report = ValidatorReport::new(&validator).to_json();
+
+        assert_eq!(
+            report,
+            json!({
+                "schema_version": 3,
+                "errors": [],
+                "stats": {
+                    "sections": 1,
+                    "sections_matched": 1,
+                    "sections_with_warnings": 0,
+                    "extra_input_blocks": 0,
+                    "fields_captured": 0,
+                    "fields_expected": 0,
+                    "fields": [],
+                },
+            })
+        );
+    }
+}
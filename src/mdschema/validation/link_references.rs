@@ -0,0 +1,154 @@
+//! Link reference definitions.
+//!
+//! Reference-style links point at their destination through a label, which
+//! is defined somewhere else in the document, usually at the bottom:
+//!
+//! ```md
+//! See [the docs][docs] for more.
+//!
+//! [docs]: https://example.com/docs
+//! ```
+//!
+//! We collect every definition of a document up front, so that links can be
+//! compared by the destination they resolve to, whichever style they use.
+use std::collections::HashMap;
+
+use tree_sitter::{Node, TreeCursor};
+
+use crate::mdschema::validation::ts_types::*;
+use crate::mdschema::validation::ts_utils::{get_node_text, walk_to_root};
+
+/// The destination a link reference definition gives its label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkDefinition {
+    /// The destination, without surrounding angle brackets.
+    pub destination: String,
+    /// Where the destination starts in the document.
+    pub start_byte: usize,
+    /// The descendant index of the destination node.
+    pub descendant_index: usize,
+}
+
+/// Every link reference definition in a document, by normalized label.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkReferences {
+    definitions: HashMap<String, LinkDefinition>,
+}
+
+impl LinkReferences {
+    /// Collect the definitions of the document the cursor is in.
+    ///
+    /// Like CommonMark, if a label is defined more than once the first
+    /// definition wins.
+    pub fn from_cursor(cursor: &TreeCursor, src: &str) -> Self {
+        let mut cursor = cursor.clone();
+        walk_to_root(&mut cursor);
+
+        let mut references = Self::default();
+        let root = cursor.node();
+        for index in 1..root.descendant_count() {
+            cursor.goto_descendant(index);
+            if is_link_reference_definition_node(&cursor.node()) {
+                references.add_definition(&cursor, src);
+            }
+        }
+
+        references
+    }
+
+    fn add_definition(&mut self, definition_cursor: &TreeCursor, src: &str) {
+        let mut label = None;
+        let mut destination = None;
+
+        let mut cursor = definition_cursor.clone();
+        if !cursor.goto_first_child() {
+            return;
+        }
+        loop {
+            let node = cursor.node();
+            if is_link_label_node(&node) {
+                label = Some(get_node_text(&node, src));
+            } else if is_link_destination_node(&node) {
+                destination = Some((node, cursor.descendant_index()));
+            }
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+
+        let (Some(label), Some((destination, descendant_index))) = (label, destination) else {
+            return;
+        };
+
+        self.definitions
+            .entry(normalize_label(label))
+            .or_insert_with(|| definition_from_node(&destination, descendant_index, src));
+    }
+
+    /// Look up the definition for a label, as written in a link.
+    pub fn resolve(&self, label: &str) -> Option<&LinkDefinition> {
+        self.definitions.get(&normalize_label(label))
+    }
+}
+
+fn definition_from_node(destination: &Node, descendant_index: usize, src: &str) -> LinkDefinition {
+    let text = get_node_text(destination, src);
+    let (destination, start_byte) = match text.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+        Some(inner) => (inner, destination.start_byte() + 1),
+        None => (text, destination.start_byte()),
+    };
+
+    LinkDefinition {
+        destination: destination.to_string(),
+        start_byte,
+        descendant_index,
+    }
+}
+
+/// A link label without its surrounding brackets, if it has them.
+pub fn label_text(label: &str) -> &str {
+    label
+        .strip_prefix('[')
+        .and_then(|label| label.strip_suffix(']'))
+        .unwrap_or(label)
+}
+
+/// Normalize a link label so that labels which CommonMark considers equal
+/// compare equal: surrounding brackets are dropped, case is folded and runs
+/// of whitespace become a single space.
+pub fn normalize_label(label: &str) -> String {
+    label_text(label)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    #[test]
+    fn test_normalize_label() {
+        assert_eq!(normalize_label("[Foo  Bar]"), "foo bar");
+        assert_eq!(normalize_label("foo\n bar"), "foo bar");
+    }
+
+    #[test]
+    fn test_collect_definitions() {
+        let src = "See [the docs][Docs].\n\n[docs]: https://example.com/docs\n[img]: <images/logo.png>\n[DOCS]: https://ignored.com\n";
+        let tree = parse_markdown(src).unwrap();
+        let references = LinkReferences::from_cursor(&tree.walk(), src);
+
+        let docs = references.resolve("Docs").unwrap();
+        assert_eq!(docs.destination, "https://example.com/docs");
+        assert_eq!(&src[docs.start_byte..docs.start_byte + 24], "https://example.com/docs");
+
+        assert_eq!(
+            references.resolve("[img]").unwrap().destination,
+            "images/logo.png"
+        );
+        assert!(references.resolve("missing").is_none());
+    }
+}
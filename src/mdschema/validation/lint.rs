@@ -0,0 +1,504 @@
+//! Finding problems in a schema without validating any input.
+//!
+//! Most schema problems only come up once an input reaches the part of the
+//! schema they are in, and some never do. `lint_schema` looks at every
+//! matcher of a schema up front and reports:
+//!
+//! - Matchers that fail to build, like ones with invalid regexes or
+//!   impossible `{min,max}` ranges like `{3,2}`, or whose regexes are more
+//!   complex than the default `RegexLimits` allow.
+//! - Variable length list matchers followed by more list items, which can
+//!   never be validated (see `SchemaError::RepeatingMatcherUnbounded`).
+//! - Matcher ids used more than once in the same scope (see `scopes`), whose
+//!   matches get collected together.
+//! - Code spans that look like they were meant to be matchers, like
+//!   `` `name: /\w+/` ``, but are compared literally. Where the mistake is a
+//!   small one, like a missing `/`, it's reported as a
+//!   `SchemaError::MatcherSyntaxSuspicious`, which validation reports as a
+//!   warning too.
+//!
+//! ```no_run
+//! use mdvalidate::mdschema::validation::lint::lint_schema;
+//!
+//! for finding in lint_schema("- `item:/\\w+/`{1,}\n- Last\n").unwrap() {
+//!     println!("{}:{}: {}", finding.position.line, finding.position.col, finding);
+//! }
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+
+use tree_sitter::{Node, Tree, TreeCursor};
+
+use crate::mdschema::validation::errors::{SchemaError, ValidationError};
+use crate::mdschema::validation::includes::include_path;
+use crate::mdschema::validation::matchers::matcher::{
+    Matcher, MatcherError, RegexLimits, has_matcher_syntax, near_miss_matcher,
+};
+use crate::mdschema::validation::scopes::SchemaScopes;
+use crate::mdschema::validation::spans::TextPosition;
+use crate::mdschema::validation::ts_types::*;
+use crate::mdschema::validation::ts_utils::{get_node_text, parse_markdown, visit_code_spans};
+
+/// How bad a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LintSeverity {
+    /// Validating against the schema will fail, whatever the input.
+    Error,
+    /// The schema works, but probably not the way it was meant to.
+    Warning,
+}
+
+impl fmt::Display for LintSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintSeverity::Error => write!(f, "error"),
+            LintSeverity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in a schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LintIssue {
+    /// An error that validating against the schema would run into.
+    Schema(SchemaError),
+    /// A matcher id that an earlier matcher already uses.
+    DuplicateMatcherId { id: String, first: TextPosition },
+    /// A code span that is compared literally, but looks like a matcher.
+    LooksLikeMatcher { text: String, error: MatcherError },
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintIssue::Schema(error) => write!(f, "{}", error),
+            LintIssue::DuplicateMatcherId { id, first } => write!(
+                f,
+                "Matcher id '{}' is already used at {}:{}, so their matches will be combined",
+                id, first.line, first.col
+            ),
+            LintIssue::LooksLikeMatcher { text, error } => write!(
+                f,
+                "{} looks like a matcher, but is compared literally ({})",
+                text, error
+            ),
+        }
+    }
+}
+
+/// A problem found in a schema, and where it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    pub severity: LintSeverity,
+    pub issue: LintIssue,
+    /// Where the problem starts in the schema.
+    pub position: TextPosition,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.issue)
+    }
+}
+
+/// Find the problems in a schema, in the order they appear in it.
+///
+/// Returns `None` if the schema can't be parsed.
+pub fn lint_schema(schema_str: &str) -> Option<Vec<LintFinding>> {
+    let tree = parse_markdown(schema_str)?;
+    let mut linter = Linter {
+        schema_str,
+        scopes: SchemaScopes::find(&tree, schema_str),
+        findings: Vec::new(),
+        ids: HashMap::new(),
+    };
+
+    visit_code_spans(&tree, |cursor| linter.lint_code_span(cursor));
+    Some(linter.findings)
+}
+
+/// The descendant indexes of the code span matchers in a schema that capture
+/// under `id`, in order.
+pub(crate) fn matcher_id_uses(schema_tree: &Tree, schema_str: &str, id: &str) -> Vec<usize> {
+    let mut uses = Vec::new();
+    visit_code_spans(schema_tree, |cursor| {
+        if Matcher::try_from_schema_cursor(cursor, schema_str)
+            .is_ok_and(|matcher| matcher.id() == Some(id))
+        {
+            uses.push(cursor.descendant_index());
+        }
+    });
+    uses
+}
+
+/// An error for every matcher of a schema whose regex is more complex than
+/// `limits` allow, in order.
+pub(crate) fn regex_limit_errors(
+    schema_tree: &Tree,
+    schema_str: &str,
+    limits: &RegexLimits,
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    visit_code_spans(schema_tree, |cursor| {
+        if let Ok(matcher) = Matcher::try_from_schema_cursor(cursor, schema_str)
+            && let Err(error) = matcher.check_regex_limits(limits)
+        {
+            errors.push(ValidationError::SchemaError(SchemaError::MatcherError {
+                error,
+                schema_index: cursor.descendant_index(),
+            }));
+        }
+    });
+    errors
+}
+
+/// An error for every code span of a schema that isn't literal code but
+/// doesn't build into a matcher, and for every variable length list matcher
+/// followed by more list items, in order.
+///
+/// Validation runs into these where the input reaches them, if it does, so
+/// this finds them before there is any input (see `SchemaDiagnostics`).
+pub(crate) fn invalid_matcher_errors(schema_tree: &Tree, schema_str: &str) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    visit_code_spans(schema_tree, |cursor| {
+        let node = cursor.node();
+        let schema_index = cursor.descendant_index();
+        match Matcher::try_from_schema_cursor(cursor, schema_str) {
+            Ok(matcher) if is_unbounded_list_matcher(&matcher, &node) => {
+                errors.push(SchemaError::RepeatingMatcherUnbounded { schema_index });
+            }
+            Ok(_) | Err(MatcherError::WasLiteralCode) => {}
+            // Includes that weren't resolved have errors of their own
+            Err(_) if include_path(get_node_text(&node, schema_str)).is_some() => {}
+            Err(error) => errors.push(SchemaError::MatcherError {
+                error,
+                schema_index,
+            }),
+        }
+    });
+    errors
+}
+
+/// A warning for every code span of a schema that looks like a matcher with a
+/// small mistake, like `` `name:/\w+` ``, in order.
+pub(crate) fn suspicious_matcher_warnings(
+    schema_tree: &Tree,
+    schema_str: &str,
+) -> Vec<ValidationError> {
+    let mut warnings = Vec::new();
+    visit_code_spans(schema_tree, |cursor| {
+        if let Err(error) = Matcher::try_from_schema_cursor(cursor, schema_str)
+            && let Some(warning) = suspicious_matcher(cursor, schema_str, &error)
+        {
+            warnings.push(ValidationError::SchemaError(warning));
+        }
+    });
+    warnings
+}
+
+/// The warning for a code span that failed to build as a matcher with `error`,
+/// if it looks like a matcher with a small mistake.
+fn suspicious_matcher(
+    cursor: &TreeCursor,
+    schema_str: &str,
+    error: &MatcherError,
+) -> Option<SchemaError> {
+    // Marked as literal code with `!`, so it's meant to not be a matcher
+    if matches!(error, MatcherError::WasLiteralCode) {
+        return None;
+    }
+
+    let text = get_node_text(&cursor.node(), schema_str);
+    let (hint, suggestion) = near_miss_matcher(text)?;
+    Some(SchemaError::MatcherSyntaxSuspicious {
+        schema_index: cursor.descendant_index(),
+        text: text.to_string(),
+        hint: hint.to_string(),
+        suggestion,
+    })
+}
+
+struct Linter<'a> {
+    schema_str: &'a str,
+    scopes: SchemaScopes,
+    findings: Vec<LintFinding>,
+    /// Where each matcher id was first used, by its scope path and id joined
+    /// with dots.
+    ids: HashMap<String, TextPosition>,
+}
+
+impl Linter<'_> {
+    fn lint_code_span(&mut self, cursor: &TreeCursor) {
+        let node = cursor.node();
+        let schema_index = cursor.descendant_index();
+
+        match Matcher::try_from_schema_cursor(cursor, self.schema_str) {
+            Ok(matcher) => {
+                if let Some(id) = matcher.id() {
+                    self.check_id_is_unique(id, &node);
+                }
+
+                if let Err(error) = matcher.check_regex_limits(&RegexLimits::default()) {
+                    self.add(
+                        LintSeverity::Error,
+                        LintIssue::Schema(SchemaError::MatcherError {
+                            error,
+                            schema_index,
+                        }),
+                        &node,
+                    );
+                }
+
+                if is_unbounded_list_matcher(&matcher, &node) {
+                    self.add(
+                        LintSeverity::Error,
+                        LintIssue::Schema(SchemaError::RepeatingMatcherUnbounded { schema_index }),
+                        &node,
+                    );
+                }
+            }
+            // Marked as literal code with `!`, so it's meant to not be a matcher
+            Err(MatcherError::WasLiteralCode) => {}
+            Err(MatcherError::MatcherExtrasError(error)) => self.add(
+                LintSeverity::Error,
+                LintIssue::Schema(SchemaError::InvalidMatcherExtras {
+                    schema_index,
+                    error,
+                }),
+                &node,
+            ),
+            Err(error) => {
+                let text = get_node_text(&node, self.schema_str);
+                if let Some(warning) = suspicious_matcher(cursor, self.schema_str, &error) {
+                    self.add(LintSeverity::Warning, LintIssue::Schema(warning), &node);
+                } else if has_matcher_syntax(text) {
+                    self.add(
+                        LintSeverity::Error,
+                        LintIssue::Schema(SchemaError::MatcherError {
+                            error,
+                            schema_index,
+                        }),
+                        &node,
+                    );
+                } else if text.contains(':') && text.contains('/') && include_path(text).is_none() {
+                    self.add(
+                        LintSeverity::Warning,
+                        LintIssue::LooksLikeMatcher {
+                            text: text.to_string(),
+                            error,
+                        },
+                        &node,
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_id_is_unique(&mut self, id: &str, node: &Node) {
+        let position = TextPosition::from_byte(self.schema_str, node.start_byte());
+        let mut path = self.scopes.scope_path(node);
+        path.push(id.to_string());
+        let key = path.join(".");
+        match self.ids.get(&key) {
+            Some(&first) => self.add(
+                LintSeverity::Warning,
+                LintIssue::DuplicateMatcherId {
+                    id: id.to_string(),
+                    first,
+                },
+                node,
+            ),
+            None => {
+                self.ids.insert(key, position);
+            }
+        }
+    }
+
+    fn add(&mut self, severity: LintSeverity, issue: LintIssue, node: &Node) {
+        self.findings.push(LintFinding {
+            severity,
+            issue,
+            position: TextPosition::from_byte(self.schema_str, node.start_byte()),
+        });
+    }
+}
+
+/// Whether a matcher repeats a variable number of times in a list item that
+/// more list items follow, so where it ends can't be known.
+fn is_unbounded_list_matcher(matcher: &Matcher, code_span: &Node) -> bool {
+    matcher.variable_length()
+        && starts_list_item(code_span)
+            .and_then(|list_item| list_item.next_sibling())
+            .is_some_and(|next| is_list_item_node(&next))
+}
+
+/// The list item a code span starts, if it is the first thing in the first
+/// paragraph of one, like `` - `item:/\w+/`{1,} ``.
+fn starts_list_item<'t>(code_span: &Node<'t>) -> Option<Node<'t>> {
+    if code_span.prev_sibling().is_some() {
+        return None;
+    }
+
+    let paragraph = code_span.parent().filter(is_paragraph_node)?;
+    paragraph.parent().filter(is_list_item_node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(schema: &str) -> Vec<(LintSeverity, usize, usize, LintIssue)> {
+        lint_schema(schema)
+            .unwrap()
+            .into_iter()
+            .map(|finding| {
+                (
+                    finding.severity,
+                    finding.position.line,
+                    finding.position.col,
+                    finding.issue,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_schema_has_no_findings() {
+        let schema = "# `title:/\\w+/`\n\n- `item:/\\w+/`{1,}\n\nRun `cargo:/x/`!\n";
+        assert_eq!(lint(schema), vec![]);
+    }
+
+    #[test]
+    fn test_duplicate_ids() {
+        let findings = lint("# `title:/\\w+/`\n\nBy `title:/\\w+/`\n");
+
+        assert_eq!(findings.len(), 1);
+        let (severity, line, col, issue) = &findings[0];
+        assert_eq!((*line, *col), (3, 4));
+        assert_eq!(*severity, LintSeverity::Warning);
+        assert_eq!(
+            *issue,
+            LintIssue::DuplicateMatcherId {
+                id: "title".to_string(),
+                first: TextPosition {
+                    line: 1,
+                    col: 3,
+                    byte: 2
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_duplicate_ids_in_different_scopes() {
+        let schema = "# Server `@scope:server`\n\n`host:/\\w+/`\n\n\
+                      # Client `@scope:client`\n\n`host:/\\w+/`\n";
+        assert_eq!(lint(schema), vec![]);
+    }
+
+    #[test]
+    fn test_unbounded_list_matcher_followed_by_items() {
+        let findings = lint("- `item:/\\w+/`{1,}\n- `last:/\\w+/`\n");
+
+        assert_eq!(findings.len(), 1);
+        let (severity, line, col, issue) = &findings[0];
+        assert_eq!((*line, *col), (1, 3));
+        assert_eq!(*severity, LintSeverity::Error);
+        assert!(matches!(
+            issue,
+            LintIssue::Schema(SchemaError::RepeatingMatcherUnbounded { .. })
+        ));
+
+        // With a fixed count, we know where the next item starts
+        assert_eq!(lint("- `item:/\\w+/`{2,2}\n- `last:/\\w+/`\n"), vec![]);
+    }
+
+    #[test]
+    fn test_invalid_matchers() {
+        let findings = lint("`name:/[a-/`\n\n- `item:/\\w+/`{3,2}\n");
+
+        assert_eq!(findings.len(), 2);
+        assert_eq!((findings[0].1, findings[0].2), (1, 1));
+        assert_eq!((findings[1].1, findings[1].2), (3, 3));
+        assert!(findings.iter().all(|finding| finding.0 == LintSeverity::Error));
+        assert!(matches!(
+            findings[0].3,
+            LintIssue::Schema(SchemaError::MatcherError { .. })
+        ));
+        assert!(matches!(
+            findings[1].3,
+            LintIssue::Schema(SchemaError::InvalidMatcherExtras { .. })
+        ));
+    }
+
+    #[test]
+    fn test_code_that_looks_like_a_matcher() {
+        let findings =
+            lint("Name: `name: /\\w+/`\n\nSee `std::fmt` and `a/b`\n\n`include:./a.mds`\n");
+
+        assert_eq!(findings.len(), 1);
+        let (severity, line, col, issue) = &findings[0];
+        assert_eq!((*line, *col), (1, 7));
+        assert_eq!(*severity, LintSeverity::Warning);
+        assert!(matches!(
+            issue,
+            LintIssue::Schema(SchemaError::MatcherSyntaxSuspicious { .. })
+        ));
+
+        let findings = lint("Name: `name: /\\w+/ x`\n");
+        assert_eq!(findings.len(), 1);
+        assert!(matches!(findings[0].3, LintIssue::LooksLikeMatcher { .. }));
+    }
+
+    #[test]
+    fn test_suspicious_matcher_warnings() {
+        let schema = "# `title:/\\w+`\n\nBy `author: /\\w+/`\n\nRun `cmd:/x`!\n\n\
+                      See `https://example.com` and `Note: hi`\n";
+        let schema_tree = parse_markdown(schema).unwrap();
+
+        let warnings = suspicious_matcher_warnings(&schema_tree, schema);
+        let suggestions: Vec<_> = warnings
+            .iter()
+            .map(|warning| match warning {
+                ValidationError::SchemaError(SchemaError::MatcherSyntaxSuspicious {
+                    hint,
+                    suggestion,
+                    ..
+                }) => (hint.as_str(), suggestion.as_str()),
+                warning => panic!("Expected a suspicious matcher, got {:?}", warning),
+            })
+            .collect();
+        assert_eq!(
+            suggestions,
+            vec![
+                ("missing closing '/'", "title:/\\w+/"),
+                ("remove the space after ':'", "author:/\\w+/"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_matcher_errors() {
+        let schema = "# `title:/[a-/`\n\nSee `std::fmt` and `Note: hi`!\n\n\
+                      - `item:/\\w+/`{1,}\n- Last\n\n``a:/b/`` `include:./a.mds`\n";
+        let schema_tree = parse_markdown(schema).unwrap();
+
+        let errors = invalid_matcher_errors(&schema_tree, schema);
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(
+            errors.as_slice(),
+            [
+                SchemaError::MatcherError { .. },
+                SchemaError::MatcherError { .. },
+                SchemaError::RepeatingMatcherUnbounded { .. },
+            ]
+        ));
+        assert!(
+            errors
+                .windows(2)
+                .all(|pair| pair[0].schema_index() < pair[1].schema_index())
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Nesting the match of a matcher under a path of keys.
+//!
+//! The dots of a matcher id separate the keys of the path its match is put
+//! under, so a schema like
+//!
+//! ```md
+//! By `meta.author:/\w+/`, under `meta.license:/[\w-]+/`
+//! ```
+//!
+//! captures `{"meta": {"author": "...", "license": "..."}}` rather than keys
+//! with dots in them. The matches of repeated matchers are collected at the
+//! end of the path too, so `` - `deps.name:/\w+/`{1,} `` captures
+//! `{"deps": {"name": [...]}}`, and the matches of sibling blocks are joined
+//! key by key. A dot escaped with a backslash, like `` `v1\.2:/\w+/` ``, is
+//! part of its key instead.
+//!
+//! Back-references and conditions refer to a match by the same dotted id.
+//! Two ids where one puts a value where the other needs an object, like
+//! `meta` and `meta.author` in the same scope (see `scopes`), are reported
+//! as a `SchemaError::MatchPathConflict` when the schema is loaded.
+use serde_json::Value;
+use tree_sitter::Tree;
+
+use crate::mdschema::validation::errors::{SchemaError, ValidationError};
+use crate::mdschema::validation::matchers::matcher::Matcher;
+use crate::mdschema::validation::scopes::SchemaScopes;
+use crate::mdschema::validation::ts_utils::visit_code_spans;
+
+/// Separates the keys of the path of a matcher id.
+pub const MATCH_PATH_SEPARATOR: char = '.';
+
+/// Escapes a `MATCH_PATH_SEPARATOR` that is part of a key.
+const ESCAPE: char = '\\';
+
+/// The keys of the path a matcher id puts its match under, outermost first.
+/// An id without dots is a path of one key.
+pub fn match_path(id: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    let mut key = String::new();
+    let mut chars = id.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ESCAPE if chars.peek() == Some(&MATCH_PATH_SEPARATOR) => {
+                key.push(MATCH_PATH_SEPARATOR);
+                chars.next();
+            }
+            MATCH_PATH_SEPARATOR => keys.push(std::mem::take(&mut key)),
+            c => key.push(c),
+        }
+    }
+    keys.push(key);
+    keys
+}
+
+/// The value at the end of a path of keys, if there is one.
+pub fn value_at_path<'v>(value: &'v Value, path: &[String]) -> Option<&'v Value> {
+    path.iter()
+        .try_fold(value, |value, key| value.get(key.as_str()))
+}
+
+/// The value at the end of a path of keys, if it's the only thing in every
+/// object along the way, like `"a"` in `{"deps": {"name": "a"}}`.
+pub(crate) fn sole_value_at_path<'v>(value: &'v Value, path: &[String]) -> Option<&'v Value> {
+    path.iter().try_fold(value, |value, key| {
+        value
+            .as_object()
+            .filter(|object| object.len() == 1)?
+            .get(key.as_str())
+    })
+}
+
+/// Remove the value at the end of a path of keys, and the objects along the
+/// way that it leaves empty.
+pub(crate) fn take_value_at_path(value: &mut Value, path: &[String]) -> Option<Value> {
+    let (key, rest) = path.split_first()?;
+    let object = value.as_object_mut()?;
+    if rest.is_empty() {
+        return object.shift_remove(key.as_str());
+    }
+
+    let inner = object.get_mut(key.as_str())?;
+    let taken = take_value_at_path(inner, rest)?;
+    if inner.as_object().is_some_and(|inner| inner.is_empty()) {
+        object.shift_remove(key.as_str());
+    }
+    Some(taken)
+}
+
+/// An error for every matcher of a schema whose path conflicts with the path
+/// of an earlier one, in order.
+///
+/// Paths conflict when one of them ends where the other goes on, so that one
+/// id captures a value where the other needs an object to nest its match
+/// in. Only dotted ids are checked, since a scope replacing a match under its
+/// own name is how scopes have always worked.
+pub(crate) fn match_path_conflict_errors(
+    schema_tree: &Tree,
+    schema_str: &str,
+    schema_scopes: &SchemaScopes,
+) -> Vec<ValidationError> {
+    let mut paths: Vec<(Vec<String>, String)> = Vec::new();
+    let mut errors = Vec::new();
+    visit_code_spans(schema_tree, |cursor| {
+        let Ok(matcher) = Matcher::try_from_schema_cursor(cursor, schema_str) else {
+            return;
+        };
+        let Some(id) = matcher.id() else {
+            return;
+        };
+        let id_path = match_path(id);
+        let mut path = schema_scopes.scope_path(&cursor.node());
+        path.extend(id_path.iter().cloned());
+
+        let conflict = paths.iter().find(|(other_path, other_id)| {
+            (id_path.len() > 1 || match_path(other_id).len() > 1)
+                && other_path.len() != path.len()
+                && other_path.iter().zip(&path).all(|(a, b)| a == b)
+        });
+        if let Some((_, other_id)) = conflict {
+            errors.push(ValidationError::SchemaError(
+                SchemaError::MatchPathConflict {
+                    schema_index: cursor.descendant_index(),
+                    id: id.to_string(),
+                    other_id: other_id.clone(),
+                },
+            ));
+        }
+        paths.push((path, id.to_string()));
+    });
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    fn path(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn test_match_path() {
+        assert_eq!(match_path("title"), path(&["title"]));
+        assert_eq!(match_path("meta.author"), path(&["meta", "author"]));
+        assert_eq!(match_path("a.b.c"), path(&["a", "b", "c"]));
+        assert_eq!(match_path("v1\\.2.notes"), path(&["v1.2", "notes"]));
+    }
+
+    #[test]
+    fn test_values_at_paths() {
+        let mut value = json!({"deps": {"name": "a", "version": "1"}, "title": "T"});
+        let name = path(&["deps", "name"]);
+
+        assert_eq!(value_at_path(&value, &name), Some(&json!("a")));
+        assert_eq!(sole_value_at_path(&value, &name), None);
+        assert_eq!(
+            sole_value_at_path(&json!({"deps": {"name": "a"}}), &name),
+            Some(&json!("a"))
+        );
+
+        assert_eq!(take_value_at_path(&mut value, &name), Some(json!("a")));
+        assert_eq!(
+            take_value_at_path(&mut value, &path(&["deps", "version"])),
+            Some(json!("1"))
+        );
+        assert_eq!(value, json!({"title": "T"}));
+    }
+
+    #[test]
+    fn test_match_path_conflicts() {
+        let schema = "# `meta:/\\w+/`\n\nBy `meta.author:/\\w+/` and `meta.license:/\\w+/`\n\n\
+                      `other.a:/\\w+/` `other.a:/\\w+/`\n";
+        let tree = parse_markdown(schema).unwrap();
+
+        let errors = match_path_conflict_errors(&tree, schema, &SchemaScopes::default());
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|error| matches!(
+            error,
+            ValidationError::SchemaError(SchemaError::MatchPathConflict { other_id, .. })
+                if other_id == "meta"
+        )));
+    }
+}
@@ -0,0 +1,370 @@
+//! Reporting matches as soon as they are found, while input is still coming
+//! in.
+//!
+//! Every match has a path: its id for a single value, like `title`, or its id
+//! and position for each value of an array, like `item[2]`. A `MatchStream`
+//! remembers which paths it has reported, so that each is reported once even
+//! though every `Validator::validate` pass reports all of the matches so far:
+//!
+//! ```no_run
+//! use mdvalidate::mdschema::validation::match_stream::MatchStream;
+//! use mdvalidate::mdschema::validation::validator::Validator;
+//!
+//! let mut stream = MatchStream::new();
+//! let mut validator = Validator::new_incomplete("- `item:/\\w+/`{1,}\n", "- a\n- b\n").unwrap();
+//! validator.validate();
+//! for update in stream.new_matches(validator.matches_so_far()) {
+//!     println!("{}", update.to_json());
+//! }
+//! ```
+//!
+//! Matchers don't capture text that more input could still extend, so the
+//! first value reported for a path is normally the one the whole document
+//! ends up with.
+//!
+//! Library users can have the validator keep track instead. It journals the
+//! matches each `validate` pass finds, down to the values of nested lists,
+//! with paths like `items[2].inner[0]`, for `Validator::drain_new_matches` to
+//! hand out or a hook set with `Validator::on_match` to be called with:
+//!
+//! ```no_run
+//! use mdvalidate::mdschema::validation::validator::Validator;
+//!
+//! let mut validator = Validator::new_incomplete("- `item:/\\w+/`{1,}\n", "").unwrap();
+//! for chunk in ["- a\n", "- b\n"] {
+//!     validator.append_more_input(chunk).unwrap();
+//!     validator.validate();
+//!     for new_match in validator.drain_new_matches() {
+//!         println!("{} = {}", new_match.path, new_match.value);
+//!     }
+//! }
+//! ```
+//!
+//! New matches come in the order of the input. Within one pass, that takes
+//! `ValidateOptions::include_spans`, without which the values of one id come
+//! before those of the next, in the order of the matches.
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::mdschema::validation::spans::span_byte_range;
+
+/// A value that was captured, and where in the matches it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchUpdate {
+    /// Like `title`, or `item[2]` for the third value of `item`.
+    pub path: String,
+    pub value: Value,
+}
+
+impl MatchUpdate {
+    /// The update as a JSON Lines record, like
+    /// `{"event":"match","path":"item[2]","value":"three"}`.
+    pub fn to_json(&self) -> Value {
+        json!({"event": "match", "path": self.path, "value": self.value})
+    }
+}
+
+/// The JSON Lines record that ends a stream of matches.
+pub fn done_record(is_valid: bool) -> Value {
+    json!({"event": "done", "valid": is_valid})
+}
+
+/// Remembers which matches have been reported already.
+#[derive(Debug, Clone, Default)]
+pub struct MatchStream {
+    reported: HashSet<String>,
+}
+
+impl MatchStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The matches that haven't been reported yet, in the order they appear
+    /// in `matches`. They count as reported from now on.
+    pub fn new_matches(&mut self, matches: &Value) -> Vec<MatchUpdate> {
+        match_updates(matches)
+            .into_iter()
+            .filter(|update| self.reported.insert(update.path.clone()))
+            .collect()
+    }
+}
+
+/// One step of a `MatchPath`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum MatchPathSegment {
+    /// The id of a match, or the key of a scope.
+    Key(String),
+    /// The position of a value in an array of them.
+    Index(usize),
+}
+
+/// Where a value is in the matches, like `items[2].inner[0]` for the first
+/// `inner` value of the third `items` value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct MatchPath(Vec<MatchPathSegment>);
+
+impl MatchPath {
+    pub fn segments(&self) -> &[MatchPathSegment] {
+        &self.0
+    }
+
+    fn with(&self, segment: MatchPathSegment) -> Self {
+        let mut path = self.clone();
+        path.0.push(segment);
+        path
+    }
+}
+
+impl fmt::Display for MatchPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                MatchPathSegment::Key(key) if i == 0 => write!(f, "{}", key)?,
+                MatchPathSegment::Key(key) => write!(f, ".{}", key)?,
+                MatchPathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A value that was captured since new matches were last drained.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewMatch {
+    pub path: MatchPath,
+    pub value: Value,
+    /// The bytes of the input it was captured from, if we know them, which
+    /// takes `ValidateOptions::include_spans`.
+    pub input_range: Option<Range<usize>>,
+}
+
+/// A function to call with every new match (see `Validator::on_match`).
+pub type MatchHook = Box<dyn FnMut(&MatchPath, &Value) + Send>;
+
+/// The matches a validator has found, pass by pass, and which of them have
+/// been handed out.
+#[derive(Default)]
+pub(crate) struct MatchJournal {
+    /// The paths of every match we've journaled. Later passes capture the
+    /// same values again, and they aren't new then.
+    journaled: HashSet<MatchPath>,
+    /// Matches that haven't been drained yet.
+    new_matches: Vec<NewMatch>,
+    hook: Option<MatchHook>,
+}
+
+impl MatchJournal {
+    pub fn set_hook(&mut self, hook: MatchHook) {
+        self.hook = Some(hook);
+    }
+
+    /// Journal the values of `matches` that we haven't yet, calling the hook
+    /// with each.
+    pub fn record(&mut self, matches: &Value) {
+        let mut new_matches = Vec::new();
+        collect_leaves(&MatchPath::default(), matches, &mut new_matches);
+        new_matches.retain(|new_match| self.journaled.insert(new_match.path.clone()));
+        if new_matches
+            .iter()
+            .all(|new_match| new_match.input_range.is_some())
+        {
+            new_matches
+                .sort_by_key(|new_match| new_match.input_range.as_ref().map(|range| range.start));
+        }
+
+        if let Some(hook) = self.hook.as_mut() {
+            for new_match in &new_matches {
+                hook(&new_match.path, &new_match.value);
+            }
+        }
+        self.new_matches.extend(new_matches);
+    }
+
+    /// Hand out the matches journaled since the last time.
+    pub fn drain(&mut self) -> std::vec::Drain<'_, NewMatch> {
+        self.new_matches.drain(..)
+    }
+
+    /// The paths journaled so far, and the matches not drained yet.
+    pub fn state(&self) -> (Vec<MatchPath>, Vec<NewMatch>) {
+        (
+            self.journaled.iter().cloned().collect(),
+            self.new_matches.clone(),
+        )
+    }
+
+    /// Carry on from the state of another journal, keeping our hook.
+    pub fn restore(&mut self, journaled: &[MatchPath], new_matches: &[NewMatch]) {
+        self.journaled = journaled.iter().cloned().collect();
+        self.new_matches = new_matches.to_vec();
+    }
+}
+
+impl fmt::Debug for MatchJournal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MatchJournal")
+            .field("journaled", &self.journaled.len())
+            .field("new_matches", &self.new_matches)
+            .finish()
+    }
+}
+
+/// The leaf values of the matches, with their paths, in order. A value with
+/// its span counts as a leaf.
+fn collect_leaves(path: &MatchPath, value: &Value, leaves: &mut Vec<NewMatch>) {
+    match value {
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                collect_leaves(&path.with(MatchPathSegment::Index(i)), value, leaves);
+            }
+        }
+        Value::Object(object) if span_byte_range(value).is_none() => {
+            for (key, value) in object {
+                collect_leaves(
+                    &path.with(MatchPathSegment::Key(key.clone())),
+                    value,
+                    leaves,
+                );
+            }
+        }
+        value => leaves.push(NewMatch {
+            path: path.clone(),
+            value: value.clone(),
+            input_range: span_byte_range(value),
+        }),
+    }
+}
+
+/// Every match, with its path, in order.
+fn match_updates(matches: &Value) -> Vec<MatchUpdate> {
+    let Some(matches) = matches.as_object() else {
+        return Vec::new();
+    };
+
+    let mut updates = Vec::new();
+    for (id, value) in matches {
+        match value {
+            Value::Array(values) => {
+                updates.extend(values.iter().enumerate().map(|(i, value)| MatchUpdate {
+                    path: format!("{}[{}]", id, i),
+                    value: value.clone(),
+                }))
+            }
+            value => updates.push(MatchUpdate {
+                path: id.clone(),
+                value: value.clone(),
+            }),
+        }
+    }
+    updates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(updates: &[MatchUpdate]) -> Vec<&str> {
+        updates.iter().map(|update| update.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_new_matches_are_reported_once() {
+        let mut stream = MatchStream::new();
+
+        let updates = stream.new_matches(&json!({"title": "Hi", "item": ["a", "b"]}));
+        assert_eq!(paths(&updates), vec!["title", "item[0]", "item[1]"]);
+        assert_eq!(updates[2].value, json!("b"));
+
+        let updates = stream.new_matches(&json!({"title": "Hi", "item": ["a", "b", "c"]}));
+        assert_eq!(paths(&updates), vec!["item[2]"]);
+        assert_eq!(
+            updates[0].to_json(),
+            json!({"event": "match", "path": "item[2]", "value": "c"})
+        );
+
+        assert_eq!(stream.new_matches(&json!({"title": "Hi"})), vec![]);
+    }
+
+    fn journaled_paths(journal: &mut MatchJournal) -> Vec<String> {
+        journal
+            .drain()
+            .map(|new_match| new_match.path.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_journal_paths_of_nested_matches() {
+        let mut journal = MatchJournal::default();
+
+        journal.record(&json!({
+            "title": "Hi",
+            "items": [{"name": "a", "inner": ["x", "y"]}, {"name": "b"}],
+        }));
+        assert_eq!(
+            journaled_paths(&mut journal),
+            vec![
+                "title",
+                "items[0].name",
+                "items[0].inner[0]",
+                "items[0].inner[1]",
+                "items[1].name"
+            ]
+        );
+
+        journal.record(&json!({
+            "title": "Hi",
+            "items": [{"name": "a", "inner": ["x", "y"]}, {"name": "b", "inner": ["z"]}],
+        }));
+        assert_eq!(journaled_paths(&mut journal), vec!["items[1].inner[0]"]);
+        assert_eq!(journaled_paths(&mut journal), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_journal_orders_by_input_with_spans() {
+        let span = |value: &str, start: usize| {
+            json!({
+                "value": value,
+                "start": {"line": 1, "col": start + 1, "byte": start},
+                "end": {"line": 1, "col": start + 2, "byte": start + 1},
+            })
+        };
+        let mut journal = MatchJournal::default();
+
+        journal.record(&json!({"a": [span("1", 0), span("3", 4)], "b": span("2", 2)}));
+        let new_matches: Vec<_> = journal.drain().collect();
+        assert_eq!(
+            new_matches
+                .iter()
+                .map(|new_match| new_match.path.to_string())
+                .collect::<Vec<_>>(),
+            vec!["a[0]", "b", "a[1]"]
+        );
+        assert_eq!(new_matches[1].input_range, Some(2..3));
+    }
+
+    #[test]
+    fn test_journal_calls_hook() {
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut journal = MatchJournal::default();
+        let hook_seen = seen.clone();
+        journal.set_hook(Box::new(move |path, value| {
+            hook_seen
+                .lock()
+                .unwrap()
+                .push(format!("{}={}", path, value));
+        }));
+
+        journal.record(&json!({"item": ["a"]}));
+        journal.record(&json!({"item": ["a", "b"]}));
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec!["item[0]=\"a\"", "item[1]=\"b\""]
+        );
+    }
+}
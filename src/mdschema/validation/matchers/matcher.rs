@@ -2,28 +2,149 @@
 
 use crate::{invariant_violation, mdschema::validation::matchers::matcher_extras::MatcherExtras};
 use core::fmt;
-use regex::Regex;
-use std::{collections::HashSet, sync::LazyLock};
+use regex::{Regex, RegexBuilder};
+use regex_automata::{Anchored, Input, hybrid};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    ops::Range,
+    sync::{Arc, LazyLock, OnceLock},
+};
 use tree_sitter::TreeCursor;
 
 use crate::mdschema::validation::{
+    errors::{NodeContentMismatchKind, SchemaViolationError},
     matchers::matcher_extras::{MatcherExtrasError, partition_at_special_chars},
+    matchers::named_patterns::NamedPattern,
     ts_types::*,
     ts_utils::{get_next_node, get_node_and_next_node, get_node_text},
 };
 
-static ID_PATTERN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9-_]+$").unwrap());
+/// An id, whose dots (unless escaped) separate the keys of the path its
+/// match is put under (see `match_paths`).
+const DOTTED_ID_PATTERN: &str = r"[a-zA-Z0-9-_]+(?:\\?\.[a-zA-Z0-9-_]+)*";
+
+static ID_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(&format!("^{}$", DOTTED_ID_PATTERN)).unwrap());
 
 static REGEX_MATCHER_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"^(?:(?P<id_with_regex>[a-zA-Z0-9-_]+):)?(?:\/(?P<regex>.+?)\/|(?P<bare_id>[a-zA-Z0-9-_]+))$").unwrap()
+    Regex::new(&(format!(r"^(?:(?P<id_with_regex>{})", DOTTED_ID_PATTERN) + r"(?::(?P<value_type>[a-z]+))?:)?(?:\/(?P<regex>(?:\\.|[^\\])+?)\/(?P<regex_flags>[a-zA-Z]*)|\[(?P<enum>[^\]]*)\]|@(?P<named>[a-zA-Z0-9-_]*)|(?P<bare_id>[a-zA-Z0-9-_]+))(?:\|(?P<message>[^`]+))?$")).unwrap()
+});
+
+/// An id, maybe with a value type, and a colon, with whatever is around and
+/// after it, like `name: /\w+/`.
+static NEAR_MISS_MATCHER_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<id>[a-zA-Z0-9-_]+(?::[a-z]+)?)(?P<before>\s*):(?P<after>\s*)(?P<rest>.*)$")
+        .unwrap()
 });
 
 static RANGE_PATTERN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{(\d*),(\d*)\}").unwrap());
 
 pub const LITERAL_INDICATOR: char = '!';
 
+/// Whether a code span is in double backticks, like ``` ``id:/\w+/`` ```.
+/// Those are always literal, so they can have text that looks like a
+/// matcher, and the text after them has no extras.
+pub fn is_always_literal_code(code_span_str: &str) -> bool {
+    code_span_str.starts_with("``")
+}
+
+/// Marks a matcher, or the schema block it is in, as optional, like
+/// `` `notes:/.*/`? ``.
+pub const OPTIONAL_INDICATOR: char = '?';
+
+/// Gives a matcher a value to capture when it is left out, like
+/// `` `env:/\w+/`=prod ``.
+pub const DEFAULT_INDICATOR: char = '=';
+
+/// Lets a matcher skip input before its match, like `` `id:/\d+/`~ ``.
+pub const UNANCHORED_INDICATOR: char = '~';
+
+/// Starts a back-reference to what a matcher captured earlier, like
+/// `` `=title` `` (see `back_references`).
+pub const BACK_REFERENCE_INDICATOR: char = '=';
+
+/// Starts a negated matcher, which only matches text its pattern doesn't,
+/// like `` `!:/TODO/` ``. It has no id, so it captures nothing.
+pub const NEGATED_MATCHER_PREFIX: &str = "!:";
+
+/// The pattern of a matcher that captures a whole section, like
+/// `` `description:section` ``.
+pub const SECTION_PATTERN: &str = "section";
+
+/// Lets a ruler matcher stand for one or more consecutive rulers, like
+/// `` `ruler`+ ``.
+pub const ONE_OR_MORE_INDICATOR: char = '+';
+
+/// A paragraph that is nothing but this matcher, like `` `ruler`? ``, stands
+/// for thematic breaks (`---`) in the input.
+pub const RULER_PATTERN: &str = "ruler";
+
+/// The default maximum size, in bytes, of a compiled matcher regex.
+pub const DEFAULT_REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// The default maximum size, in bytes, of the lazy DFA cache of a matcher
+/// regex.
+pub const DEFAULT_REGEX_DFA_SIZE_LIMIT: usize = 1 << 20;
+
+/// The most that `RegexLimits` may allow. Matchers built without limits, to
+/// look at rather than to match with, are compiled with these (see
+/// `Matcher::try_from_pattern_and_suffix_str`).
+const MAX_REGEX_SIZE_LIMIT: usize = 1 << 24;
+const MAX_REGEX_DFA_SIZE_LIMIT: usize = 1 << 24;
+
+/// Regex syntax that backtracking engines support but we can't.
+const UNSUPPORTED_REGEX_SYNTAX: [(&str, &str); 5] = [
+    ("(?=", "look-ahead"),
+    ("(?!", "negative look-ahead"),
+    ("(?<=", "look-behind"),
+    ("(?<!", "negative look-behind"),
+    ("(?>", "an atomic group"),
+];
+
+/// How complex the regexes of matchers may be.
+///
+/// Matchers are compiled with the `regex` crate, which guarantees matching in
+/// time linear to the input, so a pathological pattern like `/(a+)+$/` can't
+/// hang validation. A pattern can still compile to a huge program though,
+/// like `/(\w{1000}){1000}/`, which takes long to build and to run, so schemas
+/// with regexes bigger than `size_limit` are rejected when they are loaded.
+/// `dfa_size_limit` never rejects a regex, it only bounds how much memory
+/// matching with it may take.
+///
+/// Whatever the limits, no regex is compiled to more than 16 MiB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegexLimits {
+    /// The maximum size, in bytes, of a compiled regex.
+    pub size_limit: usize,
+    /// The maximum size, in bytes, of the cache a regex builds up while
+    /// matching. Regexes that need more fall back to slower matching, still
+    /// in linear time, rather than failing.
+    pub dfa_size_limit: usize,
+}
+
+impl Default for RegexLimits {
+    fn default() -> Self {
+        Self {
+            size_limit: DEFAULT_REGEX_SIZE_LIMIT,
+            dfa_size_limit: DEFAULT_REGEX_DFA_SIZE_LIMIT,
+        }
+    }
+}
+
+impl RegexLimits {
+    /// The most that limits may allow.
+    const MAX: RegexLimits = RegexLimits {
+        size_limit: MAX_REGEX_SIZE_LIMIT,
+        dfa_size_limit: MAX_REGEX_DFA_SIZE_LIMIT,
+    };
+}
+
 /// Errors specific to matcher construction.
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum MatcherError {
     /// The regex pattern for the interior of the matcher is invalid.
     MatcherInteriorRegexInvalid(String),
@@ -36,6 +157,23 @@ pub enum MatcherError {
     /// You tried to use a constructor meant for nodes but failed to meet an
     /// invariant of the kind of node or state of the cursor used.
     InvariantViolation(String),
+    /// The regex uses syntax that requires backtracking, like look-around.
+    UnsupportedRegexSyntax(String),
+    /// The regex compiles to more than `limit` bytes (see `RegexLimits`).
+    RegexTooComplex { limit: usize },
+    /// The text we were asked to match is longer than a single matcher may scan.
+    InputTooLong { len: usize, max_len: usize },
+    /// The matcher declares a value type we don't know, like the `integer` in
+    /// `` `age:integer:/\d+/` ``.
+    UnknownValueType(String),
+    /// The matcher uses a named pattern we don't know, like the `@dat` in
+    /// `` `released:@dat` ``, with the known name closest to it, if any.
+    UnknownNamedPattern {
+        name: String,
+        suggestion: Option<String>,
+    },
+    /// The regex has a flag we don't know, like the `g` in `` `name:/\w+/g` ``.
+    UnknownRegexFlag(char),
 }
 
 impl From<MatcherExtrasError> for MatcherError {
@@ -59,6 +197,42 @@ impl std::fmt::Display for MatcherError {
             MatcherError::InvariantViolation(err) => {
                 write!(f, "Invariant violation: {}", err)
             }
+            MatcherError::UnsupportedRegexSyntax(err) => {
+                write!(f, "Unsupported regex syntax: {}", err)
+            }
+            MatcherError::RegexTooComplex { limit } => {
+                write!(
+                    f,
+                    "Regex is too complex, since it compiles to more than {} bytes",
+                    limit
+                )
+            }
+            MatcherError::InputTooLong { len, max_len } => {
+                write!(
+                    f,
+                    "Matcher input is {} bytes long, but matchers may only scan {} bytes",
+                    len, max_len
+                )
+            }
+            MatcherError::UnknownValueType(value_type) => {
+                write!(
+                    f,
+                    "Unknown matcher type '{}' (expected one of int, float, bool or str)",
+                    value_type
+                )
+            }
+            MatcherError::UnknownNamedPattern { name, suggestion } => {
+                write!(f, "Unknown named pattern '@{}'", name)?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, ", did you mean '@{}'?", suggestion)?;
+                }
+                let known: Vec<String> =
+                    NamedPattern::ALL.iter().map(ToString::to_string).collect();
+                write!(f, " (known patterns are {})", known.join(", "))
+            }
+            MatcherError::UnknownRegexFlag(flag) => {
+                write!(f, "Unknown regex flag '{}' (expected i, m, s or x)", flag)
+            }
         }
     }
 }
@@ -94,23 +268,65 @@ pub struct Matcher {
     id: Option<String>,
     /// A compiled regex for the pattern.
     kind: MatcherKind,
+    /// The JSON type captures are output as.
+    value_type: MatcherValueType,
     /// Extra flags, which we receive via extra text that corresponds to the matcher
     flags: HashSet<MatcherFlags>,
     /// Extra configuration options
     extras: MatcherExtras,
     /// The length of the matcher and its original extras
     original_str_len: usize,
+    /// What to tell the user when the matcher doesn't match, instead of its
+    /// pattern.
+    message: Option<String>,
+    /// Whether the matcher only matches text its pattern doesn't (see
+    /// `NEGATED_MATCHER_PREFIX`).
+    negated: bool,
+    /// The lazy DFA for the matcher's regex, built the first time we check
+    /// whether more text could still match (see `could_match_with_more`), and
+    /// shared by the matcher's clones. `None` if it can't be built.
+    prefix_dfa: Arc<OnceLock<Option<hybrid::dfa::DFA>>>,
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum MatcherKind {
-    Regex(Regex),
+    /// A regex, like `/\d+/`.
+    Regex {
+        /// The regex, anchored to the start of the text unless the matcher is
+        /// unanchored.
+        regex: Regex,
+        /// The pattern as written in the schema, without the anchor.
+        pattern: String,
+        /// The flags after the pattern, like the `i` in `/alice/i`.
+        flags: RegexFlags,
+    },
+    /// Any one of a fixed set of literals, like `color:[red, green, blue]`.
+    Enum(Vec<String>),
+    /// A built-in pattern, like `@date`.
+    Named {
+        pattern: NamedPattern,
+        /// The pattern's regex, anchored like the regex of a `Regex` matcher.
+        regex: Regex,
+    },
     All,
+    /// Everything under the heading the matcher is under, as raw markdown,
+    /// like `` `description:section` ``. It is only special when it makes up a
+    /// whole paragraph; anywhere else it matches like `All`.
+    Section,
+    /// The text the matcher with this id captured earlier, like `` `=title` ``.
+    /// It matches like `All`, and what it matched is compared against the
+    /// capture once all of the matches are known (see `back_references`).
+    BackReference(String),
 }
 
 impl MatcherKind {
-    pub fn from_regex(regex: Regex) -> Self {
-        MatcherKind::Regex(regex)
+    pub fn from_regex(regex: Regex, pattern: &str) -> Self {
+        MatcherKind::Regex {
+            regex,
+            pattern: pattern.to_string(),
+            flags: RegexFlags::default(),
+        }
     }
 
     pub fn all() -> Self {
@@ -121,12 +337,179 @@ impl MatcherKind {
 impl fmt::Display for MatcherKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            MatcherKind::Regex(regex) => write!(f, "{}", regex.as_str()),
+            MatcherKind::Regex { pattern, flags, .. } => {
+                write!(f, "{}{}", flags.inline_group(), pattern)
+            }
+            MatcherKind::Enum(allowed) => write!(f, "[{}]", allowed.join(", ")),
+            MatcherKind::Named { pattern, .. } => write!(f, "{}", pattern),
             MatcherKind::All => write!(f, "all"),
+            MatcherKind::Section => write!(f, "section"),
+            MatcherKind::BackReference(id) => write!(f, "{}{}", BACK_REFERENCE_INDICATOR, id),
         }
     }
 }
 
+/// The JSON type a matcher outputs its captures as, like the `int` in
+/// `` `age:int:/\d+/` ``. Without a type, captures are output as strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MatcherValueType {
+    #[default]
+    Str,
+    Int,
+    Float,
+    Bool,
+}
+
+impl MatcherValueType {
+    /// The type a type tag in a matcher stands for, if any.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "str" => Some(MatcherValueType::Str),
+            "int" => Some(MatcherValueType::Int),
+            "float" => Some(MatcherValueType::Float),
+            "bool" => Some(MatcherValueType::Bool),
+            _ => None,
+        }
+    }
+
+    /// Convert captured text to this type, or `None` if it doesn't parse as
+    /// one.
+    pub fn convert(self, text: &str) -> Option<Value> {
+        match self {
+            MatcherValueType::Str => Some(Value::String(text.to_string())),
+            MatcherValueType::Int => text.parse::<i64>().ok().map(Value::from),
+            MatcherValueType::Float => text
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Value::Number),
+            MatcherValueType::Bool => text.parse::<bool>().ok().map(Value::Bool),
+        }
+    }
+}
+
+impl fmt::Display for MatcherValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatcherValueType::Str => write!(f, "str"),
+            MatcherValueType::Int => write!(f, "int"),
+            MatcherValueType::Float => write!(f, "float"),
+            MatcherValueType::Bool => write!(f, "bool"),
+        }
+    }
+}
+
+/// The flags a regex matcher can have after its closing `/`.
+pub const REGEX_FLAGS: &str = "imsx";
+
+/// The flags after the closing `/` of a regex matcher, like the `i` in
+/// `` `name:/alice/i` ``, which are the `regex` crate's flags of the same
+/// letters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RegexFlags {
+    /// `i`: Letters match both cases.
+    pub case_insensitive: bool,
+    /// `m`: `^` and `$` match at the start and end of every line.
+    pub multi_line: bool,
+    /// `s`: `.` matches newlines too.
+    pub dot_matches_new_line: bool,
+    /// `x`: Whitespace is ignored, and `#` starts a comment.
+    pub ignore_whitespace: bool,
+}
+
+impl RegexFlags {
+    /// Parse the flags after a regex, like `im`, rejecting the first letter
+    /// that isn't a flag.
+    pub fn parse(flags: &str) -> Result<Self, MatcherError> {
+        let mut parsed = Self::default();
+        for flag in flags.chars() {
+            match flag {
+                'i' => parsed.case_insensitive = true,
+                'm' => parsed.multi_line = true,
+                's' => parsed.dot_matches_new_line = true,
+                'x' => parsed.ignore_whitespace = true,
+                flag => return Err(MatcherError::UnknownRegexFlag(flag)),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// The flags as an inline group that turns them on, like `(?i)`, or
+    /// nothing if there are none.
+    pub fn inline_group(&self) -> String {
+        if *self == Self::default() {
+            String::new()
+        } else {
+            format!("(?{})", self)
+        }
+    }
+}
+
+impl fmt::Display for RegexFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [
+            self.case_insensitive,
+            self.multi_line,
+            self.dot_matches_new_line,
+            self.ignore_whitespace,
+        ];
+        for (flag, set) in REGEX_FLAGS.chars().zip(flags) {
+            if set {
+                write!(f, "{}", flag)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What a matcher matched, with the text of each capture group of its regex,
+/// owned so that it can outlive the text it was matched in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchCaptures {
+    /// The whole match.
+    pub full: String,
+    /// The text of each capture group, in order, or `None` for optional
+    /// groups that didn't take part in the match.
+    pub groups: Vec<Option<String>>,
+    /// The name of each capture group, in the same order, if it has one.
+    pub names: Vec<Option<String>>,
+}
+
+impl MatchCaptures {
+    /// The captures as JSON, with the text of each group converted to
+    /// `value_type`. Groups that didn't take part in the match are `null`.
+    ///
+    /// If any group is named, the named groups become keys next to the whole
+    /// match, like `{"full": "1.2", "major": "1", "minor": "2"}`. Otherwise
+    /// the groups are listed in order, like `{"full": "1.2", "groups": ["1",
+    /// "2"]}`.
+    ///
+    /// Returns `None` if the text of a group doesn't parse as `value_type`.
+    pub fn to_value(&self, value_type: MatcherValueType) -> Option<Value> {
+        let mut group_values = Vec::with_capacity(self.groups.len());
+        for group in &self.groups {
+            group_values.push(match group {
+                Some(text) => value_type.convert(text)?,
+                None => Value::Null,
+            });
+        }
+
+        let mut value = Map::new();
+        value.insert("full".to_string(), Value::String(self.full.clone()));
+        if self.names.iter().any(Option::is_some) {
+            for (name, group_value) in self.names.iter().zip(group_values) {
+                if let Some(name) = name {
+                    value.insert(name.clone(), group_value);
+                }
+            }
+        } else {
+            value.insert("groups".to_string(), Value::Array(group_values));
+        }
+        Some(Value::Object(value))
+    }
+}
+
 /// Special matcher types that extend the meaning of a group.
 ///
 /// This is the text that comes directly after the matcher codeblock. For
@@ -134,11 +517,34 @@ impl fmt::Display for MatcherKind {
 ///
 /// Make sure to update SPECIAL_CHARS_START when adding new flags.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum MatcherFlags {
     /// The {min,max} flag indicates that the matcher has a minimum and maximum number of items.
     MinMax,
 }
 
+/// The text of the matcher at a schema cursor pointing at a `code_span` node,
+/// the extras in the text node after it, if there are any, and the byte range
+/// of the schema that they span together.
+pub(crate) fn schema_cursor_matcher_text<'s>(
+    schema_cursor: &TreeCursor,
+    schema_str: &'s str,
+) -> (&'s str, Option<&'s str>, Range<usize>) {
+    let node = schema_cursor.node();
+    let pattern_str = get_node_text(&node, schema_str);
+    let extras = get_next_node(schema_cursor)
+        .filter(|n| is_text_node(n)) // don't bother if not text; extras must be in text
+        .and_then(|n| {
+            partition_at_special_chars(get_node_text(&n, schema_str))
+                .map(|(extras, _)| (extras, n.start_byte() + extras.len()))
+        });
+
+    match extras {
+        Some((extras_str, end)) => (pattern_str, Some(extras_str), node.start_byte()..end),
+        None => (pattern_str, None, node.byte_range()),
+    }
+}
+
 impl Matcher {
     /// Create a new `Matcher` with all required fields.
     pub fn new(
@@ -152,11 +558,33 @@ impl Matcher {
             id,
             flags,
             kind: r#type,
+            value_type: MatcherValueType::default(),
             extras,
             original_str_len,
+            message: None,
+            negated: false,
+            prefix_dfa: Arc::default(),
         }
     }
 
+    /// Output captures as `value_type` instead of as strings.
+    pub fn with_value_type(mut self, value_type: MatcherValueType) -> Self {
+        self.value_type = value_type;
+        self
+    }
+
+    /// Report `message` when the matcher doesn't match.
+    pub fn with_message(mut self, message: Option<String>) -> Self {
+        self.message = message;
+        self
+    }
+
+    /// Only match text the pattern doesn't match.
+    pub fn negated(mut self) -> Self {
+        self.negated = true;
+        self
+    }
+
     pub fn new_with_empty_flags(
         id: Option<String>,
         pattern: MatcherKind,
@@ -169,6 +597,10 @@ impl Matcher {
     /// Create a new Matcher given the text in a matcher codeblock and the text node's contents
     /// immediately proceeding the matcher.
     ///
+    /// The regex is compiled with the most that `RegexLimits` may allow, which
+    /// is fine for matchers we only look at. Use
+    /// `try_from_pattern_and_suffix_str_with_limits` for ones we match with.
+    ///
     /// # Arguments
     /// * `pattern` - The pattern string within the matcher codeblock.
     /// * `after_str` - Optional extras string following the pattern. This must have a sequence of valid matcher extras, only followed by additional text if there is a space in between.
@@ -176,7 +608,26 @@ impl Matcher {
         pattern_str: &str,
         after_str: Option<&str>,
     ) -> Result<Matcher, MatcherError> {
+        Self::try_from_pattern_and_suffix_str_with_limits(pattern_str, after_str, &RegexLimits::MAX)
+    }
+
+    /// Like `try_from_pattern_and_suffix_str`, but with the regex compiled
+    /// within `limits`, so that matching with it stays within them too.
+    pub fn try_from_pattern_and_suffix_str_with_limits(
+        pattern_str: &str,
+        after_str: Option<&str>,
+        limits: &RegexLimits,
+    ) -> Result<Matcher, MatcherError> {
+        if is_always_literal_code(pattern_str) {
+            return Err(MatcherError::WasLiteralCode);
+        }
+
         let pattern_str = pattern_str[1..pattern_str.len() - 1].trim(); // Remove surrounding backticks
+        let original_pattern_len = pattern_str.len();
+        let (pattern_str, negated) = match pattern_str.strip_prefix(NEGATED_MATCHER_PREFIX) {
+            Some(pattern_str) => (pattern_str, true),
+            None => (pattern_str, false),
+        };
         let captures = REGEX_MATCHER_PATTERN.captures(pattern_str);
 
         let extras = MatcherExtras::try_from_post_matcher_str(after_str)?;
@@ -187,8 +638,31 @@ impl Matcher {
             return Err(MatcherError::WasLiteralCode);
         }
 
-        let (id, pattern) = match captures {
-            Some(caps) => extract_id_and_pattern(&caps, pattern_str)?,
+        let original_str_len = original_pattern_len + after_str.map_or(0, |s| s.len());
+
+        if let Some(id) = pattern_str.strip_prefix(BACK_REFERENCE_INDICATOR)
+            && !negated
+            && ID_PATTERN.is_match(id)
+        {
+            return Ok(Self::new_with_empty_flags(
+                None,
+                MatcherKind::BackReference(id.to_string()),
+                extras,
+                original_str_len,
+            ));
+        }
+
+        let (id, pattern, value_type, message) = match captures {
+            Some(caps) => {
+                let (id, pattern) =
+                    extract_id_and_pattern(&caps, pattern_str, extras.is_unanchored(), limits)?;
+                (
+                    id,
+                    pattern,
+                    extract_value_type(&caps)?,
+                    extract_message(&caps),
+                )
+            }
             None => {
                 return Err(MatcherError::MatcherInteriorRegexInvalid(format!(
                     "Expected format: 'id:/regex/' or 'id', got {}",
@@ -197,14 +671,25 @@ impl Matcher {
             }
         };
 
-        let original_str_len = pattern_str.len() + after_str.map_or(0, |s| s.len());
+        let matcher = Self::new_with_empty_flags(id, pattern, extras, original_str_len)
+            .with_value_type(value_type)
+            .with_message(message);
+        if !negated {
+            return Ok(matcher);
+        }
 
-        Ok(Self::new_with_empty_flags(
-            id,
-            pattern,
-            extras,
-            original_str_len,
-        ))
+        // Negating everything, or a bare id, matches nothing
+        match matcher.kind {
+            MatcherKind::Regex { .. } | MatcherKind::Enum(_) | MatcherKind::Named { .. }
+                if matcher.id.is_none() =>
+            {
+                Ok(matcher.negated())
+            }
+            _ => Err(MatcherError::MatcherInteriorRegexInvalid(format!(
+                "Expected format: '{}/regex/', got {}{}",
+                NEGATED_MATCHER_PREFIX, NEGATED_MATCHER_PREFIX, pattern_str
+            ))),
+        }
     }
 
     /// Given a schema cursor pointing at a `code_span` node, attempt to extract a new `Matcher`.
@@ -224,30 +709,177 @@ impl Matcher {
     pub fn try_from_schema_cursor(
         schema_cursor: &TreeCursor,
         schema_str: &str,
+    ) -> Result<Self, MatcherError> {
+        Self::try_from_schema_cursor_with_limits(schema_cursor, schema_str, &RegexLimits::MAX)
+    }
+
+    /// Like `try_from_schema_cursor`, but with the regex compiled within
+    /// `limits` (see `try_from_pattern_and_suffix_str_with_limits`).
+    pub fn try_from_schema_cursor_with_limits(
+        schema_cursor: &TreeCursor,
+        schema_str: &str,
+        limits: &RegexLimits,
     ) -> Result<Self, MatcherError> {
         // #[cfg(feature = "invariant_violations")]
         // if !is_inline_code_node(&schema_cursor.node()) {
         //     invariant_violation!("expected inline code node for extracting a matcher");
         // }
 
-        let pattern_str = get_node_text(&schema_cursor.node(), schema_str);
-        let next_node = get_next_node(schema_cursor);
-        let extras_str = next_node
-            .filter(|n| is_text_node(n)) // don't bother if not text; extras must be in text
-            .map(|n| get_node_text(&n, schema_str))
-            .and_then(|n| partition_at_special_chars(n).map(|(extras, _)| extras));
-
-        Self::try_from_pattern_and_suffix_str(pattern_str, extras_str)
+        let (pattern_str, extras_str, _) = schema_cursor_matcher_text(schema_cursor, schema_str);
+        Self::try_from_pattern_and_suffix_str_with_limits(pattern_str, extras_str, limits)
     }
 
     /// Get an actual match string for a given text, if it matches.
+    ///
+    /// The match is at the start of the text, unless the matcher is
+    /// unanchored, in which case it is the first match anywhere in the text.
+    /// A negated matcher matches all of the text, if its pattern doesn't
+    /// match it that way.
     pub fn match_str<'a>(&self, text: &'a str) -> Option<&'a str> {
+        match self.pattern_match_str(text) {
+            Some(_) if self.negated => None,
+            None if self.negated => Some(text),
+            matched => matched,
+        }
+    }
+
+    /// The match of the matcher's pattern, whether or not it is negated.
+    fn pattern_match_str<'a>(&self, text: &'a str) -> Option<&'a str> {
         match &self.kind {
-            MatcherKind::Regex(regex) => {
+            MatcherKind::Regex { regex, .. } => {
                 let mat = regex.find(text)?;
                 Some(&text[mat.start()..mat.end()])
             }
-            MatcherKind::All => Some(text),
+            // Unanchored, a match the pattern doesn't accept may be followed by
+            // one it does
+            MatcherKind::Named { pattern, regex } => regex
+                .find_iter(text)
+                .map(|mat| mat.as_str())
+                .find(|matched| pattern.accepts(matched)),
+            // The literal that starts first, and the longest of those
+            MatcherKind::Enum(allowed) if self.is_unanchored() => allowed
+                .iter()
+                .filter_map(|literal| text.find(literal.as_str()).map(|start| (start, literal)))
+                .min_by_key(|(start, literal)| (*start, Reverse(literal.len())))
+                .map(|(start, literal)| &text[start..start + literal.len()]),
+            // Like regexes, the text only has to start with the literal. We
+            // prefer the longest one, so `[Go, Golang]` captures all of "Golang".
+            MatcherKind::Enum(allowed) => allowed
+                .iter()
+                .filter(|literal| text.starts_with(literal.as_str()))
+                .max_by_key(|literal| literal.len())
+                .map(|literal| &text[..literal.len()]),
+            MatcherKind::All | MatcherKind::Section | MatcherKind::BackReference(_) => Some(text),
+        }
+    }
+
+    /// Like `match_str`, but with the text of each capture group of the
+    /// matcher's regex too. Only regex matchers that aren't negated have
+    /// capture groups.
+    pub fn match_captures(&self, text: &str) -> Option<MatchCaptures> {
+        let regex = match &self.kind {
+            MatcherKind::Regex { regex, .. } if !self.negated => regex,
+            _ => {
+                return self.match_str(text).map(|matched| MatchCaptures {
+                    full: matched.to_string(),
+                    groups: Vec::new(),
+                    names: Vec::new(),
+                });
+            }
+        };
+
+        let captures = regex.captures(text)?;
+        Some(MatchCaptures {
+            full: captures[0].to_string(),
+            groups: captures
+                .iter()
+                .skip(1)
+                .map(|group| group.map(|group| group.as_str().to_string()))
+                .collect(),
+            names: regex
+                .capture_names()
+                .skip(1)
+                .map(|name| name.map(str::to_string))
+                .collect(),
+        })
+    }
+
+    /// Whether the matcher's regex has capture groups, so that it captures
+    /// structured values (see `MatchCaptures::to_value`).
+    pub fn has_capture_groups(&self) -> bool {
+        !self.negated
+            && matches!(&self.kind, MatcherKind::Regex { regex, .. } if regex.captures_len() > 1)
+    }
+
+    /// Get an actual match string for a given text, refusing to scan text
+    /// longer than `max_input_len` bytes.
+    ///
+    /// Matching is linear in the length of the text, so this bounds how much
+    /// work a single matcher can do on adversarial input.
+    pub fn try_match_str<'a>(
+        &self,
+        text: &'a str,
+        max_input_len: usize,
+    ) -> Result<Option<&'a str>, MatcherError> {
+        if text.len() > max_input_len {
+            return Err(MatcherError::InputTooLong {
+                len: text.len(),
+                max_len: max_input_len,
+            });
+        }
+
+        Ok(self.match_str(text))
+    }
+
+    /// Whether the matcher could still match at the start of `text` once
+    /// more text comes after it, for text it doesn't match yet.
+    ///
+    /// This is false once a character makes a match impossible, like the `x`
+    /// of `1.x` for `/\d+\.\d+/`. Where we can't tell, like for an unanchored
+    /// or negated matcher, or a regex the lazy DFA gives up on, it is true.
+    pub fn could_match_with_more(&self, text: &str) -> bool {
+        if self.negated || self.is_unanchored() {
+            return true;
+        }
+
+        let (regex, flags) = match &self.kind {
+            MatcherKind::Regex { regex, flags, .. } => (regex, *flags),
+            MatcherKind::Named { regex, .. } => (regex, RegexFlags::default()),
+            MatcherKind::Enum(allowed) => {
+                return allowed.iter().any(|literal| {
+                    literal.starts_with(text) || text.starts_with(literal.as_str())
+                });
+            }
+            MatcherKind::All | MatcherKind::Section | MatcherKind::BackReference(_) => {
+                return true;
+            }
+        };
+
+        let dfa = self
+            .prefix_dfa
+            .get_or_init(|| build_prefix_dfa(regex, flags));
+        match dfa {
+            Some(dfa) => dfa_could_match_with_more(dfa, text),
+            None => true,
+        }
+    }
+
+    /// Check that the matcher's regex, if it has one, compiles within
+    /// `limits`.
+    ///
+    /// A validator checks its schema's matchers with this when it loads it,
+    /// to report a regex that is too complex once rather than every time the
+    /// matcher is built.
+    pub fn check_regex_limits(&self, limits: &RegexLimits) -> Result<(), MatcherError> {
+        match &self.kind {
+            MatcherKind::Regex { regex, flags, .. } => {
+                build_regex_with_flags(regex.as_str(), *flags, limits).map(|_| ())
+            }
+            MatcherKind::Named { regex, .. } => build_regex(regex.as_str(), limits).map(|_| ()),
+            MatcherKind::Enum(_)
+            | MatcherKind::All
+            | MatcherKind::Section
+            | MatcherKind::BackReference(_) => Ok(()),
         }
     }
 
@@ -256,17 +888,59 @@ impl Matcher {
         self.extras().had_min_max()
     }
 
+    /// Whether the matcher, or the schema block it is in, may be left out of
+    /// the input.
+    pub fn is_optional(&self) -> bool {
+        self.extras().is_optional()
+    }
+
+    /// What the matcher captures when it is left out, like `prod` for
+    /// `` `env:/\w+/`=prod ``.
+    pub fn default_value(&self) -> Option<&str> {
+        self.extras().default()
+    }
+
+    /// Whether the matcher searches for its first match, instead of having to
+    /// match at the start of the text, like `` `id:/\d+/`~ ``.
+    pub fn is_unanchored(&self) -> bool {
+        self.extras().is_unanchored()
+    }
+
+    /// Whether the matcher only matches text its pattern doesn't, like
+    /// `` `!:/TODO/` ``.
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
     /// The ID of the matcher. This is the key in the final JSON.
     pub fn id(&self) -> Option<&str> {
         self.id.as_deref()
     }
 
+    /// The id a back-reference, like `` `=title` ``, refers to.
+    pub fn back_reference(&self) -> Option<&str> {
+        match &self.kind {
+            MatcherKind::BackReference(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The message to report when the matcher doesn't match, like `Use a
+    /// date` for `` `date:@date|Use a date` ``.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
     /// Get a reference to the extras
     pub fn extras(&self) -> &MatcherExtras {
         &self.extras
     }
 
-    /// Get a reference to the pattern
+    /// Get a reference to the pattern, which displays as written in the schema.
+    ///
+    /// Matchers are anchored: unless the matcher is unanchored (`~`), the
+    /// pattern has to match right at the start of the text it's given, as if
+    /// it started with `^`. The anchor isn't part of the displayed pattern.
     pub fn pattern(&self) -> &MatcherKind {
         &self.kind
     }
@@ -294,29 +968,230 @@ impl Matcher {
     pub fn kind(&self) -> &MatcherKind {
         &self.kind
     }
+
+    /// The JSON type the matcher outputs its captures as.
+    pub fn value_type(&self) -> MatcherValueType {
+        self.value_type
+    }
+
+    /// Convert a capture to the matcher's value type.
+    ///
+    /// If the matcher's regex has capture groups, the captured text is matched
+    /// again to split it into them (see `MatchCaptures::to_value`), unless it
+    /// no longer matches on its own, like a default value.
+    ///
+    /// Captures reported with their location are converted in place, keeping
+    /// the location. Returns `None` if the captured text doesn't parse as the
+    /// type.
+    pub fn typed_value(&self, value: Value) -> Option<Value> {
+        match value {
+            Value::String(text) => self.text_value(&text),
+            Value::Object(mut spanned) => {
+                let typed = self.text_value(spanned.get("value")?.as_str()?)?;
+                spanned.insert("value".to_string(), typed);
+                Some(Value::Object(spanned))
+            }
+            value => Some(value),
+        }
+    }
+
+    /// The value to output for captured text.
+    fn text_value(&self, text: &str) -> Option<Value> {
+        match self.match_captures(text) {
+            Some(captures) if self.has_capture_groups() => captures.to_value(self.value_type),
+            _ => self.value_type.convert(text),
+        }
+    }
+
+    /// The error to report when a capture of this matcher doesn't parse as
+    /// its value type.
+    pub fn value_type_error(
+        &self,
+        schema_index: usize,
+        input_index: usize,
+        actual: String,
+    ) -> SchemaViolationError {
+        SchemaViolationError::CapturedValueTypeMismatch {
+            schema_index,
+            input_index,
+            expected: self.value_type.to_string(),
+            actual,
+        }
+    }
+
+    /// The error to report when this matcher doesn't match some input.
+    ///
+    /// A matcher with a message reports it, with its pattern as the expected
+    /// content. Otherwise negated matchers report the pattern the text
+    /// mustn't match, enum matchers get a dedicated error listing the
+    /// allowed values, and named patterns say what they expected rather than
+    /// showing their regex, since the pattern alone makes for a confusing
+    /// message.
+    pub fn mismatch_error(
+        &self,
+        schema_index: usize,
+        input_index: usize,
+        actual: String,
+    ) -> SchemaViolationError {
+        if let Some(message) = &self.message {
+            return SchemaViolationError::NodeContentMismatch {
+                schema_index,
+                input_index,
+                expected: self.kind.to_string(),
+                actual,
+                kind: NodeContentMismatchKind::MatcherMessage(message.clone()),
+            };
+        }
+
+        match &self.kind {
+            _ if self.negated => SchemaViolationError::NodeContentMismatch {
+                schema_index,
+                input_index,
+                expected: self.kind.to_string(),
+                actual,
+                kind: NodeContentMismatchKind::NegatedMatcher,
+            },
+            MatcherKind::Enum(allowed) => SchemaViolationError::EnumMismatch {
+                schema_index,
+                input_index,
+                allowed: allowed.clone(),
+                actual,
+            },
+            MatcherKind::Named { pattern, .. } => SchemaViolationError::NodeContentMismatch {
+                schema_index,
+                input_index,
+                expected: pattern.description().to_string(),
+                actual,
+                kind: NodeContentMismatchKind::NamedPattern,
+            },
+            _ => SchemaViolationError::NodeContentMismatch {
+                schema_index,
+                input_index,
+                expected: self.kind.to_string(),
+                actual,
+                kind: NodeContentMismatchKind::Matcher,
+            },
+        }
+    }
 }
 
 impl PartialEq for Matcher {
     fn eq(&self, other: &Self) -> bool {
-        self.id == other.id && format!("{}", self.kind) == format!("{}", other.kind)
+        self.id == other.id
+            && self.negated == other.negated
+            && format!("{}", self.kind) == format!("{}", other.kind)
     }
 }
 
+/// Whether the text of a code span, backticks included, has the shape of a
+/// matcher like `` `id:/regex/` `` or `` `!:/regex/` ``, whether or not the
+/// matcher is valid.
+pub fn has_matcher_syntax(pattern_str: &str) -> bool {
+    let text = pattern_str.trim_matches('`').trim();
+    REGEX_MATCHER_PATTERN.is_match(text.strip_prefix(NEGATED_MATCHER_PREFIX).unwrap_or(text))
+}
+
+/// What looks wrong with the text of a code span, backticks included, that is
+/// almost a regex matcher, like `` `name:/\w+` `` or `` `name: /\w+/` ``, and
+/// the matcher it was probably meant to be.
+///
+/// Returns `None` for code spans with the shape of a matcher, and for ones that
+/// don't look like attempts at one, like `` `Note: see below` ``.
+pub fn near_miss_matcher(pattern_str: &str) -> Option<(&'static str, String)> {
+    let text = pattern_str.trim_matches('`').trim();
+    if has_matcher_syntax(text) {
+        return None;
+    }
+
+    let captures = NEAR_MISS_MATCHER_PATTERN.captures(text)?;
+    let id = &captures["id"];
+    let rest = &captures["rest"];
+    let is_regex = rest.len() > 2 && rest.starts_with('/') && rest.ends_with('/');
+    // Not `//`, since that's more likely a URL, like `https://example.com`
+    let is_opened = rest.starts_with('/') && !rest.starts_with("//");
+
+    let hint = match (captures["before"].is_empty(), captures["after"].is_empty()) {
+        (true, true) if is_opened && !rest.ends_with('/') => {
+            return Some(("missing closing '/'", format!("{}:{}/", id, rest)));
+        }
+        (true, true) if !rest.starts_with('/') && rest.ends_with('/') => {
+            return Some(("missing opening '/'", format!("{}:/{}", id, rest)));
+        }
+        (true, true) => return None,
+        _ if !is_regex => return None,
+        (true, false) => "remove the space after ':'",
+        (false, true) => "remove the space before ':'",
+        (false, false) => "remove the spaces around ':'",
+    };
+    Some((hint, format!("{}:{}", id, rest)))
+}
+
 /// Extract the ID and pattern from the regex captures.
+///
+/// Regexes are anchored to the start of the text, unless `unanchored`, and
+/// compiled within `limits`.
 fn extract_id_and_pattern(
     captures: &regex::Captures,
     pattern: &str,
+    unanchored: bool,
+    limits: &RegexLimits,
 ) -> Result<(Option<String>, MatcherKind), MatcherError> {
+    let id = captures
+        .name("id_with_regex")
+        .map(|m| m.as_str().to_string());
+
+    // Check if we have a section capture (e.g., `description:section`)
+    if let Some(bare_id) = captures.name("bare_id")
+        && id.is_some()
+        && bare_id.as_str() == SECTION_PATTERN
+    {
+        return Ok((id, MatcherKind::Section));
+    }
+
     // Check if we have a bare ID (e.g., `word`)
     if let Some(bare_id) = captures.name("bare_id") {
         let id = bare_id.as_str().to_string();
         return Ok((Some(id), MatcherKind::all()));
     }
 
+    // Check if we have an enum (e.g., `id:[a, b, c]`)
+    if let Some(enum_values) = captures.name("enum") {
+        let allowed: Vec<String> = enum_values
+            .as_str()
+            .split(',')
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .collect();
+
+        if allowed.is_empty() {
+            return Err(MatcherError::MatcherInteriorRegexInvalid(format!(
+                "Expected at least one value in enum matcher, got {}",
+                pattern
+            )));
+        }
+
+        return Ok((id, MatcherKind::Enum(allowed)));
+    }
+
+    // Check if we have a named pattern (e.g., `id:@date`)
+    if let Some(name) = captures.name("named") {
+        let name = name.as_str();
+        let pattern =
+            NamedPattern::from_name(name).ok_or_else(|| MatcherError::UnknownNamedPattern {
+                name: name.to_string(),
+                suggestion: NamedPattern::suggest(name).map(str::to_string),
+            })?;
+        let regex = compile_matcher_regex(
+            pattern.regex_pattern(),
+            RegexFlags::default(),
+            unanchored,
+            limits,
+        )?;
+
+        return Ok((id, MatcherKind::Named { pattern, regex }));
+    }
+
     // Otherwise, we have a regex pattern (e.g., `id:/regex/` or `/regex/`)
-    let id = captures
-        .name("id_with_regex")
-        .map(|m| m.as_str().to_string());
     let regex_pattern = captures
         .name("regex")
         .map(|m| m.as_str().to_string())
@@ -327,37 +1202,210 @@ fn extract_id_and_pattern(
             ))
         })?;
 
+    let flags = RegexFlags::parse(captures.name("regex_flags").map_or("", |m| m.as_str()))?;
+
+    reject_unsupported_regex_syntax(&regex_pattern)?;
+
     // Create a regex matcher from the pattern
-    let matcher =
-        MatcherKind::from_regex(Regex::new(&format!("^{}", regex_pattern)).map_err(|e| {
-            MatcherError::MatcherInteriorRegexInvalid(format!("Invalid regex pattern: {}", e))
-        })?);
+    let matcher = MatcherKind::Regex {
+        regex: compile_matcher_regex(&regex_pattern, flags, unanchored, limits)?,
+        pattern: regex_pattern,
+        flags,
+    };
 
     Ok((id, matcher))
 }
 
+/// Compile the regex of a matcher with its flags within `limits`, anchored to
+/// the start of the text unless `unanchored`.
+fn compile_matcher_regex(
+    regex_pattern: &str,
+    flags: RegexFlags,
+    unanchored: bool,
+    limits: &RegexLimits,
+) -> Result<Regex, MatcherError> {
+    // The group keeps the anchor on every alternative, like in `/a|b/`. `\A`
+    // only matches at the start of the text, even with the `m` flag, and a
+    // comment allowed by the `x` flag runs to the end of its line, so the
+    // group is closed on a line of its own then.
+    let anchored_pattern = match (unanchored, flags.ignore_whitespace) {
+        (true, _) => regex_pattern.to_string(),
+        (false, false) => format!("\\A(?:{})", regex_pattern),
+        (false, true) => format!("\\A(?:{}\n)", regex_pattern),
+    };
+
+    build_regex_with_flags(&anchored_pattern, flags, limits)
+}
+
+/// Build a regex within `limits`.
+pub(crate) fn build_regex(pattern: &str, limits: &RegexLimits) -> Result<Regex, MatcherError> {
+    build_regex_with_flags(pattern, RegexFlags::default(), limits)
+}
+
+/// Build a regex with the flags of a matcher within `limits`.
+fn build_regex_with_flags(
+    pattern: &str,
+    flags: RegexFlags,
+    limits: &RegexLimits,
+) -> Result<Regex, MatcherError> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(flags.case_insensitive)
+        .multi_line(flags.multi_line)
+        .dot_matches_new_line(flags.dot_matches_new_line)
+        .ignore_whitespace(flags.ignore_whitespace)
+        .size_limit(limits.size_limit)
+        .dfa_size_limit(limits.dfa_size_limit)
+        .build()
+        .map_err(|e| match e {
+            regex::Error::CompiledTooBig(limit) => MatcherError::RegexTooComplex { limit },
+            e => MatcherError::MatcherInteriorRegexInvalid(format!("Invalid regex pattern: {}", e)),
+        })
+}
+
+/// A lazy DFA for an anchored regex, to tell whether it could still match
+/// text with more text after it (see `dfa_could_match_with_more`).
+///
+/// The DFA is built for the regex's own pattern, so `\A` and the like keep
+/// their meaning, with the flags turned on inline. `None` if it can't be built.
+fn build_prefix_dfa(regex: &Regex, flags: RegexFlags) -> Option<hybrid::dfa::DFA> {
+    let pattern = format!("{}{}", flags.inline_group(), regex.as_str());
+    hybrid::dfa::DFA::new(&pattern).ok()
+}
+
+/// Whether the regex of a lazy DFA could match at the start of `text` with
+/// more text after it, which is whether the DFA is still alive after reading
+/// all of `text`.
+///
+/// If the DFA gives up part way, like at a non-ASCII byte after `\b`, we
+/// assume it could.
+fn dfa_could_match_with_more(dfa: &hybrid::dfa::DFA, text: &str) -> bool {
+    // The states the DFA finds are only kept for this check
+    let mut cache = dfa.create_cache();
+
+    let input = Input::new(text).anchored(Anchored::Yes);
+    let Ok(mut state) = dfa.start_state_forward(&mut cache, &input) else {
+        return true;
+    };
+    for &byte in text.as_bytes() {
+        state = match dfa.next_state(&mut cache, state, byte) {
+            Ok(state) if state.is_dead() => return false,
+            Ok(state) if state.is_quit() => return true,
+            Ok(state) => state,
+            Err(_) => return true,
+        };
+    }
+
+    true
+}
+
+/// The value type declared between the id and the pattern, like the `int` in
+/// `` `age:int:/\d+/` ``.
+fn extract_value_type(captures: &regex::Captures) -> Result<MatcherValueType, MatcherError> {
+    match captures.name("value_type") {
+        Some(tag) => MatcherValueType::from_tag(tag.as_str())
+            .ok_or_else(|| MatcherError::UnknownValueType(tag.as_str().to_string())),
+        None => Ok(MatcherValueType::default()),
+    }
+}
+
+/// The message after the pattern, like the `Use a date` in
+/// `` `date:@date|Use a date` ``.
+fn extract_message(captures: &regex::Captures) -> Option<String> {
+    captures
+        .name("message")
+        .map(|message| message.as_str().trim().to_string())
+        .filter(|message| !message.is_empty())
+}
+
+/// Reject regex syntax that only backtracking engines support.
+///
+/// The `regex` crate would reject these too, but with an error message that
+/// doesn't explain why.
+fn reject_unsupported_regex_syntax(regex_pattern: &str) -> Result<(), MatcherError> {
+    for (syntax, name) in UNSUPPORTED_REGEX_SYNTAX {
+        if regex_pattern.contains(syntax) {
+            return Err(MatcherError::UnsupportedRegexSyntax(format!(
+                "{} (`{}`) is not supported, since matchers must run in linear time",
+                name, syntax
+            )));
+        }
+    }
+
+    if let Some(reference) = find_back_reference(regex_pattern) {
+        return Err(MatcherError::UnsupportedRegexSyntax(format!(
+            "a back-reference (`{}`) is not supported, since matchers must run in linear time",
+            reference
+        )));
+    }
+
+    Ok(())
+}
+
+/// The first back-reference in a regex, like `\1` or `\k<name>`.
+fn find_back_reference(regex_pattern: &str) -> Option<&str> {
+    let mut chars = regex_pattern.char_indices();
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            continue;
+        }
+        // Anything escaped is skipped, so `\\1` is a backslash and a 1
+        match chars.next() {
+            Some((_, '1'..='9')) => return Some(&regex_pattern[start..start + 2]),
+            Some((_, 'k')) => {
+                let end = regex_pattern[start..]
+                    .find('>')
+                    .map_or(regex_pattern.len(), |end| start + end + 1);
+                return Some(&regex_pattern[start..end]);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
 impl fmt::Display for Matcher {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            write!(f, "{}", NEGATED_MATCHER_PREFIX)?;
+        }
         match &self.kind {
-            MatcherKind::Regex(regex) => {
-                let regex_str = regex.as_str();
-                // The regex is stored as "^<pattern>", so remove the leading ^
-                let pattern_str = regex_str.strip_prefix('^').unwrap_or(regex_str);
-
-                match &self.id {
-                    Some(id) => write!(f, "{}:/{}/", id, pattern_str),
-                    None => write!(f, "/{}/", pattern_str),
+            MatcherKind::Regex { pattern, flags, .. } => {
+                let pattern_str = pattern.as_str();
+
+                match (&self.id, self.value_type) {
+                    (Some(id), MatcherValueType::Str) => {
+                        write!(f, "{}:/{}/{}", id, pattern_str, flags)
+                    }
+                    (Some(id), value_type) => {
+                        write!(f, "{}:{}:/{}/{}", id, value_type, pattern_str, flags)
+                    }
+                    (None, _) => write!(f, "/{}/{}", pattern_str, flags),
                 }
             }
+            MatcherKind::Enum(_) => match &self.id {
+                Some(id) => write!(f, "{}:{}", id, self.kind),
+                None => write!(f, "{}", self.kind),
+            },
+            MatcherKind::Named { .. } => match (&self.id, self.value_type) {
+                (Some(id), MatcherValueType::Str) => write!(f, "{}:{}", id, self.kind),
+                (Some(id), value_type) => write!(f, "{}:{}:{}", id, value_type, self.kind),
+                (None, _) => write!(f, "{}", self.kind),
+            },
             MatcherKind::All => match &self.id {
                 Some(id) => write!(f, "{}:/all/", id),
                 None => write!(f, "/all/"),
             },
+            MatcherKind::Section => match &self.id {
+                Some(id) => write!(f, "{}:section", id),
+                None => write!(f, "section"),
+            },
+            MatcherKind::BackReference(_) => write!(f, "{}", self.kind),
         }
     }
 }
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ExtractorError {
     MatcherError(MatcherError),
     UTF8Error(std::str::Utf8Error),
@@ -418,12 +1466,15 @@ pub fn extract_text_matcher(cursor: &TreeCursor, str: &str) -> Result<Matcher, E
 #[cfg(test)]
 mod tests {
     use crate::mdschema::validation::{
+        errors::{NodeContentMismatchKind, SchemaViolationError},
         matchers::matcher::{
-            Matcher, MatcherError, MatcherExtrasError, MatcherKind, extract_text_matcher,
+            MatchCaptures, Matcher, MatcherError, MatcherExtrasError, MatcherKind,
+            MatcherValueType, RegexLimits, extract_text_matcher, near_miss_matcher,
             partition_at_special_chars,
         },
         ts_utils::{new_markdown_parser, parse_markdown},
     };
+    use serde_json::json;
 
     #[test]
     fn test_matcher_creation_and_matching_all() {
@@ -439,6 +1490,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_back_reference_matcher() {
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`=title`", None).unwrap();
+        assert!(matches!(&matcher.kind, MatcherKind::BackReference(id) if id == "title"));
+        assert_eq!(matcher.id(), None);
+        assert_eq!(matcher.back_reference(), Some("title"));
+        assert_eq!(matcher.to_string(), "=title");
+        assert_eq!(matcher.match_str("Hello world"), Some("Hello world"));
+
+        assert!(Matcher::try_from_pattern_and_suffix_str("`=`", None).is_err());
+        assert!(Matcher::try_from_pattern_and_suffix_str("`= title`", None).is_err());
+    }
+
+    #[test]
+    fn test_negated_matcher() {
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`!:/TODO/`", None).unwrap();
+        assert!(matcher.is_negated());
+        assert_eq!(matcher.id(), None);
+        assert_eq!(matcher.to_string(), "!:/TODO/");
+        assert_eq!(matcher.match_str("Done"), Some("Done"));
+        assert_eq!(matcher.match_str("TODO: write this"), None);
+        // Anchored like any matcher, unless it is unanchored
+        assert_eq!(matcher.match_str("Not TODO"), Some("Not TODO"));
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`!:/TODO/`", Some("~")).unwrap();
+        assert_eq!(matcher.match_str("Not TODO"), None);
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`!:/(\\d+)/`", None).unwrap();
+        assert!(!matcher.has_capture_groups());
+        assert_eq!(
+            matcher.mismatch_error(1, 2, "12".to_string()),
+            SchemaViolationError::NodeContentMismatch {
+                schema_index: 1,
+                input_index: 2,
+                expected: "(\\d+)".to_string(),
+                actual: "12".to_string(),
+                kind: NodeContentMismatchKind::NegatedMatcher,
+            }
+        );
+
+        // Negated matchers capture nothing, so they have no id
+        assert!(Matcher::try_from_pattern_and_suffix_str("`!:id:/TODO/`", None).is_err());
+        assert!(Matcher::try_from_pattern_and_suffix_str("`!:word`", None).is_err());
+    }
+
+    #[test]
+    fn test_section_matcher() {
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`description:section`", None).unwrap();
+        assert!(matches!(matcher.kind, MatcherKind::Section));
+        assert_eq!(matcher.id, Some("description".to_string()));
+        assert_eq!(matcher.to_string(), "description:section");
+
+        // Without an id it's just an all matcher called "section"
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`section`", None).unwrap();
+        assert!(matches!(matcher.kind, MatcherKind::All));
+        assert_eq!(matcher.id, Some("section".to_string()));
+    }
+
     #[test]
     fn test_matcher_creation_and_matching_regex() {
         let matcher = Matcher::try_from_pattern_and_suffix_str("`word:/\\w+/`", None).unwrap();
@@ -448,6 +1557,30 @@ mod tests {
         assert_eq!(matcher.match_str("!@#$"), None);
     }
 
+    #[test]
+    fn test_anchoring() {
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`id:/\\d+/`", None).unwrap();
+        assert!(!matcher.is_unanchored());
+        assert_eq!(matcher.match_str("abc 123"), None);
+        assert_eq!(matcher.match_str("123 abc"), Some("123"));
+        // The anchor is implicit, so it isn't shown
+        assert_eq!(matcher.pattern().to_string(), "\\d+");
+
+        // The anchor applies to every alternative
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`id:/a|b/`", None).unwrap();
+        assert_eq!(matcher.match_str("cb"), None);
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`id:/\\d+/`", Some("~")).unwrap();
+        assert!(matcher.is_unanchored());
+        assert_eq!(matcher.match_str("abc 123 456"), Some("123"));
+        assert_eq!(matcher.match_str("abc"), None);
+        assert_eq!(matcher.pattern().to_string(), "\\d+");
+
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`lang:[Go, Golang]`", Some("~")).unwrap();
+        assert_eq!(matcher.match_str("I like Golang"), Some("Golang"));
+    }
+
     #[test]
     fn test_all_matcher_matches_everything() {
         let matcher = Matcher::try_from_pattern_and_suffix_str("`my_id`", None).unwrap();
@@ -490,6 +1623,195 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_matcher_rejects_lookaround() {
+        let result = Matcher::try_from_pattern_and_suffix_str("`name:/foo(?=bar)/`", None);
+        assert!(matches!(
+            result,
+            Err(MatcherError::UnsupportedRegexSyntax(_))
+        ));
+
+        let result = Matcher::try_from_pattern_and_suffix_str("`name:/(?<!foo)bar/`", None);
+        assert!(matches!(
+            result,
+            Err(MatcherError::UnsupportedRegexSyntax(_))
+        ));
+    }
+
+    #[test]
+    fn test_matcher_rejects_huge_compiled_regex() {
+        let result = Matcher::try_from_pattern_and_suffix_str("`name:/(\\w{1000}){1000}/`", None);
+        assert!(matches!(result, Err(MatcherError::RegexTooComplex { .. })));
+    }
+
+    #[test]
+    fn test_check_regex_limits() {
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`name:/[a-z]{1000}/`", None).unwrap();
+        assert_eq!(matcher.check_regex_limits(&RegexLimits::default()), Ok(()));
+
+        let tight = RegexLimits {
+            size_limit: 1000,
+            ..Default::default()
+        };
+        assert_eq!(
+            matcher.check_regex_limits(&tight),
+            Err(MatcherError::RegexTooComplex { limit: 1000 })
+        );
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`name:[a, b]`", None).unwrap();
+        assert_eq!(matcher.check_regex_limits(&tight), Ok(()));
+    }
+
+    #[test]
+    fn test_matcher_is_built_within_limits() {
+        let tight = RegexLimits {
+            size_limit: 1000,
+            ..Default::default()
+        };
+
+        let result = Matcher::try_from_pattern_and_suffix_str_with_limits(
+            "`name:/[a-z]{1000}/`",
+            None,
+            &tight,
+        );
+        assert_eq!(result, Err(MatcherError::RegexTooComplex { limit: 1000 }));
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str_with_limits(
+            "`name:/[a-z]+/`",
+            None,
+            &RegexLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(matcher.match_str("abc1"), Some("abc"));
+    }
+
+    #[test]
+    fn test_matcher_rejects_back_references() {
+        for pattern in [
+            "`name:/(a)\\1/`",
+            "`name:/(?<x>a)\\k<x>/`",
+            "`name:/(?>a+)b/`",
+        ] {
+            let result = Matcher::try_from_pattern_and_suffix_str(pattern, None);
+            assert!(
+                matches!(result, Err(MatcherError::UnsupportedRegexSyntax(_))),
+                "{}: {:?}",
+                pattern,
+                result
+            );
+        }
+
+        // An escaped backslash followed by a digit is fine
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`path:/a\\\\1/`", None).unwrap();
+        assert_eq!(matcher.match_str("a\\1"), Some("a\\1"));
+    }
+
+    #[test]
+    fn test_match_captures() {
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`v:/(\\d+)\\.(\\d+)(-\\w+)?/`", None)
+                .unwrap();
+        assert!(matcher.has_capture_groups());
+        let captures = matcher.match_captures("1.2 is out").unwrap();
+        assert_eq!(
+            captures,
+            MatchCaptures {
+                full: "1.2".to_string(),
+                groups: vec![Some("1".to_string()), Some("2".to_string()), None],
+                names: vec![None, None, None],
+            }
+        );
+        assert_eq!(
+            captures.to_value(MatcherValueType::Int),
+            Some(json!({"full": "1.2", "groups": [1, 2, null]}))
+        );
+
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`v:/(?P<major>\\d+)\\.(\\d+)/`", None)
+                .unwrap();
+        assert_eq!(
+            matcher
+                .match_captures("1.2")
+                .unwrap()
+                .to_value(MatcherValueType::Str),
+            Some(json!({"full": "1.2", "major": "1"}))
+        );
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`v:/\\d+/`", None).unwrap();
+        assert!(!matcher.has_capture_groups());
+        assert!(matcher.match_captures("12").unwrap().groups.is_empty());
+        assert_eq!(matcher.typed_value(json!("12")), Some(json!("12")));
+    }
+
+    #[test]
+    fn test_near_miss_matcher() {
+        assert_eq!(
+            near_miss_matcher("`name:/\\w+`"),
+            Some(("missing closing '/'", "name:/\\w+/".to_string()))
+        );
+        assert_eq!(
+            near_miss_matcher("`name:\\w+/`"),
+            Some(("missing opening '/'", "name:/\\w+/".to_string()))
+        );
+        assert_eq!(
+            near_miss_matcher("`name: /\\w+/`"),
+            Some(("remove the space after ':'", "name:/\\w+/".to_string()))
+        );
+        assert_eq!(
+            near_miss_matcher("`name :/\\w+/`"),
+            Some(("remove the space before ':'", "name:/\\w+/".to_string()))
+        );
+        assert_eq!(
+            near_miss_matcher("`count:num : /\\d+/`"),
+            Some((
+                "remove the spaces around ':'",
+                "count:num:/\\d+/".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_near_miss_matcher_ignores_literal_code() {
+        for text in [
+            "`name:/\\w+/`",
+            "`name`",
+            "`std::fmt`",
+            "`a/b`",
+            "`Note: see below`",
+            "`key: value/`",
+            "`https://example.com`",
+            "`https://example.com/`",
+            "`include:./a.mds`",
+            "`@scope:config`",
+            "`name:/`",
+        ] {
+            assert_eq!(near_miss_matcher(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_try_match_str_enforces_max_input_len() {
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`word:/\\w+/`", None).unwrap();
+
+        assert_eq!(matcher.try_match_str("hello", 5), Ok(Some("hello")));
+        assert_eq!(
+            matcher.try_match_str("hello!", 5),
+            Err(MatcherError::InputTooLong { len: 6, max_len: 5 })
+        );
+    }
+
+    #[test]
+    fn test_nested_quantifiers_run_in_linear_time() {
+        // This pattern takes exponential time with a backtracking engine
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`slow:/(a+)+$/`", None).unwrap();
+        let input = format!("{}!", "a".repeat(100_000));
+
+        let start = std::time::Instant::now();
+        assert_eq!(matcher.match_str(&input), None);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
     #[test]
     fn test_new_matcher_with_bullshit_extras() {
         // For now, this actually is fine. It will assume there are no extras,
@@ -499,6 +1821,78 @@ mod tests {
         assert!(result.is_ok()); // TODO: for now
     }
 
+    #[test]
+    fn test_matcher_value_type() {
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`age:int:/\\d+/`", None).unwrap();
+        assert_eq!(matcher.id(), Some("age"));
+        assert_eq!(matcher.value_type(), MatcherValueType::Int);
+        assert_eq!(matcher.match_str("42 years"), Some("42"));
+        assert_eq!(matcher.to_string(), "age:int:/\\d+/");
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`age:/\\d+/`", None).unwrap();
+        assert_eq!(matcher.value_type(), MatcherValueType::Str);
+
+        assert_eq!(
+            Matcher::try_from_pattern_and_suffix_str("`age:integer:/\\d+/`", None).unwrap_err(),
+            MatcherError::UnknownValueType("integer".to_string())
+        );
+    }
+
+    #[test]
+    fn test_named_pattern_matcher() {
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`released:@date`", None).unwrap();
+        assert_eq!(matcher.id(), Some("released"));
+        assert_eq!(matcher.to_string(), "released:@date");
+        assert_eq!(matcher.match_str("2024-02-29 was a leap day"), Some("2024-02-29"));
+        assert_eq!(matcher.match_str("2023-02-29"), None);
+        assert_eq!(matcher.match_str("On 2024-02-29"), None);
+
+        // Unanchored, days that don't exist are skipped over
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`released:@date`", Some("~")).unwrap();
+        assert_eq!(matcher.match_str("2023-02-29 or 2023-03-01"), Some("2023-03-01"));
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`v:@semver`", None).unwrap();
+        let error = matcher.mismatch_error(0, 0, "v1.2".to_string());
+        assert_eq!(error.to_string(), "Expected a semver version, found 'v1.2'");
+    }
+
+    #[test]
+    fn test_unknown_named_pattern() {
+        assert_eq!(
+            Matcher::try_from_pattern_and_suffix_str("`released:@dat`", None).unwrap_err(),
+            MatcherError::UnknownNamedPattern {
+                name: "dat".to_string(),
+                suggestion: Some("date".to_string()),
+            }
+        );
+
+        let error =
+            Matcher::try_from_pattern_and_suffix_str("`id:@timestamp`", None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unknown named pattern '@timestamp' \
+             (known patterns are @date, @uuid, @url, @semver, @email)"
+        );
+    }
+
+    #[test]
+    fn test_typed_value() {
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`n:int:/\\d+/`", None).unwrap();
+        assert_eq!(matcher.typed_value(json!("42")), Some(json!(42)));
+        assert_eq!(matcher.typed_value(json!("4.2")), None);
+        assert_eq!(
+            matcher.typed_value(json!({ "value": "7", "start": 0 })),
+            Some(json!({ "value": 7, "start": 0 }))
+        );
+
+        assert_eq!(MatcherValueType::Float.convert("1.5"), Some(json!(1.5)));
+        assert_eq!(MatcherValueType::Bool.convert("false"), Some(json!(false)));
+        assert_eq!(MatcherValueType::Bool.convert("yes"), None);
+        assert_eq!(MatcherValueType::Str.convert("42"), Some(json!("42")));
+    }
+
     #[test]
     fn test_matcher_display() {
         let matcher = Matcher::try_from_pattern_and_suffix_str("`num:/\\d+/`", None).unwrap();
@@ -506,6 +1900,198 @@ mod tests {
         assert_eq!(display_str, "num:/\\d+/");
     }
 
+    #[test]
+    fn test_regex_flags() {
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`name:/alice/i`", None).unwrap();
+        assert_eq!(matcher.match_str("ALICE and Bob"), Some("ALICE"));
+        assert_eq!(matcher.to_string(), "name:/alice/i");
+        // Errors show the pattern with the flags that were applied
+        assert_eq!(matcher.pattern().to_string(), "(?i)alice");
+
+        // `^` matches at the start of every line, but the match still has to
+        // start the text
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`body:/^item$/m`", None).unwrap();
+        assert_eq!(matcher.match_str("item\nmore"), Some("item"));
+        assert_eq!(matcher.match_str("more\nitem"), None);
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`body:/a.b/s`", None).unwrap();
+        assert_eq!(matcher.match_str("a\nb"), Some("a\nb"));
+
+        // A comment doesn't comment out the anchor's group
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`v:/\\d+ \\. \\d+ # major.minor/x`", None)
+                .unwrap();
+        assert_eq!(matcher.match_str("1.2 is out"), Some("1.2"));
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`/abc/xmi`", None).unwrap();
+        assert_eq!(matcher.to_string(), "/abc/imx");
+        assert_eq!(matcher.pattern().to_string(), "(?imx)abc");
+
+        // The flags are checked against the limits too
+        assert!(matcher.check_regex_limits(&RegexLimits::default()).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_regex_flag() {
+        assert_eq!(
+            Matcher::try_from_pattern_and_suffix_str("`name:/\\w+/ig`", None).unwrap_err(),
+            MatcherError::UnknownRegexFlag('g')
+        );
+
+        let error = Matcher::try_from_pattern_and_suffix_str("`name:/\\w+/u`", None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unknown regex flag 'u' (expected i, m, s or x)"
+        );
+
+        // Slashes in the pattern aren't taken for its end
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`path:/a/b/`", None).unwrap();
+        assert_eq!(matcher.pattern().to_string(), "a/b");
+    }
+
+    #[test]
+    fn test_regex_flags_with_extras() {
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`tag:/[a-z]+/i`", Some("{1,3}")).unwrap();
+        assert!(matcher.is_repeated());
+        assert_eq!(matcher.extras().max_items(), Some(3));
+        assert_eq!(matcher.match_str("Rust"), Some("Rust"));
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`tag:/rust/i`", Some("~")).unwrap();
+        assert!(matcher.is_unanchored());
+        assert_eq!(matcher.match_str("I like RUST"), Some("RUST"));
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`tag:/rust/i`", Some("?")).unwrap();
+        assert!(matcher.is_optional());
+
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`tag:/rust/i|Name a language`", None)
+                .unwrap();
+        assert_eq!(matcher.message(), Some("Name a language"));
+        assert_eq!(matcher.match_str("Rust"), Some("Rust"));
+
+        // Literal code may have anything after its slashes
+        assert_eq!(
+            Matcher::try_from_pattern_and_suffix_str("`tag:/rust/g`", Some("!")).unwrap_err(),
+            MatcherError::WasLiteralCode
+        );
+    }
+
+    #[test]
+    fn test_could_match_with_more() {
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`v:/\\d+\\.\\d+\\.\\d+/`", None).unwrap();
+        assert!(matcher.could_match_with_more(""));
+        assert!(matcher.could_match_with_more("1.2"));
+        assert!(matcher.could_match_with_more("1.2."));
+        assert!(!matcher.could_match_with_more("1.x"));
+        assert!(!matcher.could_match_with_more("v1"));
+
+        // With the flags it was written with
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`name:/alice/i`", None).unwrap();
+        assert!(matcher.could_match_with_more("AL"));
+        assert!(!matcher.could_match_with_more("AX"));
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`v:/\\d+ \\. \\d+ # major.minor/x`", None)
+                .unwrap();
+        assert!(matcher.could_match_with_more("1."));
+        assert!(!matcher.could_match_with_more("1 "));
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`c:[red, green]`", None).unwrap();
+        assert!(matcher.could_match_with_more("gr"));
+        assert!(!matcher.could_match_with_more("gx"));
+
+        // We can't tell where an unanchored match starts, or what more text
+        // does to a negated one
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`id:/\\d+/`", Some("~")).unwrap();
+        assert!(matcher.could_match_with_more("abc"));
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`!:/TODO/`", None).unwrap();
+        assert!(matcher.could_match_with_more("TODO"));
+    }
+
+    #[test]
+    fn test_could_match_with_more_builds_dfa_once() {
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`v:/\\d+/`", None).unwrap();
+        let clone = matcher.clone();
+        assert!(matcher.prefix_dfa.get().is_none());
+
+        assert!(matcher.could_match_with_more("1"));
+        // Clones, like the ones the matcher cache hands out, share it
+        assert!(clone.prefix_dfa.get().is_some_and(|dfa| dfa.is_some()));
+        assert!(!clone.could_match_with_more("x"));
+    }
+
+    #[test]
+    fn test_enum_matcher() {
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`lang:[Go, Golang,  Rust ]`", None).unwrap();
+        assert_eq!(matcher.id, Some("lang".to_string()));
+        assert_eq!(format!("{}", matcher), "lang:[Go, Golang, Rust]");
+
+        // The longest allowed value wins
+        assert_eq!(matcher.match_str("Golang is fun"), Some("Golang"));
+        assert_eq!(matcher.match_str("Go"), Some("Go"));
+        assert_eq!(matcher.match_str("Rust"), Some("Rust"));
+        assert_eq!(matcher.match_str("Python"), None);
+    }
+
+    #[test]
+    fn test_matcher_message() {
+        let pattern_str = "`date:@date| Use a date, like 2024-01-31 `";
+        let matcher = Matcher::try_from_pattern_and_suffix_str(pattern_str, None).unwrap();
+        assert_eq!(matcher.id(), Some("date"));
+        assert_eq!(matcher.message(), Some("Use a date, like 2024-01-31"));
+        assert_eq!(matcher.match_str("2024-01-31"), Some("2024-01-31"));
+
+        // Alternatives of the regex stay in the regex
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`/yes|no/|Answer yes or no`", None).unwrap();
+        assert_eq!(matcher.id(), None);
+        assert_eq!(matcher.pattern().to_string(), "yes|no");
+        assert_eq!(matcher.message(), Some("Answer yes or no"));
+
+        let matcher = Matcher::try_from_pattern_and_suffix_str("`path:/a\\/|b/`", None).unwrap();
+        assert_eq!(matcher.pattern().to_string(), "a\\/|b");
+        assert_eq!(matcher.message(), None);
+
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`n:/\\d+/|Use digits`", None).unwrap();
+        assert_eq!(
+            matcher.mismatch_error(1, 2, "x".to_string()),
+            SchemaViolationError::NodeContentMismatch {
+                schema_index: 1,
+                input_index: 2,
+                expected: "\\d+".to_string(),
+                actual: "x".to_string(),
+                kind: NodeContentMismatchKind::MatcherMessage("Use digits".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_enum_matcher_mismatch_error() {
+        let matcher =
+            Matcher::try_from_pattern_and_suffix_str("`color:[red, green]`", None).unwrap();
+        assert_eq!(
+            matcher.mismatch_error(1, 2, "purple".to_string()),
+            SchemaViolationError::EnumMismatch {
+                schema_index: 1,
+                input_index: 2,
+                allowed: vec!["red".to_string(), "green".to_string()],
+                actual: "purple".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_empty_enum_matcher_is_invalid() {
+        let result = Matcher::try_from_pattern_and_suffix_str("`color:[ , ]`", None);
+        assert!(matches!(
+            result,
+            Err(MatcherError::MatcherInteriorRegexInvalid(_))
+        ));
+    }
+
     #[test]
     fn test_long_complicated_id_and_regex() {
         let matcher = Matcher::try_from_pattern_and_suffix_str(
@@ -704,6 +2290,33 @@ mod tests {
         assert_eq!(result, Some(("{,1}", " hi")));
     }
 
+    #[test]
+    fn get_everything_after_special_chars_escaped() {
+        // One backslash is taken off, and what it escapes is text
+        let result = partition_at_special_chars("\\{1,3} hi");
+        assert_eq!(result, Some(("", "{1,3} hi")));
+        let result = partition_at_special_chars("\\\\{1,3}");
+        assert_eq!(result, Some(("", "\\\\{1,3}")));
+
+        // A backslash before anything else stays
+        let result = partition_at_special_chars("\\d and more");
+        assert_eq!(result, Some(("", "\\d and more")));
+    }
+
+    #[test]
+    fn test_double_backtick_code_is_always_literal() {
+        match Matcher::try_from_pattern_and_suffix_str("``foo:/bar/``", None).unwrap_err() {
+            MatcherError::WasLiteralCode => {}
+            error => panic!("Expected WasLiteralCode error, got {:?}", error),
+        }
+
+        // Even with text after it that would otherwise be extras
+        match Matcher::try_from_pattern_and_suffix_str("``foo``", Some("{1,3}")).unwrap_err() {
+            MatcherError::WasLiteralCode => {}
+            error => panic!("Expected WasLiteralCode error, got {:?}", error),
+        }
+    }
+
     #[test]
     fn test_try_from_schema_cursor_simple_repeating() {
         let schema_str = "`test:/\\w+/`{1,2}";
@@ -715,7 +2328,7 @@ mod tests {
         assert_eq!(schema_cursor.node().kind(), "code_span");
 
         let matcher = Matcher::try_from_schema_cursor(&schema_cursor, schema_str).unwrap();
-        assert_eq!(matcher.pattern().to_string(), r"^\w+");
+        assert_eq!(matcher.pattern().to_string(), r"\w+");
         assert_eq!(matcher.id(), Some("test"));
     }
 
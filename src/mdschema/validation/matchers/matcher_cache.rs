@@ -0,0 +1,121 @@
+//! Building each matcher of a schema once.
+//!
+//! Building a matcher compiles its regex, which takes far longer than matching
+//! with it. Without a cache, the matcher of a repeating list item is built
+//! again for every item of the input list, and every matcher is built again on
+//! every validation pass over streamed input. A `MatcherCache` keeps what
+//! building the matcher at each spot in a schema gave, keyed by the byte range
+//! of the schema text it was built from, so two matchers with the same pattern
+//! and different ids are never mixed up. A `Validator` shares one cache with
+//! all of its walkers and passes, since its schema never changes, and starts a
+//! new one when it is given other `RegexLimits` to build matchers within.
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Mutex, PoisonError};
+
+use tree_sitter::TreeCursor;
+
+use crate::mdschema::validation::matchers::matcher::{
+    Matcher, MatcherError, RegexLimits, schema_cursor_matcher_text,
+};
+
+#[derive(Debug, Default)]
+pub struct MatcherCache {
+    matchers: Mutex<HashMap<(usize, usize), Result<Matcher, MatcherError>>>,
+}
+
+impl MatcherCache {
+    /// The matcher at a schema cursor pointing at a `code_span` node, like
+    /// `Matcher::try_from_schema_cursor_with_limits` gives, built the first
+    /// time it is asked for.
+    pub fn matcher_at(
+        &self,
+        schema_cursor: &TreeCursor,
+        schema_str: &str,
+        limits: &RegexLimits,
+    ) -> Result<Matcher, MatcherError> {
+        let (pattern_str, extras_str, range) =
+            schema_cursor_matcher_text(schema_cursor, schema_str);
+        self.get_or_build(range, || {
+            Matcher::try_from_pattern_and_suffix_str_with_limits(pattern_str, extras_str, limits)
+        })
+    }
+
+    /// The matcher built from the schema text at `range`, calling `build` to
+    /// build it if nothing has been built from that text yet.
+    pub fn get_or_build(
+        &self,
+        range: Range<usize>,
+        build: impl FnOnce() -> Result<Matcher, MatcherError>,
+    ) -> Result<Matcher, MatcherError> {
+        self.matchers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry((range.start, range.end))
+            .or_insert_with(build)
+            .clone()
+    }
+
+    /// How many matchers have been built.
+    pub fn len(&self) -> usize {
+        self.matchers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::ts_utils::{parse_markdown, visit_code_spans};
+
+    #[test]
+    fn test_matchers_with_the_same_pattern_are_cached_apart() {
+        let schema = "`a:/\\w+/` and `b:/\\w+/`{1,}\n";
+        let tree = parse_markdown(schema).unwrap();
+        let cache = MatcherCache::default();
+
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            visit_code_spans(&tree, |cursor| {
+                let matcher = cache
+                    .matcher_at(cursor, schema, &RegexLimits::default())
+                    .unwrap();
+                let built = Matcher::try_from_schema_cursor(cursor, schema).unwrap();
+                assert_eq!(matcher.id(), built.id());
+                assert_eq!(matcher.is_repeated(), built.is_repeated());
+                ids.push(matcher.id().map(str::to_string));
+            });
+        }
+
+        assert_eq!(cache.len(), 2);
+        let ids: Vec<_> = ids.iter().map(|id| id.as_deref()).collect();
+        assert_eq!(ids, vec![Some("a"), Some("b"), Some("a"), Some("b")]);
+    }
+
+    #[test]
+    fn test_errors_are_cached_too() {
+        let schema = "`bad:/[a-/`\n";
+        let tree = parse_markdown(schema).unwrap();
+        let cache = MatcherCache::default();
+
+        let mut builds = 0;
+        for _ in 0..3 {
+            visit_code_spans(&tree, |cursor| {
+                let (_, _, range) = schema_cursor_matcher_text(cursor, schema);
+                let result = cache.get_or_build(range, || {
+                    builds += 1;
+                    Matcher::try_from_schema_cursor(cursor, schema)
+                });
+                assert!(result.is_err());
+            });
+        }
+        assert_eq!(builds, 1);
+        assert_eq!(cache.len(), 1);
+    }
+}
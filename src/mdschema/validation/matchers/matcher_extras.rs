@@ -1,17 +1,46 @@
 #![allow(dead_code)]
 
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::sync::LazyLock;
 
-use crate::mdschema::validation::matchers::matcher::LITERAL_INDICATOR;
+use crate::mdschema::validation::matchers::matcher::{
+    DEFAULT_INDICATOR, LITERAL_INDICATOR, ONE_OR_MORE_INDICATOR, OPTIONAL_INDICATOR,
+    UNANCHORED_INDICATOR,
+};
 
 static RANGE_PATTERN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{(\d*),(\d*)\}").unwrap());
 
 pub static MATCHERS_EXTRA_PATTERN: LazyLock<Regex> =
-    // We can have a ! instead of matcher extras to indicate that it is a literal match
-    LazyLock::new(|| Regex::new(r#"^((\!)|([+\{\},0-9]+))"#).unwrap());
-
+    // We can have a ! instead of matcher extras to indicate that it is a literal match.
+    // A default value comes last, and runs to the next whitespace unless it is quoted.
+    LazyLock::new(|| {
+        Regex::new(r#"^((\!)|([+\{\},0-9?~]*=(?:"[^"]*"|\S+))|([+\{\},0-9?~]+))"#).unwrap()
+    });
+
+/// Characters that a backslash right after a matcher stops from being read as
+/// its extras, like the brace in `` `id:/\d+/`\{1,3} ``.
+pub const ESCAPABLE_EXTRA_CHARS: [char; 4] = [
+    '{',
+    ONE_OR_MORE_INDICATOR,
+    LITERAL_INDICATOR,
+    OPTIONAL_INDICATOR,
+];
+
+/// Split the text right after a code span into the code span's extras and the
+/// text after them.
+///
+/// If the text starts with a backslash before one of `ESCAPABLE_EXTRA_CHARS`
+/// there are no extras, and the text after them is everything after the
+/// backslash, so it is compared without it. A backslash before anything else
+/// is just text.
 pub fn partition_at_special_chars(text: &str) -> Option<(&str, &str)> {
+    if let Some(escaped) = text.strip_prefix('\\')
+        && escaped.starts_with(ESCAPABLE_EXTRA_CHARS)
+    {
+        return Some(("", escaped));
+    }
+
     // TODO: does this really need to return an Option
     let captures = MATCHERS_EXTRA_PATTERN.captures(text);
     match captures {
@@ -54,7 +83,8 @@ pub fn has_literal_within_extras(text: &str) -> bool {
 }
 
 /// Errors specific to matcher extras construction
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum MatcherExtrasError {
     /// The extras that came after the matcher were impossible and contained wrong or invalid patterns.
     ///
@@ -63,6 +93,12 @@ pub enum MatcherExtrasError {
     /// When we have a literal extra, and any other extras. If we are literal we
     /// can *only* be literal.
     MixedLiteralAndOthers,
+    /// When we have an optional extra and a `{min,max}` range. A range of
+    /// `{0,n}` already makes the matcher optional.
+    MixedOptionalAndRange,
+    /// When a `{min,max}` range has a minimum above its maximum, like
+    /// `{3,2}`, so no number of items can satisfy it.
+    ImpossibleRange { min: usize, max: usize },
 }
 
 impl std::fmt::Display for MatcherExtrasError {
@@ -74,6 +110,16 @@ impl std::fmt::Display for MatcherExtrasError {
             MatcherExtrasError::MixedLiteralAndOthers => {
                 write!(f, "Cannot mix literal extras with other extras")
             }
+            MatcherExtrasError::MixedOptionalAndRange => {
+                write!(f, "Cannot mix an optional extra with a {{min,max}} range")
+            }
+            MatcherExtrasError::ImpossibleRange { min, max } => {
+                write!(
+                    f,
+                    "Range {{{},{}}} can never be satisfied, its minimum is above its maximum",
+                    min, max
+                )
+            }
         }
     }
 }
@@ -95,6 +141,29 @@ impl std::fmt::Display for MatcherExtrasError {
 /// The `!` character indicates that matched content should be treated as literal
 /// code blocks in the output, preserving formatting and syntax.
 ///
+/// # Optional Flag
+/// The `?` character makes the schema block the matcher is in optional: a
+/// heading's whole section, or a list item, may be left out of the input.
+///
+/// On a matcher inside text, like `` Env: `env:/\w+/`? ``, the matcher itself
+/// may be left out, with the input going straight from the text before it to
+/// the text after it.
+///
+/// # Default Value
+/// `=default` makes the matcher optional, and captures `default` when it is
+/// left out, like `` `env:/\w+/`=prod ``. The default runs up to the next
+/// whitespace, unless it is quoted, like `` `env:/\w+/`="not set" ``.
+///
+/// # List Depth
+/// A run of `+` characters on a repeated list matcher, like
+/// `` - `item:/\w+/`++{1,3} ``, lets it match that many levels of nested
+/// lists, each with the same pattern and bounds, when the schema item has no
+/// nested list of its own. A single `+` is one level, as without it.
+///
+/// # Unanchored Flag
+/// The `~` character makes the matcher search for its first match in the
+/// input, instead of having to match right where it starts.
+///
 /// # Examples
 ///
 /// ```
@@ -119,6 +188,17 @@ pub struct MatcherExtras {
     had_min_max: bool,
     /// Whether it is a literal code block
     is_literal_code: bool,
+    /// Whether the matcher, or the block it is in, is optional
+    is_optional: bool,
+    /// Whether the matcher may skip input before its match
+    is_unanchored: bool,
+    /// Whether the matcher stands for one or more of something, like a ruler
+    /// matcher for consecutive rulers
+    is_one_or_more: bool,
+    /// How many levels of nested lists a repeated list matcher applies to
+    max_depth: usize,
+    /// What to capture when the matcher is left out
+    default: Option<String>,
 }
 
 impl MatcherExtras {
@@ -137,14 +217,28 @@ impl MatcherExtras {
             Some(text) => {
                 // TODO: optimization. We could not even bother calling `extract_item_count_limits` if it's literal.
                 let is_literal = text.starts_with('!');
-
-                let (min_items, max_items, had_range_syntax) = extract_item_count_limits(text);
+                let (flags, default) = partition_at_special_chars(text)
+                    .map_or(("", None), |(extras, _after)| split_default(extras));
+                let is_optional = flags.contains(OPTIONAL_INDICATOR) || default.is_some();
+                let is_unanchored = flags.contains(UNANCHORED_INDICATOR);
+                let is_one_or_more = flags.contains(ONE_OR_MORE_INDICATOR);
+
+                let (min_items, max_items, had_range_syntax) = extract_item_count_limits(flags);
+                if is_optional && had_range_syntax {
+                    return Err(MatcherExtrasError::MixedOptionalAndRange);
+                }
+                check_range_is_possible(min_items, max_items)?;
 
                 Self {
                     min_items,
                     max_items,
                     had_min_max: had_range_syntax,
                     is_literal_code: is_literal, // We handle literal code at a higher level now
+                    is_optional,
+                    is_unanchored,
+                    is_one_or_more,
+                    max_depth: list_depth(flags),
+                    default,
                 }
             }
             None => Self {
@@ -152,6 +246,11 @@ impl MatcherExtras {
                 max_items: None,
                 had_min_max: false,
                 is_literal_code: false,
+                is_optional: false,
+                is_unanchored: false,
+                is_one_or_more: false,
+                max_depth: 1,
+                default: None,
             },
         })
     }
@@ -173,15 +272,33 @@ impl MatcherExtras {
                 max_items: None,
                 had_min_max: false,
                 is_literal_code: true,
+                is_optional: false,
+                is_unanchored: false,
+                is_one_or_more: false,
+                max_depth: 1,
+                default: None,
             })
         } else {
-            let (min_items, max_items, had_range_syntax) = extract_item_count_limits(extras);
+            let (flags, default) = split_default(extras);
+            let is_optional = flags.contains(OPTIONAL_INDICATOR) || default.is_some();
+            let is_unanchored = flags.contains(UNANCHORED_INDICATOR);
+            let is_one_or_more = flags.contains(ONE_OR_MORE_INDICATOR);
+            let (min_items, max_items, had_range_syntax) = extract_item_count_limits(flags);
+            if is_optional && had_range_syntax {
+                return Err(MatcherExtrasError::MixedOptionalAndRange);
+            }
+            check_range_is_possible(min_items, max_items)?;
 
             Ok(Self {
                 min_items,
                 max_items,
                 had_min_max: had_range_syntax,
                 is_literal_code: is_literal, // We handle literal code at a higher level now
+                is_optional,
+                is_unanchored,
+                is_one_or_more,
+                max_depth: list_depth(flags),
+                default,
             })
         }
     }
@@ -223,6 +340,53 @@ impl MatcherExtras {
     pub fn is_literal_code(&self) -> bool {
         self.is_literal_code
     }
+
+    /// Whether the matcher, or the block it is in, is optional
+    pub fn is_optional(&self) -> bool {
+        self.is_optional
+    }
+
+    /// Whether the matcher may skip input before its match
+    pub fn is_unanchored(&self) -> bool {
+        self.is_unanchored
+    }
+
+    /// Whether the matcher stands for one or more of something
+    pub fn is_one_or_more(&self) -> bool {
+        self.is_one_or_more
+    }
+
+    /// How many levels of nested lists a repeated list matcher applies to,
+    /// counting its own. This is the number of `+`s, or 1 without any.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// What to capture when the matcher is left out, if anything
+    pub fn default(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+}
+
+/// How many levels of lists the flags of a matcher reach, which is how many
+/// `+`s they have, but at least one.
+fn list_depth(flags: &str) -> usize {
+    flags.matches(ONE_OR_MORE_INDICATOR).count().max(1)
+}
+
+/// Split extras into the flags before a `=default` and the default, without
+/// its quotes.
+fn split_default(extras: &str) -> (&str, Option<String>) {
+    match extras.split_once(DEFAULT_INDICATOR) {
+        Some((flags, default)) => {
+            let default = default
+                .strip_prefix('"')
+                .and_then(|default| default.strip_suffix('"'))
+                .unwrap_or(default);
+            (flags, Some(default.to_string()))
+        }
+        None => (extras, None),
+    }
 }
 
 /// Extract item count limits from {min,max} syntax in the text following the matcher.
@@ -251,6 +415,19 @@ fn extract_item_count_limits(text: &str) -> (Option<usize>, Option<usize>, bool)
     }
 }
 
+/// Reject a `{min,max}` range that no number of items can satisfy.
+fn check_range_is_possible(
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+) -> Result<(), MatcherExtrasError> {
+    match (min_items, max_items) {
+        (Some(min), Some(max)) if min > max => {
+            Err(MatcherExtrasError::ImpossibleRange { min, max })
+        }
+        _ => Ok(()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -364,6 +541,83 @@ mod tests {
         let extras = MatcherExtras::try_new(Some("+")).unwrap();
         assert_eq!(extras.min_items(), None);
         assert_eq!(extras.max_items(), None);
+        assert!(extras.is_one_or_more());
+    }
+
+    #[test]
+    fn test_unanchored() {
+        let extras = MatcherExtras::try_from_extras_str("~").unwrap();
+        assert!(extras.is_unanchored());
+        assert!(!extras.had_min_max());
+
+        let extras = MatcherExtras::try_new(Some("~ and more")).unwrap();
+        assert!(extras.is_unanchored());
+
+        assert!(!MatcherExtras::try_new(Some("?")).unwrap().is_unanchored());
+    }
+
+    #[test]
+    fn test_impossible_range() {
+        assert_eq!(
+            MatcherExtras::try_from_extras_str("{3,2}").unwrap_err(),
+            MatcherExtrasError::ImpossibleRange { min: 3, max: 2 }
+        );
+        assert!(MatcherExtras::try_new(Some("{3,2} items")).is_err());
+        assert!(MatcherExtras::try_from_extras_str("{2,2}").is_ok());
+    }
+
+    #[test]
+    fn test_optional() {
+        let extras = MatcherExtras::try_from_extras_str("?").unwrap();
+        assert!(extras.is_optional());
+        assert!(!extras.had_min_max());
+
+        let extras = MatcherExtras::try_new(Some("? and more")).unwrap();
+        assert!(extras.is_optional());
+
+        assert!(!MatcherExtras::try_new(Some("{1,2}")).unwrap().is_optional());
+        assert_eq!(
+            MatcherExtras::try_from_extras_str("?{0,1}").unwrap_err(),
+            MatcherExtrasError::MixedOptionalAndRange
+        );
+    }
+
+    #[test]
+    fn test_default() {
+        let extras = MatcherExtras::try_new(Some("=prod and more")).unwrap();
+        assert_eq!(extras.default(), Some("prod"));
+        assert!(extras.is_optional());
+        assert_eq!(get_after_extras("=prod and more"), Some(" and more"));
+
+        let extras = MatcherExtras::try_new(Some("~=\"not set\".")).unwrap();
+        assert_eq!(extras.default(), Some("not set"));
+        assert!(extras.is_unanchored());
+        assert_eq!(get_after_extras("~=\"not set\"."), Some("."));
+
+        // The range is only looked for before the default
+        let extras = MatcherExtras::try_from_extras_str("={1,2}").unwrap();
+        assert_eq!(extras.default(), Some("{1,2}"));
+        assert!(!extras.had_min_max());
+
+        assert_eq!(
+            MatcherExtras::try_from_extras_str("{1,2}=x").unwrap_err(),
+            MatcherExtrasError::MixedOptionalAndRange
+        );
+        assert_eq!(MatcherExtras::try_new(None).unwrap().default(), None);
+    }
+
+    #[test]
+    fn test_max_depth() {
+        assert_eq!(MatcherExtras::try_new(None).unwrap().max_depth(), 1);
+        assert_eq!(MatcherExtras::try_new(Some("{1,3}")).unwrap().max_depth(), 1);
+        assert_eq!(MatcherExtras::try_new(Some("+{1,3}")).unwrap().max_depth(), 1);
+
+        let extras = MatcherExtras::try_new(Some("++{1,3} text")).unwrap();
+        assert_eq!(extras.max_depth(), 2);
+        assert_eq!(extras.min_items(), Some(1));
+        assert_eq!(extras.max_items(), Some(3));
+
+        assert_eq!(MatcherExtras::try_from_extras_str("+++{,}").unwrap().max_depth(), 3);
     }
 
     #[test]
@@ -1,2 +1,4 @@
 pub mod matcher;
+pub mod matcher_cache;
 pub mod matcher_extras;
+pub mod named_patterns;
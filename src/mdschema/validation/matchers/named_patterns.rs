@@ -0,0 +1,223 @@
+//! Built-in patterns that matchers can use by name instead of a regex, like
+//! `` `released:@date` ``.
+//!
+//! Each name stands for a curated regex, so schemas don't have to repeat the
+//! same hard to get right patterns. Some also check what the regex can't
+//! express, like that a date's day exists in its month.
+use core::fmt;
+
+use crate::mdschema::validation::utils::closest_name;
+
+/// What a named pattern starts with in a matcher, like the `@` in
+/// `` `released:@date` ``.
+pub const NAMED_PATTERN_PREFIX: char = '@';
+
+/// How many edits away an unknown name can be from a known one for us to
+/// suggest it.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+const DATE_PATTERN: &str = r"\d{4}-(?:0[1-9]|1[0-2])-(?:0[1-9]|[12]\d|3[01])";
+
+const UUID_PATTERN: &str =
+    r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}";
+
+/// An http(s) URL. Punctuation at the end is left out, since it's far more
+/// likely to end the sentence than the URL.
+const URL_PATTERN: &str =
+    r#"https?://[A-Za-z0-9.-]+(?::\d+)?(?:[/?#](?:[^\s<>]*[^\s<>.,;:!?'")\]])?)?"#;
+
+/// The pattern suggested by the semver spec, without its capture groups.
+const SEMVER_PATTERN: &str = r"(?:0|[1-9]\d*)\.(?:0|[1-9]\d*)\.(?:0|[1-9]\d*)(?:-(?:0|[1-9]\d*|\d*[a-zA-Z-][0-9a-zA-Z-]*)(?:\.(?:0|[1-9]\d*|\d*[a-zA-Z-][0-9a-zA-Z-]*))*)?(?:\+[0-9a-zA-Z-]+(?:\.[0-9a-zA-Z-]+)*)?";
+
+const EMAIL_PATTERN: &str = r"[A-Za-z0-9.!#$%&'*+/=?^_`{|}~-]+@[A-Za-z0-9](?:[A-Za-z0-9-]*[A-Za-z0-9])?(?:\.[A-Za-z0-9](?:[A-Za-z0-9-]*[A-Za-z0-9])?)+";
+
+/// A built-in pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NamedPattern {
+    /// An ISO 8601 calendar date, like `2024-02-29`.
+    Date,
+    /// A UUID in its hyphenated form, of any version.
+    Uuid,
+    /// An http or https URL.
+    Url,
+    /// A semantic version, like `1.2.3-beta.1`.
+    Semver,
+    /// An email address.
+    Email,
+}
+
+impl NamedPattern {
+    /// Every named pattern, in the order we list them in.
+    pub const ALL: [NamedPattern; 5] = [
+        NamedPattern::Date,
+        NamedPattern::Uuid,
+        NamedPattern::Url,
+        NamedPattern::Semver,
+        NamedPattern::Email,
+    ];
+
+    /// The pattern a name stands for, without the `@`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|pattern| pattern.name() == name)
+    }
+
+    /// The name the pattern is used by, without the `@`.
+    pub fn name(self) -> &'static str {
+        match self {
+            NamedPattern::Date => "date",
+            NamedPattern::Uuid => "uuid",
+            NamedPattern::Url => "url",
+            NamedPattern::Semver => "semver",
+            NamedPattern::Email => "email",
+        }
+    }
+
+    /// What the pattern matches, for error messages, like "a semver version".
+    pub fn description(self) -> &'static str {
+        match self {
+            NamedPattern::Date => "a date (YYYY-MM-DD)",
+            NamedPattern::Uuid => "a UUID",
+            NamedPattern::Url => "a URL",
+            NamedPattern::Semver => "a semver version",
+            NamedPattern::Email => "an email address",
+        }
+    }
+
+    /// The regex matchers compile for the pattern.
+    pub fn regex_pattern(self) -> &'static str {
+        match self {
+            NamedPattern::Date => DATE_PATTERN,
+            NamedPattern::Uuid => UUID_PATTERN,
+            NamedPattern::Url => URL_PATTERN,
+            NamedPattern::Semver => SEMVER_PATTERN,
+            NamedPattern::Email => EMAIL_PATTERN,
+        }
+    }
+
+    /// A value the pattern accepts, for sample documents.
+    pub fn example(self) -> &'static str {
+        match self {
+            NamedPattern::Date => "2024-01-01",
+            NamedPattern::Uuid => "123e4567-e89b-12d3-a456-426614174000",
+            NamedPattern::Url => "https://example.com",
+            NamedPattern::Semver => "1.0.0",
+            NamedPattern::Email => "name@example.com",
+        }
+    }
+
+    /// Whether text that the regex matched really is what the pattern stands
+    /// for.
+    pub fn accepts(self, matched: &str) -> bool {
+        match self {
+            NamedPattern::Date => is_real_date(matched),
+            _ => true,
+        }
+    }
+
+    /// The known name closest to an unknown one, if any is close.
+    pub fn suggest(name: &str) -> Option<&'static str> {
+        closest_name(
+            name,
+            Self::ALL.into_iter().map(NamedPattern::name),
+            MAX_SUGGESTION_DISTANCE,
+        )
+    }
+}
+
+impl fmt::Display for NamedPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", NAMED_PATTERN_PREFIX, self.name())
+    }
+}
+
+/// Whether a `YYYY-MM-DD` date's day exists in its month.
+fn is_real_date(date: &str) -> bool {
+    let mut parts = date.split('-').map(|part| part.parse::<u32>().ok());
+    let (Some(Some(year)), Some(Some(month)), Some(Some(day))) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+
+    let leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap_year => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    (1..=days_in_month).contains(&day)
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    /// Whether the whole text matches the pattern.
+    fn matches_whole(pattern: NamedPattern, text: &str) -> bool {
+        let regex = Regex::new(&format!("^(?:{})$", pattern.regex_pattern())).unwrap();
+        regex.is_match(text) && pattern.accepts(text)
+    }
+
+    #[test]
+    fn test_date() {
+        assert!(matches_whole(NamedPattern::Date, "2024-02-29"));
+        assert!(matches_whole(NamedPattern::Date, "1999-12-31"));
+        assert!(!matches_whole(NamedPattern::Date, "2023-02-29"));
+        assert!(!matches_whole(NamedPattern::Date, "2024-04-31"));
+        assert!(!matches_whole(NamedPattern::Date, "2024-13-01"));
+        assert!(!matches_whole(NamedPattern::Date, "24-01-01"));
+    }
+
+    #[test]
+    fn test_uuid() {
+        assert!(matches_whole(NamedPattern::Uuid, "123e4567-e89b-12d3-a456-426614174000"));
+        assert!(!matches_whole(NamedPattern::Uuid, "123e4567e89b12d3a456426614174000"));
+        assert!(!matches_whole(NamedPattern::Uuid, "123e4567-e89b-12d3-a456-42661417400g"));
+    }
+
+    #[test]
+    fn test_url() {
+        assert!(matches_whole(NamedPattern::Url, "https://example.com"));
+        assert!(matches_whole(NamedPattern::Url, "http://localhost:8080/a/b?c=d#e"));
+        assert!(!matches_whole(NamedPattern::Url, "ftp://example.com"));
+        assert!(!matches_whole(NamedPattern::Url, "https://example.com/."));
+    }
+
+    #[test]
+    fn test_semver() {
+        assert!(matches_whole(NamedPattern::Semver, "1.2.3"));
+        assert!(matches_whole(NamedPattern::Semver, "0.0.1-beta.1+build.5"));
+        assert!(!matches_whole(NamedPattern::Semver, "1.2"));
+        assert!(!matches_whole(NamedPattern::Semver, "01.2.3"));
+        assert!(!matches_whole(NamedPattern::Semver, "v1.2.3"));
+    }
+
+    #[test]
+    fn test_email() {
+        assert!(matches_whole(NamedPattern::Email, "wolf@example.com"));
+        assert!(matches_whole(NamedPattern::Email, "first.last+tag@sub.example.org"));
+        assert!(!matches_whole(NamedPattern::Email, "wolf@localhost"));
+        assert!(!matches_whole(NamedPattern::Email, "wolf.example.com"));
+    }
+
+    #[test]
+    fn test_examples_are_accepted() {
+        for pattern in NamedPattern::ALL {
+            assert!(matches_whole(pattern, pattern.example()), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        for pattern in NamedPattern::ALL {
+            assert_eq!(NamedPattern::from_name(pattern.name()), Some(pattern));
+        }
+        assert_eq!(NamedPattern::from_name("timestamp"), None);
+        assert_eq!(NamedPattern::suggest("semvar"), Some("semver"));
+        assert_eq!(NamedPattern::suggest("timestamp"), None);
+    }
+}
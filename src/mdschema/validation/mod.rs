@@ -1,9 +1,42 @@
+pub mod back_references;
+pub mod bounded_memory;
+pub mod checkpoint;
+pub mod conditions;
+pub mod diff;
+pub mod error_codes;
+pub mod error_locations;
 pub mod errors;
+pub mod forbidden;
+pub mod frontmatter;
+pub mod generate;
+pub mod github_annotations;
+pub mod heading_slugs;
+pub mod html_report;
+pub mod includes;
+pub mod inline_html;
+pub mod json_report;
+pub mod link_references;
+pub mod lint;
+pub mod match_paths;
+pub mod match_stream;
 pub mod matchers;
+pub mod preamble;
+pub mod profile;
+pub mod progress;
 pub(crate) mod node_pos_pair;
 pub(crate) mod walkers;
+pub mod schema_diagnostics;
+pub mod scopes;
+pub mod selection;
+pub mod severity;
+pub mod snippet;
+pub mod spans;
+pub mod stats;
+pub mod suppressions;
+pub mod text_normalization;
 pub(crate) mod ts_types;
 pub(crate) mod ts_utils;
 mod utils;
+pub mod validate;
 pub mod validator;
-pub mod validator_walker;
+pub(crate) mod validator_walker;
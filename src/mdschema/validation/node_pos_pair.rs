@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use tree_sitter::TreeCursor;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct NodePosPair {
     schema_index: usize,
     input_index: usize,
@@ -0,0 +1,115 @@
+//! Reading the preamble of a schema.
+//!
+//! A schema that changes over time can say which version of it it is, with a
+//! comment on its first line:
+//!
+//! ```md
+//! <!-- mds-version: 2 -->
+//!
+//! # Release Notes
+//! ```
+//!
+//! The preamble is read, and stripped with the blank lines after it, before
+//! the schema is parsed, so it isn't a block that the input has to have. A
+//! schema without one is version 1. With `ValidateOptions::embed_schema_version`,
+//! the version is put in the matches under `$schema_version`, so documents
+//! can tell which version of the schema they were validated against.
+//!
+//! Versions are positive whole numbers. A declaration with anything else, like
+//! `<!-- mds-version: two -->`, is reported as
+//! `SchemaError::InvalidSchemaVersion`.
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// The version of a schema that doesn't declare one.
+pub const DEFAULT_SCHEMA_VERSION: u32 = 1;
+
+/// The key the schema's version is put under in the matches.
+pub const SCHEMA_VERSION_KEY: &str = "$schema_version";
+
+static PREAMBLE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\A<!--\s*mds-version\b(?P<declared>[^\n]*?)-->(?:(?:[ \t]*\r?\n)+|[ \t]*\z)")
+        .unwrap()
+});
+
+/// What a schema's preamble says about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchemaPreamble {
+    /// The version of the schema.
+    pub version: u32,
+}
+
+impl Default for SchemaPreamble {
+    fn default() -> Self {
+        Self {
+            version: DEFAULT_SCHEMA_VERSION,
+        }
+    }
+}
+
+/// Split the preamble off the start of a schema.
+///
+/// Returns the preamble, or the text of a declaration that isn't a valid
+/// version, and the schema without it. The declaration is stripped either
+/// way.
+pub fn split_preamble(schema_str: &str) -> (Result<SchemaPreamble, String>, &str) {
+    let Some(caps) = PREAMBLE.captures(schema_str) else {
+        return (Ok(SchemaPreamble::default()), schema_str);
+    };
+    let rest = &schema_str[caps.get(0).unwrap().end()..];
+    let declared = caps["declared"].trim();
+
+    let version = declared
+        .strip_prefix(':')
+        .and_then(|version| version.trim().parse::<u32>().ok())
+        .filter(|version| *version > 0);
+    let preamble = version
+        .map(|version| SchemaPreamble { version })
+        .ok_or_else(|| declared.trim_start_matches(':').trim().to_string());
+    (preamble, rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_preamble() {
+        let (preamble, rest) = split_preamble("<!-- mds-version: 2 -->\n\n# Title\n");
+        assert_eq!(preamble, Ok(SchemaPreamble { version: 2 }));
+        assert_eq!(rest, "# Title\n");
+
+        let (preamble, rest) = split_preamble("<!--mds-version:12-->");
+        assert_eq!(preamble, Ok(SchemaPreamble { version: 12 }));
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn test_schemas_without_a_preamble_are_version_1() {
+        for schema in [
+            "# Title\n",
+            "\n<!-- mds-version: 2 -->\n",
+            "<!-- mds:compare nfc -->\n\n# Title\n",
+            "<!-- mds-versions: 2 -->\n",
+        ] {
+            let (preamble, rest) = split_preamble(schema);
+            assert_eq!(preamble, Ok(SchemaPreamble { version: 1 }));
+            assert_eq!(rest, schema);
+        }
+    }
+
+    #[test]
+    fn test_invalid_versions_are_stripped() {
+        for (schema, declared) in [
+            ("<!-- mds-version: two -->\n# Title\n", "two"),
+            ("<!-- mds-version: 0 -->\n# Title\n", "0"),
+            ("<!-- mds-version: 1.2 -->\n# Title\n", "1.2"),
+            ("<!-- mds-version -->\n# Title\n", ""),
+        ] {
+            let (preamble, rest) = split_preamble(schema);
+            assert_eq!(preamble, Err(declared.to_string()));
+            assert_eq!(rest, "# Title\n");
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! Timing how long each kind of validator takes.
+//!
+//! When profiling is turned on with `set_profiling`, every validator call
+//! adds to the statistics of its validator type: how often it ran, how long
+//! it took in total and its slowest call. `take_profile` collects them into
+//! a table, slowest validator first:
+//!
+//! ```no_run
+//! use mdvalidate::mdschema::validation::profile::{set_profiling, take_profile};
+//! use mdvalidate::validate_str;
+//!
+//! set_profiling(true);
+//! validate_str("- `item:/\\w+/`{1,}\n", "- a\n- b\n").unwrap();
+//! eprintln!("{}", take_profile());
+//! ```
+//!
+//! Times include the validators a validator calls, so nested validators like
+//! `ListVsListValidator` show up as slower than the leaves doing the work.
+//! When profiling is off, validators only check a flag.
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tabled::{Table, Tabled, settings::Style};
+
+static PROFILING: AtomicBool = AtomicBool::new(false);
+
+static STATS: Mutex<Option<HashMap<&'static str, ValidatorStats>>> = Mutex::new(None);
+
+/// Turn collecting validator timings on or off, for every validation in the
+/// process.
+pub fn set_profiling(enabled: bool) {
+    PROFILING.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether validator timings are being collected.
+pub fn is_profiling() -> bool {
+    PROFILING.load(Ordering::Relaxed)
+}
+
+/// How one kind of validator performed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorStats {
+    /// The name of the validator type, like `ListVsListValidator`.
+    pub validator: &'static str,
+    /// How many times it ran.
+    pub calls: usize,
+    /// How long all of its calls took together.
+    pub total: Duration,
+    /// How long its slowest call took.
+    pub max: Duration,
+    /// The descendant index of the schema node of the slowest call.
+    pub max_schema_index: usize,
+    /// The descendant index of the input node of the slowest call.
+    pub max_input_index: usize,
+}
+
+/// The validator timings collected since profiling was turned on, or since
+/// the last `take_profile`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// The statistics of each validator that ran, slowest in total first.
+    pub validators: Vec<ValidatorStats>,
+}
+
+/// Take the validator timings collected so far, starting afresh.
+pub fn take_profile() -> Profile {
+    let stats = STATS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .take()
+        .unwrap_or_default();
+
+    let mut validators: Vec<ValidatorStats> = stats.into_values().collect();
+    validators.sort_by(|a, b| b.total.cmp(&a.total).then(a.validator.cmp(b.validator)));
+    Profile { validators }
+}
+
+/// Add a validator call to the statistics of its validator type.
+///
+/// # Arguments
+///
+/// * `validator`: The name of the validator type.
+/// * `elapsed`: How long the call took.
+/// * `schema_index`: The descendant index of the schema node it was called on.
+/// * `input_index`: The descendant index of the input node it was called on.
+pub(crate) fn record_call(
+    validator: &'static str,
+    elapsed: Duration,
+    schema_index: usize,
+    input_index: usize,
+) {
+    let mut stats = STATS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let entry = stats
+        .get_or_insert_with(HashMap::new)
+        .entry(validator)
+        .or_insert(ValidatorStats {
+            validator,
+            calls: 0,
+            total: Duration::ZERO,
+            max: Duration::ZERO,
+            max_schema_index: schema_index,
+            max_input_index: input_index,
+        });
+
+    entry.calls += 1;
+    entry.total += elapsed;
+    if elapsed > entry.max {
+        entry.max = elapsed;
+        entry.max_schema_index = schema_index;
+        entry.max_input_index = input_index;
+    }
+}
+
+/// The name of a validator type without its module path.
+pub(crate) fn validator_name<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[derive(Tabled)]
+        struct Row {
+            #[tabled(rename = "Validator")]
+            validator: &'static str,
+            #[tabled(rename = "Calls")]
+            calls: usize,
+            #[tabled(rename = "Total")]
+            total: String,
+            #[tabled(rename = "Max")]
+            max: String,
+            #[tabled(rename = "Max at (schema, input)")]
+            max_at: String,
+        }
+
+        let rows = self.validators.iter().map(|stats| Row {
+            validator: stats.validator,
+            calls: stats.calls,
+            total: format!("{:?}", stats.total),
+            max: format!("{:?}", stats.max),
+            max_at: format!("({}, {})", stats.max_schema_index, stats.max_input_index),
+        });
+
+        writeln!(f, "{}", Table::new(rows).with(Style::sharp()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::validate::validate_str;
+
+    #[test]
+    fn test_profile_has_list_validator() {
+        set_profiling(true);
+        let validation = validate_str("# Items\n\n- `item:/\\w+/`{1,}\n", "# Items\n\n- a\n- b\n")
+            .unwrap();
+        let profile = take_profile();
+        set_profiling(false);
+
+        assert!(validation.is_valid);
+        let lists = profile
+            .validators
+            .iter()
+            .find(|stats| stats.validator == "ListVsListValidator")
+            .expect("No statistics for ListVsListValidator");
+        assert!(lists.calls >= 1);
+        assert!(lists.max <= lists.total);
+        assert!(profile.to_string().contains("ListVsListValidator"));
+    }
+
+    #[test]
+    fn test_validator_name() {
+        assert_eq!(validator_name::<ValidatorStats>(), "ValidatorStats");
+    }
+}
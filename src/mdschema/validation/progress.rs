@@ -0,0 +1,146 @@
+//! Progress reports while validating long documents.
+//!
+//! A hook set with `Validator::set_progress_hook` is called every time a
+//! top-level node of the input, like a heading, paragraph or list, has been
+//! validated. That's rare enough to not slow validation down, and often
+//! enough to drive a progress bar:
+//!
+//! ```no_run
+//! use mdvalidate::mdschema::validation::validator::Validator;
+//!
+//! let input = "# Title\n\nSome text\n";
+//! let mut validator = Validator::new_complete("# Title\n\nSome text\n", input).unwrap();
+//! validator.set_progress_hook(Box::new(|progress| {
+//!     println!("{} of {:?} bytes", progress.bytes_processed, progress.total_bytes);
+//! }));
+//! validator.validate();
+//! ```
+use std::fmt;
+use std::sync::Mutex;
+
+use serde_json::{Map, Value};
+
+use crate::mdschema::validation::bounded_memory::DroppedInput;
+use crate::mdschema::validation::walkers::ValidationResult;
+
+/// How far validation has come.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    /// How many bytes of the input have been validated.
+    pub bytes_processed: usize,
+    /// How long the input is, if known. It's known once we have the whole
+    /// input, or before then if given with `Validator::set_total_input_bytes`.
+    pub total_bytes: Option<usize>,
+    /// The descendant index of the input node that was just validated.
+    pub input_index: usize,
+    /// How many errors have been found so far.
+    pub errors: usize,
+    /// How many ids have captured a value so far.
+    pub matches: usize,
+}
+
+/// A function to call with the progress of validation.
+pub type ProgressHook = Box<dyn FnMut(Progress) + Send>;
+
+/// A progress hook that walkers can call through a shared reference.
+pub(crate) struct SharedProgressHook(Mutex<ProgressHook>);
+
+impl SharedProgressHook {
+    pub fn new(hook: ProgressHook) -> Self {
+        Self(Mutex::new(hook))
+    }
+}
+
+impl fmt::Debug for SharedProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedProgressHook")
+    }
+}
+
+/// Reports the progress of one `Validator::validate` pass, on top of what
+/// earlier passes over the same input found.
+pub(crate) struct ProgressReporter<'a> {
+    hook: &'a SharedProgressHook,
+    total_bytes: Option<usize>,
+    errors_before: usize,
+    matches_before: &'a Value,
+    /// The input dropped before the input this pass is over (see
+    /// `bounded_memory`), which reports are moved past.
+    dropped_bytes: usize,
+    dropped_descendants: usize,
+}
+
+impl<'a> ProgressReporter<'a> {
+    pub fn new(
+        hook: &'a SharedProgressHook,
+        total_bytes: Option<usize>,
+        errors_before: usize,
+        matches_before: &'a Value,
+    ) -> Self {
+        Self {
+            hook,
+            total_bytes,
+            errors_before,
+            matches_before,
+            dropped_bytes: 0,
+            dropped_descendants: 0,
+        }
+    }
+
+    /// Report positions in the whole input, for a pass over what's left of it
+    /// after dropping its start.
+    pub fn after_dropped_input(mut self, dropped_input: &DroppedInput) -> Self {
+        self.dropped_bytes = dropped_input.bytes;
+        self.dropped_descendants = dropped_input.descendants;
+        self
+    }
+
+    /// Call the hook now that validation has reached the end of an input node.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes_processed`: Where the input node ends.
+    /// * `input_index`: The descendant index of the input node.
+    /// * `result`: What this pass has found so far.
+    pub fn report(&self, bytes_processed: usize, input_index: usize, result: &ValidationResult) {
+        let progress = Progress {
+            bytes_processed: self.dropped_bytes + bytes_processed,
+            total_bytes: self.total_bytes,
+            input_index: match input_index {
+                0 => 0,
+                index => index + self.dropped_descendants,
+            },
+            errors: self.errors_before + result.errors().len(),
+            matches: count_ids(self.matches_before, result.value()),
+        };
+
+        // A hook that panicked before is still worth calling
+        let mut hook = self.hook.0.lock().unwrap_or_else(|error| error.into_inner());
+        hook(progress);
+    }
+}
+
+/// How many distinct ids there are between two sets of matches.
+fn count_ids(before: &Value, new: &Value) -> usize {
+    let empty = Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let new_ids = new
+        .as_object()
+        .map_or(0, |new| new.keys().filter(|id| !before.contains_key(*id)).count());
+
+    before.len() + new_ids
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_count_ids() {
+        assert_eq!(count_ids(&json!({}), &json!({})), 0);
+        assert_eq!(count_ids(&json!({"a": 1}), &json!({"b": 2})), 2);
+        assert_eq!(count_ids(&json!({"a": [1]}), &json!({"a": [2], "c": 3})), 2);
+    }
+}
@@ -0,0 +1,129 @@
+//! Everything wrong with a schema, found before validating any input.
+//!
+//! Most problems in a schema only come up where the input reaches them, mixed
+//! in with the errors in the input, and some never do. So when a `Validator`
+//! is created, every code span of its schema that isn't literal code is built
+//! into a matcher, and what doesn't build is collected along with the other
+//! problems found loading the schema, like invalid includes or conditions.
+//! `Validator::schema_diagnostics` has them all, for reporting before any
+//! input is read, and `Validator::require_valid_schema` turns down a schema
+//! that has any:
+//!
+//! ```no_run
+//! use mdvalidate::mdschema::validation::validator::Validator;
+//!
+//! let validator = Validator::new_incomplete("# `title:/[a-/`\n", "").unwrap();
+//! match validator.require_valid_schema() {
+//!     Ok(validator) => { /* Feed it the input */ }
+//!     Err(diagnostics) => eprintln!("{}", diagnostics),
+//! }
+//! ```
+//!
+//! Validation still reports the same problems where the input reaches them,
+//! so a validator with an invalid schema works as it always has.
+use std::fmt;
+
+use crate::mdschema::validation::errors::{SchemaError, ValidationError};
+
+/// The problems with a schema, in the order they appear in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiagnostics {
+    errors: Vec<SchemaError>,
+}
+
+impl SchemaDiagnostics {
+    /// Collect the schema errors of `errors`, with the errors of the matchers
+    /// of the schema.
+    pub(crate) fn new<'a>(
+        errors: impl IntoIterator<Item = &'a ValidationError>,
+        matcher_errors: &[SchemaError],
+    ) -> Self {
+        let mut errors: Vec<SchemaError> = errors
+            .into_iter()
+            .filter_map(|error| match error {
+                ValidationError::SchemaError(error) => Some(error.clone()),
+                _ => None,
+            })
+            .chain(matcher_errors.iter().cloned())
+            .collect();
+        errors.sort_by_key(SchemaError::schema_index);
+        Self { errors }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, SchemaError> {
+        self.errors.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a SchemaDiagnostics {
+    type Item = &'a SchemaError;
+    type IntoIter = std::slice::Iter<'a, SchemaError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl fmt::Display for SchemaDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.errors.len() {
+            0 => return write!(f, "The schema has no problems"),
+            1 => write!(f, "The schema has 1 problem:")?,
+            count => write!(f, "The schema has {} problems:", count)?,
+        }
+        for error in &self.errors {
+            write!(f, "\n  [{}] {}", error.code(), error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SchemaDiagnostics {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::matchers::matcher::MatcherError;
+
+    #[test]
+    fn test_schema_diagnostics_are_in_schema_order() {
+        let errors = [
+            ValidationError::SchemaError(SchemaError::InvalidSchemaVersion {
+                schema_index: 0,
+                declared: "x".to_string(),
+            }),
+            ValidationError::ValidatorCreationFailed,
+        ];
+        let matcher_errors = [
+            SchemaError::RepeatingMatcherUnbounded { schema_index: 9 },
+            SchemaError::MatcherError {
+                error: MatcherError::WasLiteralCode,
+                schema_index: 4,
+            },
+        ];
+
+        let diagnostics = SchemaDiagnostics::new(&errors, &matcher_errors);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(SchemaError::schema_index)
+                .collect::<Vec<_>>(),
+            vec![0, 4, 9]
+        );
+        assert!(
+            diagnostics
+                .to_string()
+                .starts_with("The schema has 3 problems:\n  [MDV112]")
+        );
+        assert!(SchemaDiagnostics::default().is_empty());
+    }
+}
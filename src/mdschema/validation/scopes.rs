@@ -0,0 +1,343 @@
+//! Nesting the matches of a section under a key.
+//!
+//! A top-level heading that ends with a scope annotation, like
+//!
+//! ```md
+//! # Config `@scope:config`
+//! ```
+//!
+//! puts everything captured from it up to the next heading of the same (or a
+//! higher) level under `"config"` in the matches, instead of at the top
+//! level. Scoped headings nest, so a `## Database `@scope:db`` heading under
+//! it captures under `"config"."db"`. This lets sections reuse matcher ids,
+//! like a `host` in both a server and a client section.
+//!
+//! The annotation is stripped from the schema when it is loaded, so the
+//! heading is compared to the input without it. Two headings that scope
+//! their matches under the same keys are reported as
+//! `SchemaError::DuplicateScope`.
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use serde_json::Value;
+use tree_sitter::{Node, Tree};
+
+use crate::mdschema::validation::errors::{SchemaError, ValidationError};
+use crate::mdschema::validation::ts_types::*;
+use crate::mdschema::validation::ts_utils::{get_node_text, heading_level};
+
+/// What a scope annotation starts with, like `` `@scope:config` ``.
+pub const SCOPE_PREFIX: &str = "@scope:";
+
+/// The scope name of a scope annotation, if a code span's text is one.
+///
+/// Names are made of letters, digits, `_` and `-`.
+pub fn scope_name(code_span_text: &str) -> Option<&str> {
+    code_span_text
+        .trim_matches('`')
+        .trim()
+        .strip_prefix(SCOPE_PREFIX)
+        .filter(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
+}
+
+/// The scoped headings of a schema.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaScopes {
+    /// The scope names, by the start byte of their heading.
+    names: HashMap<usize, String>,
+    /// The byte ranges of the annotations, with the whitespace before them,
+    /// in order.
+    annotations: Vec<Range<usize>>,
+}
+
+impl SchemaScopes {
+    /// Find the scope annotations of the top-level headings of a schema.
+    pub fn find(schema_tree: &Tree, schema_str: &str) -> Self {
+        let mut scopes = Self::default();
+
+        let root = schema_tree.root_node();
+        for heading in root.children(&mut root.walk()) {
+            if heading_level(&heading).is_none() {
+                continue;
+            }
+            let Some(content) = heading
+                .children(&mut heading.walk())
+                .find(is_heading_content_node)
+            else {
+                continue;
+            };
+            let Some(code_span) = content
+                .named_child(content.named_child_count().saturating_sub(1))
+                .filter(is_inline_code_node)
+            else {
+                continue;
+            };
+            let Some(name) = scope_name(get_node_text(&code_span, schema_str)) else {
+                continue;
+            };
+            if !schema_str[code_span.end_byte()..content.end_byte()]
+                .trim()
+                .is_empty()
+            {
+                continue;
+            }
+
+            let start = schema_str[..code_span.start_byte()]
+                .trim_end_matches([' ', '\t'])
+                .len()
+                .max(content.start_byte());
+            scopes.names.insert(heading.start_byte(), name.to_string());
+            scopes.annotations.push(start..code_span.end_byte());
+        }
+
+        scopes
+    }
+
+    /// Remove the scope annotations from a schema, so that its headings get
+    /// compared without them.
+    ///
+    /// Returns the schema without them and its scopes, or `None` if it has
+    /// no scope annotations.
+    pub fn strip(schema_tree: &Tree, schema_str: &str) -> Option<(String, Self)> {
+        let found = Self::find(schema_tree, schema_str);
+        if found.is_empty() {
+            return None;
+        }
+
+        let stripped = strip_annotations(schema_str, &found.annotations);
+        Some((stripped, found.after_stripping(&found.annotations)))
+    }
+
+    /// The scopes of the schema once annotations are removed from it, like
+    /// those of conditional blocks (see `conditions`).
+    pub fn after_stripping(&self, annotations: &[Range<usize>]) -> Self {
+        Self {
+            names: self
+                .names
+                .iter()
+                .map(|(&start, name)| (start_after_stripping(start, annotations), name.clone()))
+                .collect(),
+            annotations: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The scope name of a heading, if it has one.
+    pub fn heading_scope(&self, heading: &Node) -> Option<&str> {
+        self.names.get(&heading.start_byte()).map(String::as_str)
+    }
+
+    /// The keys that what gets captured from a node is nested under, outermost
+    /// first.
+    ///
+    /// A scoped heading is in its own scope. Nodes outside any scoped heading
+    /// have an empty path.
+    pub fn scope_path(&self, node: &Node) -> Vec<String> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        // Only top-level headings start scopes
+        let mut top_level = *node;
+        while let Some(parent) = top_level.parent() {
+            if is_document_node(&parent) {
+                break;
+            }
+            top_level = parent;
+        }
+        let Some(document) = top_level.parent() else {
+            return Vec::new();
+        };
+
+        let mut stack: Vec<(usize, &str)> = Vec::new();
+        for sibling in document.children(&mut document.walk()) {
+            if sibling.start_byte() > top_level.start_byte() {
+                break;
+            }
+            self.enter_heading(&mut stack, &sibling);
+        }
+        stack.into_iter().map(|(_, name)| name.to_string()).collect()
+    }
+
+    /// The errors for headings that scope their matches under the same keys as
+    /// an earlier heading.
+    pub fn duplicate_errors(&self, schema_tree: &Tree) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        if self.is_empty() {
+            return errors;
+        }
+
+        let mut seen = HashSet::new();
+        let mut stack: Vec<(usize, &str)> = Vec::new();
+        let mut cursor = schema_tree.walk();
+        if !cursor.goto_first_child() {
+            return errors;
+        }
+        loop {
+            let node = cursor.node();
+            if self.enter_heading(&mut stack, &node) && self.heading_scope(&node).is_some() {
+                let path: Vec<&str> = stack.iter().map(|(_, name)| *name).collect();
+                let path = path.join(".");
+                if !seen.insert(path.clone()) {
+                    errors.push(ValidationError::SchemaError(SchemaError::DuplicateScope {
+                        schema_index: cursor.descendant_index(),
+                        path,
+                    }));
+                }
+            }
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+        errors
+    }
+
+    /// Update the stack of open scopes for a top-level node. Returns whether
+    /// the node was a heading.
+    fn enter_heading<'s>(&'s self, stack: &mut Vec<(usize, &'s str)>, node: &Node) -> bool {
+        let Some(level) = heading_level(node) else {
+            return false;
+        };
+        stack.retain(|(open_level, _)| *open_level < level);
+        if let Some(name) = self.heading_scope(node) {
+            stack.push((level, name));
+        }
+        true
+    }
+}
+
+/// Remove annotations, with the whitespace before them, from a schema.
+///
+/// # Arguments
+///
+/// * `schema_str`: The schema.
+/// * `annotations`: The byte ranges to remove, in order.
+pub(crate) fn strip_annotations(schema_str: &str, annotations: &[Range<usize>]) -> String {
+    let mut stripped = String::with_capacity(schema_str.len());
+    let mut last_end = 0;
+    for annotation in annotations {
+        stripped.push_str(&schema_str[last_end..annotation.start]);
+        last_end = annotation.end;
+    }
+    stripped.push_str(&schema_str[last_end..]);
+    stripped
+}
+
+/// Where a node that starts at `start` starts once annotations are removed
+/// from the schema, since it moves back by the length of the ones before it.
+pub(crate) fn start_after_stripping(start: usize, annotations: &[Range<usize>]) -> usize {
+    let removed: usize = annotations
+        .iter()
+        .filter(|annotation| annotation.end <= start)
+        .map(|annotation| annotation.len())
+        .sum();
+    start - removed
+}
+
+/// The object that the matches of a scope are in, if there is one.
+pub fn scope_value_mut<'v>(value: &'v mut Value, scope: &[String]) -> Option<&'v mut Value> {
+    scope
+        .iter()
+        .try_fold(value, |value, name| value.get_mut(name.as_str()))
+        .filter(|value| value.is_object())
+}
+
+/// Join the matches of another validation pass into ones we already have.
+///
+/// Like `join_values`, later values replace earlier ones, except that the
+/// objects of scopes are joined key by key, since a pass may only have
+/// reached part of a section.
+pub fn join_scoped_values(existing: &mut Value, new: Value, scopes: &SchemaScopes) {
+    let names: HashSet<&str> = scopes.names.values().map(String::as_str).collect();
+    join_scoped_values_inner(existing, new, &names);
+}
+
+fn join_scoped_values_inner(existing: &mut Value, new: Value, names: &HashSet<&str>) {
+    let (Value::Object(existing_map), Value::Object(new_map)) = (existing, new) else {
+        return;
+    };
+    for (key, value) in new_map {
+        match existing_map.get_mut(&key) {
+            Some(existing_value)
+                if names.contains(key.as_str())
+                    && existing_value.is_object()
+                    && value.is_object() =>
+            {
+                join_scoped_values_inner(existing_value, value, names);
+            }
+            _ => {
+                existing_map.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    #[test]
+    fn test_scope_name() {
+        assert_eq!(scope_name("`@scope:config`"), Some("config"));
+        assert_eq!(scope_name("`@scope:db-2_x`"), Some("db-2_x"));
+        assert_eq!(scope_name("`@scope:`"), None);
+        assert_eq!(scope_name("`@scope:a.b`"), None);
+        assert_eq!(scope_name("`name:/\\w+/`"), None);
+    }
+
+    #[test]
+    fn test_strip_scope_annotations() {
+        let schema = "# Config `@scope:config`\n\nText\n\n## Database `@scope:db`\n\nMore\n";
+        let tree = parse_markdown(schema).unwrap();
+
+        let (stripped, scopes) = SchemaScopes::strip(&tree, schema).unwrap();
+        assert_eq!(stripped, "# Config\n\nText\n\n## Database\n\nMore\n");
+
+        let tree = parse_markdown(&stripped).unwrap();
+        let root = tree.root_node();
+        let children: Vec<_> = root.children(&mut root.walk()).collect();
+        assert_eq!(scopes.heading_scope(&children[0]), Some("config"));
+        assert_eq!(scopes.scope_path(&children[1]), vec!["config"]);
+        assert_eq!(scopes.scope_path(&children[3]), vec!["config", "db"]);
+    }
+
+    #[test]
+    fn test_scopes_end_at_headings_of_the_same_level() {
+        let schema = "# Server `@scope:server`\n\nText\n\n# Client\n\nText\n";
+        let tree = parse_markdown(schema).unwrap();
+        let (stripped, scopes) = SchemaScopes::strip(&tree, schema).unwrap();
+
+        let tree = parse_markdown(&stripped).unwrap();
+        let root = tree.root_node();
+        let children: Vec<_> = root.children(&mut root.walk()).collect();
+        assert_eq!(scopes.scope_path(&children[1]), vec!["server"]);
+        assert!(scopes.scope_path(&children[3]).is_empty());
+    }
+
+    #[test]
+    fn test_join_scoped_values() {
+        let schema = "# Config `@scope:config`\n";
+        let tree = parse_markdown(schema).unwrap();
+        let scopes = SchemaScopes::find(&tree, schema);
+
+        let mut existing = json!({"config": {"host": "a"}, "other": {"x": 1}});
+        let new = json!({"config": {"port": 1}, "other": {"y": 2}});
+        join_scoped_values(&mut existing, new, &scopes);
+
+        assert_eq!(
+            existing,
+            json!({"config": {"host": "a", "port": 1}, "other": {"y": 2}})
+        );
+    }
+}
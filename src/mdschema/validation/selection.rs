@@ -0,0 +1,298 @@
+//! Validating one section of the input.
+//!
+//! A large document, like a README, often has a section that follows a
+//! schema of its own. With `ValidateOptions::input_selection` (`--select` on
+//! the command line), only the section under a heading of the input is
+//! validated, as if it were the whole document:
+//!
+//! ```text
+//! mdv --select "API > Authentication" auth.mds README.md
+//! ```
+//!
+//! A heading path is the text of headings separated by `>`. Its first
+//! heading can be anywhere in the input, and each heading after it has to be
+//! in the section of the one before it. Headings are compared exactly, or as
+//! globs where `*` stands for any run of characters and `?` for any one.
+//!
+//! A section is its heading and every block after it up to the next heading
+//! of the same level or higher, or the end of the input. The section is
+//! parsed on its own, and everything before it is skipped like the input
+//! that `bounded_memory` drops, so errors and spans still point into the
+//! whole input.
+//!
+//! The section isn't known until the whole input has arrived, so nothing is
+//! validated before then. When no heading, or more than one, is at the path,
+//! that's reported as a `SchemaViolationError::SelectionNotFound` or
+//! `SchemaViolationError::AmbiguousSelection`.
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use tree_sitter::Tree;
+
+use crate::mdschema::validation::{
+    bounded_memory::line_start,
+    errors::{SchemaViolationError, ValidationError},
+    ts_types::*,
+    ts_utils::{get_node_text, heading_level},
+};
+
+/// The headings above a section of the input, like `API > Authentication`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadingPath(Vec<String>);
+
+impl HeadingPath {
+    /// The headings of the path, outermost first.
+    pub fn headings(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl FromStr for HeadingPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let headings: Vec<String> = s
+            .split('>')
+            .map(|heading| heading.trim().to_string())
+            .collect();
+        if headings.iter().any(|heading| heading.is_empty()) {
+            return Err(format!(
+                "Invalid heading path '{}', expected headings separated by '>', \
+                 like 'API > Authentication'",
+                s
+            ));
+        }
+        Ok(HeadingPath(headings))
+    }
+}
+
+impl fmt::Display for HeadingPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join(" > "))
+    }
+}
+
+/// The section of the input a heading path selects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SelectedSection {
+    /// The bytes of the section, from the start of its heading's line.
+    pub byte_range: Range<usize>,
+    /// The descendant index of its heading.
+    pub heading_index: usize,
+}
+
+/// A top-level heading of the input.
+struct Heading {
+    level: usize,
+    text: String,
+    /// The descendant index of the heading.
+    index: usize,
+    /// Where the line the heading is on starts.
+    start_byte: usize,
+    /// The line the heading is on, starting at 1.
+    line: usize,
+}
+
+/// Find the section of the input at a heading path (see the module docs).
+///
+/// # Arguments
+///
+/// * `input_tree`: The tree of the whole input.
+/// * `input_str`: The whole input.
+/// * `path`: The heading path of the section.
+pub(crate) fn select_section(
+    input_tree: &Tree,
+    input_str: &str,
+    path: &HeadingPath,
+) -> Result<SelectedSection, ValidationError> {
+    let headings = top_level_headings(input_tree, input_str);
+
+    // Each heading of the path narrows down the headings to look in
+    let mut found: Vec<usize> = (0..headings.len()).collect();
+    for (depth, pattern) in path.headings().iter().enumerate() {
+        let mut candidates: Vec<usize> = if depth == 0 {
+            found.clone()
+        } else {
+            found
+                .iter()
+                .flat_map(|&parent| parent + 1..section_end(&headings, parent))
+                .collect()
+        };
+        candidates.sort_unstable();
+        candidates.dedup();
+        found = candidates
+            .into_iter()
+            .filter(|&i| glob_matches(pattern, &headings[i].text))
+            .collect();
+    }
+
+    match found.as_slice() {
+        [] => Err(ValidationError::SchemaViolation(
+            SchemaViolationError::SelectionNotFound {
+                schema_index: 0,
+                input_index: 0,
+                selection: path.to_string(),
+            },
+        )),
+        [i] => {
+            let end = headings
+                .get(section_end(&headings, *i))
+                .map_or(input_str.len(), |next| next.start_byte);
+            Ok(SelectedSection {
+                byte_range: headings[*i].start_byte..end,
+                heading_index: headings[*i].index,
+            })
+        }
+        [_, second, ..] => Err(ValidationError::SchemaViolation(
+            SchemaViolationError::AmbiguousSelection {
+                schema_index: 0,
+                input_index: headings[*second].index,
+                selection: path.to_string(),
+                lines: found.iter().map(|&i| headings[i].line).collect(),
+            },
+        )),
+    }
+}
+
+/// The headings that are children of the root, in order.
+fn top_level_headings(input_tree: &Tree, input_str: &str) -> Vec<Heading> {
+    let mut cursor = input_tree.walk();
+    let mut headings = vec![];
+    if !cursor.goto_first_child() {
+        return headings;
+    }
+
+    loop {
+        let node = cursor.node();
+        if let Some(level) = heading_level(&node) {
+            let text = node
+                .children(&mut node.walk())
+                .find(is_heading_content_node)
+                .map_or("", |content| get_node_text(&content, input_str).trim());
+            headings.push(Heading {
+                level,
+                text: text.to_string(),
+                index: cursor.descendant_index(),
+                start_byte: line_start(input_str, node.start_byte()),
+                line: node.start_position().row + 1,
+            });
+        }
+        if !cursor.goto_next_sibling() {
+            return headings;
+        }
+    }
+}
+
+/// The position of the first heading after the section of `headings[i]`,
+/// which may be past the last heading.
+fn section_end(headings: &[Heading], i: usize) -> usize {
+    headings[i + 1..]
+        .iter()
+        .position(|heading| heading.level <= headings[i].level)
+        .map_or(headings.len(), |offset| i + 1 + offset)
+}
+
+/// Whether text matches a pattern where `*` stands for any run of characters
+/// and `?` for any one character.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` is, and how much of the text it has taken
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    fn select(input: &str, path: &str) -> Result<&str, ValidationError> {
+        let tree = parse_markdown(input).unwrap();
+        select_section(&tree, input, &path.parse().unwrap())
+            .map(|section| &input[section.byte_range])
+    }
+
+    #[test]
+    fn test_parse_heading_path() {
+        let path: HeadingPath = " API >Authentication ".parse().unwrap();
+        assert_eq!(path.headings(), ["API", "Authentication"]);
+        assert_eq!(path.to_string(), "API > Authentication");
+
+        assert!("API >".parse::<HeadingPath>().is_err());
+        assert!("".parse::<HeadingPath>().is_err());
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("Install", "Install"));
+        assert!(!glob_matches("Install", "Installation"));
+        assert!(glob_matches("Install*", "Installation"));
+        assert!(glob_matches("v?.*", "v1.2.3"));
+        assert!(glob_matches("*ation", "Installation"));
+        assert!(!glob_matches("v?", "v10"));
+    }
+
+    #[test]
+    fn test_select_nested_section() {
+        let input = "# Intro\n\nHi\n\n# API\n\n## Authentication\n\nUse a token.\n\n\
+                     ### Scopes\n\nRead.\n\n## Pagination\n\nPages.\n";
+
+        assert_eq!(
+            select(input, "API > Authentication"),
+            Ok("## Authentication\n\nUse a token.\n\n### Scopes\n\nRead.\n\n")
+        );
+        assert_eq!(select(input, "API > Scopes"), Ok("### Scopes\n\nRead.\n\n"));
+        assert!(select(input, "Intro > Authentication").is_err());
+    }
+
+    #[test]
+    fn test_select_section_at_end_of_input() {
+        let input = "# Title\n\n## Usage\n\nRun it.\n\n## License\n\nMIT";
+        assert_eq!(select(input, "License"), Ok("## License\n\nMIT"));
+        assert_eq!(select(input, "Title"), Ok(input));
+    }
+
+    #[test]
+    fn test_selection_errors() {
+        let input = "# v1\n\n## Usage\n\nOld.\n\n# v2\n\n## Usage\n\nNew.\n";
+
+        assert_eq!(select(input, "v2 > Usage"), Ok("## Usage\n\nNew.\n"));
+        assert!(matches!(
+            select(input, "Usage"),
+            Err(ValidationError::SchemaViolation(
+                SchemaViolationError::AmbiguousSelection { lines, .. }
+            )) if lines == [3, 9]
+        ));
+        assert!(matches!(
+            select(input, "v3 > Usage"),
+            Err(ValidationError::SchemaViolation(
+                SchemaViolationError::SelectionNotFound { .. }
+            ))
+        ));
+    }
+}
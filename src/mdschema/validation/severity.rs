@@ -0,0 +1,265 @@
+//! Severity overrides.
+//!
+//! Some checks matter less to some documents, like the exact text after a
+//! matcher. `SeverityOverrides` lists the kinds of errors to report as
+//! warnings instead, which are kept with the errors suppression comments
+//! turn into warnings (see `suppressions`), so they are still reported but
+//! don't make validation fail.
+//!
+//! Each rule is a stable error code, like `MDV003`, or a kind, like
+//! `node_content_mismatch`. Content mismatches can be narrowed down to one
+//! kind of content, like `node_content_mismatch:suffix`.
+use std::str::FromStr;
+
+use crate::mdschema::validation::error_codes::{ERROR_EXPLANATIONS, explain};
+use crate::mdschema::validation::errors::{SchemaViolationError, ValidationError};
+
+/// The `kind` of every error (see `ValidationError::kind`).
+pub(crate) const ERROR_KINDS: &[&str] = &[
+    "node_type_mismatch",
+    "node_content_mismatch",
+    "enum_mismatch",
+    "not_enough_nodes_for_repeating_paragraph",
+    "non_repeating_matcher_in_list_context",
+    "children_length_mismatch",
+    "node_list_too_deep",
+    "wrong_list_count",
+    "wrong_section_count",
+    "malformed_node_structure",
+    "unresolved_link_reference",
+    "broken_anchor",
+    "wrong_row_count",
+    "captured_value_type_mismatch",
+    "missing_frontmatter",
+    "missing_frontmatter_key",
+    "unaligned_blocks",
+    "list_marker_mismatch",
+    "trailing_content",
+    "selection_not_found",
+    "ambiguous_selection",
+    "back_reference_mismatch",
+    "missing_content",
+    "condition_not_met",
+    "nesting_too_deep",
+    "forbidden_content",
+    "unordered_items_unmatched",
+    "multiple_matchers_in_node_children",
+    "repeating_matcher_in_text_container",
+    "invalid_matcher_extras",
+    "unclosed_matcher",
+    "matcher_error",
+    "repeating_matcher_unbounded",
+    "utf8_error",
+    "duplicate_matcher_id",
+    "invalid_include",
+    "duplicate_scope",
+    "matcher_syntax_suspicious",
+    "invalid_schema_version",
+    "invalid_back_reference",
+    "invalid_condition",
+    "invalid_forbidden_pattern",
+    "match_path_conflict",
+    "unknown_compare_mode",
+    "io_error",
+    "parser_error",
+    "validator_creation_failed",
+    "internal_invariant_violated",
+];
+
+/// The name of every kind of content mismatch (see
+/// `NodeContentMismatchKind::name`).
+const CONTENT_MISMATCH_KINDS: &[&str] = &[
+    "prefix",
+    "suffix",
+    "matcher",
+    "named_pattern",
+    "negated_matcher",
+    "literal",
+    "normalized_literal",
+    "info_string",
+    "similar_literal",
+];
+
+/// Which errors a rule of `SeverityOverrides` applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WarnRule {
+    /// Errors with a code, like `MDV003`.
+    Code(&'static str),
+    /// Errors of a kind, like `node_content_mismatch`.
+    Kind(&'static str),
+    /// Content mismatches of a kind of content, like `suffix`.
+    ContentMismatch(&'static str),
+}
+
+impl WarnRule {
+    /// Whether the rule applies to an error.
+    pub fn matches(&self, error: &ValidationError) -> bool {
+        match self {
+            WarnRule::Code(code) => error.code() == *code,
+            WarnRule::Kind(kind) => error.kind() == *kind,
+            WarnRule::ContentMismatch(name) => matches!(
+                error,
+                ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                    kind,
+                    ..
+                }) if kind.name() == *name
+            ),
+        }
+    }
+}
+
+impl FromStr for WarnRule {
+    type Err = String;
+
+    /// Parse a code like `MDV003`, a kind like `node_content_mismatch`, or
+    /// `node_content_mismatch:KIND`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.get(..3)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("mdv"))
+        {
+            return explain(s)
+                .map(|explanation| WarnRule::Code(explanation.code))
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown error code '{}', expected one of {} to {} (see `mdv explain`)",
+                        s,
+                        ERROR_EXPLANATIONS[0].code,
+                        ERROR_EXPLANATIONS[ERROR_EXPLANATIONS.len() - 1].code
+                    )
+                });
+        }
+
+        match s.split_once(':') {
+            Some(("node_content_mismatch", content_kind)) => CONTENT_MISMATCH_KINDS
+                .iter()
+                .copied()
+                .find(|name| *name == content_kind)
+                .map(WarnRule::ContentMismatch)
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown kind of content mismatch '{}', expected one of: {}",
+                        content_kind,
+                        CONTENT_MISMATCH_KINDS.join(", ")
+                    )
+                }),
+            Some((kind, _)) => Err(format!(
+                "Only node_content_mismatch can be narrowed down with ':', got '{}'",
+                kind
+            )),
+            None => ERROR_KINDS
+                .iter()
+                .copied()
+                .find(|kind| *kind == s)
+                .map(WarnRule::Kind)
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown error kind '{}', expected a code like MDV005 or one of: {}",
+                        s,
+                        ERROR_KINDS.join(", ")
+                    )
+                }),
+        }
+    }
+}
+
+/// The kinds of errors to report as warnings instead of errors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeverityOverrides {
+    warnings: Vec<WarnRule>,
+}
+
+impl SeverityOverrides {
+    /// Report the errors a rule applies to as warnings too.
+    pub fn warn(mut self, rule: WarnRule) -> Self {
+        self.warnings.push(rule);
+        self
+    }
+
+    /// Whether an error should be reported as a warning.
+    pub fn is_warning(&self, error: &ValidationError) -> bool {
+        self.warnings.iter().any(|rule| rule.matches(error))
+    }
+}
+
+impl FromIterator<WarnRule> for SeverityOverrides {
+    fn from_iter<I: IntoIterator<Item = WarnRule>>(rules: I) -> Self {
+        Self {
+            warnings: rules.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::errors::NodeContentMismatchKind;
+
+    fn content_mismatch(kind: NodeContentMismatchKind) -> ValidationError {
+        ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+            schema_index: 0,
+            input_index: 0,
+            expected: "a".to_string(),
+            actual: "b".to_string(),
+            kind,
+        })
+    }
+
+    #[test]
+    fn test_parse_warn_rule() {
+        assert_eq!("MDV003".parse(), Ok(WarnRule::Code("MDV003")));
+        assert_eq!(" mdv003".parse(), Ok(WarnRule::Code("MDV003")));
+        assert_eq!(
+            "node_content_mismatch".parse(),
+            Ok(WarnRule::Kind("node_content_mismatch"))
+        );
+        assert_eq!(
+            "node_content_mismatch:suffix".parse(),
+            Ok(WarnRule::ContentMismatch("suffix"))
+        );
+    }
+
+    #[test]
+    fn test_unknown_rules_are_rejected() {
+        let error = "MDV999".parse::<WarnRule>().unwrap_err();
+        assert!(error.contains("Unknown error code 'MDV999'"), "{}", error);
+
+        let error = "node_mismatch".parse::<WarnRule>().unwrap_err();
+        assert!(
+            error.contains("Unknown error kind 'node_mismatch'"),
+            "{}",
+            error
+        );
+        assert!(error.contains("node_content_mismatch"), "{}", error);
+
+        let error = "node_content_mismatch:ending"
+            .parse::<WarnRule>()
+            .unwrap_err();
+        assert!(error.contains("'ending'"), "{}", error);
+        assert!(error.contains("suffix"), "{}", error);
+
+        assert!("enum_mismatch:suffix".parse::<WarnRule>().is_err());
+    }
+
+    #[test]
+    fn test_is_warning() {
+        let suffix = content_mismatch(NodeContentMismatchKind::Suffix);
+        let literal = content_mismatch(NodeContentMismatchKind::Literal);
+
+        let overrides = SeverityOverrides::default().warn(WarnRule::ContentMismatch("suffix"));
+        assert!(overrides.is_warning(&suffix));
+        assert!(!overrides.is_warning(&literal));
+
+        let overrides = SeverityOverrides::default().warn(WarnRule::Code("MDV005"));
+        assert!(!overrides.is_warning(&suffix));
+        assert!(overrides.is_warning(&literal));
+
+        let overrides: SeverityOverrides = [WarnRule::Kind("node_content_mismatch")]
+            .into_iter()
+            .collect();
+        assert!(overrides.is_warning(&suffix));
+        assert!(overrides.is_warning(&literal));
+        assert!(!SeverityOverrides::default().is_warning(&literal));
+    }
+}
@@ -0,0 +1,236 @@
+//! Source snippets that show where in a document an error is.
+//!
+//! A snippet shows the line a byte range starts on, with a line number gutter
+//! and a line of context on each side, and underlines the range with carets:
+//!
+//! ```text
+//!  --> input.md:3:4
+//!   |
+//! 2 |
+//! 3 | By Wofl
+//!   |    ^^^^ found here
+//! 4 |
+//! ```
+//!
+//! Ranges that span several lines are underlined up to the end of their first
+//! line, with a note saying how many more lines they cover.
+use std::ops::Range;
+
+use crate::mdschema::validation::spans::TextPosition;
+
+/// How many lines to show above and below the line an error is on.
+pub const CONTEXT_LINES: usize = 1;
+
+/// How many columns a tab advances to the next multiple of.
+pub const TAB_WIDTH: usize = 4;
+
+/// The ANSI codes we color snippets with. We write them ourselves rather
+/// than going through `colored`, so that `color` is all that decides.
+const GUTTER_STYLE: &str = "\x1b[1;34m";
+const UNDERLINE_STYLE: &str = "\x1b[1;31m";
+const RESET_STYLE: &str = "\x1b[0m";
+
+/// Render a snippet of `source` that underlines `range`.
+///
+/// # Arguments
+///
+/// * `source`: The whole document.
+/// * `filename`: The name of the document, shown with the position.
+/// * `range`: The byte range to underline. It is clamped to the document.
+/// * `line_offset`: How many lines of the document come before `source`, when
+///   it is only the end of it. They are added to the line numbers shown.
+/// * `label`: Shown after the underline.
+/// * `color`: Whether to color the gutter and underline with ANSI codes.
+pub fn render_snippet(
+    source: &str,
+    filename: &str,
+    range: Range<usize>,
+    line_offset: usize,
+    label: &str,
+    color: bool,
+) -> String {
+    let start = floor_char_boundary(source, range.start.min(source.len()));
+    let end = floor_char_boundary(source, range.end.clamp(start, source.len()));
+    let position = TextPosition::from_byte(source, start);
+
+    let lines: Vec<&str> = source.split('\n').map(|line| line.trim_end_matches('\r')).collect();
+    let line_index = position.line - 1;
+    // A range ending right after a newline doesn't cover the next line
+    let more_lines = source[start..end]
+        .trim_end_matches('\n')
+        .matches('\n')
+        .count();
+
+    let first_shown = line_index.saturating_sub(CONTEXT_LINES);
+    let last_shown = if more_lines > 0 {
+        line_index
+    } else {
+        (line_index + CONTEXT_LINES).min(lines.len().saturating_sub(1))
+    };
+    let gutter_width = (line_offset + last_shown + 1).to_string().len();
+
+    let paint = |text: String| style(text, GUTTER_STYLE, color);
+    let empty_gutter = paint(format!("{} |", " ".repeat(gutter_width)));
+
+    let mut snippet = format!(
+        "{}{} {}:{}:{}\n{}\n",
+        " ".repeat(gutter_width),
+        paint("-->".to_string()),
+        filename,
+        line_offset + position.line,
+        position.col,
+        empty_gutter
+    );
+    for (index, line) in lines.iter().enumerate().take(last_shown + 1).skip(first_shown) {
+        let gutter = paint(format!(
+            "{:>width$} |",
+            line_offset + index + 1,
+            width = gutter_width
+        ));
+        let line = expand_tabs(line);
+        snippet.push_str(format!("{} {}", gutter, line).trim_end());
+        snippet.push('\n');
+
+        if index == line_index {
+            snippet.push_str(&empty_gutter);
+            snippet.push_str(&underline(
+                lines[line_index],
+                position.col - 1,
+                &source[start..end],
+                label,
+                more_lines,
+                color,
+            ));
+        }
+    }
+
+    snippet
+}
+
+/// The caret line under the line a range starts on, like `   ^^^^ label`.
+///
+/// # Arguments
+///
+/// * `line`: The line, as in the source.
+/// * `start_col`: How many characters into the line the range starts.
+/// * `underlined`: The text of the range.
+/// * `label`: Shown after the carets.
+/// * `more_lines`: How many lines the range covers past this one.
+/// * `color`: Whether to color the carets.
+fn underline(
+    line: &str,
+    start_col: usize,
+    underlined: &str,
+    label: &str,
+    more_lines: usize,
+    color: bool,
+) -> String {
+    let before: String = line.chars().take(start_col).collect();
+    let indent = expand_tabs(&before).chars().count();
+
+    let first_line = underlined.split('\n').next().unwrap_or_default().trim_end_matches('\r');
+    let width = expand_tabs(&format!("{}{}", before, first_line)).chars().count() - indent;
+
+    let mut carets = "^".repeat(width.max(1));
+    if !label.is_empty() {
+        carets = format!("{} {}", carets, label);
+    }
+    if more_lines > 0 {
+        carets = format!(
+            "{} (+{} more line{})",
+            carets,
+            more_lines,
+            if more_lines == 1 { "" } else { "s" }
+        );
+    }
+
+    format!(" {}{}\n", " ".repeat(indent), style(carets, UNDERLINE_STYLE, color))
+}
+
+/// Wrap text in an ANSI style, if we're coloring.
+fn style(text: String, ansi_style: &str, color: bool) -> String {
+    if color {
+        format!("{}{}{}", ansi_style, text, RESET_STYLE)
+    } else {
+        text
+    }
+}
+
+/// Replace tabs with spaces up to the next multiple of `TAB_WIDTH`, so that
+/// carets line up with the text above them.
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    for c in line.chars() {
+        if c == '\t' {
+            let width = TAB_WIDTH - expanded.chars().count() % TAB_WIDTH;
+            expanded.push_str(&" ".repeat(width));
+        } else {
+            expanded.push(c);
+        }
+    }
+    expanded
+}
+
+/// The closest char boundary at or before `index`.
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_snippet_with_context() {
+        let source = "# Title\n\nBy Wofl\n\nThe end\n";
+        let start = source.find("Wofl").unwrap();
+
+        let snippet = render_snippet(source, "input.md", start..start + 4, 0, "found here", false);
+        assert_eq!(
+            snippet,
+            " --> input.md:3:4\n  |\n2 |\n3 | By Wofl\n  |    ^^^^ found here\n4 |\n"
+        );
+    }
+
+    #[test]
+    fn test_render_snippet_after_dropped_lines() {
+        let source = "By Wofl\n\nThe end\n";
+
+        let snippet = render_snippet(source, "input.md", 3..7, 8, "found here", false);
+        assert_eq!(
+            snippet,
+            "  --> input.md:9:4\n   |\n 9 | By Wofl\n   |    ^^^^ found here\n10 |\n"
+        );
+    }
+
+    #[test]
+    fn test_render_snippet_expands_tabs() {
+        let source = "a\tb\n";
+
+        let snippet = render_snippet(source, "input.md", 2..3, 0, "", false);
+        assert!(snippet.contains("1 | a   b\n"), "{}", snippet);
+        assert!(snippet.contains("  |     ^\n"), "{}", snippet);
+    }
+
+    #[test]
+    fn test_render_snippet_multi_line_range() {
+        let source = "- one\n- two\n- three\n";
+
+        let snippet = render_snippet(source, "input.md", 0..source.len(), 0, "", false);
+        assert!(snippet.contains("1 | - one\n  | ^^^^^ (+2 more lines)\n"), "{}", snippet);
+        // Only the first line of the range is shown
+        assert!(!snippet.contains("two"), "{}", snippet);
+    }
+
+    #[test]
+    fn test_render_snippet_without_color() {
+        let snippet = render_snippet("# Title\n", "input.md", 2..7, 0, "here", false);
+        assert!(!snippet.contains('\x1b'));
+
+        let snippet = render_snippet("# Title\n", "input.md", 2..7, 0, "here", true);
+        assert!(snippet.contains('\x1b'));
+    }
+}
@@ -0,0 +1,260 @@
+//! Source locations of captured values.
+//!
+//! With `ValidateOptions::include_spans`, every captured leaf value is
+//! reported with where it came from in the input instead of as a bare string:
+//!
+//! ```json
+//! {
+//!   "value": "Alice",
+//!   "start": { "line": 1, "col": 8, "byte": 7 },
+//!   "end": { "line": 1, "col": 13, "byte": 12 }
+//! }
+//! ```
+use std::ops::Range;
+
+use serde_json::{Map, Value, json};
+
+/// A position in the input document.
+///
+/// Lines and columns start at 1, and columns count characters (not bytes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextPosition {
+    pub line: usize,
+    pub col: usize,
+    pub byte: usize,
+}
+
+impl TextPosition {
+    /// Find the line and column of a byte offset in `src`.
+    pub fn from_byte(src: &str, byte: usize) -> Self {
+        let before = &src[..byte.min(src.len())];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+
+        Self {
+            line: before.matches('\n').count() + 1,
+            col: before[line_start..].chars().count() + 1,
+            byte,
+        }
+    }
+
+    pub fn to_value(self) -> Value {
+        json!({ "line": self.line, "col": self.col, "byte": self.byte })
+    }
+}
+
+/// Where each line of a document starts, to find positions in it without
+/// counting its lines from the start for every one of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineStarts {
+    starts: Vec<usize>,
+}
+
+impl LineStarts {
+    pub fn new(src: &str) -> Self {
+        let mut line_starts = Self { starts: vec![0] };
+        line_starts.extend(0, src);
+        line_starts
+    }
+
+    /// Add the lines of `text`, which was appended to the document at byte
+    /// `offset`.
+    pub fn extend(&mut self, offset: usize, text: &str) {
+        self.starts.extend(
+            text.match_indices('\n')
+                .map(|(newline, _)| offset + newline + 1),
+        );
+    }
+
+    /// The position of a byte offset in `src`, the document the lines are of,
+    /// like `TextPosition::from_byte` finds it.
+    pub fn position(&self, src: &str, byte: usize) -> TextPosition {
+        let end = byte.min(src.len());
+        let line = self.starts.partition_point(|&start| start <= end);
+        let line_start = self.starts[line - 1];
+
+        TextPosition {
+            line,
+            col: src[line_start..end].chars().count() + 1,
+            byte,
+        }
+    }
+}
+
+/// The byte offset of `inner` within `outer`, if `inner` is a slice of
+/// `outer`.
+pub fn subslice_offset(outer: &str, inner: &str) -> Option<usize> {
+    let start = (inner.as_ptr() as usize).checked_sub(outer.as_ptr() as usize)?;
+    (start + inner.len() <= outer.len()).then_some(start)
+}
+
+/// The JSON value for a capture that starts at `start_byte` in the input.
+///
+/// # Arguments
+///
+/// * `input_str`: The full input document (so far).
+/// * `captured`: The captured text.
+/// * `start_byte`: Where the captured text starts in `input_str`.
+/// * `line_starts`: Where the lines of `input_str` start, if we're wrapping
+///   the text with its location.
+pub fn captured_value(
+    input_str: &str,
+    captured: &str,
+    start_byte: usize,
+    line_starts: Option<&LineStarts>,
+) -> Value {
+    captured_range_value(
+        input_str,
+        captured,
+        start_byte..start_byte + captured.len(),
+        line_starts,
+    )
+}
+
+/// Like `captured_value`, for a capture that came from `range` of the input
+/// but isn't exactly the text there, like text with its line endings
+/// normalized (see `NormalizedLines`).
+pub fn captured_range_value(
+    input_str: &str,
+    captured: &str,
+    range: Range<usize>,
+    line_starts: Option<&LineStarts>,
+) -> Value {
+    let Some(line_starts) = line_starts else {
+        return json!(captured);
+    };
+
+    json!({
+        "value": captured,
+        "start": line_starts.position(input_str, range.start).to_value(),
+        "end": line_starts.position(input_str, range.end).to_value(),
+    })
+}
+
+/// Move the spans of captured values, anywhere in `value`, `bytes` bytes and
+/// `lines` lines further into the input, for values captured from text that
+/// starts that far in (see `bounded_memory`). The text has to start at the
+/// start of a line, so columns stay the same.
+pub fn shift_spans(value: &mut Value, bytes: usize, lines: usize) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                shift_spans(value, bytes, lines);
+            }
+        }
+        Value::Object(object) if is_span(object) => {
+            for key in ["start", "end"] {
+                let position = &mut object[key];
+                position["byte"] = json!(position["byte"].as_u64().unwrap_or(0) as usize + bytes);
+                position["line"] = json!(position["line"].as_u64().unwrap_or(1) as usize + lines);
+            }
+        }
+        Value::Object(object) => {
+            for value in object.values_mut() {
+                shift_spans(value, bytes, lines);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The bytes of the input a captured value with its span came from, or
+/// `None` if the value doesn't have its span.
+pub(crate) fn span_byte_range(value: &Value) -> Option<Range<usize>> {
+    let object = value.as_object().filter(|object| is_span(object))?;
+    let byte = |key: &str| object[key]["byte"].as_u64().map(|byte| byte as usize);
+    Some(byte("start")?..byte("end")?)
+}
+
+/// Whether an object is a captured value with its span, as
+/// `captured_range_value` makes it.
+fn is_span(object: &Map<String, Value>) -> bool {
+    object.len() == 3
+        && object.get("value").is_some_and(Value::is_string)
+        && ["start", "end"].iter().all(|key| {
+            object
+                .get(*key)
+                .is_some_and(|position| position.get("byte").is_some())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_position_from_byte() {
+        let src = "# Title\n\nBy Alice é\n";
+
+        assert_eq!(
+            TextPosition::from_byte(src, 0),
+            TextPosition {
+                line: 1,
+                col: 1,
+                byte: 0
+            }
+        );
+        assert_eq!(
+            TextPosition::from_byte(src, 12),
+            TextPosition {
+                line: 3,
+                col: 4,
+                byte: 12
+            }
+        );
+        // Columns count characters, so the multi-byte "é" is one column
+        assert_eq!(TextPosition::from_byte(src, src.len() - 1).col, 11);
+    }
+
+    #[test]
+    fn test_line_starts_find_positions_like_from_byte() {
+        let src = "# Title\n\nBy Alice é\n\nMore";
+        let line_starts = LineStarts::new(src);
+
+        for byte in (0..=src.len()).filter(|byte| src.is_char_boundary(*byte)) {
+            assert_eq!(
+                line_starts.position(src, byte),
+                TextPosition::from_byte(src, byte)
+            );
+        }
+
+        // Lines of appended text are added after the ones we had
+        let mut appended = LineStarts::new(&src[..12]);
+        appended.extend(12, &src[12..]);
+        assert_eq!(appended, line_starts);
+    }
+
+    #[test]
+    fn test_captured_value() {
+        let src = "Name: Alice\n";
+        let line_starts = LineStarts::new(src);
+
+        assert_eq!(captured_value(src, "Alice", 6, None), json!("Alice"));
+        assert_eq!(
+            captured_value(src, "Alice", 6, Some(&line_starts)),
+            json!({
+                "value": "Alice",
+                "start": { "line": 1, "col": 7, "byte": 6 },
+                "end": { "line": 1, "col": 12, "byte": 11 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_shift_spans() {
+        let src = "# Title\n\nName: Alice\n";
+        let rest = &src[9..];
+        let mut value = json!({
+            "name": captured_value(rest, "Alice", 6, Some(&LineStarts::new(rest))),
+            "tags": ["a", {"value": "b", "children": {}}],
+        });
+
+        shift_spans(&mut value, 9, 2);
+        assert_eq!(
+            value,
+            json!({
+                "name": captured_value(src, "Alice", 15, Some(&LineStarts::new(src))),
+                "tags": ["a", {"value": "b", "children": {}}],
+            })
+        );
+    }
+}
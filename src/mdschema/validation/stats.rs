@@ -0,0 +1,440 @@
+//! How much of a schema an input matched.
+//!
+//! `ValidationStats` sums up a validation for someone skimming a report, like
+//! "5/7 sections matched, 7/9 fields captured". Sections start at every
+//! top-level heading of the schema, and the blocks before the first heading
+//! are a section of their own. A section failed if an error points into it,
+//! and matched with warnings if only suppressed errors do. When the input
+//! runs out before the schema does, the sections it never got to failed too.
+//!
+//! Every matcher with an id that isn't optional is a field. A repeated
+//! matcher, like `` `step:/.+/`{2,3} ``, counts each item it captured, out of
+//! its minimum number of items if it has a maximum, and out of how many it
+//! captured (or its minimum, if more) if it doesn't.
+//!
+//! The stats are worked out from the errors and matches found so far, so
+//! while streaming they are of the input read so far.
+use std::fmt;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::mdschema::validation::errors::{
+    MalformedStructureKind, SchemaViolationError, ValidationError,
+};
+use crate::mdschema::validation::match_paths::match_path;
+use crate::mdschema::validation::matchers::matcher::{Matcher, MatcherKind, RULER_PATTERN};
+use crate::mdschema::validation::ts_types::*;
+use crate::mdschema::validation::ts_utils::{
+    goto_first_child_skipping_directives, goto_next_sibling_skipping_directives, visit_code_spans,
+};
+use crate::mdschema::validation::validator::{Validator, ValidatorState};
+
+/// How much of a schema an input matched.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationStats {
+    /// How many sections the schema has.
+    pub sections: usize,
+    /// How many sections have no errors, counting those with warnings.
+    pub sections_matched: usize,
+    /// How many of the matched sections have warnings.
+    pub sections_with_warnings: usize,
+    /// How many blocks of the input the schema didn't expect. They aren't in
+    /// any section.
+    pub extra_input_blocks: usize,
+    /// How many values the input has for the fields.
+    pub fields_captured: usize,
+    /// How many values the fields expect.
+    pub fields_expected: usize,
+    /// Every field, in the order of the schema.
+    pub fields: Vec<FieldStats>,
+}
+
+/// How many values the input has for a matcher of the schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldStats {
+    /// The path the matcher captures under, with dots between the keys.
+    pub id: String,
+    /// How many values the input has for it, up to `expected`.
+    pub captured: usize,
+    /// How many values it expects.
+    pub expected: usize,
+}
+
+/// How well a section of the schema matched, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum SectionStatus {
+    Matched,
+    Warnings,
+    Failed,
+}
+
+/// A top-level block of the schema.
+struct Block {
+    /// The descendant indexes of the block and of everything in it.
+    indexes: Range<usize>,
+    /// The section the block is in.
+    section: usize,
+}
+
+impl ValidationStats {
+    /// The stats of what a validator has found so far.
+    pub fn from_validator(validator: &Validator) -> Self {
+        let statuses = section_statuses(validator);
+        let fields = field_stats(validator);
+
+        Self {
+            sections: statuses.len(),
+            sections_matched: statuses
+                .iter()
+                .filter(|status| **status != SectionStatus::Failed)
+                .count(),
+            sections_with_warnings: statuses
+                .iter()
+                .filter(|status| **status == SectionStatus::Warnings)
+                .count(),
+            extra_input_blocks: validator
+                .errors_so_far()
+                .chain(validator.suppressed_errors_so_far())
+                .map(extra_input_blocks)
+                .sum(),
+            fields_captured: fields.iter().map(|field| field.captured).sum(),
+            fields_expected: fields.iter().map(|field| field.expected).sum(),
+            fields,
+        }
+    }
+}
+
+impl fmt::Display for ValidationStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} sections matched, {}/{} fields captured",
+            self.sections_matched, self.sections, self.fields_captured, self.fields_expected
+        )
+    }
+}
+
+/// The top-level blocks of the schema, and how many sections they are in.
+fn schema_blocks(validator: &Validator) -> (Vec<Block>, usize) {
+    let mut blocks = Vec::new();
+    let mut sections = 0;
+
+    let mut cursor = validator.schema_tree().walk();
+    if !goto_first_child_skipping_directives(&mut cursor, validator.schema_str()) {
+        return (blocks, sections);
+    }
+    loop {
+        let node = cursor.node();
+        if blocks.is_empty() || is_any_heading_node(&node) {
+            sections += 1;
+        }
+        let start = cursor.descendant_index();
+        blocks.push(Block {
+            indexes: start..start + node.descendant_count(),
+            section: sections - 1,
+        });
+        if !goto_next_sibling_skipping_directives(&mut cursor, validator.schema_str()) {
+            break;
+        }
+    }
+
+    (blocks, sections)
+}
+
+/// How well each section of the schema matched.
+fn section_statuses(validator: &Validator) -> Vec<SectionStatus> {
+    let (blocks, sections) = schema_blocks(validator);
+    let mut statuses = vec![SectionStatus::Matched; sections];
+
+    let errors = validator
+        .errors_so_far()
+        .map(|error| (error, SectionStatus::Failed));
+    let warnings = validator
+        .suppressed_errors_so_far()
+        .map(|error| (error, SectionStatus::Warnings));
+    for (error, status) in errors.chain(warnings) {
+        // Errors about the whole document, like trailing content, point at
+        // the root, which is in no block
+        let Some(schema_index) = error.node_indexes().0 else {
+            continue;
+        };
+        let Some(first) = blocks
+            .iter()
+            .position(|block| block.indexes.contains(&schema_index))
+        else {
+            continue;
+        };
+
+        let end = match error {
+            ValidationError::SchemaViolation(SchemaViolationError::UnalignedBlocks {
+                skipped_schema,
+                ..
+            }) => first + skipped_schema,
+            // The input ran out at this block, so it has nothing for the rest
+            ValidationError::SchemaViolation(
+                SchemaViolationError::MissingContent { .. }
+                | SchemaViolationError::MalformedNodeStructure {
+                    kind: MalformedStructureKind::SchemaHasChildInputDoesnt,
+                    ..
+                },
+            ) if blocks[first].indexes.start == schema_index => blocks.len(),
+            _ => first + 1,
+        };
+        for block in &blocks[first..end.min(blocks.len())] {
+            let section_status = &mut statuses[block.section];
+            *section_status = (*section_status).max(status);
+        }
+    }
+
+    // Validation stops at the first error, so nothing after it matched
+    if validator.options().fast_fail
+        && let Some(first_failed) = statuses
+            .iter()
+            .position(|status| *status == SectionStatus::Failed)
+    {
+        statuses[first_failed..].fill(SectionStatus::Failed);
+    }
+
+    statuses
+}
+
+/// How many blocks of the input an error says the schema didn't expect.
+fn extra_input_blocks(error: &ValidationError) -> usize {
+    match error {
+        ValidationError::SchemaViolation(SchemaViolationError::TrailingContent {
+            extra_blocks,
+            ..
+        }) => *extra_blocks,
+        ValidationError::SchemaViolation(SchemaViolationError::UnalignedBlocks {
+            skipped_input,
+            ..
+        }) => *skipped_input,
+        _ => 0,
+    }
+}
+
+/// How many values the input has for each matcher of the schema that should
+/// capture one.
+fn field_stats(validator: &Validator) -> Vec<FieldStats> {
+    let mut fields = Vec::new();
+
+    visit_code_spans(validator.schema_tree(), |cursor| {
+        let Ok(matcher) = Matcher::try_from_schema_cursor(cursor, validator.schema_str()) else {
+            return;
+        };
+        let Some(id) = matcher.id() else {
+            return;
+        };
+        let is_ruler = id == RULER_PATTERN && matches!(matcher.kind(), MatcherKind::All);
+        if is_ruler || matcher.is_optional() || matcher.back_reference().is_some() {
+            return;
+        }
+
+        let mut path = validator.schema_scopes().scope_path(&cursor.node());
+        path.extend(match_path(id));
+        let captured = values_at_path(validator.matches_so_far(), &path)
+            .into_iter()
+            .map(|value| match value {
+                Value::Array(items) if matcher.is_repeated() => items.len(),
+                _ => 1,
+            })
+            .sum();
+
+        let extras = matcher.extras();
+        let expected = match (matcher.is_repeated(), extras.max_items()) {
+            (false, _) => 1,
+            (true, Some(_)) => extras.min_items_or(0),
+            (true, None) => extras.min_items_or(0).max(captured),
+        };
+
+        fields.push(FieldStats {
+            id: path.join("."),
+            captured: captured.min(expected),
+            expected,
+        });
+    });
+
+    fields
+}
+
+/// The values at the end of a path of keys, looking in every item of the
+/// arrays along the way, like the matches of repeated sections.
+fn values_at_path<'v>(value: &'v Value, path: &[String]) -> Vec<&'v Value> {
+    match (value, path.split_first()) {
+        (Value::Null, _) => Vec::new(),
+        (value, None) => vec![value],
+        (Value::Object(object), Some((key, rest))) => object
+            .get(key.as_str())
+            .map_or_else(Vec::new, |value| values_at_path(value, rest)),
+        (Value::Array(items), Some(_)) => items
+            .iter()
+            .flat_map(|item| values_at_path(item, path))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mdschema::validation::validator::ValidateOptions;
+
+    const SCHEMA: &str = r#"# Report `title:/\w+/`
+
+## Summary
+
+Owner: `owner:/\w+/`
+
+## Steps
+
+- `step:/.+/`{2,3}
+
+## Status
+
+Status: `status:/(done|todo)/`
+
+## Notes
+
+- `note:/.+/`{1,}
+
+## Contact
+
+Email: `email:/\S+@\S+/`
+
+Reach us on the forum.
+
+## License
+
+License: `license:/\w+/`
+"#;
+
+    /// Matches the first sections, has a status that doesn't match, a typo
+    /// that is suppressed, and stops before the license.
+    const INPUT: &str = r#"# Report Weekly
+
+## Summary
+
+Owner: Alice
+
+## Steps
+
+- Plan
+- Ship
+
+## Status
+
+Status: blocked
+
+## Notes
+
+- First
+- Second
+
+## Contact
+
+Email: someone@example.com
+
+<!-- mdvalidate-disable-next-block -->
+Reach us on the forums.
+"#;
+
+    fn stats_of(schema: &str, input: &str) -> ValidationStats {
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+        ValidationStats::from_validator(&validator)
+    }
+
+    fn field(id: &str, captured: usize, expected: usize) -> FieldStats {
+        FieldStats {
+            id: id.to_string(),
+            captured,
+            expected,
+        }
+    }
+
+    #[test]
+    fn test_stats_of_a_partly_matching_report() {
+        let stats = stats_of(SCHEMA, INPUT);
+
+        assert_eq!(
+            stats,
+            ValidationStats {
+                sections: 7,
+                sections_matched: 5,
+                sections_with_warnings: 1,
+                extra_input_blocks: 0,
+                fields_captured: 7,
+                fields_expected: 9,
+                fields: vec![
+                    field("title", 1, 1),
+                    field("owner", 1, 1),
+                    field("step", 2, 2),
+                    field("status", 0, 1),
+                    field("note", 2, 2),
+                    field("email", 1, 1),
+                    field("license", 0, 1),
+                ],
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "5/7 sections matched, 7/9 fields captured"
+        );
+    }
+
+    #[test]
+    fn test_bounded_repeats_count_up_to_their_minimum() {
+        let schema = "# Steps\n\n- `step:/.+/`{2,5}\n\n# Notes\n\n- `note:/.+/`{1,}\n";
+        let input = "# Steps\n\n- Plan\n- Build\n- Ship\n\n# Notes\n\n- A\n- B\n- C\n";
+
+        let stats = stats_of(schema, input);
+
+        assert_eq!(stats.sections_matched, 2);
+        assert_eq!(stats.fields, vec![field("step", 2, 2), field("note", 3, 3)]);
+    }
+
+    #[test]
+    fn test_empty_input_matches_no_sections() {
+        let stats = stats_of("Intro `intro:/\\w+/`\n\n# A\n\n# B\n", "");
+
+        assert_eq!(stats.sections, 3);
+        assert_eq!(stats.sections_matched, 0);
+        assert_eq!(
+            stats.to_string(),
+            "0/3 sections matched, 0/1 fields captured"
+        );
+    }
+
+    #[test]
+    fn test_fast_fail_fails_the_sections_after_the_first_error() {
+        let schema = "# A\n\nSome text\n\n# B\n\nMore text\n";
+        let input = "# A\n\nOther text\n\n# B\n\nMore text\n";
+
+        let mut validator = Validator::new_complete(schema, input)
+            .unwrap()
+            .with_options(ValidateOptions {
+                fast_fail: true,
+                ..Default::default()
+            });
+        validator.validate();
+        let stats = ValidationStats::from_validator(&validator);
+
+        assert_eq!(stats.sections_matched, 0);
+        assert_eq!(
+            serde_json::to_value(&stats).unwrap(),
+            json!({
+                "sections": 2,
+                "sections_matched": 0,
+                "sections_with_warnings": 0,
+                "extra_input_blocks": 0,
+                "fields_captured": 0,
+                "fields_expected": 0,
+                "fields": [],
+            })
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Suppression comments in the input.
+//!
+//! A document can opt a single block out of some checks with a comment right
+//! before it:
+//!
+//! ```md
+//! <!-- mdvalidate-disable-next-block MDV005,MDV010 -->
+//! # A heading the schema doesn't expect
+//! ```
+//!
+//! Errors with one of the listed codes whose input node is in that block are
+//! reported as suppressed warnings instead of errors. Without any codes every
+//! error in the block is suppressed.
+use std::sync::LazyLock;
+
+use regex::Regex;
+use tree_sitter::Node;
+
+use crate::mdschema::validation::errors::ValidationError;
+use crate::mdschema::validation::ts_utils::{find_node_by_index, get_node_text, is_html_comment};
+
+static DISABLE_NEXT_BLOCK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^<!--\s*mdvalidate-disable-next-block(?P<codes>(?:\s[^>]*?)?)\s*-->$").unwrap()
+});
+
+/// The codes a suppression comment disables, or `None` if the comment isn't
+/// a suppression comment. An empty list means every code.
+pub fn suppressed_codes(comment: &str) -> Option<Vec<String>> {
+    let captures = DISABLE_NEXT_BLOCK.captures(comment.trim())?;
+    Some(
+        captures["codes"]
+            .split(',')
+            .map(str::trim)
+            .filter(|code| !code.is_empty())
+            .map(str::to_uppercase)
+            .collect(),
+    )
+}
+
+/// Whether an error is disabled by a suppression comment in the input.
+///
+/// That's the case if its input node, or any block containing it, directly
+/// follows a suppression comment listing the error's code.
+///
+/// # Arguments
+///
+/// * `error`: The error to check.
+/// * `input_root`: The root node of the input.
+/// * `input_str`: The input (so far).
+pub fn is_suppressed(error: &ValidationError, input_root: Node, input_str: &str) -> bool {
+    let (_, Some(input_index)) = error.node_indexes() else {
+        return false;
+    };
+
+    let mut node = Some(find_node_by_index(input_root, input_index));
+    while let Some(current) = node {
+        if let Some(previous) = current.prev_sibling()
+            && is_html_comment(&previous, input_str)
+            && let Some(codes) = suppressed_codes(get_node_text(&previous, input_str))
+            && (codes.is_empty() || codes.iter().any(|code| code == error.code()))
+        {
+            return true;
+        }
+        node = current.parent();
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suppressed_codes() {
+        assert_eq!(
+            suppressed_codes("<!-- mdvalidate-disable-next-block -->"),
+            Some(vec![])
+        );
+        assert_eq!(
+            suppressed_codes("<!--mdvalidate-disable-next-block mdv005, MDV010-->\n"),
+            Some(vec!["MDV005".to_string(), "MDV010".to_string()])
+        );
+        assert_eq!(suppressed_codes("<!-- mdvalidate-disable-next-blocks -->"), None);
+        assert_eq!(suppressed_codes("<!-- just a comment -->"), None);
+    }
+}
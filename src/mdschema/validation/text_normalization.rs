@@ -0,0 +1,447 @@
+//! Normalizations applied to literal text before comparing schema and input.
+//!
+//! By default literal text is compared exactly. Normalizations can be turned
+//! on for the whole schema with a directive comment:
+//!
+//! ```md
+//! <!-- mds:compare case-insensitive, collapse-whitespace -->
+//! ```
+//!
+//! Or for a single literal text node with a trailing modifier:
+//!
+//! ```md
+//! # Getting started {compare: case-insensitive}
+//! ```
+//!
+//! Boilerplate whose wording may drift a little can instead be compared by how
+//! similar it is (see `utils::similarity`), with a trailing modifier giving
+//! how similar it has to be, from 0 to 1:
+//!
+//! ```md
+//! This document describes the public API of the service. {similar: 0.9}
+//! ```
+use core::fmt;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::LazyLock;
+use tree_sitter::Tree;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::mdschema::validation::errors::{SchemaError, ValidationError};
+use crate::mdschema::validation::ts_utils::descendant_index_spanning;
+
+/// Schema-wide directive enabling normalizations.
+static COMPARE_DIRECTIVE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<!--\s*mds:compare\s+(?P<modes>[^>]*?)\s*-->").unwrap());
+
+/// Per-node modifier at the end of a literal text node.
+static COMPARE_MODIFIER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s*\{compare:\s*(?P<modes>[a-z\-,\s]+?)\s*\}\s*$").unwrap());
+
+/// Per-node modifier at the end of a literal text node asking for a similarity
+/// comparison.
+static SIMILAR_MODIFIER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\s*\{similar:\s*(?P<threshold>[01](?:\.\d{1,3})?|\.\d{1,3})\s*\}\s*$").unwrap()
+});
+
+pub const CASE_INSENSITIVE: &str = "case-insensitive";
+pub const COLLAPSE_WHITESPACE: &str = "collapse-whitespace";
+pub const UNICODE_NFC: &str = "nfc";
+
+/// Every comparison mode, as it is written in a schema.
+pub const COMPARE_MODES: &[&str] = &[CASE_INSENSITIVE, COLLAPSE_WHITESPACE, UNICODE_NFC];
+
+/// Which normalizations to apply to literal text before comparing it.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextNormalizations {
+    /// Compare text ignoring case.
+    pub case_insensitive: bool,
+    /// Treat any run of whitespace as a single space, and ignore leading and
+    /// trailing whitespace.
+    pub collapse_whitespace: bool,
+    /// Normalize both sides to Unicode normalization form C.
+    pub unicode_nfc: bool,
+}
+
+impl TextNormalizations {
+    /// Parse a comma separated list of modes, like
+    /// `case-insensitive, collapse-whitespace`.
+    ///
+    /// # Arguments
+    ///
+    /// * `modes`: The list of modes.
+    /// * `schema_index`: The descendant index of the schema node the modes
+    ///   are in, for the error about a mode we don't know.
+    pub fn from_modes_str(modes: &str, schema_index: usize) -> Result<Self, SchemaError> {
+        let mut normalizations = Self::default();
+        for mode in modes
+            .split(',')
+            .map(str::trim)
+            .filter(|mode| !mode.is_empty())
+        {
+            match mode {
+                CASE_INSENSITIVE => normalizations.case_insensitive = true,
+                COLLAPSE_WHITESPACE => normalizations.collapse_whitespace = true,
+                UNICODE_NFC => normalizations.unicode_nfc = true,
+                _ => {
+                    return Err(SchemaError::UnknownCompareMode {
+                        schema_index,
+                        mode: mode.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(normalizations)
+    }
+
+    /// Collect every `<!-- mds:compare ... -->` directive in a schema.
+    ///
+    /// Returns the normalizations, and an error for every directive with a
+    /// mode we don't know, which is left out.
+    pub fn from_schema_directives(
+        schema_tree: &Tree,
+        schema_str: &str,
+    ) -> (Self, Vec<ValidationError>) {
+        let mut normalizations = Self::default();
+        let mut errors = Vec::new();
+        for modes in COMPARE_DIRECTIVE
+            .captures_iter(schema_str)
+            .filter_map(|caps| caps.name("modes"))
+        {
+            let schema_index = descendant_index_spanning(schema_tree, modes.range());
+            match Self::from_modes_str(modes.as_str(), schema_index) {
+                Ok(modes) => normalizations = normalizations.union(modes),
+                Err(error) => errors.push(ValidationError::SchemaError(error)),
+            }
+        }
+        (normalizations, errors)
+    }
+
+    /// Split a trailing `{compare: ...}` modifier off a literal schema text.
+    ///
+    /// Returns the text without the modifier, and the normalizations the
+    /// modifier asked for (which are exact if there was no modifier).
+    ///
+    /// # Arguments
+    ///
+    /// * `schema_text`: The literal schema text.
+    /// * `schema_index`: The descendant index of its node, for the error
+    ///   about a mode we don't know.
+    pub fn split_node_modifier(
+        schema_text: &str,
+        schema_index: usize,
+    ) -> Result<(&str, Self), SchemaError> {
+        match COMPARE_MODIFIER.captures(schema_text) {
+            Some(caps) => {
+                let modifier_start = caps.get(0).map_or(schema_text.len(), |m| m.start());
+                let modes = caps.name("modes").map_or("", |m| m.as_str());
+                let normalizations = Self::from_modes_str(modes, schema_index)?;
+                Ok((&schema_text[..modifier_start], normalizations))
+            }
+            None => Ok((schema_text, Self::default())),
+        }
+    }
+
+    /// Enable every normalization that is enabled in either.
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            case_insensitive: self.case_insensitive || other.case_insensitive,
+            collapse_whitespace: self.collapse_whitespace || other.collapse_whitespace,
+            unicode_nfc: self.unicode_nfc || other.unicode_nfc,
+        }
+    }
+
+    /// Whether we compare text exactly, without any normalization.
+    pub fn is_exact(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Normalize a piece of text.
+    pub fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+
+        if self.unicode_nfc {
+            text = Cow::Owned(text.nfc().collect());
+        }
+        if self.collapse_whitespace {
+            text = Cow::Owned(text.split_whitespace().collect::<Vec<_>>().join(" "));
+        }
+        if self.case_insensitive {
+            text = Cow::Owned(text.to_lowercase());
+        }
+
+        text
+    }
+
+    /// Whether the two texts are equal after normalization.
+    pub fn texts_equal(&self, schema_text: &str, input_text: &str) -> bool {
+        self.normalize(schema_text) == self.normalize(input_text)
+    }
+
+    /// Whether the input text is a prefix of the schema text after
+    /// normalization. Used while we are still waiting for more input.
+    pub fn texts_prefix(&self, schema_text: &str, input_text: &str) -> bool {
+        self.normalize(schema_text)
+            .starts_with(self.normalize(input_text).as_ref())
+    }
+}
+
+/// Split a trailing `{similar: 0.9}` modifier off a literal schema text.
+///
+/// Returns the text without the modifier, and how similar the input has to be
+/// in thousandths (see `utils::similarity`), if there was a modifier. A
+/// threshold over 1 isn't a modifier.
+pub fn split_similarity_modifier(schema_text: &str) -> (&str, Option<u16>) {
+    let Some(caps) = SIMILAR_MODIFIER.captures(schema_text) else {
+        return (schema_text, None);
+    };
+
+    let threshold = caps["threshold"]
+        .parse::<f64>()
+        .ok()
+        .map(|threshold| (threshold * 1000.0).round() as u16)
+        .filter(|threshold| *threshold <= 1000);
+    match (threshold, caps.get(0)) {
+        (Some(threshold), Some(modifier)) => (&schema_text[..modifier.start()], Some(threshold)),
+        _ => (schema_text, None),
+    }
+}
+
+/// Text with Windows line endings read as `\n`, and the whitespace at the end
+/// of each line removed, that remembers where its bytes came from.
+///
+/// Documents written on Windows end their lines with `\r\n`, and editors
+/// tend to leave trailing spaces behind, neither of which shows in a
+/// terminal. Unless `ValidateOptions::strict_line_endings` is set, input text
+/// is compared, and given to matchers, in this form.
+///
+/// Only whole ends of lines are removed, so every byte that is kept is on
+/// the same line and column as in the original text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedLines<'a> {
+    text: Cow<'a, str>,
+    /// Where each line starts, in the normalized and the original text.
+    line_starts: Vec<(usize, usize)>,
+}
+
+impl<'a> NormalizedLines<'a> {
+    pub fn new(original: &'a str) -> Self {
+        let mut text = String::with_capacity(original.len());
+        let mut line_starts = Vec::new();
+        let mut original_start = 0;
+        for line in original.split_inclusive('\n') {
+            line_starts.push((text.len(), original_start));
+            let content = line.strip_suffix('\n').unwrap_or(line);
+            text.push_str(content.trim_end_matches([' ', '\t', '\r']));
+            if content.len() < line.len() {
+                text.push('\n');
+            }
+            original_start += line.len();
+        }
+
+        // Only the end was trimmed, so we can keep borrowing the original
+        let text = match original.starts_with(text.as_str()) {
+            true => Cow::Borrowed(&original[..text.len()]),
+            false => Cow::Owned(text),
+        };
+        Self { text, line_starts }
+    }
+
+    /// Text that is left as it is, for when we compare line endings exactly.
+    pub fn exact(original: &'a str) -> Self {
+        Self {
+            text: Cow::Borrowed(original),
+            line_starts: vec![(0, 0)],
+        }
+    }
+
+    /// The normalized text.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The byte offset in the original text of a byte offset in the
+    /// normalized text.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        let line = self
+            .line_starts
+            .partition_point(|(start, _)| *start <= offset)
+            .saturating_sub(1);
+        self.line_starts
+            .get(line)
+            .map_or(offset, |(start, original_start)| original_start + offset - start)
+    }
+}
+
+impl fmt::Display for TextNormalizations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modes: Vec<&str> = [
+            (self.unicode_nfc, UNICODE_NFC),
+            (self.collapse_whitespace, COLLAPSE_WHITESPACE),
+            (self.case_insensitive, CASE_INSENSITIVE),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect();
+
+        if modes.is_empty() {
+            write!(f, "exact")
+        } else {
+            write!(f, "{}", modes.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    #[test]
+    fn test_from_modes_str() {
+        let normalizations =
+            TextNormalizations::from_modes_str("case-insensitive, collapse-whitespace,", 0)
+                .unwrap();
+
+        assert!(normalizations.case_insensitive);
+        assert!(normalizations.collapse_whitespace);
+        assert!(!normalizations.unicode_nfc);
+    }
+
+    #[test]
+    fn test_from_modes_str_rejects_unknown_modes() {
+        let error = TextNormalizations::from_modes_str("case-insensitive, bogus", 3).unwrap_err();
+        assert_eq!(
+            error,
+            SchemaError::UnknownCompareMode {
+                schema_index: 3,
+                mode: "bogus".to_string(),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Unknown comparison mode 'bogus', expected one of: \
+             case-insensitive, collapse-whitespace, nfc"
+        );
+    }
+
+    #[test]
+    fn test_from_schema_directives() {
+        let schema = "<!-- mds:compare case-insensitive -->\n\n# Title\n\n<!-- mds:compare nfc -->\n";
+        let tree = parse_markdown(schema).unwrap();
+        let (normalizations, errors) = TextNormalizations::from_schema_directives(&tree, schema);
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(normalizations.case_insensitive);
+        assert!(normalizations.unicode_nfc);
+        assert!(!normalizations.collapse_whitespace);
+
+        let tree = parse_markdown("# Title").unwrap();
+        let (normalizations, _) = TextNormalizations::from_schema_directives(&tree, "# Title");
+        assert!(normalizations.is_exact());
+
+        // A directive with a mode we don't know is left out
+        let schema = "# Title\n\n<!-- mds:compare nfd, case-insensitive -->\n";
+        let tree = parse_markdown(schema).unwrap();
+        let (normalizations, errors) = TextNormalizations::from_schema_directives(&tree, schema);
+        assert!(normalizations.is_exact());
+        assert!(matches!(
+            errors.as_slice(),
+            [ValidationError::SchemaError(SchemaError::UnknownCompareMode {
+                schema_index,
+                mode,
+            })] if *schema_index > 0 && mode == "nfd"
+        ));
+    }
+
+    #[test]
+    fn test_split_node_modifier() {
+        let (text, normalizations) = TextNormalizations::split_node_modifier(
+            "Getting Started {compare: case-insensitive}",
+            0,
+        )
+        .unwrap();
+        assert_eq!(text, "Getting Started");
+        assert!(normalizations.case_insensitive);
+
+        let (text, normalizations) =
+            TextNormalizations::split_node_modifier("Getting Started", 0).unwrap();
+        assert_eq!(text, "Getting Started");
+        assert!(normalizations.is_exact());
+
+        assert!(
+            TextNormalizations::split_node_modifier("Getting Started {compare: nfd}", 0).is_err()
+        );
+    }
+
+    #[test]
+    fn test_split_similarity_modifier() {
+        assert_eq!(
+            split_similarity_modifier("Some boilerplate. {similar: 0.9}"),
+            ("Some boilerplate.", Some(900))
+        );
+        assert_eq!(
+            split_similarity_modifier("Text {similar:.85}"),
+            ("Text", Some(850))
+        );
+        assert_eq!(
+            split_similarity_modifier("Text {similar: 1}"),
+            ("Text", Some(1000))
+        );
+        assert_eq!(
+            split_similarity_modifier("Text {similar: 1.5}"),
+            ("Text {similar: 1.5}", None)
+        );
+        assert_eq!(split_similarity_modifier("Text"), ("Text", None));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let normalizations = TextNormalizations {
+            case_insensitive: true,
+            collapse_whitespace: true,
+            unicode_nfc: true,
+        };
+
+        assert!(normalizations.texts_equal("Hello  World ", "hello world"));
+        // "e" followed by a combining acute accent vs the precomposed "é"
+        assert!(normalizations.texts_equal("Caf\u{0065}\u{0301}", "café"));
+        assert!(!normalizations.texts_equal("Hello", "Goodbye"));
+        assert!(normalizations.texts_prefix("Hello World", "hello  wo"));
+        assert!(TextNormalizations::default().texts_equal("Hello", "Hello"));
+        assert!(!TextNormalizations::default().texts_equal("Hello", "hello"));
+    }
+
+    #[test]
+    fn test_normalized_lines() {
+        let normalized = NormalizedLines::new("Name: Alice  \r\nAge: 30\r\n");
+        assert_eq!(normalized.as_str(), "Name: Alice\nAge: 30\n");
+        // "3" of the second line, after the removed "  \r"
+        assert_eq!(normalized.original_offset(17), 20);
+        assert_eq!(normalized.original_offset(6), 6);
+
+        // Trimming only the end borrows the original
+        let original = "Name: Alice \t";
+        let normalized = NormalizedLines::new(original);
+        assert_eq!(normalized.as_str(), "Name: Alice");
+        assert!(matches!(normalized.text, Cow::Borrowed(_)));
+
+        assert_eq!(NormalizedLines::new("").as_str(), "");
+        assert_eq!(NormalizedLines::new("a\n\n b").as_str(), "a\n\n b");
+    }
+
+    #[test]
+    fn test_display() {
+        let normalizations = TextNormalizations {
+            case_insensitive: true,
+            collapse_whitespace: true,
+            unicode_nfc: false,
+        };
+        assert_eq!(
+            normalizations.to_string(),
+            "collapse-whitespace, case-insensitive"
+        );
+        assert_eq!(TextNormalizations::default().to_string(), "exact");
+    }
+}
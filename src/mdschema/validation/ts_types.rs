@@ -72,6 +72,18 @@ node_kind_pair!(
     "Check if both nodes are link text nodes.",
     ["link_text"]
 );
+node_kind_pair!(
+    is_link_label_node,
+    both_are_link_label_nodes,
+    "Check if both nodes are link label nodes.",
+    ["link_label"]
+);
+node_kind_pair!(
+    is_link_reference_definition_node,
+    both_are_link_reference_definition_nodes,
+    "Check if both nodes are link reference definition nodes.",
+    ["link_reference_definition"]
+);
 node_kind_pair!(
     is_image_node,
     both_are_image_nodes,
@@ -90,6 +102,12 @@ node_kind_pair!(
     "Check if both nodes are link description nodes.",
     ["link_text", "image_description"]
 );
+node_kind_pair!(
+    is_document_node,
+    both_are_documents,
+    "Check if both nodes are document nodes.",
+    ["document"]
+);
 node_kind_pair!(
     is_paragraph_node,
     both_are_paragraphs,
@@ -120,6 +138,24 @@ node_kind_pair!(
     "Check if both nodes are headings.",
     ["atx_heading"]
 );
+node_kind_pair!(
+    is_setext_heading_node,
+    both_are_setext_headings,
+    "Check if both nodes are setext headings, which are underlined with `===` or `---`.",
+    ["setext_heading"]
+);
+node_kind_pair!(
+    is_any_heading_node,
+    both_are_any_headings,
+    "Check if both nodes are headings, ATX or setext, maybe one of each.",
+    ["atx_heading", "setext_heading"]
+);
+node_predicate_pair!(
+    is_setext_underline_node,
+    both_are_setext_underlines,
+    "Check if both nodes are the underlines of setext headings.",
+    |node: &Node| node.kind().starts_with("setext_h") && node.kind().ends_with("_underline")
+);
 node_kind_pair!(
     is_ruler_node,
     both_are_rulers,
@@ -186,6 +222,30 @@ node_kind_pair!(
     "Check if both nodes are table delimiter rows.",
     ["table_delimiter_row"]
 );
+node_kind_pair!(
+    is_emphasis_node,
+    both_are_emphasis,
+    "Check if both nodes are emphasis, strong emphasis or strikethrough nodes.",
+    ["emphasis", "strong_emphasis", "strikethrough"]
+);
+node_kind_pair!(
+    is_emphasis_delimiter_node,
+    both_are_emphasis_delimiters,
+    "Check if both nodes are emphasis delimiter nodes.",
+    ["emphasis_delimiter"]
+);
+node_kind_pair!(
+    is_html_block_node,
+    both_are_html_blocks,
+    "Check if both nodes are HTML block nodes.",
+    ["html_block"]
+);
+node_kind_pair!(
+    is_inline_html_node,
+    both_are_inline_html,
+    "Check if both nodes are inline HTML nodes.",
+    ["html_tag", "html_comment"]
+);
 node_predicate_pair!(
     is_marker_node,
     both_are_markers,
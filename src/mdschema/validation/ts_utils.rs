@@ -9,12 +9,21 @@ use crate::mdschema::validation::ts_types::*;
 use crate::mdschema::validation::{errors::ValidationError, validator::ValidatorState};
 
 use regex::Regex;
+use std::ops::Range;
 use std::sync::LazyLock;
 
 /// Extract text from a tree-sitter node using the provided source string.
+///
+/// If the node's range doesn't line up with the char boundaries of `src`, as
+/// can happen for a tree that tree-sitter recovered from errors in, the range
+/// is widened out to the nearest ones instead of panicking.
 pub fn get_node_text<'a, S: Into<&'a str>>(node: &Node, src: S) -> &'a str {
     let src_ref = src.into();
-    let node_str = node.utf8_text(src_ref.as_bytes()).unwrap();
+    let node_str = node
+        .utf8_text(src_ref.as_bytes())
+        .unwrap_or_else(|_| {
+            get_str_at_char_boundaries(src_ref, node.start_byte(), node.end_byte())
+        });
 
     if is_table_cell_node(node) || node.parent().is_some_and(|n| is_table_cell_node(&n)) {
         node_str.trim_start().trim_end()
@@ -23,6 +32,116 @@ pub fn get_node_text<'a, S: Into<&'a str>>(node: &Node, src: S) -> &'a str {
     }
 }
 
+/// Get `src[start..end]`, widening the range out to the nearest char
+/// boundaries instead of panicking when an offset lands inside a multi-byte
+/// character.
+///
+/// Offsets past the end of `src` are clamped to its length.
+pub fn get_str_at_char_boundaries(src: &str, start: usize, end: usize) -> &str {
+    if let Some(slice) = src.get(start..end) {
+        return slice;
+    }
+
+    let mut start = start.min(src.len());
+    let mut end = end.clamp(start, src.len());
+    while !src.is_char_boundary(start) {
+        start -= 1;
+    }
+    while !src.is_char_boundary(end) {
+        end += 1;
+    }
+    log::trace!(
+        "Widened slice to char boundaries {}..{} of a {} byte string",
+        start,
+        end,
+        src.len()
+    );
+
+    &src[start..end]
+}
+
+/// Whether a node is an HTML comment, either a whole `html_block` or an
+/// inline `html_comment`.
+pub fn is_html_comment(node: &Node, src: &str) -> bool {
+    matches!(node.kind(), "html_block" | "html_comment")
+        && get_node_text(node, src).trim_start().starts_with("<!--")
+}
+
+/// Whether a node is an HTML comment holding an `mds:` directive, like
+/// `<!-- mds:compare case-insensitive -->`.
+///
+/// Directives configure validation, so they are not compared against input.
+pub fn is_directive_comment(node: &Node, src: &str) -> bool {
+    is_html_comment(node, src) && get_node_text(node, src).trim_start().starts_with("<!-- mds:")
+}
+
+/// Whether a schema node is never compared against input: directive comments,
+/// link reference definitions (links are compared by what they resolve to
+/// instead), and front matter, which is compared line by line.
+pub fn is_schema_only_node(node: &Node, src: &str) -> bool {
+    is_directive_comment(node, src)
+        || crate::mdschema::validation::ts_types::is_link_reference_definition_node(node)
+        || crate::mdschema::validation::frontmatter::is_frontmatter_node(node, src)
+}
+
+/// Like `TreeCursor::goto_next_sibling`, but skips over nodes for which `skip`
+/// returns true.
+///
+/// If there is no later sibling that isn't skipped the cursor is left where
+/// it was.
+pub fn goto_next_sibling_skipping(cursor: &mut TreeCursor, skip: impl Fn(&Node) -> bool) -> bool {
+    let start = cursor.clone();
+    while cursor.goto_next_sibling() {
+        if !skip(&cursor.node()) {
+            return true;
+        }
+    }
+    cursor.reset_to(&start);
+    false
+}
+
+/// Like `TreeCursor::goto_first_child`, but skips over nodes for which `skip`
+/// returns true.
+///
+/// If every child is skipped the cursor is left where it was.
+pub fn goto_first_child_skipping(cursor: &mut TreeCursor, skip: impl Fn(&Node) -> bool) -> bool {
+    if !cursor.goto_first_child() {
+        return false;
+    }
+    if !skip(&cursor.node()) || goto_next_sibling_skipping(cursor, &skip) {
+        return true;
+    }
+    cursor.goto_parent();
+    false
+}
+
+/// Like `TreeCursor::goto_last_child`, but skips over nodes for which `skip`
+/// returns true.
+///
+/// If every child is skipped the cursor is left where it was.
+pub fn goto_last_child_skipping(cursor: &mut TreeCursor, skip: impl Fn(&Node) -> bool) -> bool {
+    if !cursor.goto_last_child() {
+        return false;
+    }
+    while skip(&cursor.node()) {
+        if !cursor.goto_previous_sibling() {
+            cursor.goto_parent();
+            return false;
+        }
+    }
+    true
+}
+
+/// Like `TreeCursor::goto_next_sibling`, but skips over schema-only nodes.
+pub fn goto_next_sibling_skipping_directives(cursor: &mut TreeCursor, src: &str) -> bool {
+    goto_next_sibling_skipping(cursor, |node| is_schema_only_node(node, src))
+}
+
+/// Like `TreeCursor::goto_first_child`, but skips over schema-only nodes.
+pub fn goto_first_child_skipping_directives(cursor: &mut TreeCursor, src: &str) -> bool {
+    goto_first_child_skipping(cursor, |node| is_schema_only_node(node, src))
+}
+
 /// Ordered lists use numbers followed by period . or right paren )
 static ORDERED_LIST_MARKER_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^\d+[.)]").unwrap());
@@ -107,6 +226,25 @@ pub fn parse_markdown(text: &str) -> Option<Tree> {
     parser.parse(text, None)
 }
 
+/// Call `visit` with a cursor at every code span of a tree, depth first.
+pub fn visit_code_spans(tree: &Tree, mut visit: impl FnMut(&TreeCursor)) {
+    let mut cursor = tree.walk();
+    loop {
+        if crate::mdschema::validation::ts_types::is_inline_code_node(&cursor.node()) {
+            visit(&cursor);
+        }
+
+        if cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return;
+            }
+        }
+    }
+}
+
 /// Determine whether a given node is the last node in the tree.
 ///
 /// It is the last node if it is the deepest and right most node that ends at
@@ -124,6 +262,18 @@ pub fn find_node_by_index(root: Node, target_index: usize) -> Node {
     cursor.node()
 }
 
+/// The descendant index of the smallest node that spans a byte range.
+pub fn descendant_index_spanning(tree: &Tree, range: Range<usize>) -> usize {
+    let root = tree.root_node();
+    let target = root
+        .descendant_for_byte_range(range.start, range.end)
+        .unwrap_or(root);
+
+    let mut cursor = tree.walk();
+    while cursor.node() != target && cursor.goto_first_child_for_byte(range.start).is_some() {}
+    cursor.descendant_index()
+}
+
 pub fn ends_at_end(node: &Node, last_input_str: &str) -> bool {
     let last_input_str = last_input_str.trim_end();
     node.byte_range().end >= last_input_str.len()
@@ -154,7 +304,7 @@ pub fn extract_list_marker<'a>(cursor: &TreeCursor<'a>, schema_str: &'a str) ->
     get_node_text(&marker, schema_str)
 }
 
-/// Extract the *type* of heading a given atx_heading is.
+/// Extract the *type* of heading a given atx_heading or setext_heading is.
 ///
 /// Headings look like this:
 ///
@@ -163,13 +313,30 @@ pub fn extract_list_marker<'a>(cursor: &TreeCursor<'a>, schema_str: &'a str) ->
 // │  ├─ (atx_h2_marker)
 // │  └─ (heading_content)
 // │     └─ (text)
+//
+// (setext_heading)
+// │  ├─ (heading_content)
+// │  │  └─ (text)
+// │  └─ (setext_h1_underline)
 // ```
 //
-// You can call `get_heading_kind` when you are at an `atx_heading` to extract
-// the literal kind, `atx_xx_marker`.
+// You can call `get_heading_kind` when you are at a heading to extract the
+// literal kind, `atx_hX_marker` or `setext_hX_underline`.
 pub fn get_heading_kind<'a>(cursor: &TreeCursor<'a>) -> Result<&'a str, ValidationError> {
     let mut cursor = cursor.clone();
 
+    if is_setext_heading_node(&cursor.node()) {
+        let node = cursor.node();
+        if let Some(underline) = node
+            .children(&mut node.walk())
+            .find(|child| is_setext_underline_node(child))
+        {
+            return Ok(underline.kind());
+        }
+        #[cfg(feature = "invariant_violations")]
+        invariant_violation!(&cursor, &cursor, "expected underline for setext_heading");
+    }
+
     #[cfg(feature = "invariant_violations")]
     if !cursor.goto_first_child() || !cursor.node().kind().ends_with("marker") {
         invariant_violation!(&cursor, &cursor, "expected heading marker for atx_heading");
@@ -177,6 +344,73 @@ pub fn get_heading_kind<'a>(cursor: &TreeCursor<'a>) -> Result<&'a str, Validati
     Ok(cursor.node().kind())
 }
 
+/// The level of a heading of a kind from `get_heading_kind`, from 1 for
+/// `atx_h1_marker` or `setext_h1_underline` to 6 for `atx_h6_marker`.
+pub fn heading_kind_level(kind: &str) -> Option<usize> {
+    kind.strip_prefix("atx_h")
+        .and_then(|level| level.strip_suffix("_marker"))
+        .or_else(|| {
+            kind.strip_prefix("setext_h")
+                .and_then(|level| level.strip_suffix("_underline"))
+        })?
+        .parse()
+        .ok()
+}
+
+/// The closing sequence of an ATX heading, like the ` ##` of `## Section ##`.
+static ATX_CLOSING_SEQUENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[ \t]+#+\s*$").unwrap());
+
+/// Where the text of a node ends, before the closing sequence of an ATX
+/// heading, like the ` ##` of `## Section ##`, if the node is the content of
+/// one or the last node of its content. Other nodes end where they end.
+pub fn text_end_without_closing_hashes(node: &Node, src: &str) -> usize {
+    let content = if is_heading_content_node(node) {
+        Some(*node)
+    } else if node.next_sibling().is_none() {
+        node.parent().filter(is_heading_content_node)
+    } else {
+        None
+    };
+    if !content
+        .and_then(|content| content.parent())
+        .is_some_and(|heading| is_heading_node(&heading))
+    {
+        return node.end_byte();
+    }
+
+    match ATX_CLOSING_SEQUENCE.find(get_node_text(node, src)) {
+        Some(closing) => node.start_byte() + closing.start(),
+        None => node.end_byte(),
+    }
+}
+
+/// The text of a node, without the closing sequence of the ATX heading it
+/// ends (see `text_end_without_closing_hashes`).
+pub fn get_node_text_without_closing_hashes<'a>(node: &Node, src: &'a str) -> &'a str {
+    get_str_at_char_boundaries(
+        src,
+        node.start_byte(),
+        text_end_without_closing_hashes(node, src),
+    )
+}
+
+/// The level of an `atx_heading` node, from 1 for `#` to 6 for `######`.
+///
+/// Returns `None` if the node isn't a heading.
+pub fn heading_level(node: &Node) -> Option<usize> {
+    if !is_heading_node(node) {
+        return None;
+    }
+
+    node.child(0)?
+        .kind()
+        .strip_prefix("atx_h")?
+        .strip_suffix("_marker")?
+        .parse()
+        .ok()
+}
+
 /// Check if the treesitter schema node has a single code_span child (indicating
 /// a matcher).
 pub fn has_single_code_child(schema_cursor: &TreeCursor) -> bool {
@@ -362,6 +596,17 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_get_str_at_char_boundaries() {
+        let src = "hi 😀 there";
+
+        assert_eq!(get_str_at_char_boundaries(src, 0, 2), "hi");
+        // The emoji spans bytes 3..7, so offsets inside it widen to include it
+        assert_eq!(get_str_at_char_boundaries(src, 0, 5), "hi 😀");
+        assert_eq!(get_str_at_char_boundaries(src, 5, 8), "😀 ");
+        assert_eq!(get_str_at_char_boundaries(src, 8, 100), "there");
+    }
+
     #[test]
     fn test_is_ordered_list_marker() {
         assert!(is_ordered_list_marker("1."));
@@ -531,6 +776,40 @@ mod tests {
         assert_eq!(node.kind(), "text");
     }
 
+    #[test]
+    fn test_heading_kind_level() {
+        assert_eq!(heading_kind_level("atx_h1_marker"), Some(1));
+        assert_eq!(heading_kind_level("atx_h6_marker"), Some(6));
+        assert_eq!(heading_kind_level("setext_h2_underline"), Some(2));
+        assert_eq!(heading_kind_level("list_marker_minus"), None);
+    }
+
+    #[test]
+    fn test_text_without_closing_hashes() {
+        let input = "## Section ##\n\n# C#\n\nSome text ##\n";
+        let tree = parse_markdown(input).unwrap();
+        let root_node = tree.root_node();
+
+        // The heading content, and its text
+        let heading_text = find_node_by_index(root_node, 4);
+        assert_eq!(
+            get_node_text_without_closing_hashes(&find_node_by_index(root_node, 3), input),
+            "Section"
+        );
+        assert_eq!(
+            get_node_text_without_closing_hashes(&heading_text, input),
+            "Section"
+        );
+
+        // Hashes that are part of the text, or not in a heading, stay
+        let texts: Vec<&str> = (5..root_node.descendant_count())
+            .map(|index| find_node_by_index(root_node, index))
+            .filter(|node| is_text_node(node))
+            .map(|node| get_node_text_without_closing_hashes(&node, input))
+            .collect();
+        assert_eq!(texts, vec!["C#", "Some text ##"]);
+    }
+
     #[test]
     fn test_get_node_and_next_node_with_both() {
         let input = "# Heading\n\nThis is a paragraph.";
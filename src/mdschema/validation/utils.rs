@@ -19,6 +19,113 @@ pub fn join_values(a: &mut Value, b: Value) {
     }
 }
 
+/// Combine a value captured under an id that already has a value into one
+/// array, in the order they were captured. Arrays, like the captures of a
+/// repeated matcher, are spread into it rather than nested.
+pub fn collect_values(existing: &mut Value, new: Value) {
+    let mut values = match existing.take() {
+        Value::Array(values) => values,
+        value => vec![value],
+    };
+    match new {
+        Value::Array(new_values) => values.extend(new_values),
+        value => values.push(value),
+    }
+    *existing = Value::Array(values);
+}
+
+/// The candidate closest to `name`, for "did you mean" suggestions.
+///
+/// Closeness is the edit distance between the two, ignoring case. Returns
+/// `None` if no candidate is within `max_distance` edits.
+pub fn closest_name<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    max_distance: usize,
+) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between two strings, ignoring case.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    edit_distances_to_prefixes(&a, &b)[b.len()]
+}
+
+/// The Levenshtein distance between `a` and each prefix of `b`, from the
+/// empty one to all of `b`.
+fn edit_distances_to_prefixes(a: &[char], b: &[char]) -> Vec<usize> {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous
+}
+
+/// How similar two texts are, in thousandths, ignoring case and treating any
+/// run of whitespace as a single space.
+///
+/// This is the share of the characters of the longer text that don't have to
+/// be edited to turn one into the other, rounded down, so two texts are at
+/// least `threshold` similar exactly when this is `>= threshold`. Two empty
+/// texts are the same.
+pub fn similarity(a: &str, b: &str) -> u16 {
+    let a = similarity_chars(a);
+    let b = similarity_chars(b);
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1000;
+    }
+
+    let distance = edit_distances_to_prefixes(&a, &b)[b.len()];
+    ((longest - distance) * 1000 / longest) as u16
+}
+
+/// Whether a text that starts with `input` could still be at least
+/// `threshold` similar to `schema` (see `similarity`), once the rest of it
+/// comes in.
+pub fn could_become_similar(schema: &str, input: &str, threshold: u16) -> bool {
+    if threshold == 0 {
+        return true;
+    }
+
+    let schema = similarity_chars(schema);
+    let input = similarity_chars(input);
+    let threshold = usize::from(threshold);
+    // Every character the input has past the schema's length is an edit, so
+    // the whole input can only be so long. The edits it already needs are at
+    // least those to the closest prefix of the schema, and they are allowed
+    // the most when the whole input is that long.
+    let closest = edit_distances_to_prefixes(&input, &schema)
+        .into_iter()
+        .min()
+        .unwrap_or_default();
+    input.len() * threshold <= schema.len() * 1000
+        && closest * threshold <= (1000 - threshold) * schema.len()
+}
+
+/// The characters of a text that `similarity` compares.
+fn similarity_chars(text: &str) -> Vec<char> {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .chars()
+        .collect()
+}
+
+#[cfg(feature = "cli")]
 #[allow(dead_code)]
 pub fn test_logging() {
     use tracing_subscriber::EnvFilter;
@@ -46,7 +153,10 @@ pub fn parse_markdown_and_get_tree(input: &str) -> Tree {
 
 #[cfg(test)]
 mod tests {
-    use super::{join_values, parse_markdown_and_get_tree, Value};
+    use super::{
+        Value, closest_name, collect_values, could_become_similar, join_values,
+        parse_markdown_and_get_tree, similarity,
+    };
     use crate::mdschema::validation::ts_utils::CodeblockContents;
 
     #[test]
@@ -175,4 +285,53 @@ mod tests {
             panic!("a is not an array");
         }
     }
+
+    #[test]
+    fn test_collect_values() {
+        let mut a = serde_json::json!("one");
+        collect_values(&mut a, serde_json::json!("two"));
+        assert_eq!(a, serde_json::json!(["one", "two"]));
+
+        collect_values(&mut a, serde_json::json!(["three", "four"]));
+        assert_eq!(a, serde_json::json!(["one", "two", "three", "four"]));
+
+        let mut a = serde_json::json!(["one"]);
+        collect_values(&mut a, serde_json::json!({"value": "two"}));
+        assert_eq!(a, serde_json::json!(["one", {"value": "two"}]));
+    }
+
+    #[test]
+    fn test_closest_name() {
+        let names = ["date", "uuid", "url", "semver", "email"];
+        assert_eq!(closest_name("dat", names, 2), Some("date"));
+        assert_eq!(closest_name("SemVer", names, 2), Some("semver"));
+        assert_eq!(closest_name("ulr", names, 2), Some("url"));
+        assert_eq!(closest_name("timestamp", names, 2), None);
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("Hello  World", "hello world"), 1000);
+        assert_eq!(similarity("", ""), 1000);
+        // One edit in ten characters
+        assert_eq!(similarity("abcdefghij", "abcdefghiX"), 900);
+        assert_eq!(similarity("abcdefghij", "abcdefghi"), 900);
+        // Two edits in eleven characters is a little less than 0.9
+        assert_eq!(similarity("abcdefghijk", "abcdefghiXY"), 818);
+        assert_eq!(similarity("abc", ""), 0);
+    }
+
+    #[test]
+    fn test_could_become_similar() {
+        let schema = "abcdefghij";
+        assert!(could_become_similar(schema, "abcde", 900));
+        assert!(could_become_similar(schema, "abXde", 900));
+        // Two edits are too many for ten characters at 0.9
+        assert!(!could_become_similar(schema, "aXcXe", 900));
+        assert!(could_become_similar(schema, "aXcXe", 800));
+        // The input can be at most one character longer than the schema
+        assert!(could_become_similar(schema, "abcdefghijk", 900));
+        assert!(!could_become_similar(schema, "abcdefghijkl", 900));
+        assert!(could_become_similar(schema, "anything", 0));
+    }
 }
@@ -0,0 +1,378 @@
+//! One-call validation, for embedding `mdvalidate` as a library.
+//!
+//! Instead of driving a `Validator` by hand, validate a whole document at once
+//! and get the matches and errors back together:
+//!
+//! ```no_run
+//! let validation = mdvalidate::validate_str("# `title:/\\w+/`\n", "# Hello\n").unwrap();
+//! assert!(validation.is_valid);
+//! assert_eq!(validation.matches["title"], "Hello");
+//! ```
+//!
+//! `validate_reader` does the same for input that is read in chunks, feeding
+//! it to the validator as it comes in.
+use std::fmt;
+use std::io::Read;
+
+use serde_json::Value;
+
+use crate::mdschema::validation::errors::ValidationError;
+use crate::mdschema::validation::json_report::ValidatorReport;
+use crate::mdschema::validation::match_stream::{MatchStream, MatchUpdate};
+use crate::mdschema::validation::progress::ProgressHook;
+use crate::mdschema::validation::validator::{ValidateOptions, Validator, ValidatorState};
+
+static DEFAULT_BUFFER_SIZE: usize = 2048;
+
+/// The byte order mark some (mostly Windows) tools put at the start of UTF-8 files.
+const UTF8_BOM: char = '\u{FEFF}';
+
+/// The outcome of validating a whole document.
+#[derive(Debug)]
+pub struct Validation {
+    /// Everything the matchers of the schema captured.
+    pub matches: Value,
+    pub errors: Vec<ValidationError>,
+    /// Errors a suppression comment in the input or a severity override
+    /// turned into warnings.
+    pub suppressed_errors: Vec<ValidationError>,
+    /// Whether the input conforms to the schema, i.e. there are no errors.
+    pub is_valid: bool,
+    /// The validator that did the validation, for pretty printing errors.
+    pub validator: Validator,
+}
+
+impl Validation {
+    fn from_validator(validator: Validator) -> Self {
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        let suppressed_errors: Vec<_> = validator.suppressed_errors_so_far().cloned().collect();
+
+        Self {
+            matches: validator.matches_so_far().clone(),
+            is_valid: errors.is_empty(),
+            errors,
+            suppressed_errors,
+            validator,
+        }
+    }
+
+    /// A machine-readable report of the errors, with their locations.
+    ///
+    /// # Arguments
+    ///
+    /// * `filename`: The name of the input, reported alongside the errors.
+    pub fn report(&self, filename: &str) -> ValidatorReport<'_> {
+        ValidatorReport::new(&self.validator).with_filename(filename)
+    }
+}
+
+/// An error that stopped us from reading the whole input.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReadInputError {
+    Validation(ValidationError),
+    Io(std::io::Error),
+    Utf8(std::str::Utf8Error),
+    /// The input contained bytes that aren't valid UTF-8, starting at this
+    /// byte offset.
+    InvalidUtf8(usize),
+}
+
+impl fmt::Display for ReadInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadInputError::Validation(e) => write!(f, "Validation error: {}", e),
+            ReadInputError::Io(e) => write!(f, "IO error: {}", e),
+            ReadInputError::Utf8(e) => write!(f, "UTF-8 error: {}", e),
+            ReadInputError::InvalidUtf8(offset) => {
+                write!(f, "Input is not valid UTF-8 (invalid byte at offset {})", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReadInputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadInputError::Validation(_) => None,
+            ReadInputError::Io(e) => Some(e),
+            ReadInputError::Utf8(e) => Some(e),
+            ReadInputError::InvalidUtf8(_) => None,
+        }
+    }
+}
+
+impl From<ValidationError> for ReadInputError {
+    fn from(error: ValidationError) -> Self {
+        ReadInputError::Validation(error)
+    }
+}
+
+impl From<std::io::Error> for ReadInputError {
+    fn from(error: std::io::Error) -> Self {
+        ReadInputError::Io(error)
+    }
+}
+
+impl From<std::str::Utf8Error> for ReadInputError {
+    fn from(error: std::str::Utf8Error) -> Self {
+        ReadInputError::Utf8(error)
+    }
+}
+
+/// Validate a whole input against a schema, with the default options.
+///
+/// The `Err` case is for when we can't validate at all, like when the schema
+/// fails to parse. Errors in the input are in the returned `Validation`.
+pub fn validate_str(schema_str: &str, input_str: &str) -> Result<Validation, ValidationError> {
+    validate_str_with_options(schema_str, input_str, &ValidateOptions::default())
+}
+
+/// Like `validate_str`, but with custom options.
+pub fn validate_str_with_options(
+    schema_str: &str,
+    input_str: &str,
+    options: &ValidateOptions,
+) -> Result<Validation, ValidationError> {
+    let mut validator = Validator::new_complete(schema_str, strip_bom(input_str))
+        .ok_or(ValidationError::ValidatorCreationFailed)?
+        .with_options(options.clone());
+    validator.validate();
+
+    Ok(Validation::from_validator(validator))
+}
+
+/// Validate input read from a reader against a schema, validating as the
+/// input comes in.
+///
+/// With `ValidateOptions::fast_fail` we stop reading at the first error.
+///
+/// # Arguments
+///
+/// * `schema_str`: The schema.
+/// * `input`: Where to read the input from, until EOF. The `BUFFER_SIZE`
+///   environment variable sets how many bytes to read at a time.
+/// * `options`: The options to validate with.
+pub fn validate_reader<R: Read>(
+    schema_str: &str,
+    input: &mut R,
+    options: &ValidateOptions,
+) -> Result<Validation, ReadInputError> {
+    read_and_validate(schema_str, input, options, None, None)
+}
+
+/// Like `validate_reader`, but calling a hook with the progress of
+/// validation (see `progress`).
+///
+/// # Arguments
+///
+/// * `schema_str`: The schema.
+/// * `input`: Where to read the input from, until EOF.
+/// * `options`: The options to validate with.
+/// * `progress_hook`: Called every time a top-level node of the input has
+///   been validated.
+/// * `total_bytes`: How long the input is, if known, like the size of the
+///   file it is read from.
+pub fn validate_reader_with_progress<R: Read>(
+    schema_str: &str,
+    input: &mut R,
+    options: &ValidateOptions,
+    progress_hook: ProgressHook,
+    total_bytes: Option<usize>,
+) -> Result<Validation, ReadInputError> {
+    read_and_validate(
+        schema_str,
+        input,
+        options,
+        Some((progress_hook, total_bytes)),
+        None,
+    )
+}
+
+/// Like `validate_reader`, but calling `on_match` with every match as soon
+/// as it is found, once per match (see `match_stream`).
+///
+/// # Arguments
+///
+/// * `schema_str`: The schema.
+/// * `input`: Where to read the input from, until EOF.
+/// * `options`: The options to validate with.
+/// * `progress`: A hook to call with the progress of validation, and how long
+///   the input is if known (see `validate_reader_with_progress`).
+/// * `on_match`: Called with each match, in the order of the matches.
+pub fn validate_reader_streaming_matches<R: Read>(
+    schema_str: &str,
+    input: &mut R,
+    options: &ValidateOptions,
+    progress: Option<(ProgressHook, Option<usize>)>,
+    on_match: &mut dyn FnMut(MatchUpdate),
+) -> Result<Validation, ReadInputError> {
+    read_and_validate(schema_str, input, options, progress, Some(on_match))
+}
+
+fn read_and_validate<R: Read>(
+    schema_str: &str,
+    input: &mut R,
+    options: &ValidateOptions,
+    progress: Option<(ProgressHook, Option<usize>)>,
+    mut on_match: Option<&mut dyn FnMut(MatchUpdate)>,
+) -> Result<Validation, ReadInputError> {
+    let buffer_size = get_buffer_size();
+    let mut buffer = vec![0; buffer_size];
+
+    // The validator keeps the input (or, with bounded memory, what it still
+    // needs of it), so we only hand it what's new
+    let mut validator = Validator::new_incomplete(schema_str, "")
+        .ok_or(ValidationError::ValidatorCreationFailed)?
+        .with_options(options.clone());
+    if let Some((progress_hook, total_bytes)) = progress {
+        validator.set_progress_hook(progress_hook);
+        validator.set_total_input_bytes(total_bytes);
+    }
+
+    let mut match_stream = MatchStream::new();
+    let mut report_new_matches = |validator: &Validator| {
+        if let Some(on_match) = on_match.as_mut() {
+            for update in match_stream.new_matches(validator.matches_so_far()) {
+                on_match(update);
+            }
+        }
+    };
+
+    // Bytes we've read but not decoded yet, because a read ended partway
+    // through a multi-byte character.
+    let mut pending_bytes: Vec<u8> = Vec::new();
+    let mut bytes_decoded = 0;
+
+    loop {
+        let bytes_read = input.read(&mut buffer)?;
+
+        // If we're done reading, mark EOF
+        if bytes_read == 0 {
+            if !pending_bytes.is_empty() {
+                return Err(ReadInputError::InvalidUtf8(bytes_decoded));
+            }
+
+            validator.append_final_input("")?;
+            validator.validate();
+            report_new_matches(&validator);
+
+            break;
+        }
+
+        pending_bytes.extend_from_slice(&buffer[..bytes_read]);
+        let valid_len = match std::str::from_utf8(&pending_bytes) {
+            Ok(_) => pending_bytes.len(),
+            // The character may be completed by the next read
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(error) => {
+                return Err(ReadInputError::InvalidUtf8(
+                    bytes_decoded + error.valid_up_to(),
+                ));
+            }
+        };
+        if valid_len == 0 {
+            continue;
+        }
+
+        let new_text = std::str::from_utf8(&pending_bytes[..valid_len])?;
+        if bytes_decoded == 0 {
+            validator.append_more_input(strip_bom(new_text))?;
+        } else {
+            validator.append_more_input(new_text)?;
+        }
+        bytes_decoded += valid_len;
+        pending_bytes.drain(..valid_len);
+
+        validator.validate();
+        report_new_matches(&validator);
+
+        // Check for fast-fail AFTER validation
+        if options.fast_fail && validator.errors_so_far().count() > 0 {
+            break;
+        }
+    }
+
+    Ok(Validation::from_validator(validator))
+}
+
+/// Strip a leading UTF-8 byte order mark, if there is one.
+pub fn strip_bom(text: &str) -> &str {
+    text.strip_prefix(UTF8_BOM).unwrap_or(text)
+}
+
+fn get_buffer_size() -> usize {
+    std::env::var("BUFFER_SIZE")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_BUFFER_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_str() {
+        let schema = "# `title:/\\w+/`\n\n- `item:/\\w+/`{1,}\n";
+
+        let validation = validate_str(schema, "# Hello\n\n- one\n- two\n").unwrap();
+        assert!(validation.errors.is_empty());
+        assert!(validation.is_valid);
+        assert_eq!(validation.matches, json!({"title": "Hello", "item": ["one", "two"]}));
+
+        let validation = validate_str(schema, "# Hello\n\nNot a list\n").unwrap();
+        assert!(!validation.errors.is_empty());
+        assert!(!validation.is_valid);
+
+        let report = validation.report("input.md").to_json();
+        assert_eq!(report["file"], "input.md");
+        assert_eq!(report["errors"].as_array().unwrap().len(), validation.errors.len());
+    }
+
+    #[test]
+    fn test_validate_reader_matches_validate_str() {
+        let schema = "# `title:/\\w+/`\n\nSome `word:/\\w+/` here\n";
+        let input = "\u{FEFF}# Hello\n\nSome text here\n";
+
+        let from_str = validate_str(schema, input).unwrap();
+        let from_reader =
+            validate_reader(schema, &mut Cursor::new(input), &ValidateOptions::default()).unwrap();
+
+        assert_eq!(from_reader.errors, from_str.errors);
+        assert_eq!(from_reader.matches, from_str.matches);
+        assert_eq!(from_reader.matches, json!({"title": "Hello", "word": "text"}));
+    }
+
+    #[test]
+    fn test_validate_reader_with_progress() {
+        let schema = "# `title:/\\w+/`\n\nSome text\n";
+        let input = "# Hello\n\nSome text\n";
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let hook_reports = Arc::clone(&reports);
+        let validation = validate_reader_with_progress(
+            schema,
+            &mut Cursor::new(input),
+            &ValidateOptions::default(),
+            Box::new(move |progress| hook_reports.lock().unwrap().push(progress)),
+            Some(input.len()),
+        )
+        .unwrap();
+        assert!(validation.is_valid);
+
+        // The final pass over the whole input reports once per top-level node
+        let reports = reports.lock().unwrap();
+        let last_pass = &reports[reports.len() - 2..];
+        assert_eq!(last_pass[0].bytes_processed, "# Hello\n".len());
+        assert_eq!(last_pass[1].bytes_processed, input.len());
+        assert!(reports.iter().all(|progress| progress.total_bytes == Some(input.len())));
+        assert_eq!(last_pass[1].errors, 0);
+        assert_eq!(last_pass[1].matches, 1);
+    }
+}
@@ -1,32 +1,289 @@
-use line_col::LineColLookup;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+
 use serde_json::{Map, Value};
-use tree_sitter::{InputEdit, Point, Tree};
+use tree_sitter::{InputEdit, Node, Point, Tree};
 
 use crate::mdschema::validation::{
-    errors::{ParserError, ValidationError},
+    back_references::back_reference_errors,
+    bounded_memory::{DroppedInput, droppable_prefix},
+    checkpoint::ValidatorCheckpoint,
+    conditions::SchemaConditions,
+    error_locations::ErrorLocations,
+    errors::{ParserError, SchemaError, ValidationError},
+    forbidden::ForbiddenPatterns,
+    frontmatter::{awaiting_frontmatter, frontmatter_range, validate_frontmatter},
+    heading_slugs::{HEADINGS_KEY, HeadingSlug, collect_heading_slugs},
+    includes::{IncludeError, find_includes},
+    link_references::LinkReferences,
+    lint::{
+        invalid_matcher_errors, matcher_id_uses, regex_limit_errors, suspicious_matcher_warnings,
+    },
+    match_paths::match_path_conflict_errors,
+    match_stream::{MatchHook, MatchJournal, NewMatch},
+    matchers::{
+        matcher::{MatcherError, RegexLimits},
+        matcher_cache::MatcherCache,
+    },
     node_pos_pair::NodePosPair,
+    preamble::{SCHEMA_VERSION_KEY, SchemaPreamble, split_preamble},
+    progress::{ProgressHook, ProgressReporter, SharedProgressHook},
+    schema_diagnostics::SchemaDiagnostics,
+    scopes::{SchemaScopes, join_scoped_values, scope_value_mut},
+    selection::{HeadingPath, select_section},
+    severity::SeverityOverrides,
+    spans::LineStarts,
+    text_normalization::TextNormalizations,
     walkers::{
         ValidationResult,
+        parallel::validate_top_level_in_parallel,
         validators::{Validator as ValidatorTrait, nodes::NodeVsNodeValidator},
     },
-    ts_utils::new_markdown_parser,
+    ts_utils::{find_node_by_index, new_markdown_parser},
     utils::join_values,
     validator_walker::ValidatorWalker,
 };
 
+/// The default maximum number of bytes a single matcher may scan.
+pub const DEFAULT_MAX_MATCHER_INPUT_LEN: usize = 1 << 20;
+
+/// The default maximum number of errors reported for the children of a single
+/// paragraph, heading or emphasis.
+pub const DEFAULT_MAX_ERRORS_PER_CONTAINER: usize = 5;
+
+/// The default maximum depth validators may be nested in each other.
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 128;
+
+/// Options that change how a `Validator` validates its input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidateOptions {
+    /// Validate independent top-level sections on a thread pool once the full
+    /// input has been received. Falls back to serial validation when the
+    /// sections can't be matched one-to-one.
+    pub parallel: bool,
+    /// How many threads to validate top-level sections on when `parallel`.
+    /// Without a count, rayon's global thread pool is used, which has a
+    /// thread per CPU.
+    pub parallel_threads: Option<usize>,
+    /// The maximum number of bytes of input a single matcher may scan. Longer
+    /// input is reported as a `SchemaError` instead of being matched.
+    pub max_matcher_input_len: usize,
+    /// How complex the regexes of matchers may be. Matchers with regexes that
+    /// compile to more are reported as a `SchemaError` when the schema is
+    /// loaded.
+    pub regex_limits: RegexLimits,
+    /// Normalizations to apply to literal text before comparing it. These are
+    /// combined with any `<!-- mds:compare ... -->` directives in the schema.
+    pub text_normalizations: TextNormalizations,
+    /// Match HTML comments in the input against the schema like any other
+    /// node. By default they are skipped, since schemas rarely mention them.
+    pub strict_comments: bool,
+    /// Skip inline HTML in the input, like `<br>` or `<sup>`, that the schema
+    /// doesn't have. The text on either side of it is matched as if the HTML
+    /// wasn't there (see `inline_html`).
+    pub ignore_inline_html: bool,
+    /// Skip HTML blocks in the input, like a `<div>` on lines of its own,
+    /// instead of matching them against the schema. A schema with HTML blocks
+    /// of its own needs them compared, so shouldn't be used with this.
+    pub ignore_html_blocks: bool,
+    /// Compare the ends of lines exactly. By default `\r\n` in the input is
+    /// read as `\n`, and whitespace at the end of lines is ignored, both when
+    /// comparing text and when matching it (see `NormalizedLines`).
+    pub strict_line_endings: bool,
+    /// Report every captured value as an object with its source location
+    /// (see `spans`) instead of a bare string.
+    pub include_spans: bool,
+    /// Stop visiting nodes as soon as the first error is found.
+    pub fast_fail: bool,
+    /// The maximum number of errors to report for the inline children of a
+    /// single paragraph, heading or emphasis. Once it is reached, the rest of
+    /// the children are still matched, but their errors are dropped, since
+    /// they are likely to follow from the earlier ones.
+    pub max_errors_per_container: usize,
+    /// How deep validators may be nested in each other. The validator of a
+    /// node runs inside the validator of its parent, so input nested deeper,
+    /// like a list nested in a list thousands of times, is reported as a
+    /// `SchemaViolationError::NestingTooDeep` instead of running out of
+    /// stack.
+    pub max_nesting_depth: usize,
+    /// Drop the top-level blocks of streamed input once they have been
+    /// validated, so that only the blocks still being worked on are kept in
+    /// memory (see `bounded_memory`). Errors, matches and spans are the same
+    /// as without it.
+    pub bounded_memory: bool,
+    /// Report each item of a repeated list matcher as
+    /// `{"value": ..., "children": {...}}`, with the captures of its nested
+    /// list under `children`, instead of appending the nested captures to the
+    /// array of values.
+    pub structured_matches: bool,
+    /// Report every heading of the input, with its GitHub-style anchor, under
+    /// `__headings` in the matches (see `heading_slugs`).
+    pub capture_heading_slugs: bool,
+    /// Sort the keys of every object in the matches lexicographically. By
+    /// default keys are in the order their matchers appear in the schema.
+    pub sort_keys: bool,
+    /// Put the version the schema declares in its preamble (see `preamble`)
+    /// in the matches, under `$schema_version`.
+    pub embed_schema_version: bool,
+    /// Report errors even when a `<!-- mdvalidate-disable-next-block -->`
+    /// comment in the input suppresses them (see `suppressions`).
+    pub ignore_suppressions: bool,
+    /// Kinds of errors to report as warnings, like suppressed errors, instead
+    /// of errors (see `severity`).
+    pub severity_overrides: SeverityOverrides,
+    /// What to do when more than one matcher captures a value under the same
+    /// id.
+    pub on_duplicate_id: DuplicateIdPolicy,
+    /// Check list markers too, so that ordered lists are numbered like the
+    /// schema (counting up from its number for the items of a repeated
+    /// matcher) and bullet lists use the schema's bullet. Mismatches are
+    /// reported as `SchemaViolationError::ListMarkerMismatch`.
+    pub enforce_ordered_list_numbers: bool,
+    /// Report a loose list (with blank lines between its items) where the
+    /// schema has a tight one, and the other way around, as a
+    /// `SchemaViolationError::NodeTypeMismatch`. By default both are the same
+    /// kind of list, and only ordered and bullet lists are told apart.
+    pub strict_list_tightness: bool,
+    /// Report a setext heading (underlined with `===` or `---`) where the
+    /// schema has an ATX one (`#`), and the other way around, as a
+    /// `SchemaViolationError::NodeTypeMismatch`, and compare the closing
+    /// hashes of ATX headings, like the ` ##` of `## Section ##`, as part of
+    /// their text. By default headings of the same level are the same kind of
+    /// heading, and closing hashes are left out.
+    pub strict_heading_form: bool,
+    /// What to do with blocks of the input that come after everything in the
+    /// schema has been matched.
+    pub trailing_content: TrailingContent,
+    /// Only validate the section of the input under this heading path, as if
+    /// it were the whole input (see `selection`).
+    pub input_selection: Option<HeadingPath>,
+}
+
+/// What to do when more than one matcher captures a value under the same id,
+/// like two sections of a schema that both use `` `title:/\w+/` ``.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[non_exhaustive]
+pub enum DuplicateIdPolicy {
+    /// Keep the value captured last.
+    Overwrite,
+    /// Collect the values into an array, in the order they appear in the
+    /// input. Arrays, like the captures of a repeated list matcher, are
+    /// concatenated rather than nested.
+    #[default]
+    Collect,
+    /// Keep the value captured last, and report a
+    /// `SchemaError::DuplicateMatcherId`.
+    Error,
+}
+
+/// What to do with blocks of the input after the end of the schema, like a
+/// paragraph after the last section the schema describes.
+///
+/// Nothing is decided until the whole input has arrived, since more blocks
+/// may still come.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TrailingContent {
+    /// Report them as a single `SchemaViolationError::TrailingContent`.
+    #[default]
+    Forbid,
+    /// Ignore them.
+    Allow,
+    /// Capture their raw markdown under the given key.
+    Capture(String),
+}
+
+impl std::str::FromStr for TrailingContent {
+    type Err = String;
+
+    /// Parse `forbid`, `allow` or `capture:KEY`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "forbid" => Ok(TrailingContent::Forbid),
+            "allow" => Ok(TrailingContent::Allow),
+            _ => match s.strip_prefix("capture:") {
+                Some(key) if !key.is_empty() => Ok(TrailingContent::Capture(key.to_string())),
+                _ => Err(format!(
+                    "Expected 'forbid', 'allow' or 'capture:KEY', got '{}'",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl Default for ValidateOptions {
+    fn default() -> Self {
+        Self {
+            parallel: false,
+            parallel_threads: None,
+            max_matcher_input_len: DEFAULT_MAX_MATCHER_INPUT_LEN,
+            regex_limits: RegexLimits::default(),
+            text_normalizations: TextNormalizations::default(),
+            strict_comments: false,
+            ignore_inline_html: false,
+            ignore_html_blocks: false,
+            strict_line_endings: false,
+            include_spans: false,
+            fast_fail: false,
+            max_errors_per_container: DEFAULT_MAX_ERRORS_PER_CONTAINER,
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+            bounded_memory: false,
+            structured_matches: false,
+            capture_heading_slugs: false,
+            sort_keys: false,
+            embed_schema_version: false,
+            ignore_suppressions: false,
+            severity_overrides: SeverityOverrides::default(),
+            on_duplicate_id: DuplicateIdPolicy::default(),
+            enforce_ordered_list_numbers: false,
+            strict_list_tightness: false,
+            strict_heading_form: false,
+            trailing_content: TrailingContent::default(),
+            input_selection: None,
+        }
+    }
+}
+
 /// A Validator implementation that uses a zipper tree approach to validate
 /// an input Markdown document against a markdown schema treesitter tree.
 #[derive(Debug)]
 pub struct Validator {
     /// The schema tree, which does not change after initialization.
     schema_tree: Tree,
-    /// The full schema string. Does not change.
+    /// The full schema string, without its preamble, forbid blocks, scope or
+    /// condition annotations.
+    /// Does not change.
     schema_str: String,
+    /// The version the schema declares in its preamble.
+    schema_version: u32,
+    /// The scoped headings of the schema (see `scopes`).
+    schema_scopes: SchemaScopes,
+    /// The conditional headings and list items of the schema (see
+    /// `conditions`).
+    schema_conditions: SchemaConditions,
+    /// The patterns the input mustn't have anywhere (see `forbidden`).
+    schema_forbidden: ForbiddenPatterns,
     /// The current input tree. When read_input is called, this is replaced with a new tree.
     input_tree: Tree,
     /// The full input string as last read. Not used internally but useful for
     /// debugging or reporting.
     last_input_str: String,
+    /// Where `last_input_str` ends, as a point in the input tree.
+    last_input_end: Point,
+    /// The link reference definitions of the schema, collected when first
+    /// needed.
+    schema_references: Arc<OnceLock<LinkReferences>>,
+    /// The link reference definitions of the input, collected when first
+    /// needed and reset when new input may define more.
+    input_references: Arc<OnceLock<LinkReferences>>,
+    /// Where the lines of the input start, found when first needed to report
+    /// the spans of captured values with, and extended as more input comes.
+    input_line_starts: Arc<OnceLock<LineStarts>>,
+    /// The matchers of the schema, built once when first needed and shared
+    /// by every validation pass (see `matcher_cache`).
+    matcher_cache: Arc<MatcherCache>,
     /// Whether we have received the end of the input. This means that last
     /// input tree descendant index is at the end of the input.
     got_eof: bool,
@@ -34,8 +291,42 @@ pub struct Validator {
     matches_so_far: Value,
     /// Any errors encountered during validation.
     errors_so_far: Vec<ValidationError>,
+    /// Errors that a suppression comment in the input or the severity
+    /// overrides turned into warnings, and the schema's warnings.
+    suppressed_errors_so_far: Vec<ValidationError>,
     /// Our farthest reached position.
     farthest_reached_pos: NodePosPair,
+    /// Options that change how we validate.
+    options: ValidateOptions,
+    /// Called as top-level nodes of the input get validated.
+    progress_hook: Option<SharedProgressHook>,
+    /// How long the input will be, if we were told before getting all of it.
+    total_input_bytes: Option<usize>,
+    /// Errors found in the schema when it was loaded, which every
+    /// validation reports.
+    schema_load_errors: Vec<ValidationError>,
+    /// Errors of the matchers of the schema, which validation reports where
+    /// the input reaches them (see `schema_diagnostics`).
+    schema_matcher_errors: Vec<SchemaError>,
+    /// Warnings about the schema found when it was loaded, like code spans
+    /// that look like matchers with a typo, which every validation reports.
+    schema_load_warnings: Vec<ValidationError>,
+    /// Whether the front matter has been validated since we last started
+    /// from the beginning of the input.
+    frontmatter_validated: bool,
+    /// The start of the input that we've dropped, with
+    /// `ValidateOptions::bounded_memory`, or that isn't in the section
+    /// `ValidateOptions::input_selection` selects. `last_input_str` and
+    /// `input_tree` are of what's left.
+    dropped_input: DroppedInput,
+    /// Whether the input has been cut down to the selected section.
+    input_selected: bool,
+    /// The matches found pass by pass, to hand out as they're found (see
+    /// `match_stream`).
+    match_journal: MatchJournal,
+    /// The nodes the errors of each pass point at, looked up at the time
+    /// (see `error_locations`).
+    error_locations: HashMap<ValidationError, ErrorLocations>,
 }
 
 pub trait ValidatorState {
@@ -57,24 +348,191 @@ pub trait ValidatorState {
 impl Validator {
     /// Create a new Validator with the given schema and input strings.
     fn new(schema_str: &str, input_str: &str, got_eof: bool) -> Option<Self> {
+        // The preamble isn't a block the input has to have
+        let (schema_preamble, schema_str) = split_preamble(schema_str);
+
         let mut schema_parser = new_markdown_parser();
         let schema_tree = schema_parser.parse(schema_str, None)?;
 
+        // Nor are the blocks of forbidden patterns
+        let (schema_str, schema_tree, schema_forbidden) =
+            match ForbiddenPatterns::strip(&schema_tree, schema_str) {
+                Some((stripped, forbidden)) => {
+                    let stripped_tree = schema_parser.parse(&stripped, None)?;
+                    (stripped, stripped_tree, forbidden)
+                }
+                None => (
+                    schema_str.to_string(),
+                    schema_tree,
+                    ForbiddenPatterns::default(),
+                ),
+            };
+        // Scope annotations aren't part of the heading they are on
+        let (schema_str, schema_tree, schema_scopes) =
+            match SchemaScopes::strip(&schema_tree, &schema_str) {
+                Some((stripped, scopes)) => {
+                    let stripped_tree = schema_parser.parse(&stripped, None)?;
+                    (stripped, stripped_tree, scopes)
+                }
+                None => (schema_str, schema_tree, SchemaScopes::default()),
+            };
+        // Nor are condition annotations part of their heading or list item
+        let (schema_str, schema_tree, schema_conditions) =
+            match SchemaConditions::strip(&schema_tree, &schema_str) {
+                Some((stripped, conditions)) => {
+                    let stripped_tree = schema_parser.parse(&stripped, None)?;
+                    (stripped, stripped_tree, conditions)
+                }
+                None => (schema_str, schema_tree, SchemaConditions::default()),
+            };
+        let schema_scopes = schema_scopes.after_stripping(schema_conditions.annotations());
+
         let mut input_parser = new_markdown_parser();
         let input_tree = input_parser.parse(input_str, None)?;
 
+        // Includes are resolved before a schema gets here, if it has a path
+        let mut schema_load_errors: Vec<ValidationError> =
+            find_includes(&schema_tree, &schema_str)
+                .into_iter()
+                .map(|include| {
+                    ValidationError::SchemaError(SchemaError::InvalidInclude {
+                        schema_index: include.schema_index,
+                        error: IncludeError::NoSchemaPath { path: include.path },
+                    })
+                })
+                .collect();
+        let schema_version = match schema_preamble {
+            Ok(preamble) => preamble.version,
+            Err(declared) => {
+                schema_load_errors.push(ValidationError::SchemaError(
+                    SchemaError::InvalidSchemaVersion {
+                        schema_index: 0,
+                        declared,
+                    },
+                ));
+                SchemaPreamble::default().version
+            }
+        };
+        schema_load_errors.extend(schema_scopes.duplicate_errors(&schema_tree));
+        schema_load_errors.extend(regex_limit_errors(
+            &schema_tree,
+            &schema_str,
+            &RegexLimits::default(),
+        ));
+        schema_load_errors.extend(back_reference_errors(&schema_tree, &schema_str));
+        schema_load_errors.extend(match_path_conflict_errors(
+            &schema_tree,
+            &schema_str,
+            &schema_scopes,
+        ));
+        schema_load_errors.extend(schema_conditions.errors(&schema_tree, &schema_str));
+        schema_load_errors.extend(schema_forbidden.errors(&schema_tree));
+        let schema_matcher_errors = invalid_matcher_errors(&schema_tree, &schema_str);
+        let schema_load_warnings = suspicious_matcher_warnings(&schema_tree, &schema_str);
+
+        let (text_normalizations, directive_errors) =
+            TextNormalizations::from_schema_directives(&schema_tree, &schema_str);
+        schema_load_errors.extend(directive_errors);
         Some(Validator {
             schema_tree,
-            schema_str: schema_str.to_string(),
+            schema_str,
+            schema_version,
+            schema_scopes,
+            schema_conditions,
+            schema_forbidden,
             input_tree,
             last_input_str: input_str.to_string(),
+            last_input_end: point_after(Point::default(), input_str),
+            schema_references: Arc::default(),
+            input_references: Arc::default(),
+            input_line_starts: Arc::default(),
+            matcher_cache: Arc::default(),
             got_eof,
             matches_so_far: Value::Object(Map::new()),
-            errors_so_far: Vec::new(),
+            errors_so_far: schema_load_errors.clone(),
+            suppressed_errors_so_far: schema_load_warnings.clone(),
             farthest_reached_pos: NodePosPair::default(),
+            options: ValidateOptions {
+                text_normalizations,
+                ..Default::default()
+            },
+            progress_hook: None,
+            total_input_bytes: None,
+            schema_load_errors,
+            schema_matcher_errors,
+            schema_load_warnings,
+            frontmatter_validated: false,
+            dropped_input: DroppedInput::default(),
+            input_selected: false,
+            match_journal: MatchJournal::default(),
+            error_locations: HashMap::new(),
         })
     }
 
+    /// Replace the options used for validation.
+    ///
+    /// Normalizations enabled by directives in the schema stay enabled.
+    pub fn with_options(mut self, options: ValidateOptions) -> Self {
+        // Directives with modes we don't know were reported when the schema
+        // was loaded
+        let (directive_normalizations, _) =
+            TextNormalizations::from_schema_directives(&self.schema_tree, &self.schema_str);
+        let text_normalizations = options.text_normalizations.union(directive_normalizations);
+
+        // Check the matchers against the new limits instead, and build them
+        // within those from now on
+        if options.regex_limits != self.options.regex_limits {
+            self.matcher_cache = Arc::default();
+            let errors =
+                regex_limit_errors(&self.schema_tree, &self.schema_str, &options.regex_limits);
+            for found in [&mut self.schema_load_errors, &mut self.errors_so_far] {
+                found.retain(|error| !is_regex_limit_error(error));
+                found.extend(errors.iter().cloned());
+            }
+        }
+        self.options = ValidateOptions {
+            text_normalizations,
+            ..options
+        };
+        self
+    }
+
+    pub fn options(&self) -> &ValidateOptions {
+        &self.options
+    }
+
+    /// Only validate the section of the input under a heading path, as if it
+    /// were the whole input (see `selection`).
+    pub fn with_input_selection(mut self, path: HeadingPath) -> Self {
+        self.options.input_selection = Some(path);
+        self
+    }
+
+    /// Call `hook` every time a top-level node of the input has been
+    /// validated (see `progress`).
+    pub fn set_progress_hook(&mut self, hook: ProgressHook) {
+        self.progress_hook = Some(SharedProgressHook::new(hook));
+    }
+
+    /// Call `hook` with every match as it's found, with where it is in the
+    /// matches, like `items[2].inner[0]` (see `match_stream`).
+    pub fn on_match(&mut self, hook: MatchHook) {
+        self.match_journal.set_hook(hook);
+    }
+
+    /// The matches found since the last call, in the order of the input, with
+    /// where they are in the matches and, with
+    /// `ValidateOptions::include_spans`, in the input (see `match_stream`).
+    pub fn drain_new_matches(&mut self) -> impl Iterator<Item = NewMatch> + '_ {
+        self.match_journal.drain()
+    }
+
+    /// Say how long the input will be, so that progress reports know the
+    /// total before we have all of the input.
+    pub fn set_total_input_bytes(&mut self, total_input_bytes: Option<usize>) {
+        self.total_input_bytes = total_input_bytes;
+    }
+
     pub fn new_complete(schema_str: &str, input_str: &str) -> Option<Self> {
         Self::new(schema_str, input_str, true)
     }
@@ -83,6 +541,69 @@ impl Validator {
         Self::new(schema_str, input_str, false)
     }
 
+    /// Save what we have found so far, to carry on from with `resume`, like
+    /// on another machine (see `checkpoint`).
+    pub fn checkpoint(&self) -> ValidatorCheckpoint {
+        let (schema_index, input_index) = self.farthest_reached_pos.as_pos();
+        let (journaled_matches, new_matches) = self.match_journal.state();
+        ValidatorCheckpoint {
+            input: self.last_input_str.clone(),
+            got_eof: self.got_eof,
+            schema_index,
+            input_index,
+            errors: self.errors_so_far.clone(),
+            suppressed_errors: self.suppressed_errors_so_far.clone(),
+            matches: self.matches_so_far.clone(),
+            frontmatter_validated: self.frontmatter_validated,
+            total_input_bytes: self.total_input_bytes,
+            dropped_input: self.dropped_input.clone(),
+            input_selected: self.input_selected,
+            journaled_matches,
+            new_matches,
+            error_locations: self
+                .error_locations
+                .iter()
+                .map(|(error, locations)| (error.clone(), locations.clone()))
+                .collect(),
+        }
+    }
+
+    /// Make a validator that carries on from a checkpoint of one for the same
+    /// schema, as if it had read and validated the same input. The options
+    /// aren't part of the checkpoint, so set them again with `with_options`.
+    ///
+    /// Returns `None` if the input can't be parsed, or the position we were
+    /// at isn't in the trees, like for a checkpoint of another schema.
+    pub fn resume(schema_str: &str, checkpoint: &ValidatorCheckpoint) -> Option<Self> {
+        let mut validator = Self::new(schema_str, &checkpoint.input, checkpoint.got_eof)?;
+
+        // The trees are parsed again, so check that we can get back to where
+        // we were in them
+        let farthest_reached_pos = checkpoint.farthest_reached_pos();
+        let mut schema_cursor = validator.schema_tree.walk();
+        let mut input_cursor = validator.input_tree.walk();
+        farthest_reached_pos.walk_cursors_to_pos(&mut schema_cursor, &mut input_cursor);
+        if NodePosPair::from_cursors(&schema_cursor, &input_cursor) != farthest_reached_pos {
+            return None;
+        }
+
+        validator.farthest_reached_pos = farthest_reached_pos;
+        validator.errors_so_far.clone_from(&checkpoint.errors);
+        validator
+            .suppressed_errors_so_far
+            .clone_from(&checkpoint.suppressed_errors);
+        validator.matches_so_far = checkpoint.matches.clone();
+        validator.frontmatter_validated = checkpoint.frontmatter_validated;
+        validator.total_input_bytes = checkpoint.total_input_bytes;
+        validator.dropped_input = checkpoint.dropped_input.clone();
+        validator.input_selected = checkpoint.input_selected;
+        validator
+            .match_journal
+            .restore(&checkpoint.journaled_matches, &checkpoint.new_matches);
+        validator.error_locations = checkpoint.error_locations.iter().cloned().collect();
+        Some(validator)
+    }
+
     pub fn report(
         &self,
     ) -> (
@@ -94,52 +615,101 @@ impl Validator {
 
     /// Read new input. Updates the input tree with a new input tree for the full new input.
     ///
+    /// New input that starts with the last input extends it, as it does when
+    /// streaming. Only the appended text is copied, and the old tree is
+    /// edited and reparsed incrementally, so reading a document chunk by chunk
+    /// stays linear in its length. Nodes before the appended text keep their
+    /// descendant indices, so validation can resume from where it left off.
+    /// Any other input replaces the last input, and is parsed from scratch.
+    ///
     /// Does not update the schema tree or change the descendant indices. You will still
     /// need to call `validate` to validate until the end of the current input
     /// (which this updates).
+    ///
+    /// The input we dropped with `ValidateOptions::bounded_memory` is skipped,
+    /// so the input is still the whole input so far.
     #[tracing::instrument(skip(self, input))]
     fn read_input(&mut self, input: &str, got_eof: bool) -> Result<(), ValidationError> {
-        // Update internal state of the last input string
-        self.set_last_input_str(input.to_string());
-
         // If we already got EOF, do not accept more input
         if self.got_eof() {
             return Err(ValidationError::ParserError(ParserError::ReadAfterEOF));
         }
 
-        self.set_got_eof(got_eof);
+        let input = input.get(self.dropped_input.bytes..).unwrap_or_default();
+        let old_len = self.last_input_str.len();
+        let appended = input.starts_with(self.last_input_str.as_str());
+        if !appended {
+            // Not a continuation of what we had, so nothing can be reused
+            self.set_got_eof(got_eof);
+            self.set_last_input_str(input.to_string());
+            self.last_input_end = point_after(Point::default(), input);
+            self.input_references = Arc::default();
+            self.input_line_starts = Arc::default();
+            return self.reparse_input(None);
+        }
 
-        // Calculate the range of new content
-        let old_len = self.input_tree.root_node().byte_range().end;
-        let new_len = input.len();
+        self.append_input(&input[old_len..], got_eof)
+    }
+
+    /// Read the text that comes after the input we've read so far, editing
+    /// the old tree and reparsing it incrementally (see `read_input`).
+    fn append_input(&mut self, new_text: &str, got_eof: bool) -> Result<(), ValidationError> {
+        if self.got_eof() {
+            return Err(ValidationError::ParserError(ParserError::ReadAfterEOF));
+        }
+
+        self.set_got_eof(got_eof);
 
         // Only parse if there's actually new content
-        if new_len <= old_len {
+        if new_text.is_empty() {
             return Ok(());
         }
 
-        // Parse incrementally, providing the edit information
-        let edit = InputEdit {
-            start_byte: old_len,
-            old_end_byte: old_len,
-            new_end_byte: new_len,
-            start_position: self.input_tree.root_node().end_position(),
-            old_end_position: self.input_tree.root_node().end_position(),
-            new_end_position: {
-                let lookup = LineColLookup::new(input);
-                let (row, col) = lookup.get(new_len);
-                Point { row, column: col }
-            },
-        };
+        // A new definition needs a `]:`, which may straddle the chunk boundary
+        if new_text.contains("]:")
+            || (self.last_input_str.ends_with(']') && new_text.starts_with(':'))
+        {
+            self.input_references = Arc::default();
+        }
+
+        let old_len = self.last_input_str.len();
+        let old_end = self.last_input_end;
+        let new_end = point_after(old_end, new_text);
+        self.last_input_str.push_str(new_text);
+        self.last_input_end = new_end;
+
+        // The lines we had stay where they are, so we only add the new ones
+        match Arc::get_mut(&mut self.input_line_starts) {
+            Some(line_starts) => {
+                if let Some(line_starts) = line_starts.get_mut() {
+                    line_starts.extend(old_len, new_text);
+                }
+            }
+            None => self.input_line_starts = Arc::default(),
+        }
 
         // We need to call edit() to inform the tree about changes in the source text
         // before reusing it for incremental parsing. This allows tree-sitter to
         // efficiently reparse only the modified portions of the tree. (it
         // requires the state to match the new text)
-        self.input_tree.edit(&edit); // edit doesn't know about the new text content!
+        self.input_tree.edit(&InputEdit {
+            start_byte: old_len,
+            old_end_byte: old_len,
+            new_end_byte: self.last_input_str.len(),
+            start_position: old_end,
+            old_end_position: old_end,
+            new_end_position: new_end,
+        });
+
+        let old_tree = self.input_tree.clone();
+        self.reparse_input(Some(&old_tree))
+    }
 
+    /// Parse `last_input_str` into the input tree, reusing the old tree if
+    /// given (it must already have been edited to match).
+    fn reparse_input(&mut self, old_tree: Option<&Tree>) -> Result<(), ValidationError> {
         let mut input_parser = new_markdown_parser();
-        match input_parser.parse(input, Some(&self.input_tree)) {
+        match input_parser.parse(&self.last_input_str, old_tree) {
             Some(parse) => {
                 self.input_tree = parse;
                 Ok(())
@@ -156,41 +726,387 @@ impl Validator {
         self.read_input(input, false)
     }
 
+    /// Read the text that comes after the input we've read so far. Unlike
+    /// `read_more_input`, which takes the whole input so far, this only takes
+    /// the new text, so the caller doesn't have to keep the whole input
+    /// either (see `ValidateOptions::bounded_memory`).
+    pub fn append_more_input(&mut self, text: &str) -> Result<(), ValidationError> {
+        self.append_input(text, false)
+    }
+
+    /// Like `append_more_input`, for the last of the input.
+    pub fn append_final_input(&mut self, text: &str) -> Result<(), ValidationError> {
+        self.append_input(text, true)
+    }
+
     /// Validates the input markdown against the schema by traversing both trees
     /// in parallel to the ends, starting from where we last left off.
     pub fn validate(&mut self) {
         if self.got_eof() {
             self.set_farthest_reached_pos(NodePosPair::default());
             // Clear errors when revalidating from the beginning at EOF
-            // to avoid duplicate errors from streaming validation
-            self.errors_so_far.clear();
+            // to avoid duplicate errors from streaming validation, keeping
+            // the ones found when loading the schema
+            self.errors_so_far.clone_from(&self.schema_load_errors);
+            self.suppressed_errors_so_far.clone_from(&self.schema_load_warnings);
             self.matches_so_far = Value::Object(Map::new());
+            self.frontmatter_validated = false;
+            self.error_locations.clear();
+        }
+
+        // Nothing we validate from here on can be reported anyway
+        if !self.got_eof() && self.options.fast_fail && !self.errors_so_far.is_empty() {
+            return;
+        }
+
+        if !self.select_input() {
+            return;
+        }
+
+        // Front matter is compared as a whole, so wait until we have all of it
+        let schema_has_frontmatter = frontmatter_range(&self.schema_str).is_some();
+        if schema_has_frontmatter
+            && !self.got_eof()
+            && awaiting_frontmatter(&self.last_input_str)
+        {
+            return;
         }
 
         let got_eof = self.got_eof();
         let farthest_reached_pos = self.farthest_reached_pos();
-        let schema_str = self.schema_str.clone();
-        let input_str = self.last_input_str.clone();
 
-        let validation_result = {
-            let mut schema_cursor = self.schema_tree.walk();
-            let mut input_cursor = self.input_tree.walk();
-            farthest_reached_pos.walk_cursors_to_pos(&mut schema_cursor, &mut input_cursor);
+        let frontmatter_result = if self.frontmatter_validated {
+            None
+        } else {
+            self.frontmatter_validated = true;
+            validate_frontmatter(
+                &self.schema_tree,
+                &self.schema_str,
+                &self.input_tree,
+                &self.last_input_str,
+                &self.options,
+                self.options.include_spans.then(|| {
+                    self.input_line_starts
+                        .get_or_init(|| LineStarts::new(&self.last_input_str))
+                }),
+            )
+        };
+
+        let total_bytes = if got_eof {
+            Some(self.dropped_input.bytes + self.last_input_str.len())
+        } else {
+            self.total_input_bytes
+        };
+        let progress = self.progress_hook.as_ref().map(|hook| {
+            ProgressReporter::new(
+                hook,
+                total_bytes,
+                self.errors_so_far.len(),
+                &self.matches_so_far,
+            )
+            .after_dropped_input(&self.dropped_input)
+        });
+
+        // Sections are only independent once we have the whole input, since a
+        // partial final section can still change shape. When failing fast the
+        // serial path is quicker, since it stops at the first error. Scopes
+        // and conditions span sections, so they need the serial path too, as
+        // does input we dropped some of.
+        if got_eof
+            && self.options.parallel
+            && !self.options.fast_fail
+            && self.schema_scopes.is_empty()
+            && self.schema_conditions.is_empty()
+            && self.dropped_input.is_empty()
+            && let Some(validation_result) = validate_top_level_in_parallel(
+                &self.schema_tree,
+                &self.schema_str,
+                &self.input_tree,
+                &self.last_input_str,
+                &self.options,
+                &self.matcher_cache,
+            )
+        {
+            // The sections finish in any order, so only report the end
+            if let Some(progress) = &progress {
+                let root = self.input_tree.root_node();
+                progress.report(root.end_byte(), 0, &validation_result);
+            }
+            let validation_result = after_frontmatter(frontmatter_result, validation_result);
+            self.push_validation_result(validation_result);
+            return;
+        }
 
-            let walker = ValidatorWalker::new(schema_cursor, &schema_str, input_cursor, &input_str);
+        let validation_result = {
+            let mut walker = self.walk().with_progress(progress.as_ref());
+            let (schema_cursor, input_cursor) = walker.cursors_mut();
+            farthest_reached_pos.walk_cursors_to_pos(schema_cursor, input_cursor);
             NodeVsNodeValidator.validate(&walker, got_eof)
         };
 
-        self.push_validation_result(validation_result);
+        self.push_validation_result(after_frontmatter(frontmatter_result, validation_result));
+
+        if !got_eof && self.bounds_memory() {
+            self.drop_validated_input();
+        }
+    }
+
+    /// Cut the input down to the section `ValidateOptions::input_selection`
+    /// selects, once we have all of it (see `selection`). A selection that
+    /// doesn't pick out one section is reported.
+    ///
+    /// Returns whether there is input to validate.
+    fn select_input(&mut self) -> bool {
+        let Some(path) = &self.options.input_selection else {
+            return true;
+        };
+        if self.input_selected {
+            return true;
+        }
+        if !self.got_eof() {
+            return false;
+        }
+
+        let section = match select_section(&self.input_tree, &self.last_input_str, path) {
+            Ok(section) => section,
+            Err(error) => {
+                self.errors_so_far.push(error);
+                return false;
+            }
+        };
+        let Some(input_tree) =
+            new_markdown_parser().parse(&self.last_input_str[section.byte_range.clone()], None)
+        else {
+            self.errors_so_far
+                .push(ValidationError::ParserError(ParserError::TreesitterError));
+            return false;
+        };
+
+        self.dropped_input
+            .select(&mut self.last_input_str, &section);
+        self.input_tree = input_tree;
+        self.last_input_end = point_after(Point::default(), &self.last_input_str);
+        self.input_references = Arc::default();
+        self.input_line_starts = Arc::default();
+        self.farthest_reached_pos = NodePosPair::default();
+        self.input_selected = true;
+        true
+    }
+
+    /// Whether we drop the input we're done with, as
+    /// `ValidateOptions::bounded_memory` asks, which we can't do if the schema
+    /// needs the whole input (see `bounded_memory`).
+    fn bounds_memory(&self) -> bool {
+        self.options.bounded_memory
+            && !self.options.capture_heading_slugs
+            && self.schema_scopes.is_empty()
+            && self.schema_forbidden.is_empty()
+            && frontmatter_range(&self.schema_str).is_none()
+    }
+
+    /// Drop the blocks at the start of the input that we're done with (see
+    /// `bounded_memory`), and parse what's left on its own.
+    fn drop_validated_input(&mut self) {
+        let Some(compaction) = droppable_prefix(&self.walk(), self.dropped_input.schema_start)
+        else {
+            return;
+        };
+        let Some(input_tree) =
+            new_markdown_parser().parse(&self.last_input_str[compaction.cut_byte()..], None)
+        else {
+            return;
+        };
+
+        self.dropped_input
+            .drop_prefix(&mut self.last_input_str, compaction);
+        self.input_tree = input_tree;
+        self.last_input_end = point_after(Point::default(), &self.last_input_str);
+        self.input_references = Arc::default();
+        self.input_line_starts = Arc::default();
+        self.farthest_reached_pos = NodePosPair::default();
+    }
+
+    /// The version the schema declares in its preamble, or 1 if it doesn't
+    /// declare one (see `preamble`).
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    /// The scoped headings of the schema (see `scopes`).
+    pub(crate) fn schema_scopes(&self) -> &SchemaScopes {
+        &self.schema_scopes
+    }
+
+    /// Everything wrong with the schema, found when it was loaded, whatever
+    /// the input (see `schema_diagnostics`).
+    pub fn schema_diagnostics(&self) -> SchemaDiagnostics {
+        SchemaDiagnostics::new(&self.schema_load_errors, &self.schema_matcher_errors)
+    }
+
+    /// The validator, or what is wrong with its schema if anything is, for
+    /// refusing to validate against a broken schema at all.
+    pub fn require_valid_schema(self) -> Result<Self, SchemaDiagnostics> {
+        let diagnostics = self.schema_diagnostics();
+        if diagnostics.is_empty() {
+            Ok(self)
+        } else {
+            Err(diagnostics)
+        }
+    }
+
+    /// The start of the input we've dropped, with
+    /// `ValidateOptions::bounded_memory`. The descendant indexes of errors
+    /// are of the whole input, so they have to be mapped to the kept input
+    /// with `DroppedInput::kept_index` to find their nodes in `input_tree`.
+    pub fn dropped_input(&self) -> &DroppedInput {
+        &self.dropped_input
+    }
+
+    /// The node of the input tree at a descendant index of the whole input,
+    /// like the input index of an error.
+    pub fn input_node(&self, index: usize) -> Node<'_> {
+        find_node_by_index(
+            self.input_tree.root_node(),
+            self.dropped_input.kept_index(index),
+        )
+    }
+
+    /// The kinds, bytes and text of the nodes an error points at, as they
+    /// were when it was found (see `error_locations`). Errors that weren't
+    /// found validating the input, like ones in the schema, are looked up now.
+    pub fn error_locations(&self, error: &ValidationError) -> ErrorLocations {
+        self.error_locations
+            .get(error)
+            .cloned()
+            .unwrap_or_else(|| self.locate(error))
+    }
+
+    /// Look up the nodes of an error in the trees as they are now.
+    fn locate(&self, error: &ValidationError) -> ErrorLocations {
+        ErrorLocations::resolve(
+            error,
+            (&self.schema_tree, &self.schema_str),
+            (&self.input_tree, &self.last_input_str),
+            &self.dropped_input,
+        )
+    }
+
+    /// Errors that a suppression comment in the input or the severity
+    /// overrides turned into warnings, and warnings about the schema, like
+    /// `SchemaError::MatcherSyntaxSuspicious`.
+    pub fn suppressed_errors_so_far(&self) -> std::slice::Iter<'_, ValidationError> {
+        self.suppressed_errors_so_far.iter()
+    }
+
+    /// The error for more than one matcher capturing under `id`, pointing at
+    /// the second matcher in the schema with that id.
+    fn duplicate_id_error(&self, id: &str) -> ValidationError {
+        let uses = matcher_id_uses(&self.schema_tree, &self.schema_str, id);
+        ValidationError::SchemaError(SchemaError::DuplicateMatcherId {
+            schema_index: uses.get(1).or(uses.first()).copied().unwrap_or(0),
+            id: id.to_string(),
+        })
+    }
+
+    /// The errors for back-references of a result that don't match what
+    /// their ids captured (see `back_references`), leaving out the ones we
+    /// have already reported, since later passes can match them again.
+    fn back_reference_mismatches(&self, result: &ValidationResult) -> Vec<ValidationError> {
+        result
+            .back_references()
+            .iter()
+            .filter_map(|back_reference| {
+                let uses = matcher_id_uses(&self.schema_tree, &self.schema_str, &back_reference.id);
+                back_reference.mismatch(&self.matches_so_far, uses.first().copied().unwrap_or(0))
+            })
+            .filter(|error| {
+                let mut error = error.clone();
+                self.dropped_input.shift_error(&mut error);
+                !self.errors_so_far.contains(&error)
+                    && !self.suppressed_errors_so_far.contains(&error)
+            })
+            .collect()
+    }
+
+    /// The forbidden content of the input (see `forbidden`), leaving out
+    /// what we have already reported, since later passes find it again.
+    fn forbidden_content(&self) -> Vec<ValidationError> {
+        self.schema_forbidden
+            .find(
+                &self.schema_tree,
+                &self.input_tree,
+                &self.last_input_str,
+                self.got_eof,
+            )
+            .into_iter()
+            .filter(|error| {
+                !self.errors_so_far.contains(error)
+                    && !self.suppressed_errors_so_far.contains(error)
+            })
+            .collect()
     }
 
-    pub fn walk(&self) -> ValidatorWalker<'_> {
+    pub(crate) fn walk(&self) -> ValidatorWalker<'_> {
         ValidatorWalker::new(
             self.schema_tree.walk(),
             &self.schema_str,
             self.input_tree.walk(),
             &self.last_input_str,
         )
+        .with_options(&self.options)
+        .with_link_references(&self.schema_references, &self.input_references)
+        .with_input_line_starts(&self.input_line_starts)
+        .with_matcher_cache(&self.matcher_cache)
+        .with_schema_scopes(&self.schema_scopes)
+        .with_schema_conditions(&self.schema_conditions)
+        .with_earlier_matches(&self.matches_so_far)
+        .with_schema_start(self.dropped_input.schema_start)
+        .with_input_frontmatter(
+            frontmatter_range(&self.schema_str)
+                .and(frontmatter_range(&self.last_input_str))
+                .map(|range| range.end),
+        )
+    }
+}
+
+/// Put the errors and matches of the front matter, if we validated it, before
+/// the ones of the rest of the document.
+fn after_frontmatter(
+    frontmatter_result: Option<ValidationResult>,
+    validation_result: ValidationResult,
+) -> ValidationResult {
+    let Some(mut result) = frontmatter_result else {
+        return validation_result;
+    };
+    result.join_data(validation_result.data());
+    result.set_farthest_reached_pos(*validation_result.farthest_reached_pos());
+    result
+}
+
+/// Whether an error is about a matcher regex that is more complex than the
+/// `RegexLimits` allow.
+fn is_regex_limit_error(error: &ValidationError) -> bool {
+    matches!(
+        error,
+        ValidationError::SchemaError(SchemaError::MatcherError {
+            error: MatcherError::RegexTooComplex { .. },
+            ..
+        })
+    )
+}
+
+/// The point the text ends at, if it starts at `start`. Columns count bytes,
+/// like tree-sitter's.
+fn point_after(start: Point, text: &str) -> Point {
+    match text.rfind('\n') {
+        Some(last_newline) => Point {
+            row: start.row + text.matches('\n').count(),
+            column: text.len() - last_newline - 1,
+        },
+        None => Point {
+            row: start.row,
+            column: start.column + text.len(),
+        },
     }
 }
 
@@ -233,14 +1149,99 @@ impl ValidatorState for Validator {
 
     fn join_new_matches(&mut self, new_matches: Value) {
         let joined = &mut self.matches_so_far.clone();
-        join_values(joined, new_matches);
+        if self.schema_scopes.is_empty() {
+            join_values(joined, new_matches);
+        } else {
+            join_scoped_values(joined, new_matches, &self.schema_scopes);
+        }
         self.matches_so_far = joined.clone();
     }
 
     fn push_validation_result(&mut self, result: ValidationResult) {
-        self.join_new_matches(result.value().clone());
-        self.errors_so_far.extend(result.errors().to_vec());
+        let result = self.dropped_input.with_committed(result);
+        let mut new_matches = result.value().clone();
+        if self.options.on_duplicate_id != DuplicateIdPolicy::Collect {
+            // Later values replace the arrays they were collected into
+            for duplicate in result.duplicates() {
+                if let Some(scope_matches) = scope_value_mut(&mut new_matches, &duplicate.scope) {
+                    scope_matches[&duplicate.id] = duplicate.value.clone();
+                }
+            }
+        }
+        // Across passes the same values get captured again, so they replace
+        // what we had
+        self.join_new_matches(new_matches);
+
+        if self.options.on_duplicate_id == DuplicateIdPolicy::Error {
+            let mut ids = HashSet::new();
+            let errors: Vec<_> = result
+                .duplicates()
+                .iter()
+                .filter(|duplicate| ids.insert(duplicate.id.as_str()))
+                .map(|duplicate| self.duplicate_id_error(&duplicate.id))
+                .collect();
+            let (warnings, errors): (Vec<_>, Vec<_>) = errors
+                .into_iter()
+                .partition(|error| self.options.severity_overrides.is_warning(error));
+            self.errors_so_far.extend(errors);
+            self.suppressed_errors_so_far.extend(warnings);
+        }
+
+        let back_reference_mismatches = self.back_reference_mismatches(&result);
+        let forbidden_content = self.forbidden_content();
+        let (mut suppressed, mut errors): (Vec<_>, Vec<_>) = {
+            let walker = self.walk();
+            result
+                .errors()
+                .iter()
+                .cloned()
+                .chain(back_reference_mismatches)
+                .chain(forbidden_content)
+                .partition(|error| walker.is_warning(error))
+        };
+        for error in errors.iter_mut().chain(&mut suppressed) {
+            self.dropped_input.shift_error(error);
+        }
+        // The input tree changes with more input, so the nodes are looked up
+        // while it still matches the errors
+        for error in errors.iter().chain(&suppressed) {
+            if !self.error_locations.contains_key(error) {
+                let locations = self.locate(error);
+                self.error_locations.insert(error.clone(), locations);
+            }
+        }
+        self.errors_so_far.extend(errors);
+        self.suppressed_errors_so_far.extend(suppressed);
         self.farthest_reached_pos = *result.farthest_reached_pos();
+
+        if self.options.capture_heading_slugs
+            && let Value::Object(matches) = &mut self.matches_so_far
+        {
+            let mut headings =
+                collect_heading_slugs(&self.input_tree.walk(), &self.last_input_str, self.got_eof);
+            for heading in &mut headings {
+                heading.line += self.dropped_input.lines;
+            }
+            matches.insert(
+                HEADINGS_KEY.to_string(),
+                Value::Array(headings.iter().map(HeadingSlug::to_value).collect()),
+            );
+        }
+
+        if self.options.embed_schema_version
+            && let Value::Object(matches) = &mut self.matches_so_far
+        {
+            matches.insert(
+                SCHEMA_VERSION_KEY.to_string(),
+                Value::from(self.schema_version),
+            );
+        }
+
+        if self.options.sort_keys {
+            self.matches_so_far.sort_all_objects();
+        }
+
+        self.match_journal.record(&self.matches_so_far);
     }
 
     fn farthest_reached_pos(&self) -> NodePosPair {
@@ -256,7 +1257,11 @@ impl ValidatorState for Validator {
 mod tests {
     use serde_json::json;
 
-    use crate::mdschema::validation::errors::{SchemaError, SchemaViolationError};
+    use crate::mdschema::validation::back_references::BackReferenceError;
+    use crate::mdschema::validation::errors::{
+        ChildrenLengthRange, NodeContentMismatchKind, SchemaError, SchemaViolationError,
+    };
+    use crate::mdschema::validation::ts_utils::{find_node_by_index, get_node_text, parse_markdown};
 
     use super::*;
 
@@ -272,6 +1277,24 @@ mod tests {
         )
     }
 
+    /// Like `do_validate`, but validates with the given options
+    fn do_validate_with_options(
+        schema: &str,
+        input: &str,
+        eof: bool,
+        options: ValidateOptions,
+    ) -> (Vec<ValidationError>, Value) {
+        let mut validator = Validator::new(schema, input, eof)
+            .expect("Failed to create validator")
+            .with_options(options);
+        validator.validate();
+
+        (
+            validator.errors_so_far().cloned().collect(),
+            validator.matches_so_far().clone(),
+        )
+    }
+
     /// Helper function to create a validator for incremental testing
     /// Returns the validator for further manipulation
     fn get_validator_for_incremental(schema: &str, input: &str, eof: bool) -> Validator {
@@ -302,6 +1325,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_input_replaces_input_it_does_not_extend() {
+        let schema = "# Title\n\nSome text\n";
+        let mut validator = get_validator_for_incremental(schema, "# Title\n\nSome tex", false);
+        validator.validate();
+
+        // As long as what we had, and longer, but not a continuation of it
+        for input in ["# Title\n\nSome tax", "# Title\n\nSome taxes"] {
+            validator.read_input(input, false).unwrap();
+            assert_eq!(validator.last_input_str(), input);
+            assert_eq!(
+                validator
+                    .input_tree
+                    .root_node()
+                    .utf8_text(validator.last_input_str().as_bytes())
+                    .unwrap(),
+                input
+            );
+        }
+
+        validator
+            .read_final_input("# Title\n\nSome text\n")
+            .unwrap();
+        validator.validate();
+        assert_eq!(validator.errors_so_far().count(), 0);
+    }
+
     #[test]
     fn test_initial_validate_with_eof_works() {
         let input = "Hello World";
@@ -370,68 +1420,341 @@ mod tests {
         assert_eq!(errors.count(), 0);
     }
 
-    #[test]
-    fn test_validation_should_fail_with_mismatched_content() {
-        let schema = "# Test\n\nfooobar\n\ntest\n";
-        let input = "# Test\n\nfooobar\n\ntestt\n";
+    /// Feed the input to a validator in chunks of (at least) `chunk_len` bytes,
+    /// validating after each one like `process_stdio` does.
+    fn stream_input(schema: &str, input: &str, chunk_len: usize) -> Validator {
+        let mut validator = Validator::new_incomplete(schema, "").unwrap();
+        let mut end = 0;
+        while end < input.len() {
+            end = (end + chunk_len).min(input.len());
+            while !input.is_char_boundary(end) {
+                end += 1;
+            }
+            validator.read_more_input(&input[..end]).unwrap();
+            validator.validate();
+        }
+        validator.read_final_input(input).unwrap();
+        validator.validate();
+        validator
+    }
 
-        let (errors, _) = do_validate(schema, input, true);
-        match &errors[0] {
-            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
-                ..
-            }) => {}
-            _ => panic!("Expected TextMismatch error, got {:?}", errors[0]),
+    #[test]
+    fn test_streamed_input_tree_matches_fresh_parse() {
+        let schema = "# Title\n\n- `item:/\\w+/`{1,}\n\nSome *text* 🦀\n\n```rust\nfn main() {}\n```\n";
+        let input = "# Title\n\n- one\n- two\n- three\n\nSome *text* 🦀\n\n```rust\nfn main() {}\n```\n";
+
+        let validator = stream_input(schema, input, 3);
+        let fresh_tree = parse_markdown(input).unwrap();
+
+        // Every node, and so every descendant index, is where a fresh parse
+        // would put it
+        let mut streamed_cursor = validator.input_tree().walk();
+        let mut fresh_cursor = fresh_tree.walk();
+        let descendant_count = fresh_tree.root_node().descendant_count();
+        assert_eq!(validator.input_tree().root_node().descendant_count(), descendant_count);
+        for index in 0..descendant_count {
+            streamed_cursor.goto_descendant(index);
+            fresh_cursor.goto_descendant(index);
+            let (streamed, fresh) = (streamed_cursor.node(), fresh_cursor.node());
+            assert_eq!(streamed.kind(), fresh.kind());
+            assert_eq!(streamed.byte_range(), fresh.byte_range());
+            assert_eq!(streamed.start_position(), fresh.start_position());
+            assert_eq!(streamed.end_position(), fresh.end_position());
         }
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert_eq!(validator.errors_so_far().cloned().collect::<Vec<_>>(), errors);
+        assert_eq!(validator.matches_so_far(), &value);
     }
 
     #[test]
-    fn test_validation_passes_with_different_whitespace() {
-        let schema = "# Test\n\nfooobar\n\ntest\n";
-        let input = "# Test\n\n\nfooobar\n\n\n\ntest\n\n";
+    fn test_errors_are_the_same_wherever_a_chunk_ends() {
+        let schema = "# Release\n\nVersion: `v:/\\d+\\.\\d+\\.\\d+/`\n\n\
+                      By `first:/\\w+/` `last:/\\w+/`\n";
+        let inputs = [
+            "# Release\n\nVersion: 1.2.3\n\nBy Ada Lovelace\n",
+            "# Release\n\nVersion: 1.x.3\n\nBy Ada Lovelace\n",
+            "# Release\n\nVersion: 1.2.3\n\nBy Ada -\n",
+        ];
 
-        let (errors, _) = do_validate(schema, input, true);
-        assert_eq!(
-            errors,
-            vec![],
-            "Expected no validation errors but found {:?}",
-            errors
-        );
+        for input in inputs {
+            let (errors, value) = do_validate(schema, input, true);
+            assert_eq!(errors.is_empty(), input == inputs[0]);
+
+            // Ending the first chunk mid-word, like in the middle of the
+            // version, changes nothing once the rest comes in
+            for split in 1..input.len() {
+                let mut validator = Validator::new_incomplete(schema, &input[..split]).unwrap();
+                validator.validate();
+                validator.read_final_input(input).unwrap();
+                validator.validate();
+
+                assert_eq!(
+                    validator.errors_so_far().cloned().collect::<Vec<_>>(),
+                    errors,
+                    "split at {} of {:?}",
+                    split,
+                    input
+                );
+                assert_eq!(validator.matches_so_far(), &value);
+            }
+        }
     }
 
+    /// Streaming a document should take time linear in its length. Run with
+    /// `cargo test --release -- --ignored` to check.
     #[test]
-    fn test_validation_should_fail_with_mismatched_content_using_escaped_newlines() {
-        let schema = "# Test\n\nfooobar\n\ntest\n";
-        let input = "# Test\n\nfooobar\n\ntestt\n";
+    #[ignore = "timing-sensitive"]
+    fn test_streaming_large_input_is_roughly_linear() {
+        fn time_streaming(sections: usize) -> std::time::Duration {
+            let document: String = (0..sections)
+                .map(|i| format!("## Entry {}\n\nA line of log output for entry {}.\n\n", i, i))
+                .collect();
+
+            let start = std::time::Instant::now();
+            // Chunks the size of `process_stdio`'s default buffer
+            let validator = stream_input(&document, &document, 2048);
+            let elapsed = start.elapsed();
+
+            assert_eq!(validator.errors_so_far().count(), 0);
+            elapsed
+        }
 
-        let (errors, _) = do_validate(schema, input, true);
+        // About 1MB and 4MB
+        let small = time_streaming(16_000);
+        let large = time_streaming(64_000);
         assert!(
-            !errors.is_empty(),
-            "Expected validation errors but found none"
+            large < small * 8,
+            "Streaming 4x the input took {:?} vs {:?}",
+            large,
+            small
         );
     }
 
+    fn long_list(items: usize) -> String {
+        (0..items).map(|i| format!("- entry{}\n", i)).collect()
+    }
+
     #[test]
-    fn test_when_different_node_counts_and_got_eof_reports_error() {
-        let schema = r#"
-# Test
+    fn test_matchers_are_built_once_across_items_and_passes() {
+        const ITEMS: usize = 2_000;
+        let schema = "- `item:/\\w+/`{1,}\n";
+        let input = long_list(ITEMS);
 
-fooobar
+        let validator = stream_input(schema, &input, 1024);
+        assert_eq!(validator.errors_so_far().count(), 0);
+        assert_eq!(
+            validator.matches_so_far()["item"].as_array().map(Vec::len),
+            Some(ITEMS)
+        );
+        assert_eq!(validator.matcher_cache.len(), 1);
+    }
 
-test
-"#;
+    #[test]
+    fn test_cached_matchers_with_the_same_pattern_keep_their_ids() {
+        let schema = "`first:/\\w+/` and `second:/\\w+/`\n\n- `rest:/\\w+/`{1,}\n";
+        let input = "one and two\n\n- three\n- four\n";
 
-        let input = r#"
-# Test
+        let validator = stream_input(schema, input, 4);
+        let (errors, value) = do_validate(schema, input, true);
+        assert_eq!(validator.errors_so_far().count(), 0);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(validator.matches_so_far(), &value);
+        assert_eq!(
+            value,
+            json!({"first": "one", "second": "two", "rest": ["three", "four"]})
+        );
+    }
 
-fooobar
-"#;
+    /// Validating a long repeated list should take time linear in its length,
+    /// since its matcher is only built once. Run with
+    /// `cargo test --release -- --ignored` to check.
+    #[test]
+    #[ignore = "timing-sensitive"]
+    fn test_long_repeated_list_is_roughly_linear() {
+        fn time_list(items: usize) -> std::time::Duration {
+            let schema = "# Entries\n\n- `entry:/\\w+/`{1,}\n";
+            let input = format!("# Entries\n\n{}", long_list(items));
+
+            let start = std::time::Instant::now();
+            let (errors, _) = do_validate(schema, &input, true);
+            let elapsed = start.elapsed();
+
+            assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+            elapsed
+        }
 
-        let (errors, _) = do_validate(schema, input, true);
-        assert_eq!(errors.len(), 1);
+        let small = time_list(10_000);
+        let large = time_list(40_000);
+        assert!(
+            large < small * 8,
+            "Validating a list 4x as long took {:?} vs {:?}",
+            large,
+            small
+        );
+    }
 
-        match &errors[0] {
-            ValidationError::SchemaViolation(SchemaViolationError::MalformedNodeStructure {
-                schema_index,
+    #[test]
+    fn test_bounded_memory_streams_huge_input_in_little_memory() {
+        const BLOCKS: usize = 100_000;
+        const TYPO: usize = BLOCKS - 3;
+
+        // Every block is as long as the others, some capture their number and
+        // one near the end has a typo
+        let (schema_blocks, input_blocks): (Vec<String>, Vec<String>) = (0..BLOCKS)
+            .map(|i| {
+                let input = match i {
+                    TYPO => format!("Block {:06} of the documnet.\n\n", i),
+                    _ => format!("Block {:06} of the document.\n\n", i),
+                };
+                let schema = match i % 10_000 {
+                    0 => "Block `n:/\\d+/` of the document.\n\n".to_string(),
+                    _ => format!("Block {:06} of the document.\n\n", i),
+                };
+                (schema, input)
+            })
+            .unzip();
+        let schema = schema_blocks.concat();
+        let input = input_blocks.concat();
+        let largest_block = input_blocks.iter().map(String::len).max().unwrap();
+
+        let options = ValidateOptions {
+            include_spans: true,
+            bounded_memory: true,
+            ..Default::default()
+        };
+        let mut validator = Validator::new_incomplete(&schema, "")
+            .unwrap()
+            .with_options(options.clone());
+        let mut peak_len = 0;
+        for chunk in input_blocks.chunks(4) {
+            validator.append_more_input(&chunk.concat()).unwrap();
+            peak_len = peak_len.max(validator.last_input_str().len());
+            validator.validate();
+        }
+        validator.append_final_input("").unwrap();
+        validator.validate();
+
+        let mut expected = Validator::new_complete(&schema, &input)
+            .unwrap()
+            .with_options(ValidateOptions {
+                bounded_memory: false,
+                ..options
+            });
+        expected.validate();
+
+        assert!(
+            peak_len <= 8 * largest_block,
+            "Kept {} bytes, with blocks of {} bytes",
+            peak_len,
+            largest_block
+        );
+        assert_eq!(
+            validator.dropped_input().bytes + validator.last_input_str().len(),
+            input.len()
+        );
+
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        assert_eq!(errors, expected.errors_so_far().cloned().collect::<Vec<_>>());
+        assert_eq!(errors.len(), 1);
+        let (_, input_index) = errors[0].node_indexes();
+        let typo_node = validator.input_node(input_index.unwrap());
+        assert!(get_node_text(&typo_node, validator.last_input_str()).contains("documnet"));
+
+        assert_eq!(validator.matches_so_far(), expected.matches_so_far());
+        assert_eq!(validator.matches_so_far()["n"].as_array().unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_bounded_memory_keeps_input_a_repeating_paragraph_may_still_match() {
+        let schema = "# Log\n\n`line:/\\w+/`{1,}\n\n## End\n";
+        let input = "# Log\n\nfirst\n\nsecond\n\nthird\n\n## End\n";
+        let options = ValidateOptions {
+            bounded_memory: true,
+            ..Default::default()
+        };
+
+        let mut validator = Validator::new_incomplete(schema, "")
+            .unwrap()
+            .with_options(options);
+        for line in input.split_inclusive('\n') {
+            validator.append_more_input(line).unwrap();
+            validator.validate();
+        }
+        validator.append_final_input("").unwrap();
+        validator.validate();
+
+        // Only the heading before the repeating paragraph can go
+        assert_eq!(validator.dropped_input().bytes, "# Log\n\n".len());
+        assert_eq!(validator.errors_so_far().count(), 0);
+        assert_eq!(
+            validator.matches_so_far(),
+            &do_validate(schema, input, true).1
+        );
+    }
+
+    #[test]
+    fn test_validation_should_fail_with_mismatched_content() {
+        let schema = "# Test\n\nfooobar\n\ntest\n";
+        let input = "# Test\n\nfooobar\n\ntestt\n";
+
+        let (errors, _) = do_validate(schema, input, true);
+        match &errors[0] {
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                ..
+            }) => {}
+            _ => panic!("Expected TextMismatch error, got {:?}", errors[0]),
+        }
+    }
+
+    #[test]
+    fn test_validation_passes_with_different_whitespace() {
+        let schema = "# Test\n\nfooobar\n\ntest\n";
+        let input = "# Test\n\n\nfooobar\n\n\n\ntest\n\n";
+
+        let (errors, _) = do_validate(schema, input, true);
+        assert_eq!(
+            errors,
+            vec![],
+            "Expected no validation errors but found {:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_validation_should_fail_with_mismatched_content_using_escaped_newlines() {
+        let schema = "# Test\n\nfooobar\n\ntest\n";
+        let input = "# Test\n\nfooobar\n\ntestt\n";
+
+        let (errors, _) = do_validate(schema, input, true);
+        assert!(
+            !errors.is_empty(),
+            "Expected validation errors but found none"
+        );
+    }
+
+    #[test]
+    fn test_when_different_node_counts_and_got_eof_reports_error() {
+        let schema = r#"
+# Test
+
+fooobar
+
+test
+"#;
+
+        let input = r#"
+# Test
+
+fooobar
+"#;
+
+        let (errors, _) = do_validate(schema, input, true);
+        assert_eq!(errors.len(), 1);
+
+        match &errors[0] {
+            ValidationError::SchemaViolation(SchemaViolationError::MalformedNodeStructure {
+                schema_index,
                 input_index,
                 ..
             }) => {
@@ -758,6 +2081,62 @@ Version: 1
         );
     }
 
+    #[test]
+    fn test_literal_matcher_at_end_of_paragraph() {
+        let schema = "Run `x`!\n";
+        let input = "Run `x`\n";
+
+        let (errors, _) = do_validate(schema, input, true);
+        assert_eq!(
+            errors,
+            vec![],
+            "Expected no validation errors but found {:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_literal_matcher_with_suffix() {
+        let schema = "`x`! suffix\n";
+
+        let (errors, _) = do_validate(schema, "`x` suffix\n", true);
+        assert_eq!(
+            errors,
+            vec![],
+            "Expected no validation errors but found {:?}",
+            errors
+        );
+
+        let (errors, _) = do_validate(schema, "`x`\n", true);
+        assert!(
+            errors.iter().any(|error| matches!(
+                error,
+                ValidationError::SchemaViolation(SchemaViolationError::ChildrenLengthMismatch {
+                    expected: ChildrenLengthRange(2, 2),
+                    actual: 1,
+                    ..
+                })
+            )),
+            "Expected a children length mismatch but found {:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_matchers_next_to_emphasis() {
+        let schema = "`name:/\\w+/` *said hi*\n\n*Hi* `other:/\\w+/`\n\n`x`!*said hi*\n";
+        let input = "Wolf *said hi*\n\n*Hi* Wolf\n\n`x`*said hi*\n";
+
+        let (errors, matches) = do_validate(schema, input, true);
+        assert_eq!(
+            errors,
+            vec![],
+            "Expected no validation errors but found {:?}",
+            errors
+        );
+        assert_eq!(matches, json!({"name": "Wolf", "other": "Wolf"}));
+    }
+
     #[test]
     fn test_only_whitespace() {
         let schema = "\n\n";
@@ -894,6 +2273,41 @@ Paragraph text
         );
     }
 
+    #[test]
+    fn test_capture_groups_are_structured() {
+        let schema = "Version `version:/(\\d+)\\.(\\d+)\\.(\\d+)/`\n\n\
+                      Released `date:/(?P<year>\\d{4})-(?P<month>\\d{2})(-(?P<day>\\d{2}))?/`\n\n\
+                      By `author:/\\w+/`\n";
+        let input = "Version 1.2.3\n\nReleased 2024-05\n\nBy Alice\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert_eq!(errors, vec![]);
+        assert_eq!(
+            value,
+            json!({
+                "version": {"full": "1.2.3", "groups": ["1", "2", "3"]},
+                "date": {"full": "2024-05", "year": "2024", "month": "05", "day": null},
+                "author": "Alice",
+            })
+        );
+    }
+
+    #[test]
+    fn test_repeated_list_matcher_with_capture_groups() {
+        let schema = "- `dep:int:/(\\d+)\\.(\\d+)/`{1,}\n";
+        let input = "- 1.2\n- 3.4\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert_eq!(errors, vec![]);
+        assert_eq!(
+            value,
+            json!({"dep": [
+                {"full": "1.2", "groups": [1, 2]},
+                {"full": "3.4", "groups": [3, 4]},
+            ]})
+        );
+    }
+
     #[test]
     fn test_matcher_entire_line() {
         let schema = "`line:/.+/`\n";
@@ -988,9 +2402,77 @@ Footer: goodbye
     #[test]
     fn test_multiple_matchers() {
         // The schema becomes a paragraph with multiple code nodes
-        let schema = "`id:/test/` `id:/example/`";
+        let schema = "`first:/test/` `second:/example/`";
         let input = "test example";
 
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"first": "test", "second": "example"}));
+    }
+
+    #[test]
+    fn test_multiple_matchers_anchored_by_literals() {
+        let schema =
+            "Name: `first:/\\w+/` `last:/\\w+/`, age `age:int:/\\d+/`, `op:/[<>]/` `=`! `x:/.*/`\n";
+        let input = "Name: Ada Lovelace, age 36, < `=` y\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(
+            value,
+            json!({"first": "Ada", "last": "Lovelace", "age": 36, "op": "<", "x": "y"})
+        );
+    }
+
+    #[test]
+    fn test_multiple_matchers_error_points_at_failing_matcher() {
+        let schema = "`a:/\\d+/` and `b:/\\d+/`\n";
+        let input = "1 and x\n";
+
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+        let errors: Vec<_> = validator.errors_so_far().collect();
+
+        assert_eq!(errors.len(), 1);
+        match errors[0] {
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                schema_index,
+                actual,
+                kind: NodeContentMismatchKind::Matcher,
+                ..
+            }) => {
+                let schema_node =
+                    find_node_by_index(validator.schema_tree().root_node(), *schema_index);
+                assert_eq!(get_node_text(&schema_node, schema), "`b:/\\d+/`");
+                assert_eq!(actual, "x");
+            }
+            error => panic!("Expected a matcher mismatch, got {:?}", error),
+        }
+        assert_eq!(validator.matches_so_far(), &json!({"a": "1"}));
+    }
+
+    #[test]
+    fn test_multiple_matchers_defer_trailing_matcher() {
+        let schema = "`a:/\\d+/` and `b:/\\d+/`";
+
+        let (errors, value) = do_validate(schema, "1 and 2", false);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"a": "1"}));
+
+        let (errors, value) = do_validate(schema, "1 an", false);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({}));
+
+        let (errors, value) = do_validate(schema, "1 and 2", true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"a": "1", "b": "2"}));
+    }
+
+    #[test]
+    fn test_multiple_matchers_with_formatting_between() {
+        let schema = "`a:/test/` *and* `b:/example/`";
+        let input = "test *and* example";
+
         let mut validator =
             Validator::new(schema, input, true).expect("Failed to create validator");
         validator.validate();
@@ -1152,4 +2634,2141 @@ Content for section 3."#;
             errors
         );
     }
+
+    #[test]
+    fn test_slow_regex_on_long_line_completes() {
+        // Nested quantifiers would backtrack exponentially in other engines
+        let schema = "Start `slow:/(a+)+$/`";
+        let input = format!("Start {}!", "a".repeat(100_000));
+
+        let (errors, value) = do_validate(schema, &input, true);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch { .. })
+        ));
+        assert_eq!(value, json!({}));
+    }
+
+    #[test]
+    fn test_matcher_input_longer_than_budget_is_schema_error() {
+        let schema = "Start `word:/\\w+/`";
+        let input = format!("Start {}", "a".repeat(200));
+
+        let mut validator = Validator::new_complete(schema, &input)
+            .unwrap()
+            .with_options(ValidateOptions {
+                max_matcher_input_len: 100,
+                ..Default::default()
+            });
+        validator.validate();
+
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        assert_eq!(errors.len(), 1);
+        match &errors[0] {
+            ValidationError::SchemaError(SchemaError::MatcherError { error, .. }) => {
+                assert_eq!(
+                    error,
+                    &crate::mdschema::validation::matchers::matcher::MatcherError::InputTooLong {
+                        len: 200,
+                        max_len: 100
+                    }
+                );
+            }
+            error => panic!("Expected an input too long error, got {:?}", error),
+        }
+        assert_eq!(validator.matches_so_far(), &json!({}));
+    }
+
+    #[test]
+    fn test_regex_limits_are_checked_when_loading_the_schema() {
+        let schema = "# `title:/[a-z]{1000}/`\n\nBy `author:/\\w+/`\n";
+        let input = "# title\n\nBy Alice\n";
+
+        let (errors, _) = do_validate(schema, input, true);
+        assert!(
+            !errors.iter().any(is_regex_limit_error),
+            "Expected no regex limit errors, got {:?}",
+            errors
+        );
+
+        let mut validator = Validator::new_complete(schema, input)
+            .unwrap()
+            .with_options(ValidateOptions {
+                regex_limits: RegexLimits {
+                    size_limit: 10_000,
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        validator.validate();
+
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        assert!(
+            matches!(
+                errors.as_slice(),
+                [ValidationError::SchemaError(SchemaError::MatcherError {
+                    error: MatcherError::RegexTooComplex { limit: 10_000 },
+                    ..
+                })]
+            ),
+            "{:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_pathological_regex_is_rejected_quickly() {
+        let schema = "`slow:/(\\w{1000}){1000}/`\n";
+        let input = "a".repeat(10_000);
+
+        let start = std::time::Instant::now();
+        let (errors, _) = do_validate(schema, &input, true);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+        assert!(
+            errors.iter().any(is_regex_limit_error),
+            "Expected a regex limit error, got {:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_compare_directive_makes_literals_case_insensitive() {
+        let schema = "<!-- mds:compare case-insensitive, collapse-whitespace -->\n\n# Getting  Started\n\nSome text\n";
+        let input = "# getting started\n\nSome TEXT\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({}));
+    }
+
+    #[test]
+    fn test_compare_modifier_only_applies_to_its_node() {
+        let schema = "# Getting Started {compare: case-insensitive}\n\nSome text\n";
+        let input = "# getting started\n\nSome TEXT\n";
+
+        let (errors, _) = do_validate(schema, input, true);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unknown_compare_mode_is_a_schema_error() {
+        let schema = "# Getting Started {compare: case-insensitve}\n";
+
+        let (errors, _) = do_validate(schema, "# getting started\n", true);
+        assert!(matches!(
+            errors.as_slice(),
+            [ValidationError::SchemaError(SchemaError::UnknownCompareMode { mode, .. })]
+                if mode == "case-insensitve"
+        ));
+
+        let schema = "<!-- mds:compare nfd -->\n\n# Getting Started\n";
+        let (errors, _) = do_validate(schema, "# Getting Started\n", true);
+        assert!(matches!(
+            errors.as_slice(),
+            [ValidationError::SchemaError(SchemaError::UnknownCompareMode { mode, .. })]
+                if mode == "nfd"
+        ));
+    }
+
+    #[test]
+    fn test_input_comment_between_headings_is_skipped() {
+        let schema = "# One\n\n# Two\n";
+        let input = "# One\n\n<!-- prettier-ignore -->\n\n# Two\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({}));
+
+        let strict_comments = ValidateOptions {
+            strict_comments: true,
+            ..Default::default()
+        };
+        let (errors, _) = do_validate_with_options(schema, input, true, strict_comments);
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn test_input_comment_as_last_node_is_skipped() {
+        let schema = "# One\n\n# Two\n";
+        let input = "# One\n\n# Two\n\n<!-- SPDX-License-Identifier: MIT -->\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({}));
+
+        let strict_comments = ValidateOptions {
+            strict_comments: true,
+            ..Default::default()
+        };
+        let (errors, _) = do_validate_with_options(schema, input, true, strict_comments);
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn test_input_comment_inside_list_item_is_skipped() {
+        let schema = "- one\n- two\n";
+        let input = "- one\n  <!-- note -->\n- two\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({}));
+
+        let schema = "- `item:/\\w+/`{1,}\n";
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"item": ["one", "two"]}));
+    }
+
+    #[test]
+    fn test_ignore_inline_html() {
+        let schema = "Written by `author:/[\\w ]+/`\n";
+        let input = "Written by <b>Ada</b> Lovelace\n";
+
+        let (errors, _) = do_validate(schema, input, true);
+        assert!(!errors.is_empty());
+
+        let (errors, value) = do_validate_with_options(
+            schema,
+            input,
+            true,
+            ValidateOptions {
+                ignore_inline_html: true,
+                include_spans: true,
+                ..Default::default()
+            },
+        );
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(
+            value,
+            json!({
+                "author": {
+                    "value": "Ada Lovelace",
+                    "start": { "line": 1, "col": 15, "byte": 14 },
+                    "end": { "line": 1, "col": 31, "byte": 30 },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_ignore_inline_html_between_matchers() {
+        let schema = "`first:/\\w+/` and `second:/\\w+/`\n";
+        let input = "one<sup>1</sup> and two<br>\n";
+        let options = ValidateOptions {
+            ignore_inline_html: true,
+            include_spans: true,
+            ..Default::default()
+        };
+
+        // The text of the `<sup>` is kept, and only its tags are skipped
+        let (errors, value) = do_validate_with_options(schema, input, true, options.clone());
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value["first"]["value"], "one1");
+        assert_eq!(value["first"]["start"]["byte"], 0);
+        assert_eq!(value["first"]["end"]["byte"], 9);
+        assert_eq!(value["second"]["value"], "two");
+        assert_eq!(value["second"]["start"]["byte"], 20);
+        assert_eq!(value["second"]["end"]["byte"], 23);
+
+        let (errors, value) = do_validate_with_options(
+            schema,
+            input,
+            true,
+            ValidateOptions {
+                include_spans: false,
+                ..options
+            },
+        );
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"first": "one1", "second": "two"}));
+    }
+
+    #[test]
+    fn test_inline_html_in_the_schema_is_required() {
+        let schema = "Line one<br>\nLine two\n";
+
+        let (errors, _) = do_validate(schema, "Line one<br>\nLine two\n", true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+
+        for options in [
+            ValidateOptions::default(),
+            ValidateOptions {
+                ignore_inline_html: true,
+                ..Default::default()
+            },
+        ] {
+            let (errors, _) =
+                do_validate_with_options(schema, "Line one<br/>\nLine two\n", true, options);
+            assert_eq!(errors.len(), 1, "Expected one error, got {:?}", errors);
+            assert!(matches!(
+                &errors[0],
+                ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                    expected,
+                    actual,
+                    kind: NodeContentMismatchKind::Literal,
+                    ..
+                }) if expected == "<br>" && actual == "<br/>"
+            ));
+        }
+    }
+
+    #[test]
+    fn test_ignore_html_blocks() {
+        let schema = "# Title\n\nSome text\n";
+        let input = concat!(
+            "# Title\n\n",
+            "<div align=\"center\">\n  <img src=\"logo.png\">\n</div>\n\n",
+            "Some text\n",
+        );
+
+        let (errors, _) = do_validate(schema, input, true);
+        assert!(!errors.is_empty());
+
+        let (errors, value) = do_validate_with_options(
+            schema,
+            input,
+            true,
+            ValidateOptions {
+                ignore_html_blocks: true,
+                ..Default::default()
+            },
+        );
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({}));
+    }
+
+    #[test]
+    fn test_html_blocks_in_the_schema_are_required() {
+        let schema = "# Title\n\n<div align=\"center\">\n</div>\n\nSome text\n";
+
+        let (errors, _) = do_validate(schema, schema, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+
+        let input = "# Title\n\n<div align=\"left\">\n</div>\n\nSome text\n";
+        let (errors, _) = do_validate(schema, input, true);
+        assert_eq!(errors.len(), 1, "Expected one error, got {:?}", errors);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                kind: NodeContentMismatchKind::Literal,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_suppression_comment_turns_errors_into_warnings() {
+        let schema = "# Title\n\nSome text\n";
+        let input = "<!-- mdvalidate-disable-next-block MDV005 -->\n# Titel\n\nSome text\n";
+
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+        assert_eq!(validator.errors_so_far().count(), 0);
+        let suppressed: Vec<_> = validator.suppressed_errors_so_far().collect();
+        assert_eq!(suppressed.len(), 1);
+        assert_eq!(suppressed[0].code(), "MDV005");
+
+        let mut validator = Validator::new_complete(schema, input)
+            .unwrap()
+            .with_options(ValidateOptions {
+                ignore_suppressions: true,
+                ..Default::default()
+            });
+        validator.validate();
+        assert_eq!(validator.errors_so_far().count(), 1);
+        assert_eq!(validator.suppressed_errors_so_far().count(), 0);
+    }
+
+    #[test]
+    fn test_suppression_comment_only_covers_listed_codes_and_next_block() {
+        let schema = "# Title\n\nSome text\n";
+
+        let input = "<!-- mdvalidate-disable-next-block MDV001 -->\n# Titel\n\nSome text\n";
+        let (errors, _) = do_validate(schema, input, true);
+        assert_eq!(errors.len(), 1);
+
+        let input = "<!-- mdvalidate-disable-next-block -->\n# Title\n\nOther text\n";
+        let (errors, _) = do_validate(schema, input, true);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_severity_overrides_turn_errors_into_warnings() {
+        let schema = "# Title\n\n`name:/\\w+/` (required)\n\nSome text\n";
+        let input = "# Title\n\nJose (optional)\n\nOther text\n";
+        let validate_with = |rules: &[&str]| {
+            let mut validator = Validator::new_complete(schema, input)
+                .unwrap()
+                .with_options(ValidateOptions {
+                    severity_overrides: rules.iter().map(|rule| rule.parse().unwrap()).collect(),
+                    ..Default::default()
+                });
+            validator.validate();
+            let codes = |errors: std::slice::Iter<'_, ValidationError>| {
+                errors.map(ValidationError::code).collect::<Vec<_>>()
+            };
+            (
+                codes(validator.errors_so_far()),
+                codes(validator.suppressed_errors_so_far()),
+            )
+        };
+
+        assert_eq!(validate_with(&[]), (vec!["MDV003", "MDV005"], vec![]));
+        assert_eq!(validate_with(&["MDV005"]), (vec!["MDV003"], vec!["MDV005"]));
+        assert_eq!(
+            validate_with(&["node_content_mismatch:suffix"]),
+            (vec!["MDV005"], vec!["MDV003"])
+        );
+        assert_eq!(
+            validate_with(&["node_content_mismatch"]),
+            (vec![], vec!["MDV003", "MDV005"])
+        );
+    }
+
+    #[test]
+    fn test_suspicious_matchers_are_warnings() {
+        let schema = "# Title\n\nBy `author:/\\w+`\n";
+        let input = "# Title\n\nBy `author:/\\w+`\n";
+
+        let mut validator = Validator::new_complete(schema, input).unwrap();
+        validator.validate();
+        validator.validate();
+        assert_eq!(validator.errors_so_far().count(), 0);
+        let warnings: Vec<_> = validator.suppressed_errors_so_far().collect();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code(), "MDV111");
+        assert!(
+            warnings[0].to_string().contains("did you mean `author:/\\w+/`?"),
+            "{}",
+            warnings[0]
+        );
+
+        let schema = "# Title\n\nBy `author:/\\w+/`\n";
+        let validator = Validator::new_complete(schema, "# Title\n").unwrap();
+        assert_eq!(validator.suppressed_errors_so_far().count(), 0);
+    }
+
+    #[test]
+    fn test_similar_literals() {
+        let schema = "# Title\n\nThis page describes the API. {similar: 0.9}\n";
+
+        // Streamed input with a typo isn't reported before it ends
+        let validator = stream_input(schema, "# Title\n\nThis page describes teh API.\n", 4);
+        assert_eq!(validator.errors_so_far().count(), 0);
+
+        let validator = stream_input(schema, "# Title\n\nThis page lists the endpoints.\n", 4);
+        let errors: Vec<_> = validator.errors_so_far().collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code(), "MDV025");
+    }
+
+    #[test]
+    fn test_schema_version() {
+        let schema = "<!-- mds-version: 2 -->\n\n# `title:/\\w+/`\n";
+
+        let mut validator = Validator::new_complete(schema, "# Hello\n")
+            .unwrap()
+            .with_options(ValidateOptions {
+                embed_schema_version: true,
+                ..Default::default()
+            });
+        validator.validate();
+        assert_eq!(validator.errors_so_far().count(), 0);
+        assert_eq!(validator.schema_version(), 2);
+        assert_eq!(
+            validator.matches_so_far(),
+            &json!({"title": "Hello", "$schema_version": 2})
+        );
+
+        let validator = Validator::new_complete("# Hello\n", "# Hello\n").unwrap();
+        assert_eq!(validator.schema_version(), 1);
+    }
+
+    #[test]
+    fn test_invalid_schema_version() {
+        let schema = "<!-- mds-version: two -->\n# Hello\n";
+
+        let mut validator = Validator::new_complete(schema, "# Hello\n").unwrap();
+        validator.validate();
+        validator.validate();
+        assert_eq!(
+            validator.errors_so_far().cloned().collect::<Vec<_>>(),
+            vec![ValidationError::SchemaError(
+                SchemaError::InvalidSchemaVersion {
+                    schema_index: 0,
+                    declared: "two".to_string(),
+                }
+            )]
+        );
+        assert_eq!(validator.schema_version(), 1);
+    }
+
+    #[test]
+    fn test_input_selection() {
+        let input = "# Intro\n\nHi\n\n# API\n\n## Auth\n\nUse a tken.\n\n## Other\n\nMore.\n";
+        let select = |schema: &str, options: ValidateOptions| {
+            let mut validator = Validator::new_complete(schema, input)
+                .unwrap()
+                .with_options(options)
+                .with_input_selection("API > Auth".parse().unwrap());
+            validator.validate();
+            validator
+        };
+
+        // Errors point into the whole input
+        let validator = select("## Auth\n\nUse a token.\n", ValidateOptions::default());
+        let errors: Vec<_> = validator.errors_so_far().collect();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].code(), "MDV005");
+        let report = crate::mdschema::validation::json_report::ValidatorReport::new(&validator)
+            .to_json();
+        assert_eq!(
+            report["errors"][0]["input"]["start"],
+            json!({ "line": 9, "col": 1, "byte": 29 })
+        );
+
+        // And so do spans
+        let options = ValidateOptions {
+            include_spans: true,
+            ..Default::default()
+        };
+        let validator = select("## Auth\n\nUse a `what:/\\w+/`.\n", options);
+        assert_eq!(validator.errors_so_far().count(), 0);
+        assert_eq!(validator.matches_so_far()["what"]["value"], "tken");
+        assert_eq!(
+            validator.matches_so_far()["what"]["start"],
+            json!({ "line": 9, "col": 7, "byte": 35 })
+        );
+    }
+
+    #[test]
+    fn test_input_selection_waits_for_the_whole_input() {
+        let schema = "## Usage\n\nRun `command:/\\w+/`.\n";
+        let path: HeadingPath = "Usage".parse().unwrap();
+
+        let mut validator = Validator::new_incomplete(schema, "# Tool\n\n## Usage\n\nRun i")
+            .unwrap()
+            .with_input_selection(path.clone());
+        validator.validate();
+        assert_eq!(validator.errors_so_far().count(), 0);
+        assert_eq!(validator.matches_so_far(), &json!({}));
+
+        validator
+            .read_final_input("# Tool\n\n## Usage\n\nRun it.\n\n## License\n")
+            .unwrap();
+        validator.validate();
+        assert_eq!(validator.errors_so_far().count(), 0);
+        assert_eq!(validator.matches_so_far(), &json!({"command": "it"}));
+
+        let mut validator = Validator::new_complete(schema, "# Tool\n")
+            .unwrap()
+            .with_input_selection(path);
+        validator.validate();
+        validator.validate();
+        let errors: Vec<_> = validator.errors_so_far().collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind(), "selection_not_found");
+        assert!(errors[0].to_string().contains("'Usage'"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn test_include_spans() {
+        let schema = "# Title\n\nBy `author:/\\w+/`\n\n- `tag:/\\w+/`{1,}\n";
+        let input = "# Title\n\nBy Alice\n\n- one\n- two\n";
+
+        let mut validator = Validator::new_complete(schema, input)
+            .unwrap()
+            .with_options(ValidateOptions {
+                include_spans: true,
+                ..Default::default()
+            });
+        validator.validate();
+
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(
+            validator.matches_so_far(),
+            &json!({
+                "author": {
+                    "value": "Alice",
+                    "start": { "line": 3, "col": 4, "byte": 12 },
+                    "end": { "line": 3, "col": 9, "byte": 17 },
+                },
+                "tag": [
+                    {
+                        "value": "one",
+                        "start": { "line": 5, "col": 3, "byte": 21 },
+                        "end": { "line": 5, "col": 6, "byte": 24 },
+                    },
+                    {
+                        "value": "two",
+                        "start": { "line": 6, "col": 3, "byte": 27 },
+                        "end": { "line": 6, "col": 6, "byte": 30 },
+                    },
+                ],
+            })
+        );
+    }
+
+    fn matches_json(schema: &str, input: &str, options: ValidateOptions) -> String {
+        let (errors, value) = do_validate_with_options(schema, input, true, options);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        value.to_string()
+    }
+
+    #[test]
+    fn test_matches_are_in_schema_order() {
+        let schema = "# Hi `zeta:/\\w+/`\n\nBy `author:/\\w+/`\n\n- `alpha:/\\w+/`{1,}\n";
+        let input = "# Hi Title\n\nBy Alice\n\n- one\n- two\n";
+
+        let first = matches_json(schema, input, ValidateOptions::default());
+        let second = matches_json(schema, input, ValidateOptions::default());
+        assert_eq!(first, second);
+        assert_eq!(
+            first,
+            r#"{"zeta":"Title","author":"Alice","alpha":["one","two"]}"#
+        );
+
+        let sorted = matches_json(
+            schema,
+            input,
+            ValidateOptions {
+                sort_keys: true,
+                include_spans: true,
+                ..Default::default()
+            },
+        );
+        assert!(
+            sorted.starts_with(r#"{"alpha":[{"end":{"byte":"#),
+            "Expected sorted keys, got {}",
+            sorted
+        );
+        assert!(sorted.contains(r#""author":{"end":"#), "{}", sorted);
+        assert!(sorted.find("\"author\"") < sorted.find("\"zeta\""));
+    }
+
+    #[test]
+    fn test_include_spans_after_multibyte_text() {
+        // Columns count characters but offsets count bytes, so these differ
+        // as soon as there's a multi-byte character before (or in) a capture
+        let schema = "# Títle\n\nCafé ☕ by `author:/\\w+/`\n\n- `tag:/\\w+/`{1,}\n";
+        let input = "# Títle\n\nCafé ☕ by Zoë\n\n- one\n- ünï\n";
+
+        let mut validator = Validator::new_complete(schema, input)
+            .unwrap()
+            .with_options(ValidateOptions {
+                include_spans: true,
+                ..Default::default()
+            });
+        validator.validate();
+
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(
+            validator.matches_so_far(),
+            &json!({
+                "author": {
+                    "value": "Zoë",
+                    "start": { "line": 3, "col": 11, "byte": 23 },
+                    "end": { "line": 3, "col": 14, "byte": 27 },
+                },
+                "tag": [
+                    {
+                        "value": "one",
+                        "start": { "line": 5, "col": 3, "byte": 31 },
+                        "end": { "line": 5, "col": 6, "byte": 34 },
+                    },
+                    {
+                        "value": "ünï",
+                        "start": { "line": 6, "col": 3, "byte": 37 },
+                        "end": { "line": 6, "col": 6, "byte": 42 },
+                    },
+                ],
+            })
+        );
+
+        let value = &validator.matches_so_far()["author"];
+        let (start, end) = (value["start"]["byte"].as_u64(), value["end"]["byte"].as_u64());
+        assert_eq!(&input[start.unwrap() as usize..end.unwrap() as usize], "Zoë");
+    }
+
+    #[test]
+    fn test_include_spans_of_streamed_input() {
+        let schema = "# Title\n\nBy `author:/\\w+/`\n\n- `tag:/\\w+/`{1,}\n";
+        let input = "# Title\n\nBy Alice\n\n- one\n- two\n";
+
+        let mut validator = Validator::new(schema, &input[..12], false)
+            .unwrap()
+            .with_options(ValidateOptions {
+                include_spans: true,
+                ..Default::default()
+            });
+        validator.validate();
+        // The lines found so far are kept, and the appended ones are added
+        validator.read_input(&input[..23], false).unwrap();
+        validator.validate();
+        validator.read_final_input(input).unwrap();
+        validator.validate();
+
+        let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(
+            validator.matches_so_far()["tag"][1],
+            json!({
+                "value": "two",
+                "start": { "line": 6, "col": 3, "byte": 27 },
+                "end": { "line": 6, "col": 6, "byte": 30 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_enum_matcher_inline_and_heading() {
+        let schema = "# Status `status:[draft, final]`\n\nColor: `color:[red, green, blue]`\n";
+
+        let input = "# Status final\n\nColor: green\n";
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"status": "final", "color": "green"}));
+
+        let input = "# Status final\n\nColor: purple\n";
+        let (errors, _) = do_validate(schema, input, true);
+        assert_eq!(errors.len(), 1);
+        match &errors[0] {
+            ValidationError::SchemaViolation(SchemaViolationError::EnumMismatch {
+                allowed,
+                actual,
+                ..
+            }) => {
+                assert_eq!(allowed, &vec!["red", "green", "blue"]);
+                assert_eq!(actual, "purple");
+            }
+            error => panic!("Expected an enum mismatch, got {:?}", error),
+        }
+        assert_eq!(
+            errors[0].to_string(),
+            "Schema violation: Expected one of: red, green, blue; got 'purple'"
+        );
+    }
+
+    #[test]
+    fn test_enum_matcher_in_repeated_list_and_link() {
+        let schema = "- `size:[S, M, L]`{1,}\n\n[Deploy]({env:[staging,production]})\n";
+        let input = "- S\n- L\n\n[Deploy](production)\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"size": ["S", "L"], "env": "production"}));
+    }
+
+    #[test]
+    fn test_reference_link_resolves_against_inline_schema() {
+        let schema = "See [the docs]({url:/https:\\/\\/.+/}).\n";
+        let input = "See [the docs][docs].\n\n[docs]: https://example.com/docs\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"url": "https://example.com/docs"}));
+
+        let input = "See [the docs][docs].\n\n[docs]: ftp://example.com/docs\n";
+        let (errors, _) = do_validate(schema, input, true);
+        assert_eq!(errors.len(), 1, "Expected one error, got {:?}", errors);
+    }
+
+    #[test]
+    fn test_reference_link_in_schema() {
+        let schema = "See [the docs][docs].\n\n[docs]: https://example.com/docs\n";
+
+        // Inline in the input, or with a different label
+        for input in [
+            "See [the docs](https://example.com/docs).\n",
+            "See [the docs][manual].\n\n[manual]: https://example.com/docs\n",
+        ] {
+            let (errors, _) = do_validate(schema, input, true);
+            assert!(errors.is_empty(), "Expected no errors for {:?}, got {:?}", input, errors);
+        }
+    }
+
+    #[test]
+    fn test_unresolved_link_reference() {
+        let schema = "See [the docs](https://example.com/docs).\n";
+        let input = "See [the docs][docs].\n\n[documentation]: https://example.com/docs\n";
+
+        let (errors, _) = do_validate(schema, input, true);
+        assert!(!errors.is_empty(), "Expected an error for the undefined label");
+        let ValidationError::SchemaViolation(
+            error @ SchemaViolationError::UnresolvedLinkReference { .. },
+        ) = &errors[0]
+        else {
+            panic!("Expected UnresolvedLinkReference, got {:?}", errors[0]);
+        };
+        assert_eq!(error.to_string(), "Link reference 'docs' has no definition");
+    }
+
+    #[test]
+    fn test_capture_heading_slugs() {
+        let schema = "# Usage\n\n## `step:/.+/`{1,}\n";
+        let input = "# Usage\n\n## Install it!\n\n## Install it\n";
+
+        let mut validator = Validator::new_complete(schema, input)
+            .unwrap()
+            .with_options(ValidateOptions {
+                capture_heading_slugs: true,
+                ..Default::default()
+            });
+        validator.validate();
+
+        assert_eq!(validator.errors_so_far().count(), 0);
+        assert_eq!(
+            validator.matches_so_far()["__headings"],
+            json!([
+                { "level": 1, "text": "Usage", "slug": "usage", "line": 1 },
+                { "level": 2, "text": "Install it!", "slug": "install-it", "line": 3 },
+                { "level": 2, "text": "Install it", "slug": "install-it-1", "line": 5 },
+            ])
+        );
+
+        // Off by default
+        let (_, value) = do_validate(schema, input, true);
+        assert!(value.get("__headings").is_none());
+    }
+
+    #[test]
+    fn test_broken_anchor() {
+        let schema = "# Getting Started\n\nSee [usage](#usage).\n\n## `section:/.+/`\n";
+
+        let (errors, _) = do_validate(
+            schema,
+            "# Getting Started\n\nSee [usage](#usage).\n\n## Usage\n",
+            true,
+        );
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+
+        let (errors, _) = do_validate(
+            schema,
+            "# Getting Started\n\nSee [usage](#usage).\n\n## Install\n",
+            true,
+        );
+        assert_eq!(errors.len(), 1, "Expected one error, got {:?}", errors);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::BrokenAnchor { anchor, .. })
+                if anchor == "usage"
+        ));
+
+        // The heading may still arrive
+        let (errors, _) = do_validate(
+            schema,
+            "# Getting Started\n\nSee [usage](#usage).\n\n",
+            false,
+        );
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+    }
+
+    #[test]
+    fn test_repeated_sections() {
+        let schema = "# Changelog\n\n## `date:/\\d{4}-\\d{2}-\\d{2}/`{1,}\n\n- `change:/.+/`{1,}\n\n## Contributors\n\n`who`\n";
+        let input = "# Changelog\n\n## 2024-02-01\n\n- Fix the bug\n\n## 2024-01-01\n\n- Add the bug\n- Add a feature\n\n## Contributors\n\nWolf\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(
+            value,
+            json!({
+                "date": [
+                    { "date": "2024-02-01", "change": ["Fix the bug"] },
+                    { "date": "2024-01-01", "change": ["Add the bug", "Add a feature"] },
+                ],
+                "who": "Wolf",
+            })
+        );
+    }
+
+    #[test]
+    fn test_repeated_sections_wrong_count() {
+        let schema = "# Releases\n\n## `version:/v\\d+/`{2,3}\n\nNotes\n";
+        let input = "# Releases\n\n## v1\n\nNotes\n";
+
+        let (errors, _) = do_validate(schema, input, true);
+        assert_eq!(errors.len(), 1, "Expected one error, got {:?}", errors);
+        assert!(matches!(
+            errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::WrongSectionCount {
+                min: Some(2),
+                max: Some(3),
+                actual: 1,
+                ..
+            })
+        ));
+        assert_eq!(
+            errors[0].to_string(),
+            "Schema violation: Expected 2-3 sections, found 1"
+        );
+    }
+
+    #[test]
+    fn test_repeated_sections_can_match_nothing() {
+        let schema = "# Title\n\n## `note`{0,}\n\nText\n\n# End\n";
+        let input = "# Title\n\n# End\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"note": []}));
+    }
+
+    #[test]
+    fn test_optional_section() {
+        let schema = "# Report\n\nSummary: `summary:/.+/`\n\n## `notes:/Notes/`?\n\n`note_text`\n\n## Sources\n\n- `source:/.+/`{1,}\n";
+
+        let input = "# Report\n\nSummary: all good\n\n## Notes\n\nSome notes.\n\n## Sources\n\n- one\n";
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(
+            value,
+            json!({
+                "summary": "all good",
+                "notes": "Notes",
+                "note_text": "Some notes.",
+                "source": ["one"],
+            })
+        );
+
+        let input = "# Report\n\nSummary: all good\n\n## Sources\n\n- one\n";
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"summary": "all good", "source": ["one"]}));
+    }
+
+    #[test]
+    fn test_optional_section_at_end() {
+        let schema = "# Title\n\n## `notes:/Notes/`?\n\n`text`\n";
+
+        for got_eof in [false, true] {
+            let (errors, value) = do_validate(schema, "# Title\n", got_eof);
+            assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+            assert_eq!(value, json!({}));
+        }
+
+        let input = "# Title\n\n## Notes\n\nHello\n";
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"notes": "Notes", "text": "Hello"}));
+
+        // Something that isn't the optional section is still extra input
+        let (errors, _) = do_validate(schema, "# Title\n\n## Other\n\nHello\n", true);
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn test_optional_list_item() {
+        let schema = "- `name:/\\w+/`\n- `nickname:/aka \\w+/`?\n- `age:/\\d+/`\n";
+
+        let (errors, value) = do_validate(schema, "- Ann\n- aka Annie\n- 30\n", true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(
+            value,
+            json!({"name": "Ann", "nickname": "aka Annie", "age": "30"})
+        );
+
+        let (errors, value) = do_validate(schema, "- Ann\n- 30\n", true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"name": "Ann", "age": "30"}));
+
+        let (errors, _) = do_validate(schema, "- Ann\n- thirty\n", true);
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn test_typed_matchers() {
+        let schema = "Age: `age:int:/\\d+/`\n\nScore: `score:float:/[\\d.]+/`\n\nActive: `active:bool:/true|false/`\n";
+        let input = "Age: 42\n\nScore: 9.5\n\nActive: true\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"age": 42, "score": 9.5, "active": true}));
+    }
+
+    #[test]
+    fn test_typed_repeated_list_matcher() {
+        let schema = "- `nums:int:/\\d+/`{,}\n";
+        let input = "- 1\n- 2\n- 3\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"nums": [1, 2, 3]}));
+    }
+
+    #[test]
+    fn test_typed_matcher_value_that_does_not_parse() {
+        let schema = "Age: `age:int:/[\\d.]+/`\n";
+        let input = "Age: 4.2\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(matches!(
+            errors.as_slice(),
+            [ValidationError::SchemaViolation(
+                SchemaViolationError::CapturedValueTypeMismatch { expected, actual, .. }
+            )] if expected == "int" && actual == "4.2"
+        ));
+        assert_eq!(value, json!({}));
+    }
+
+    #[test]
+    fn test_section_capture() {
+        let schema = "# Title\n\n## Description\n\n`description:section`\n\n## Usage\n\nRun it\n";
+        let input = "# Title\n\n## Description\n\nSome *text*.\n\n### Details\n\nMore\n\n## Usage\n\nRun it\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(
+            value,
+            json!({"description": "Some *text*.\n\n### Details\n\nMore"})
+        );
+
+        // An empty section
+        let input = "# Title\n\n## Description\n\n## Usage\n\nRun it\n";
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"description": ""}));
+    }
+
+    #[test]
+    fn test_section_capture_waits_for_end_of_section() {
+        let schema = "## Description\n\n`description:section`\n";
+        let input = "## Description\n\nSome text\n\nMore text\n";
+
+        let (errors, value) = do_validate(schema, input, false);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({}));
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"description": "Some text\n\nMore text"}));
+    }
+
+    #[test]
+    fn test_matches_keys_are_in_schema_order() {
+        let schema = "# `zebra:/\\w+/`\n\n`apple:/\\w+/`\n\n- `mango:/\\w+/`{1,}\n";
+        let input = "# Z\n\nA\n\n- m1\n- m2\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(
+            value.to_string(),
+            r#"{"zebra":"Z","apple":"A","mango":["m1","m2"]}"#
+        );
+    }
+
+    fn validate_with_structured_matches(schema: &str, input: &str) -> Value {
+        let options = ValidateOptions {
+            structured_matches: true,
+            ..Default::default()
+        };
+        let (errors, value) = do_validate_with_options(schema, input, true, options);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        value
+    }
+
+    #[test]
+    fn test_structured_matches_flat_list() {
+        let schema = "- `item:/\\w+/`{1,}\n";
+        let input = "- one\n- two\n";
+
+        assert_eq!(
+            validate_with_structured_matches(schema, input),
+            json!({
+                "item": [
+                    { "value": "one", "children": {} },
+                    { "value": "two", "children": {} },
+                ]
+            })
+        );
+        // The default shape is unchanged
+        assert_eq!(do_validate(schema, input, true).1, json!({"item": ["one", "two"]}));
+    }
+
+    #[test]
+    fn test_structured_matches_nested_list() {
+        let schema = "- `test:/test\\d/`{1,1}\n    - `deep:/deep\\d/`{1,1}\n";
+        let input = "- test1\n    - deep1\n";
+
+        assert_eq!(
+            validate_with_structured_matches(schema, input),
+            json!({
+                "test": [
+                    { "value": "test1", "children": {
+                        "deep": [{ "value": "deep1", "children": {} }]
+                    } },
+                ]
+            })
+        );
+        assert_eq!(
+            do_validate(schema, input, true).1,
+            json!({"test": ["test1", {"deep": ["deep1"]}]})
+        );
+    }
+
+    #[test]
+    fn test_structured_matches_deep_nesting_and_stacking() {
+        let schema = r#"
+- `test:/test\d/`{2,2}
+- `barbar:/barbar\d/`{2,2}
+    + `deep:/deep\d/`{1,1}
+        - `deeper:/deeper\d/`{2,2}
+        - `deepest:/deepest\d/`{2,}
+"#;
+        let input = r#"
+- test1
+- test2
+- barbar1
+- barbar2
+    + deep1
+        - deeper1
+        - deeper2
+        - deepest1
+        - deepest2
+        - deepest3
+"#;
+
+        assert_eq!(
+            validate_with_structured_matches(schema, input),
+            json!({
+                "test": [
+                    { "value": "test1", "children": {} },
+                    { "value": "test2", "children": {} },
+                ],
+                "barbar": [
+                    { "value": "barbar1", "children": {} },
+                    { "value": "barbar2", "children": {
+                        "deep": [
+                            { "value": "deep1", "children": {
+                                "deeper": [
+                                    { "value": "deeper1", "children": {} },
+                                    { "value": "deeper2", "children": {} },
+                                ],
+                                "deepest": [
+                                    { "value": "deepest1", "children": {} },
+                                    { "value": "deepest2", "children": {} },
+                                    { "value": "deepest3", "children": {} },
+                                ],
+                            } },
+                        ]
+                    } },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_fast_fail_stops_visiting_nodes() {
+        use crate::mdschema::validation::walkers::validators::VALIDATOR_CALLS;
+
+        let mut schema = String::new();
+        let mut input = String::new();
+        for i in 0..200 {
+            schema.push_str(&format!("# Section {i}\n\nSome text\n\n- one\n- two\n\n"));
+            input.push_str(&format!("# Section {i}\n\nSome text\n\n- one\n- two\n\n"));
+        }
+        // Break the very first paragraph
+        let input = input.replacen("Some text", "Other text", 1);
+
+        let validate_counting_calls = |fast_fail: bool| {
+            VALIDATOR_CALLS.with(|calls| calls.set(0));
+            let mut validator = Validator::new_complete(&schema, &input)
+                .unwrap()
+                .with_options(ValidateOptions {
+                    fast_fail,
+                    ..Default::default()
+                });
+            validator.validate();
+            let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+            (errors, VALIDATOR_CALLS.with(|calls| calls.get()))
+        };
+
+        let (errors, calls) = validate_counting_calls(true);
+        assert_eq!(errors.len(), 1);
+        // We only got as far as the first section
+        assert!(calls < 20, "Expected to stop early, but ran {} validators", calls);
+
+        let (errors, full_calls) = validate_counting_calls(false);
+        assert_eq!(errors.len(), 1);
+        assert!(full_calls > 200 * 3);
+    }
+
+    const DUPLICATE_IDS_SCHEMA: &str = "# `title:/\\w+/`\n\nBy `author:/\\w+/`\n\n## `title:/\\w+/`\n";
+    const DUPLICATE_IDS_INPUT: &str = "# Hello\n\nBy Wolf\n\n## World\n";
+
+    fn validate_with_duplicate_id_policy(
+        on_duplicate_id: DuplicateIdPolicy,
+    ) -> (Vec<ValidationError>, Value) {
+        do_validate_with_options(
+            DUPLICATE_IDS_SCHEMA,
+            DUPLICATE_IDS_INPUT,
+            true,
+            ValidateOptions {
+                on_duplicate_id,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_duplicate_ids_in_sibling_blocks_are_collected() {
+        let (errors, value) = validate_with_duplicate_id_policy(DuplicateIdPolicy::Collect);
+
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"title": ["Hello", "World"], "author": "Wolf"}));
+    }
+
+    #[test]
+    fn test_duplicate_ids_in_sibling_lists_are_concatenated() {
+        let schema = "- `item:/\\w+/`{1,}\n\nThen\n\n- `item:/\\w+/`{1,}\n";
+        let input = "- a\n- b\n\nThen\n\n- c\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"item": ["a", "b", "c"]}));
+    }
+
+    #[test]
+    fn test_duplicate_ids_overwrite() {
+        let (errors, value) = validate_with_duplicate_id_policy(DuplicateIdPolicy::Overwrite);
+
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value, json!({"title": "World", "author": "Wolf"}));
+    }
+
+    #[test]
+    fn test_duplicate_ids_error() {
+        let (errors, value) = validate_with_duplicate_id_policy(DuplicateIdPolicy::Error);
+
+        assert_eq!(errors.len(), 1);
+        match &errors[0] {
+            ValidationError::SchemaError(SchemaError::DuplicateMatcherId { schema_index, id }) => {
+                assert_eq!(id, "title");
+                let tree = parse_markdown(DUPLICATE_IDS_SCHEMA).unwrap();
+                let node = find_node_by_index(tree.root_node(), *schema_index);
+                assert_eq!(get_node_text(&node, DUPLICATE_IDS_SCHEMA), "`title:/\\w+/`");
+                assert_eq!(node.start_position().row, 4);
+            }
+            error => panic!("Expected a duplicate matcher id error, got {:?}", error),
+        }
+        assert_eq!(value, json!({"title": "World", "author": "Wolf"}));
+    }
+
+    #[test]
+    fn test_includes_in_schema_strings_are_rejected() {
+        let schema = "# Title\n\n`include:./person.mds`\n";
+        let input = "# Title\n\n`include:./person.mds`\n";
+
+        let (errors, _) = do_validate(schema, input, true);
+        assert_eq!(errors.len(), 1, "Expected one error, got {:?}", errors);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaError(SchemaError::InvalidInclude {
+                error: IncludeError::NoSchemaPath { .. },
+                ..
+            })
+        ));
+
+        // Streaming doesn't report it twice
+        let mut validator = get_validator_for_incremental(schema, "# Title\n", false);
+        validator.validate();
+        validator.read_final_input(input).unwrap();
+        validator.validate();
+        assert_eq!(validator.errors_so_far().count(), 1);
+    }
+
+    const SCOPED_SCHEMA: &str = "# Config `@scope:config`\n\nHost `host:/\\w+/`\n\n\
+                                 Port `port:/\\d+/`\n\n# Other\n\nName `host:/\\w+/`\n";
+    const SCOPED_INPUT: &str = "# Config\n\nHost local\n\nPort 80\n\n# Other\n\nName web\n";
+
+    #[test]
+    fn test_scoped_heading_nests_matches() {
+        let (errors, value) = do_validate(SCOPED_SCHEMA, SCOPED_INPUT, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(
+            value,
+            json!({"config": {"host": "local", "port": "80"}, "host": "web"})
+        );
+
+        let validator = stream_input(SCOPED_SCHEMA, SCOPED_INPUT, 7);
+        assert_eq!(validator.errors_so_far().count(), 0);
+        assert_eq!(validator.matches_so_far(), &value);
+    }
+
+    #[test]
+    fn test_scopes_nest_and_collect_repeated_list_items() {
+        let schema = "# Server `@scope:server`\n\n- `host:/\\w+/`{1,}\n\n\
+                      ## Database `@scope:db`\n\n- `host:/\\w+/`{1,}\n";
+        let input = "# Server\n\n- a\n- b\n\n## Database\n\n- c\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(
+            value,
+            json!({"server": {"host": ["a", "b"], "db": {"host": ["c"]}}})
+        );
+    }
+
+    #[test]
+    fn test_duplicate_scopes_are_an_error() {
+        let schema = "# Server `@scope:config`\n\n`host:/\\w+/`\n\n# Client `@scope:config`\n";
+        let input = "# Server\n\nlocal\n\n# Client\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert_eq!(errors.len(), 1, "Expected one error, got {:?}", errors);
+        match &errors[0] {
+            ValidationError::SchemaError(SchemaError::DuplicateScope { schema_index, path }) => {
+                assert_eq!(path, "config");
+                let validator = Validator::new_complete(schema, input).unwrap();
+                let node = find_node_by_index(validator.schema_tree().root_node(), *schema_index);
+                assert_eq!(node.start_position().row, 4);
+            }
+            error => panic!("Expected a duplicate scope error, got {:?}", error),
+        }
+        assert_eq!(value, json!({"config": {"host": "local"}}));
+    }
+
+    #[test]
+    fn test_frontmatter_is_validated_before_the_document() {
+        let schema = "---\ntitle: `title:/.+/`\n---\n\n# `heading:/\\w+/`\n";
+
+        let input = "---\ntitle: Hello there\n---\n\n# Notes\n";
+        let (errors, matches) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(matches["title"], "Hello there");
+        assert_eq!(matches["heading"], "Notes");
+
+        // One error for the missing front matter rather than one per key
+        let (errors, _) = do_validate(schema, "# Notes\n", true);
+        assert_eq!(errors.len(), 1, "Expected one error, got {:?}", errors);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::MissingFrontmatter { .. })
+        ));
+    }
+
+    fn validate_with_list_numbers(schema: &str, input: &str) -> (Vec<ValidationError>, Value) {
+        let options = ValidateOptions {
+            enforce_ordered_list_numbers: true,
+            ..Default::default()
+        };
+        do_validate_with_options(schema, input, true, options)
+    }
+
+    /// The expected and actual markers of a `ListMarkerMismatch`.
+    fn list_marker_mismatch(error: &ValidationError) -> Option<(&str, &str)> {
+        match error {
+            ValidationError::SchemaViolation(SchemaViolationError::ListMarkerMismatch {
+                expected,
+                actual,
+                ..
+            }) => Some((expected.as_str(), actual.as_str())),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_enforce_ordered_list_numbers() {
+        let schema = "1. foo\n2. bar\n";
+        let input = "1. foo\n5. bar\n";
+
+        let (errors, _) = validate_with_list_numbers(schema, input);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(list_marker_mismatch(&errors[0]), Some(("2.", "5.")));
+
+        // Off by default
+        let (errors, _) = do_validate(schema, input, true);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_enforce_ordered_list_numbers_counts_up_for_repeated_matchers() {
+        let schema = "1. `item:/\\w+/`{1,}\n";
+
+        let (errors, value) = validate_with_list_numbers(schema, "1. a\n2. b\n3. c\n");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({"item": ["a", "b", "c"]}));
+
+        let (errors, _) = validate_with_list_numbers(schema, "1. a\n2. b\n4. c\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(list_marker_mismatch(&errors[0]), Some(("3.", "4.")));
+    }
+
+    #[test]
+    fn test_enforce_bullet_list_markers() {
+        let schema = "- `item:/\\w+/`{1,}\n";
+
+        let (errors, value) = validate_with_list_numbers(schema, "- a\n- b\n");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({"item": ["a", "b"]}));
+
+        let (errors, _) = validate_with_list_numbers(schema, "* a\n* b\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(list_marker_mismatch(&errors[0]), Some(("-", "*")));
+    }
+
+    #[test]
+    fn test_tight_and_loose_lists_match() {
+        let tight = "- `item:/\\w+/`{1,}\n";
+        let loose = "- `first:/\\w+/`\n\n- `second:/\\w+/`\n";
+
+        let (errors, value) = do_validate(tight, "- a\n\n- b\n\n- c\n", true);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({"item": ["a", "b", "c"]}));
+
+        let (errors, value) = do_validate(loose, "- a\n- b\n", true);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({"first": "a", "second": "b"}));
+
+        // Ordered and bullet lists are still told apart
+        let (errors, _) = do_validate(tight, "1. a\n\n2. b\n", true);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_strict_list_tightness() {
+        let schema = "- `item:/\\w+/`{1,}\n";
+        let validate_strictly = |input: &str| {
+            let options = ValidateOptions {
+                strict_list_tightness: true,
+                ..Default::default()
+            };
+            do_validate_with_options(schema, input, true, options).0
+        };
+
+        assert!(validate_strictly("- a\n- b\n").is_empty());
+
+        let errors = validate_strictly("- a\n\n- b\n");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeTypeMismatch {
+                expected,
+                actual,
+                ..
+            }) if expected == "tight_list" && actual == "loose_list"
+        ));
+    }
+
+    #[test]
+    fn test_heading_forms_are_equivalent() {
+        let forms = [
+            "# Overview\n\nText\n",
+            "# Overview #\n\nText\n",
+            "Overview\n===\n\nText\n",
+        ];
+        for schema in forms {
+            for input in forms {
+                let case = format!("{:?} vs {:?}", schema, input);
+                let (errors, _) = do_validate(schema, input, true);
+                assert!(errors.is_empty(), "{}: {:?}", case, errors);
+            }
+        }
+
+        let forms = [
+            "## `section:/.+/`\n",
+            "## `section:/.+/` ##\n",
+            "`section:/.+/`\n---\n",
+        ];
+        let inputs = ["## Usage\n", "## Usage ##\n", "Usage\n---\n"];
+        for schema in forms {
+            for input in inputs {
+                let case = format!("{:?} vs {:?}", schema, input);
+                let (errors, value) = do_validate(schema, input, true);
+                assert!(errors.is_empty(), "{}: {:?}", case, errors);
+                assert_eq!(value, json!({"section": "Usage"}), "{}", case);
+            }
+        }
+
+        // Still only at the same level
+        let (errors, _) = do_validate("# Overview\n", "Overview\n---\n", true);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_strict_heading_form() {
+        let validate_strictly = |schema: &str, input: &str| {
+            let options = ValidateOptions {
+                strict_heading_form: true,
+                ..Default::default()
+            };
+            do_validate_with_options(schema, input, true, options).0
+        };
+
+        assert!(validate_strictly("# Overview\n", "# Overview\n").is_empty());
+        assert!(validate_strictly("# Overview #\n", "# Overview #\n").is_empty());
+
+        let errors = validate_strictly("# Overview\n", "Overview\n===\n");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeTypeMismatch {
+                expected,
+                actual,
+                ..
+            }) if expected == "atx_heading" && actual == "setext_heading"
+        ));
+
+        let errors = validate_strictly("# Overview\n", "# Overview #\n");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_max_errors_per_container() {
+        let schema = "Hello *big* world and **bold** text\n";
+        let input = "Helo *big* wrld and **bold** txt\n";
+        let validate_with_max = |max_errors_per_container: usize| {
+            let options = ValidateOptions {
+                max_errors_per_container,
+                ..Default::default()
+            };
+            let (errors, _) = do_validate_with_options(schema, input, true, options);
+            errors.len()
+        };
+
+        assert_eq!(validate_with_max(DEFAULT_MAX_ERRORS_PER_CONTAINER), 3);
+        assert_eq!(validate_with_max(2), 2);
+        // A mismatch is always reported
+        assert_eq!(validate_with_max(0), 1);
+    }
+
+    #[test]
+    fn test_max_nesting_depth() {
+        // Every `- ` starts a list in the item before it
+        let nested_list = format!("{}item\n", "- ".repeat(10_000));
+        let (errors, _) = do_validate(&nested_list, &nested_list, true);
+        assert!(!errors.is_empty());
+        assert!(
+            errors.iter().all(|error| matches!(
+                error,
+                ValidationError::SchemaViolation(SchemaViolationError::NestingTooDeep {
+                    max_depth: DEFAULT_MAX_NESTING_DEPTH,
+                    ..
+                })
+            )),
+            "{:?}",
+            errors
+        );
+
+        let nested_list = format!("{}item\n", "- ".repeat(5));
+        let validate_with_max = |max_nesting_depth: usize| {
+            let options = ValidateOptions {
+                max_nesting_depth,
+                ..Default::default()
+            };
+            let (errors, _) = do_validate_with_options(&nested_list, &nested_list, true, options);
+            errors.len()
+        };
+        assert_eq!(validate_with_max(DEFAULT_MAX_NESTING_DEPTH), 0);
+        assert!(validate_with_max(3) > 0);
+    }
+
+    #[test]
+    fn test_max_nesting_depth_of_long_flat_optional_list() {
+        // Moving on to the next item doesn't nest, so a flat list can have
+        // more items than the limit
+        let schema: String = (0..40)
+            .map(|i| format!("- `item_{i}:/item{i}/`?\n"))
+            .collect();
+        let input: String = (0..40).step_by(2).map(|i| format!("- item{i}\n")).collect();
+        let options = ValidateOptions {
+            max_nesting_depth: 16,
+            ..Default::default()
+        };
+
+        let (errors, value) = do_validate_with_options(&schema, &input, true, options);
+        assert!(errors.is_empty(), "Expected no errors, got {:?}", errors);
+        assert_eq!(value.as_object().unwrap().len(), 20);
+        assert_eq!(value["item_38"], "item38");
+    }
+
+    #[test]
+    fn test_back_reference_matches_capture() {
+        let schema = "# `title:/.+/`\n\nName: `=title`\n";
+
+        let (errors, value) = do_validate(schema, "# Widgets\n\nName: Widgets\n", true);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({"title": "Widgets"}));
+    }
+
+    #[test]
+    fn test_back_reference_mismatch() {
+        let schema = "# `title:/.+/`\n\nName: `=title`\n";
+        let input = "# Widgets\n\nName: Gadgets\n";
+
+        // Not reported again when the paragraph is validated again
+        let mut validator = get_validator_for_incremental(schema, "# Widgets\n\n", false);
+        validator.validate();
+        validator.append_more_input("Name: Gadgets\n").unwrap();
+        validator.validate();
+        let reported = validator.errors_so_far().count();
+        validator.validate();
+        assert!(reported <= 1);
+        assert_eq!(validator.errors_so_far().count(), reported);
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::BackReferenceMismatch {
+                id,
+                expected,
+                actual,
+                ..
+            }) if id == "title" && expected == "Widgets" && actual == "Gadgets"
+        ));
+        assert_eq!(value, json!({"title": "Widgets"}));
+    }
+
+    #[test]
+    fn test_back_reference_to_unknown_id() {
+        let schema = "# `title:/.+/`\n\nName: `=name`\n";
+
+        let (errors, _) = do_validate(schema, "# A\n\nName: A\n", true);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaError(SchemaError::InvalidBackReference {
+                id,
+                error: BackReferenceError::UnknownId,
+                ..
+            }) if id == "name"
+        ));
+    }
+
+    #[test]
+    fn test_dotted_ids_nest_matches() {
+        let schema = "# `title:/.+/`\n\nBy `meta.author:/\\w+/`\n\n\
+                      Under `meta.license:/[\\w-]+/`\n\n- `deps.name:/\\w+/`{1,}\n";
+        let input = "# Widgets\n\nBy Ada\n\nUnder MIT-0\n\n- serde\n- regex\n";
+
+        let (errors, value) = do_validate(schema, input, true);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(
+            value,
+            json!({
+                "title": "Widgets",
+                "meta": {"author": "Ada", "license": "MIT-0"},
+                "deps": {"name": ["serde", "regex"]},
+            })
+        );
+    }
+
+    #[test]
+    fn test_dotted_id_back_reference_and_escape() {
+        let schema = "# `meta.name:/\\w+/` `v1\\.2:/\\d+/`\n\nName: `=meta.name`\n";
+
+        let (errors, value) = do_validate(schema, "# Widgets 7\n\nName: Widgets\n", true);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({"meta": {"name": "Widgets"}, "v1.2": "7"}));
+    }
+
+    #[test]
+    fn test_dotted_id_conflict() {
+        let schema = "# `meta:/\\w+/`\n\nBy `meta.author:/\\w+/`\n";
+
+        let (errors, _) = do_validate(schema, "# Widgets\n\nBy Ada\n", true);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaError(SchemaError::MatchPathConflict { id, other_id, .. })
+                if id == "meta.author" && other_id == "meta"
+        ));
+    }
+
+    #[test]
+    fn test_conditional_section() {
+        let schema = "# `kind:/\\w+/`\n\n## API `@when:kind=library`\n\nText\n";
+
+        let (errors, value) = do_validate(schema, "# library\n\n## API\n\nText\n", true);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({"kind": "library"}));
+
+        let (errors, _) = do_validate(schema, "# library\n", true);
+        assert!(!errors.is_empty());
+
+        let (errors, value) = do_validate(schema, "# binary\n", true);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({"kind": "binary"}));
+
+        let (errors, _) = do_validate(schema, "# binary\n\n## API\n\nText\n", true);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::ConditionNotMet {
+                id,
+                expected,
+                actual,
+                ..
+            }) if id == "kind" && expected == "library" && actual.as_deref() == Some("binary")
+        ));
+    }
+
+    #[test]
+    fn test_conditional_list_item() {
+        let schema = "# `kind:/\\w+/`\n\n- Intro\n- Docs `@when:kind=library`\n- Outro\n";
+
+        let (errors, _) = do_validate(schema, "# library\n\n- Intro\n- Docs\n- Outro\n", true);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let (errors, _) = do_validate(schema, "# binary\n\n- Intro\n- Outro\n", true);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let (errors, _) = do_validate(schema, "# binary\n\n- Intro\n- Docs\n- Outro\n", true);
+        assert!(errors.iter().any(|error| matches!(
+            error,
+            ValidationError::SchemaViolation(SchemaViolationError::ConditionNotMet { .. })
+        )));
+    }
+
+    #[test]
+    fn test_condition_on_unknown_id() {
+        let schema = "# `kind:/\\w+/`\n\n## API `@when:type=library`\n\nText\n";
+
+        let (errors, _) = do_validate(schema, "# library\n\n## API\n\nText\n", true);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaError(SchemaError::InvalidCondition {
+                id,
+                error: BackReferenceError::UnknownId,
+                ..
+            }) if id == "type"
+        ));
+    }
+
+    #[test]
+    fn test_negated_matcher() {
+        let schema = "Status: `!:/TODO/`\n";
+
+        let (errors, value) = do_validate(schema, "Status: done\n", true);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({}));
+
+        let (errors, _) = do_validate(schema, "Status: TODO later\n", true);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                kind: NodeContentMismatchKind::NegatedMatcher,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_negated_matcher_with_suffix() {
+        let schema = "Status: `!:/TODO/`.\n";
+
+        let (errors, value) = do_validate(schema, "Status: Done.\n", true);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({}));
+
+        let (errors, _) = do_validate(schema, "Status: TODO.\n", true);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                kind: NodeContentMismatchKind::NegatedMatcher,
+                actual,
+                ..
+            }) if actual == "TODO"
+        ));
+
+        let (errors, _) = do_validate(schema, "Status: Done\n", true);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                kind: NodeContentMismatchKind::Suffix,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_forbidden_content() {
+        let schema = "# `title:/.+/`\n\n```mds-forbid\nTODO|FIXME\n```\n";
+
+        let (errors, value) = do_validate(schema, "# Release notes\n", true);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({"title": "Release notes"}));
+
+        let (errors, value) = do_validate(schema, "# TODO and FIXME\n", true);
+        let found: Vec<_> = errors
+            .iter()
+            .map(|error| match error {
+                ValidationError::SchemaViolation(SchemaViolationError::ForbiddenContent {
+                    actual,
+                    ..
+                }) => actual.as_str(),
+                error => panic!("Unexpected error: {:?}", error),
+            })
+            .collect();
+        assert_eq!(found, ["TODO", "FIXME"]);
+        assert_eq!(value, json!({"title": "TODO and FIXME"}));
+
+        // Streamed in, the same content is only reported once
+        let mut validator = Validator::new_incomplete(schema, "# TODO\n\nMo").unwrap();
+        validator.validate();
+        validator.read_final_input("# TODO\n\nMore\n").unwrap();
+        validator.validate();
+        let forbidden = validator
+            .errors_so_far()
+            .filter(|error| error.kind() == "forbidden_content")
+            .count();
+        assert_eq!(forbidden, 1);
+    }
+
+    #[test]
+    fn test_schema_diagnostics() {
+        let schema = "# `title:/.+/`\n\nSome text\n\nBy `author:/[a-/`\n\n\
+                      - `item:/\\w+/`{1,}\n- Last\n";
+
+        // Found before there is any input to reach them
+        let validator = Validator::new_incomplete(schema, "").unwrap();
+        let diagnostics = validator.schema_diagnostics();
+        assert!(matches!(
+            diagnostics.iter().collect::<Vec<_>>().as_slice(),
+            [
+                SchemaError::MatcherError { .. },
+                SchemaError::RepeatingMatcherUnbounded { .. },
+            ]
+        ));
+        assert_eq!(validator.errors_so_far().count(), 0);
+        assert_eq!(validator.require_valid_schema().unwrap_err(), diagnostics);
+
+        let validator = Validator::new_incomplete("# `title:/.+/`\n\nRun `ls`!\n", "").unwrap();
+        assert!(validator.schema_diagnostics().is_empty());
+        assert!(validator.require_valid_schema().is_ok());
+    }
+
+    fn validate_with_trailing_content(
+        trailing_content: TrailingContent,
+        input: &str,
+        got_eof: bool,
+    ) -> (Vec<ValidationError>, Value) {
+        let options = ValidateOptions {
+            trailing_content,
+            ..Default::default()
+        };
+        do_validate_with_options("# Title\n\nHello\n", input, got_eof, options)
+    }
+
+    #[test]
+    fn test_trailing_content_forbidden_by_default() {
+        let input = "# Title\n\nHello\n\nExtra\n\n- item\n";
+
+        let (errors, value) = validate_with_trailing_content(TrailingContent::Forbid, input, true);
+        assert_eq!(
+            errors,
+            vec![ValidationError::SchemaViolation(
+                SchemaViolationError::TrailingContent {
+                    schema_index: 5,
+                    input_index: 7,
+                    extra_blocks: 2,
+                }
+            )]
+        );
+        assert_eq!(value, json!({}));
+        assert_eq!(do_validate("# Title\n\nHello\n", input, true).0, errors);
+
+        // More blocks may still come
+        let (errors, _) = validate_with_trailing_content(TrailingContent::Forbid, input, false);
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_trailing_content_allowed() {
+        let input = "# Title\n\nHello\n\nExtra\n\n- item\n";
+
+        let (errors, value) = validate_with_trailing_content(TrailingContent::Allow, input, true);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({}));
+    }
+
+    #[test]
+    fn test_trailing_content_captured() {
+        let capture = TrailingContent::Capture("rest".to_string());
+        let input = "# Title\n\nHello\n\nExtra *text*\n\n- item\n";
+
+        let (errors, value) = validate_with_trailing_content(capture.clone(), input, true);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({"rest": "Extra *text*\n\n- item"}));
+
+        let (errors, value) = validate_with_trailing_content(capture, input, false);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({}));
+
+        let (errors, value) = validate_with_trailing_content(
+            TrailingContent::Capture("rest".to_string()),
+            "# Title\n\nHello\n",
+            true,
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(value, json!({}));
+    }
+
+    #[test]
+    fn test_parse_trailing_content() {
+        assert_eq!("forbid".parse(), Ok(TrailingContent::Forbid));
+        assert_eq!("allow".parse(), Ok(TrailingContent::Allow));
+        assert_eq!(
+            "capture:rest".parse(),
+            Ok(TrailingContent::Capture("rest".to_string()))
+        );
+        assert!("capture:".parse::<TrailingContent>().is_err());
+        assert!("ignore".parse::<TrailingContent>().is_err());
+    }
+
+    fn validate_with_empty_schema(
+        schema: &str,
+        trailing_content: TrailingContent,
+        input: &str,
+        got_eof: bool,
+    ) -> Vec<ValidationError> {
+        let options = ValidateOptions {
+            trailing_content,
+            ..Default::default()
+        };
+        do_validate_with_options(schema, input, got_eof, options).0
+    }
+
+    #[test]
+    fn test_empty_schema_and_empty_input() {
+        for (schema, input) in [("", ""), (" \n\t\n", "\n\n"), ("", "   \n")] {
+            for got_eof in [true, false] {
+                let (errors, value) = do_validate(schema, input, got_eof);
+                assert_eq!(errors, vec![], "{:?} vs {:?}", schema, input);
+                assert_eq!(value, json!({}));
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_schema_with_content() {
+        let input = "Extra\n\n- item\n";
+        let trailing = vec![ValidationError::SchemaViolation(
+            SchemaViolationError::TrailingContent {
+                schema_index: 0,
+                input_index: 1,
+                extra_blocks: 2,
+            },
+        )];
+
+        for schema in ["", "\n  \n"] {
+            assert_eq!(
+                validate_with_empty_schema(schema, TrailingContent::Forbid, input, true),
+                trailing
+            );
+            assert_eq!(
+                validate_with_empty_schema(schema, TrailingContent::Allow, input, true),
+                vec![]
+            );
+            // More blocks may still come
+            assert_eq!(
+                validate_with_empty_schema(schema, TrailingContent::Forbid, input, false),
+                vec![]
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_input_with_schema() {
+        let missing_content = vec![ValidationError::SchemaViolation(
+            SchemaViolationError::MissingContent {
+                schema_index: 1,
+                input_index: 0,
+                expected: "# Title".to_string(),
+            },
+        )];
+
+        for input in ["", "\n \n"] {
+            assert_eq!(do_validate("# Title\n\nHello\n", input, true).0, missing_content);
+            // The input may still come
+            assert_eq!(do_validate("# Title\n\nHello\n", input, false).0, vec![]);
+        }
+
+        let mut validator = Validator::new_incomplete("# Title\n\nHello\n", "").unwrap();
+        validator.validate();
+        assert_eq!(validator.errors_so_far().count(), 0);
+        validator.append_final_input("").unwrap();
+        validator.validate();
+        assert_eq!(
+            validator.errors_so_far().cloned().collect::<Vec<_>>(),
+            missing_content
+        );
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let schema = "# `title:/\\w+/`\n\nSome text\n\nMore text\n";
+        let mut validator =
+            Validator::new_incomplete(schema, "# Hello\n\nSome text\n\nMo").unwrap();
+        validator.validate();
+        let checkpoint = validator.checkpoint();
+        assert_eq!(checkpoint.input, "# Hello\n\nSome text\n\nMo");
+
+        let mut resumed = Validator::resume(schema, &checkpoint).unwrap();
+        assert_eq!(
+            resumed.farthest_reached_pos(),
+            validator.farthest_reached_pos()
+        );
+        assert_eq!(resumed.errors_so_far().count(), 0);
+        assert_eq!(resumed.matches_so_far(), &json!({"title": "Hello"}));
+
+        resumed.append_final_input("re text\n").unwrap();
+        resumed.validate();
+        assert_eq!(resumed.errors_so_far().count(), 0);
+        assert_eq!(resumed.matches_so_far(), &json!({"title": "Hello"}));
+
+        // The match from before the checkpoint is only handed out once
+        let paths: Vec<_> = resumed
+            .drain_new_matches()
+            .map(|new_match| new_match.path.to_string())
+            .collect();
+        assert_eq!(paths, vec!["title"]);
+    }
+
+    /// Stream the input a few bytes at a time, draining the new matches after
+    /// every pass, and return their paths with how much input we had when
+    /// each was found.
+    fn drain_matches_in_chunks(
+        schema: &str,
+        input: &str,
+        options: ValidateOptions,
+    ) -> Vec<(String, usize)> {
+        let mut validator = Validator::new_incomplete(schema, "")
+            .unwrap()
+            .with_options(options);
+        let mut found = Vec::new();
+        let mut end = 0;
+        while end < input.len() {
+            end = (end + 3).min(input.len());
+            if end == input.len() {
+                validator.read_final_input(input).unwrap();
+            } else {
+                validator.read_more_input(&input[..end]).unwrap();
+            }
+            validator.validate();
+            found.extend(
+                validator
+                    .drain_new_matches()
+                    .map(|new_match| (new_match.path.to_string(), end)),
+            );
+        }
+        assert_eq!(validator.errors_so_far().count(), 0);
+        found
+    }
+
+    #[test]
+    fn test_drain_new_matches_while_streaming() {
+        let schema = "# `title:/\\w+/`\n\n- `item:/\\w+/`{1,}\n";
+        let input = "# Todo\n\n- one\n- two\n- three\n";
+
+        let found = drain_matches_in_chunks(schema, input, ValidateOptions::default());
+        let paths: Vec<_> = found.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, vec!["title", "item[0]", "item[1]", "item[2]"]);
+        // The title is found before the rest of the input comes in
+        assert!(found[0].1 < input.len(), "{:?}", found);
+    }
+
+    #[test]
+    fn test_drain_new_matches_of_nested_lists() {
+        let schema = "- `test:/test\\d/`{1,1}\n    - `deep:/deep\\d/`{1,1}\n";
+        let input = "- test1\n    - deep1\n";
+
+        let found = drain_matches_in_chunks(schema, input, ValidateOptions::default());
+        let paths: Vec<_> = found.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, vec!["test[0]", "test[1].deep[0]"]);
+    }
+
+    #[test]
+    fn test_on_match_with_spans() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut validator = Validator::new_incomplete("By `author:/\\w+/`\n", "")
+            .unwrap()
+            .with_options(ValidateOptions {
+                include_spans: true,
+                ..Default::default()
+            });
+        let hook_seen = seen.clone();
+        validator.on_match(Box::new(move |path, value| {
+            hook_seen
+                .lock()
+                .unwrap()
+                .push((path.to_string(), value["value"].clone()));
+        }));
+
+        validator.read_more_input("By Alice").unwrap();
+        validator.validate();
+        validator.read_final_input("By Alice\n").unwrap();
+        validator.validate();
+
+        assert_eq!(validator.errors_so_far().count(), 0);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![("author".to_string(), json!("Alice"))]
+        );
+        let new_matches: Vec<_> = validator.drain_new_matches().collect();
+        assert_eq!(new_matches.len(), 1);
+        assert_eq!(new_matches[0].input_range, Some(3..8));
+    }
+
+    #[test]
+    fn test_error_locations_survive_more_input() {
+        let schema = "# Title\n\nSome text\n\nMore text\n\nLast text\n";
+        let input = "# Title\n\nOther text\n\nMore text\n\nLast text\n";
+        let split = input.find("Last").unwrap() + 2;
+
+        let mut one_shot = Validator::new_complete(schema, input).unwrap();
+        one_shot.validate();
+        let one_shot_errors: Vec<_> = one_shot.errors_so_far().cloned().collect();
+        assert_eq!(one_shot_errors.len(), 1);
+        let expected = one_shot.error_locations(&one_shot_errors[0]);
+        let input_location = expected.input.as_ref().unwrap();
+        assert_eq!(input_location.snippet, "Other text");
+        assert_eq!(&input[input_location.byte_range.clone()], "Other text");
+        assert_eq!(expected.schema.as_ref().unwrap().snippet, "Some text");
+
+        let mut streamed = Validator::new_incomplete(schema, &input[..split]).unwrap();
+        streamed.validate();
+        let errors: Vec<_> = streamed.errors_so_far().cloned().collect();
+        assert_eq!(errors, one_shot_errors);
+
+        // The tree is parsed again with the rest of the input, but the error
+        // still points where it did
+        streamed.read_final_input(input).unwrap();
+        assert_eq!(streamed.error_locations(&errors[0]), expected);
+        streamed.validate();
+        assert_eq!(
+            streamed.errors_so_far().cloned().collect::<Vec<_>>(),
+            errors
+        );
+        assert_eq!(streamed.error_locations(&errors[0]), expected);
+    }
+
+    #[test]
+    fn test_resume_rejects_checkpoint_of_another_schema() {
+        let mut validator = Validator::new_incomplete("# Title\n", "# Title\n").unwrap();
+        validator.validate();
+        let mut checkpoint = validator.checkpoint();
+        assert!(Validator::resume("# Title\n", &checkpoint).is_some());
+
+        // No node of the schema is this far in
+        checkpoint.schema_index = 100;
+        assert!(Validator::resume("# Title\n", &checkpoint).is_none());
+    }
 }
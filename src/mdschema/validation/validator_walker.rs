@@ -1,10 +1,56 @@
-use tree_sitter::TreeCursor;
+use std::cell::Cell;
+use std::ops::Range;
+use std::sync::{Arc, LazyLock, OnceLock};
+
+use serde_json::Value;
+use tree_sitter::{Node, TreeCursor};
+
+use crate::mdschema::validation::{
+    back_references::find_captured, conditions::{Condition, SchemaConditions},
+    errors::ValidationError, link_references::LinkReferences,
+    matchers::{matcher::{Matcher, MatcherError}, matcher_cache::MatcherCache},
+    progress::ProgressReporter,
+    scopes::SchemaScopes, spans::{LineStarts, captured_range_value, captured_value},
+    suppressions, ts_types::*, ts_utils::{is_html_comment, walk_to_root},
+    validator::ValidateOptions, walkers::ValidationResult,
+};
+
+static DEFAULT_VALIDATE_OPTIONS: LazyLock<ValidateOptions> = LazyLock::new(ValidateOptions::default);
 
 pub struct ValidatorWalker<'a> {
     schema_cursor: TreeCursor<'a>,
     schema_str: &'a str,
     input_cursor: TreeCursor<'a>,
     input_str: &'a str,
+    options: &'a ValidateOptions,
+    schema_references: Arc<OnceLock<LinkReferences>>,
+    input_references: Arc<OnceLock<LinkReferences>>,
+    /// Where the lines of the input start, found when first needed to report
+    /// the spans of captured values with.
+    input_line_starts: Arc<OnceLock<LineStarts>>,
+    /// The matchers of the schema built so far (see `matcher_cache`).
+    matcher_cache: Arc<MatcherCache>,
+    progress: Option<&'a ProgressReporter<'a>>,
+    /// Where the input's front matter ends, if it has front matter that the
+    /// schema's front matter was compared against.
+    input_frontmatter_end: Option<usize>,
+    /// The scoped headings of the schema, if it has any (see `scopes`).
+    schema_scopes: Option<&'a SchemaScopes>,
+    /// The conditional headings and list items of the schema, if it has any
+    /// (see `conditions`).
+    schema_conditions: Option<&'a SchemaConditions>,
+    /// What this validation pass has captured before the node we're at, to
+    /// decide conditions with.
+    matches: Option<&'a Value>,
+    /// What earlier validation passes over the same input captured.
+    earlier_matches: Option<&'a Value>,
+    /// The schema block to compare the first input block against, if the
+    /// input blocks before it were dropped (see `bounded_memory`).
+    schema_start: Option<usize>,
+    /// How many validators are running on this walker and the walkers it was
+    /// made from, nested in each other (see
+    /// `ValidateOptions::max_nesting_depth`).
+    depth: Cell<usize>,
 }
 
 impl<'a> ValidatorWalker<'a> {
@@ -19,6 +65,19 @@ impl<'a> ValidatorWalker<'a> {
             schema_str,
             input_cursor,
             input_str,
+            options: &DEFAULT_VALIDATE_OPTIONS,
+            schema_references: Arc::default(),
+            input_references: Arc::default(),
+            input_line_starts: Arc::default(),
+            matcher_cache: Arc::default(),
+            progress: None,
+            input_frontmatter_end: None,
+            schema_scopes: None,
+            schema_conditions: None,
+            matches: None,
+            earlier_matches: None,
+            schema_start: None,
+            depth: Cell::new(0),
         }
     }
 
@@ -41,12 +100,160 @@ impl<'a> ValidatorWalker<'a> {
         schema_cursor: &TreeCursor<'a>,
         input_cursor: &TreeCursor<'a>,
     ) -> Self {
-        Self::new(
-            schema_cursor.clone(),
-            self.schema_str,
-            input_cursor.clone(),
-            self.input_str,
-        )
+        // The documents are the same, so we can share their link definitions
+        Self {
+            schema_cursor: schema_cursor.clone(),
+            schema_str: self.schema_str,
+            input_cursor: input_cursor.clone(),
+            input_str: self.input_str,
+            options: self.options,
+            schema_references: Arc::clone(&self.schema_references),
+            input_references: Arc::clone(&self.input_references),
+            input_line_starts: Arc::clone(&self.input_line_starts),
+            matcher_cache: Arc::clone(&self.matcher_cache),
+            progress: self.progress,
+            input_frontmatter_end: self.input_frontmatter_end,
+            schema_scopes: self.schema_scopes,
+            schema_conditions: self.schema_conditions,
+            matches: self.matches,
+            earlier_matches: self.earlier_matches,
+            schema_start: self.schema_start,
+            depth: Cell::new(self.depth.get()),
+        }
+    }
+
+    /// Like `with_cursors`, but for moving on to siblings of the nodes the
+    /// current validator is at. The validator of the siblings takes the place
+    /// of the current one rather than being nested in it, so it starts at the
+    /// depth the current one started at.
+    pub(crate) fn with_sibling_cursors(
+        &self,
+        schema_cursor: &TreeCursor<'a>,
+        input_cursor: &TreeCursor<'a>,
+    ) -> Self {
+        let walker = self.with_cursors(schema_cursor, input_cursor);
+        walker.set_depth(self.depth().saturating_sub(1));
+        walker
+    }
+
+    /// A walker at the same nodes that knows what this validation pass has
+    /// captured so far, for the conditions of the nodes after them (see
+    /// `conditions`).
+    pub(crate) fn with_matches<'b>(&'b self, matches: &'b Value) -> ValidatorWalker<'b> {
+        ValidatorWalker {
+            schema_cursor: self.schema_cursor.clone(),
+            schema_str: self.schema_str,
+            input_cursor: self.input_cursor.clone(),
+            input_str: self.input_str,
+            options: self.options,
+            schema_references: Arc::clone(&self.schema_references),
+            input_references: Arc::clone(&self.input_references),
+            input_line_starts: Arc::clone(&self.input_line_starts),
+            matcher_cache: Arc::clone(&self.matcher_cache),
+            progress: self.progress,
+            input_frontmatter_end: self.input_frontmatter_end,
+            schema_scopes: self.schema_scopes,
+            schema_conditions: self.schema_conditions,
+            matches: Some(matches),
+            earlier_matches: self.earlier_matches,
+            schema_start: self.schema_start,
+            depth: Cell::new(self.depth.get()),
+        }
+    }
+
+    /// Use the given options instead of the defaults.
+    pub fn with_options(mut self, options: &'a ValidateOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Share link reference definitions with other walkers over the same
+    /// documents. They are collected the first time a walker needs them, so
+    /// walkers created for every chunk of streamed input don't each scan the
+    /// whole document.
+    pub fn with_link_references(
+        mut self,
+        schema_references: &Arc<OnceLock<LinkReferences>>,
+        input_references: &Arc<OnceLock<LinkReferences>>,
+    ) -> Self {
+        self.schema_references = Arc::clone(schema_references);
+        self.input_references = Arc::clone(input_references);
+        self
+    }
+
+    /// Share where the lines of the input start with other walkers over the
+    /// same input, so they are only found once.
+    pub fn with_input_line_starts(mut self, input_line_starts: &Arc<OnceLock<LineStarts>>) -> Self {
+        self.input_line_starts = Arc::clone(input_line_starts);
+        self
+    }
+
+    /// Share the matchers built from the schema with other walkers over the
+    /// same schema, so each one is only built once.
+    pub fn with_matcher_cache(mut self, matcher_cache: &Arc<MatcherCache>) -> Self {
+        self.matcher_cache = Arc::clone(matcher_cache);
+        self
+    }
+
+    /// Skip the input's front matter, which ends at `end`, since it has been
+    /// compared separately (see `frontmatter`).
+    pub(crate) fn with_input_frontmatter(mut self, end: Option<usize>) -> Self {
+        self.input_frontmatter_end = end;
+        self
+    }
+
+    /// Nest what gets captured under scoped headings (see `scopes`).
+    pub(crate) fn with_schema_scopes(mut self, schema_scopes: &'a SchemaScopes) -> Self {
+        self.schema_scopes = Some(schema_scopes);
+        self
+    }
+
+    /// Skip or require blocks depending on what was captured before them (see
+    /// `conditions`).
+    pub(crate) fn with_schema_conditions(
+        mut self,
+        schema_conditions: &'a SchemaConditions,
+    ) -> Self {
+        self.schema_conditions = Some(schema_conditions);
+        self
+    }
+
+    /// Decide conditions with what earlier validation passes captured too,
+    /// since a pass may start after the blocks that captured it.
+    pub(crate) fn with_earlier_matches(mut self, earlier_matches: &'a Value) -> Self {
+        self.earlier_matches = Some(earlier_matches);
+        self
+    }
+
+    /// Compare the first block of the input against the schema block at
+    /// `schema_start` instead of the first one, since the input blocks before
+    /// it were dropped (see `bounded_memory`).
+    pub(crate) fn with_schema_start(mut self, schema_start: Option<usize>) -> Self {
+        self.schema_start = schema_start;
+        self
+    }
+
+    /// Report progress as top-level nodes get validated.
+    pub(crate) fn with_progress(mut self, progress: Option<&'a ProgressReporter<'a>>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Report progress, if anyone is listening, now that validation has
+    /// reached the end of an input node.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_cursor`: The cursor pointing at the input node.
+    /// * `result`: What validation has found so far.
+    pub fn report_progress(&self, input_cursor: &TreeCursor, result: &ValidationResult) {
+        if let Some(progress) = self.progress {
+            progress.report(
+                input_cursor.node().end_byte(),
+                input_cursor.descendant_index(),
+                result,
+            );
+        }
     }
 
     pub fn input_cursor(&self) -> &TreeCursor<'a> {
@@ -65,6 +272,157 @@ impl<'a> ValidatorWalker<'a> {
         self.input_str
     }
 
+    pub fn options(&self) -> &ValidateOptions {
+        self.options
+    }
+
+    /// How many validators are running on this walker, nested in each other.
+    pub(crate) fn depth(&self) -> usize {
+        self.depth.get()
+    }
+
+    /// Count a validator starting (with `depth + 1`) or finishing (with the
+    /// depth it started at) on this walker. Walkers made from it afterwards
+    /// start at the same depth.
+    pub(crate) fn set_depth(&self, depth: usize) {
+        self.depth.set(depth);
+    }
+
+    /// The descendant index of the schema block the first input block is
+    /// compared against, if it isn't the first schema block.
+    pub(crate) fn schema_start(&self) -> Option<usize> {
+        self.schema_start
+    }
+
+    /// The keys that what gets captured from the node at a schema cursor is
+    /// nested under (see `SchemaScopes::scope_path`).
+    pub(crate) fn scope_path(&self, schema_cursor: &TreeCursor) -> Vec<String> {
+        self.schema_scopes
+            .map(|scopes| scopes.scope_path(&schema_cursor.node()))
+            .unwrap_or_default()
+    }
+
+    /// The condition of the heading or list item at a schema cursor, if it has
+    /// one (see `conditions`).
+    pub(crate) fn condition(&self, schema_cursor: &TreeCursor) -> Option<&'a Condition> {
+        self.schema_conditions?.condition(&schema_cursor.node())
+    }
+
+    /// What was captured under the id of the condition of the node at a
+    /// schema cursor, in the scope of the node, by this validation pass or
+    /// an earlier one.
+    pub(crate) fn captured_for(&self, schema_cursor: &TreeCursor, id: &str) -> Option<&'a Value> {
+        let scope = self.scope_path(schema_cursor);
+        [self.matches, self.earlier_matches]
+            .into_iter()
+            .flatten()
+            .find_map(|matches| find_captured(matches, &scope, id))
+    }
+
+    /// Whether the condition of the node at a schema cursor is met, or `None`
+    /// if we need more input to tell (see `Condition::is_met`).
+    ///
+    /// # Arguments
+    ///
+    /// * `schema_cursor`: The cursor at the conditional node.
+    /// * `condition`: Its condition.
+    /// * `got_eof`: Whether we have received the full input document.
+    pub(crate) fn is_condition_met(
+        &self,
+        schema_cursor: &TreeCursor,
+        condition: &Condition,
+        got_eof: bool,
+    ) -> Option<bool> {
+        condition.is_met(self.captured_for(schema_cursor, &condition.id), got_eof)
+    }
+
+    /// The link reference definitions in the schema.
+    pub fn schema_references(&self) -> &LinkReferences {
+        self.schema_references
+            .get_or_init(|| LinkReferences::from_cursor(&self.schema_cursor, self.schema_str))
+    }
+
+    /// The matcher at a schema cursor pointing at a `code_span` node, built
+    /// the first time any walker over the schema needs it.
+    pub fn matcher_at(&self, schema_cursor: &TreeCursor) -> Result<Matcher, MatcherError> {
+        self.matcher_cache
+            .matcher_at(schema_cursor, self.schema_str, &self.options.regex_limits)
+    }
+
+    /// The matchers of the schema built so far.
+    pub fn matcher_cache(&self) -> &MatcherCache {
+        &self.matcher_cache
+    }
+
+    /// The link reference definitions in the input (so far).
+    pub fn input_references(&self) -> &LinkReferences {
+        self.input_references
+            .get_or_init(|| LinkReferences::from_cursor(&self.input_cursor, self.input_str))
+    }
+
+    /// Whether we should stop visiting nodes, because we're failing fast and
+    /// the result already has an error that isn't a warning.
+    pub fn should_stop(&self, result: &ValidationResult) -> bool {
+        self.options.fast_fail && result.errors().iter().any(|error| !self.is_warning(error))
+    }
+
+    /// Whether an error is reported as a warning, because a suppression
+    /// comment disables it or the severity overrides downgrade it.
+    pub fn is_warning(&self, error: &ValidationError) -> bool {
+        self.options.severity_overrides.is_warning(error) || self.is_suppressed(error)
+    }
+
+    /// Whether an error is disabled by a suppression comment in the input
+    /// (see `suppressions`).
+    pub fn is_suppressed(&self, error: &ValidationError) -> bool {
+        if self.options.ignore_suppressions {
+            return false;
+        }
+        let mut cursor = self.input_cursor.clone();
+        walk_to_root(&mut cursor);
+        suppressions::is_suppressed(error, cursor.node(), self.input_str)
+    }
+
+    /// Where the lines of the input start, if we're including the spans of
+    /// captured values.
+    pub fn input_line_starts(&self) -> Option<&LineStarts> {
+        self.options.include_spans.then(|| {
+            self.input_line_starts
+                .get_or_init(|| LineStarts::new(self.input_str))
+        })
+    }
+
+    /// The JSON value to store for text captured from the input at
+    /// `start_byte`, with its source location if we're including spans.
+    pub fn captured_value(&self, captured: &str, start_byte: usize) -> Value {
+        captured_value(
+            self.input_str,
+            captured,
+            start_byte,
+            self.input_line_starts(),
+        )
+    }
+
+    /// Like `captured_value`, for a capture that came from `range` of the
+    /// input but isn't exactly the text there (see `captured_range_value`).
+    pub fn captured_range_value(&self, captured: &str, range: Range<usize>) -> Value {
+        captured_range_value(self.input_str, captured, range, self.input_line_starts())
+    }
+
+    /// Whether an input node should be skipped over instead of being matched
+    /// against the schema. That's any link reference definition, since links
+    /// are compared by what they resolve to, front matter that was compared
+    /// separately, unless we're in strict comments mode any HTML comment, and
+    /// HTML blocks if we're ignoring them.
+    pub fn skips_input_node(&self, node: &Node) -> bool {
+        is_link_reference_definition_node(node)
+            || (!self.options.strict_comments && is_html_comment(node, self.input_str))
+            || (self.options.ignore_html_blocks && is_html_block_node(node))
+            || self
+                .input_frontmatter_end
+                .is_some_and(|end| node.start_byte() < end && node.end_byte() <= end)
+    }
+
     pub fn cursors_mut(&mut self) -> (&mut TreeCursor<'a>, &mut TreeCursor<'a>) {
         (&mut self.schema_cursor, &mut self.input_cursor)
     }
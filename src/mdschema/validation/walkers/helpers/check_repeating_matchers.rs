@@ -6,6 +6,16 @@ use crate::mdschema::validation::{
 };
 
 pub fn check_repeating_matchers(schema_cursor: &TreeCursor, schema_str: &str) -> Option<usize> {
+    find_matcher(schema_cursor, schema_str, Matcher::is_repeated)
+}
+
+/// Find the first matcher among the children of the node the cursor is at for
+/// which `predicate` holds, and return its descendant index.
+pub fn find_matcher(
+    schema_cursor: &TreeCursor,
+    schema_str: &str,
+    predicate: impl Fn(&Matcher) -> bool,
+) -> Option<usize> {
     let mut schema_cursor = schema_cursor.clone();
 
     schema_cursor.goto_first_child();
@@ -20,7 +30,7 @@ pub fn check_repeating_matchers(schema_cursor: &TreeCursor, schema_str: &str) ->
         }
 
         match Matcher::try_from_schema_cursor(&schema_cursor, schema_str) {
-            Ok(matcher) if matcher.is_repeated() => {
+            Ok(matcher) if predicate(&matcher) => {
                 return Some(schema_cursor.descendant_index());
             }
             Ok(_) => {}
@@ -1,15 +1,20 @@
 use tree_sitter::TreeCursor;
 
 use crate::mdschema::validation::errors::{SchemaViolationError, ValidationError};
+use crate::mdschema::validation::ts_types::*;
 use crate::mdschema::validation::ts_utils::{
-    extract_list_marker, get_heading_kind, is_ordered_list_marker, is_unordered_list_marker,
+    extract_list_marker, get_heading_kind, heading_kind_level, is_ordered_list_marker,
+    is_unordered_list_marker,
 };
 
 /// Compare the kinds (types) of two nodes and return an error if they don't match.
 ///
 /// Special handling for:
-/// - Tight lists: checks list marker type (ordered vs unordered)
-/// - Headings: checks heading level
+/// - Lists: checks list marker type (ordered vs unordered). Tight and loose
+///   lists are the same kind of list (see `ValidateOptions::strict_list_tightness`)
+/// - Headings: checks heading level, of ATX and setext headings alike, so
+///   that `# Title` and `Title` underlined with `===` are the same kind of
+///   heading (see `ValidateOptions::strict_heading_form`)
 /// - Other nodes: checks exact kind match
 ///
 /// # Arguments
@@ -29,10 +34,10 @@ pub fn compare_node_kinds(
     let schema_kind = schema_node.kind();
     let input_kind = input_node.kind();
 
-    // If they are both tight lists, check the first children of each of them,
-    // which are list markers. This will indicate whether they are the same type
-    // of list.
-    if schema_cursor.node().kind() == "tight_list" && input_cursor.node().kind() == "tight_list" {
+    // If they are both lists, check the first children of each of them, which
+    // are list markers. This will indicate whether they are the same type of
+    // list.
+    if both_are_list_nodes(&schema_node, &input_node) {
         let schema_list_marker = extract_list_marker(schema_cursor, schema_str);
         let input_list_marker = extract_list_marker(input_cursor, input_str);
 
@@ -62,7 +67,7 @@ pub fn compare_node_kinds(
         }
     }
 
-    if schema_cursor.node().kind() == "atx_heading" && input_cursor.node().kind() == "atx_heading" {
+    if both_are_any_headings(&schema_node, &input_node) {
         let schema_heading_kind = match get_heading_kind(schema_cursor) {
             Ok(kind) => kind,
             Err(error) => return Some(error),
@@ -72,7 +77,7 @@ pub fn compare_node_kinds(
             Err(error) => return Some(error),
         };
 
-        if schema_heading_kind != input_heading_kind {
+        if heading_kind_level(schema_heading_kind) != heading_kind_level(input_heading_kind) {
             return Some(ValidationError::SchemaViolation(
                 SchemaViolationError::NodeTypeMismatch {
                     schema_index: schema_cursor.descendant_index(),
@@ -84,7 +89,10 @@ pub fn compare_node_kinds(
         }
     }
 
-    if schema_kind != input_kind {
+    if schema_kind != input_kind
+        && !both_are_list_nodes(&schema_node, &input_node)
+        && !both_are_any_headings(&schema_node, &input_node)
+    {
         Some(ValidationError::SchemaViolation(
             SchemaViolationError::NodeTypeMismatch {
                 schema_index: schema_cursor.descendant_index(),
@@ -211,4 +219,45 @@ mod tests {
             "Different heading levels should not match"
         );
     }
+
+    #[test]
+    fn test_compare_node_kinds_atx_and_setext_headings() {
+        assert!(compare_first_blocks("# Title\n", "Title\n===\n").is_none());
+        assert!(compare_first_blocks("Title\n---\n", "## Title\n").is_none());
+        assert!(compare_first_blocks("# Title\n", "Title\n---\n").is_some());
+    }
+
+    /// Parse two documents and compare the kinds of their first blocks, like
+    /// lists.
+    fn compare_first_blocks(schema: &str, input: &str) -> Option<ValidationError> {
+        let schema_tree = parse_markdown(schema).unwrap();
+        let mut schema_cursor = schema_tree.walk();
+        let input_tree = parse_markdown(input).unwrap();
+        let mut input_cursor = input_tree.walk();
+
+        schema_cursor.goto_first_child();
+        input_cursor.goto_first_child();
+
+        compare_node_kinds(&schema_cursor, &input_cursor, schema, input)
+    }
+
+    #[test]
+    fn test_compare_node_kinds_tight_and_loose_lists() {
+        let tight = "- test1\n- test2\n";
+        let loose = "- test1\n\n- test2\n";
+
+        assert!(compare_first_blocks(tight, loose).is_none());
+        assert!(compare_first_blocks(loose, tight).is_none());
+        assert!(compare_first_blocks("1. test1\n\n2. test2\n", "1. test1\n2. test2\n").is_none());
+    }
+
+    #[test]
+    fn test_compare_node_kinds_ordered_and_unordered_lists() {
+        let result = compare_first_blocks("1. test1\n\n2. test2\n", "- test1\n- test2\n");
+        assert!(
+            result.is_some(),
+            "Ordered and unordered lists should not match"
+        );
+        assert!(compare_first_blocks("- test1\n- test2\n", "1. test1\n\n2. test2\n").is_some());
+    }
 }
@@ -1,13 +1,21 @@
-use serde_json::json;
 use tree_sitter::TreeCursor;
 
+use crate::invariant_violation;
 use crate::mdschema::validation::errors::{
     NodeContentMismatchKind, SchemaError, SchemaViolationError, ValidationError,
 };
 use crate::mdschema::validation::matchers::matcher::MatcherError;
+use crate::mdschema::validation::spans::{LineStarts, captured_range_value, subslice_offset};
 use crate::mdschema::validation::walkers::ValidationResult;
 use crate::mdschema::validation::walkers::helpers::curly_matchers::extract_matcher_from_curly_delineated_text;
-use crate::mdschema::validation::ts_utils::get_node_text;
+use crate::mdschema::validation::text_normalization::{
+    NormalizedLines, TextNormalizations, split_similarity_modifier,
+};
+use crate::mdschema::validation::ts_utils::{
+    get_node_text, get_node_text_without_closing_hashes, get_str_at_char_boundaries,
+};
+use crate::mdschema::validation::utils::{could_become_similar, similarity};
+use crate::mdschema::validation::validator::ValidateOptions;
 
 /// Compare text contents between schema and input nodes.
 /// Handles both literal text and curly-delimited matchers.
@@ -19,6 +27,8 @@ use crate::mdschema::validation::ts_utils::get_node_text;
 /// * `input_cursor`: Cursor at input text node
 /// * `is_partial_match`: Whether we're doing a partial match (not at EOF)
 /// * `strip_extras`: Whether to strip extras (like `!`) from schema text
+/// * `options`: The options we are validating with
+/// * `input_line_starts`: Where the input lines start, if we report the spans of captures
 pub fn compare_text_contents(
     schema_str: &str,
     input_str: &str,
@@ -26,11 +36,22 @@ pub fn compare_text_contents(
     input_cursor: &TreeCursor,
     is_partial_match: bool,
     strip_extras: bool,
+    options: &ValidateOptions,
+    input_line_starts: Option<&LineStarts>,
 ) -> ValidationResult {
     let mut result = ValidationResult::from_cursors(schema_cursor, input_cursor);
 
-    let schema_text = get_node_text(&schema_cursor.node(), schema_str);
-    let input_text = get_node_text(&input_cursor.node(), input_str);
+    // Unless we compare headings exactly, `## Section ##` is `## Section`
+    let (schema_text, input_text) = match options.strict_heading_form {
+        true => (
+            get_node_text(&schema_cursor.node(), schema_str),
+            get_node_text(&input_cursor.node(), input_str),
+        ),
+        false => (
+            get_node_text_without_closing_hashes(&schema_cursor.node(), schema_str),
+            get_node_text_without_closing_hashes(&input_cursor.node(), input_str),
+        ),
+    };
 
     let schema_text = if strip_extras {
         // TODO: this assumes that ! is the only extra when it is an extra
@@ -48,28 +69,82 @@ pub fn compare_text_contents(
         schema_text.to_string()
     };
 
+    // A literal node may ask to only be similar enough with a trailing `{similar: ...}`,
+    // and for extra normalizations with a trailing `{compare: ...}`
+    let (schema_text, similarity_threshold) = split_similarity_modifier(&schema_text);
+    let (schema_text, node_normalizations) = match TextNormalizations::split_node_modifier(
+        schema_text,
+        schema_cursor.descendant_index(),
+    ) {
+        Ok(split) => split,
+        Err(error) => {
+            result.add_error(ValidationError::SchemaError(error));
+            return result;
+        }
+    };
+    let normalizations = options.text_normalizations.union(node_normalizations);
+    let schema_text = match options.strict_line_endings {
+        true => schema_text.to_string(),
+        false => NormalizedLines::new(schema_text).as_str().to_string(),
+    };
+
+    // Windows line endings and trailing whitespace are invisible, so unless
+    // asked not to we compare and match without them
+    let input_start = input_cursor.node().start_byte();
+    let input_lines = match options.strict_line_endings {
+        true => NormalizedLines::exact(input_text),
+        false => NormalizedLines::new(input_text),
+    };
+    let input_text = input_lines.as_str();
+
     // Try to match schema matcher against input text
-    if let Some(matcher_result) = extract_matcher_from_curly_delineated_text(&schema_text) {
+    if let Some(matcher_result) =
+        extract_matcher_from_curly_delineated_text(&schema_text, &options.regex_limits)
+    {
         match matcher_result {
-            Ok(matcher) => {
-                if let Some(matched_str) = matcher.match_str(input_text) {
-                    if let Some(id) = matcher.id() {
-                        result.set_match(id, json!(matched_str));
-                    }
-                } else if !is_partial_match {
-                    result.add_error(ValidationError::SchemaViolation(
-                        SchemaViolationError::NodeContentMismatch {
-                            schema_index: schema_cursor.descendant_index(),
-                            input_index: input_cursor.descendant_index(),
-                            expected: matcher.pattern().to_string(),
-                            actual: input_text.into(),
-                            kind: NodeContentMismatchKind::Matcher,
-                        },
-                    ));
+            Ok(matcher) => match matcher.try_match_str(input_text, options.max_matcher_input_len) {
+                Ok(Some(matched_str)) => {
+                    let Some(match_start) = subslice_offset(input_text, matched_str) else {
+                        invariant_violation!(
+                            result,
+                            schema_cursor,
+                            input_cursor,
+                            "a match should be a slice of the text it is in"
+                        );
+                    };
+                    let match_end = match_start + matched_str.len();
+                    let match_range = input_start + input_lines.original_offset(match_start)
+                        ..input_start + input_lines.original_offset(match_end);
+                    result.set_matcher_match(
+                        &matcher,
+                        captured_range_value(
+                            input_str,
+                            matched_str,
+                            match_range,
+                            input_line_starts,
+                        ),
+                        schema_cursor.descendant_index(),
+                        input_cursor.descendant_index(),
+                    );
+                    return result;
                 }
-
-                return result;
-            }
+                Ok(None) if !is_partial_match => {
+                    result.add_error(ValidationError::SchemaViolation(matcher.mismatch_error(
+                        schema_cursor.descendant_index(),
+                        input_cursor.descendant_index(),
+                        input_text.into(),
+                    )));
+                    return result;
+                }
+                Ok(None) => return result,
+                Err(error) => {
+                    result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                        error,
+                        schema_index: schema_cursor.descendant_index(),
+                    }));
+                    return result;
+                }
+            },
             Err(MatcherError::WasLiteralCode) => {}
             Err(error) => {
                 result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
@@ -81,6 +156,55 @@ pub fn compare_text_contents(
         }
     }
 
+    if let Some(threshold) = similarity_threshold {
+        let schema_normalized = normalizations.normalize(&schema_text);
+        let input_normalized = normalizations.normalize(input_text);
+        let similar = if is_partial_match {
+            could_become_similar(&schema_normalized, &input_normalized, threshold)
+        } else {
+            similarity(&schema_normalized, &input_normalized) >= threshold
+        };
+
+        if !similar {
+            result.add_error(ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch {
+                    schema_index: schema_cursor.descendant_index(),
+                    input_index: input_cursor.descendant_index(),
+                    kind: NodeContentMismatchKind::SimilarLiteral {
+                        threshold,
+                        similarity: similarity(&schema_normalized, &input_normalized),
+                    },
+                    expected: schema_text,
+                    actual: input_text.into(),
+                },
+            ));
+        }
+
+        return result;
+    }
+
+    if !normalizations.is_exact() {
+        let matches = if is_partial_match {
+            normalizations.texts_prefix(&schema_text, input_text)
+        } else {
+            normalizations.texts_equal(&schema_text, input_text)
+        };
+
+        if !matches {
+            result.add_error(ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch {
+                    schema_index: schema_cursor.descendant_index(),
+                    input_index: input_cursor.descendant_index(),
+                    expected: schema_text,
+                    actual: input_text.into(),
+                    kind: NodeContentMismatchKind::NormalizedLiteral(normalizations),
+                },
+            ));
+        }
+
+        return result;
+    }
+
     // Fall back to literal text comparison
     let mut schema_text = schema_text.as_str();
 
@@ -100,7 +224,7 @@ pub fn compare_text_contents(
             return result;
         } else {
             // The schema might be longer than the input, so crop the schema to the input we've got
-            schema_text = &schema_text[..input_text.len()];
+            schema_text = get_str_at_char_boundaries(schema_text, 0, input_text.len());
         }
     }
 
@@ -134,6 +258,8 @@ pub fn compare_text_contents(
 ///     input_cursor,
 ///     is_partial_match,
 ///     strip_extras,
+///     options,
+///     input_line_starts,
 ///     result
 /// );
 /// ```
@@ -146,6 +272,8 @@ macro_rules! compare_text_contents_check {
         $input_cursor:expr,
         $is_partial_match:expr,
         $strip_extras:expr,
+        $options:expr,
+        $input_line_starts:expr,
         $result:expr
     ) => {
         {
@@ -156,6 +284,8 @@ macro_rules! compare_text_contents_check {
                 &$input_cursor,
                 $is_partial_match,
                 $strip_extras,
+                $options,
+                $input_line_starts,
             );
             $result.join_other_result(&text_result);
             if text_result.has_errors() {
@@ -170,6 +300,7 @@ mod tests {
     use crate::mdschema::validation::ts_utils::new_markdown_parser;
 
     use super::*;
+    use serde_json::json;
 
     #[test]
     fn test_compare_text_contents_simple_match() {
@@ -196,6 +327,8 @@ mod tests {
             &input_cursor,
             false,
             false,
+            &ValidateOptions::default(),
+            None,
         );
         // Result depends on whether we found matching nodes, so just verify it doesn't panic
         let _ = result;
@@ -225,6 +358,8 @@ mod tests {
             &input_cursor,
             false,
             true,
+            &ValidateOptions::default(),
+            None,
         );
         // Just verify no panic
         let _ = result;
@@ -254,6 +389,8 @@ mod tests {
             &input_cursor,
             true,
             false,
+            &ValidateOptions::default(),
+            None,
         );
         let _ = result;
     }
@@ -283,6 +420,8 @@ mod tests {
             &input_cursor,
             false,
             false,
+            &ValidateOptions::default(),
+            None,
         );
 
         // Should match and capture
@@ -317,6 +456,8 @@ mod tests {
             &input_cursor,
             false,
             false,
+            &ValidateOptions::default(),
+            None,
         );
 
         // Should have an error
@@ -326,4 +467,161 @@ mod tests {
         );
         assert_eq!(result.errors().len(), 1);
     }
+
+    #[test]
+    fn test_compare_text_contents_with_normalizations() {
+        let schema_str = "Hello  World";
+        let input_str = "hello world";
+
+        let mut parser = new_markdown_parser();
+        let schema_tree = parser.parse(schema_str, None).unwrap();
+        let input_tree = parser.parse(input_str, None).unwrap();
+
+        let mut schema_cursor = schema_tree.walk();
+        let mut input_cursor = input_tree.walk();
+
+        schema_cursor.goto_first_child();
+        input_cursor.goto_first_child();
+
+        // Exact comparison is the default
+        let result = compare_text_contents(
+            schema_str,
+            input_str,
+            &schema_cursor,
+            &input_cursor,
+            false,
+            false,
+            &ValidateOptions::default(),
+            None,
+        );
+        assert_eq!(result.errors().len(), 1);
+
+        let options = ValidateOptions {
+            text_normalizations: TextNormalizations {
+                case_insensitive: true,
+                collapse_whitespace: true,
+                unicode_nfc: false,
+            },
+            ..Default::default()
+        };
+        let result = compare_text_contents(
+            schema_str,
+            input_str,
+            &schema_cursor,
+            &input_cursor,
+            false,
+            false,
+            &options,
+            None,
+        );
+        assert_eq!(result.errors(), &vec![]);
+    }
+
+    #[test]
+    fn test_compare_text_contents_normalized_mismatch_keeps_original_text() {
+        let schema_str = "Hello World {compare: case-insensitive}";
+        let input_str = "Goodbye World";
+
+        let mut parser = new_markdown_parser();
+        let schema_tree = parser.parse(schema_str, None).unwrap();
+        let input_tree = parser.parse(input_str, None).unwrap();
+
+        let mut schema_cursor = schema_tree.walk();
+        let mut input_cursor = input_tree.walk();
+
+        schema_cursor.goto_first_child();
+        input_cursor.goto_first_child();
+
+        let result = compare_text_contents(
+            schema_str,
+            input_str,
+            &schema_cursor,
+            &input_cursor,
+            false,
+            false,
+            &ValidateOptions::default(),
+            None,
+        );
+
+        assert_eq!(
+            result.errors(),
+            &vec![ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch {
+                    schema_index: schema_cursor.descendant_index(),
+                    input_index: input_cursor.descendant_index(),
+                    expected: "Hello World".into(),
+                    actual: "Goodbye World".into(),
+                    kind: NodeContentMismatchKind::NormalizedLiteral(TextNormalizations {
+                        case_insensitive: true,
+                        ..Default::default()
+                    }),
+                },
+            )]
+        );
+    }
+
+    fn similar_literal(
+        schema_str: &str,
+        input_str: &str,
+        is_partial_match: bool,
+    ) -> ValidationResult {
+        let mut parser = new_markdown_parser();
+        let schema_tree = parser.parse(schema_str, None).unwrap();
+        let input_tree = parser.parse(input_str, None).unwrap();
+
+        let mut schema_cursor = schema_tree.walk();
+        let mut input_cursor = input_tree.walk();
+
+        schema_cursor.goto_first_child();
+        input_cursor.goto_first_child();
+
+        compare_text_contents(
+            schema_str,
+            input_str,
+            &schema_cursor,
+            &input_cursor,
+            is_partial_match,
+            false,
+            &ValidateOptions::default(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_compare_text_contents_similar_literal() {
+        let schema_str = "abcdefghij {similar: 0.9}";
+
+        // One of ten characters differs, which is just similar enough
+        let result = similar_literal(schema_str, "abcdefghiX", false);
+        assert_eq!(result.errors(), &vec![]);
+        let result = similar_literal(schema_str, "ABCDEFGHIJ", false);
+        assert_eq!(result.errors(), &vec![]);
+
+        let result = similar_literal(schema_str, "abcdefghXY", false);
+        assert_eq!(result.errors().len(), 1);
+        assert!(matches!(
+            &result.errors()[0],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                expected,
+                kind: NodeContentMismatchKind::SimilarLiteral {
+                    threshold: 900,
+                    similarity: 800,
+                },
+                ..
+            }) if expected == "abcdefghij"
+        ));
+    }
+
+    #[test]
+    fn test_compare_text_contents_similar_literal_partial() {
+        let schema_str = "abcdefghij {similar: 0.9}";
+
+        // A typo so far could still end up similar enough
+        let result = similar_literal(schema_str, "abcX", true);
+        assert_eq!(result.errors(), &vec![]);
+
+        // Two differences can't be made up for anymore
+        let result = similar_literal(schema_str, "abXY", true);
+        assert_eq!(result.errors().len(), 1);
+    }
 }
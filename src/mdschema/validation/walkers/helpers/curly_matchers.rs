@@ -1,24 +1,28 @@
 use regex::Regex;
 use std::sync::LazyLock;
 
-use crate::mdschema::validation::matchers::matcher::{Matcher, MatcherError};
+use crate::mdschema::validation::matchers::matcher::{Matcher, MatcherError, RegexLimits};
 
 static CURLY_MATCHER: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^\{(?P<inner>.+?)\}(?P<suffix>.*)?$").unwrap());
 
 static CURLY_ID: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\{(?P<id>\w+)\}$").unwrap());
 
+/// Extract a matcher from curly braces like `{id:/test/}{1,2}`, with its regex
+/// compiled within `limits`.
 pub fn extract_matcher_from_curly_delineated_text(
     input: &str,
+    limits: &RegexLimits,
 ) -> Option<Result<Matcher, MatcherError>> {
     let caps = CURLY_MATCHER.captures(input)?;
 
     let matcher_str = caps.name("inner").map(|m| m.as_str()).unwrap_or("").trim();
     let suffix = caps.name("suffix").map(|m| m.as_str());
 
-    Some(Matcher::try_from_pattern_and_suffix_str(
+    Some(Matcher::try_from_pattern_and_suffix_str_with_limits(
         &format!("`{}`", matcher_str),
         suffix,
+        limits,
     ))
 }
 
@@ -50,13 +54,13 @@ mod tests {
     #[test]
     fn test_extract_matcher_from_curly_delineated_text() {
         let input = "{id:/test/}{1,2}";
-        let result = extract_matcher_from_curly_delineated_text(input)
+        let result = extract_matcher_from_curly_delineated_text(input, &RegexLimits::default())
             .unwrap()
             .unwrap();
         assert_eq!(result.id(), Some("id"));
 
         // Check that the pattern displays correctly.
-        assert_eq!(format!("{}", result.pattern()), "^test");
+        assert_eq!(format!("{}", result.pattern()), "test");
 
         assert!(result.extras().had_min_max());
         assert_eq!(result.extras().min_items(), Some(1));
@@ -4,17 +4,18 @@ use crate::invariant_violation;
 use crate::mdschema::validation::{
     errors::{SchemaError, ValidationError},
     matchers::{
-        matcher::{Matcher, MatcherError},
+        matcher::{Matcher, MatcherError, is_always_literal_code},
         matcher_extras::{get_after_extras, get_all_extras},
     },
     ts_types::*,
-    ts_utils::get_next_node,
+    ts_utils::{get_next_node, get_str_at_char_boundaries},
 };
 
 /// Get node text without trimming, even for table cells.
 /// This is needed for structural analysis where we need to see the actual content.
 fn get_node_text_raw<'a>(node: &tree_sitter::Node, src: &'a str) -> &'a str {
-    node.utf8_text(src.as_bytes()).unwrap()
+    node.utf8_text(src.as_bytes())
+        .unwrap_or_else(|_| get_str_at_char_boundaries(src, node.start_byte(), node.end_byte()))
 }
 
 /// Determine the number of nodes we expect in some corresponding input string.
@@ -215,12 +216,15 @@ fn extras_after_matcher<'a>(
 ///
 /// - `Some(false)` if we are at a matcher that is not coalescing.
 /// - `Some(true)` if we are at a matcher that is coalescing.
-/// - `None` if we are not at a matcher.
+/// - `None` if we are not at a matcher. Code in double backticks isn't one,
+///   since no extras come after it.
 fn at_coalescing_matcher(
     schema_cursor: &TreeCursor,
     schema_str: &str,
 ) -> Result<Option<bool>, ValidationError> {
-    if !is_inline_code_node(&schema_cursor.node()) {
+    if !is_inline_code_node(&schema_cursor.node())
+        || is_always_literal_code(get_node_text_raw(&schema_cursor.node(), schema_str))
+    {
         return Ok(None);
     }
 
@@ -433,6 +437,13 @@ mod tests {
         assert_eq!(get_expected_input_nodes(schema_str), 2);
     }
 
+    #[test]
+    fn test_expected_input_nodes_double_backtick_code() {
+        assert_eq!(get_expected_input_nodes("``foo:/bar/``"), 1);
+        assert_eq!(get_expected_input_nodes("``foo``{1,3}"), 2);
+        assert_eq!(get_expected_input_nodes("`foo:/bar/` and ``baz``"), 2);
+    }
+
     #[test]
     fn test_expected_input_nodes_no_matcher() {
         let schema_str = "test *test*";
@@ -487,6 +498,13 @@ mod tests {
         assert_eq!(get_expected_input_nodes(schema_str), 2);
     }
 
+    #[test]
+    fn test_expected_input_nodes_matcher_next_to_emphasis() {
+        assert_eq!(get_expected_input_nodes("`foo:/bar/` *test*"), 2);
+        assert_eq!(get_expected_input_nodes("*test* `foo:/bar/`"), 2);
+        assert_eq!(get_expected_input_nodes("`test`!*test*"), 2);
+    }
+
     #[test]
     fn test_expected_input_nodes_repeated_matcher() {
         let schema_str = r"`test2:/\w+/`{1,1}";
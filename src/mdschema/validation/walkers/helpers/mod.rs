@@ -5,3 +5,4 @@ pub(crate) mod count_non_literal_matchers_in_children;
 pub(crate) mod curly_matchers;
 pub(crate) mod expected_input_nodes;
 pub(crate) mod node_children_lengths;
+pub(crate) mod resync;
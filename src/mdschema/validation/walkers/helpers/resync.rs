@@ -0,0 +1,258 @@
+//! Getting the top-level walk back in step after a block doesn't match.
+//!
+//! When a top-level block of the input isn't even the same kind of block as
+//! the schema block it's paired with, the usual cause is an extra or a missing
+//! block, and every pair after it is off by one too. Rather than reporting
+//! all of those, we look a few blocks ahead on both sides for the pair of
+//! blocks that "anchor" best, report the blocks before them with a single
+//! `SchemaViolationError::UnalignedBlocks`, and resume validating from there.
+//!
+//! Blocks anchor when they have the same kind, counting tight and loose lists
+//! as one kind, and headings also need the same level and, if the schema
+//! heading starts with literal text, to start with that text. We only resume at a pair if the pair after it anchors too, and
+//! score the candidates by how well they and the next few pairs after them
+//! anchor, minus how many blocks we'd skip to get to them.
+use tree_sitter::{Node, TreeCursor};
+
+use crate::mdschema::validation::{
+    errors::{SchemaViolationError, ValidationError},
+    ts_types::*,
+    ts_utils::{
+        get_node_text, goto_next_sibling_skipping, goto_next_sibling_skipping_directives,
+        heading_level,
+    },
+    validator_walker::ValidatorWalker,
+    walkers::validators::{
+        containers::is_repeated_matcher_paragraph,
+        rulers::ruler_matcher,
+        sections::{repeated_section_matcher, section_capture_matcher},
+    },
+};
+
+/// The most blocks we skip on either side to get back in step.
+const MAX_SKIPPED_BLOCKS: usize = 8;
+
+/// How many pairs of blocks, starting at a candidate, we score it by.
+const ANCHOR_LOOKAHEAD: usize = 3;
+
+/// How well a schema block and an input block anchor, from 0 for not at all.
+///
+/// Headings anchor best, since they are what documents are structured by,
+/// and best of all when the input heading starts with the schema heading's
+/// literal text.
+pub(crate) fn anchor_score(
+    schema_node: &Node,
+    schema_str: &str,
+    input_node: &Node,
+    input_str: &str,
+) -> usize {
+    if schema_node.kind() != input_node.kind() && !both_are_list_nodes(schema_node, input_node) {
+        return 0;
+    }
+
+    match (heading_level(schema_node), heading_level(input_node)) {
+        (Some(schema_level), Some(input_level)) if schema_level != input_level => 0,
+        (Some(_), Some(_)) => {
+            let prefix = heading_literal_prefix(schema_node, schema_str);
+            if prefix.is_empty() {
+                2
+            } else if heading_text(input_node, input_str).starts_with(prefix) {
+                4
+            } else {
+                0
+            }
+        }
+        _ => 1,
+    }
+}
+
+/// Move the cursors past blocks that don't line up, if that gets them back in
+/// step.
+///
+/// The cursors point at a pair of top-level blocks that failed to validate.
+/// If they don't anchor, and skipping some blocks on either side lines the
+/// following blocks up better than staying put, the cursors are moved to the
+/// first pair of blocks that line up again.
+///
+/// # Returns
+///
+/// The error summarizing the skipped blocks, or `None` if the cursors were
+/// left where they were.
+pub(crate) fn resync_cursors(
+    walker: &ValidatorWalker,
+    schema_cursor: &mut TreeCursor,
+    input_cursor: &mut TreeCursor,
+) -> Option<ValidationError> {
+    if anchor_score(
+        &schema_cursor.node(),
+        walker.schema_str(),
+        &input_cursor.node(),
+        walker.input_str(),
+    ) > 0
+    {
+        return None;
+    }
+
+    let mut schema_blocks = following_blocks(
+        schema_cursor,
+        |cursor| goto_next_sibling_skipping_directives(cursor, walker.schema_str()),
+        |cursor| consumes_many_inputs(cursor, walker.schema_str()),
+    );
+    let mut input_blocks = following_blocks(
+        input_cursor,
+        |cursor| goto_next_sibling_skipping(cursor, |node| walker.skips_input_node(node)),
+        |_| false,
+    );
+
+    let anchors_at = |schema_index: usize, input_index: usize| -> bool {
+        match (schema_blocks.get(schema_index), input_blocks.get(input_index)) {
+            (Some(schema_block), Some(input_block)) => {
+                anchor_score(
+                    &schema_block.node(),
+                    walker.schema_str(),
+                    &input_block.node(),
+                    walker.input_str(),
+                ) > 0
+            }
+            _ => false,
+        }
+    };
+    // How well the blocks line up if we resume at the given pair
+    let score_from = |schema_start: usize, input_start: usize| -> usize {
+        (0..ANCHOR_LOOKAHEAD)
+            .map_while(|offset| {
+                let schema_block = schema_blocks.get(schema_start + offset)?;
+                let input_block = input_blocks.get(input_start + offset)?;
+                Some(anchor_score(
+                    &schema_block.node(),
+                    walker.schema_str(),
+                    &input_block.node(),
+                    walker.input_str(),
+                ))
+            })
+            .sum()
+    };
+
+    let staying_score = score_from(0, 0);
+    let (skipped_schema, skipped_input) = (0..schema_blocks.len().min(MAX_SKIPPED_BLOCKS + 1))
+        .flat_map(|skipped_schema| {
+            (0..input_blocks.len().min(MAX_SKIPPED_BLOCKS + 1))
+                .map(move |skipped_input| (skipped_schema, skipped_input))
+        })
+        .filter(|&(skipped_schema, skipped_input)| {
+            (skipped_schema, skipped_input) != (0, 0)
+                && anchors_at(skipped_schema, skipped_input)
+                && anchors_at(skipped_schema + 1, skipped_input + 1)
+        })
+        // Prefer the best score, then skipping the fewest blocks
+        .max_by_key(|&(skipped_schema, skipped_input)| {
+            let skipped = skipped_schema + skipped_input;
+            let score = score_from(skipped_schema, skipped_input).saturating_sub(skipped);
+            (score, std::cmp::Reverse(skipped))
+        })
+        .filter(|&(skipped_schema, skipped_input)| {
+            let skipped = skipped_schema + skipped_input;
+            score_from(skipped_schema, skipped_input).saturating_sub(skipped) > staying_score
+        })?;
+
+    let error = ValidationError::SchemaViolation(SchemaViolationError::UnalignedBlocks {
+        schema_index: schema_cursor.descendant_index(),
+        input_index: input_cursor.descendant_index(),
+        skipped_schema,
+        skipped_input,
+    });
+    *schema_cursor = schema_blocks.swap_remove(skipped_schema);
+    *input_cursor = input_blocks.swap_remove(skipped_input);
+
+    Some(error)
+}
+
+/// The block at the cursor and the blocks after it, as far as we'd look to
+/// get back in step.
+///
+/// # Arguments
+///
+/// * `cursor`: The cursor at the first block.
+/// * `goto_next`: Moves a cursor to the next block, returning false if there
+///   isn't one.
+/// * `stop_at`: Whether we can't look past a block. It, and every block after
+///   it, are left out.
+fn following_blocks<'t>(
+    cursor: &TreeCursor<'t>,
+    goto_next: impl Fn(&mut TreeCursor<'t>) -> bool,
+    stop_at: impl Fn(&TreeCursor<'t>) -> bool,
+) -> Vec<TreeCursor<'t>> {
+    let mut blocks = Vec::new();
+    let mut cursor = cursor.clone();
+    loop {
+        if stop_at(&cursor) {
+            break;
+        }
+        blocks.push(cursor.clone());
+        if blocks.len() > MAX_SKIPPED_BLOCKS + ANCHOR_LOOKAHEAD || !goto_next(&mut cursor) {
+            break;
+        }
+    }
+    blocks
+}
+
+/// Whether a schema block can match any number of input blocks, so we can't
+/// tell where the input would line up with the blocks after it.
+fn consumes_many_inputs(schema_cursor: &TreeCursor, schema_str: &str) -> bool {
+    is_repeated_matcher_paragraph(schema_cursor, schema_str)
+        || repeated_section_matcher(schema_cursor, schema_str).is_some()
+        || section_capture_matcher(schema_cursor, schema_str).is_some()
+        || ruler_matcher(schema_cursor, schema_str).is_some()
+}
+
+/// The text of a schema heading before its first matcher, like "Step " for
+/// ``## Step `number:/\d+/` ``.
+fn heading_literal_prefix<'a>(heading: &Node, schema_str: &'a str) -> &'a str {
+    let Some(content) = heading_content(heading) else {
+        return "";
+    };
+
+    let mut cursor = content.walk();
+    let end = content
+        .children(&mut cursor)
+        .find(|child| child.kind() == "code_span")
+        .map_or(content.end_byte(), |code_span| code_span.start_byte());
+    schema_str[content.start_byte()..end].trim()
+}
+
+/// The text of a heading, without its marker.
+fn heading_text<'a>(heading: &Node, src: &'a str) -> &'a str {
+    heading_content(heading).map_or("", |content| get_node_text(&content, src).trim())
+}
+
+fn heading_content<'t>(heading: &Node<'t>) -> Option<Node<'t>> {
+    let mut cursor = heading.walk();
+    heading.children(&mut cursor).find(is_heading_content_node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::anchor_score;
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    /// The anchor score of the first blocks of two documents.
+    fn first_blocks_score(schema: &str, input: &str) -> usize {
+        let schema_tree = parse_markdown(schema).unwrap();
+        let input_tree = parse_markdown(input).unwrap();
+        let schema_node = schema_tree.root_node().child(0).unwrap();
+        let input_node = input_tree.root_node().child(0).unwrap();
+        anchor_score(&schema_node, schema, &input_node, input)
+    }
+
+    #[test]
+    fn test_anchor_score() {
+        assert_eq!(first_blocks_score("## Usage\n", "## Usage\n"), 4);
+        assert_eq!(first_blocks_score("## Step `n:/\\d+/`\n", "## Step 2\n"), 4);
+        assert_eq!(first_blocks_score("## `title:/.+/`\n", "## Anything\n"), 2);
+        assert_eq!(first_blocks_score("Some text\n", "Other text\n"), 1);
+
+        assert_eq!(first_blocks_score("## Usage\n", "## Install\n"), 0);
+        assert_eq!(first_blocks_score("## Usage\n", "### Usage\n"), 0);
+        assert_eq!(first_blocks_score("## Usage\n", "Usage\n"), 0);
+    }
+}
@@ -1,8 +1,9 @@
 pub mod node_walker;
 
-pub use validation_result::ValidationResult;
+pub use validation_result::{DuplicateMatch, ValidationData, ValidationResult};
 
-mod helpers;
+pub(crate) mod helpers;
+pub(crate) mod parallel;
 mod validation_result;
 pub(super) mod validators;
 
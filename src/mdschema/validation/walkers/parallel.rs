@@ -0,0 +1,376 @@
+//! Parallel validation of independent top-level sections.
+//!
+//! When the schema and input documents have the same number of top-level
+//! children, and none of the schema's top-level children can consume more than
+//! one input node (repeating matcher paragraphs, repeating sections and section
+//! captures) or is
+//! skipped (directive comments, link reference definitions, and input
+//! comments), every top-level pair is validated
+//! independently of the others. We split those pairs into chunks
+//! and validate each chunk on the rayon thread pool, or on a pool of
+//! `ValidateOptions::parallel_threads` threads if that's set. Those pools are
+//! built once per thread count and kept for later validations.
+//!
+//! Tree-sitter cursors can't be shared across threads, so each worker parses
+//! its own copy of the schema and input. Since the text is identical, the
+//! descendant indexes in the resulting errors are identical to the ones the
+//! serial path produces. For the same reason, the workers can share the
+//! matchers they build (see `matcher_cache`).
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use log::warn;
+use rayon::{ThreadPool, prelude::*};
+use tree_sitter::{Tree, TreeCursor};
+
+use crate::mdschema::validation::{
+    matchers::matcher_cache::MatcherCache,
+    node_pos_pair::NodePosPair,
+    ts_utils::{is_schema_only_node, parse_markdown},
+    validator::ValidateOptions,
+    validator_walker::ValidatorWalker,
+    walkers::{
+        ValidationResult,
+        helpers::resync::anchor_score,
+        validators::{
+            Validator, containers::is_repeated_matcher_paragraph, nodes::NodeVsNodeValidator,
+            rulers::ruler_matcher,
+            sections::{repeated_section_matcher, section_capture_matcher},
+        },
+    },
+};
+
+/// Validate the top-level sections of the schema and input in parallel.
+///
+/// Returns `None` if the documents can't be split into independent
+/// one-to-one sections, in which case the caller should fall back to the
+/// serial path. Results are merged in document order, so errors and matches
+/// are identical to what the serial path produces.
+///
+/// # Arguments
+///
+/// * `schema_tree`: The parsed schema tree, used to decide whether we can partition.
+/// * `schema_str`: The full input document (so far).
+/// * `input_tree`: The parsed input tree, used to decide whether we can partition.
+/// * `input_str`: The full schema document.
+/// * `options`: The options to validate each section with.
+/// * `matcher_cache`: The matchers built from the schema so far.
+pub(crate) fn validate_top_level_in_parallel(
+    schema_tree: &Tree,
+    schema_str: &str,
+    input_tree: &Tree,
+    input_str: &str,
+    options: &ValidateOptions,
+    matcher_cache: &Arc<MatcherCache>,
+) -> Option<ValidationResult> {
+    let section_count =
+        count_independent_sections(schema_tree, schema_str, input_tree, input_str, options)?;
+
+    let validate_chunks = || {
+        let sections: Vec<usize> = (0..section_count).collect();
+        let chunk_size = section_count
+            .div_ceil(rayon::current_num_threads())
+            .max(1);
+
+        sections
+            .par_chunks(chunk_size)
+            .map(|chunk| validate_sections(chunk, schema_str, input_str, options, matcher_cache))
+            .collect::<Option<Vec<_>>>()
+    };
+    let chunk_results: Vec<Vec<ValidationResult>> = match options.parallel_threads {
+        Some(num_threads) => thread_pool(num_threads)?.install(validate_chunks)?,
+        None => validate_chunks()?,
+    };
+
+    let mut result = ValidationResult::from_cursors(&schema_tree.walk(), &input_tree.walk());
+    for section_result in chunk_results.iter().flatten() {
+        result.join_other_result(section_result);
+        result.set_farthest_reached_pos(*section_result.farthest_reached_pos());
+    }
+
+    Some(result)
+}
+
+/// The thread pool with `num_threads` threads, built the first time it's asked for.
+///
+/// Returns `None` if the pool couldn't be built, in which case the caller
+/// should fall back to the serial path. We only log that the first time.
+fn thread_pool(num_threads: usize) -> Option<Arc<ThreadPool>> {
+    static POOLS: OnceLock<Mutex<HashMap<usize, Option<Arc<ThreadPool>>>>> = OnceLock::new();
+
+    let mut pools = POOLS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    pools
+        .entry(num_threads)
+        .or_insert_with(|| {
+            match rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+            {
+                Ok(pool) => Some(Arc::new(pool)),
+                Err(error) => {
+                    warn!(
+                        "Failed to build a pool of {} threads, validating serially: {}",
+                        num_threads, error
+                    );
+                    None
+                }
+            }
+        })
+        .clone()
+}
+
+/// Count the top-level sections, or `None` if they aren't independent.
+fn count_independent_sections<'a>(
+    schema_tree: &'a Tree,
+    schema_str: &'a str,
+    input_tree: &'a Tree,
+    input_str: &'a str,
+    options: &'a ValidateOptions,
+) -> Option<usize> {
+    let schema_root = schema_tree.root_node();
+    let input_root = input_tree.root_node();
+
+    if schema_root.kind() != input_root.kind()
+        || schema_root.child_count() != input_root.child_count()
+        || schema_root.child_count() < 2
+    {
+        return None;
+    }
+
+    // Skipped input nodes would shift the section pairing
+    let walker = ValidatorWalker::from_cursors(
+        &schema_tree.walk(),
+        schema_str,
+        &input_tree.walk(),
+        input_str,
+    )
+    .with_options(options);
+    if has_skipped_top_level_node(&walker, input_tree) {
+        return None;
+    }
+
+    let mut schema_cursor = schema_tree.walk();
+    schema_cursor.goto_first_child();
+    loop {
+        if !matches_one_input_block(&schema_cursor, schema_str) {
+            return None;
+        }
+        if !schema_cursor.goto_next_sibling() {
+            break;
+        }
+    }
+
+    Some(schema_root.child_count())
+}
+
+/// Whether a top-level schema block is always compared against exactly one
+/// input block. Repeating matcher paragraphs, repeating sections, section
+/// captures and ruler matchers can consume any number of input blocks, and
+/// schema-only nodes, like directive comments, none.
+pub(crate) fn matches_one_input_block(schema_cursor: &TreeCursor, schema_str: &str) -> bool {
+    !(is_repeated_matcher_paragraph(schema_cursor, schema_str)
+        || repeated_section_matcher(schema_cursor, schema_str).is_some()
+        || section_capture_matcher(schema_cursor, schema_str).is_some()
+        || ruler_matcher(schema_cursor, schema_str).is_some()
+        || is_schema_only_node(&schema_cursor.node(), schema_str))
+}
+
+/// Validate a contiguous run of top-level sections on the current thread.
+fn validate_sections(
+    sections: &[usize],
+    schema_str: &str,
+    input_str: &str,
+    options: &ValidateOptions,
+    matcher_cache: &Arc<MatcherCache>,
+) -> Option<Vec<ValidationResult>> {
+    let schema_tree = parse_markdown(schema_str)?;
+    let input_tree = parse_markdown(input_str)?;
+
+    let mut schema_cursor = schema_tree.walk();
+    let mut input_cursor = input_tree.walk();
+    schema_cursor.goto_first_child();
+    input_cursor.goto_first_child();
+    goto_nth_sibling(&mut schema_cursor, sections[0]);
+    goto_nth_sibling(&mut input_cursor, sections[0]);
+
+    let walker = ValidatorWalker::from_cursors(&schema_cursor, schema_str, &input_cursor, input_str)
+        .with_options(options)
+        .with_matcher_cache(matcher_cache);
+    // Serially, the sections are validated inside the validator of the document
+    walker.set_depth(1);
+
+    let mut results = Vec::with_capacity(sections.len());
+    loop {
+        let mut section_result =
+            NodeVsNodeValidator.validate(&walker.with_cursors(&schema_cursor, &input_cursor), true);
+        // Like the serial top-level loop, we resume from the section pair itself.
+        section_result.set_farthest_reached_pos(NodePosPair::from_cursors(
+            &schema_cursor,
+            &input_cursor,
+        ));
+        // The serial path may skip ahead to get the sections back in step,
+        // which splitting them up front can't
+        if section_result.has_errors()
+            && anchor_score(
+                &schema_cursor.node(),
+                schema_str,
+                &input_cursor.node(),
+                input_str,
+            ) == 0
+        {
+            return None;
+        }
+        results.push(section_result);
+
+        if results.len() == sections.len() {
+            break;
+        }
+        schema_cursor.goto_next_sibling();
+        input_cursor.goto_next_sibling();
+    }
+
+    Some(results)
+}
+
+fn has_skipped_top_level_node(walker: &ValidatorWalker, input_tree: &Tree) -> bool {
+    let root = input_tree.root_node();
+    let mut cursor = root.walk();
+    root.children(&mut cursor)
+        .any(|child| walker.skips_input_node(&child))
+}
+
+fn goto_nth_sibling(cursor: &mut TreeCursor, n: usize) {
+    for _ in 0..n {
+        cursor.goto_next_sibling();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::validate_top_level_in_parallel;
+    use crate::mdschema::validation::{
+        ts_utils::parse_markdown,
+        validator::{ValidateOptions, Validator, ValidatorState},
+    };
+
+    fn validate_both_ways(schema: &str, input: &str) -> (Validator, Validator) {
+        validate_both_ways_on(schema, input, None)
+    }
+
+    fn validate_both_ways_on(
+        schema: &str,
+        input: &str,
+        parallel_threads: Option<usize>,
+    ) -> (Validator, Validator) {
+        let mut serial = Validator::new_complete(schema, input).unwrap();
+        serial.validate();
+
+        let mut parallel = Validator::new_complete(schema, input)
+            .unwrap()
+            .with_options(ValidateOptions {
+                parallel: true,
+                parallel_threads,
+                ..Default::default()
+            });
+        parallel.validate();
+
+        (serial, parallel)
+    }
+
+    #[test]
+    fn test_parallel_matches_serial_output() {
+        let mut schema = String::new();
+        let mut input = String::new();
+        for i in 0..64 {
+            schema.push_str(&format!("# Section {i}\n\nValue `value_{i}:/\\w+/`\n\n"));
+            input.push_str(&format!("# Section {i}\n\nValue v{i}\n\n"));
+        }
+        // Break a few sections so we also compare errors
+        let input = input.replace("# Section 7\n", "# Sektion 7\n");
+        let input = input.replace("# Section 40\n", "## Section 40\n");
+
+        let (serial, parallel) = validate_both_ways(&schema, &input);
+
+        assert_eq!(serial.farthest_reached_pos(), parallel.farthest_reached_pos());
+        assert_eq!(
+            serial.errors_so_far().collect::<Vec<_>>(),
+            parallel.errors_so_far().collect::<Vec<_>>()
+        );
+        assert_eq!(serial.matches_so_far(), parallel.matches_so_far());
+    }
+
+    #[test]
+    fn test_parallel_on_a_fixed_number_of_threads() {
+        let mut schema = String::new();
+        let mut input = String::new();
+        for i in 0..16 {
+            schema.push_str(&format!("Item {i}: `item_{i}:/\\d+/`\n\n"));
+            input.push_str(&format!("Item {i}: {}\n\n", i * 7));
+        }
+        let input = input.replace("Item 3: 21", "Item 3: twenty-one");
+
+        for num_threads in [1, 3] {
+            let (serial, parallel) = validate_both_ways_on(&schema, &input, Some(num_threads));
+            assert_eq!(
+                serial.errors_so_far().collect::<Vec<_>>(),
+                parallel.errors_so_far().collect::<Vec<_>>()
+            );
+            assert_eq!(serial.matches_so_far(), parallel.matches_so_far());
+        }
+    }
+
+    #[test]
+    fn test_parallel_falls_back_with_repeating_paragraph() {
+        let schema = "Intro\n\n`para`{1,}\n";
+        let input = "Intro\n\nOne\n";
+
+        let schema_tree = parse_markdown(schema).unwrap();
+        let input_tree = parse_markdown(input).unwrap();
+
+        assert!(
+            validate_top_level_in_parallel(
+                &schema_tree,
+                schema,
+                &input_tree,
+                input,
+                &ValidateOptions::default(),
+                &Arc::default()
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_parallel_falls_back_with_mismatched_section_count() {
+        let schema = "First\n\nSecond\n\nThird\n";
+        let input = "First\n\nSecond\n";
+
+        let schema_tree = parse_markdown(schema).unwrap();
+        let input_tree = parse_markdown(input).unwrap();
+
+        assert!(
+            validate_top_level_in_parallel(
+                &schema_tree,
+                schema,
+                &input_tree,
+                input,
+                &ValidateOptions::default(),
+                &Arc::default()
+            )
+            .is_none()
+        );
+
+        let (serial, parallel) = validate_both_ways(schema, input);
+        assert_eq!(
+            serial.errors_so_far().collect::<Vec<_>>(),
+            parallel.errors_so_far().collect::<Vec<_>>()
+        );
+    }
+}
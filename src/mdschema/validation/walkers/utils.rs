@@ -1,4 +1,4 @@
-use tree_sitter::TreeCursor;
+use tree_sitter::{Tree, TreeCursor};
 
 #[cfg(test)]
 use serde_json::Value;
@@ -62,3 +62,58 @@ pub fn pretty_print_cursor_pair(schema_cursor: &TreeCursor, input_cursor: &TreeC
 
     Table::new(vec![content]).with(Style::blank()).to_string()
 }
+
+/// Render the schema and input subtrees around a pair of nodes side by side.
+///
+/// Each side is rooted `ancestor_levels` levels above its node, with the node
+/// itself highlighted and text excerpts shown. Either side may be `None` (for
+/// example, schema errors only point into the schema), in which case that
+/// column is left empty.
+pub fn pretty_print_subtree_pair(
+    schema: Option<(&Tree, &str, usize)>,
+    input: Option<(&Tree, &str, usize)>,
+    ancestor_levels: usize,
+) -> String {
+    use tabled::{Table, Tabled, settings::Style};
+
+    #[derive(Tabled)]
+    struct Content {
+        #[tabled(rename = "Schema:")]
+        schema: String,
+        #[tabled(rename = "Input:")]
+        input: String,
+    }
+
+    let render = |side: Option<(&Tree, &str, usize)>| {
+        side.map_or_else(String::new, |(tree, src, index)| {
+            pretty_print_subtree(tree, src, index, ancestor_levels)
+        })
+    };
+
+    let content = Content {
+        schema: render(schema),
+        input: render(input),
+    };
+
+    Table::new(vec![content]).with(Style::blank()).to_string()
+}
+
+/// Render the subtree `ancestor_levels` levels above the node at `index`,
+/// highlighting that node.
+fn pretty_print_subtree(tree: &Tree, src: &str, index: usize, ancestor_levels: usize) -> String {
+    let mut cursor = tree.walk();
+    cursor.goto_descendant(index);
+    for _ in 0..ancestor_levels {
+        if !cursor.goto_parent() {
+            break;
+        }
+    }
+
+    cursor
+        .node()
+        .get_pretty_printer()
+        .start_index(cursor.descendant_index())
+        .highlight(&[index])
+        .show_text()
+        .print(src)
+}
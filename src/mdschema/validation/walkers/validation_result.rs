@@ -1,29 +1,58 @@
-use serde_json::{Value, json};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
 use tree_sitter::TreeCursor;
 
+use crate::mdschema::validation::back_references::BackReference;
 use crate::mdschema::validation::errors::ValidationError;
+use crate::mdschema::validation::match_paths::match_path;
+use crate::mdschema::validation::matchers::matcher::Matcher;
 use crate::mdschema::validation::node_pos_pair::NodePosPair;
-use crate::mdschema::validation::utils::join_values;
+use crate::mdschema::validation::utils::{collect_values, join_values};
+
+/// A value captured under an id that already had one.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateMatch {
+    /// The keys of the scope the id is in (see `scopes`), outermost first.
+    pub scope: Vec<String>,
+    pub id: String,
+    /// The value captured the second (or later) time.
+    pub value: Value,
+}
 
 /// Validation data containing errors and matched values, without position tracking
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ValidationData {
     /// The resulting JSON value with all matches
     pub value: Value,
     /// Vector of all validation errors encountered
     pub errors: Vec<ValidationError>,
+    /// Values captured under ids that already had a value, in the order they
+    /// were captured. Both values are collected into an array in `value`,
+    /// and these let the validator undo that (see `DuplicateIdPolicy`).
+    pub duplicates: Vec<DuplicateMatch>,
+    /// What back-references matched, in order, to compare against what their
+    /// ids captured once all of the matches are known (see `back_references`).
+    pub back_references: Vec<BackReference>,
+    /// The keys of the objects in `value` that dotted ids nest their matches
+    /// in (see `match_paths`). These are joined key by key with the objects
+    /// of other data, where the objects of captures are collected instead.
+    #[serde(default)]
+    pub nested: Vec<Vec<String>>,
 }
 
 impl ValidationData {
     pub fn new(value: Value, errors: Vec<ValidationError>) -> Self {
-        Self { value, errors }
+        Self {
+            value,
+            errors,
+            duplicates: Vec::new(),
+            back_references: Vec::new(),
+            nested: Vec::new(),
+        }
     }
 
     pub fn empty() -> Self {
-        Self {
-            value: json!({}),
-            errors: Vec::new(),
-        }
+        Self::new(json!({}), Vec::new())
     }
 
     pub fn has_errors(&self) -> bool {
@@ -34,24 +63,133 @@ impl ValidationData {
         self.errors.push(error);
     }
 
+    /// Add a match under an `id`. If the id already has a value, the two are
+    /// collected into an array.
     pub fn set_match(&mut self, id: &str, value: Value) {
-        self.value[id] = value;
+        self.set_match_at(&[], id, value);
+    }
+
+    /// Add a match under an `id` in the object at a scope path (see
+    /// `scopes`), creating the objects of the path as needed. A dotted id
+    /// nests the match further, under the keys of its path (see
+    /// `match_paths`).
+    ///
+    /// A scope, or a key of a path, replaces anything captured under an id
+    /// with the same name as it. Schemas where that happens for a path are
+    /// reported as a `SchemaError::MatchPathConflict` when they are loaded.
+    pub fn set_match_at(&mut self, scope: &[String], id: &str, value: Value) {
+        let mut path = match_path(id);
+        let Some(key) = path.pop() else {
+            return;
+        };
+
+        let mut scope = scope.to_vec();
+        for name in path {
+            scope.push(name);
+            self.mark_nested(&scope);
+        }
+        self.insert_match(&scope, key, value);
+    }
+
+    /// Add a match under a `key` in the object at a scope path, without
+    /// reading the key as a path.
+    ///
+    /// New keys are added after the ones already there, and a key that is
+    /// already there keeps its place, so keys are in the order they were
+    /// first captured.
+    fn insert_match(&mut self, scope: &[String], key: String, value: Value) {
+        let mut target = &mut self.value;
+        for name in scope {
+            target = &mut target[name.as_str()];
+            if !target.is_object() {
+                *target = Value::Object(Map::new());
+            }
+        }
+
+        match target.get_mut(key.as_str()) {
+            Some(existing) => {
+                self.duplicates.push(DuplicateMatch {
+                    scope: scope.to_vec(),
+                    id: key,
+                    value: value.clone(),
+                });
+                collect_values(existing, value);
+            }
+            None => target[key.as_str()] = value,
+        }
+    }
+
+    fn mark_nested(&mut self, path: &[String]) {
+        if !self.nested.iter().any(|nested| nested == path) {
+            self.nested.push(path.to_vec());
+        }
+    }
+
+    /// Join in the matches of another value under a scope path, key by key.
+    ///
+    /// # Arguments
+    ///
+    /// * `scope`: Where to put the matches.
+    /// * `matches`: The matches, or the part of them under `path`.
+    /// * `path`: The keys of `matches` in the value they came from.
+    /// * `nested`: The keys of the objects of dotted ids in that value.
+    fn join_matches(
+        &mut self,
+        scope: &[String],
+        matches: Map<String, Value>,
+        path: &[String],
+        nested: &[Vec<String>],
+    ) {
+        for (key, value) in matches {
+            let mut inner_scope = scope.to_vec();
+            inner_scope.push(key.clone());
+            let mut inner_path = path.to_vec();
+            inner_path.push(key.clone());
+
+            let is_nested = self.nested.contains(&inner_scope) || nested.contains(&inner_path);
+            match value {
+                Value::Object(inner) if is_nested => {
+                    self.mark_nested(&inner_scope);
+                    self.join_matches(&inner_scope, inner, &inner_path, nested);
+                }
+                value => self.insert_match(scope, key, value),
+            }
+        }
     }
 
     pub fn join(&mut self, other: &ValidationData) {
-        // Join in their values
-        let joined = &mut self.value.clone();
-        join_values(joined, other.value.clone());
-        self.value = joined.clone();
+        // Join in their values, with the objects of dotted ids key by key
+        match other.value.clone() {
+            Value::Object(matches) if self.value.is_object() => {
+                self.join_matches(&[], matches, &[], &other.nested)
+            }
+            value => join_values(&mut self.value, value),
+        }
+        self.duplicates.extend(other.duplicates.iter().cloned());
+        self.back_references
+            .extend(other.back_references.iter().cloned());
 
         // Join in their errors
         self.errors.extend(other.errors.clone());
     }
+
+    /// Join in the matches of another value, collecting the values of ids
+    /// that both have into arrays. Our ids come first, followed by the new
+    /// ones of the other value, in its order. Objects that we nest dotted ids
+    /// in are joined key by key.
+    pub fn join_value(&mut self, value: Value) {
+        match value {
+            Value::Object(matches) if self.value.is_object() => {
+                self.join_matches(&[], matches, &[], &[])
+            }
+            value => join_values(&mut self.value, value),
+        }
+    }
 }
 
 /// Validation results containing a Value with all matches, vector of all
 /// errors, and the descendant indexes after validation
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ValidationResult {
     /// The validation data (errors and values)
     data: ValidationData,
@@ -104,6 +242,16 @@ impl ValidationResult {
         &self.data.errors
     }
 
+    /// Access the values captured under ids that already had a value
+    pub fn duplicates(&self) -> &[DuplicateMatch] {
+        &self.data.duplicates
+    }
+
+    /// Access what back-references matched
+    pub fn back_references(&self) -> &[BackReference] {
+        &self.data.back_references
+    }
+
     /// Updates the cursor positions to the positions of the given cursors.
     pub fn sync_cursor_pos(&mut self, schema_cursor: &TreeCursor, input_cursor: &TreeCursor) {
         self.farthest_reached_pos = NodePosPair::from_cursors(schema_cursor, input_cursor);
@@ -129,12 +277,76 @@ impl ValidationResult {
         self.data.has_errors()
     }
 
-    /// Add a match under an `id`.
+    /// Add a match under an `id`. If the id already has a value, the two are
+    /// collected into an array, in the order they were captured.
     #[allow(dead_code)]
     pub fn set_match(&mut self, id: &str, value: Value) {
         self.data.set_match(id, value);
     }
 
+    /// Add what a matcher captured under its id, converted to the type the
+    /// matcher declares (see `Matcher::typed_value`).
+    ///
+    /// If the capture doesn't parse as that type, an error is added instead.
+    /// What a back-reference matched is kept to compare against its id's
+    /// capture later, instead.
+    pub fn set_matcher_match(
+        &mut self,
+        matcher: &Matcher,
+        value: Value,
+        schema_index: usize,
+        input_index: usize,
+    ) {
+        if let Some(id) = matcher.back_reference() {
+            let text = value
+                .get("value")
+                .unwrap_or(&value)
+                .as_str()
+                .unwrap_or_default();
+            self.data.back_references.push(BackReference {
+                scope: Vec::new(),
+                id: id.to_string(),
+                text: text.to_string(),
+                schema_index,
+                input_index,
+            });
+            return;
+        }
+
+        let Some(id) = matcher.id() else {
+            return;
+        };
+        if let Some(value) = self.typed_value(matcher, value, schema_index, input_index) {
+            self.set_match(id, value);
+        }
+    }
+
+    /// Convert a capture to the type its matcher declares, adding a
+    /// `CapturedValueTypeMismatch` error if it doesn't parse as that type.
+    pub fn typed_value(
+        &mut self,
+        matcher: &Matcher,
+        value: Value,
+        schema_index: usize,
+        input_index: usize,
+    ) -> Option<Value> {
+        let typed = matcher.typed_value(value.clone());
+        if typed.is_none() {
+            let actual = value.get("value").unwrap_or(&value).as_str().unwrap_or_default();
+            self.add_error(ValidationError::SchemaViolation(matcher.value_type_error(
+                schema_index,
+                input_index,
+                actual.to_string(),
+            )));
+        }
+        typed
+    }
+
+    /// Drop all but the first `len` errors.
+    pub fn truncate_errors(&mut self, len: usize) {
+        self.data.errors.truncate(len);
+    }
+
     /// Join in validation data (errors and values) from another result without updating position.
     pub fn join_data(&mut self, other: &ValidationData) {
         self.data.join(other);
@@ -146,6 +358,9 @@ impl ValidationResult {
     }
 
     /// Join in a different validation result including position tracking.
+    ///
+    /// The other result should be of a later block, since its matches are
+    /// added after ours (see `ValidationData::join_value`).
     pub fn join_other_result(&mut self, other: &ValidationResult) {
         self.data.join(&other.data);
 
@@ -156,9 +371,38 @@ impl ValidationResult {
 
     /// Join in just the value from another value
     pub fn join_value(&mut self, value: Value) {
-        let joined = &mut self.data.value.clone();
-        join_values(joined, value);
-        self.data.value = joined.clone();
+        self.data.join_value(value);
+    }
+
+    /// Join in a different validation result like `join_other_result`, but
+    /// with everything it captured nested under the keys of a scope (see
+    /// `scopes`), so that `{"host": ...}` becomes `{"config": {"host": ...}}`.
+    pub fn join_scoped_result(&mut self, scope: &[String], other: &ValidationResult) {
+        if scope.is_empty() {
+            self.join_other_result(other);
+            return;
+        }
+
+        if let Value::Object(matches) = other.value().clone() {
+            self.data
+                .join_matches(scope, matches, &[], &other.data.nested);
+        }
+        self.data
+            .duplicates
+            .extend(other.duplicates().iter().map(|duplicate| DuplicateMatch {
+                scope: scope.iter().chain(&duplicate.scope).cloned().collect(),
+                ..duplicate.clone()
+            }));
+        for back_reference in other.back_references() {
+            self.data.back_references.push(BackReference {
+                scope: scope.iter().chain(&back_reference.scope).cloned().collect(),
+                ..back_reference.clone()
+            });
+        }
+        self.data.errors.extend(other.errors().iter().cloned());
+
+        self.farthest_reached_pos
+            .keep_farther_pos(other.farthest_reached_pos());
     }
 
     pub fn keep_farther_pos(&mut self, other: &NodePosPair) {
@@ -218,4 +462,106 @@ mod tests {
         assert_eq!(result.value(), &json!({"id": "value"}));
         assert_eq!(result.errors().len(), 0);
     }
+
+    #[test]
+    fn test_set_match_collects_duplicate_ids() {
+        let mut result = ValidationResult::default();
+
+        result.set_match("id", json!("first"));
+        result.set_match("other", json!("value"));
+        result.set_match("id", json!("second"));
+
+        assert_eq!(
+            result.value(),
+            &json!({"id": ["first", "second"], "other": "value"})
+        );
+        assert_eq!(
+            result.duplicates(),
+            &[DuplicateMatch {
+                scope: Vec::new(),
+                id: "id".to_string(),
+                value: json!("second"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_join_other_result_collects_duplicate_ids() {
+        let mut result = ValidationResult::default();
+        result.set_match("item", json!(["a", "b"]));
+        result.set_match("title", json!("Hello"));
+
+        let mut other = ValidationResult::from_descendant_indexes(1, 1);
+        other.set_match("item", json!(["c"]));
+        other.set_match("item", json!(["d"]));
+        result.join_other_result(&other);
+
+        // Arrays from repeated matchers are concatenated, in order
+        assert_eq!(
+            result.value(),
+            &json!({"item": ["a", "b", "c", "d"], "title": "Hello"})
+        );
+        let duplicate_values: Vec<_> = result
+            .duplicates()
+            .iter()
+            .map(|duplicate| (duplicate.id.as_str(), duplicate.value.clone()))
+            .collect();
+        assert_eq!(
+            duplicate_values,
+            vec![("item", json!(["c", "d"])), ("item", json!(["d"]))]
+        );
+    }
+
+    #[test]
+    fn test_join_other_result_keeps_key_order() {
+        let mut result = ValidationResult::default();
+        result.set_match("zeta", json!("z"));
+        result.set_match("item", json!(["a"]));
+
+        let mut other = ValidationResult::from_descendant_indexes(1, 1);
+        other.set_match("beta", json!("b"));
+        other.set_match("item", json!(["b"]));
+        result.join_other_result(&other);
+
+        // Objects compare equal in any order, so compare the JSON instead
+        assert_eq!(
+            result.value().to_string(),
+            r#"{"zeta":"z","item":["a","b"],"beta":"b"}"#
+        );
+    }
+
+    #[test]
+    fn test_set_match_nests_dotted_ids() {
+        let mut result = ValidationResult::default();
+        result.set_match("meta.author", json!("Ada"));
+        result.set_match("meta.license", json!("MIT"));
+        result.set_match("v1\\.2", json!("old"));
+
+        assert_eq!(
+            result.value(),
+            &json!({"meta": {"author": "Ada", "license": "MIT"}, "v1.2": "old"})
+        );
+        assert!(result.duplicates().is_empty());
+    }
+
+    #[test]
+    fn test_join_other_result_merges_dotted_ids() {
+        let mut result = ValidationResult::default();
+        result.set_match("meta.author", json!("Ada"));
+        result.set_match("span", json!({"start": 0}));
+
+        let mut other = ValidationResult::from_descendant_indexes(1, 1);
+        other.set_match("meta.license", json!("MIT"));
+        other.set_match("span", json!({"end": 1}));
+        result.join_other_result(&other);
+
+        // Captured objects are still collected, rather than merged
+        assert_eq!(
+            result.value(),
+            &json!({
+                "meta": {"author": "Ada", "license": "MIT"},
+                "span": [{"start": 0}, {"end": 1}],
+            })
+        );
+    }
 }
@@ -2,11 +2,11 @@
 //!
 //! Types:
 //! - `CodeVsCodeValidator`: validates code block language and content, with
-//!   optional matcher-based captures in schema text.
-use serde_json::json;
-
+//!   optional matcher-based captures in schema text and body.
 use crate::invariant_violation;
-use crate::mdschema::validation::ts_utils::CodeblockContents;
+use crate::mdschema::validation::matchers::matcher::{Matcher, RegexLimits};
+use crate::mdschema::validation::spans::subslice_offset;
+use crate::mdschema::validation::ts_utils::{CodeblockContents, waiting_at_end};
 use crate::mdschema::validation::validator_walker::ValidatorWalker;
 use crate::mdschema::validation::{
     errors::{NodeContentMismatchKind, SchemaError, SchemaViolationError, ValidationError},
@@ -23,7 +23,7 @@ use crate::mdschema::validation::{
 ///
 /// Compares both the language specifier and code content between input and schema.
 /// The schema can use matchers in the language field to capture or validate patterns,
-/// and can use `{id}` in the code content to capture the entire code block.
+/// and can use `{id}` or a matcher in the code content to capture the entire code block.
 ///
 /// Language validation supports:
 /// - Literal matching: both must have the same language string
@@ -32,6 +32,8 @@ use crate::mdschema::validation::{
 /// Code content validation supports:
 /// - Literal matching: exact string comparison
 /// - Capture: schema uses `{id}` to capture input code without validation
+/// - Matching: schema body is a single matcher line, like `` `payload:/[\s\S]+/` ``,
+///   which has to match the whole input body, and captures it
 ///
 /// # Examples
 ///
@@ -48,20 +50,17 @@ use crate::mdschema::validation::{
 /// ```
 ///
 /// Captures: { "lang": "python", "code": "print(\"hello\")" }
-///
-/// Note you cannot yet enforce regex on the actual code content.
 /// ```
 #[derive(Default)]
 pub(super) struct CodeVsCodeValidator;
 
 impl ValidatorImpl for CodeVsCodeValidator {
     fn validate_impl(&self, walker: &ValidatorWalker, got_eof: bool) -> ValidationResult {
-        let _got_eof = got_eof;
-        validate_code_vs_code_impl(walker)
+        validate_code_vs_code_impl(walker, got_eof)
     }
 }
 
-fn validate_code_vs_code_impl(walker: &ValidatorWalker) -> ValidationResult {
+fn validate_code_vs_code_impl(walker: &ValidatorWalker, got_eof: bool) -> ValidationResult {
     let mut result = ValidationResult::from_cursors(walker.schema_cursor(), walker.input_cursor());
 
     let schema_cursor = walker.schema_cursor().clone();
@@ -79,6 +78,10 @@ fn validate_code_vs_code_impl(walker: &ValidatorWalker) -> ValidationResult {
         );
     }
 
+    // A code block that runs to the end of the input may still be missing
+    // lines, or even its body
+    let is_partial_match = waiting_at_end(got_eof, walker.input_str(), &input_cursor);
+
     let input_extracted =
         match CodeblockContents::try_from_cursor(&input_cursor, walker.input_str()) {
             Ok(value) => value,
@@ -98,6 +101,10 @@ fn validate_code_vs_code_impl(walker: &ValidatorWalker) -> ValidationResult {
 
     let (Some(schema_contents), Some(input_contents)) = (&schema_extracted, &input_extracted)
     else {
+        if is_partial_match && schema_extracted.is_some() {
+            return result;
+        }
+
         #[cfg(feature = "invariant_violations")]
         // The only reason the "entire thing" would be wrong is because we're
         // doing something wrong in our usage of it. That would be a bug!
@@ -118,19 +125,46 @@ fn validate_code_vs_code_impl(walker: &ValidatorWalker) -> ValidationResult {
 
     // Check if schema language has a matcher pattern (like {lang:/\w*/})
     match schema_lang.as_ref().and_then(|(lang, descendant_index)| {
-        extract_matcher_from_curly_delineated_text(lang)
+        extract_matcher_from_curly_delineated_text(lang, &walker.options().regex_limits)
             .map(|matcher_result| (matcher_result, descendant_index))
     }) {
         // If the schema has a matcher, and we were able to extract it, do matching!
         Some((Ok(schema_lang_matcher), schema_lang_descendant_index)) => {
             // Schema has matcher, validate input against it
             if let Some((input_lang_str, input_lang_descendant_index)) = input_lang {
-                if let Some(match_result) = schema_lang_matcher.match_str(input_lang_str) {
-                    // Match succeeded - capture if matcher has an ID
-                    if let Some(id) = schema_lang_matcher.id() {
-                        result.set_match(id, json!(match_result));
+                let lang_match = match schema_lang_matcher
+                    .try_match_str(input_lang_str, walker.options().max_matcher_input_len)
+                {
+                    Ok(lang_match) => lang_match,
+                    Err(error) => {
+                        result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                            error,
+                            schema_index: *schema_lang_descendant_index,
+                        }));
+                        return result;
                     }
-                } else {
+                };
+
+                if let Some(match_result) = lang_match {
+                    // Match succeeded - capture if matcher has an ID
+                    let Some(offset) = subslice_offset(input_lang_str, match_result) else {
+                        invariant_violation!(
+                            result,
+                            &schema_cursor,
+                            &input_cursor,
+                            "a match should be a slice of the text it is in"
+                        );
+                    };
+                    let start_byte =
+                        input_node_start_byte(walker, *input_lang_descendant_index) + offset;
+                    result.set_matcher_match(
+                        &schema_lang_matcher,
+                        walker.captured_value(match_result, start_byte),
+                        *schema_lang_descendant_index,
+                        *input_lang_descendant_index,
+                    );
+                } else if !input_node_waiting_at_end(walker, *input_lang_descendant_index, got_eof)
+                {
                     // Match failed
                     result.add_error(ValidationError::SchemaViolation(
                         SchemaViolationError::NodeContentMismatch {
@@ -141,7 +175,7 @@ fn validate_code_vs_code_impl(walker: &ValidatorWalker) -> ValidationResult {
                                 .map(|(s, _)| s.clone())
                                 .unwrap_or_default(),
                             actual: input_lang_str.clone(),
-                            kind: NodeContentMismatchKind::Literal,
+                            kind: NodeContentMismatchKind::InfoString,
                         },
                     ));
                     return result;
@@ -159,21 +193,48 @@ fn validate_code_vs_code_impl(walker: &ValidatorWalker) -> ValidationResult {
         }
         None => {
             // No matcher - do literal language comparison, treating as a literal string
-            if let (
-                Some((input_lang_str, input_lang_descendant_index)),
-                Some((schema_lang_str, schema_lang_descendant_index)),
-            ) = (schema_lang, input_lang)
-                && input_lang_str != schema_lang_str
-            {
-                result.add_error(ValidationError::SchemaViolation(
-                    SchemaViolationError::NodeContentMismatch {
-                        schema_index: *schema_lang_descendant_index,
-                        input_index: *input_lang_descendant_index,
-                        expected: schema_lang_str.clone(),
-                        actual: input_lang_str.clone(),
-                        kind: NodeContentMismatchKind::Literal,
-                    },
-                ));
+            match (schema_lang, input_lang) {
+                (
+                    Some((schema_lang_str, schema_lang_descendant_index)),
+                    Some((input_lang_str, input_lang_descendant_index)),
+                ) => {
+                    let matches = if input_node_waiting_at_end(
+                        walker,
+                        *input_lang_descendant_index,
+                        got_eof,
+                    ) {
+                        schema_lang_str.starts_with(input_lang_str.as_str())
+                    } else {
+                        schema_lang_str == input_lang_str
+                    };
+
+                    if !matches {
+                        result.add_error(ValidationError::SchemaViolation(
+                            SchemaViolationError::NodeContentMismatch {
+                                schema_index: *schema_lang_descendant_index,
+                                input_index: *input_lang_descendant_index,
+                                expected: schema_lang_str.clone(),
+                                actual: input_lang_str.clone(),
+                                kind: NodeContentMismatchKind::InfoString,
+                            },
+                        ));
+                    }
+                }
+                // The input may still be waiting for its info string
+                (Some((schema_lang_str, schema_lang_descendant_index)), None)
+                    if !is_partial_match =>
+                {
+                    result.add_error(ValidationError::SchemaViolation(
+                        SchemaViolationError::NodeContentMismatch {
+                            schema_index: *schema_lang_descendant_index,
+                            input_index: input_cursor.descendant_index(),
+                            expected: schema_lang_str.clone(),
+                            actual: String::new(),
+                            kind: NodeContentMismatchKind::InfoString,
+                        },
+                    ));
+                }
+                _ => {}
             }
         }
     }
@@ -188,10 +249,58 @@ fn validate_code_vs_code_impl(walker: &ValidatorWalker) -> ValidationResult {
     // that key in the result.
     if let Some(id) = extract_id_from_curly_braces(schema_code) {
         // Schema has {id} - capture the input code
-        result.set_match(id, json!(input_code));
+        result.set_match(
+            id,
+            walker.captured_value(
+                input_code,
+                input_code_start_byte(walker, *input_code_descendant_index),
+            ),
+        );
+    } else if let Some(matcher) = body_matcher(schema_code, &walker.options().regex_limits) {
+        // Schema body is a single matcher, which has to match the whole body
+        let body_match =
+            match matcher.try_match_str(input_code, walker.options().max_matcher_input_len) {
+                Ok(body_match) => body_match,
+                Err(error) => {
+                    result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                        error,
+                        schema_index: *schema_code_descendant_index,
+                    }));
+                    return result;
+                }
+            };
+
+        match body_match {
+            // The body may still be cut off, so wait for all of it before capturing
+            _ if is_partial_match => {}
+            Some(matched) if matched.len() == input_code.len() => {
+                result.set_matcher_match(
+                    &matcher,
+                    walker.captured_value(
+                        matched,
+                        input_code_start_byte(walker, *input_code_descendant_index),
+                    ),
+                    *schema_code_descendant_index,
+                    *input_code_descendant_index,
+                );
+            }
+            _ => {
+                result.add_error(ValidationError::SchemaViolation(matcher.mismatch_error(
+                    *schema_code_descendant_index,
+                    *input_code_descendant_index,
+                    input_code.into(),
+                )));
+            }
+        }
     } else {
         // No ID - do literal comparison of the code, treating it as a literal string
-        if input_code != schema_code {
+        let matches = if is_partial_match {
+            schema_code.starts_with(complete_lines(input_code))
+        } else {
+            input_code == schema_code
+        };
+
+        if !matches {
             result.add_error(ValidationError::SchemaViolation(
                 SchemaViolationError::NodeContentMismatch {
                     schema_index: *schema_code_descendant_index,
@@ -207,6 +316,60 @@ fn validate_code_vs_code_impl(walker: &ValidatorWalker) -> ValidationResult {
     result
 }
 
+/// The matcher a schema code block body consists of, if it is a single line
+/// holding just a matcher, like `` `payload:/[\s\S]+/` ``.
+///
+/// Bodies that are a code span but not a valid matcher, like `` `ls -la` ``,
+/// are literal code. The matcher's regex is compiled within `limits`.
+fn body_matcher(schema_code: &str, limits: &RegexLimits) -> Option<Matcher> {
+    let line = schema_code.trim();
+    if line.len() < 2 || line.contains('\n') || !line.starts_with('`') || !line.ends_with('`') {
+        return None;
+    }
+
+    Matcher::try_from_pattern_and_suffix_str_with_limits(line, None, limits).ok()
+}
+
+/// The lines of a code body that can't change anymore while more input may
+/// still come, without the line break after them. The last line may be cut
+/// off, or be the start of the closing fence.
+fn complete_lines(code: &str) -> &str {
+    code.rfind('\n').map_or("", |end| &code[..end])
+}
+
+/// Where the body of the input code block starts, given the descendant index
+/// of the text inside its `code_fence_content`.
+fn input_code_start_byte(walker: &ValidatorWalker, code_descendant_index: usize) -> usize {
+    let mut code_cursor = walker.input_cursor().clone();
+    code_cursor.goto_descendant(code_descendant_index);
+    code_cursor.goto_parent();
+    code_cursor.node().start_byte()
+}
+
+/// Whether the input node at `descendant_index` may still grow.
+///
+/// # Arguments
+///
+/// * `walker`: The walker, with its input cursor anywhere in the input.
+/// * `descendant_index`: The descendant index of the input node.
+/// * `got_eof`: Whether we have received the full input document.
+fn input_node_waiting_at_end(
+    walker: &ValidatorWalker,
+    descendant_index: usize,
+    got_eof: bool,
+) -> bool {
+    let mut cursor = walker.input_cursor().clone();
+    cursor.goto_descendant(descendant_index);
+    waiting_at_end(got_eof, walker.input_str(), &cursor)
+}
+
+/// Where the input node at `descendant_index` starts.
+fn input_node_start_byte(walker: &ValidatorWalker, descendant_index: usize) -> usize {
+    let mut cursor = walker.input_cursor().clone();
+    cursor.goto_descendant(descendant_index);
+    cursor.node().start_byte()
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::json;
@@ -285,4 +448,101 @@ fn main() {}
             &json!({ "lang": "rust", "code": "fn main() {}" })
         )
     }
+
+    #[test]
+    fn test_validate_code_vs_code_body_matcher() {
+        let schema_str = "```json\n`payload:/\\{[\\s\\S]+\\}/`\n```";
+        let input_str = "```json\n{\n  \"ok\": true\n}\n```";
+
+        let result = ValidatorTester::<CodeVsCodeValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_codeblocks(s, i)))
+            .validate_complete();
+
+        assert_eq!(result.errors(), &vec![]);
+        assert_eq!(
+            result.value(),
+            &json!({ "payload": "{\n  \"ok\": true\n}" })
+        );
+
+        let input_str = "```json\n{\n  \"ok\": true\n}\nmore\n```";
+        let result = ValidatorTester::<CodeVsCodeValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        // The match has to cover the whole body
+        assert_eq!(result.errors().len(), 1);
+        match &result.errors()[0] {
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                kind,
+                ..
+            }) => assert_eq!(*kind, NodeContentMismatchKind::Matcher),
+            error => panic!("Expected a matcher mismatch, got {:?}", error),
+        }
+        assert_eq!(result.value(), &json!({}));
+    }
+
+    #[test]
+    fn test_validate_code_vs_code_info_string_mismatch() {
+        let schema_str = "```json\n`payload:/[\\s\\S]+/`\n```";
+        let input_str = "```yaml\nok: true\n```";
+
+        let result = ValidatorTester::<CodeVsCodeValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert_eq!(result.errors().len(), 1);
+        match &result.errors()[0] {
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                expected,
+                actual,
+                kind,
+                ..
+            }) => {
+                assert_eq!(*kind, NodeContentMismatchKind::InfoString);
+                assert_eq!(expected, "json");
+                assert_eq!(actual, "yaml");
+            }
+            error => panic!("Expected an info string mismatch, got {:?}", error),
+        }
+        assert_eq!(result.value(), &json!({ "payload": "ok: true" }));
+    }
+
+    #[test]
+    fn test_validate_code_vs_code_unfinished_block() {
+        let schema_str = "```json\n`payload:/\\{[\\s\\S]+\\}/`\n```";
+        let input_str = "```json\n{\n  \"ok\": tr";
+
+        let result = ValidatorTester::<CodeVsCodeValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .validate_incomplete();
+
+        assert_eq!(result.errors(), &vec![]);
+        assert_eq!(result.value(), &json!({}));
+
+        let schema_str = "```rust\nfn main() {}\n```";
+        for input_str in ["```ru", "```rust\nfn ma", "```rust\nfn main() {}\n``"] {
+            let result = ValidatorTester::<CodeVsCodeValidator>::from_strs(schema_str, input_str)
+                .walk()
+                .goto_first_child_then_unwrap()
+                .validate_incomplete();
+
+            assert_eq!(result.errors(), &vec![], "Input {:?}", input_str);
+        }
+
+        // Lines that are already complete are still checked
+        let result = ValidatorTester::<CodeVsCodeValidator>::from_strs(
+            schema_str,
+            "```rust\nfn other() {}\n",
+        )
+        .walk()
+        .goto_first_child_then_unwrap()
+        .validate_incomplete();
+
+        assert_eq!(result.errors().len(), 1);
+    }
 }
@@ -7,6 +7,7 @@
 //! - `RepeatedMatcherParagraphVsParagraphValidator`: handles paragraphs that
 //!   contain a single repeating matcher, collecting matches across repeated
 //!   paragraphs before delegating to nested validation.
+use crate::mdschema::validation::inline_html::has_inline_html_child;
 use crate::mdschema::validation::matchers::matcher::MatcherKind;
 use crate::mdschema::validation::walkers::helpers::check_repeating_matchers::check_repeating_matchers;
 use crate::mdschema::validation::walkers::helpers::count_non_literal_matchers_in_children::count_non_literal_matchers_in_children;
@@ -15,12 +16,16 @@ use crate::mdschema::validation::validator_walker::ValidatorWalker;
 use crate::mdschema::validation::{
     errors::*,
     matchers::matcher::Matcher,
+    spans::subslice_offset,
     walkers::{
         ValidationResult,
         helpers::expected_input_nodes::expected_input_nodes,
         validators::{
-            Validator, ValidatorImpl, links::LinkVsLinkValidator,
-            textual::TextualVsTextualValidator,
+            Validator, ValidatorImpl,
+            html::HtmlVsHtmlValidator,
+            links::LinkVsLinkValidator,
+            matchers::{MultipleMatchersVsTextValidator, is_text_and_code_spans_only},
+            textual::{TextualVsTextualValidator, has_matcher_inside},
         },
     },
     ts_types::*,
@@ -29,7 +34,6 @@ use crate::mdschema::validation::{
 use crate::{compare_node_kinds_check, invariant_violation};
 use derive_builder::Builder;
 use log::trace;
-use serde_json::Value;
 use tree_sitter::TreeCursor;
 
 /// Validate a textual region of input against a textual region of schema.
@@ -51,8 +55,11 @@ use tree_sitter::TreeCursor;
 ///
 /// This works by:
 ///
-/// 1. Count the number of top level matchers in the schema. Find the first
-///    valid one. Then keep going, but if there are more than 1, error.
+/// 1. Count the number of top level matchers in the schema. If there is more
+///    than one and the container is just text and code spans, delegate to
+///    `MultipleMatchersVsTextValidator`. Otherwise more than one is an error.
+///    Text and code spans are matched that way too when the input has inline
+///    HTML that we're ignoring.
 /// 2. Count the number of nodes for both the input and schema using special
 ///    utility that takes into account literal matchers.
 /// 3. Walk the input and schema cursors at the same rate, and walk down ane
@@ -115,16 +122,34 @@ impl ValidatorImpl for ContainerVsContainerValidator {
                 return result;
             }
 
+        // Inline HTML the schema doesn't have is left out of the input's text,
+        // which is then matched as a whole
+        if walker.options().ignore_inline_html
+            && has_inline_html_child(&input_cursor.node())
+            && is_text_and_code_spans_only(&schema_cursor)
+        {
+            return MultipleMatchersVsTextValidator.validate(walker, got_eof);
+        }
+
         match count_non_literal_matchers_in_children(&schema_cursor, walker.schema_str()) {
-            Ok(non_literal_matchers_in_children)
-                if non_literal_matchers_in_children > 1 && got_eof =>
-            {
-                result.add_error(ValidationError::SchemaError(
-                    SchemaError::MultipleMatchersInNodeChildren {
-                        schema_index: schema_cursor.descendant_index(),
-                        received: non_literal_matchers_in_children,
-                    },
-                ))
+            Ok(non_literal_matchers_in_children) if non_literal_matchers_in_children > 1 => {
+                // Plain text with matchers in it can be matched as raw text,
+                // with the literals between the matchers as anchors.
+                if is_text_and_code_spans_only(&schema_cursor) {
+                    return MultipleMatchersVsTextValidator.validate(walker, got_eof);
+                }
+
+                if got_eof {
+                    result.add_error(ValidationError::SchemaError(
+                        SchemaError::MultipleMatchersInNodeChildren {
+                            schema_index: schema_cursor.descendant_index(),
+                            received: non_literal_matchers_in_children,
+                        },
+                    ));
+                    if walker.should_stop(&result) {
+                        return result;
+                    }
+                }
             }
             Ok(_) => {
                 // Exactly one non repeating matcher is OK!
@@ -157,15 +182,26 @@ impl ValidatorImpl for ContainerVsContainerValidator {
             (expected_input_node_count, actual_input_node_count)
         };
 
+        // Once the children don't line up, what they find is just noise
+        let mut children_mismatched = false;
         if (actual_input_node_count != expected_input_node_count) && got_eof {
-            result.add_error(ValidationError::SchemaViolation(
+            children_mismatched = true;
+            let missing_emphasis = if actual_input_node_count < expected_input_node_count {
+                missing_matcher_emphasis(&schema_cursor, &input_cursor, walker.schema_str())
+            } else {
+                None
+            };
+            result.add_error(missing_emphasis.unwrap_or(ValidationError::SchemaViolation(
                 SchemaViolationError::ChildrenLengthMismatch {
                     schema_index: schema_cursor.descendant_index(),
                     input_index: input_cursor.descendant_index(),
                     expected: expected_input_node_count.into(),
                     actual: actual_input_node_count,
                 },
-            ));
+            )));
+            if walker.should_stop(&result) {
+                return result;
+            }
         }
 
         // Go from the container to the first child in the container, and then
@@ -183,6 +219,7 @@ impl ValidatorImpl for ContainerVsContainerValidator {
                     // okay, we'll just wait!
                     return need_to_restart_result;
                 } else {
+                    children_mismatched = true;
                     result.add_error(ValidationError::SchemaViolation(
                         SchemaViolationError::MalformedNodeStructure {
                             schema_index: schema_cursor.descendant_index(),
@@ -190,6 +227,9 @@ impl ValidatorImpl for ContainerVsContainerValidator {
                             kind: MalformedStructureKind::InputHasChildSchemaDoesnt,
                         },
                     ));
+                    if walker.should_stop(&result) {
+                        return result;
+                    }
                 }
             }
             (true, false) => {
@@ -209,12 +249,24 @@ impl ValidatorImpl for ContainerVsContainerValidator {
             }
         }
 
+        // Mismatches of one child don't throw off the next, so we keep going
+        // after them, but past a few more are likely to follow from the
+        // earlier ones
+        let child_errors = if children_mismatched {
+            0
+        } else {
+            walker.options().max_errors_per_container.max(1)
+        };
+        let max_errors = result.errors().len() + child_errors;
         loop {
             let pair_result = if both_are_link_nodes(&schema_cursor.node(), &input_cursor.node())
                 || both_are_image_nodes(&schema_cursor.node(), &input_cursor.node())
             {
                 LinkVsLinkValidator
                     .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof)
+            } else if both_are_inline_html(&schema_cursor.node(), &input_cursor.node()) {
+                HtmlVsHtmlValidator
+                    .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof)
             } else {
                 let new_result = TextualVsTextualValidator
                     .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof);
@@ -223,8 +275,12 @@ impl ValidatorImpl for ContainerVsContainerValidator {
             };
 
             result.join_other_result(&pair_result);
+            result.truncate_errors(max_errors);
 
-            if !schema_cursor.goto_next_sibling() || !input_cursor.goto_next_sibling() {
+            if walker.should_stop(&result)
+                || !schema_cursor.goto_next_sibling()
+                || !input_cursor.goto_next_sibling()
+            {
                 break;
             }
         }
@@ -233,6 +289,62 @@ impl ValidatorImpl for ContainerVsContainerValidator {
     }
 }
 
+/// The error for plain text in the input where the schema wraps a matcher in
+/// emphasis, like `Widget` for `` **`product:/\w+/`** ``, which leaves the
+/// input with fewer children than the schema.
+///
+/// The emphasis is part of what the matcher requires, so rather than that the
+/// number of children differs, we report the kind of emphasis the input is
+/// missing, at the text it would have been in.
+///
+/// # Arguments
+///
+/// * `schema_cursor`: The schema's textual container.
+/// * `input_cursor`: The input's textual container.
+/// * `schema_str`: The full schema.
+fn missing_matcher_emphasis(
+    schema_cursor: &TreeCursor,
+    input_cursor: &TreeCursor,
+    schema_str: &str,
+) -> Option<ValidationError> {
+    let mut schema_cursor = schema_cursor.clone();
+    if !schema_cursor.goto_first_child() {
+        return None;
+    }
+    let mut position = 0;
+    while !(is_emphasis_node(&schema_cursor.node())
+        && has_matcher_inside(&schema_cursor, schema_str))
+    {
+        if !schema_cursor.goto_next_sibling() {
+            return None;
+        }
+        position += 1;
+    }
+
+    // The text would have run on into where the emphasis should be
+    let mut input_cursor = input_cursor.clone();
+    if !input_cursor.goto_first_child() {
+        return None;
+    }
+    for _ in 0..position {
+        if !input_cursor.goto_next_sibling() {
+            break;
+        }
+    }
+    if !is_text_node(&input_cursor.node()) {
+        return None;
+    }
+
+    Some(ValidationError::SchemaViolation(
+        SchemaViolationError::NodeTypeMismatch {
+            schema_index: schema_cursor.descendant_index(),
+            input_index: input_cursor.descendant_index(),
+            expected: schema_cursor.node().kind().to_string(),
+            actual: input_cursor.node().kind().to_string(),
+        },
+    ))
+}
+
 /// We special case paragraphs that are just a single code node that is a
 /// repeated matcher. This function attempts to match what we call a repeated
 /// matcher paragraph.
@@ -307,7 +419,7 @@ impl ValidatorImpl for RepeatedMatcherParagraphVsParagraphValidator {
             );
         }
 
-        match Matcher::try_from_schema_cursor(&schema_cursor, walker.schema_str()) {
+        match walker.matcher_at(&schema_cursor) {
             Ok(matcher) if matcher.is_repeated() => {
                 let mut matches = vec![];
 
@@ -319,7 +431,18 @@ impl ValidatorImpl for RepeatedMatcherParagraphVsParagraphValidator {
                     let input_paragraph_text =
                         get_node_text(&input_cursor.node(), walker.input_str());
 
-                    if let Some(matched) = matcher.match_str(input_paragraph_text) { matches.push(matched) }
+                    if let Some(matched) = matcher.match_str(input_paragraph_text) {
+                        let Some(offset) = subslice_offset(input_paragraph_text, matched) else {
+                            invariant_violation!(
+                                result,
+                                &schema_cursor,
+                                &input_cursor,
+                                "a match should be a slice of the text it is in"
+                            );
+                        };
+                        let matched_start = input_cursor.node().start_byte() + offset;
+                        matches.push((matched, matched_start, input_cursor.descendant_index()))
+                    }
 
                     let prev_sibling = input_cursor.clone();
                     if input_cursor.goto_next_sibling() && is_paragraph_node(&input_cursor.node()) {
@@ -352,15 +475,20 @@ impl ValidatorImpl for RepeatedMatcherParagraphVsParagraphValidator {
                 result.sync_cursor_pos(&next_schema_cursor, &input_cursor);
 
                 if let Some(id) = matcher.id() {
-                    result.set_match(
-                        id,
-                        serde_json::Value::Array(
-                            matches
-                                .iter()
-                                .map(|s| Value::String(s.to_string()))
-                                .collect(),
-                        ),
-                    );
+                    let values = matches
+                        .iter()
+                        .map(|(matched, matched_start, input_index)| {
+                            result.typed_value(
+                                &matcher,
+                                walker.captured_value(matched, *matched_start),
+                                schema_cursor.descendant_index(),
+                                *input_index,
+                            )
+                        })
+                        .collect::<Option<Vec<_>>>();
+                    if let Some(values) = values {
+                        result.set_match(id, serde_json::Value::Array(values));
+                    }
                 }
 
                 result
@@ -402,7 +530,7 @@ impl ValidatorImpl for RepeatedMatcherParagraphVsParagraphValidator {
 ///
 /// * `schema_cursor`: The cursor pointing to a paragraph that might be a repeated matcher paragraph.
 /// * `schema_str`: The full input document (so far).
-fn is_repeated_matcher_paragraph(schema_cursor: &TreeCursor, schema_str: &str) -> bool {
+pub(crate) fn is_repeated_matcher_paragraph(schema_cursor: &TreeCursor, schema_str: &str) -> bool {
     // We must be at a paragraph node
     if !is_paragraph_node(&schema_cursor.node()) {
         trace!("is_repeated_matcher_paragraph: not a paragraph node, returning false");
@@ -589,6 +717,49 @@ mod tests {
         assert_eq!(value, json!({"a": "a", "b": "b"}));
     }
 
+    #[test]
+    fn test_paragraph_with_three_wrong_words() {
+        let schema_str = "Hello *big* world and **bold** text";
+        let input_str = "Helo *big* wrld and **bold** txt";
+
+        let result =
+            ValidatorTester::<ContainerVsContainerValidator>::from_strs(schema_str, input_str)
+                .walk()
+                .goto_first_child_then_unwrap()
+                .peek_nodes(|(s, i)| assert!(both_are_paragraphs(s, i)))
+                .validate_complete();
+
+        assert_eq!(result.errors().len(), 3, "{:?}", result.errors());
+        assert!(result.errors().iter().all(|error| matches!(
+            error,
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch { .. })
+        )));
+    }
+
+    #[test]
+    fn test_paragraph_with_wrong_structure() {
+        let schema_str = "Hello *big* world";
+        let input_str = "Hello big **world** now *x*";
+
+        let result =
+            ValidatorTester::<ContainerVsContainerValidator>::from_strs(schema_str, input_str)
+                .walk()
+                .goto_first_child_then_unwrap()
+                .peek_nodes(|(s, i)| assert!(both_are_paragraphs(s, i)))
+                .validate_complete();
+
+        // The children don't line up, so only the paragraph is reported
+        assert_eq!(result.errors().len(), 1, "{:?}", result.errors());
+        assert!(matches!(
+            &result.errors()[0],
+            ValidationError::SchemaViolation(SchemaViolationError::ChildrenLengthMismatch {
+                expected,
+                actual: 4,
+                ..
+            }) if *expected == 3
+        ));
+    }
+
     #[test]
     fn test_paragraph_vs_repeated_matcher_paragraph_simple() {
         let schema_str = r#"
@@ -691,7 +862,7 @@ test
                 .peek_nodes(|(s, i)| assert!(both_are_paragraphs(s, i)))
                 .validate_complete();
 
-        // Should have no errors since "test" matches the pattern "^test"
+        // Should have no errors since "test" matches the pattern "test"
         assert_eq!(result.errors(), vec![]);
         assert_eq!(*result.value(), json!({"data": "test"}));
     }
@@ -712,7 +883,7 @@ foo
                 .peek_nodes(|(s, i)| assert!(both_are_paragraphs(s, i)))
                 .validate_complete();
 
-        // Should have an error since "foo" doesn't match the pattern "^test"
+        // Should have an error since "foo" doesn't match the pattern "test"
         assert!(!result.errors().is_empty());
     }
 
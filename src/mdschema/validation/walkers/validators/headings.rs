@@ -3,14 +3,21 @@
 //! Types:
 //! - `HeadingVsHeadingValidator`: confirms heading kinds align and delegates
 //!   content checks to textual container validation.
+//!
+//! ATX headings (`# Title`) and setext headings (`Title` underlined with
+//! `===` or `---`) are validated the same way, once we are at their content.
+//! Unless `ValidateOptions::strict_heading_form` is set, either form matches
+//! the other at the same level, and the closing hashes of an ATX heading,
+//! like the ` ##` of `## Section ##`, are left out of its content.
+use derive_builder::Builder;
 use log::trace;
 use tree_sitter::TreeCursor;
 
 use crate::invariant_violation;
-use crate::mdschema::validation::errors::ValidationError;
+use crate::mdschema::validation::errors::{SchemaViolationError, ValidationError};
 use crate::mdschema::validation::walkers::ValidationResult;
 use crate::mdschema::validation::walkers::helpers::compare_node_kinds::compare_node_kinds;
-use crate::mdschema::validation::walkers::validators::containers::ContainerVsContainerValidator;
+use crate::mdschema::validation::walkers::validators::containers::ContainerVsContainerValidatorBuilder;
 use crate::mdschema::validation::walkers::validators::{Validator, ValidatorImpl};
 use crate::mdschema::validation::ts_types::*;
 use crate::mdschema::validation::ts_utils::waiting_at_end;
@@ -19,9 +26,16 @@ use crate::mdschema::validation::validator_walker::ValidatorWalker;
 /// Validate two headings.
 ///
 /// Checks that they are the same kind of heading, and and then delegates to
-/// `TextualContainerVsTextualContainerValidator::validate`.
-#[derive(Default)]
-pub(super) struct HeadingVsHeadingValidator;
+/// `ContainerVsContainerValidator::validate`. Their content is validated like
+/// a paragraph, so it can have several matchers, with literals before, between
+/// and after them, like `` # `id:/\d+/`: `title:/.+/` ``.
+///
+/// Headings may only contain a repeated matcher when they start a repeating
+/// section, in which case `allow_repeating` is set.
+#[derive(Default, Builder)]
+pub(super) struct HeadingVsHeadingValidator {
+    allow_repeating: bool,
+}
 
 impl ValidatorImpl for HeadingVsHeadingValidator {
     fn validate_impl(&self, walker: &ValidatorWalker, got_eof: bool) -> ValidationResult {
@@ -33,12 +47,13 @@ impl ValidatorImpl for HeadingVsHeadingValidator {
 
         // Both should be the start of headings
         #[cfg(feature = "invariant_violations")]
-        if !is_heading_node(&schema_cursor.node()) || !is_heading_node(&input_cursor.node()) {
+        if !is_any_heading_node(&schema_cursor.node()) || !is_any_heading_node(&input_cursor.node())
+        {
             invariant_violation!(
                 result,
                 &schema_cursor,
                 &input_cursor,
-                "heading validation expects atx_heading nodes"
+                "heading validation expects atx_heading or setext_heading nodes"
             );
         }
 
@@ -50,7 +65,8 @@ impl ValidatorImpl for HeadingVsHeadingValidator {
             walker.input_str(),
         ) {
             if waiting_at_end(got_eof, walker.input_str(), &input_cursor)
-                && both_are_headings(&schema_cursor.node(), &input_cursor.node())
+                && is_any_heading_node(&schema_cursor.node())
+                && is_any_heading_node(&input_cursor.node())
             {
             } else {
                 result.add_error(error);
@@ -59,6 +75,11 @@ impl ValidatorImpl for HeadingVsHeadingValidator {
         };
         trace!("Node kinds mismatched");
 
+        if let Some(error) = heading_form_mismatch(walker, &schema_cursor, &input_cursor) {
+            result.add_error(error);
+            return result;
+        }
+
         // Go to the actual heading content
         {
             let mut failed_to_walk_to_heading = false;
@@ -100,11 +121,39 @@ impl ValidatorImpl for HeadingVsHeadingValidator {
         }
 
         // Now that we're at the heading content, use `validate_text_vs_text`
-        ContainerVsContainerValidator::default()
+        ContainerVsContainerValidatorBuilder::default()
+            .allow_repeating(self.allow_repeating)
+            .build()
+            .unwrap()
             .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof)
     }
 }
 
+/// With `ValidateOptions::strict_heading_form`, check that the input heading
+/// is ATX if the schema heading is and setext if the schema heading is, with
+/// both cursors at the headings. Otherwise `compare_node_kinds` only checks
+/// that they are of the same level.
+fn heading_form_mismatch(
+    walker: &ValidatorWalker,
+    schema_cursor: &TreeCursor,
+    input_cursor: &TreeCursor,
+) -> Option<ValidationError> {
+    let schema_node = schema_cursor.node();
+    let input_node = input_cursor.node();
+    if !walker.options().strict_heading_form || schema_node.kind() == input_node.kind() {
+        return None;
+    }
+
+    Some(ValidationError::SchemaViolation(
+        SchemaViolationError::NodeTypeMismatch {
+            schema_index: schema_cursor.descendant_index(),
+            input_index: input_cursor.descendant_index(),
+            expected: schema_node.kind().into(),
+            actual: input_node.kind().into(),
+        },
+    ))
+}
+
 fn ensure_at_heading_content(cursor: &mut TreeCursor) -> Result<bool, ValidationError> {
     // Headings look like this:
     //
@@ -112,9 +161,17 @@ fn ensure_at_heading_content(cursor: &mut TreeCursor) -> Result<bool, Validation
     // │  ├─ (atx_h2_marker)
     // │  └─ (heading_content)
     // │     └─ (text)
-    if is_heading_node(&cursor.node()) {
+    //
+    // (setext_heading)
+    // │  ├─ (heading_content)
+    // │  │  └─ (text)
+    // │  └─ (setext_h1_underline)
+    if is_any_heading_node(&cursor.node()) {
         cursor.goto_first_child();
         ensure_at_heading_content(cursor)
+    } else if is_textual_container_node(&cursor.node()) {
+        // Setext headings start with their content
+        Ok(true)
     } else if is_marker_node(&cursor.node()) {
         if cursor.goto_next_sibling() {
             #[cfg(feature = "invariant_violations")]
@@ -143,7 +200,7 @@ mod tests {
     use crate::mdschema::validation::{
         errors::{NodeContentMismatchKind, SchemaViolationError},
         node_pos_pair::NodePosPair,
-        ts_utils::parse_markdown,
+        ts_utils::{find_node_by_index, get_node_text, parse_markdown},
     };
     use serde_json::json;
 
@@ -357,5 +414,169 @@ mod tests {
         );
         assert_eq!(result.value(), &json!({}));
     }
+
+    #[test]
+    fn test_validate_heading_vs_heading_matchers_around_literal() {
+        let schema_str = "# `id:/\\d+/`: `title:/.+/`";
+        let input_str = "# 42: The answer";
+
+        let result = ValidatorTester::<HeadingVsHeadingValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_headings(s, i)))
+            .validate_complete();
+
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+        assert_eq!(result.value(), &json!({"id": "42", "title": "The answer"}));
+    }
+
+    #[test]
+    fn test_validate_heading_vs_heading_matcher_then_literal() {
+        let schema_str = "# `name:/\\w+/` release notes";
+        let input_str = "# Jammy release notes";
+
+        let result = ValidatorTester::<HeadingVsHeadingValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_headings(s, i)))
+            .validate_complete();
+
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+        assert_eq!(result.value(), &json!({"name": "Jammy"}));
+    }
+
+    #[test]
+    fn test_validate_heading_vs_heading_prefix_matchers_and_suffix() {
+        let schema_str = "# Release `major:/\\d+/`.`minor:/\\d+/` (stable)";
+        let input_str = "# Release 1.20 (stable)";
+
+        let result = ValidatorTester::<HeadingVsHeadingValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_headings(s, i)))
+            .validate_complete();
+
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+        assert_eq!(result.value(), &json!({"major": "1", "minor": "20"}));
+    }
+
+    #[test]
+    fn test_validate_heading_vs_heading_matchers_around_wrong_literal() {
+        let schema_str = "# `id:/\\d+/`: `title:/.+/`";
+        let input_str = "# 42 - The answer";
+
+        let result = ValidatorTester::<HeadingVsHeadingValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_headings(s, i)))
+            .validate_complete();
+
+        let [
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                schema_index,
+                input_index,
+                expected,
+                actual,
+                kind: NodeContentMismatchKind::Literal,
+            }),
+        ] = result.errors()
+        else {
+            panic!("Expected a literal mismatch, got {:?}", result.errors());
+        };
+        // The error points at the literal in the schema, and the heading
+        // content in the input
+        let schema_tree = parse_markdown(schema_str).unwrap();
+        let schema_node = find_node_by_index(schema_tree.root_node(), *schema_index);
+        assert!(get_node_text(&schema_node, schema_str).starts_with(':'));
+        let input_tree = parse_markdown(input_str).unwrap();
+        let input_node = find_node_by_index(input_tree.root_node(), *input_index);
+        assert!(is_heading_content_node(&input_node));
+        assert_eq!(expected, ": ");
+        assert_eq!(actual, " -");
+        assert_eq!(result.value(), &json!({"id": "42"}));
+    }
+
+    #[test]
+    fn test_ensure_at_setext_heading_content() {
+        let input_str = "Setext heading\n===\n";
+        let input_tree = parse_markdown(input_str).unwrap();
+        let mut input_cursor = input_tree.walk();
+
+        input_cursor.goto_first_child();
+        assert!(is_setext_heading_node(&input_cursor.node()));
+
+        assert!(ensure_at_heading_content(&mut input_cursor).unwrap());
+        assert!(is_textual_container_node(&input_cursor.node()));
+    }
+
+    #[test]
+    fn test_validate_setext_heading_vs_setext_heading_with_matchers() {
+        let schema_str = "Chapter `number:/\\d+/`: `title:/.+/`\n===\n";
+        let input_str = "Chapter 3: The end\n===\n";
+
+        let result = ValidatorTester::<HeadingVsHeadingValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_setext_headings(s, i)))
+            .validate_complete();
+
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+        assert_eq!(result.value(), &json!({"number": "3", "title": "The end"}));
+    }
+
+    #[test]
+    fn test_validate_setext_heading_vs_setext_heading_wrong_level() {
+        let schema_str = "Heading\n===\n";
+        let input_str = "Heading\n---\n";
+
+        let result = ValidatorTester::<HeadingVsHeadingValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_setext_headings(s, i)))
+            .validate_complete();
+
+        assert_eq!(
+            result.errors(),
+            &[ValidationError::SchemaViolation(
+                SchemaViolationError::NodeTypeMismatch {
+                    schema_index: 1,
+                    input_index: 1,
+                    expected: "setext_heading(setext_h1_underline)".to_string(),
+                    actual: "setext_heading(setext_h2_underline)".to_string(),
+                }
+            )]
+        );
+        assert_eq!(result.value(), &json!({}));
+    }
+
+    #[test]
+    fn test_validate_heading_vs_setext_heading_with_matcher() {
+        let schema_str = "# Chapter `number:/\\d+/`\n";
+        let input_str = "Chapter 3\n===\n";
+
+        let result = ValidatorTester::<HeadingVsHeadingValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_any_headings(s, i)))
+            .validate_complete();
+
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+        assert_eq!(result.value(), &json!({"number": "3"}));
+    }
+
+    #[test]
+    fn test_validate_setext_heading_vs_heading_with_closing_hashes() {
+        let schema_str = "Chapter `number:/\\d+/`\n===\n";
+        let input_str = "# Chapter 3 ##\n";
+
+        let result = ValidatorTester::<HeadingVsHeadingValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_any_headings(s, i)))
+            .validate_complete();
+
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+        assert_eq!(result.value(), &json!({"number": "3"}));
+    }
     // TODO: tests for got_eof=false
 }
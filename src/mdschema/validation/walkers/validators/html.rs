@@ -0,0 +1,136 @@
+//! HTML validator for node-walker comparisons.
+//!
+//! Types:
+//! - `HtmlVsHtmlValidator`: compares HTML blocks and inline HTML in the schema
+//!   with the HTML at the same place in the input, as text.
+use crate::compare_node_kinds_check;
+use crate::mdschema::validation::errors::{
+    NodeContentMismatchKind, SchemaViolationError, ValidationError,
+};
+use crate::mdschema::validation::text_normalization::NormalizedLines;
+use crate::mdschema::validation::ts_utils::{get_node_text, waiting_at_end};
+use crate::mdschema::validation::validator_walker::ValidatorWalker;
+use crate::mdschema::validation::walkers::ValidationResult;
+use crate::mdschema::validation::walkers::validators::ValidatorImpl;
+
+/// Validator for HTML nodes.
+///
+/// HTML isn't parsed any further than markdown does, so the schema's HTML is
+/// required in the input exactly, besides the whitespace at the ends of its
+/// lines unless we compare line endings strictly.
+#[derive(Default)]
+pub(super) struct HtmlVsHtmlValidator;
+
+impl ValidatorImpl for HtmlVsHtmlValidator {
+    fn validate_impl(&self, walker: &ValidatorWalker, got_eof: bool) -> ValidationResult {
+        let mut result =
+            ValidationResult::from_cursors(walker.schema_cursor(), walker.input_cursor());
+
+        let schema_cursor = walker.schema_cursor();
+        let input_cursor = walker.input_cursor();
+
+        compare_node_kinds_check!(
+            schema_cursor,
+            input_cursor,
+            walker.schema_str(),
+            walker.input_str(),
+            result
+        );
+
+        let schema_text = get_node_text(&schema_cursor.node(), walker.schema_str()).trim_end();
+        let input_text = get_node_text(&input_cursor.node(), walker.input_str()).trim_end();
+        let (schema_lines, input_lines) = match walker.options().strict_line_endings {
+            true => (
+                NormalizedLines::exact(schema_text),
+                NormalizedLines::exact(input_text),
+            ),
+            false => (
+                NormalizedLines::new(schema_text),
+                NormalizedLines::new(input_text),
+            ),
+        };
+
+        // The rest of the HTML may still be on its way
+        let matches = if waiting_at_end(got_eof, walker.input_str(), input_cursor) {
+            schema_lines.as_str().starts_with(input_lines.as_str())
+        } else {
+            schema_lines.as_str() == input_lines.as_str()
+        };
+
+        if !matches {
+            result.add_error(ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch {
+                    schema_index: schema_cursor.descendant_index(),
+                    input_index: input_cursor.descendant_index(),
+                    expected: schema_lines.as_str().to_string(),
+                    actual: input_lines.as_str().to_string(),
+                    kind: NodeContentMismatchKind::Literal,
+                },
+            ));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_utils::ValidatorTester;
+    use super::HtmlVsHtmlValidator;
+    use crate::mdschema::validation::{
+        errors::{NodeContentMismatchKind, SchemaViolationError, ValidationError},
+        node_pos_pair::NodePosPair,
+    };
+
+    #[test]
+    fn test_validate_html_block_vs_html_block() {
+        let schema_str = "<div align=\"center\">\n  <img src=\"logo.png\">\n</div>\n";
+        let input_str = "<div align=\"center\">  \r\n  <img src=\"logo.png\">\r\n</div>\r\n";
+
+        let result = ValidatorTester::<HtmlVsHtmlValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert_eq!(*result.farthest_reached_pos(), NodePosPair::from_pos(1, 1));
+        assert_eq!(result.errors(), vec![]);
+    }
+
+    #[test]
+    fn test_validate_html_block_vs_html_block_mismatch() {
+        let schema_str = "<div align=\"center\">\n</div>\n";
+        let input_str = "<div align=\"left\">\n</div>\n";
+
+        let result = ValidatorTester::<HtmlVsHtmlValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert_eq!(*result.farthest_reached_pos(), NodePosPair::from_pos(1, 1));
+        assert_eq!(
+            result.errors(),
+            vec![ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch {
+                    schema_index: 1,
+                    input_index: 1,
+                    expected: "<div align=\"center\">\n</div>".to_string(),
+                    actual: "<div align=\"left\">\n</div>".to_string(),
+                    kind: NodeContentMismatchKind::Literal,
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_validate_incomplete_html_block() {
+        let schema_str = "<div align=\"center\">\n</div>\n";
+        let input_str = "<div align=\"cen";
+
+        let result = ValidatorTester::<HtmlVsHtmlValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .validate_incomplete();
+
+        assert_eq!(result.errors(), vec![]);
+    }
+}
@@ -3,21 +3,28 @@
 //! Types:
 //! - `LinkVsLinkValidator`: checks link-like node kinds, destinations, and
 //!   visible text with optional matcher-based comparisons.
-use serde_json::json;
+//!
+//! The text of a link (or description of an image) may be a curly matcher,
+//! like `[{title:/.+/}](...)`, or a code span matcher on its own, like
+//! `` [`title:/.+/`](...) ``, which matches the whole text.
 use tree_sitter::TreeCursor;
 
 use crate::invariant_violation;
 use crate::mdschema::validation::errors::{
     NodeContentMismatchKind, SchemaError, SchemaViolationError, ValidationError,
 };
-use crate::mdschema::validation::matchers::matcher::MatcherError;
+use crate::mdschema::validation::heading_slugs::collect_heading_slugs;
+use crate::mdschema::validation::link_references::{LinkReferences, label_text};
+use crate::mdschema::validation::matchers::matcher::{Matcher, MatcherError, RegexLimits};
 use crate::mdschema::validation::node_pos_pair::NodePosPair;
+use crate::mdschema::validation::spans::{LineStarts, captured_value, subslice_offset};
 use crate::mdschema::validation::walkers::ValidationResult;
 use crate::mdschema::validation::walkers::helpers::compare_text_contents::compare_text_contents;
 use crate::mdschema::validation::walkers::helpers::curly_matchers::extract_matcher_from_curly_delineated_text;
 use crate::mdschema::validation::walkers::validators::ValidatorImpl;
 use crate::mdschema::validation::ts_types::*;
 use crate::mdschema::validation::ts_utils::{get_node_text, waiting_at_end};
+use crate::mdschema::validation::validator::ValidateOptions;
 use crate::mdschema::validation::validator_walker::ValidatorWalker;
 
 // Use the macro from node_walker module
@@ -98,6 +105,8 @@ impl ValidatorImpl for LinkVsLinkValidator {
             walker.schema_str(),
             walker.input_str(),
             got_eof,
+            walker.options(),
+            walker.input_line_starts(),
         );
         result.join_other_result(&child_result);
         if child_result.has_errors() {
@@ -108,45 +117,72 @@ impl ValidatorImpl for LinkVsLinkValidator {
             result.keep_farther_pos(&pos);
         }
 
-        #[cfg(feature = "invariant_violations")]
-        if !schema_cursor.goto_next_sibling() || !input_cursor.goto_next_sibling() {
-            invariant_violation!(
-                result,
-                &schema_cursor,
-                &input_cursor,
-                "link nodes must have a destination"
-            );
-        }
-
-        compare_node_kinds_check!(
-            schema_cursor,
-            input_cursor,
-            walker.schema_str(),
-            walker.input_str(),
-            result
-        );
-
-        if is_link_destination_node(&schema_cursor.node()) {
-            let destination_result = validate_link_destination(
-                &schema_cursor,
-                &input_cursor,
-                walker.schema_str(),
-                walker.input_str(),
-                got_eof,
-            );
-            result.join_other_result(&destination_result);
-            // Don't return early since we want to move the cursor (20 lines down) first
-        } else {
-            let child_result = compare_link_child_text(
-                &schema_cursor,
-                &input_cursor,
-                walker.schema_str(),
-                walker.input_str(),
-                got_eof,
-            );
-            result.join_other_result(&child_result);
-            if child_result.has_errors() {
-                return result;
+        match (
+            link_target(&schema_cursor, walker.schema_str()),
+            link_target(&input_cursor, walker.input_str()),
+        ) {
+            (LinkTarget::Inline(schema_target_cursor), LinkTarget::Inline(input_target_cursor)) => {
+                schema_cursor = schema_target_cursor;
+                input_cursor = input_target_cursor;
+
+                compare_node_kinds_check!(
+                    schema_cursor,
+                    input_cursor,
+                    walker.schema_str(),
+                    walker.input_str(),
+                    result
+                );
+
+                if is_link_destination_node(&schema_cursor.node()) {
+                    let destination_result = validate_link_destination(
+                        &schema_cursor,
+                        &input_cursor,
+                        walker.schema_str(),
+                        walker.input_str(),
+                        got_eof,
+                        walker.options(),
+                        walker.input_line_starts(),
+                    );
+                    result.join_other_result(&destination_result);
+                    // Don't return early since we want to move the cursor (20 lines down) first
+
+                    if let Some(error) = broken_anchor_error(
+                        walker,
+                        &Destination::from_cursor(&schema_cursor, walker.schema_str()),
+                        input_cursor.descendant_index(),
+                        got_eof,
+                    ) {
+                        result.add_error(error);
+                    }
+                } else {
+                    let child_result = compare_link_child_text(
+                        &schema_cursor,
+                        &input_cursor,
+                        walker.schema_str(),
+                        walker.input_str(),
+                        got_eof,
+                        walker.options(),
+                        walker.input_line_starts(),
+                    );
+                    result.join_other_result(&child_result);
+                    if child_result.has_errors() {
+                        return result;
+                    }
+                }
+            }
+            (schema_target, input_target) => {
+                // At least one side is reference-style, so compare what they
+                // resolve to
+                let is_partial_match =
+                    waiting_at_end(got_eof, walker.input_str(), &link_input_cursor);
+                let destination_result = validate_resolved_destination(
+                    walker,
+                    &schema_target,
+                    &input_target,
+                    got_eof,
+                    is_partial_match,
+                );
+                result.join_data(destination_result.data());
             }
         }
 
@@ -176,12 +212,197 @@ fn ensure_at_link_start(cursor: &mut TreeCursor) -> Result<(), ValidationError>
     );
 }
 
+/// Where a link points, found from the node after its text.
+enum LinkTarget<'a> {
+    /// `[text](destination)`, with the cursor at the node after the text.
+    Inline(TreeCursor<'a>),
+    /// `[text][label]`, `[label][]` or `[label]`, with the descendant index of
+    /// the node holding the label.
+    Reference { label: &'a str, index: usize },
+}
+
+/// Find where a link points.
+///
+/// # Arguments
+///
+/// * `text_cursor`: The cursor pointing at the link text or image description.
+/// * `src`: The full document the cursor is in.
+fn link_target<'a>(text_cursor: &TreeCursor<'a>, src: &'a str) -> LinkTarget<'a> {
+    let mut cursor = text_cursor.clone();
+
+    if !cursor.goto_next_sibling() {
+        // Collapsed and shortcut references use the text as the label
+        return LinkTarget::Reference {
+            label: label_text(get_node_text(&text_cursor.node(), src)),
+            index: text_cursor.descendant_index(),
+        };
+    }
+
+    if is_link_label_node(&cursor.node()) {
+        LinkTarget::Reference {
+            label: label_text(get_node_text(&cursor.node(), src)),
+            index: cursor.descendant_index(),
+        }
+    } else {
+        LinkTarget::Inline(cursor)
+    }
+}
+
+/// A link destination's text, wherever in the document it was written.
+struct Destination<'a> {
+    text: &'a str,
+    start_byte: usize,
+    index: usize,
+}
+
+impl<'a> Destination<'a> {
+    fn from_cursor(cursor: &TreeCursor, src: &'a str) -> Self {
+        let mut text_cursor = cursor.clone();
+        if is_link_destination_node(&text_cursor.node()) {
+            text_cursor.goto_first_child();
+        }
+
+        Self {
+            text: get_node_text(&text_cursor.node(), src),
+            start_byte: text_cursor.node().start_byte(),
+            index: text_cursor.descendant_index(),
+        }
+    }
+
+    /// Resolve a link target to its destination.
+    ///
+    /// Returns the label if it is a reference with no definition.
+    fn resolve(
+        target: &LinkTarget<'a>,
+        references: &'a LinkReferences,
+        src: &'a str,
+    ) -> Result<Self, &'a str> {
+        match target {
+            LinkTarget::Inline(cursor) => Ok(Self::from_cursor(cursor, src)),
+            LinkTarget::Reference { label, .. } => references
+                .resolve(label)
+                .map(|definition| Self {
+                    text: &definition.destination,
+                    start_byte: definition.start_byte,
+                    index: definition.descendant_index,
+                })
+                .ok_or(*label),
+        }
+    }
+}
+
+fn target_index(target: &LinkTarget) -> usize {
+    match target {
+        LinkTarget::Inline(cursor) => cursor.descendant_index(),
+        LinkTarget::Reference { index, .. } => *index,
+    }
+}
+
+/// Validate the destinations of two links when at least one of them is
+/// reference-style, by resolving both through their document's link
+/// reference definitions.
+fn validate_resolved_destination(
+    walker: &ValidatorWalker,
+    schema_target: &LinkTarget,
+    input_target: &LinkTarget,
+    got_eof: bool,
+    is_partial_match: bool,
+) -> ValidationResult {
+    let schema_index = target_index(schema_target);
+    let input_index = target_index(input_target);
+    let mut result = ValidationResult::from_descendant_indexes(schema_index, input_index);
+
+    let schema_destination =
+        Destination::resolve(schema_target, walker.schema_references(), walker.schema_str());
+    let input_destination =
+        Destination::resolve(input_target, walker.input_references(), walker.input_str());
+
+    let (schema_destination, input_destination) = match (schema_destination, input_destination) {
+        (Ok(schema_destination), Ok(input_destination)) => (schema_destination, input_destination),
+        // The definition may still be on its way
+        (Ok(_), Err(_)) if !got_eof => return result,
+        (Err(label), _) | (_, Err(label)) => {
+            result.add_error(ValidationError::SchemaViolation(
+                SchemaViolationError::UnresolvedLinkReference {
+                    schema_index,
+                    input_index,
+                    label: label.to_string(),
+                },
+            ));
+            return result;
+        }
+    };
+
+    if let Some(error) = broken_anchor_error(walker, &schema_destination, input_index, got_eof) {
+        result.add_error(error);
+    }
+
+    if let Some(matcher_result) = compare_destination_matchers(
+        &schema_destination,
+        &input_destination,
+        walker.input_str(),
+        is_partial_match,
+        walker.options(),
+        walker.input_line_starts(),
+    ) {
+        return matcher_result;
+    }
+
+    let matches = if is_partial_match {
+        schema_destination.text.starts_with(input_destination.text)
+    } else {
+        schema_destination.text == input_destination.text
+    };
+    if !matches {
+        result.add_error(ValidationError::SchemaViolation(
+            SchemaViolationError::NodeContentMismatch {
+                schema_index: schema_destination.index,
+                input_index: input_destination.index,
+                expected: schema_destination.text.into(),
+                actual: input_destination.text.into(),
+                kind: NodeContentMismatchKind::Literal,
+            },
+        ));
+    }
+
+    result
+}
+
+/// Check that a schema link to a fragment, like `[Usage](#usage)`, points at a
+/// heading of the input. We can only tell once we have the whole input.
+fn broken_anchor_error(
+    walker: &ValidatorWalker,
+    schema_destination: &Destination,
+    input_index: usize,
+    got_eof: bool,
+) -> Option<ValidationError> {
+    let anchor = schema_destination.text.strip_prefix('#')?;
+    if !got_eof {
+        return None;
+    }
+
+    let headings = collect_heading_slugs(walker.input_cursor(), walker.input_str(), got_eof);
+    if headings.iter().any(|heading| heading.slug == anchor) {
+        return None;
+    }
+
+    Some(ValidationError::SchemaViolation(
+        SchemaViolationError::BrokenAnchor {
+            schema_index: schema_destination.index,
+            input_index,
+            anchor: anchor.to_string(),
+        },
+    ))
+}
+
 fn compare_link_child_text(
     schema_cursor: &TreeCursor,
     input_cursor: &TreeCursor,
     schema_str: &str,
     input_str: &str,
     got_eof: bool,
+    options: &ValidateOptions,
+    input_line_starts: Option<&LineStarts>,
 ) -> ValidationResult {
     let mut result = ValidationResult::from_cursors(schema_cursor, input_cursor);
 
@@ -198,6 +419,23 @@ fn compare_link_child_text(
         );
     }
 
+    if let Some(matcher_result) =
+        description_matcher(&schema_text_cursor, schema_str, &options.regex_limits)
+    {
+        let is_partial_match = waiting_at_end(got_eof, input_str, input_cursor);
+        let matcher_result = compare_description_matcher(
+            matcher_result,
+            schema_text_cursor.descendant_index(),
+            input_cursor,
+            input_str,
+            is_partial_match,
+            options,
+            input_line_starts,
+        );
+        result.join_data(matcher_result.data());
+        return result;
+    }
+
     let is_partial_match = waiting_at_end(got_eof, input_str, &input_text_cursor);
     let text_result = compare_text_contents(
         schema_str,
@@ -206,6 +444,8 @@ fn compare_link_child_text(
         &input_text_cursor,
         is_partial_match,
         false,
+        options,
+        input_line_starts,
     );
     // Only take errors and values, not position (parent already tracks position at link level)
     result.join_data(text_result.data());
@@ -213,12 +453,98 @@ fn compare_link_child_text(
     result
 }
 
+/// The matcher a schema link text or image description is made of, when it
+/// is a lone code span like `` [`title:/.+/`](...) ``.
+///
+/// # Arguments
+///
+/// * `schema_text_cursor`: The cursor pointing at the first child of the link
+///   text or image description.
+/// * `schema_str`: The full schema document.
+/// * `limits`: The limits to compile the matcher's regex within.
+fn description_matcher(
+    schema_text_cursor: &TreeCursor,
+    schema_str: &str,
+    limits: &RegexLimits,
+) -> Option<Result<Matcher, MatcherError>> {
+    if !is_inline_code_node(&schema_text_cursor.node())
+        || schema_text_cursor.node().next_sibling().is_some()
+    {
+        return None;
+    }
+
+    match Matcher::try_from_schema_cursor_with_limits(schema_text_cursor, schema_str, limits) {
+        Err(MatcherError::WasLiteralCode) => None,
+        matcher_result => Some(matcher_result),
+    }
+}
+
+/// Match a code span matcher against the whole text of an input link or image
+/// description, formatting included.
+fn compare_description_matcher(
+    matcher_result: Result<Matcher, MatcherError>,
+    schema_index: usize,
+    input_cursor: &TreeCursor,
+    input_str: &str,
+    is_partial_match: bool,
+    options: &ValidateOptions,
+    input_line_starts: Option<&LineStarts>,
+) -> ValidationResult {
+    let input_index = input_cursor.descendant_index();
+    let mut result = ValidationResult::from_descendant_indexes(schema_index, input_index);
+
+    let matcher = match matcher_result {
+        Ok(matcher) => matcher,
+        Err(error) => {
+            result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                error,
+                schema_index,
+            }));
+            return result;
+        }
+    };
+
+    let input_text = get_node_text(&input_cursor.node(), input_str);
+    match matcher.try_match_str(input_text, options.max_matcher_input_len) {
+        Ok(Some(matched_str)) => {
+            let Some(offset) = subslice_offset(input_text, matched_str) else {
+                invariant_violation!(result, "a match should be a slice of the text it is in");
+            };
+            let start = input_cursor.node().start_byte() + offset;
+            result.set_matcher_match(
+                &matcher,
+                captured_value(input_str, matched_str, start, input_line_starts),
+                schema_index,
+                input_index,
+            );
+        }
+        Ok(None) if !is_partial_match => {
+            result.add_error(ValidationError::SchemaViolation(matcher.mismatch_error(
+                schema_index,
+                input_index,
+                input_text.into(),
+            )));
+        }
+        Ok(None) => {}
+        Err(error) => {
+            result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                error,
+                schema_index,
+            }));
+        }
+    }
+
+    result
+}
+
 fn validate_link_destination(
     schema_cursor: &TreeCursor,
     input_cursor: &TreeCursor,
     schema_str: &str,
     input_str: &str,
     got_eof: bool,
+    options: &ValidateOptions,
+    input_line_starts: Option<&LineStarts>,
 ) -> ValidationResult {
     let mut result = ValidationResult::from_cursors(schema_cursor, input_cursor);
 
@@ -237,74 +563,155 @@ fn validate_link_destination(
         }
     }
 
-    let schema_text = get_node_text(&schema_text_cursor.node(), schema_str);
-    let input_text = get_node_text(&input_text_cursor.node(), input_str);
-
     let is_partial_match = waiting_at_end(got_eof, input_str, &input_text_cursor);
 
-    if let Some(matcher_result) = extract_matcher_from_curly_delineated_text(schema_text) {
+    if let Some(matcher_result) = compare_destination_matchers(
+        &Destination::from_cursor(&schema_text_cursor, schema_str),
+        &Destination::from_cursor(&input_text_cursor, input_str),
+        input_str,
+        is_partial_match,
+        options,
+        input_line_starts,
+    ) {
+        // Only take errors and values, not position (parent already tracks position at link level)
+        result.join_data(matcher_result.data());
+        return result;
+    }
+
+    let text_result = compare_text_contents(
+        schema_str,
+        input_str,
+        &schema_text_cursor,
+        &input_text_cursor,
+        is_partial_match,
+        false,
+        options,
+        input_line_starts,
+    );
+    // Only take errors and values, not position (parent already tracks position at link level)
+    result.join_data(text_result.data());
+
+    result
+}
+
+/// Compare two link destinations where either may be a curly matcher, like
+/// `{url:/https?:\/\/.+/}`.
+///
+/// Returns `None` if neither destination is a matcher, in which case they
+/// should be compared as literal text.
+fn compare_destination_matchers(
+    schema_destination: &Destination,
+    input_destination: &Destination,
+    input_str: &str,
+    is_partial_match: bool,
+    options: &ValidateOptions,
+    input_line_starts: Option<&LineStarts>,
+) -> Option<ValidationResult> {
+    let mut result =
+        ValidationResult::from_descendant_indexes(schema_destination.index, input_destination.index);
+
+    if let Some(matcher_result) =
+        extract_matcher_from_curly_delineated_text(schema_destination.text, &options.regex_limits)
+    {
         match matcher_result {
             Ok(matcher) => {
-                if let Some(matched_str) = matcher.match_str(input_text) {
-                    if let Some(id) = matcher.id() {
-                        result.set_match(id, json!(matched_str));
-                    }
-                } else if !is_partial_match {
-                    result.add_error(ValidationError::SchemaViolation(
-                        SchemaViolationError::NodeContentMismatch {
-                            schema_index: schema_text_cursor.descendant_index(),
-                            input_index: input_text_cursor.descendant_index(),
-                            expected: matcher.pattern().to_string(),
-                            actual: input_text.into(),
-                            kind: NodeContentMismatchKind::Matcher,
-                        },
-                    ));
-                }
-
-                return result;
+                return Some(match_destination(
+                    &matcher,
+                    schema_destination,
+                    input_destination,
+                    input_str,
+                    is_partial_match,
+                    options,
+                    input_line_starts,
+                ));
             }
             Err(MatcherError::WasLiteralCode) => {}
             Err(error) => {
                 result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
                     error,
-                    schema_index: schema_text_cursor.descendant_index(),
+                    schema_index: schema_destination.index,
                 }));
-                return result;
+                return Some(result);
             }
         }
     }
 
-    if let Some(matcher_result) = extract_matcher_from_curly_delineated_text(input_text)
+    if let Some(matcher_result) =
+        extract_matcher_from_curly_delineated_text(input_destination.text, &options.regex_limits)
         && let Ok(matcher) = matcher_result {
-            if let Some(matched_str) = matcher.match_str(schema_text) {
-                if let Some(id) = matcher.id() {
-                    result.set_match(id, json!(matched_str));
-                }
+            if let Some(matched_str) = matcher.match_str(schema_destination.text) {
+                result.set_matcher_match(
+                    &matcher,
+                    captured_value(
+                        input_str,
+                        matched_str,
+                        input_destination.start_byte,
+                        input_line_starts,
+                    ),
+                    schema_destination.index,
+                    input_destination.index,
+                );
             } else if !is_partial_match {
-                result.add_error(ValidationError::SchemaViolation(
-                    SchemaViolationError::NodeContentMismatch {
-                        schema_index: schema_text_cursor.descendant_index(),
-                        input_index: input_text_cursor.descendant_index(),
-                        expected: matcher.pattern().to_string(),
-                        actual: schema_text.into(),
-                        kind: NodeContentMismatchKind::Matcher,
-                    },
-                ));
+                result.add_error(ValidationError::SchemaViolation(matcher.mismatch_error(
+                    schema_destination.index,
+                    input_destination.index,
+                    schema_destination.text.into(),
+                )));
             }
 
-            return result;
+            return Some(result);
         }
 
-    let text_result = compare_text_contents(
-        schema_str,
-        input_str,
-        &schema_text_cursor,
-        &input_text_cursor,
-        is_partial_match,
-        false,
+    None
+}
+
+/// Match a curly matcher of a schema link destination against the input one.
+fn match_destination(
+    matcher: &Matcher,
+    schema_destination: &Destination,
+    input_destination: &Destination,
+    input_str: &str,
+    is_partial_match: bool,
+    options: &ValidateOptions,
+    input_line_starts: Option<&LineStarts>,
+) -> ValidationResult {
+    let mut result = ValidationResult::from_descendant_indexes(
+        schema_destination.index,
+        input_destination.index,
     );
-    // Only take errors and values, not position (parent already tracks position at link level)
-    result.join_data(text_result.data());
+
+    match matcher.try_match_str(input_destination.text, options.max_matcher_input_len) {
+        Ok(Some(matched_str)) => {
+            let Some(offset) = subslice_offset(input_destination.text, matched_str) else {
+                invariant_violation!(result, "a match should be a slice of the text it is in");
+            };
+            result.set_matcher_match(
+                matcher,
+                captured_value(
+                    input_str,
+                    matched_str,
+                    input_destination.start_byte + offset,
+                    input_line_starts,
+                ),
+                schema_destination.index,
+                input_destination.index,
+            );
+        }
+        Ok(None) if !is_partial_match => {
+            result.add_error(ValidationError::SchemaViolation(matcher.mismatch_error(
+                schema_destination.index,
+                input_destination.index,
+                input_destination.text.into(),
+            )));
+        }
+        Ok(None) => {}
+        Err(error) => {
+            result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                error,
+                schema_index: schema_destination.index,
+            }));
+        }
+    }
 
     result
 }
@@ -317,8 +724,11 @@ fn link_child_pos(schema_cursor: &TreeCursor, input_cursor: &TreeCursor) -> Opti
         return None;
     }
 
+    // A code span matcher on the schema side stands in for any input text
     #[cfg(feature = "invariant_violations")]
-    if !both_are_text_nodes(&schema_text_cursor.node(), &input_text_cursor.node()) {
+    if !both_are_text_nodes(&schema_text_cursor.node(), &input_text_cursor.node())
+        && !is_inline_code_node(&schema_text_cursor.node())
+    {
         invariant_violation!(
             &schema_text_cursor,
             &input_text_cursor,
@@ -338,6 +748,7 @@ mod tests {
 
     use super::super::test_utils::ValidatorTester;
     use super::LinkVsLinkValidator;
+    use crate::mdschema::validation::errors::{SchemaViolationError, ValidationError};
     use crate::mdschema::validation::node_pos_pair::NodePosPair;
 
     #[test]
@@ -498,4 +909,107 @@ mod tests {
             &json!({"text": "hello", "url": "https://test.com"})
         );
     }
+
+    #[test]
+    fn test_validate_link_text_code_span_matcher() {
+        let schema_str = "[`title:/.+/`]({url:/https:.+/})";
+        let input_str = "[Hello *world*](https://example.com)";
+
+        let result = ValidatorTester::<LinkVsLinkValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert_eq!(result.errors(), &vec![]);
+        assert_eq!(
+            result.value(),
+            &json!({"title": "Hello *world*", "url": "https://example.com"})
+        );
+    }
+
+    #[test]
+    fn test_validate_link_text_code_span_matcher_with_literal_destination() {
+        let schema_str = "[`title:/\\w+/`](https://example.com)";
+
+        let result = ValidatorTester::<LinkVsLinkValidator>::from_strs(
+            schema_str,
+            "[Hello](https://example.com)",
+        )
+        .walk()
+        .goto_first_child_then_unwrap()
+        .goto_first_child_then_unwrap()
+        .validate_complete();
+
+        assert_eq!(result.errors(), &vec![]);
+        assert_eq!(result.value(), &json!({"title": "Hello"}));
+
+        let result = ValidatorTester::<LinkVsLinkValidator>::from_strs(
+            schema_str,
+            "[Hello](https://different.com)",
+        )
+        .walk()
+        .goto_first_child_then_unwrap()
+        .goto_first_child_then_unwrap()
+        .validate_complete();
+
+        assert_eq!(result.errors().len(), 1);
+        assert_eq!(result.value(), &json!({"title": "Hello"}));
+    }
+
+    #[test]
+    fn test_validate_link_text_matches_but_destination_matcher_fails() {
+        let schema_str = "[`title:/.+/`]({url:/https:.+/})";
+        let input_str = "[Hello](http://example.com)";
+
+        let result = ValidatorTester::<LinkVsLinkValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        // The text is still captured, and only the destination is an error
+        assert_eq!(result.errors().len(), 1);
+        match &result.errors()[0] {
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                actual,
+                ..
+            }) => assert_eq!(actual, "http://example.com"),
+            error => panic!("Expected a destination mismatch, got {:?}", error),
+        }
+        assert_eq!(result.value(), &json!({"title": "Hello"}));
+    }
+
+    #[test]
+    fn test_validate_link_text_code_span_matcher_mismatch() {
+        let schema_str = "[`title:/\\d+/`](https://example.com)";
+        let input_str = "[Hello](https://example.com)";
+
+        let result = ValidatorTester::<LinkVsLinkValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert_eq!(result.errors().len(), 1);
+        assert_eq!(result.value(), &json!({}));
+    }
+
+    #[test]
+    fn test_validate_image_description_code_span_matcher() {
+        let schema_str = "![`alt:/.+/`]({src:/.+\\.png/})";
+        let input_str = "![A logo](assets/logo.png)";
+
+        let result = ValidatorTester::<LinkVsLinkValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert_eq!(result.errors(), &vec![]);
+        assert_eq!(
+            result.value(),
+            &json!({"alt": "A logo", "src": "assets/logo.png"})
+        );
+    }
 }
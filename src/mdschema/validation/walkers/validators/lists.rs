@@ -6,27 +6,40 @@
 #[cfg(feature = "invariant_violations")]
 use crate::mdschema::validation::validator_walker::ValidatorWalker;
 use crate::mdschema::validation::{
+    conditions::Condition,
     errors::MalformedStructureKind,
-    matchers::matcher::{Matcher, MatcherError},
+    match_paths::{match_path, sole_value_at_path, take_value_at_path},
+    matchers::{
+        matcher::{Matcher, MatcherError, RegexLimits},
+        matcher_cache::MatcherCache,
+        matcher_extras::get_after_extras,
+    },
     walkers::{
         ValidationResult,
-        validators::{Validator, ValidatorImpl, containers::ContainerVsContainerValidatorBuilder},
+        validators::{
+            Validator, ValidatorImpl,
+            containers::ContainerVsContainerValidatorBuilder,
+            nodes::NodeVsNodeValidator,
+            unordered_lists::{unordered_directive, validate_unordered_list},
+        },
     },
     ts_types::*,
     ts_utils::{
-        count_siblings, get_node_and_next_node, get_node_text, has_single_code_child,
-        has_subsequent_node_of_kind, waiting_at_end,
+        count_siblings, get_node_and_next_node, get_node_text, goto_last_child_skipping,
+        goto_next_sibling_skipping, has_single_code_child, has_subsequent_node_of_kind,
+        waiting_at_end,
     },
 };
 use crate::{
     invariant_violation,
     mdschema::validation::errors::{
-        ChildrenLengthRange, SchemaError, SchemaViolationError, ValidationError,
+        ChildrenLengthRange, NodeContentMismatchKind, SchemaError, SchemaViolationError,
+        ValidationError,
     },
 };
 use log::trace;
-use serde_json::json;
-use tree_sitter::TreeCursor;
+use serde_json::{Value, json};
+use tree_sitter::{Node, TreeCursor};
 
 // Use the macro from node_walker module
 use crate::compare_node_kinds_check;
@@ -86,6 +99,9 @@ use crate::compare_node_kinds_check;
 ///
 /// Note that a limitation here is that you cannot have a variable-length list
 /// that is not the final list in your schema.
+///
+/// A schema list right after an `<!-- mds:unordered -->` directive is matched
+/// in any order instead (see `unordered_lists`).
 #[derive(Default)]
 pub(super) struct ListVsListValidator;
 
@@ -105,9 +121,16 @@ impl ValidatorImpl for ListVsListValidator {
             walker.input_str(),
             result
         );
+        if let Some(error) = list_tightness_mismatch(walker, &schema_cursor, &input_cursor) {
+            result.add_error(error);
+            return result;
+        }
+        if let Some(unordered) = unordered_directive(&schema_cursor, walker.schema_str()) {
+            return validate_unordered_list(walker, unordered, got_eof);
+        }
 
-        let at_list_schema_cursor = schema_cursor.clone();
-        let at_list_input_cursor = input_cursor.clone();
+        let mut at_list_schema_cursor = schema_cursor.clone();
+        let mut at_list_input_cursor = input_cursor.clone();
 
         match (
             ensure_at_first_list_item(&mut input_cursor),
@@ -122,404 +145,731 @@ impl ValidatorImpl for ListVsListValidator {
             _ => {} // we'll deal with the mismatch later in `validate_list_item_contents_vs_list_item_contents`
         }
 
-        #[cfg(feature = "invariant_violations")]
-        if !is_list_item_node(&schema_cursor.node()) || !is_list_item_node(&input_cursor.node()) {
-            invariant_violation!(
-                result,
-                &schema_cursor,
-                &input_cursor,
-                "expected list_item nodes after list traversal"
-            );
-        }
-
-        match extract_repeated_matcher_from_list_item(&schema_cursor, walker.schema_str()) {
-            // We were able to find a valid repeated matcher in the schema list item.
-            Some(Ok(matcher)) => {
-                let extras = matcher.extras();
-                let min_items_option = extras.min_items();
-                let min_items = extras.min_items_or(0);
-                let max_items = extras.max_items();
-                trace!(
-                    "Found repeated matcher: id={:?}, min_items={}, max_items={:?}, variable_length={}",
-                    matcher.id(),
-                    min_items,
-                    max_items,
-                    matcher.variable_length()
+        // Pairs of literal items are validated one after the other in this
+        // loop, rather than by recursing into the next pair, so that a long
+        // list doesn't nest a validator per item (see
+        // `ValidateOptions::max_nesting_depth`).
+        loop {
+            #[cfg(feature = "invariant_violations")]
+            if !is_list_item_node(&schema_cursor.node()) || !is_list_item_node(&input_cursor.node())
+            {
+                invariant_violation!(
+                    result,
+                    &schema_cursor,
+                    &input_cursor,
+                    "expected list_item nodes after list traversal"
                 );
+            }
 
-                // We don't let you have a variable length matcher where there are more list elements in the schema.
-                if matcher.variable_length()
-                    && has_subsequent_node_of_kind(&schema_cursor, "list_item")
-                {
-                    trace!(
-                        "Error: Variable length matcher found with subsequent schema list items"
-                    );
-                    result.add_error(ValidationError::SchemaError(
-                        SchemaError::RepeatingMatcherUnbounded {
-                            schema_index: schema_cursor.descendant_index(),
-                        },
+            if let Some(condition) = walker.condition(&schema_cursor) {
+                result.join_other_result(&validate_conditional_list_item(
+                    walker,
+                    &schema_cursor,
+                    &input_cursor,
+                    condition,
+                    got_eof,
+                ));
+                return result;
+            }
+
+            match extract_repeated_matcher_from_list_item(
+                &schema_cursor,
+                walker.schema_str(),
+                walker.matcher_cache(),
+                &walker.options().regex_limits,
+            ) {
+                // The schema list item is optional, so the input item may belong to
+                // the next schema item instead.
+                Some(Ok(matcher)) if matcher.is_optional() => {
+                    result.join_other_result(&validate_optional_list_item(
+                        walker,
+                        &schema_cursor,
+                        &input_cursor,
+                        got_eof,
                     ));
-                    return result;
                 }
+                // We were able to find a valid repeated matcher in the schema list item.
+                Some(Ok(matcher)) => {
+                    let extras = matcher.extras();
+                    let min_items_option = extras.min_items();
+                    let min_items = extras.min_items_or(0);
+                    let max_items = extras.max_items();
+                    trace!(
+                        "Found repeated matcher: id={:?}, min_items={}, max_items={:?}, variable_length={}",
+                        matcher.id(),
+                        min_items,
+                        max_items,
+                        matcher.variable_length()
+                    );
 
-                let mut values_at_level = Vec::with_capacity(extras.max_items_or(1));
-                let mut validate_so_far = 0;
-
-                loop {
-                    trace!("Validating list item #{}", validate_so_far + 1,);
-
-                    #[cfg(feature = "invariant_violations")]
-                    if input_cursor.node().kind() != "list_item"
-                        || schema_cursor.node().kind() != "list_item"
+                    // We don't let you have a variable length matcher where there are more list elements in the schema.
+                    if matcher.variable_length()
+                        && has_subsequent_node_of_kind(&schema_cursor, "list_item")
                     {
-                        invariant_violation!(
-                            result,
-                            &schema_cursor,
-                            &input_cursor,
-                            "expected list_item nodes while validating repeated matcher"
+                        trace!(
+                            "Error: Variable length matcher found with subsequent schema list items"
                         );
+                        result.add_error(ValidationError::SchemaError(
+                            SchemaError::RepeatingMatcherUnbounded {
+                                schema_index: schema_cursor.descendant_index(),
+                            },
+                        ));
+                        return result;
                     }
 
-                    let (new_matches, early_return) =
-                        validate_list_item_contents_vs_list_item_contents(
-                            &schema_cursor,
-                            &input_cursor,
-                            walker.schema_str(),
-                            walker.input_str(),
-                            got_eof,
-                        );
+                    let mut values_at_level = Vec::with_capacity(extras.max_items_or(1));
+                    // The captures of the list nested in the last item, if any
+                    let mut nested_value = None;
+                    let mut validate_so_far = 0;
+                    let schema_item_cursor = schema_cursor.clone();
 
-                    let has_errors = new_matches.has_errors();
-                    validate_so_far += 1;
-                    values_at_level.push(new_matches.value().clone());
-                    result.join_errors(new_matches.errors());
-                    if early_return || has_errors {
-                        return result;
-                    }
+                    loop {
+                        trace!("Validating list item #{}", validate_so_far + 1,);
 
-                    trace!(
-                        "Completed validation of list item #{}, moving to next",
-                        validate_so_far
-                    );
+                        #[cfg(feature = "invariant_violations")]
+                        if input_cursor.node().kind() != "list_item"
+                            || schema_cursor.node().kind() != "list_item"
+                        {
+                            invariant_violation!(
+                                result,
+                                &schema_cursor,
+                                &input_cursor,
+                                "expected list_item nodes while validating repeated matcher"
+                            );
+                        }
+
+                        let (new_matches, early_return) =
+                            validate_list_item_contents_vs_list_item_contents(
+                                walker,
+                                &schema_cursor,
+                                &input_cursor,
+                                got_eof,
+                            );
+
+                        let has_errors = new_matches.has_errors();
+                        validate_so_far += 1;
+                        values_at_level.push(new_matches.value().clone());
+                        result.join_errors(new_matches.errors());
+                        if early_return || has_errors {
+                            return result;
+                        }
 
-                    // If we've now validated the max number of items, check if there are more
-                    if let Some(max_items_value) = max_items
-                        && validate_so_far == max_items_value
-                    {
                         trace!(
-                            "Reached max items limit ({}), checking if there are more items",
-                            max_items_value
+                            "Completed validation of list item #{}, moving to next",
+                            validate_so_far
                         );
 
-                        // Check if there are more items beyond the max
-                        if input_cursor.clone().goto_next_sibling()
-                            && !schema_cursor.clone().goto_next_sibling()
+                        // If we've now validated the max number of items, check if there are more
+                        if let Some(max_items_value) = max_items
+                            && validate_so_far == max_items_value
                         {
-                            // There are more input items and no schema sibling to handle them
-                            // Report error immediately - extra items won't disappear
                             trace!(
-                                "Error: More items than max allowed ({} > {}), early exit",
-                                "at least one more", max_items_value
+                                "Reached max items limit ({}), checking if there are more items",
+                                max_items_value
                             );
-                            result.add_error(ValidationError::SchemaViolation(
-                                SchemaViolationError::ChildrenLengthMismatch {
-                                    schema_index: schema_cursor.descendant_index(),
-                                    input_index: input_cursor.descendant_index(),
-                                    expected: ChildrenLengthRange::from_optional_bounds(
-                                        min_items_option,
-                                        Some(max_items_value),
-                                    ),
-                                    actual: validate_so_far + 1, // At least one more
-                                },
-                            ));
-                            // Early exit - no more schema items to handle the extras
+
+                            // Check if there are more items beyond the max
+                            if input_cursor.clone().goto_next_sibling()
+                                && !schema_cursor.clone().goto_next_sibling()
+                            {
+                                // There are more input items and no schema sibling to handle them
+                                // Report error immediately - extra items won't disappear
+                                trace!(
+                                    "Error: More items than max allowed ({} > {}), early exit",
+                                    "at least one more", max_items_value
+                                );
+                                result.add_error(ValidationError::SchemaViolation(
+                                    SchemaViolationError::ChildrenLengthMismatch {
+                                        schema_index: schema_cursor.descendant_index(),
+                                        input_index: input_cursor.descendant_index(),
+                                        expected: ChildrenLengthRange::from_optional_bounds(
+                                            min_items_option,
+                                            Some(max_items_value),
+                                        ),
+                                        actual: validate_so_far + 1, // At least one more
+                                    },
+                                ));
+                                // Early exit - no more schema items to handle the extras
+                                break;
+                            }
                             break;
                         }
-                        break;
-                    }
 
-                    // Otherwise move to the next sibling, or break if there are none left
-                    if !input_cursor.goto_next_sibling() {
-                        break;
+                        // Otherwise move to the next sibling, or break if there are none left
+                        if !input_cursor.goto_next_sibling() {
+                            break;
+                        }
                     }
-                }
-
-                // Check if we validated enough items
-                if validate_so_far < min_items && got_eof {
-                    trace!(
-                        "Error: Not enough items validated ({} < {}) and at EOF",
-                        validate_so_far, min_items
-                    );
-                    result.add_error(ValidationError::SchemaViolation(
-                        SchemaViolationError::ChildrenLengthMismatch {
-                            schema_index: schema_cursor.descendant_index(),
-                            input_index: input_cursor.descendant_index(),
-                            expected: ChildrenLengthRange::from_optional_bounds(
-                                min_items_option,
-                                max_items,
-                            ),
-                            actual: validate_so_far,
-                        },
-                    ));
-                }
-
-                // If we didn't make it to the end of the input list, there
-                // might be more items but that correspond to another matcher.
-                //
-                // For example, with a schema like:
-                //
-                // ```md
-                // - `testA:/test\d/`{2,2}
-                // - `testB:/line2test\d/`{2,2}
-                // ```
-                //
-                // And input like:
-                //
-                // ```md
-                // - test1
-                // - test2
-                // - line2test1
-                // - line2test2
-                // ```
-                //
-                // We want to validate the first two, pushing them into our
-                // list, and then the second two.
-                //
-                // { "testA": ["test1", "test2"],
-                //   "testB": ["line2test1", "line2test2"] }
-                //
-                // In these cases we are looking at an schema tree that looks like:
-                //
-                // (tight_list)
-                // ├── (list_item) <-- where we are now
-                // │   ├── (list_marker)
-                // │   └── (paragraph)
-                // │       ├── (code_span)
-                // │       │   └── (text)
-                // │       └── (text)
-                // └── (list_item) <-- where we are after .goto_next_sibling() when it returns true
-                //     ├── (list_marker)
-                //     └── (paragraph)
-                //         ├── (code_span)
-                //         │   └── (text)
-                //         └── (text)
-                //
-                // If there are more items to validate AT THE SAME LEVEL, recurse to
-                // validate them. We now use the *next* schema node too.
-                if schema_cursor.goto_next_sibling() && input_cursor.goto_next_sibling() {
-                    let next_result = ListVsListValidator
-                        .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof);
-                    result.join_other_result(&next_result);
-                }
 
-                trace!("Completed validation of all {} list items", validate_so_far);
-
-                // Now, if there's another pair, recurse and validate it
-                if schema_cursor.goto_first_child() && input_cursor.goto_first_child() {
-                    while schema_cursor.goto_next_sibling() && input_cursor.goto_next_sibling() {}
-
-                    // There is a deeper list!
-                    if is_list_node(&schema_cursor.node()) && is_list_node(&input_cursor.node()) {
+                    // Check if we validated enough items
+                    if validate_so_far < min_items && got_eof {
                         trace!(
-                            "Found next sibling pairs, recursing to validate next list elements; cursors are at {:?} and {:?}",
-                            input_cursor.node().kind(),
-                            schema_cursor.node().kind()
+                            "Error: Not enough items validated ({} < {}) and at EOF",
+                            validate_so_far, min_items
                         );
+                        result.add_error(ValidationError::SchemaViolation(
+                            SchemaViolationError::ChildrenLengthMismatch {
+                                schema_index: schema_cursor.descendant_index(),
+                                input_index: input_cursor.descendant_index(),
+                                expected: ChildrenLengthRange::from_optional_bounds(
+                                    min_items_option,
+                                    max_items,
+                                ),
+                                actual: validate_so_far,
+                            },
+                        ));
+                    }
 
+                    if walker.should_stop(&result) {
+                        return result;
+                    }
+                    let last_input_item_cursor = input_cursor.clone();
+
+                    // If we didn't make it to the end of the input list, there
+                    // might be more items but that correspond to another matcher.
+                    //
+                    // For example, with a schema like:
+                    //
+                    // ```md
+                    // - `testA:/test\d/`{2,2}
+                    // - `testB:/line2test\d/`{2,2}
+                    // ```
+                    //
+                    // And input like:
+                    //
+                    // ```md
+                    // - test1
+                    // - test2
+                    // - line2test1
+                    // - line2test2
+                    // ```
+                    //
+                    // We want to validate the first two, pushing them into our
+                    // list, and then the second two.
+                    //
+                    // { "testA": ["test1", "test2"],
+                    //   "testB": ["line2test1", "line2test2"] }
+                    //
+                    // In these cases we are looking at an schema tree that looks like:
+                    //
+                    // (tight_list)
+                    // ├── (list_item) <-- where we are now
+                    // │   ├── (list_marker)
+                    // │   └── (paragraph)
+                    // │       ├── (code_span)
+                    // │       │   └── (text)
+                    // │       └── (text)
+                    // └── (list_item) <-- where we are after .goto_next_sibling() when it returns true
+                    //     ├── (list_marker)
+                    //     └── (paragraph)
+                    //         ├── (code_span)
+                    //         │   └── (text)
+                    //         └── (text)
+                    //
+                    // If there are more items to validate AT THE SAME LEVEL, recurse to
+                    // validate them. We now use the *next* schema node too.
+                    if schema_cursor.goto_next_sibling() && input_cursor.goto_next_sibling() {
                         let next_result = ListVsListValidator
                             .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof);
-                        // We need to be able to capture errors that happen in the recursive call
-                        result.join_errors(next_result.errors());
-                        values_at_level.push(next_result.value().clone());
+                        result.join_other_result(&next_result);
+                        if walker.should_stop(&result) {
+                            return result;
+                        }
                     }
-                } else {
-                    trace!("No more sibling pairs found");
-                }
 
-                // Store the array that we just gathered
-                if let Some(matcher_id) = matcher.id() {
-                    trace!("Storing matches for matcher id: {}", matcher_id);
-
-                    result.set_match(
-                        matcher_id,
-                        json!(
-                            values_at_level
-                                .iter()
-                                .map(|value| {
-                                    // If we have a schema:
-                                    //
-                                    // ```md
-                                    // - `name:/test\d/`{2,2}
-                                    //   - `name:/test\d/`{1,1}
-                                    // ```
-                                    //
-                                    // Initially, we run this at the top level, gather something like
-                                    //
-                                    // matches_at_level = [{ "test": "test1" }, { "test": "test2" }]
-                                    //
-                                    // Then we might recurse, and end up with something like
-                                    //
-                                    // matches_at_level = [{ "test": "test1" }, { "test": "test2" }, { "deep": "test3" }]
-                                    //
-                                    // Then we iterate over the matches_at_level and unpack all the ones that have our
-                                    // id (we are top level), so "test," and get
-                                    //
-                                    // matches_at_level = ["test1", "test2", { "deep": "test3" }]
-                                    //
-                                    // Note that we don't unpack anything that is not our id (see below, where we
-                                    // "don't unpack!").
-
-                                    let mut matches_as_obj = value.as_object().unwrap().clone();
-
-                                    // TODO: can we avoid these clones?
-                                    if let Some(matcher_id) = matcher.id() {
-                                        let match_for_same_id = matches_as_obj.remove(matcher_id);
-
-                                        // Unwrap it to be loose in the array if we can
-                                        match match_for_same_id {
-                                            Some(match_for_same_id) => match_for_same_id,
-                                            None => value.clone(), // don't unpack!
-                                        }
-                                    } else {
-                                        value.clone()
-                                    }
-                                })
-                                .collect::<Vec<_>>()
-                        ),
-                    );
-                }
+                    trace!("Completed validation of all {} list items", validate_so_far);
 
-                // Now we have validated as many as we could, let's add it to the result.
-                // Update the cursors to be as far as we got, and then join the results.
-                trace!("Returning validation result for repeated matcher");
-                return result;
-            }
-            // We were able to find a matcher in the schema list item, but it was invalid (we failed to parse it).
-            Some(Err(e)) => {
-                trace!("Error: Found invalid matcher in schema list item: {:?}", e);
-                result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
-                    error: e,
-                    schema_index: schema_cursor.descendant_index(),
-                }));
-            }
-            // We didn't find a repeating matcher. In this case, just use validate the insides directly and move on.
-            None => {
-                trace!(
-                    "No repeated matcher found, using textual validation. Current node kinds: {:?} and {:?}",
-                    input_cursor.node().kind(),
-                    schema_cursor.node().kind()
-                );
+                    // Now, if there's another pair, recurse and validate it
+                    if schema_cursor.goto_last_child()
+                        && goto_last_child_skipping(&mut input_cursor, |node| {
+                            walker.skips_input_node(node)
+                        })
+                    {
+                        // There is a deeper list!
+                        if is_list_node(&schema_cursor.node()) && is_list_node(&input_cursor.node())
+                        {
+                            trace!(
+                                "Found next sibling pairs, recursing to validate next list elements; cursors are at {:?} and {:?}",
+                                input_cursor.node().kind(),
+                                schema_cursor.node().kind()
+                            );
 
-                // In this case we want to make sure that the children have the
-                // exact same length, since they are both literal lists. Dynamic
-                // lengths aren't allowed for literal lists.
-                let remaining_schema_nodes = count_siblings(&schema_cursor);
-                let literal_chunk_count =
-                    count_next_n_literal_lists(&schema_cursor, walker.schema_str());
-                let remaining_input_nodes = count_siblings(&input_cursor);
-                if remaining_schema_nodes != remaining_input_nodes {
-                    let available_literal_items = remaining_input_nodes + 1;
-
-                    if available_literal_items < literal_chunk_count {
-                        if waiting_at_end(got_eof, walker.input_str(), &input_cursor) {
-                            // Don't care for now
-                            return result;
-                        } else {
-                            result.add_error(ValidationError::SchemaViolation(
-                                SchemaViolationError::ChildrenLengthMismatch {
-                                    schema_index: at_list_schema_cursor.descendant_index(),
-                                    input_index: at_list_input_cursor.descendant_index(),
-                                    // +1 because we need to include this first node that we are currently on
-                                    expected: literal_chunk_count.into(),
-                                    actual: available_literal_items,
-                                },
-                            ));
-                            return result;
+                            let next_result = ListVsListValidator.validate(
+                                &walker.with_cursors(&schema_cursor, &input_cursor),
+                                got_eof,
+                            );
+                            // We need to be able to capture errors that happen in the recursive call
+                            result.join_errors(next_result.errors());
+                            nested_value = Some(next_result.value().clone());
                         }
+                    } else {
+                        trace!("No more sibling pairs found");
                     }
-                }
 
-                if remaining_schema_nodes != remaining_input_nodes
-                    && literal_chunk_count == remaining_schema_nodes + 1
-                {
-                    result.add_error(ValidationError::SchemaViolation(
-                        SchemaViolationError::ChildrenLengthMismatch {
-                            schema_index: at_list_schema_cursor.descendant_index(),
-                            input_index: at_list_input_cursor.descendant_index(),
-                            // +1 because we need to include this first node that we are currently on
-                            expected: (remaining_schema_nodes + 1).into(),
-                            actual: remaining_input_nodes + 1,
-                        },
-                    ));
-                    return result;
-                }
+                    // Without a nested schema list of its own, a `++` matcher
+                    // matches the nested input list itself
+                    if nested_value.is_none()
+                        && matcher.extras().max_depth() > 1
+                        && nested_list_cursor(&schema_item_cursor, |_| false).is_none()
+                    {
+                        nested_value = validate_nested_list_vs_matcher(
+                            walker,
+                            &schema_item_cursor,
+                            &last_input_item_cursor,
+                            &matcher,
+                            2,
+                            got_eof,
+                            &mut result,
+                        );
+                    }
 
-                let (list_item_match_result, early_return) =
-                    validate_list_item_contents_vs_list_item_contents(
-                        &schema_cursor,
-                        &input_cursor,
-                        walker.schema_str(),
-                        walker.input_str(),
-                        got_eof,
-                    );
-                result.join_other_result(&list_item_match_result);
+                    // Store the array that we just gathered
+                    if let Some(matcher_id) = matcher.id() {
+                        trace!("Storing matches for matcher id: {}", matcher_id);
+
+                        let values = if walker.options().structured_matches {
+                            structured_list_values(matcher_id, &values_at_level, nested_value)
+                        } else {
+                            values_at_level.extend(nested_value);
+                            legacy_list_values(matcher_id, &values_at_level)
+                        };
+                        result.set_match(matcher_id, Value::Array(values));
+                    }
 
-                if early_return || list_item_match_result.has_errors() {
+                    // Now we have validated as many as we could, let's add it to the result.
+                    // Update the cursors to be as far as we got, and then join the results.
+                    trace!("Returning validation result for repeated matcher");
                     return result;
                 }
+                // We were able to find a matcher in the schema list item, but it was invalid (we failed to parse it).
+                Some(Err(e)) => {
+                    trace!("Error: Found invalid matcher in schema list item: {:?}", e);
+                    result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                        error: e,
+                        schema_index: schema_cursor.descendant_index(),
+                    }));
+                }
+                // We didn't find a repeating matcher. In this case, just use validate the insides directly and move on.
+                None => {
+                    trace!(
+                        "No repeated matcher found, using textual validation. Current node kinds: {:?} and {:?}",
+                        input_cursor.node().kind(),
+                        schema_cursor.node().kind()
+                    );
+
+                    // In this case we want to make sure that the children have the
+                    // exact same length, since they are both literal lists. Dynamic
+                    // lengths aren't allowed for literal lists.
+                    let remaining_schema_nodes = count_siblings(&schema_cursor);
+                    let literal_chunk_count =
+                        count_next_n_literal_lists(walker, &schema_cursor, got_eof);
+                    let remaining_input_nodes = count_siblings(&input_cursor);
+                    if remaining_schema_nodes != remaining_input_nodes {
+                        let available_literal_items = remaining_input_nodes + 1;
+
+                        if available_literal_items < literal_chunk_count {
+                            if waiting_at_end(got_eof, walker.input_str(), &input_cursor) {
+                                // Don't care for now
+                                return result;
+                            } else {
+                                result.add_error(ValidationError::SchemaViolation(
+                                    SchemaViolationError::ChildrenLengthMismatch {
+                                        schema_index: at_list_schema_cursor.descendant_index(),
+                                        input_index: at_list_input_cursor.descendant_index(),
+                                        // +1 because we need to include this first node that we are currently on
+                                        expected: literal_chunk_count.into(),
+                                        actual: available_literal_items,
+                                    },
+                                ));
+                                return result;
+                            }
+                        }
+                    }
+
+                    if remaining_schema_nodes != remaining_input_nodes
+                        && literal_chunk_count == remaining_schema_nodes + 1
+                    {
+                        result.add_error(ValidationError::SchemaViolation(
+                            SchemaViolationError::ChildrenLengthMismatch {
+                                schema_index: at_list_schema_cursor.descendant_index(),
+                                input_index: at_list_input_cursor.descendant_index(),
+                                // +1 because we need to include this first node that we are currently on
+                                expected: (remaining_schema_nodes + 1).into(),
+                                actual: remaining_input_nodes + 1,
+                            },
+                        ));
+                        return result;
+                    }
 
-                {
-                    // Recurse down into the next list if there is one
-                    let mut schema_cursor = schema_cursor.clone();
-                    let mut input_cursor = input_cursor.clone();
+                    let (list_item_match_result, early_return) =
+                        validate_list_item_contents_vs_list_item_contents(
+                            walker,
+                            &schema_cursor,
+                            &input_cursor,
+                            got_eof,
+                        );
+                    result.join_other_result(&list_item_match_result);
 
-                    input_cursor.goto_last_child();
-                    schema_cursor.goto_last_child();
+                    if early_return || list_item_match_result.has_errors() {
+                        return result;
+                    }
 
-                    compare_node_kinds_check!(
-                        schema_cursor,
-                        input_cursor,
-                        walker.schema_str(),
-                        walker.input_str(),
-                        result
-                    );
+                    {
+                        // Recurse down into the next list if there is one
+                        let mut schema_cursor = schema_cursor.clone();
+                        let mut input_cursor = input_cursor.clone();
+
+                        goto_last_child_skipping(&mut input_cursor, |node| {
+                            walker.skips_input_node(node)
+                        });
+                        schema_cursor.goto_last_child();
+
+                        compare_node_kinds_check!(
+                            schema_cursor,
+                            input_cursor,
+                            walker.schema_str(),
+                            walker.input_str(),
+                            result
+                        );
+                        if let Some(error) =
+                            list_tightness_mismatch(walker, &schema_cursor, &input_cursor)
+                        {
+                            result.add_error(error);
+                            return result;
+                        }
 
-                    if is_list_node(&input_cursor.node()) {
-                        // and we know that schema is the same
-                        input_cursor.goto_first_child();
-                        schema_cursor.goto_first_child();
+                        if is_list_node(&input_cursor.node()) {
+                            // and we know that schema is the same
+                            input_cursor.goto_first_child();
+                            schema_cursor.goto_first_child();
 
-                        let deeper_result = ListVsListValidator
-                            .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof);
-                        result.join_other_result(&deeper_result);
+                            let deeper_result = ListVsListValidator.validate(
+                                &walker.with_cursors(&schema_cursor, &input_cursor),
+                                got_eof,
+                            );
+                            result.join_other_result(&deeper_result);
+                            if walker.should_stop(&result) {
+                                return result;
+                            }
+                        }
                     }
-                }
 
-                // Recurse on next sibling if available!
-                if schema_cursor.goto_next_sibling() && input_cursor.goto_next_sibling() {
-                    trace!("Moving to next sibling list items for continued validation");
-                    let new_matches = ListVsListValidator
-                        .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof);
-                    result.join_other_result(&new_matches);
-                } else {
+                    // Move on to the next pair of items, if there is one
+                    if schema_cursor.goto_next_sibling() && input_cursor.goto_next_sibling() {
+                        trace!("Moving to next sibling list items for continued validation");
+                        at_list_schema_cursor = schema_cursor.clone();
+                        at_list_input_cursor = input_cursor.clone();
+                        continue;
+                    }
                     trace!("No more sibling pairs found, validation complete");
                 }
             }
+
+            return result;
+        }
+    }
+}
+
+/// Validate an input list item against an optional schema list item, like
+/// `` - `note:/Note: .+/`? ``.
+///
+/// If the input item matches, its captures are kept as they are (not wrapped
+/// in an array) and both lists move on to their next item. If it doesn't, the
+/// schema item is skipped and the same input item is validated against the
+/// next schema item instead.
+fn validate_optional_list_item<'a>(
+    walker: &ValidatorWalker<'a>,
+    schema_cursor: &TreeCursor<'a>,
+    input_cursor: &TreeCursor<'a>,
+    got_eof: bool,
+) -> ValidationResult {
+    let (item_result, early_return) =
+        validate_list_item_contents_vs_list_item_contents(walker, schema_cursor, input_cursor, got_eof);
+
+    let mut next_schema_cursor = schema_cursor.clone();
+    let has_next_schema_item = next_schema_cursor.goto_next_sibling();
+
+    if !item_result.has_errors() {
+        let mut result = item_result;
+        if !early_return {
+            result.join_other_result(&validate_items_after_match(
+                walker,
+                schema_cursor,
+                input_cursor,
+                got_eof,
+            ));
+        }
+        return result;
+    }
+
+    if waiting_at_end(got_eof, walker.input_str(), input_cursor) {
+        // The item may still grow into a match
+        return ValidationResult::from_cursors(schema_cursor, input_cursor);
+    }
+
+    if !has_next_schema_item {
+        // There's nothing else the input item could be
+        return item_result;
+    }
+
+    trace!("Input list item doesn't match the optional schema item, trying the next one");
+    ListVsListValidator.validate(
+        &walker.with_sibling_cursors(&next_schema_cursor, input_cursor),
+        got_eof,
+    )
+}
+
+/// Validate an input list item against a schema list item with a condition,
+/// like `` - Docs `@when:type=library` `` (see `conditions`).
+///
+/// If the condition is met, the items are validated like literal items. If it
+/// isn't, the input shouldn't have the schema item: an input item that
+/// matches it anyway is reported as `SchemaViolationError::ConditionNotMet`,
+/// and any other input item is validated against the next schema item
+/// instead.
+fn validate_conditional_list_item<'a>(
+    walker: &ValidatorWalker<'a>,
+    schema_cursor: &TreeCursor<'a>,
+    input_cursor: &TreeCursor<'a>,
+    condition: &Condition,
+    got_eof: bool,
+) -> ValidationResult {
+    let Some(is_met) = walker.is_condition_met(schema_cursor, condition, got_eof) else {
+        // We'll know once its id has been captured
+        return ValidationResult::from_cursors(schema_cursor, input_cursor);
+    };
+
+    let (mut result, early_return) = validate_list_item_contents_vs_list_item_contents(
+        walker,
+        schema_cursor,
+        input_cursor,
+        got_eof,
+    );
+
+    if !is_met && result.has_errors() {
+        if waiting_at_end(got_eof, walker.input_str(), input_cursor) {
+            // The item may still grow into a match
+            return ValidationResult::from_cursors(schema_cursor, input_cursor);
+        }
+
+        let mut next_schema_cursor = schema_cursor.clone();
+        if !next_schema_cursor.goto_next_sibling() {
+            // There's nothing else the input item could be
+            return result;
+        }
+        trace!("Input list item isn't the one whose condition isn't met, trying the next one");
+        return ListVsListValidator.validate(
+            &walker.with_sibling_cursors(&next_schema_cursor, input_cursor),
+            got_eof,
+        );
+    }
+
+    if !is_met {
+        result.add_error(condition.not_met_error(
+            walker.captured_for(schema_cursor, &condition.id),
+            schema_cursor.descendant_index(),
+            input_cursor.descendant_index(),
+        ));
+    } else if result.has_errors() {
+        return result;
+    }
+
+    if !early_return && !walker.should_stop(&result) {
+        result.join_other_result(&validate_items_after_match(
+            walker,
+            schema_cursor,
+            input_cursor,
+            got_eof,
+        ));
+    }
+    result
+}
+
+/// Validate what comes after a pair of list items whose contents match: the
+/// lists nested in them, if they have any, and then the next pair of items.
+fn validate_items_after_match<'a>(
+    walker: &ValidatorWalker<'a>,
+    schema_cursor: &TreeCursor<'a>,
+    input_cursor: &TreeCursor<'a>,
+    got_eof: bool,
+) -> ValidationResult {
+    let mut result = ValidationResult::from_cursors(schema_cursor, input_cursor);
+
+    // Recurse down into the item's nested list, if there is one
+    let mut schema_child_cursor = schema_cursor.clone();
+    let mut input_child_cursor = input_cursor.clone();
+    if schema_child_cursor.goto_last_child()
+        && goto_last_child_skipping(&mut input_child_cursor, |node| {
+            walker.skips_input_node(node)
+        })
+        && both_are_list_nodes(&schema_child_cursor.node(), &input_child_cursor.node())
+    {
+        if let Some(error) =
+            list_tightness_mismatch(walker, &schema_child_cursor, &input_child_cursor)
+        {
+            result.add_error(error);
+            return result;
+        }
+        schema_child_cursor.goto_first_child();
+        input_child_cursor.goto_first_child();
+        result.join_other_result(&ListVsListValidator.validate(
+            &walker.with_cursors(&schema_child_cursor, &input_child_cursor),
+            got_eof,
+        ));
+        if walker.should_stop(&result) {
+            return result;
+        }
+    }
+
+    let mut next_schema_cursor = schema_cursor.clone();
+    let mut next_input_cursor = input_cursor.clone();
+    if next_schema_cursor.goto_next_sibling() && next_input_cursor.goto_next_sibling() {
+        result.join_other_result(&ListVsListValidator.validate(
+            &walker.with_sibling_cursors(&next_schema_cursor, &next_input_cursor),
+            got_eof,
+        ));
+    }
+    result
+}
+
+/// Validate the list nested in an input item against the repeated matcher of
+/// the schema item above it, for matchers that reach nested lists with `++`.
+///
+/// Each level is matched like the matcher's own, with the same pattern and
+/// bounds, and its captures nest like those of an explicit nested schema
+/// list. A list deeper than the matcher reaches is reported as
+/// `NodeListTooDeep`.
+///
+/// Returns the captures of the nested list, if the input item has one.
+///
+/// # Arguments
+///
+/// * `schema_item_cursor`: The cursor at the schema list item with the matcher.
+/// * `input_item_cursor`: The cursor at the input list item the list may be nested in.
+/// * `depth`: The depth of the nested list, where the matcher's own list is 1.
+fn validate_nested_list_vs_matcher<'a>(
+    walker: &ValidatorWalker<'a>,
+    schema_item_cursor: &TreeCursor<'a>,
+    input_item_cursor: &TreeCursor<'a>,
+    matcher: &Matcher,
+    depth: usize,
+    got_eof: bool,
+    result: &mut ValidationResult,
+) -> Option<Value> {
+    let input_list_cursor =
+        nested_list_cursor(input_item_cursor, |node| walker.skips_input_node(node))?;
+
+    let extras = matcher.extras();
+    if depth > extras.max_depth() {
+        result.add_error(ValidationError::SchemaViolation(
+            SchemaViolationError::NodeListTooDeep {
+                schema_index: schema_item_cursor.descendant_index(),
+                input_index: input_list_cursor.descendant_index(),
+                max_depth: extras.max_depth(),
+            },
+        ));
+        return None;
+    }
+
+    let mut values_at_level = Vec::new();
+    let mut input_cursor = input_list_cursor.clone();
+    input_cursor.goto_first_child();
+    let mut last_item_cursor = input_cursor.clone();
+    loop {
+        if is_list_item_node(&input_cursor.node()) {
+            let (item_result, early_return) = validate_list_item_contents_vs_list_item_contents(
+                walker,
+                schema_item_cursor,
+                &input_cursor,
+                got_eof,
+            );
+            values_at_level.push(item_result.value().clone());
+            result.join_errors(item_result.errors());
+            if early_return || item_result.has_errors() {
+                return None;
+            }
+            last_item_cursor = input_cursor.clone();
+        }
+
+        if !input_cursor.goto_next_sibling() {
+            break;
         }
+    }
 
-        result
+    let count = values_at_level.len();
+    if extras
+        .max_items()
+        .is_some_and(|max_items| count > max_items)
+        || (got_eof && count < extras.min_items_or(0))
+    {
+        result.add_error(ValidationError::SchemaViolation(
+            SchemaViolationError::ChildrenLengthMismatch {
+                schema_index: schema_item_cursor.descendant_index(),
+                input_index: input_list_cursor.descendant_index(),
+                expected: ChildrenLengthRange::from_matcher_extras(extras),
+                actual: count,
+            },
+        ));
+        return None;
     }
+
+    let nested_value = validate_nested_list_vs_matcher(
+        walker,
+        schema_item_cursor,
+        &last_item_cursor,
+        matcher,
+        depth + 1,
+        got_eof,
+        result,
+    );
+
+    let matcher_id = matcher.id()?;
+    let values = if walker.options().structured_matches {
+        structured_list_values(matcher_id, &values_at_level, nested_value)
+    } else {
+        values_at_level.extend(nested_value);
+        legacy_list_values(matcher_id, &values_at_level)
+    };
+    Some(json!({ matcher_id: values }))
+}
+
+/// A cursor at the list nested in a list item, if it has one.
+pub(super) fn nested_list_cursor<'a>(
+    list_item_cursor: &TreeCursor<'a>,
+    skip: impl Fn(&Node) -> bool,
+) -> Option<TreeCursor<'a>> {
+    let mut cursor = list_item_cursor.clone();
+    (goto_last_child_skipping(&mut cursor, skip) && is_list_node(&cursor.node())).then_some(cursor)
 }
 
 /// Walk forward and see how many lists after this one at the same level are literal lists.
-fn count_next_n_literal_lists(schema_cursor: &TreeCursor, schema_str: &str) -> usize {
+///
+/// An item with a condition counts as literal if its condition is met, since
+/// then the input has to have it (see `conditions`).
+fn count_next_n_literal_lists(
+    walker: &ValidatorWalker,
+    schema_cursor: &TreeCursor,
+    got_eof: bool,
+) -> usize {
     let mut schema_cursor = schema_cursor.clone();
     let mut count = 0;
     loop {
-        match extract_repeated_matcher_from_list_item(&schema_cursor, schema_str) {
-            Some(Ok(_)) | Some(Err(_)) => break,
-            None => {
-                count += 1;
+        if let Some(condition) = walker.condition(&schema_cursor) {
+            if walker.is_condition_met(&schema_cursor, condition, got_eof) != Some(true) {
+                break;
+            }
+            count += 1;
+        } else {
+            match extract_repeated_matcher_from_list_item(
+                &schema_cursor,
+                walker.schema_str(),
+                walker.matcher_cache(),
+                &walker.options().regex_limits,
+            ) {
+                Some(Ok(_)) | Some(Err(_)) => break,
+                None => {
+                    count += 1;
+                }
             }
         }
 
@@ -536,100 +886,612 @@ fn count_next_n_literal_lists(schema_cursor: &TreeCursor, schema_str: &str) -> u
 ///
 /// ```ansi
 /// ├─ (list_item) <-- we are here
-/// │  ├─ (list_marker) <-- we already validated this
-/// │  └─ (paragraph) <-- we want to be here
-/// │     └─ (text)
+/// │  ├─ (list_marker) <-- we check this first
+/// │  ├─ (paragraph) <-- then each block after it
+/// │  │  └─ (text)
+/// │  ├─ (fenced_code_block)
+/// │  └─ (tight_list) <-- but not a nested list at the end
 /// ```
 ///
-/// Walks into their actual paragraphs and runs textual container validation.
-fn validate_list_item_contents_vs_list_item_contents(
-    schema_cursor: &TreeCursor,
-    input_cursor: &TreeCursor,
-    schema_str: &str,
-    input_str: &str,
+/// A paragraph as the first block runs textual container validation, since
+/// that's where a repeated matcher or a task checkbox goes, and every other
+/// pair of blocks goes through `NodeVsNodeValidator`. Items of loose lists can
+/// have any number of blocks, but the input item needs as many as the schema
+/// item.
+///
+/// Returns the result, and whether there's nothing left of the items for the
+/// caller to validate, like their nested lists.
+pub(super) fn validate_list_item_contents_vs_list_item_contents<'a>(
+    walker: &ValidatorWalker<'a>,
+    schema_cursor: &TreeCursor<'a>,
+    input_cursor: &TreeCursor<'a>,
     got_eof: bool,
 ) -> (ValidationResult, bool) {
     let mut result = ValidationResult::from_cursors(schema_cursor, input_cursor);
 
+    let schema_item_cursor = schema_cursor;
+    let input_item_cursor = input_cursor;
     let mut schema_cursor = schema_cursor.clone();
     let mut input_cursor = input_cursor.clone();
 
-    #[cfg(feature = "invariant_violations")]
-    if !both_are_list_items(&schema_cursor.node(), &input_cursor.node()) {
-        invariant_violation!(
-            result,
-            &schema_cursor,
-            &input_cursor,
-            "expected list_item nodes before validating list item contents"
-        );
+    #[cfg(feature = "invariant_violations")]
+    if !both_are_list_items(&schema_cursor.node(), &input_cursor.node()) {
+        return (
+            item_contents_invariant_violation(
+                result,
+                &schema_cursor,
+                &input_cursor,
+                "expected list_item nodes before validating list item contents",
+            ),
+            false,
+        );
+    }
+
+    schema_cursor.goto_first_child();
+    input_cursor.goto_first_child();
+
+    #[cfg(feature = "invariant_violations")]
+    if !both_are_markers(&schema_cursor.node(), &input_cursor.node()) {
+        return (
+            item_contents_invariant_violation(
+                result,
+                &schema_cursor,
+                &input_cursor,
+                "expected list_marker nodes while validating list item contents",
+            ),
+            false,
+        );
+    }
+
+    if walker.options().enforce_ordered_list_numbers
+        && let Some(error) = list_marker_mismatch(walker, &schema_cursor, &input_cursor)
+    {
+        result.add_error(error);
+    }
+
+    let schema_blocks = list_item_blocks(schema_item_cursor, |_| false);
+    let input_blocks = list_item_blocks(input_item_cursor, |node| walker.skips_input_node(node));
+
+    match (schema_blocks.first(), input_blocks.first()) {
+        (Some(schema_block), Some(input_block)) => {
+            result.join_other_result(&validate_first_list_item_block(
+                walker,
+                schema_block,
+                input_block,
+                got_eof,
+            ));
+
+            for (schema_block, input_block) in schema_blocks.iter().zip(&input_blocks).skip(1) {
+                if walker.should_stop(&result) {
+                    return (result, false);
+                }
+                result.join_other_result(
+                    &NodeVsNodeValidator
+                        .validate(&walker.with_cursors(schema_block, input_block), got_eof),
+                );
+            }
+
+            // The input item's other blocks may still be on their way
+            if schema_blocks.len() != input_blocks.len()
+                && !input_blocks
+                    .last()
+                    .is_some_and(|block| waiting_at_end(got_eof, walker.input_str(), block))
+            {
+                result.add_error(ValidationError::SchemaViolation(
+                    SchemaViolationError::ChildrenLengthMismatch {
+                        schema_index: schema_item_cursor.descendant_index(),
+                        input_index: input_item_cursor.descendant_index(),
+                        expected: schema_blocks.len().into(),
+                        actual: input_blocks.len(),
+                    },
+                ));
+            }
+            (result, false)
+        }
+        (Some(schema_block), None) => {
+            // Input has only marker, no content yet
+            // Only report error if we've reached EOF - otherwise more content may be coming
+            if !waiting_at_end(got_eof, walker.input_str(), &input_cursor) {
+                result.add_error(ValidationError::SchemaViolation(
+                    SchemaViolationError::MalformedNodeStructure {
+                        kind: MalformedStructureKind::MissingListItemContent,
+                        schema_index: schema_block.descendant_index(),
+                        input_index: input_cursor.descendant_index(),
+                    },
+                ));
+            }
+            (result, true)
+        }
+        (None, Some(input_block)) => {
+            result.add_error(ValidationError::SchemaViolation(
+                SchemaViolationError::MalformedNodeStructure {
+                    kind: MalformedStructureKind::HadExtraListItem,
+                    schema_index: schema_cursor.descendant_index(),
+                    input_index: input_block.descendant_index(),
+                },
+            ));
+            (result, true)
+        }
+        (None, None) => (result, true),
+    }
+}
+
+/// Validate the first block of an input list item against the first block of
+/// a schema list item.
+///
+/// Paragraphs are textual containers that may repeat, or task items with a
+/// checkbox. Other blocks, like a code block that an item starts with, are
+/// validated like anywhere else.
+fn validate_first_list_item_block<'a>(
+    walker: &ValidatorWalker<'a>,
+    schema_cursor: &TreeCursor<'a>,
+    input_cursor: &TreeCursor<'a>,
+    got_eof: bool,
+) -> ValidationResult {
+    let walker = walker.with_cursors(schema_cursor, input_cursor);
+    if !both_are_paragraphs(&schema_cursor.node(), &input_cursor.node()) {
+        return NodeVsNodeValidator.validate(&walker, got_eof);
+    }
+
+    let schema_text = get_node_text(&schema_cursor.node(), walker.schema_str());
+    if let Some((checkbox, checkbox_len)) = task_checkbox(schema_text) {
+        return validate_task_item_paragraph(
+            &walker,
+            schema_cursor,
+            input_cursor,
+            checkbox,
+            checkbox_len,
+            got_eof,
+        );
+    }
+
+    ContainerVsContainerValidatorBuilder::default()
+        .allow_repeating(true)
+        .build()
+        .unwrap()
+        .validate(&walker, got_eof)
+}
+
+/// The cursors at the blocks of a list item after its marker, without the
+/// list nested at the end of it, which is validated as a list of its own.
+fn list_item_blocks<'a>(
+    list_item_cursor: &TreeCursor<'a>,
+    skip: impl Fn(&Node) -> bool,
+) -> Vec<TreeCursor<'a>> {
+    let mut blocks = Vec::new();
+    let mut cursor = list_item_cursor.clone();
+    // The first child is the marker
+    if !cursor.goto_first_child() {
+        return blocks;
+    }
+    while goto_next_sibling_skipping(&mut cursor, &skip) {
+        blocks.push(cursor.clone());
+    }
+
+    if blocks
+        .last()
+        .is_some_and(|block| is_list_node(&block.node()))
+    {
+        blocks.pop();
+    }
+    blocks
+}
+
+/// The checkbox at the start of a task list item, like `- [x] Buy milk`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Checkbox {
+    /// `[ ]`
+    Unchecked,
+    /// `[x]` or `[X]`
+    Checked,
+    /// `[?]`, which only schemas use, for an item that may be either.
+    Either,
+}
+
+impl Checkbox {
+    /// How the checkbox is written, or both ways for `Either`.
+    fn marker(&self) -> &'static str {
+        match self {
+            Checkbox::Unchecked => "[ ]",
+            Checkbox::Checked => "[x]",
+            Checkbox::Either => "[ ] or [x]",
+        }
+    }
+}
+
+/// The checkbox that the text of a list item's paragraph starts with, and how
+/// many bytes it takes up along with the whitespace after it.
+///
+/// We look at the bytes rather than the tree, since the grammar has no node
+/// for task markers, and may even parse `[x]` as a link.
+fn task_checkbox(text: &str) -> Option<(Checkbox, usize)> {
+    let checkbox = match text.as_bytes().get(..3)? {
+        b"[ ]" => Checkbox::Unchecked,
+        b"[x]" | b"[X]" => Checkbox::Checked,
+        b"[?]" => Checkbox::Either,
+        _ => return None,
+    };
+
+    let after = &text[3..];
+    let rest = after.trim_start_matches([' ', '\t']);
+    if rest.len() == after.len() && !after.is_empty() && !after.starts_with(['\n', '\r']) {
+        return None;
+    }
+    Some((checkbox, text.len() - rest.len()))
+}
+
+/// The cursor at the code span right after the checkbox of a task list item's
+/// paragraph, if there is one.
+fn code_span_after_checkbox<'a>(
+    paragraph_cursor: &TreeCursor<'a>,
+    schema_str: &str,
+) -> Option<TreeCursor<'a>> {
+    let (_, checkbox_len) = task_checkbox(get_node_text(&paragraph_cursor.node(), schema_str))?;
+    let content_start = paragraph_cursor.node().start_byte() + checkbox_len;
+
+    let mut cursor = paragraph_cursor.clone();
+    if !cursor.goto_first_child() {
+        return None;
+    }
+    // The checkbox is text, or a link, before the code span
+    while cursor.node().end_byte() <= content_start {
+        if !cursor.goto_next_sibling() {
+            return None;
+        }
+    }
+
+    (is_inline_code_node(&cursor.node()) && cursor.node().start_byte() == content_start)
+        .then_some(cursor)
+}
+
+/// Validate the paragraph of an input task list item against the paragraph of
+/// a schema task list item, like `` - [ ] `task:/.+/` ``.
+///
+/// The input has to have a checkbox in the state the schema's checkbox asks
+/// for, where `[?]` accepts either. The text after it is then compared with
+/// the text after the schema's checkbox, which is literal text, or a matcher
+/// with literal text after it.
+///
+/// A matcher after `[?]` captures `{"text": ..., "checked": ...}`, since the
+/// state isn't known up front, and after `[ ]` or `[x]` just the text.
+fn validate_task_item_paragraph(
+    walker: &ValidatorWalker,
+    schema_cursor: &TreeCursor,
+    input_cursor: &TreeCursor,
+    checkbox: Checkbox,
+    checkbox_len: usize,
+    got_eof: bool,
+) -> ValidationResult {
+    let mut result = ValidationResult::from_cursors(schema_cursor, input_cursor);
+    let schema_index = schema_cursor.descendant_index();
+    let input_index = input_cursor.descendant_index();
+    let is_partial_match = waiting_at_end(got_eof, walker.input_str(), input_cursor);
+
+    let schema_text = get_node_text(&schema_cursor.node(), walker.schema_str());
+    let input_text = get_node_text(&input_cursor.node(), walker.input_str());
+
+    let checked = match task_checkbox(input_text) {
+        Some((Checkbox::Unchecked, len)) => Some((false, len)),
+        Some((Checkbox::Checked, len)) => Some((true, len)),
+        _ => None,
+    };
+    let Some((checked, input_checkbox_len)) = checked else {
+        // The checkbox may still be on its way
+        if is_partial_match && input_text.trim_end().len() < 3 {
+            return result;
+        }
+        result.add_error(ValidationError::SchemaViolation(
+            SchemaViolationError::ListMarkerMismatch {
+                schema_index,
+                input_index,
+                expected: checkbox.marker().to_string(),
+                actual: String::new(),
+            },
+        ));
+        return result;
+    };
+
+    let expected_checked = match checkbox {
+        Checkbox::Unchecked => Some(false),
+        Checkbox::Checked => Some(true),
+        Checkbox::Either => None,
+    };
+    if expected_checked.is_some_and(|expected| expected != checked) {
+        result.add_error(ValidationError::SchemaViolation(
+            SchemaViolationError::ListMarkerMismatch {
+                schema_index,
+                input_index,
+                expected: checkbox.marker().to_string(),
+                actual: input_text[..3].to_string(),
+            },
+        ));
+        return result;
+    }
+
+    let schema_rest = schema_text[checkbox_len..].trim_end();
+    let input_rest = input_text[input_checkbox_len..].trim_end();
+
+    let matcher = match code_span_after_checkbox(schema_cursor, walker.schema_str()) {
+        Some(code_cursor) => {
+            match try_from_code_and_text_node_cursor(
+                &code_cursor,
+                walker.schema_str(),
+                &walker.options().regex_limits,
+            ) {
+                Ok(matcher) => Some((matcher, code_cursor)),
+                Err(MatcherError::WasLiteralCode) => None,
+                Err(error) => {
+                    result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                        error,
+                        schema_index: code_cursor.descendant_index(),
+                    }));
+                    return result;
+                }
+            }
+        }
+        None => None,
+    };
+
+    let Some((matcher, code_cursor)) = matcher else {
+        if schema_rest == input_rest {
+            return result;
+        }
+        if is_partial_match && schema_rest.starts_with(input_rest) {
+            // We may get the rest of it later
+            return result;
+        }
+        result.add_error(ValidationError::SchemaViolation(
+            SchemaViolationError::NodeContentMismatch {
+                schema_index,
+                input_index,
+                expected: schema_rest.to_string(),
+                actual: input_rest.to_string(),
+                kind: NodeContentMismatchKind::Literal,
+            },
+        ));
+        return result;
+    };
+
+    // More input may still change the match
+    if is_partial_match {
+        return result;
+    }
+
+    // What follows the matcher's extras has to follow its match
+    let after_code =
+        &schema_text[code_cursor.node().end_byte() - schema_cursor.node().start_byte()..];
+    let suffix = get_after_extras(after_code)
+        .unwrap_or(after_code)
+        .trim_end();
+    let Some(candidate) = input_rest.strip_suffix(suffix) else {
+        result.add_error(ValidationError::SchemaViolation(
+            SchemaViolationError::NodeContentMismatch {
+                schema_index: code_cursor.descendant_index(),
+                input_index,
+                expected: suffix.to_string(),
+                actual: input_rest.to_string(),
+                kind: NodeContentMismatchKind::Suffix,
+            },
+        ));
+        return result;
+    };
+
+    let matched = match matcher.try_match_str(candidate, walker.options().max_matcher_input_len) {
+        Ok(Some(matched)) if matched.len() == candidate.len() => matched,
+        Ok(_) => {
+            result.add_error(ValidationError::SchemaViolation(matcher.mismatch_error(
+                code_cursor.descendant_index(),
+                input_index,
+                candidate.to_string(),
+            )));
+            return result;
+        }
+        Err(error) => {
+            result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                error,
+                schema_index: code_cursor.descendant_index(),
+            }));
+            return result;
+        }
+    };
+
+    // The match is all of the candidate, which starts right after the checkbox
+    let matched_start = input_cursor.node().start_byte() + input_checkbox_len;
+    if let Some(id) = matcher.id()
+        && let Some(value) = result.typed_value(
+            &matcher,
+            walker.captured_value(matched, matched_start),
+            code_cursor.descendant_index(),
+            input_index,
+        )
+    {
+        let value = match checkbox {
+            Checkbox::Either => json!({ "text": value, "checked": checked }),
+            _ => value,
+        };
+        result.set_match(id, value);
+    }
+
+    result
+}
+
+/// With `ValidateOptions::strict_list_tightness`, check that the input list is
+/// tight if the schema list is and loose if the schema list is, with both
+/// cursors at the lists. Otherwise `compare_node_kinds` treats them as the
+/// same kind of list.
+fn list_tightness_mismatch(
+    walker: &ValidatorWalker,
+    schema_cursor: &TreeCursor,
+    input_cursor: &TreeCursor,
+) -> Option<ValidationError> {
+    let schema_node = schema_cursor.node();
+    let input_node = input_cursor.node();
+    if !walker.options().strict_list_tightness
+        || !both_are_list_nodes(&schema_node, &input_node)
+        || schema_node.kind() == input_node.kind()
+    {
+        return None;
+    }
+
+    Some(ValidationError::SchemaViolation(
+        SchemaViolationError::NodeTypeMismatch {
+            schema_index: schema_cursor.descendant_index(),
+            input_index: input_cursor.descendant_index(),
+            expected: schema_node.kind().into(),
+            actual: input_node.kind().into(),
+        },
+    ))
+}
+
+/// Check the marker of an input list item against the marker of the schema
+/// list item it is validated against, with both cursors at the markers.
+///
+/// Bullets have to be the schema's bullet. Ordered items are numbered like the
+/// schema, and the items a repeated matcher captures count up from its number,
+/// so `` 1. `item:/\w+/`{1,} `` wants `1.`, `2.`, `3.` and so on.
+fn list_marker_mismatch(
+    walker: &ValidatorWalker,
+    schema_cursor: &TreeCursor,
+    input_cursor: &TreeCursor,
+) -> Option<ValidationError> {
+    if !both_are_list_markers(&schema_cursor.node(), &input_cursor.node()) {
+        return None;
+    }
+
+    let schema_marker = get_node_text(&schema_cursor.node(), walker.schema_str()).trim();
+    let input_marker = get_node_text(&input_cursor.node(), walker.input_str()).trim();
+
+    let expected = match ordered_marker_parts(schema_marker) {
+        Some((number, delimiter)) => {
+            // Both markers' items are how far along their lists they are
+            let schema_position = schema_cursor.node().parent().map_or(0, |n| item_position(&n));
+            let input_position = input_cursor.node().parent().map_or(0, |n| item_position(&n));
+            match (number + input_position).checked_sub(schema_position) {
+                Some(number) => format!("{}{}", number, delimiter),
+                None => schema_marker.to_string(),
+            }
+        }
+        None => schema_marker.to_string(),
+    };
+
+    (expected != input_marker).then(|| {
+        ValidationError::SchemaViolation(SchemaViolationError::ListMarkerMismatch {
+            schema_index: schema_cursor.descendant_index(),
+            input_index: input_cursor.descendant_index(),
+            expected,
+            actual: input_marker.to_string(),
+        })
+    })
+}
+
+/// The number and delimiter of an ordered list marker, like `(2, '.')` for
+/// `2.`, or `None` for a bullet.
+fn ordered_marker_parts(marker: &str) -> Option<(usize, char)> {
+    let delimiter = marker.chars().last().filter(|c| matches!(c, '.' | ')'))?;
+    let number = marker[..marker.len() - 1].parse().ok()?;
+    Some((number, delimiter))
+}
+
+/// How many list items come before a list item in its list.
+fn item_position(list_item: &Node) -> usize {
+    let mut position = 0;
+    let mut sibling = list_item.prev_sibling();
+    while let Some(node) = sibling {
+        if is_list_item_node(&node) {
+            position += 1;
+        }
+        sibling = node.prev_sibling();
     }
+    position
+}
 
-    schema_cursor.goto_first_child();
-    input_cursor.goto_first_child();
+/// Add a broken invariant to the result of
+/// `validate_list_item_contents_vs_list_item_contents`, which returns more
+/// than just its result.
+#[cfg(feature = "invariant_violations")]
+fn item_contents_invariant_violation(
+    mut result: ValidationResult,
+    schema_cursor: &TreeCursor,
+    input_cursor: &TreeCursor,
+    message: &str,
+) -> ValidationResult {
+    invariant_violation!(result, schema_cursor, input_cursor, "{}", message)
+}
 
-    #[cfg(feature = "invariant_violations")]
-    if !both_are_markers(&schema_cursor.node(), &input_cursor.node()) {
-        invariant_violation!(
-            result,
-            &schema_cursor,
-            &input_cursor,
-            "expected list_marker nodes while validating list item contents"
-        );
-    }
+/// Build the array of values for a repeated list matcher, in the legacy shape.
+///
+/// If we have a schema:
+///
+/// ```md
+/// - `name:/test\d/`{2,2}
+///   - `name:/test\d/`{1,1}
+/// ```
+///
+/// Initially, we run this at the top level, gather something like
+///
+/// values_at_level = [{ "test": "test1" }, { "test": "test2" }]
+///
+/// Then we might recurse, and end up with something like
+///
+/// values_at_level = [{ "test": "test1" }, { "test": "test2" }, { "deep": "test3" }]
+///
+/// Then we iterate over the values_at_level and unpack all the ones that have our
+/// id (we are top level), so "test," and get
+///
+/// values_at_level = ["test1", "test2", { "deep": "test3" }]
+///
+/// Note that we don't unpack anything that is not our id, or an item that
+/// captured more than our id, like from a code block after its paragraph.
+pub(super) fn legacy_list_values(matcher_id: &str, values_at_level: &[Value]) -> Vec<Value> {
+    let path = match_path(matcher_id);
+    values_at_level
+        .iter()
+        .map(|value| match sole_value_at_path(value, &path) {
+            // Unwrap it to be loose in the array if we can
+            Some(match_for_same_id) => match_for_same_id.clone(),
+            None => value.clone(), // don't unpack!
+        })
+        .collect()
+}
 
-    match (
-        schema_cursor.goto_next_sibling(),
-        input_cursor.goto_next_sibling(),
-    ) {
-        (true, true) => {
-            #[cfg(feature = "invariant_violations")]
-            if !both_are_paragraphs(&schema_cursor.node(), &input_cursor.node()) {
-                invariant_violation!(
-                    result,
-                    &schema_cursor,
-                    &input_cursor,
-                    "expected paragraph nodes while validating list item contents"
-                );
+/// Build the array of values for a repeated list matcher, in the structured
+/// shape.
+///
+/// Every item becomes `{"value": ..., "children": {...}}`, where the captures
+/// of the list nested in the last item go in its `children`. For the schema
+/// above we get
+///
+/// ```json
+/// [
+///     { "value": "test1", "children": {} },
+///     { "value": "test2", "children": { "deep": [...] } }
+/// ]
+/// ```
+pub(super) fn structured_list_values(
+    matcher_id: &str,
+    values_at_level: &[Value],
+    nested_value: Option<Value>,
+) -> Vec<Value> {
+    let mut values: Vec<Value> = values_at_level
+        .iter()
+        .map(|value| {
+            let mut children = value.clone();
+            let own_value = take_value_at_path(&mut children, &match_path(matcher_id))
+                .unwrap_or(Value::Null);
+            if !children.is_object() {
+                children = json!({});
             }
+            json!({ "value": own_value, "children": children })
+        })
+        .collect();
 
-            let walker =
-                ValidatorWalker::from_cursors(&schema_cursor, schema_str, &input_cursor, input_str);
-
-            (
-                ContainerVsContainerValidatorBuilder::default()
-                    .allow_repeating(true)
-                    .build()
-                    .unwrap()
-                    .validate(&walker, got_eof),
-                false,
-            )
-        }
-        (true, false) => {
-            // Input has only marker, no content yet
-            // Only report error if we've reached EOF - otherwise more content may be coming
-            if !waiting_at_end(got_eof, input_str, &input_cursor) {
-                result.add_error(ValidationError::SchemaViolation(
-                    SchemaViolationError::MalformedNodeStructure {
-                        kind: MalformedStructureKind::MissingListItemContent,
-                        schema_index: schema_cursor.descendant_index(),
-                        input_index: input_cursor.descendant_index(),
-                    },
-                ));
-            }
-            (result, true)
-        }
-        (false, true) => {
-            result.add_error(ValidationError::SchemaViolation(
-                SchemaViolationError::MalformedNodeStructure {
-                    kind: MalformedStructureKind::HadExtraListItem,
-                    schema_index: schema_cursor.descendant_index(),
-                    input_index: input_cursor.descendant_index(),
-                },
-            ));
-            (result, true)
+    if let (Some(last), Some(Value::Object(nested))) = (values.last_mut(), nested_value) {
+        for (key, value) in nested {
+            last["children"][key] = value;
         }
-        (false, false) => (result, true),
     }
+
+    values
 }
 
 /// Creates a new matcher from a tree-sitter cursor pointing at code node in
@@ -637,10 +1499,12 @@ fn validate_list_item_contents_vs_list_item_contents(
 ///
 /// This will attempt to grab the current node the cursor is pointing at,
 /// which must be a code node, and the following one, which will be counted
-/// as extras if it is a text node.
+/// as extras if it is a text node. The matcher's regex is compiled within
+/// `limits`.
 fn try_from_code_and_text_node_cursor(
     cursor: &TreeCursor,
     schema_str: &str,
+    limits: &RegexLimits,
 ) -> Result<Matcher, MatcherError> {
     let (node, next_node) = get_node_and_next_node(cursor).ok_or_else(|| {
         MatcherError::InvariantViolation(
@@ -654,23 +1518,25 @@ fn try_from_code_and_text_node_cursor(
         ));
     }
 
-    try_from_code_and_text_node(node, next_node, schema_str)
+    try_from_code_and_text_node(node, next_node, schema_str, limits)
 }
 
 /// Create a new Matcher from two tree-sitter nodes and a schema string.
 ///
 /// - The first node should be a code_span node containing the matcher pattern.
 /// - The second node (optional) should be a text node containing extras.
+/// - The matcher's regex is compiled within `limits`.
 fn try_from_code_and_text_node(
     matcher_node: tree_sitter::Node,
     suffix_node: Option<tree_sitter::Node>,
     schema_str: &str,
+    limits: &RegexLimits,
 ) -> Result<Matcher, MatcherError> {
     let matcher_text = get_node_text(&matcher_node, schema_str);
 
     let suffix_text = suffix_node.map(|node| get_node_text(&node, schema_str));
 
-    Matcher::try_from_pattern_and_suffix_str(matcher_text, suffix_text)
+    Matcher::try_from_pattern_and_suffix_str_with_limits(matcher_text, suffix_text, limits)
 }
 
 /// Walk from a list item node to the actual content, which is a paragraph node.
@@ -687,9 +1553,13 @@ fn try_from_code_and_text_node(
 /// - The matcher is not repeated
 ///
 /// Otherwise we attempt to construct the matcher, maybe returning an error.
-fn extract_repeated_matcher_from_list_item(
+/// The matcher is only built the first time, within `limits`, and taken from
+/// `matcher_cache` for every item of the input list after that.
+pub(super) fn extract_repeated_matcher_from_list_item(
     schema_cursor: &TreeCursor,
     schema_str: &str,
+    matcher_cache: &MatcherCache,
+    limits: &RegexLimits,
 ) -> Option<Result<Matcher, MatcherError>> {
     #[cfg(feature = "invariant_violations")]
     if schema_cursor.node().kind() != "list_item" {
@@ -731,17 +1601,34 @@ fn extract_repeated_matcher_from_list_item(
         trace!("List item does not contain a single code child");
         return None;
     }
-    // list_item -> code_span (first item in list_item)
-    list_item_cursor.goto_first_child();
+    // A task item's matcher comes right after its checkbox
+    if let Some(code_cursor) = code_span_after_checkbox(&list_item_cursor, schema_str) {
+        list_item_cursor = code_cursor;
+    } else {
+        // list_item -> code_span (first item in list_item)
+        list_item_cursor.goto_first_child();
+    }
     if list_item_cursor.node().kind() != "code_span" {
         trace!("List item code_span is not the first paragraph child");
         return None;
     }
 
-    match try_from_code_and_text_node_cursor(&list_item_cursor, schema_str) {
-        Ok(matcher) if matcher.is_repeated() => Some(Ok(matcher)),
+    // The matcher is built from the code span and all of the text after it
+    let matcher_range = match get_node_and_next_node(&list_item_cursor) {
+        Some((node, Some(next_node))) => node.start_byte()..next_node.end_byte(),
+        _ => list_item_cursor.node().byte_range(),
+    };
+    let matcher = matcher_cache.get_or_build(matcher_range, || {
+        try_from_code_and_text_node_cursor(&list_item_cursor, schema_str, limits)
+    });
+    match matcher {
+        Ok(matcher) if matcher.is_repeated() || matcher.is_optional() => Some(Ok(matcher)),
         Ok(_) => None,
-        Err(e @ MatcherError::MatcherInteriorRegexInvalid(_)) => Some(Err(e)),
+        Err(
+            e @ (MatcherError::MatcherInteriorRegexInvalid(_)
+            | MatcherError::UnsupportedRegexSyntax(_)
+            | MatcherError::RegexTooComplex { .. }),
+        ) => Some(Err(e)),
         Err(e) => {
             trace!("Failed to extract repeated matcher from list item: {}", e);
             None
@@ -753,7 +1640,7 @@ fn extract_repeated_matcher_from_list_item(
 ///
 /// Successful if we manage to get to the next list item, otherwise error
 /// because there was none.
-fn ensure_at_first_list_item(input_cursor: &mut TreeCursor) -> Result<(), ()> {
+pub(super) fn ensure_at_first_list_item(input_cursor: &mut TreeCursor) -> Result<(), ()> {
     if !is_list_item_node(&input_cursor.node()) {
         if !input_cursor.goto_first_child() {
             return Err(());
@@ -775,7 +1662,8 @@ mod tests {
 
     use super::super::test_utils::ValidatorTester;
     use super::{
-        ListVsListValidator, ensure_at_first_list_item, extract_repeated_matcher_from_list_item,
+        ListVsListValidator, MatcherCache, ensure_at_first_list_item,
+        extract_repeated_matcher_from_list_item,
     };
     use crate::mdschema::validation::errors::ChildrenLengthRange;
     use crate::mdschema::validation::{
@@ -863,7 +1751,9 @@ mod tests {
             }
         }
 
-        let matcher = try_from_code_and_text_node_cursor(&cursor, schema_str).unwrap();
+        let matcher =
+            try_from_code_and_text_node_cursor(&cursor, schema_str, &RegexLimits::default())
+                .unwrap();
 
         assert_eq!(matcher.id(), Some("word"));
         assert_eq!(matcher.match_str("hello"), Some("hello"));
@@ -881,9 +1771,14 @@ mod tests {
         input_cursor.goto_first_child();
         let _ = ensure_at_first_list_item(&mut input_cursor);
 
-        let matcher = extract_repeated_matcher_from_list_item(&mut input_cursor, input_str)
-            .unwrap()
-            .unwrap();
+        let matcher = extract_repeated_matcher_from_list_item(
+            &mut input_cursor,
+            input_str,
+            &MatcherCache::default(),
+            &RegexLimits::default(),
+        )
+        .unwrap()
+        .unwrap();
 
         assert_eq!(matcher.id(), Some("name"));
         // MatcherType is now always a regex pattern
@@ -910,9 +1805,14 @@ mod tests {
         // schema_cursor.goto_first_child(); // -> list_marker
         // assert_eq!(schema_cursor.node().kind(), "list_marker");
 
-        let matcher = extract_repeated_matcher_from_list_item(&schema_cursor, schema_str)
-            .unwrap()
-            .unwrap();
+        let matcher = extract_repeated_matcher_from_list_item(
+            &schema_cursor,
+            schema_str,
+            &MatcherCache::default(),
+            &RegexLimits::default(),
+        )
+        .unwrap()
+        .unwrap();
         assert_eq!(matcher.id(), "name".into());
     }
 
@@ -1247,7 +2147,7 @@ Footer: test (footer isn't validated with_list_vs_list)
                     kind: NodeContentMismatchKind::Matcher,
                     schema_index: 11,
                     input_index: 9,
-                    expected: "^line2test\\d".into(),
+                    expected: "line2test\\d".into(),
                     actual: "test2".into(),
                 }
             )],
@@ -1278,6 +2178,121 @@ Footer: test (footer isn't validated with_list_vs_list)
         );
     }
 
+    #[test]
+    fn test_validate_list_vs_list_with_depth_extras() {
+        let schema_str = r#"
+- `item:/\w+/`++{1,3}
+"#;
+        let input_str = r#"
+- a
+- b
+    - c
+    - d
+"#;
+
+        let result = validate_lists(schema_str, input_str, true);
+
+        assert!(result.errors().is_empty());
+        assert_eq!(result.value(), &json!({"item": ["a", "b", ["c", "d"]]}));
+    }
+
+    #[test]
+    fn test_validate_list_vs_list_with_depth_extras_and_child_list() {
+        let schema_str = r#"
+- `item:/\w+/`++{1,3}
+    - `sub:/\d+/`{1,}
+"#;
+        let input_str = r#"
+- a
+    - 1
+"#;
+
+        let result = validate_lists(schema_str, input_str, true);
+
+        assert!(result.errors().is_empty());
+        assert_eq!(result.value(), &json!({"item": ["a", {"sub": ["1"]}]}));
+    }
+
+    #[test]
+    fn test_validate_list_vs_list_with_depth_extras_child_list_wins() {
+        let schema_str = r#"
+- `item:/\w+/`++{1,3}
+    - `sub:/\d+/`{1,}
+"#;
+        let input_str = r#"
+- a
+    - b
+"#;
+
+        let result = validate_lists(schema_str, input_str, true);
+
+        assert_eq!(
+            result.errors(),
+            &[ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch {
+                    kind: NodeContentMismatchKind::Matcher,
+                    schema_index: 12,
+                    input_index: 10,
+                    expected: "\\d+".into(),
+                    actual: "b".into(),
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_validate_list_vs_list_too_deep_for_depth_extras() {
+        let schema_str = r#"
+- `item:/\w+/`++{1,}
+"#;
+        let input_str = r#"
+- a
+    - b
+        - c
+"#;
+
+        let result = validate_lists(schema_str, input_str, true);
+
+        assert_eq!(
+            result.errors(),
+            &[ValidationError::SchemaViolation(
+                SchemaViolationError::NodeListTooDeep {
+                    schema_index: 2,
+                    input_index: 11,
+                    max_depth: 2,
+                }
+            )]
+        );
+        assert_eq!(result.value(), &json!({"item": ["a", ["b"]]}));
+    }
+
+    #[test]
+    fn test_validate_list_vs_list_with_depth_extras_too_many_nested() {
+        let schema_str = r#"
+- `item:/\w+/`++{1,2}
+"#;
+        let input_str = r#"
+- a
+    - b
+    - c
+    - d
+"#;
+
+        let result = validate_lists(schema_str, input_str, true);
+
+        assert_eq!(
+            result.errors(),
+            &[ValidationError::SchemaViolation(
+                SchemaViolationError::ChildrenLengthMismatch {
+                    schema_index: 2,
+                    input_index: 6,
+                    expected: ChildrenLengthRange(1, 2),
+                    actual: 3,
+                }
+            )]
+        );
+    }
+
     #[test]
     fn test_validate_list_vs_list_with_deep_nesting() {
         let schema_str = r#"
@@ -1839,4 +2854,210 @@ Footer: test (footer isn't validated with_list_vs_list)
             "Should not report error when streaming and only marker received"
         );
     }
+
+    #[test]
+    fn test_task_list_items_capture_checked_state() {
+        let schema_str = "- [?] `task:/.+/`{1,}";
+        let input_str = "- [ ] buy milk\n- [x] walk the dog\n- [X] call home";
+        let result = validate_lists(schema_str, input_str, true);
+
+        assert_eq!(result.errors(), &[]);
+        assert_eq!(
+            *result.value(),
+            json!({"task": [
+                {"text": "buy milk", "checked": false},
+                {"text": "walk the dog", "checked": true},
+                {"text": "call home", "checked": true}
+            ]})
+        );
+    }
+
+    #[test]
+    fn test_repeated_list_matcher_with_regex_flags() {
+        let schema_str = "- `lang:/[a-z]+/i`{2,}";
+
+        let result = validate_lists(schema_str, "- Rust\n- GO\n- zig", true);
+        assert_eq!(result.errors(), &[]);
+        assert_eq!(*result.value(), json!({"lang": ["Rust", "GO", "zig"]}));
+
+        let result = validate_lists(schema_str, "- Rust\n- 42", true);
+        assert!(!result.errors().is_empty());
+    }
+
+    #[test]
+    fn test_task_list_items_with_the_wrong_state() {
+        let schema_str = "- [x] `task:/.+/`{1,}";
+        let input_str = "- [x] buy milk\n- [ ] walk the dog";
+        let result = validate_lists(schema_str, input_str, true);
+
+        assert!(matches!(
+            result.errors(),
+            [ValidationError::SchemaViolation(
+                SchemaViolationError::ListMarkerMismatch { expected, actual, .. }
+            )] if expected == "[x]" && actual == "[ ]"
+        ));
+    }
+
+    #[test]
+    fn test_literal_task_list_items() {
+        let schema_str = "- [ ] Buy milk\n- [x] Walk the dog";
+
+        let result = validate_lists(schema_str, schema_str, true);
+        assert_eq!(result.errors(), &[]);
+        assert_eq!(result.value(), &json!({}));
+
+        let result = validate_lists(schema_str, "- Buy milk\n- [x] Walk the dog", true);
+        assert!(matches!(
+            result.errors(),
+            [ValidationError::SchemaViolation(
+                SchemaViolationError::ListMarkerMismatch { expected, actual, .. }
+            )] if expected == "[ ]" && actual.is_empty()
+        ));
+
+        let result = validate_lists(schema_str, "- [ ] Buy bread\n- [x] Walk the dog", true);
+        assert!(matches!(
+            result.errors(),
+            [ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch {
+                    kind: NodeContentMismatchKind::Literal,
+                    expected,
+                    actual,
+                    ..
+                }
+            )] if expected == "Buy milk" && actual == "Buy bread"
+        ));
+    }
+
+    #[test]
+    fn test_streaming_task_list_item_waits_for_its_checkbox() {
+        let schema_str = "- [x] `task:/.+/`";
+
+        let result = validate_lists(schema_str, "- [x", false);
+        assert_eq!(result.errors(), &[]);
+
+        let result = validate_lists(schema_str, "- [x] buy", true);
+        assert_eq!(result.errors(), &[]);
+        assert_eq!(result.value(), &json!({"task": "buy"}));
+    }
+
+    #[test]
+    fn test_code_block_in_list_item() {
+        let schema_str = "- Install it\n\n  ```bash\n  cargo install mdvalidate\n  ```\n- Run it\n";
+
+        let result = validate_lists(schema_str, schema_str, true);
+        assert_eq!(result.errors(), &[]);
+        assert_eq!(result.value(), &json!({}));
+
+        let input_str = "- Install it\n\n  ```bash\n  cargo install mdv\n  ```\n- Run it\n";
+        let result = validate_lists(schema_str, input_str, true);
+        assert!(matches!(
+            result.errors(),
+            [ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch {
+                    kind: NodeContentMismatchKind::Literal,
+                    ..
+                }
+            )]
+        ));
+
+        let result = validate_lists(schema_str, "- Install it\n\n- Run it\n", true);
+        assert_eq!(
+            result.errors(),
+            &[ValidationError::SchemaViolation(
+                SchemaViolationError::ChildrenLengthMismatch {
+                    schema_index: 2,
+                    input_index: 2,
+                    expected: 2.into(),
+                    actual: 1,
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_code_block_in_repeated_list_items() {
+        let schema_str = "- `step:/\\w+/`{1,}\n\n  ```{lang:/\\w+/}\n  make\n  ```\n";
+        let input_str = "- Build\n\n  ```sh\n  make\n  ```\n- Test\n\n  ```bash\n  make\n  ```\n";
+
+        let result = validate_lists(schema_str, input_str, true);
+        assert_eq!(result.errors(), &[]);
+        assert_eq!(
+            result.value(),
+            &json!({"step": [
+                {"step": "Build", "lang": "sh"},
+                {"step": "Test", "lang": "bash"}
+            ]})
+        );
+
+        // Every item needs the code block
+        let input_str = "- Build\n\n  ```sh\n  make\n  ```\n- Test\n";
+        let result = validate_lists(schema_str, input_str, true);
+        assert!(matches!(
+            result.errors(),
+            [ValidationError::SchemaViolation(
+                SchemaViolationError::ChildrenLengthMismatch {
+                    expected: ChildrenLengthRange(2, 2),
+                    actual: 1,
+                    ..
+                }
+            )]
+        ));
+    }
+
+    #[test]
+    fn test_block_quote_in_list_item() {
+        let schema_str = "- Note\n\n  > Be careful\n";
+
+        let result = validate_lists(schema_str, schema_str, true);
+        assert_eq!(result.errors(), &[]);
+
+        let result = validate_lists(schema_str, "- Note\n\n  > Be quick\n", true);
+        assert!(matches!(
+            result.errors(),
+            [ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch {
+                    kind: NodeContentMismatchKind::Literal,
+                    expected,
+                    actual,
+                    ..
+                }
+            )] if expected == "Be careful" && actual == "Be quick"
+        ));
+
+        let result = validate_lists(schema_str, "- Note\n\n  > Be careful\n\n  > Twice\n", true);
+        assert!(matches!(
+            result.errors(),
+            [ValidationError::SchemaViolation(
+                SchemaViolationError::ChildrenLengthMismatch {
+                    expected: ChildrenLengthRange(2, 2),
+                    actual: 3,
+                    ..
+                }
+            )]
+        ));
+    }
+
+    #[test]
+    fn test_block_quote_in_repeated_list_items() {
+        let schema_str = "- `name:/\\w+/`{1,}\n\n  > `known_for:/.+/`\n";
+        let input_str = "- Ada\n\n  > Numbers\n- Grace\n\n  > Compilers\n";
+
+        let result = validate_lists(schema_str, input_str, true);
+        assert_eq!(result.errors(), &[]);
+        assert_eq!(
+            result.value(),
+            &json!({"name": [
+                {"name": "Ada", "known_for": "Numbers"},
+                {"name": "Grace", "known_for": "Compilers"}
+            ]})
+        );
+    }
+
+    #[test]
+    fn test_streaming_list_item_waits_for_its_other_blocks() {
+        let schema_str = "- Install it\n\n  ```bash\n  cargo install mdvalidate\n  ```\n";
+
+        let result = validate_lists(schema_str, "- Install it\n\n", false);
+        assert_eq!(result.errors(), &[]);
+    }
 }
@@ -10,28 +10,68 @@
 //! - `LiteralMatcherVsTextualValidator`: resolves matcher usage when literal
 //!   matchers span multiple textual nodes, computing matches across adjacent
 //!   literal fragments.
+//! - `MultipleMatchersVsTextValidator`: validates textual containers, and
+//!   emphasis with matchers in it, using the literal text between the
+//!   matchers as anchors.
+use std::ops::Range;
+
 use log::trace;
-use serde_json::json;
-use tree_sitter::TreeCursor;
+use serde_json::Value;
+use tree_sitter::{Node, TreeCursor};
 
 use crate::invariant_violation;
 use crate::mdschema::validation::errors::{
     NodeContentMismatchKind, SchemaError, SchemaViolationError, ValidationError,
 };
-use crate::mdschema::validation::matchers::matcher::{Matcher, MatcherError};
+use crate::mdschema::validation::inline_html::WithoutInlineHtml;
+use crate::mdschema::validation::matchers::matcher::{
+    Matcher, MatcherError, is_always_literal_code,
+};
 use crate::mdschema::validation::matchers::matcher_extras::get_after_extras;
 use crate::mdschema::validation::node_pos_pair::NodePosPair;
+use crate::mdschema::validation::spans::subslice_offset;
+use crate::mdschema::validation::text_normalization::NormalizedLines;
 use crate::mdschema::validation::walkers::ValidationResult;
 use crate::mdschema::validation::walkers::helpers::compare_text_contents::compare_text_contents;
 use crate::mdschema::validation::walkers::validators::{Validator, ValidatorImpl};
 use crate::mdschema::validation::ts_types::*;
 use crate::mdschema::validation::ts_utils::{
-    get_next_node, get_node_n_nodes_ahead, get_node_text, waiting_at_end,
+    get_next_node, get_node_n_nodes_ahead, get_node_text, get_node_text_without_closing_hashes,
+    get_str_at_char_boundaries, text_end_without_closing_hashes, waiting_at_end,
 };
+use crate::mdschema::validation::validator::ValidateOptions;
 use crate::mdschema::validation::validator_walker::ValidatorWalker;
 
 use super::textual::validate_textual_vs_textual_direct;
 
+/// Text to match or compare, with its line endings normalized unless we
+/// compare them exactly.
+fn line_endings<'a>(text: &'a str, options: &ValidateOptions) -> NormalizedLines<'a> {
+    match options.strict_line_endings {
+        true => NormalizedLines::exact(text),
+        false => NormalizedLines::new(text),
+    }
+}
+
+/// Where the text of a node ends, before the closing hashes of an ATX heading
+/// unless we compare headings exactly (see
+/// `ValidateOptions::strict_heading_form`).
+fn text_end(node: &Node, src: &str, options: &ValidateOptions) -> usize {
+    match options.strict_heading_form {
+        true => node.end_byte(),
+        false => text_end_without_closing_hashes(node, src),
+    }
+}
+
+/// The text of a node, without the closing hashes of an ATX heading unless we
+/// compare headings exactly.
+fn node_text<'a>(node: &Node, src: &'a str, options: &ValidateOptions) -> &'a str {
+    match options.strict_heading_form {
+        true => get_node_text(node, src),
+        false => get_node_text_without_closing_hashes(node, src),
+    }
+}
+
 #[derive(Default)]
 pub(super) struct MatcherVsTextValidator;
 
@@ -55,7 +95,10 @@ impl ValidatorImpl for MatcherVsTextValidator {
         } else if is_text_node(&schema_cursor.node()) {
             Some(schema_cursor.node())
         } else {
-            unreachable!(
+            invariant_violation!(
+                result,
+                &schema_cursor,
+                &input_cursor,
                 "only should be called with `code_span` or text but got {:?}",
                 schema_cursor.node()
             )
@@ -70,14 +113,15 @@ impl ValidatorImpl for MatcherVsTextValidator {
             }
         };
 
-        let matcher = {
-            // Make sure we create the matcher when we are pointing at a `code_span`
+        // Make sure we create the matcher when we are pointing at a `code_span`
+        let matcher_cursor = {
             let mut schema_cursor = schema_cursor.clone();
             if schema_prefix_node.is_some() && !schema_cursor_is_code_node {
                 schema_cursor.goto_next_sibling();
             }
-            Matcher::try_from_schema_cursor(&schema_cursor, walker.schema_str())
+            schema_cursor
         };
+        let matcher = walker.matcher_at(&matcher_cursor);
 
         // How far along we've validated the input. We'll update this as we go
         let mut input_byte_offset = input_cursor.node().byte_range().start;
@@ -97,6 +141,44 @@ impl ValidatorImpl for MatcherVsTextValidator {
         }
         schema_cursor_at_prefix.goto_first_child();
 
+        // An optional matcher may be left out, with the input going straight
+        // from the text before it to the text after it
+        if let Ok(matcher) = &matcher
+            && matcher.is_optional()
+            && !waiting_at_end(got_eof, walker.input_str(), &input_cursor)
+        {
+            let schema_prefix =
+                schema_prefix_node.map_or("", |node| get_node_text(&node, walker.schema_str()));
+            let schema_suffix = schema_suffix_node
+                .filter(|node| is_text_node(node))
+                .and_then(|node| {
+                    get_after_extras(node_text(&node, walker.schema_str(), walker.options()))
+                })
+                .unwrap_or_default();
+            let input_end = text_end(&input_cursor.node(), walker.input_str(), walker.options());
+            let input_text = walker
+                .input_str()
+                .get(input_byte_offset..input_end)
+                .unwrap_or_default();
+
+            if input_skips_matcher(schema_prefix, schema_suffix, input_text) {
+                trace!("Optional matcher was left out of the input");
+                if let Some(default) = matcher.default_value() {
+                    result.set_matcher_match(
+                        matcher,
+                        Value::String(default.to_string()),
+                        matcher_cursor.descendant_index(),
+                        input_cursor_descendant_index,
+                    );
+                }
+
+                let mut schema_cursor = matcher_cursor.clone();
+                schema_cursor.goto_first_child();
+                result.keep_farther_pos(&NodePosPair::from_cursors(&schema_cursor, &input_cursor));
+                return result;
+            }
+        }
+
         match at_text_and_next_at_literal_matcher(&schema_cursor, walker.schema_str()) {
             Ok(Some(true)) => {
                 let prefix_result = validate_textual_vs_textual_direct(
@@ -105,6 +187,8 @@ impl ValidatorImpl for MatcherVsTextValidator {
                     walker.schema_str(),
                     walker.input_str(),
                     got_eof,
+                    walker.options(),
+                    walker.input_line_starts(),
                 );
                 result.join_other_result(&prefix_result);
             }
@@ -120,13 +204,19 @@ impl ValidatorImpl for MatcherVsTextValidator {
                     let schema_prefix_str = &walker.schema_str()[schema_prefix_node.byte_range()];
 
                     // Calculate how much input we have available from the current offset
-                    let input_prefix_len = walker.input_str().len() - input_byte_offset;
+                    let input_prefix_len =
+                        walker.input_str().len().saturating_sub(input_byte_offset);
 
                     // Check that the input extends enough that we can cover the full prefix.
                     if input_prefix_len >= schema_prefix_str.len() {
                         // We have enough input to compare the full prefix
-                        let input_prefix_str = &walker.input_str()
-                            [input_byte_offset..input_byte_offset + schema_prefix_str.len()];
+                        // The prefix may end inside a multi-byte character of the
+                        // input, in which case it can't match anyway.
+                        let input_prefix_str = get_str_at_char_boundaries(
+                            walker.input_str(),
+                            input_byte_offset,
+                            input_byte_offset + schema_prefix_str.len(),
+                        );
 
                         // Do the actual prefix comparison
                         if schema_prefix_str != input_prefix_str {
@@ -154,7 +244,8 @@ impl ValidatorImpl for MatcherVsTextValidator {
                         input_byte_offset += schema_prefix_node.byte_range().len();
                     } else if got_eof {
                         // We've reached EOF, so the input is complete and too short
-                        let input_prefix_str = &walker.input_str()[input_byte_offset..];
+                        let input_prefix_str =
+                            walker.input_str().get(input_byte_offset..).unwrap_or_default();
 
                         trace!(
                             "Prefix mismatch (input too short at EOF): expected '{}', got '{}'",
@@ -176,8 +267,13 @@ impl ValidatorImpl for MatcherVsTextValidator {
                     } else {
                         // We haven't reached EOF yet, so partial match is OK
                         // Check if what we have so far matches
-                        let input_prefix_str = &walker.input_str()[input_byte_offset..];
-                        let schema_prefix_partial = &schema_prefix_str[..input_prefix_str.len()];
+                        let input_prefix_str =
+                            walker.input_str().get(input_byte_offset..).unwrap_or_default();
+                        let schema_prefix_partial = get_str_at_char_boundaries(
+                            schema_prefix_str,
+                            0,
+                            input_prefix_str.len(),
+                        );
 
                         trace!("Input prefix not long enough, but waiting at end of input");
 
@@ -212,8 +308,11 @@ impl ValidatorImpl for MatcherVsTextValidator {
                     .map(|node| &walker.schema_str()[node.byte_range()])
                     .unwrap_or("");
 
-                let best_prefix_input_we_can_do =
-                    &walker.input_str()[input_cursor.node().byte_range().start..];
+                let best_prefix_input_we_can_do = get_str_at_char_boundaries(
+                    walker.input_str(),
+                    input_cursor.node().start_byte(),
+                    walker.input_str().len(),
+                );
 
                 result.add_error(ValidationError::SchemaViolation(
                     SchemaViolationError::NodeContentMismatch {
@@ -231,37 +330,92 @@ impl ValidatorImpl for MatcherVsTextValidator {
             return result;
         }
 
-        // All input that comes after the expected prefix
-        let input_after_prefix =
-            walker.input_str()[input_byte_offset..input_cursor.node().byte_range().end].to_string();
+        // All input that comes after the expected prefix, with its line
+        // endings normalized unless we compare them exactly
+        let input_end = text_end(&input_cursor.node(), walker.input_str(), walker.options());
+        let input_after_prefix_lines = line_endings(
+            walker
+                .input_str()
+                .get(input_byte_offset..input_end)
+                .unwrap_or_default(),
+            walker.options(),
+        );
+        let input_after_prefix = input_after_prefix_lines.as_str().to_string();
+
+        // Everything in the schema after the matcher and its extras
+        let schema_suffix_lines =
+            schema_suffix_node
+                .filter(|node| is_text_node(node))
+                .map(|node| {
+                    let text = node_text(&node, walker.schema_str(), walker.options());
+                    line_endings(get_after_extras(text).unwrap_or(text), walker.options())
+                });
 
         match matcher {
             Ok(matcher) => {
-                // Actually perform the match for the matcher
-                match matcher.match_str(&input_after_prefix) {
-                    Some(matched_str) => {
+                // A negated matcher takes all the text it is given, so leave
+                // out the schema suffix that the input should end with
+                let match_input = match &schema_suffix_lines {
+                    Some(schema_suffix) if matcher.is_negated() => input_after_prefix
+                        .strip_suffix(schema_suffix.as_str())
+                        .unwrap_or(&input_after_prefix),
+                    _ => &input_after_prefix,
+                };
+
+                // Actually perform the match for the matcher. An unanchored
+                // matcher may skip input, unless it has to line up with a
+                // prefix in the schema.
+                let match_result = matcher
+                    .try_match_str(match_input, walker.options().max_matcher_input_len)
+                    .map(|matched| {
+                        matched.filter(|matched_str| {
+                            schema_prefix_node.is_none()
+                                || subslice_offset(&input_after_prefix, matched_str) == Some(0)
+                        })
+                    });
+                match match_result {
+                    Ok(Some(matched_str)) => {
                         trace!(
                             "Matcher successfully matched input: '{}' (length={})",
                             matched_str,
                             matched_str.len()
                         );
 
-                        input_byte_offset += matched_str.len();
+                        let Some(match_offset) = subslice_offset(&input_after_prefix, matched_str)
+                        else {
+                            invariant_violation!(
+                                result,
+                                &schema_cursor,
+                                &input_cursor,
+                                "a match should be a slice of the text it is in"
+                            );
+                        };
+                        let match_end_offset = match_offset + matched_str.len();
+                        let match_start_byte = input_byte_offset
+                            + input_after_prefix_lines.original_offset(match_offset);
+                        input_byte_offset +=
+                            input_after_prefix_lines.original_offset(match_end_offset);
 
                         // Good match! Add the matched node to the matches (if it has an id)
                         //
                         // If we're at the end though, don't add it just yet!
                         if !waiting_at_end(got_eof, walker.input_str(), &input_cursor) {
-                            if let Some(id) = matcher.id() {
-                                trace!("Storing match for id '{}': '{}'", id, matched_str);
-                                result.set_match(id, json!(matched_str));
-                            } else {
-                                trace!("Matcher has no id, not storing match");
-                            }
+                            trace!("Storing match for id {:?}: '{}'", matcher.id(), matched_str);
+                            result.set_matcher_match(
+                                &matcher,
+                                walker.captured_range_value(
+                                    matched_str,
+                                    match_start_byte..input_byte_offset,
+                                ),
+                                schema_cursor.descendant_index(),
+                                input_cursor_descendant_index,
+                            );
                         }
 
-                        // Walk so that we are ON the `code_span`
-                        schema_cursor.goto_next_sibling();
+                        // Walk so that we are ON the `code_span`, if we started at its prefix
+                        if !schema_cursor_is_code_node {
+                            schema_cursor.goto_next_sibling();
+                        }
 
                         // Walk down into the `code_span` and mark its child text as already validated!
                         {
@@ -278,8 +432,12 @@ impl ValidatorImpl for MatcherVsTextValidator {
                             }
                         }
                     }
-                    None => {
-                        if waiting_at_end(got_eof, walker.input_str(), &input_cursor) {
+                    Ok(None) => {
+                        // More input may still make it match, unless what we
+                        // have so far already rules that out
+                        if waiting_at_end(got_eof, walker.input_str(), &input_cursor)
+                            && matcher.could_match_with_more(&input_after_prefix)
+                        {
                             return result;
                         };
 
@@ -290,15 +448,21 @@ impl ValidatorImpl for MatcherVsTextValidator {
                         );
 
                         result.add_error(ValidationError::SchemaViolation(
-                            SchemaViolationError::NodeContentMismatch {
-                                schema_index: schema_cursor.descendant_index(),
-                                input_index: input_cursor_descendant_index,
-                                expected: matcher.pattern().to_string(),
-                                actual: input_after_prefix,
-                                kind: NodeContentMismatchKind::Matcher,
-                            },
+                            matcher.mismatch_error(
+                                schema_cursor.descendant_index(),
+                                input_cursor_descendant_index,
+                                match_input.to_string(),
+                            ),
                         ));
 
+                        return result;
+                    }
+                    Err(error) => {
+                        result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                            error,
+                            schema_index: schema_cursor.descendant_index(),
+                        }));
+
                         return result;
                     }
                 }
@@ -329,25 +493,35 @@ impl ValidatorImpl for MatcherVsTextValidator {
         }
 
         // Validate suffix if there is one
-        if let Some(schema_suffix_node) = schema_suffix_node {
+        if schema_suffix_node.is_some() {
             schema_cursor.goto_next_sibling(); // code_span -> text
 
-            // Return early if it is not text
+            // Inline nodes like emphasis right after the matcher are the
+            // caller's to validate, against the input nodes after this text,
+            // so leave it at the `code_span` for it to move on from
             if !is_text_node(&schema_cursor.node()) {
+                if !waiting_at_end(got_eof, walker.input_str(), &input_cursor) {
+                    schema_cursor.goto_previous_sibling();
+                    result.sync_cursor_pos(&schema_cursor, &input_cursor);
+                }
                 return result;
             }
 
             // Everything that comes after the matcher
-            let schema_suffix = {
-                let text_node_after_code_node_str_contents =
-                    get_node_text(&schema_suffix_node, walker.schema_str());
-                // All text after the matcher node and maybe the text node right after it ("extras")
-                get_after_extras(text_node_after_code_node_str_contents).unwrap()
-            };
-
-            // Seek forward from the current input byte offset by the length of the suffix
-            let input_suffix_raw =
-                &walker.input_str()[input_byte_offset..input_cursor.node().byte_range().end];
+            let schema_suffix = schema_suffix_lines
+                .as_ref()
+                .map_or("", |lines| lines.as_str());
+
+            // Everything in the input after the match. If there's nothing left,
+            // we handle it like any other too-short suffix below.
+            let input_suffix_lines = line_endings(
+                walker
+                    .input_str()
+                    .get(input_byte_offset..input_end)
+                    .unwrap_or_default(),
+                walker.options(),
+            );
+            let input_suffix_raw = input_suffix_lines.as_str();
 
             // Trim the input suffix if we're in a table cell context, to match how schema_suffix is obtained
             let input_suffix = if is_table_cell_node(&input_cursor.node())
@@ -380,7 +554,8 @@ impl ValidatorImpl for MatcherVsTextValidator {
                 } else {
                     // We haven't reached EOF yet, so partial match is OK
                     // Check if what we have so far matches
-                    let schema_suffix_partial = &schema_suffix[..input_suffix.len()];
+                    let schema_suffix_partial =
+                        get_str_at_char_boundaries(schema_suffix, 0, input_suffix.len());
                     if schema_suffix_partial != input_suffix {
                         trace!(
                             "Suffix partial mismatch: expected '{}', got '{}'",
@@ -418,11 +593,15 @@ impl ValidatorImpl for MatcherVsTextValidator {
             } else {
                 trace!("Suffix matched successfully");
 
-                // We validated this one! Load the result with the new pos!
-                result.keep_farther_pos(&NodePosPair::from_cursors(
-                    walker.schema_cursor(),
-                    walker.input_cursor(),
-                ));
+                // We validated this one! Load the result with the new pos, so
+                // that the caller moves on to what comes after the suffix,
+                // like emphasis
+                if !waiting_at_end(got_eof, walker.input_str(), &input_cursor) {
+                    result.keep_farther_pos(&NodePosPair::from_cursors(
+                        &schema_cursor,
+                        &input_cursor,
+                    ));
+                }
             }
         }
 
@@ -476,6 +655,8 @@ impl ValidatorImpl for TextualVsMatcherValidator {
                 &input_cursor,
                 false,
                 false,
+                walker.options(),
+                walker.input_line_starts(),
             );
             result.join_other_result(&text_result);
             if text_result.has_errors() {
@@ -483,9 +664,19 @@ impl ValidatorImpl for TextualVsMatcherValidator {
             }
         }
 
-        // The schema cursor definitely has a text node after the code node, which
-        // at minimum contains "!" (which indicates that it is a literal matcher in
-        // the first place).
+        // Code in double backticks is literal without a "!", so it may have no
+        // text after it at all
+        let code_span_str = get_node_text(&schema_cursor.node(), walker.schema_str());
+        if is_always_literal_code(code_span_str)
+            && get_next_node(&schema_cursor).is_none_or(|node| !is_text_node(&node))
+        {
+            result.sync_cursor_pos(&schema_cursor, &input_cursor);
+            return result;
+        }
+
+        // Otherwise the schema cursor definitely has a text node after the code
+        // node, which at minimum contains "!" (which indicates that it is a
+        // literal matcher in the first place).
         #[cfg(feature = "invariant_violations")]
         if !schema_cursor.goto_next_sibling() && is_text_node(&schema_cursor.node()) {
             invariant_violation!(
@@ -502,18 +693,15 @@ impl ValidatorImpl for TextualVsMatcherValidator {
         let schema_node_str_has_more_than_extras = schema_node_str.len() > 1;
 
         // Now see if there is more text than just the "!" in the schema text node.
-        let schema_text_after_extras = match get_after_extras(schema_node_str) {
+        let schema_text_after_extras = match after_literal_code(code_span_str, schema_node_str) {
             Some(text) => text,
             None => {
-                #[cfg(feature = "invariant_violations")]
-                {
-                    invariant_violation!(
-                        result,
-                        &schema_cursor,
-                        &input_cursor,
-                        "we should have had extras in the matcher string"
-                    );
-                }
+                invariant_violation!(
+                    result,
+                    &schema_cursor,
+                    &input_cursor,
+                    "we should have had extras in the matcher string"
+                );
             }
         };
 
@@ -534,13 +722,21 @@ impl ValidatorImpl for TextualVsMatcherValidator {
             return result;
         }
 
-        let input_text_after_code = get_node_text(&input_cursor.node(), walker.input_str());
+        let input_text_after_code = get_str_at_char_boundaries(
+            walker.input_str(),
+            input_cursor.node().start_byte(),
+            input_cursor.node().end_byte(),
+        );
 
         // Partial match is OK if got_eof is false.
         if input_text_after_code.len() < schema_text_after_extras.len() {
             if !got_eof {
                 let schema_text_after_extras_to_compare_against_so_far =
-                    &schema_text_after_extras[..input_text_after_code.len()];
+                    get_str_at_char_boundaries(
+                        schema_text_after_extras,
+                        0,
+                        input_text_after_code.len(),
+                    );
 
                 // Do the partial comparison.
                 if schema_text_after_extras_to_compare_against_so_far != input_text_after_code {
@@ -647,6 +843,8 @@ impl ValidatorImpl for LiteralMatcherVsTextualValidator {
                 &input_cursor,
                 false,
                 false,
+                walker.options(),
+                walker.input_line_starts(),
             );
             result.join_other_result(&text_result);
             if text_result.has_errors() {
@@ -654,9 +852,19 @@ impl ValidatorImpl for LiteralMatcherVsTextualValidator {
             }
         }
 
-        // The schema cursor definitely has a text node after the code node, which
-        // at minimum contains "!" (which indicates that it is a literal matcher in
-        // the first place).
+        // Code in double backticks is literal without a "!", so it may have no
+        // text after it at all
+        let code_span_str = get_node_text(&schema_cursor.node(), walker.schema_str());
+        if is_always_literal_code(code_span_str)
+            && get_next_node(&schema_cursor).is_none_or(|node| !is_text_node(&node))
+        {
+            result.sync_cursor_pos(&schema_cursor, &input_cursor);
+            return result;
+        }
+
+        // Otherwise the schema cursor definitely has a text node after the code
+        // node, which at minimum contains "!" (which indicates that it is a
+        // literal matcher in the first place).
         #[cfg(feature = "invariant_violations")]
         if !schema_cursor.goto_next_sibling() && is_text_node(&schema_cursor.node()) {
             invariant_violation!(
@@ -673,18 +881,15 @@ impl ValidatorImpl for LiteralMatcherVsTextualValidator {
         let schema_node_str_has_more_than_extras = schema_node_str.len() > 1;
 
         // Now see if there is more text than just the "!" in the schema text node.
-        let schema_text_after_extras = match get_after_extras(schema_node_str) {
+        let schema_text_after_extras = match after_literal_code(code_span_str, schema_node_str) {
             Some(text) => text,
             None => {
-                #[cfg(feature = "invariant_violations")]
-                {
-                    invariant_violation!(
-                        result,
-                        &schema_cursor,
-                        &input_cursor,
-                        "we should have had extras in the matcher string"
-                    );
-                }
+                invariant_violation!(
+                    result,
+                    &schema_cursor,
+                    &input_cursor,
+                    "we should have had extras in the matcher string"
+                );
             }
         };
 
@@ -705,16 +910,21 @@ impl ValidatorImpl for LiteralMatcherVsTextualValidator {
             return result;
         }
 
-        let input_text_after_code = input_cursor
-            .node()
-            .utf8_text(walker.input_str().as_bytes())
-            .unwrap();
+        let input_text_after_code = get_str_at_char_boundaries(
+            walker.input_str(),
+            input_cursor.node().start_byte(),
+            input_cursor.node().end_byte(),
+        );
 
         // Partial match is OK if got_eof is false.
         if input_text_after_code.len() < schema_text_after_extras.len() {
             if !got_eof {
                 let schema_text_after_extras_to_compare_against_so_far =
-                    &schema_text_after_extras[..input_text_after_code.len()];
+                    get_str_at_char_boundaries(
+                        schema_text_after_extras,
+                        0,
+                        input_text_after_code.len(),
+                    );
 
                 // Do the partial comparison.
                 if schema_text_after_extras_to_compare_against_so_far != input_text_after_code {
@@ -774,6 +984,435 @@ impl ValidatorImpl for LiteralMatcherVsTextualValidator {
     }
 }
 
+/// Validate a textual container with several matchers in it.
+///
+/// Also validates the content of emphasis (like `` *`label:/\w+/`* ``) with
+/// matchers in it, between the delimiters.
+///
+/// The schema's children are split into alternating literal and matcher
+/// segments, and the raw input text is consumed left to right. Literals have
+/// to appear as is, and anchor where each matcher may stop: a matcher that is
+/// followed by a literal has to match all the input up to the next occurrence
+/// of that literal. For example, with
+///
+/// ```md
+/// Name: `first:/\w+/` `last:/\w+/`, age `age:int:/\d+/`
+/// ```
+///
+/// the input `Name: Ada Lovelace, age 36` captures `first`, `last` and `age`.
+///
+/// Errors point at the segment that failed. Unless we have the whole input, a
+/// matcher with no literal after it (yet) is left alone until more comes in,
+/// unless it ends the line and no more input could make it match.
+#[derive(Default)]
+pub(super) struct MultipleMatchersVsTextValidator;
+
+impl ValidatorImpl for MultipleMatchersVsTextValidator {
+    fn validate_impl(&self, walker: &ValidatorWalker, got_eof: bool) -> ValidationResult {
+        let mut result =
+            ValidationResult::from_cursors(walker.schema_cursor(), walker.input_cursor());
+
+        #[cfg(feature = "invariant_violations")]
+        if !is_text_and_code_spans_only(walker.schema_cursor()) {
+            invariant_violation!(
+                result,
+                walker.schema_cursor(),
+                walker.input_cursor(),
+                "multiple matcher validation expects only text and code_span children"
+            );
+        }
+
+        let segments = match matcher_line_segments(
+            walker.schema_cursor(),
+            walker.schema_str(),
+            walker.options(),
+        ) {
+            Ok(segments) => segments,
+            Err(error) => {
+                result.add_error(error);
+                return result;
+            }
+        };
+
+        let input_index = walker.input_cursor().descendant_index();
+        let input_range =
+            content_text_range(walker.input_cursor(), walker.input_str(), walker.options());
+        // The text on either side of inline HTML we skip runs together
+        let input_text = if walker.options().ignore_inline_html {
+            WithoutInlineHtml::new(&walker.input_cursor().node(), input_range, walker.input_str())
+        } else {
+            WithoutInlineHtml::with_html(input_range, walker.input_str())
+        };
+        let is_partial_match = waiting_at_end(got_eof, walker.input_str(), walker.input_cursor());
+
+        // How far along we've validated the input text
+        let mut input_byte_offset = 0;
+        // Whether the last matcher was left out after whitespace, which then
+        // only needs to be there once
+        let mut after_left_out_matcher = false;
+
+        for (i, segment) in segments.iter().enumerate() {
+            let input_rest = &input_text.as_str()[input_byte_offset..];
+
+            match segment {
+                MatcherLineSegment::Literal { text, schema_index } => {
+                    let text = if after_left_out_matcher {
+                        text.trim_start()
+                    } else {
+                        text.as_str()
+                    };
+                    after_left_out_matcher = false;
+
+                    // The last literal has to cover the rest of the input, and
+                    // so does one that only optional matchers come after, but
+                    // without any trailing whitespace if they were all left out.
+                    let is_last = i + 1 == segments.len();
+                    let matches = if is_last {
+                        input_rest == text
+                    } else {
+                        input_rest.starts_with(text)
+                            || (input_rest == text.trim_end()
+                                && segments[i + 1..].iter().all(|segment| {
+                                    matches!(
+                                        segment,
+                                        MatcherLineSegment::Matcher { matcher, .. }
+                                            if matcher.is_optional()
+                                    )
+                                }))
+                    };
+
+                    if matches {
+                        input_byte_offset += text.len().min(input_rest.len());
+                        continue;
+                    }
+                    if is_partial_match && text.starts_with(input_rest) {
+                        // We may get the rest of it later
+                        return result;
+                    }
+
+                    let actual = if is_last {
+                        input_rest
+                    } else {
+                        get_str_at_char_boundaries(input_rest, 0, text.len())
+                    };
+                    trace!("Literal mismatch: expected '{}', got '{}'", text, actual);
+                    result.add_error(ValidationError::SchemaViolation(
+                        SchemaViolationError::NodeContentMismatch {
+                            schema_index: *schema_index,
+                            input_index,
+                            expected: text.to_string(),
+                            actual: actual.into(),
+                            kind: NodeContentMismatchKind::Literal,
+                        },
+                    ));
+                    return result;
+                }
+                MatcherLineSegment::Matcher {
+                    matcher,
+                    schema_index,
+                } => {
+                    // A matcher followed by a literal takes everything up to
+                    // where that literal first shows up.
+                    let bound = match segments.get(i + 1) {
+                        Some(MatcherLineSegment::Literal { text, .. }) => {
+                            input_rest.find(text.as_str())
+                        }
+                        _ => None,
+                    };
+
+                    let candidate = &input_rest[..bound.unwrap_or(input_rest.len())];
+
+                    // Without a bound more input may still change the match,
+                    // unless the matcher is the last thing in the line and
+                    // what we have so far can't start a match
+                    if bound.is_none()
+                        && is_partial_match
+                        && (i + 1 < segments.len() || matcher.could_match_with_more(candidate))
+                    {
+                        return result;
+                    }
+
+                    // An unanchored matcher may skip input, unless it has to
+                    // line up with the literal before it
+                    let may_skip = !matches!(
+                        i.checked_sub(1).and_then(|previous| segments.get(previous)),
+                        Some(MatcherLineSegment::Literal { .. })
+                    );
+                    let match_result =
+                        matcher.try_match_str(candidate, walker.options().max_matcher_input_len);
+                    // How much input the match skipped
+                    let skipped = match &match_result {
+                        Ok(Some(matched)) => match subslice_offset(candidate, matched) {
+                            Some(skipped) => skipped,
+                            None => invariant_violation!(
+                                result,
+                                walker.schema_cursor(),
+                                walker.input_cursor(),
+                                "a match should be a slice of the text it is in"
+                            ),
+                        },
+                        _ => 0,
+                    };
+                    let matched = match match_result {
+                        Ok(Some(matched))
+                            if (may_skip || skipped == 0)
+                                && (bound.is_none()
+                                    || skipped + matched.len() == candidate.len()) =>
+                        {
+                            matched
+                        }
+                        // Left out, so the input goes straight on to what's next
+                        Ok(_) if matcher.is_optional() => {
+                            trace!("Optional matcher was left out of the input");
+                            if let Some(default) = matcher.default_value() {
+                                result.set_matcher_match(
+                                    matcher,
+                                    Value::String(default.to_string()),
+                                    *schema_index,
+                                    input_index,
+                                );
+                            }
+                            after_left_out_matcher = matches!(
+                                i.checked_sub(1).and_then(|previous| segments.get(previous)),
+                                Some(MatcherLineSegment::Literal { text, .. })
+                                    if text.ends_with(char::is_whitespace)
+                            );
+                            continue;
+                        }
+                        Ok(_) => {
+                            trace!(
+                                "Matcher did not match input string: pattern={}, input='{}'",
+                                matcher.pattern(),
+                                candidate
+                            );
+                            result.add_error(ValidationError::SchemaViolation(
+                                matcher.mismatch_error(
+                                    *schema_index,
+                                    input_index,
+                                    candidate.into(),
+                                ),
+                            ));
+                            return result;
+                        }
+                        Err(error) => {
+                            result.add_error(ValidationError::SchemaError(
+                                SchemaError::MatcherError {
+                                    error,
+                                    schema_index: *schema_index,
+                                },
+                            ));
+                            return result;
+                        }
+                    };
+
+                    trace!("Storing match for id {:?}: '{}'", matcher.id(), matched);
+                    let match_start = input_byte_offset + skipped;
+                    let match_range =
+                        input_text.original_range(match_start..match_start + matched.len());
+                    result.set_matcher_match(
+                        matcher,
+                        walker.captured_range_value(matched, match_range),
+                        *schema_index,
+                        input_index,
+                    );
+                    input_byte_offset = match_start + matched.len();
+                }
+            }
+        }
+
+        // We validated all of it, so we're done with both containers
+        let mut schema_cursor = walker.schema_cursor().clone();
+        let mut input_cursor = walker.input_cursor().clone();
+        if schema_cursor.goto_last_child() && input_cursor.goto_last_child() {
+            result.sync_cursor_pos(&schema_cursor, &input_cursor);
+        }
+
+        result
+    }
+}
+
+/// A piece of a textual container that has several matchers in it.
+#[derive(Debug)]
+enum MatcherLineSegment {
+    /// Text that has to appear in the input as is. Literal code spans, like
+    /// `` `code`! ``, are part of it.
+    Literal { text: String, schema_index: usize },
+    /// A matcher, which takes input up to the next literal.
+    Matcher { matcher: Matcher, schema_index: usize },
+}
+
+/// Split the children of a textual container in the schema into literal and
+/// matcher segments. Adjacent literals are merged into one.
+///
+/// # Arguments
+///
+/// * `schema_cursor`: The cursor pointing at the textual container.
+/// * `schema_str`: The schema string.
+/// * `options`: The options we are validating with.
+fn matcher_line_segments(
+    schema_cursor: &TreeCursor,
+    schema_str: &str,
+    options: &ValidateOptions,
+) -> Result<Vec<MatcherLineSegment>, ValidationError> {
+    let mut segments = vec![];
+
+    let content_range = content_text_range(schema_cursor, schema_str, options);
+    let mut cursor = schema_cursor.clone();
+    if !cursor.goto_first_child() {
+        return Ok(segments);
+    }
+
+    let mut previous_end = content_range.start;
+    let mut after_code_span = false;
+    loop {
+        let node = cursor.node();
+        if is_emphasis_delimiter_node(&node) {
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+            continue;
+        }
+
+        // Keep anything between nodes, like whitespace
+        push_literal(
+            &mut segments,
+            &schema_str[previous_end..node.start_byte()],
+            cursor.descendant_index(),
+        );
+
+        if is_inline_code_node(&node) {
+            match Matcher::try_from_schema_cursor_with_limits(
+                &cursor,
+                schema_str,
+                &options.regex_limits,
+            ) {
+                Ok(matcher) => segments.push(MatcherLineSegment::Matcher {
+                    matcher,
+                    schema_index: cursor.descendant_index(),
+                }),
+                Err(MatcherError::WasLiteralCode) => push_literal(
+                    &mut segments,
+                    get_node_text(&node, schema_str),
+                    cursor.descendant_index(),
+                ),
+                Err(error) => {
+                    return Err(ValidationError::SchemaError(SchemaError::MatcherError {
+                        error,
+                        schema_index: cursor.descendant_index(),
+                    }));
+                }
+            }
+            after_code_span = !is_always_literal_code(get_node_text(&node, schema_str));
+        } else {
+            let text = node_text(&node, schema_str, options);
+            // Text right after a code span starts with its extras
+            let text = if after_code_span {
+                get_after_extras(text).unwrap_or(text)
+            } else {
+                text
+            };
+            push_literal(&mut segments, text, cursor.descendant_index());
+            after_code_span = false;
+        }
+
+        previous_end = node.end_byte();
+        if !cursor.goto_next_sibling() {
+            break;
+        }
+    }
+
+    // Emphasis may end with text that isn't a node of its own
+    if previous_end < content_range.end {
+        push_literal(
+            &mut segments,
+            &schema_str[previous_end..content_range.end],
+            schema_cursor.descendant_index(),
+        );
+    }
+
+    Ok(segments)
+}
+
+/// Whether the input is the text before and after a matcher in the schema,
+/// without the matcher.
+///
+/// Where both sides have whitespace at the join it only needs to be there
+/// once, and at the end of the text trailing whitespace is left out, since
+/// the input's text never has any.
+fn input_skips_matcher(schema_prefix: &str, schema_suffix: &str, input: &str) -> bool {
+    let joined = if schema_prefix.ends_with(char::is_whitespace) {
+        format!("{}{}", schema_prefix, schema_suffix.trim_start())
+    } else {
+        format!("{}{}", schema_prefix, schema_suffix)
+    };
+
+    input == joined || input == joined.trim_end()
+}
+
+/// The text after literal code, without its extras. Code in double backticks
+/// has none, so that is all of the text.
+fn after_literal_code<'a>(code_span_str: &str, text: &'a str) -> Option<&'a str> {
+    match is_always_literal_code(code_span_str) {
+        true => Some(text),
+        false => get_after_extras(text),
+    }
+}
+
+fn push_literal(segments: &mut Vec<MatcherLineSegment>, text: &str, schema_index: usize) {
+    if text.is_empty() {
+        return;
+    }
+
+    match segments.last_mut() {
+        Some(MatcherLineSegment::Literal { text: literal, .. }) => literal.push_str(text),
+        _ => segments.push(MatcherLineSegment::Literal {
+            text: text.to_string(),
+            schema_index,
+        }),
+    }
+}
+
+/// Whether a textual container only has text and `code_span` children, so
+/// that it can be matched as raw text.
+pub(super) fn is_text_and_code_spans_only(cursor: &TreeCursor) -> bool {
+    let node = cursor.node();
+    node.children(&mut node.walk()).all(|child| {
+        is_text_node(&child) || is_inline_code_node(&child) || is_emphasis_delimiter_node(&child)
+    })
+}
+
+/// The bytes of a node's content. For emphasis that is everything between
+/// its delimiters, and otherwise what its children cover, or an empty range
+/// at its start if it has none.
+fn content_byte_range(cursor: &TreeCursor, src: &str) -> Range<usize> {
+    let node = cursor.node();
+    if is_emphasis_node(&node) {
+        let text = get_node_text(&node, src);
+        let delimiter_len = match node.kind() {
+            "emphasis" => 1,
+            "strong_emphasis" => 2,
+            _ => text.len() - text.trim_start_matches('~').len(),
+        };
+        // An unclosed delimiter at the end of a partial input is all we have
+        let start = (node.start_byte() + delimiter_len).min(node.end_byte());
+        let end = node.end_byte().saturating_sub(delimiter_len).max(start);
+        return start..end;
+    }
+
+    match (node.child(0), node.child(node.child_count().saturating_sub(1))) {
+        (Some(first), Some(last)) => first.start_byte()..last.end_byte(),
+        _ => node.start_byte()..node.start_byte(),
+    }
+}
+
+/// The bytes of a node's content like `content_byte_range`, without the
+/// closing hashes of an ATX heading unless we compare headings exactly.
+fn content_text_range(cursor: &TreeCursor, src: &str, options: &ValidateOptions) -> Range<usize> {
+    let range = content_byte_range(cursor, src);
+    let end = text_end(&cursor.node(), src, options);
+    range.start..range.end.min(end).max(range.start)
+}
+
 fn at_text_and_next_at_literal_matcher(
     schema_cursor: &TreeCursor,
     schema_str: &str,
@@ -837,6 +1476,148 @@ mod tests {
         assert_eq!(result.value(), &json!({}));
     }
 
+    #[test]
+    fn test_validate_matcher_vs_text_partial_that_cannot_match() {
+        let schema_str = r#"Version: `v:/\d+\.\d+\.\d+/`"#;
+
+        // A chunk that ends mid-match waits for the rest
+        let result =
+            ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, "Version: 1.2.")
+                .walk()
+                .goto_first_child_then_unwrap()
+                .goto_first_child_then_unwrap()
+                .validate_incomplete();
+
+        assert!(result.errors().is_empty());
+        assert_eq!(result.value(), &json!({}));
+
+        // No more input can make `1.x` match, so we don't wait for it
+        let result =
+            ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, "Version: 1.x")
+                .walk()
+                .goto_first_child_then_unwrap()
+                .goto_first_child_then_unwrap()
+                .validate_incomplete();
+
+        assert!(matches!(
+            result.errors(),
+            [ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch { actual, .. }
+            )] if actual == "1.x"
+        ));
+        assert_eq!(result.value(), &json!({}));
+    }
+
+    #[test]
+    fn test_validate_matcher_vs_text_multibyte_prefix_and_match() {
+        let schema_str = r#"Café: `name:/\w+/`"#;
+        let input_str = "Café: José";
+
+        let result = ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert!(result.errors().is_empty());
+        assert_eq!(result.value(), &json!({"name": "José"}));
+    }
+
+    #[test]
+    fn test_validate_matcher_vs_text_suffix_past_end_of_input() {
+        let schema_str = r#"Name: `n:/\w+/` (required)"#;
+        let input_str = "Name: José";
+
+        let result = ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert_eq!(
+            result.errors(),
+            &vec![ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch {
+                    schema_index: 5,
+                    input_index: 2,
+                    expected: " (required)".into(),
+                    actual: "".into(),
+                    kind: NodeContentMismatchKind::Suffix,
+                }
+            )]
+        );
+
+        // The rest of the suffix may still come in
+        let result = ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_incomplete();
+
+        assert!(result.errors().is_empty());
+    }
+
+    #[test]
+    fn test_validate_matcher_vs_text_multibyte_suffix() {
+        let schema_str = r#"`n:/\w+/` café"#;
+
+        let result = ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, "José cafe")
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert_eq!(
+            result.errors(),
+            &vec![ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch {
+                    schema_index: 4,
+                    input_index: 2,
+                    expected: " café".into(),
+                    actual: " cafe".into(),
+                    kind: NodeContentMismatchKind::Suffix,
+                }
+            )]
+        );
+
+        // Cut off partway through the suffix
+        let result = ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, "José caf")
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_incomplete();
+
+        assert!(result.errors().is_empty());
+    }
+
+    #[test]
+    fn test_validate_matcher_vs_text_with_regex_flags() {
+        let schema_str = r#"Name: `name:/alice/i` (admin)"#;
+
+        let result =
+            ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, "Name: ALICE (admin)")
+                .walk()
+                .goto_first_child_then_unwrap()
+                .goto_first_child_then_unwrap()
+                .validate_complete();
+
+        assert!(result.errors().is_empty());
+        assert_eq!(result.value(), &json!({"name": "ALICE"}));
+
+        let result = ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, "Name: Bob")
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert!(matches!(
+            result.errors().first(),
+            Some(ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch { expected, .. }
+            )) if expected == "(?i)alice"
+        ));
+    }
+
     #[test]
     fn test_validate_matcher_vs_text_with_prefix_no_suffix_test() {
         let schema_str = "prefix `test:/test/`";
@@ -896,11 +1677,96 @@ mod tests {
             .goto_first_child_then_unwrap()
             .validate_complete();
 
-        assert_eq!(*result.farthest_reached_pos(), NodePosPair::from_pos(4, 2));
+        // At the text between the matcher and the emphasis, so that the
+        // emphasis is validated next
+        assert_eq!(*result.farthest_reached_pos(), NodePosPair::from_pos(5, 2));
         assert!(result.errors().is_empty());
         assert_eq!(result.value(), &json!({"test": "test"}));
     }
 
+    #[test]
+    fn test_validate_matcher_vs_text_unanchored() {
+        let schema_str = r#"`id:/\d+/`~ items"#;
+        let input_str = "abc 123 items";
+
+        let result = ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert!(result.errors().is_empty());
+        assert_eq!(result.value(), &json!({"id": "123"}));
+
+        // Without the `~` the number has to come first
+        let schema_str = r#"`id:/\d+/` items"#;
+        let result = ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert_eq!(result.errors().len(), 1);
+        assert_eq!(result.value(), &json!({}));
+    }
+
+    #[test]
+    fn test_validate_matcher_vs_text_unanchored_after_prefix() {
+        // The prefix has to be followed by the match, so nothing may be skipped
+        let schema_str = r#"Count: `id:/\d+/`~"#;
+        let input_str = "Count: abc 123";
+
+        let result = ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        match result.errors().first() {
+            Some(ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch {
+                kind: NodeContentMismatchKind::Matcher,
+                ..
+            })) => {}
+            error => panic!("expected a matcher mismatch, got {:?}", error),
+        }
+        assert_eq!(result.value(), &json!({}));
+    }
+
+    #[test]
+    fn test_validate_matcher_vs_text_optional_at_end_of_line() {
+        let schema_str = r#"Env: `env:/\w+/`=prod"#;
+
+        // More input may still come, so we can't tell if it was left out yet
+        let result = ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, "Env:")
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_incomplete();
+
+        assert_eq!(*result.farthest_reached_pos(), NodePosPair::from_pos(2, 2));
+        assert!(result.errors().is_empty());
+        assert_eq!(result.value(), &json!({}));
+
+        let result = ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, "Env: sta")
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_incomplete();
+
+        assert!(result.errors().is_empty());
+        assert_eq!(result.value(), &json!({}));
+
+        let result = ValidatorTester::<MatcherVsTextValidator>::from_strs(schema_str, "Env:")
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_first_child_then_unwrap()
+            .validate_complete();
+
+        assert_eq!(*result.farthest_reached_pos(), NodePosPair::from_pos(4, 2));
+        assert!(result.errors().is_empty());
+        assert_eq!(result.value(), &json!({"env": "prod"}));
+    }
+
     #[test]
     fn test_validate_matcher_vs_text_with_prefix_and_suffix() {
         let schema_str = "prefix `test:/test/` suffix";
@@ -2,7 +2,7 @@
 //!
 //! Types:
 //! - `ValidatorImpl`: core validator implementation trait.
-//! - `Validator`: wrapper used to get tracing on every validator call.
+//! - `Validator`: wrapper used to get tracing, and profiling when it's on, on every validator call.
 //!
 //! Validator implementations:
 //! - `nodes::NodeVsNodeValidator`: dispatches between textual, code, list, table, heading, quote, and link validators.
@@ -10,28 +10,39 @@
 //! - `matchers::MatcherVsTextValidator`: handles schema nodes that embed matcher syntax inside text or code spans.
 //! - `code::CodeVsCodeValidator`: validates fenced code blocks (matcher-based language, capture support).
 //! - `headings::HeadingVsHeadingValidator`: ensures heading kinds match and defers to textual container comparison.
+//! - `html::HtmlVsHtmlValidator`: compares HTML blocks and inline HTML as text.
 //! - `quotes::QuoteVsQuoteValidator`: validates block quotes by walking their contents with textual container logic.
 //! - `links::LinkVsLinkValidator`: checks link and image destinations plus alt text, with matcher coverage.
 //! - `tables::TableVsTableValidator`: walks table rows/cells and hands off textual cells to textual container validation.
 //! - `lists::ListVsListValidator`: aligns schema and input list items, handling nested structures and matcher-aware text.
+//! - `unordered_lists::validate_unordered_list`: pairs the items of a list marked `<!-- mds:unordered -->` with input items in any order.
 //! - `containers::TextualContainerVsTextualContainerValidator`: walks inline container nodes and compares literal/matcher-driven text.
+//! - `sections::validate_repeated_sections`: matches a heading with a repeated or optional matcher, and everything under it, against consecutive input sections.
+//! - `rulers::validate_rulers`: matches a `` `ruler` `` matcher paragraph, which may be optional or repeated, against consecutive thematic breaks.
+use std::time::Instant;
+
 #[allow(dead_code)]
 use tracing::instrument;
 
 use crate::mdschema::validation::{
-    walkers::ValidationResult, validator_walker::ValidatorWalker,
+    errors::{SchemaViolationError, ValidationError},
+    profile, walkers::ValidationResult, validator_walker::ValidatorWalker,
 };
 
 pub(super) mod code;
 pub(super) mod containers;
 pub(super) mod headings;
+pub(super) mod html;
 pub(super) mod links;
 pub(super) mod lists;
 pub(super) mod matchers;
 pub(crate) mod nodes;
 pub(super) mod quotes;
+pub(crate) mod rulers;
+pub(crate) mod sections;
 pub(super) mod tables;
 pub(super) mod textual;
+pub(super) mod unordered_lists;
 
 pub trait ValidatorImpl {
     fn validate_impl(&self, walker: &ValidatorWalker, got_eof: bool) -> ValidationResult;
@@ -47,10 +58,58 @@ impl<T: ValidatorImpl> Validator for T {
         s = %walker.schema_cursor().descendant_index(),
     ), ret)]
     fn validate(&self, walker: &ValidatorWalker, got_eof: bool) -> ValidationResult {
-        self.validate_impl(walker, got_eof)
+        #[cfg(test)]
+        VALIDATOR_CALLS.with(|calls| calls.set(calls.get() + 1));
+
+        // Validators of nested nodes run inside the validators of their
+        // parents, so deeply nested input would run out of stack
+        let depth = walker.depth();
+        if depth >= walker.options().max_nesting_depth {
+            return nesting_too_deep(walker);
+        }
+        walker.set_depth(depth + 1);
+
+        let result = if profile::is_profiling() {
+            let start = Instant::now();
+            let result = self.validate_impl(walker, got_eof);
+            profile::record_call(
+                profile::validator_name::<T>(),
+                start.elapsed(),
+                walker.schema_cursor().descendant_index(),
+                walker.input_cursor().descendant_index(),
+            );
+            result
+        } else {
+            self.validate_impl(walker, got_eof)
+        };
+
+        walker.set_depth(depth);
+        result
     }
 }
 
+/// The result of a validator that would be nested deeper than
+/// `ValidateOptions::max_nesting_depth`. The nodes aren't compared, and the
+/// error points at them.
+fn nesting_too_deep(walker: &ValidatorWalker) -> ValidationResult {
+    let mut result = ValidationResult::from_cursors(walker.schema_cursor(), walker.input_cursor());
+    result.add_error(ValidationError::SchemaViolation(
+        SchemaViolationError::NestingTooDeep {
+            schema_index: walker.schema_cursor().descendant_index(),
+            input_index: walker.input_cursor().descendant_index(),
+            max_depth: walker.options().max_nesting_depth,
+        },
+    ));
+    result
+}
+
+#[cfg(test)]
+thread_local! {
+    /// How many validators have run on this thread, so tests can check that we
+    /// stop visiting nodes early.
+    pub(crate) static VALIDATOR_CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
 #[cfg(test)]
 mod test_utils {
     use tree_sitter::{Node, Tree, TreeCursor};
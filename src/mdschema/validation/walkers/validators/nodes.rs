@@ -4,21 +4,33 @@
 //! - `NodeVsNodeValidator`: routes node-vs-node checks to the specific validator
 //!   based on node kinds and performs shared structural checks.
 use log::trace;
+use tree_sitter::{Node, TreeCursor};
 
 use crate::mdschema::validation::errors::{
     MalformedStructureKind, SchemaViolationError, ValidationError,
 };
 use crate::mdschema::validation::node_pos_pair::NodePosPair;
 use crate::mdschema::validation::ts_types::*;
-use crate::mdschema::validation::ts_utils::waiting_at_end;
+use crate::mdschema::validation::ts_utils::{
+    get_node_text, goto_first_child_skipping, goto_first_child_skipping_directives,
+    goto_next_sibling_skipping, goto_next_sibling_skipping_directives, waiting_at_end,
+};
+use crate::mdschema::validation::validator::TrailingContent;
 use crate::mdschema::validation::validator_walker::ValidatorWalker;
 use crate::mdschema::validation::walkers::ValidationResult;
+use crate::mdschema::validation::walkers::helpers::resync::resync_cursors;
 use crate::mdschema::validation::walkers::validators::code::CodeVsCodeValidator;
 use crate::mdschema::validation::walkers::validators::containers::ContainerVsContainerValidator;
 use crate::mdschema::validation::walkers::validators::headings::HeadingVsHeadingValidator;
+use crate::mdschema::validation::walkers::validators::html::HtmlVsHtmlValidator;
 use crate::mdschema::validation::walkers::validators::links::LinkVsLinkValidator;
 use crate::mdschema::validation::walkers::validators::lists::ListVsListValidator;
 use crate::mdschema::validation::walkers::validators::quotes::QuoteVsQuoteValidator;
+use crate::mdschema::validation::walkers::validators::rulers::{ruler_matcher, validate_rulers};
+use crate::mdschema::validation::walkers::validators::sections::{
+    capture_section, only_skippable_sections_remain, repeated_section_matcher,
+    section_capture_matcher, skip_conditional_section, validate_repeated_sections,
+};
 use crate::mdschema::validation::walkers::validators::tables::TableVsTableValidator;
 use crate::mdschema::validation::walkers::validators::textual::TextualVsTextualValidator;
 use crate::mdschema::validation::walkers::validators::{Validator, ValidatorImpl};
@@ -90,14 +102,19 @@ impl ValidatorImpl for NodeVsNodeValidator {
             return ListVsListValidator
                 .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof);
         }
+        // Both are HTML blocks
+        else if both_are_html_blocks(&schema_node, &input_node) {
+            return HtmlVsHtmlValidator
+                .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof);
+        }
         // Both are ruler nodes
         else if both_are_rulers(&schema_node, &input_node) {
             trace!("Both are rulers. No extra validation happens for rulers.");
-        } else if both_are_headings(&schema_node, &input_node) {
+        } else if both_are_any_headings(&schema_node, &input_node) {
             // First, if they are headings, validate the headings themselves.
             trace!("Both are heading nodes, validating heading vs heading");
 
-            let heading_result = HeadingVsHeadingValidator
+            let heading_result = HeadingVsHeadingValidator::default()
                 .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof);
 
             result.join_other_result(&heading_result);
@@ -119,24 +136,65 @@ impl ValidatorImpl for NodeVsNodeValidator {
             // Since we're dealing with top level nodes it is our responsibility to ensure that they have the same number of children.
             // compare_node_children_lengths_check!(schema_cursor, input_cursor, got_eof, result);
 
+            if is_document_node(&input_node)
+                && let Some(empty_result) = validate_empty_documents(walker, got_eof)
+            {
+                return empty_result;
+            }
+
             let parent_pos = NodePosPair::from_cursors(&schema_cursor, &input_cursor);
 
+            // Whether the input cursor stays put for the next schema node,
+            // because a repeating section didn't match any input.
+            let mut hold_input = false;
+
             // Now actually go down to the children
             match (
-                schema_cursor.goto_first_child(),
-                input_cursor.goto_first_child(),
+                goto_first_schema_child(walker, &schema_node, &mut schema_cursor),
+                goto_first_child_skipping(&mut input_cursor, |node| walker.skips_input_node(node)),
             ) {
                 (true, true) => {
-                    let new_result = NodeVsNodeValidator
-                        .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof);
-                    result.join_other_result(&new_result);
+                    let scope = top_level_scope(walker, &schema_node, &schema_cursor);
+                    let pair_result = validate_child_pair(
+                        &walker.with_matches(result.value()),
+                        &mut schema_cursor,
+                        &mut input_cursor,
+                        got_eof,
+                    );
+                    let Some((new_result, hold)) = pair_result else {
+                        // Stop for now. We will revalidate from here later.
+                        result.set_farthest_reached_pos(parent_pos);
+                        return result;
+                    };
+                    hold_input = hold;
+                    result.join_scoped_result(&scope, &new_result);
                     result.sync_cursor_pos(&schema_cursor, &input_cursor);
+                    if is_document_node(&input_node) {
+                        walker.report_progress(&input_cursor, &result);
+                    }
+                    if walker.should_stop(&result) {
+                        return result;
+                    }
                 }
                 (true, false) if waiting_at_end(got_eof, walker.input_str(), &input_cursor) => {
                     // Stop for now. We will revalidate from here later.
                     result.set_farthest_reached_pos(parent_pos);
                     return result;
                 }
+                (false, true) if is_document_node(&input_node) => {
+                    let Some(trailing_result) = validate_trailing_content(
+                        walker,
+                        &schema_cursor,
+                        &input_cursor,
+                        got_eof,
+                    ) else {
+                        // Stop for now. We will revalidate from here later.
+                        result.set_farthest_reached_pos(parent_pos);
+                        return result;
+                    };
+                    result.join_other_result(&trailing_result);
+                    return result;
+                }
                 (false, true) => {
                     if waiting_at_end(got_eof, walker.input_str(), &input_cursor) {
                         // okay, we'll just wait!
@@ -149,13 +207,20 @@ impl ValidatorImpl for NodeVsNodeValidator {
                                 kind: MalformedStructureKind::InputHasChildSchemaDoesnt,
                             },
                         ));
+                        if walker.should_stop(&result) {
+                            return result;
+                        }
                     }
                 }
                 (true, false) => {
                     if waiting_at_end(got_eof, walker.input_str(), &input_cursor) {
                         // okay, we'll just wait!
                         return need_to_restart_result;
-                    } else {
+                    } else if !only_skippable_sections_remain(
+                        &schema_cursor,
+                        walker.schema_str(),
+                        |cursor| condition_not_met(&walker.with_matches(result.value()), cursor),
+                    ) {
                         result.add_error(ValidationError::SchemaViolation(
                             SchemaViolationError::MalformedNodeStructure {
                                 schema_index: schema_cursor.descendant_index(),
@@ -173,20 +238,54 @@ impl ValidatorImpl for NodeVsNodeValidator {
 
             loop {
                 match (
-                    schema_cursor.goto_next_sibling(),
-                    input_cursor.goto_next_sibling(),
+                    goto_next_sibling_skipping_directives(&mut schema_cursor, walker.schema_str()),
+                    std::mem::take(&mut hold_input)
+                        || goto_next_sibling_skipping(&mut input_cursor, |node| {
+                            walker.skips_input_node(node)
+                        }),
                 ) {
                     (true, true) => {
-                        let new_result = NodeVsNodeValidator
-                            .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof);
-                        result.join_other_result(&new_result);
+                        let scope = top_level_scope(walker, &schema_node, &schema_cursor);
+                        let pair_result = validate_child_pair(
+                            &walker.with_matches(result.value()),
+                            &mut schema_cursor,
+                            &mut input_cursor,
+                            got_eof,
+                        );
+                        let Some((new_result, hold)) = pair_result else {
+                            // Stop for now. We will revalidate from here later.
+                            result.set_farthest_reached_pos(parent_pos);
+                            return result;
+                        };
+                        hold_input = hold;
+                        result.join_scoped_result(&scope, &new_result);
                         result.sync_cursor_pos(&schema_cursor, &input_cursor);
+                        if is_document_node(&input_node) {
+                            walker.report_progress(&input_cursor, &result);
+                        }
+                        if walker.should_stop(&result) {
+                            return result;
+                        }
                     }
                     (true, false) if waiting_at_end(got_eof, walker.input_str(), &input_cursor) => {
                         // Stop for now. We will revalidate from here later.
                         result.set_farthest_reached_pos(parent_pos);
                         return result;
                     }
+                    (false, true) if is_document_node(&input_node) => {
+                        let Some(trailing_result) = validate_trailing_content(
+                            walker,
+                            &schema_cursor,
+                            &input_cursor,
+                            got_eof,
+                        ) else {
+                            // Stop for now. We will revalidate from here later.
+                            result.set_farthest_reached_pos(parent_pos);
+                            return result;
+                        };
+                        result.join_other_result(&trailing_result);
+                        return result;
+                    }
                     (false, true) => {
                         if waiting_at_end(got_eof, walker.input_str(), &input_cursor) {
                             // okay, we'll just wait!
@@ -199,13 +298,22 @@ impl ValidatorImpl for NodeVsNodeValidator {
                                     kind: MalformedStructureKind::InputHasChildSchemaDoesnt,
                                 },
                             ));
+                            if walker.should_stop(&result) {
+                                return result;
+                            }
                         }
                     }
                     (true, false) => {
                         if waiting_at_end(got_eof, walker.input_str(), &input_cursor) {
                             // okay, we'll just wait!
                             return need_to_restart_result;
-                        } else {
+                        } else if !only_skippable_sections_remain(
+                            &schema_cursor,
+                            walker.schema_str(),
+                            |cursor| {
+                                condition_not_met(&walker.with_matches(result.value()), cursor)
+                            },
+                        ) {
                             result.add_error(ValidationError::SchemaViolation(
                                 SchemaViolationError::MalformedNodeStructure {
                                     schema_index: schema_cursor.descendant_index(),
@@ -250,6 +358,246 @@ impl ValidatorImpl for NodeVsNodeValidator {
     }
 }
 
+/// Move the schema cursor to the first child of a top-level node that isn't a
+/// directive. For a document whose first input blocks were dropped, that's
+/// the schema block the first kept input block is compared against (see
+/// `bounded_memory`).
+fn goto_first_schema_child(
+    walker: &ValidatorWalker,
+    schema_node: &Node,
+    schema_cursor: &mut TreeCursor,
+) -> bool {
+    match walker.schema_start() {
+        Some(schema_start) if is_document_node(schema_node) => {
+            schema_cursor.goto_descendant(schema_start);
+            true
+        }
+        _ => goto_first_child_skipping_directives(schema_cursor, walker.schema_str()),
+    }
+}
+
+/// The scope that what gets captured from a child of a top-level node is
+/// nested under (see `scopes`). Only the children of the document are in
+/// scopes, since the scope of a heading covers everything in it.
+fn top_level_scope(
+    walker: &ValidatorWalker,
+    schema_node: &Node,
+    schema_cursor: &TreeCursor,
+) -> Vec<String> {
+    if is_document_node(schema_node) {
+        walker.scope_path(schema_cursor)
+    } else {
+        Vec::new()
+    }
+}
+
+/// Validate a pair of documents where the schema or the input is empty, with
+/// no blocks or only whitespace:
+///
+/// - Both empty: valid.
+/// - An empty schema: the input's blocks are trailing content, which
+///   `ValidateOptions::trailing_content` says what to do with.
+/// - An empty input: a single `SchemaViolationError::MissingContent`, unless
+///   every schema block can match nothing. Until we have the whole input, the
+///   rest of it may still come, so that's valid so far.
+///
+/// Returns `None` if both have blocks to validate. Input we dropped (see
+/// `bounded_memory`) isn't empty, so then we always return `None`.
+///
+/// # Arguments
+///
+/// * `walker`: The walker of the document pair.
+/// * `got_eof`: Whether we have received the full input document.
+fn validate_empty_documents(walker: &ValidatorWalker, got_eof: bool) -> Option<ValidationResult> {
+    if walker.schema_start().is_some() {
+        return None;
+    }
+
+    let mut schema_cursor = walker.schema_cursor().clone();
+    let mut input_cursor = walker.input_cursor().clone();
+    let mut result = ValidationResult::from_cursors(&schema_cursor, &input_cursor);
+
+    let schema_is_empty = walker.schema_str().trim().is_empty()
+        || !goto_first_child_skipping_directives(&mut schema_cursor, walker.schema_str());
+    let input_is_empty = walker.input_str().trim().is_empty()
+        || !goto_first_child_skipping(&mut input_cursor, |node| walker.skips_input_node(node));
+
+    match (schema_is_empty, input_is_empty) {
+        (false, false) => None,
+        (true, true) => Some(result),
+        (true, false) => Some(
+            // Back at the document, since there is no schema block
+            validate_trailing_content(walker, walker.schema_cursor(), &input_cursor, got_eof)
+                // Stop for now. We will revalidate from here later.
+                .unwrap_or(result),
+        ),
+        (false, true) => {
+            if got_eof
+                && !only_skippable_sections_remain(&schema_cursor, walker.schema_str(), |cursor| {
+                    condition_not_met(walker, cursor)
+                })
+            {
+                let first_block = get_node_text(&schema_cursor.node(), walker.schema_str());
+                result.add_error(ValidationError::SchemaViolation(
+                    SchemaViolationError::MissingContent {
+                        schema_index: schema_cursor.descendant_index(),
+                        input_index: walker.input_cursor().descendant_index(),
+                        expected: first_block.lines().next().unwrap_or("").trim().to_string(),
+                    },
+                ));
+            }
+            Some(result)
+        }
+    }
+}
+
+/// Deal with the blocks of a document's input after its last schema block,
+/// starting at the one the input cursor is at, as
+/// `ValidateOptions::trailing_content` says: report them as one error, ignore
+/// them or capture their markdown.
+///
+/// Returns `None` if we need the whole input before we know what they are.
+///
+/// # Arguments
+///
+/// * `walker`: The walker of the document pair.
+/// * `schema_cursor`: The cursor at the last schema block, or the document if
+///   the schema is empty.
+/// * `input_cursor`: The cursor at the first extra input block.
+/// * `got_eof`: Whether we have received the full input document.
+fn validate_trailing_content(
+    walker: &ValidatorWalker,
+    schema_cursor: &TreeCursor,
+    input_cursor: &TreeCursor,
+    got_eof: bool,
+) -> Option<ValidationResult> {
+    let mut result = ValidationResult::from_cursors(schema_cursor, input_cursor);
+
+    let capture_key = match &walker.options().trailing_content {
+        TrailingContent::Allow => return Some(result),
+        TrailingContent::Forbid => None,
+        TrailingContent::Capture(key) => Some(key),
+    };
+    // More blocks may still come
+    if !got_eof {
+        return None;
+    }
+
+    let mut last_input_cursor = input_cursor.clone();
+    let mut extra_blocks = 1;
+    while goto_next_sibling_skipping(&mut last_input_cursor, |node| walker.skips_input_node(node))
+    {
+        extra_blocks += 1;
+    }
+    trace!("Found {} trailing blocks", extra_blocks);
+
+    match capture_key {
+        Some(key) => {
+            let start = input_cursor.node().start_byte();
+            let end = last_input_cursor.node().end_byte();
+            let captured = walker.input_str()[start..end].trim_end();
+            result.set_match(key, walker.captured_value(captured, start));
+        }
+        None => result.add_error(ValidationError::SchemaViolation(
+            SchemaViolationError::TrailingContent {
+                schema_index: schema_cursor.descendant_index(),
+                input_index: input_cursor.descendant_index(),
+                extra_blocks,
+            },
+        )),
+    }
+
+    Some(result)
+}
+
+/// Whether the schema section at a cursor is skipped, because its heading has
+/// a condition that isn't met (see `conditions`). Only called once the input
+/// has run out, so an id that wasn't captured won't be.
+fn condition_not_met(walker: &ValidatorWalker, schema_cursor: &TreeCursor) -> bool {
+    walker.condition(schema_cursor).is_some_and(|condition| {
+        walker.is_condition_met(schema_cursor, condition, true) == Some(false)
+    })
+}
+
+/// Validate a pair of children of a document.
+///
+/// Usually this is just `NodeVsNodeValidator`, but a heading with a repeated
+/// or optional matcher starts a repeating section, which can span many (or,
+/// if it is optional, no) nodes on both sides. In that case the cursors are moved to the last node of the sections.
+/// Likewise a section capture paragraph takes up any number of input nodes,
+/// and a ruler matcher paragraph any number of thematic breaks. A heading
+/// with a condition that isn't met is skipped, along with its section (see
+/// `conditions`).
+///
+/// If the pair doesn't validate because the blocks stopped lining up, like
+/// after an extra paragraph in the input, we skip ahead to the next pair of
+/// blocks that do line up and validate that instead (see `resync`). We only
+/// do this once we have the whole input, since the blocks to skip ahead to
+/// might not have arrived yet.
+///
+/// Returns `None` if we need more input, and otherwise the result and whether
+/// the input cursor should stay where it is for the next schema node.
+fn validate_child_pair(
+    walker: &ValidatorWalker,
+    schema_cursor: &mut TreeCursor,
+    input_cursor: &mut TreeCursor,
+    got_eof: bool,
+) -> Option<(ValidationResult, bool)> {
+    if let Some(condition) = walker.condition(schema_cursor)
+        && !walker.is_condition_met(schema_cursor, condition, got_eof)?
+    {
+        let (result, matched_input) = skip_conditional_section(
+            &walker.with_cursors(schema_cursor, input_cursor),
+            condition,
+            got_eof,
+        )?;
+        result.walk_cursors_to_pos(schema_cursor, input_cursor);
+        return Some((result, !matched_input));
+    }
+
+    if section_capture_matcher(schema_cursor, walker.schema_str()).is_some() {
+        let (result, matched_input) =
+            capture_section(&walker.with_cursors(schema_cursor, input_cursor), got_eof)?;
+        result.walk_cursors_to_pos(schema_cursor, input_cursor);
+        return Some((result, !matched_input));
+    }
+
+    if ruler_matcher(schema_cursor, walker.schema_str()).is_some() {
+        let (result, matched_input) =
+            validate_rulers(&walker.with_cursors(schema_cursor, input_cursor), got_eof)?;
+        result.walk_cursors_to_pos(schema_cursor, input_cursor);
+        return Some((result, !matched_input));
+    }
+
+    if repeated_section_matcher(schema_cursor, walker.schema_str()).is_none() {
+        let result =
+            NodeVsNodeValidator.validate(&walker.with_cursors(schema_cursor, input_cursor), got_eof);
+
+        if got_eof
+            && !walker.options().fast_fail
+            && result.errors().iter().any(|e| !walker.is_warning(e))
+            && let Some(error) = resync_cursors(walker, schema_cursor, input_cursor)
+        {
+            // The errors of the unaligned pair are just noise
+            let mut resynced_result = ValidationResult::from_cursors(schema_cursor, input_cursor);
+            resynced_result.add_error(error);
+            resynced_result.join_other_result(
+                &NodeVsNodeValidator
+                    .validate(&walker.with_cursors(schema_cursor, input_cursor), got_eof),
+            );
+            return Some((resynced_result, false));
+        }
+
+        return Some((result, false));
+    }
+
+    let (result, matched_input) =
+        validate_repeated_sections(&walker.with_cursors(schema_cursor, input_cursor), got_eof)?;
+    result.walk_cursors_to_pos(schema_cursor, input_cursor);
+
+    Some((result, !matched_input))
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::json;
@@ -257,7 +605,7 @@ mod tests {
     use super::super::test_utils::ValidatorTester;
     use super::NodeVsNodeValidator;
     use crate::mdschema::validation::{
-        errors::{MalformedStructureKind, SchemaViolationError, ValidationError},
+        errors::{SchemaViolationError, ValidationError},
         node_pos_pair::NodePosPair,
         ts_types::both_are_paragraphs,
     };
@@ -444,10 +792,10 @@ mod tests {
         assert_eq!(
             result.errors(),
             &vec![ValidationError::SchemaViolation(
-                SchemaViolationError::MalformedNodeStructure {
+                SchemaViolationError::TrailingContent {
                     schema_index: 0,
                     input_index: 1,
-                    kind: MalformedStructureKind::InputHasChildSchemaDoesnt,
+                    extra_blocks: 1,
                 }
             )]
         );
@@ -541,4 +889,69 @@ buzz
         assert_eq!(result.errors(), vec![]);
         assert_eq!(*result.value(), json!({"items": ["foo", "bar", "buzz"]}));
     }
+
+    /// A schema of five sections, and input for it with `edit` applied.
+    fn five_sections(edit: impl Fn(String) -> String) -> (String, String) {
+        let mut schema_str = String::new();
+        let mut input_str = String::new();
+        for i in 1..=5 {
+            schema_str.push_str(&format!("## Section {i}\n\nValue: `v{i}:/\\d+/`\n\n"));
+            input_str.push_str(&format!("## Section {i}\n\nValue: {i}\n\n"));
+        }
+        (schema_str, edit(input_str))
+    }
+
+    #[test]
+    fn test_resyncs_after_an_extra_block() {
+        let (schema_str, input_str) = five_sections(|input| {
+            input
+                .replace("Value: 2\n\n", "Value: 2\n\nAn extra paragraph\n\n")
+                .replace("Value: 4", "Value: four")
+        });
+
+        let result = ValidatorTester::<NodeVsNodeValidator>::from_strs(&schema_str, &input_str)
+            .walk()
+            .validate_complete();
+
+        // One error for the extra paragraph, and the later sections are still
+        // validated against the right schema blocks
+        assert_eq!(result.errors().len(), 2, "Got {:?}", result.errors());
+        assert!(matches!(
+            result.errors()[0],
+            ValidationError::SchemaViolation(SchemaViolationError::UnalignedBlocks {
+                skipped_schema: 0,
+                skipped_input: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            result.errors()[1],
+            ValidationError::SchemaViolation(SchemaViolationError::NodeContentMismatch { .. })
+        ));
+        assert_eq!(
+            result.value(),
+            &json!({"v1": "1", "v2": "2", "v3": "3", "v5": "5"})
+        );
+    }
+
+    #[test]
+    fn test_resyncs_after_a_missing_block() {
+        let (schema_str, input_str) = five_sections(|input| {
+            input.replace("## Section 2\n\nValue: 2\n\n", "## Section 2\n\n")
+        });
+
+        let result = ValidatorTester::<NodeVsNodeValidator>::from_strs(&schema_str, &input_str)
+            .walk()
+            .validate_complete();
+
+        assert_eq!(result.errors().len(), 1, "Got {:?}", result.errors());
+        assert_eq!(
+            result.errors()[0].to_string(),
+            "Schema violation: Missing 1 block the schema expects"
+        );
+        assert_eq!(
+            result.value(),
+            &json!({"v1": "1", "v3": "3", "v4": "4", "v5": "5"})
+        );
+    }
 }
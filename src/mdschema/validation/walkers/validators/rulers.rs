@@ -0,0 +1,159 @@
+//! Ruler matcher validator.
+//!
+//! Types:
+//! - `validate_rulers`: matches a schema paragraph that is nothing but a ruler
+//!   matcher, like `` `ruler`? ``, against the thematic breaks (`---`) of the
+//!   input.
+//!
+//! A plain `` `ruler` `` stands for exactly one thematic break, `` `ruler`? ``
+//! for an optional one and `` `ruler`+ `` for one or more consecutive ones.
+//! For example, this schema
+//!
+//! ```md
+//! # Notes
+//!
+//! `ruler`?
+//!
+//! `note:/.+/`
+//! ```
+//!
+//! accepts a note with or without a `---` above it.
+use log::trace;
+use tree_sitter::TreeCursor;
+
+use crate::mdschema::validation::errors::{SchemaViolationError, ValidationError};
+use crate::mdschema::validation::matchers::matcher::{Matcher, MatcherKind, RULER_PATTERN};
+use crate::mdschema::validation::node_pos_pair::NodePosPair;
+use crate::mdschema::validation::ts_types::*;
+use crate::mdschema::validation::ts_utils::{goto_next_sibling_skipping, waiting_at_end};
+use crate::mdschema::validation::validator_walker::ValidatorWalker;
+use crate::mdschema::validation::walkers::ValidationResult;
+
+/// Get the ruler matcher of a schema paragraph that is nothing but a ruler
+/// matcher, like `` `ruler`+ ``.
+///
+/// # Arguments
+///
+/// * `schema_cursor`: The cursor pointing at a possible paragraph.
+/// * `schema_str`: The full schema.
+pub(crate) fn ruler_matcher(schema_cursor: &TreeCursor, schema_str: &str) -> Option<Matcher> {
+    let node = schema_cursor.node();
+    if !is_paragraph_node(&node) || node.named_child_count() != 1 {
+        return None;
+    }
+
+    let mut schema_cursor = schema_cursor.clone();
+    schema_cursor.goto_first_child();
+    if !is_inline_code_node(&schema_cursor.node()) {
+        return None;
+    }
+
+    Matcher::try_from_schema_cursor(&schema_cursor, schema_str)
+        .ok()
+        .filter(|matcher| {
+            matcher.id() == Some(RULER_PATTERN) && matches!(matcher.kind(), MatcherKind::All)
+        })
+}
+
+/// The fewest and the most consecutive thematic breaks a ruler matcher
+/// stands for. `None` means there is no limit.
+pub(crate) fn ruler_bounds(matcher: &Matcher) -> (usize, Option<usize>) {
+    let extras = matcher.extras();
+    match (extras.is_optional(), extras.is_one_or_more()) {
+        (true, true) => (0, None),
+        (true, false) => (0, Some(1)),
+        (false, true) => (1, None),
+        (false, false) => (1, Some(1)),
+    }
+}
+
+/// Match consecutive thematic breaks of the input, starting at the input node
+/// the walker is at, against the ruler matcher paragraph the schema cursor is
+/// at.
+///
+/// Returns `None` if we need more input before we know how many rulers there
+/// are. Otherwise returns the result, positioned at the schema paragraph and
+/// the last ruler we matched, and whether we matched any input at all. If we
+/// didn't, the input node is left for the next schema node.
+pub(super) fn validate_rulers(
+    walker: &ValidatorWalker,
+    got_eof: bool,
+) -> Option<(ValidationResult, bool)> {
+    let mut result = ValidationResult::from_cursors(walker.schema_cursor(), walker.input_cursor());
+
+    let matcher = ruler_matcher(walker.schema_cursor(), walker.schema_str())?;
+    let (min_rulers, max_rulers) = ruler_bounds(&matcher);
+
+    let mut input_cursor = walker.input_cursor().clone();
+    let mut last_ruler_cursor: Option<TreeCursor> = None;
+    let mut rulers = 0;
+    while max_rulers.is_none_or(|max_rulers| rulers < max_rulers)
+        && is_ruler_node(&input_cursor.node())
+    {
+        rulers += 1;
+        last_ruler_cursor = Some(input_cursor.clone());
+        if !goto_next_sibling_skipping(&mut input_cursor, |node| walker.skips_input_node(node)) {
+            // More rulers may still come.
+            if !got_eof && max_rulers.is_none_or(|max_rulers| rulers < max_rulers) {
+                return None;
+            }
+            break;
+        }
+    }
+    trace!("Matched {} rulers (expected {}..{:?})", rulers, min_rulers, max_rulers);
+
+    if rulers < min_rulers {
+        // What's there may still turn into a ruler
+        if waiting_at_end(got_eof, walker.input_str(), &input_cursor) {
+            return None;
+        }
+
+        result.add_error(ValidationError::SchemaViolation(
+            SchemaViolationError::NodeTypeMismatch {
+                schema_index: walker.schema_cursor().descendant_index(),
+                input_index: input_cursor.descendant_index(),
+                expected: "thematic_break".to_string(),
+                actual: input_cursor.node().kind().to_string(),
+            },
+        ));
+    }
+
+    let matched_input = last_ruler_cursor.is_some();
+    let input_end = last_ruler_cursor.unwrap_or_else(|| walker.input_cursor().clone());
+    result.set_farthest_reached_pos(NodePosPair::from_cursors(walker.schema_cursor(), &input_end));
+
+    Some((result, matched_input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ruler_bounds, ruler_matcher};
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    #[test]
+    fn test_ruler_matcher() {
+        let schema_str = "`ruler`\n\n`ruler`?\n\n`ruler`+\n\n`ruler` and text\n\n`name:/\\w+/`\n";
+        let tree = parse_markdown(schema_str).unwrap();
+        let mut cursor = tree.walk();
+        cursor.goto_first_child();
+
+        let mut bounds = Vec::new();
+        loop {
+            bounds.push(ruler_matcher(&cursor, schema_str).map(|matcher| ruler_bounds(&matcher)));
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+
+        assert_eq!(
+            bounds,
+            vec![
+                Some((1, Some(1))),
+                Some((0, Some(1))),
+                Some((1, None)),
+                None,
+                None,
+            ]
+        );
+    }
+}
@@ -0,0 +1,541 @@
+//! Repeating section validator.
+//!
+//! Types:
+//! - `validate_repeated_sections`: matches a schema section whose heading
+//!   holds a repeated or optional matcher against as many consecutive input
+//!   sections as the matcher allows.
+//! - `capture_section`: captures the rest of an input section as raw markdown,
+//!   for a schema paragraph like `` `description:section` ``.
+//! - `skip_conditional_section`: skips a schema section whose heading has a
+//!   condition that isn't met (see `conditions`).
+//!
+//! A section is a heading and every sibling after it up to the next heading of
+//! the same or a higher level. For example, this schema
+//!
+//! ```md
+//! # Changelog
+//!
+//! ## `date:/\d{4}-\d{2}-\d{2}/`{1,}
+//!
+//! - `change:/.+/`{1,}
+//! ```
+//!
+//! Matches a changelog with one or more dated sections, each with a list of
+//! changes, and captures
+//!
+//! ```json
+//! {
+//!     "date": [
+//!         { "date": "2024-02-01", "change": ["Fix the bug"] },
+//!         { "date": "2024-01-01", "change": ["Add the bug", "Add a feature"] }
+//!     ]
+//! }
+//! ```
+//!
+//! An optional section, like `` ## `notes:/Notes/`? ``, is a section that
+//! repeats at most once. Its captures are not wrapped in an array, and if the
+//! input doesn't have it they are left out entirely.
+use log::trace;
+use serde_json::Value;
+use tree_sitter::{Node, TreeCursor};
+
+use crate::mdschema::validation::conditions::Condition;
+use crate::mdschema::validation::errors::{
+    MalformedStructureKind, SchemaViolationError, ValidationError,
+};
+use crate::mdschema::validation::matchers::matcher::{Matcher, MatcherKind};
+use crate::mdschema::validation::node_pos_pair::NodePosPair;
+use crate::mdschema::validation::ts_types::*;
+use crate::mdschema::validation::ts_utils::{
+    goto_next_sibling_skipping, heading_level, is_schema_only_node, waiting_at_end,
+};
+use crate::mdschema::validation::validator_walker::ValidatorWalker;
+use crate::mdschema::validation::walkers::ValidationResult;
+use crate::mdschema::validation::walkers::helpers::check_repeating_matchers::find_matcher;
+use crate::mdschema::validation::walkers::validators::Validator;
+use crate::mdschema::validation::walkers::validators::headings::{
+    HeadingVsHeadingValidator, HeadingVsHeadingValidatorBuilder,
+};
+use crate::mdschema::validation::walkers::validators::nodes::NodeVsNodeValidator;
+use crate::mdschema::validation::walkers::validators::rulers::{ruler_bounds, ruler_matcher};
+
+/// Get the repeated or optional matcher in a schema heading, if it starts a
+/// repeating or optional section.
+///
+/// # Arguments
+///
+/// * `schema_cursor`: The cursor pointing at a possible `atx_heading`.
+/// * `schema_str`: The full input document (so far).
+pub(crate) fn repeated_section_matcher(
+    schema_cursor: &TreeCursor,
+    schema_str: &str,
+) -> Option<Matcher> {
+    if !is_heading_node(&schema_cursor.node()) {
+        return None;
+    }
+
+    let mut schema_cursor = schema_cursor.clone();
+    schema_cursor.goto_first_child(); // the heading marker
+    if !schema_cursor.goto_next_sibling() || !is_heading_content_node(&schema_cursor.node()) {
+        return None;
+    }
+
+    let matcher_index = find_matcher(&schema_cursor, schema_str, |matcher| {
+        matcher.is_repeated() || matcher.is_optional()
+    })?;
+    schema_cursor.goto_descendant(matcher_index);
+    Matcher::try_from_schema_cursor(&schema_cursor, schema_str).ok()
+}
+
+/// Validate consecutive input sections against a repeating schema section.
+///
+/// Returns `None` if we need more input before we can tell which sections
+/// belong to the repetition. Otherwise returns the result, positioned at the
+/// last node of the schema section and the last node of the input sections
+/// that we matched, and whether we matched any input at all.
+///
+/// An input section belongs to the repetition if its heading is at the same
+/// level as the schema heading and matches it. An input section only belongs
+/// to an optional section if all of it matches, since otherwise it is more
+/// likely to be the section the schema expects next.
+pub(super) fn validate_repeated_sections(
+    walker: &ValidatorWalker,
+    got_eof: bool,
+) -> Option<(ValidationResult, bool)> {
+    let mut result = ValidationResult::from_cursors(walker.schema_cursor(), walker.input_cursor());
+
+    let matcher = repeated_section_matcher(walker.schema_cursor(), walker.schema_str())?;
+    let extras = matcher.extras();
+    let max_sections = match matcher.is_optional() {
+        true => 1,
+        false => extras.max_items_or(usize::MAX),
+    };
+    let level = heading_level(&walker.schema_cursor().node());
+
+    let schema_section = section_cursors(walker.schema_cursor(), |node| {
+        is_schema_only_node(node, walker.schema_str())
+    });
+    let schema_section_end = schema_section
+        .last()
+        .cloned()
+        .unwrap_or_else(|| walker.schema_cursor().clone());
+
+    let mut sections: Vec<Value> = vec![];
+    let mut input_cursor = walker.input_cursor().clone();
+    let mut last_input_cursor: Option<TreeCursor> = None;
+
+    while sections.len() < max_sections {
+        if heading_level(&input_cursor.node()) != level {
+            break;
+        }
+
+        let input_section =
+            section_cursors(&input_cursor, |node| walker.skips_input_node(node));
+        let input_section_end = input_section.last().cloned().unwrap_or(input_cursor.clone());
+
+        // The section may still grow, or its heading may still change.
+        if waiting_at_end(got_eof, walker.input_str(), &input_section_end) {
+            return None;
+        }
+
+        let heading_result = HeadingVsHeadingValidatorBuilder::default()
+            .allow_repeating(true)
+            .build()
+            .unwrap()
+            .validate(&walker.with_cursors(walker.schema_cursor(), &input_cursor), got_eof);
+        if heading_result.has_errors() {
+            trace!("Input heading doesn't match the repeating section heading, stopping");
+            break;
+        }
+
+        let mut section_result = heading_result;
+        section_result.join_other_result(&validate_section_bodies(
+            walker,
+            &schema_section,
+            &input_section,
+            got_eof,
+        ));
+        if matcher.is_optional() && section_result.has_errors() {
+            trace!("Input section doesn't match the optional section, skipping it");
+            break;
+        }
+        result.join_errors(section_result.errors());
+        sections.push(section_result.value().clone());
+
+        last_input_cursor = Some(input_section_end.clone());
+        input_cursor = input_section_end;
+        if walker.should_stop(&result)
+            || !goto_next_sibling_skipping(&mut input_cursor, |node| walker.skips_input_node(node))
+        {
+            break;
+        }
+    }
+
+    if sections.len() < extras.min_items_or(0) {
+        result.add_error(ValidationError::SchemaViolation(
+            SchemaViolationError::WrongSectionCount {
+                schema_index: walker.schema_cursor().descendant_index(),
+                input_index: walker.input_cursor().descendant_index(),
+                min: extras.min_items(),
+                max: extras.max_items(),
+                actual: sections.len(),
+            },
+        ));
+    }
+
+    match matcher.id() {
+        Some(id) if !matcher.is_optional() => result.set_match(id, Value::Array(sections)),
+        _ => {
+            for section in sections {
+                result.join_value(section);
+            }
+        }
+    }
+
+    let matched_input = last_input_cursor.is_some();
+    let input_end = last_input_cursor.unwrap_or_else(|| walker.input_cursor().clone());
+    result.set_farthest_reached_pos(NodePosPair::from_cursors(&schema_section_end, &input_end));
+
+    Some((result, matched_input))
+}
+
+/// Get the section matcher of a schema paragraph that is nothing but a section
+/// capture, like `` `description:section` ``.
+///
+/// # Arguments
+///
+/// * `schema_cursor`: The cursor pointing at a possible paragraph.
+/// * `schema_str`: The full schema.
+pub(crate) fn section_capture_matcher(
+    schema_cursor: &TreeCursor,
+    schema_str: &str,
+) -> Option<Matcher> {
+    let node = schema_cursor.node();
+    if !is_paragraph_node(&node) || node.named_child_count() != 1 {
+        return None;
+    }
+
+    let mut schema_cursor = schema_cursor.clone();
+    schema_cursor.goto_first_child();
+    if !is_inline_code_node(&schema_cursor.node()) {
+        return None;
+    }
+
+    Matcher::try_from_schema_cursor(&schema_cursor, schema_str)
+        .ok()
+        .filter(|matcher| matches!(matcher.kind(), MatcherKind::Section))
+}
+
+/// Capture everything from the input node the walker is at up to the next
+/// heading of the same or a higher level than the heading the schema
+/// paragraph is under, as raw markdown. Without a heading above it, the
+/// capture runs to the end of the document.
+///
+/// Returns `None` if we need more input before we know where the section
+/// ends. Otherwise returns the result, positioned at the schema paragraph and
+/// the last input node we captured, and whether we captured any input at all.
+pub(super) fn capture_section(
+    walker: &ValidatorWalker,
+    got_eof: bool,
+) -> Option<(ValidationResult, bool)> {
+    let mut result = ValidationResult::from_cursors(walker.schema_cursor(), walker.input_cursor());
+
+    let matcher = section_capture_matcher(walker.schema_cursor(), walker.schema_str())?;
+    let level = {
+        let mut schema_cursor = walker.schema_cursor().clone();
+        loop {
+            if !schema_cursor.goto_previous_sibling() {
+                break None;
+            }
+            if let Some(level) = heading_level(&schema_cursor.node()) {
+                break Some(level);
+            }
+        }
+    };
+
+    let mut input_cursor = walker.input_cursor().clone();
+    let mut last_input_cursor: Option<TreeCursor> = None;
+    loop {
+        if let Some(input_level) = heading_level(&input_cursor.node())
+            && level.is_some_and(|level| input_level <= level)
+        {
+            break;
+        }
+
+        last_input_cursor = Some(input_cursor.clone());
+        if !input_cursor.goto_next_sibling() {
+            // The section may still grow.
+            if !got_eof {
+                return None;
+            }
+            break;
+        }
+    }
+
+    let start = walker.input_cursor().node().start_byte();
+    let end = last_input_cursor
+        .as_ref()
+        .map_or(start, |last_input_cursor| last_input_cursor.node().end_byte());
+    let captured = walker.input_str()[start..end].trim_end();
+    trace!("Captured section for id {:?}: '{}'", matcher.id(), captured);
+    result.set_matcher_match(
+        &matcher,
+        walker.captured_value(captured, start),
+        walker.schema_cursor().descendant_index(),
+        walker.input_cursor().descendant_index(),
+    );
+
+    let matched_input = last_input_cursor.is_some();
+    let input_end = last_input_cursor.unwrap_or_else(|| walker.input_cursor().clone());
+    result.set_farthest_reached_pos(NodePosPair::from_cursors(walker.schema_cursor(), &input_end));
+
+    Some((result, matched_input))
+}
+
+/// Skip a schema section whose heading has a condition that isn't met (see
+/// `conditions`), since the input shouldn't have it.
+///
+/// If the input has a section whose heading matches it anyway, that's
+/// reported as a `SchemaViolationError::ConditionNotMet`, and we skip that
+/// input section too.
+///
+/// Returns `None` if we need more input before we know whether the input has
+/// the section. Otherwise returns the result, positioned at the last node of
+/// the schema section and the last input node we skipped, and whether we
+/// skipped any input at all.
+pub(super) fn skip_conditional_section(
+    walker: &ValidatorWalker,
+    condition: &Condition,
+    got_eof: bool,
+) -> Option<(ValidationResult, bool)> {
+    let mut result = ValidationResult::from_cursors(walker.schema_cursor(), walker.input_cursor());
+
+    let schema_section_end = section_cursors(walker.schema_cursor(), |_| false)
+        .pop()
+        .unwrap_or_else(|| walker.schema_cursor().clone());
+
+    let mut input_end = walker.input_cursor().clone();
+    let mut matched_input = false;
+    if heading_level(&walker.input_cursor().node()) == heading_level(&walker.schema_cursor().node())
+    {
+        let input_section_end =
+            section_cursors(walker.input_cursor(), |node| walker.skips_input_node(node))
+                .pop()
+                .unwrap_or_else(|| walker.input_cursor().clone());
+
+        // The section may still grow, or its heading may still change.
+        if waiting_at_end(got_eof, walker.input_str(), &input_section_end) {
+            return None;
+        }
+
+        if !HeadingVsHeadingValidator::default()
+            .validate(walker, got_eof)
+            .has_errors()
+        {
+            trace!("Input has the section of a condition that isn't met, skipping it");
+            result.add_error(condition.not_met_error(
+                walker.captured_for(walker.schema_cursor(), &condition.id),
+                walker.schema_cursor().descendant_index(),
+                walker.input_cursor().descendant_index(),
+            ));
+            input_end = input_section_end;
+            matched_input = true;
+        }
+    }
+
+    result.set_farthest_reached_pos(NodePosPair::from_cursors(&schema_section_end, &input_end));
+    Some((result, matched_input))
+}
+
+/// Whether the schema node the cursor is at, and every schema node after it,
+/// belong to sections that can match no input at all: optional sections,
+/// repeating sections without a minimum, optional rulers, and sections for
+/// which `skips_section` returns true, like those whose condition isn't met
+/// (see `conditions`).
+///
+/// # Arguments
+///
+/// * `schema_cursor`: The cursor pointing at the first remaining schema node.
+/// * `schema_str`: The full input document (so far).
+/// * `skips_section`: Whether the section at a schema heading is skipped.
+pub(crate) fn only_skippable_sections_remain(
+    schema_cursor: &TreeCursor,
+    schema_str: &str,
+    skips_section: impl Fn(&TreeCursor) -> bool,
+) -> bool {
+    let mut schema_cursor = schema_cursor.clone();
+    loop {
+        if skips_section(&schema_cursor) {
+            if let Some(section_end) = section_cursors(&schema_cursor, |_| false).pop() {
+                schema_cursor = section_end;
+            }
+        } else if let Some(matcher) = ruler_matcher(&schema_cursor, schema_str) {
+            if ruler_bounds(&matcher).0 > 0 {
+                return false;
+            }
+        } else if !is_schema_only_node(&schema_cursor.node(), schema_str)
+            && section_capture_matcher(&schema_cursor, schema_str).is_none()
+        {
+            let Some(matcher) = repeated_section_matcher(&schema_cursor, schema_str) else {
+                return false;
+            };
+            if matcher.extras().min_items_or(0) > 0 {
+                return false;
+            }
+            if let Some(section_end) = section_cursors(&schema_cursor, |_| false).pop() {
+                schema_cursor = section_end;
+            }
+        }
+
+        if !schema_cursor.goto_next_sibling() {
+            return true;
+        }
+    }
+}
+
+/// Validate everything after the headings of a schema section and an input
+/// section, pairing up their nodes in order.
+fn validate_section_bodies(
+    walker: &ValidatorWalker,
+    schema_section: &[TreeCursor],
+    input_section: &[TreeCursor],
+    got_eof: bool,
+) -> ValidationResult {
+    let mut result = ValidationResult::from_cursors(walker.schema_cursor(), walker.input_cursor());
+
+    let schema_body = schema_section.get(1..).unwrap_or_default();
+    let input_body = input_section.get(1..).unwrap_or_default();
+
+    for (schema_cursor, input_cursor) in schema_body.iter().zip(input_body) {
+        let pair_result =
+            NodeVsNodeValidator.validate(&walker.with_cursors(schema_cursor, input_cursor), got_eof);
+        result.join_other_result(&pair_result);
+        if walker.should_stop(&result) {
+            return result;
+        }
+    }
+
+    let last_input_cursor = input_section.last().unwrap_or(walker.input_cursor());
+    if let Some(schema_cursor) = schema_body.get(input_body.len()) {
+        result.add_error(ValidationError::SchemaViolation(
+            SchemaViolationError::MalformedNodeStructure {
+                schema_index: schema_cursor.descendant_index(),
+                input_index: last_input_cursor.descendant_index(),
+                kind: MalformedStructureKind::SchemaHasChildInputDoesnt,
+            },
+        ));
+    } else if let Some(input_cursor) = input_body.get(schema_body.len()) {
+        let last_schema_cursor = schema_section.last().unwrap_or(walker.schema_cursor());
+        result.add_error(ValidationError::SchemaViolation(
+            SchemaViolationError::MalformedNodeStructure {
+                schema_index: last_schema_cursor.descendant_index(),
+                input_index: input_cursor.descendant_index(),
+                kind: MalformedStructureKind::InputHasChildSchemaDoesnt,
+            },
+        ));
+    }
+
+    result
+}
+
+/// Cursors at each node of the section starting at the heading the cursor
+/// points at, leaving out nodes for which `skip` returns true.
+fn section_cursors<'a>(
+    heading_cursor: &TreeCursor<'a>,
+    skip: impl Fn(&Node) -> bool,
+) -> Vec<TreeCursor<'a>> {
+    let level = heading_level(&heading_cursor.node()).unwrap_or(0);
+
+    let mut cursors = vec![heading_cursor.clone()];
+    let mut cursor = heading_cursor.clone();
+    while cursor.goto_next_sibling() {
+        if heading_level(&cursor.node()).is_some_and(|next_level| next_level <= level) {
+            break;
+        }
+        if !skip(&cursor.node()) {
+            cursors.push(cursor.clone());
+        }
+    }
+
+    cursors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{only_skippable_sections_remain, repeated_section_matcher, section_cursors};
+    use crate::mdschema::validation::ts_utils::parse_markdown;
+
+    #[test]
+    fn test_repeated_section_matcher() {
+        let schema_str = "## `date:/\\d+/`{1,}\n\n## `date:/\\d+/`\n\nText `t`{1,}\n";
+        let schema_tree = parse_markdown(schema_str).unwrap();
+        let mut schema_cursor = schema_tree.walk();
+        schema_cursor.goto_first_child();
+
+        let matcher = repeated_section_matcher(&schema_cursor, schema_str).unwrap();
+        assert_eq!(matcher.id(), Some("date"));
+
+        // Not repeated
+        schema_cursor.goto_next_sibling();
+        assert!(repeated_section_matcher(&schema_cursor, schema_str).is_none());
+
+        // Not a heading
+        schema_cursor.goto_next_sibling();
+        assert!(repeated_section_matcher(&schema_cursor, schema_str).is_none());
+
+        let schema_str = "## `notes:/Notes/`?\n";
+        let schema_tree = parse_markdown(schema_str).unwrap();
+        let mut schema_cursor = schema_tree.walk();
+        schema_cursor.goto_first_child();
+        assert!(repeated_section_matcher(&schema_cursor, schema_str).unwrap().is_optional());
+    }
+
+    #[test]
+    fn test_only_skippable_sections_remain() {
+        let schema_str =
+            "# Title\n\n## `notes:/Notes/`?\n\nText\n\n## `log:/.+/`{,}\n\n- item\n\n## `see:/See also/`{1,}\n";
+        let schema_tree = parse_markdown(schema_str).unwrap();
+        let mut schema_cursor = schema_tree.walk();
+        schema_cursor.goto_first_child();
+        assert!(!only_skippable_sections_remain(
+            &schema_cursor,
+            schema_str,
+            |_| false
+        ));
+
+        // The optional and unbounded sections, but then one that needs input
+        schema_cursor.goto_next_sibling();
+        assert!(!only_skippable_sections_remain(
+            &schema_cursor,
+            schema_str,
+            |_| false
+        ));
+
+        let schema_str = "## `notes:/Notes/`?\n\nText\n\n## `log:/.+/`{,}\n\n- item\n";
+        let schema_tree = parse_markdown(schema_str).unwrap();
+        let mut schema_cursor = schema_tree.walk();
+        schema_cursor.goto_first_child();
+        assert!(only_skippable_sections_remain(
+            &schema_cursor,
+            schema_str,
+            |_| false
+        ));
+    }
+
+    #[test]
+    fn test_section_cursors_stop_at_same_or_higher_level_heading() {
+        let input_str = "## One\n\nText\n\n### Sub\n\nMore\n\n## Two\n\nText\n";
+        let input_tree = parse_markdown(input_str).unwrap();
+        let mut input_cursor = input_tree.walk();
+        input_cursor.goto_first_child();
+
+        let kinds: Vec<_> = section_cursors(&input_cursor, |_| false)
+            .iter()
+            .map(|cursor| cursor.node().kind())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec!["atx_heading", "paragraph", "atx_heading", "paragraph"]
+        );
+    }
+}
@@ -7,11 +7,12 @@
 //!   repeaters, keeping the schema stationary while validating multiple input
 //!   rows against a repeating matcher row.
 use crate::mdschema::validation::errors::{
-    MalformedStructureKind, NodeContentMismatchKind, SchemaViolationError, ValidationError,
+    MalformedStructureKind, SchemaError, SchemaViolationError, ValidationError,
 };
-use crate::mdschema::validation::matchers::matcher::Matcher;
+use crate::mdschema::validation::matchers::matcher::{Matcher, RegexLimits};
 use crate::mdschema::validation::matchers::matcher_extras::MatcherExtras;
 use crate::mdschema::validation::node_pos_pair::NodePosPair;
+use crate::mdschema::validation::spans::subslice_offset;
 use crate::mdschema::validation::walkers::ValidationResult;
 use crate::mdschema::validation::walkers::validators::containers::ContainerVsContainerValidator;
 use crate::mdschema::validation::walkers::validators::{Validator, ValidatorImpl};
@@ -20,6 +21,7 @@ use crate::mdschema::validation::ts_utils::{get_node_text, waiting_at_end};
 use crate::mdschema::validation::validator_walker::ValidatorWalker;
 use crate::invariant_violation;
 use log::trace;
+use serde_json::Value;
 use tree_sitter::TreeCursor;
 
 /// Validate two tables.
@@ -107,6 +109,16 @@ impl ValidatorImpl for TableVsTableValidator {
                 repeated_row_result.walk_cursors_to_pos(&mut schema_cursor, &mut input_cursor);
 
                 // Now continue to advance both cursors to the next row
+            } else if let Some(error) = row_length_error(
+                &schema_cursor,
+                &input_cursor,
+                count_cells(&schema_cursor),
+                walker,
+                got_eof,
+            ) {
+                // A ragged row. Its cells can't be lined up with the schema's,
+                // so we report the row as a whole and move on to the next one.
+                result.add_error(error);
             } else {
                 // Dive in to the first row, iterate over children, hop back (hop
                 // back is automatic since we use different cursors in the context)
@@ -254,7 +266,7 @@ impl RepeatedRowVsRowValidator {
 
 impl ValidatorImpl for RepeatedRowVsRowValidator {
     fn validate_impl(&self, walker: &ValidatorWalker, got_eof: bool) -> ValidationResult {
-        let mut schema_cursor = walker.schema_cursor().clone();
+        let schema_cursor = walker.schema_cursor().clone();
         let mut input_cursor = walker.input_cursor().clone();
 
         let mut result = ValidationResult::from_cursors(&schema_cursor, &input_cursor);
@@ -271,7 +283,9 @@ impl ValidatorImpl for RepeatedRowVsRowValidator {
             )
         }
 
-        let max_bound = self.bounds.1.unwrap_or(usize::MAX);
+        let (min_bound, max_bound) = self.bounds;
+        // The last cell of a repeated schema row holds the `{min,max}` extras
+        let expected_cells = count_cells(&schema_cursor).saturating_sub(1);
 
         let corresponding_matchers = {
             let mut schema_cursor = schema_cursor.clone();
@@ -282,7 +296,11 @@ impl ValidatorImpl for RepeatedRowVsRowValidator {
                 invariant_violation!("should have had first child")
             }
 
-            get_cell_indexes_that_have_simple_matcher(&schema_cursor, walker.schema_str())
+            get_cell_indexes_that_have_simple_matcher(
+                &schema_cursor,
+                walker.schema_str(),
+                &walker.options().regex_limits,
+            )
         };
 
         let corresponding_matchers_only_matchers: Vec<&Matcher> = corresponding_matchers
@@ -291,36 +309,78 @@ impl ValidatorImpl for RepeatedRowVsRowValidator {
             .collect();
         let num_corresponding_matchers = corresponding_matchers_only_matchers.len();
 
-        let mut all_matches: Vec<Vec<String>> = vec![Vec::new(); num_corresponding_matchers];
+        let mut all_matches: Vec<Vec<Value>> = vec![Vec::new(); num_corresponding_matchers];
+
+        let mut rows_matched = 0;
+        'row_iter: loop {
+            if let Some(error) = row_length_error(
+                &schema_cursor,
+                &input_cursor,
+                expected_cells,
+                walker,
+                got_eof,
+            ) {
+                result.add_error(error);
+                return result;
+            }
 
-        'row_iter: for _ in 0..max_bound {
             // Validate the entire row
             let mut input_cursor_at_first_cell = get_cursor_at_first_cell(&input_cursor);
             let mut schema_cursor_at_first_cell = get_cursor_at_first_cell(&schema_cursor);
 
             let mut matcher_num = 0;
             'col_iter: for i in 0.. {
-                let cell_str =
-                    get_node_text(&input_cursor_at_first_cell.node(), walker.input_str()).trim();
+                let cell_text =
+                    get_node_text(&input_cursor_at_first_cell.node(), walker.input_str());
+                let cell_str = cell_text.trim();
 
                 match corresponding_matchers.get(i).unwrap() {
-                    Some(matcher) => match matcher.match_str(cell_str) {
-                        Some(captured_str) => {
+                    Some(matcher) => match matcher
+                        .try_match_str(cell_str, walker.options().max_matcher_input_len)
+                    {
+                        Ok(Some(captured_str)) => {
+                            let Some(captured_offset) = subslice_offset(cell_text, captured_str)
+                            else {
+                                invariant_violation!(
+                                    result,
+                                    &schema_cursor_at_first_cell,
+                                    &input_cursor_at_first_cell,
+                                    "a match should be a slice of the text it is in"
+                                );
+                            };
+                            let captured_start =
+                                input_cursor_at_first_cell.node().start_byte() + captured_offset;
+                            let Some(value) = result.typed_value(
+                                matcher,
+                                walker.captured_value(captured_str, captured_start),
+                                schema_cursor_at_first_cell.descendant_index(),
+                                input_cursor_at_first_cell.descendant_index(),
+                            ) else {
+                                return result;
+                            };
                             all_matches
                                 .get_mut(matcher_num)
                                 .unwrap() // we pre filled it properly ahead of time
-                                .push(captured_str.to_string());
+                                .push(value);
 
                             matcher_num += 1;
                         }
-                        None => {
+                        Ok(None) => {
                             result.add_error(ValidationError::SchemaViolation(
-                                SchemaViolationError::NodeContentMismatch {
+                                matcher.mismatch_error(
+                                    schema_cursor_at_first_cell.descendant_index(),
+                                    input_cursor_at_first_cell.descendant_index(),
+                                    cell_str.into(),
+                                ),
+                            ));
+
+                            return result;
+                        }
+                        Err(error) => {
+                            result.add_error(ValidationError::SchemaError(
+                                SchemaError::MatcherError {
+                                    error,
                                     schema_index: schema_cursor_at_first_cell.descendant_index(),
-                                    input_index: input_cursor_at_first_cell.descendant_index(),
-                                    expected: matcher.pattern().to_string(),
-                                    actual: cell_str.into(),
-                                    kind: NodeContentMismatchKind::Matcher,
                                 },
                             ));
 
@@ -353,22 +413,45 @@ impl ValidatorImpl for RepeatedRowVsRowValidator {
                 }
             }
 
+            rows_matched += 1;
+            if max_bound.is_some_and(|max| rows_matched >= max) {
+                break 'row_iter;
+            }
+
             // Move the input to the next row (the schema stays put!)
-            if !input_cursor.goto_next_sibling() {
+            let mut next_input_cursor = input_cursor.clone();
+            if !next_input_cursor.goto_next_sibling() {
                 break 'row_iter;
             }
+            input_cursor = next_input_cursor;
+        }
+
+        if let Some(min) = min_bound
+            && rows_matched < min
+            && !waiting_at_end(got_eof, walker.input_str(), &input_cursor)
+        {
+            result.add_error(ValidationError::SchemaViolation(
+                SchemaViolationError::WrongRowCount {
+                    schema_index: schema_cursor.descendant_index(),
+                    input_index: input_cursor.descendant_index(),
+                    min: min_bound,
+                    max: max_bound,
+                    actual: rows_matched,
+                },
+            ));
         }
 
         for (matches, matcher) in all_matches.iter().zip(corresponding_matchers_only_matchers) {
             if let Some(key) = matcher.id() {
-                result.set_match(key, matches.clone().into());
+                result.set_match(key, Value::Array(matches.clone()));
             }
         }
 
         // Update the result to reflect where we ended up:
         // - schema_cursor stays at the repeating row definition
-        // - input_cursor has advanced past all matched rows
-        schema_cursor.goto_next_sibling();
+        // - input_cursor is at the last matched row
+        //
+        // so that both move on to the row after them together.
         result.sync_cursor_pos(&schema_cursor, &input_cursor);
 
         result
@@ -383,6 +466,7 @@ impl ValidatorImpl for RepeatedRowVsRowValidator {
 ///
 /// * `schema_cursor` - A cursor pointing to the first cell in the repeating schema row.
 /// * `schema_str` - The string representation of the schema.
+/// * `limits` - The limits to compile the matchers' regexes within.
 ///
 /// # Returns
 ///
@@ -390,6 +474,7 @@ impl ValidatorImpl for RepeatedRowVsRowValidator {
 fn get_cell_indexes_that_have_simple_matcher(
     schema_cursor: &TreeCursor,
     schema_str: &str,
+    limits: &RegexLimits,
 ) -> Vec<Option<Matcher>> {
     #[cfg(feature = "invariant_violations")]
     if !is_table_cell_node(&schema_cursor.node()) {
@@ -432,9 +517,11 @@ fn get_cell_indexes_that_have_simple_matcher(
                     for _ in 0..code_idx {
                         matcher_cursor.goto_next_sibling();
                     }
-                    if let Ok(matcher) =
-                        Matcher::try_from_schema_cursor(&matcher_cursor, schema_str)
-                    {
+                    if let Ok(matcher) = Matcher::try_from_schema_cursor_with_limits(
+                        &matcher_cursor,
+                        schema_str,
+                        limits,
+                    ) {
                         indexes.push(Some(matcher));
                     } else {
                         indexes.push(None);
@@ -459,6 +546,47 @@ fn get_cell_indexes_that_have_simple_matcher(
     indexes
 }
 
+/// The number of cells in the row the cursor is at.
+fn count_cells(row_cursor: &TreeCursor) -> usize {
+    let row = row_cursor.node();
+    row.children(&mut row.walk()).filter(is_table_cell_node).count()
+}
+
+/// A `ChildrenLengthMismatch` for the input row if it doesn't have
+/// `expected_cells` cells.
+///
+/// A row that runs to the end of the input may still be missing cells, so
+/// unless we have the whole input it's never considered ragged.
+///
+/// # Arguments
+///
+/// * `schema_cursor`: A cursor at the schema row.
+/// * `input_cursor`: A cursor at the input row.
+/// * `expected_cells`: How many cells the input row should have.
+/// * `walker`: The walker we are validating with.
+/// * `got_eof`: Whether we have received the full input document.
+fn row_length_error(
+    schema_cursor: &TreeCursor,
+    input_cursor: &TreeCursor,
+    expected_cells: usize,
+    walker: &ValidatorWalker,
+    got_eof: bool,
+) -> Option<ValidationError> {
+    let actual_cells = count_cells(input_cursor);
+    if actual_cells == expected_cells || waiting_at_end(got_eof, walker.input_str(), input_cursor) {
+        return None;
+    }
+
+    Some(ValidationError::SchemaViolation(
+        SchemaViolationError::ChildrenLengthMismatch {
+            schema_index: schema_cursor.descendant_index(),
+            input_index: input_cursor.descendant_index(),
+            expected: expected_cells.into(),
+            actual: actual_cells,
+        },
+    ))
+}
+
 /// Walk down to the first node, and debug assert that it is a table cell.
 fn get_cursor_at_first_cell<'a>(cursor: &TreeCursor<'a>) -> TreeCursor<'a> {
     let mut cursor = cursor.clone();
@@ -576,7 +704,11 @@ mod tests {
         assert!(is_table_cell_node(&schema_cursor.node()));
 
         assert_eq!(
-            get_cell_indexes_that_have_simple_matcher(&schema_cursor, schema_str),
+            get_cell_indexes_that_have_simple_matcher(
+                &schema_cursor,
+                schema_str,
+                &RegexLimits::default()
+            ),
             vec![
                 None,
                 Some(Matcher::try_from_pattern_and_suffix_str("`foo:/test/`", None).unwrap()),
@@ -917,7 +1049,7 @@ mod tests {
                 SchemaViolationError::NodeContentMismatch {
                     schema_index: 14,
                     input_index: 18,
-                    expected: "^xx".to_string(),
+                    expected: "xx".to_string(),
                     actual: "b2".to_string(),
                     kind: NodeContentMismatchKind::Matcher,
                 }
@@ -1011,6 +1143,139 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_table_vs_table_ragged_row() {
+        let schema_str = r#"
+|c1|c2|
+|-|-|
+|r1|r2|
+            "#;
+        let input_str = r#"
+|c1|c2|
+|-|-|
+|r1|r2|r3|
+"#;
+
+        let result = ValidatorTester::<TableVsTableValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_tables(s, i)))
+            .validate_complete();
+
+        assert_eq!(
+            result.errors(),
+            vec![ValidationError::SchemaViolation(
+                SchemaViolationError::ChildrenLengthMismatch {
+                    schema_index: 10,
+                    input_index: 10,
+                    expected: 2.into(),
+                    actual: 3,
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_validate_table_vs_table_with_repeated_cell_ragged_row() {
+        let schema_str = r#"
+|c1|c2|
+|-|-|
+|`a:/.*/`|`b:/.*/`|{,}
+            "#;
+        let input_str = r#"
+|c1|c2|
+|-|-|
+|a1|b1|
+|a2|
+"#;
+
+        let result = ValidatorTester::<TableVsTableValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_tables(s, i)))
+            .validate_complete();
+
+        assert!(matches!(
+            result.errors(),
+            [ValidationError::SchemaViolation(
+                SchemaViolationError::ChildrenLengthMismatch { actual: 1, .. }
+            )]
+        ));
+    }
+
+    #[test]
+    fn test_validate_table_vs_table_with_repeated_cell_too_few_rows() {
+        let schema_str = r#"
+|c1|c2|
+|-|-|
+|`a:/.*/`|`b:/.*/`|{2,}
+            "#;
+        let input_str = r#"
+|c1|c2|
+|-|-|
+|a1|b1|
+"#;
+
+        let result = ValidatorTester::<TableVsTableValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_tables(s, i)))
+            .validate_complete();
+
+        assert_eq!(
+            result.errors(),
+            vec![ValidationError::SchemaViolation(
+                SchemaViolationError::WrongRowCount {
+                    schema_index: 10,
+                    input_index: 10,
+                    min: Some(2),
+                    max: None,
+                    actual: 1,
+                }
+            )]
+        );
+
+        // While streaming more rows may still come
+        let result = ValidatorTester::<TableVsTableValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_tables(s, i)))
+            .validate_incomplete();
+
+        assert_eq!(result.errors(), vec![]);
+    }
+
+    #[test]
+    fn test_validate_table_vs_table_repeated_then_literal_mismatch() {
+        let schema_str = r#"
+|c1|c2|
+|-|-|
+|`a:/.*/`|`b:/.*/`|{,2}
+|lit1|lit2|
+            "#;
+        let input_str = r#"
+|c1|c2|
+|-|-|
+|a1|b1|
+|a2|b2|
+|bad|lit2|
+"#;
+
+        let result = ValidatorTester::<TableVsTableValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_tables(s, i)))
+            .validate_complete();
+
+        // The row right after the repeated rows is checked too
+        assert!(matches!(
+            result.errors(),
+            [ValidationError::SchemaViolation(
+                SchemaViolationError::NodeContentMismatch { expected, actual, .. }
+            )] if expected == "lit1" && actual == "bad"
+        ));
+    }
+
     #[test]
     fn test_validate_table_vs_table_literal_repeated_literal_sandwich_with_footer() {
         let schema_str = r#"
@@ -2,7 +2,8 @@
 //!
 //! Types:
 //! - `TextualVsTextualValidator`: compares text and inline code nodes, delegating
-//!   to matcher validation when schema content contains matcher syntax.
+//!   to matcher validation when schema content contains matcher syntax, also
+//!   inside emphasis.
 use tracing::instrument;
 use tree_sitter::TreeCursor;
 
@@ -10,7 +11,12 @@ use crate::compare_node_kinds_check;
 use crate::invariant_violation;
 use crate::mdschema::validation::walkers::helpers::compare_text_contents::compare_text_contents;
 use crate::mdschema::validation::walkers::validators::ValidatorImpl;
-use crate::mdschema::validation::walkers::validators::matchers::MatcherVsTextValidator;
+use crate::mdschema::validation::walkers::validators::matchers::{
+    MatcherVsTextValidator, MultipleMatchersVsTextValidator, is_text_and_code_spans_only,
+};
+use crate::mdschema::validation::matchers::matcher::Matcher;
+use crate::mdschema::validation::spans::LineStarts;
+use crate::mdschema::validation::validator::ValidateOptions;
 use crate::mdschema::validation::validator_walker::ValidatorWalker;
 use crate::mdschema::validation::{
     walkers::{ValidationResult, validators::Validator},
@@ -25,7 +31,12 @@ use crate::mdschema::validation::{
 /// 1. Check if the schema node is at a `code_span`, or the current node is a
 ///    text node and the next node is a `code_span`. If so, delegate to
 ///    `MatcherVsTextValidator::validate`.
-/// 2. Otherwise, check that the node kind and text contents are the same.
+/// 2. If the schema node is emphasis (or strong emphasis, or strikethrough)
+///    with a matcher in it, check that the input node is the same kind of
+///    emphasis and match its content, between the delimiters, against the
+///    schema's. Nested emphasis, like `` _*`x:/\w+/`*_ ``, is matched level by
+///    level.
+/// 3. Otherwise, check that the node kind and text contents are the same.
 #[derive(Default)]
 pub(super) struct TextualVsTextualValidator;
 
@@ -52,20 +63,121 @@ fn validate_textual_vs_textual_impl(walker: &ValidatorWalker, got_eof: bool) ->
         return MatcherVsTextValidator.validate(walker, got_eof);
     }
 
+    if is_emphasis_node(&walker.schema_cursor().node())
+        && has_matcher_inside(walker.schema_cursor(), walker.schema_str())
+    {
+        return validate_emphasis_with_matchers(walker, got_eof);
+    }
+
     validate_textual_vs_textual_direct(
         walker.schema_cursor(),
         walker.input_cursor(),
         walker.schema_str(),
         walker.input_str(),
         got_eof,
+        walker.options(),
+        walker.input_line_starts(),
     )
 }
 
+/// Validate emphasis in the schema that has a matcher in it against emphasis
+/// in the input.
+///
+/// The result stays at the emphasis nodes, so that the caller moves on to
+/// their next siblings.
+fn validate_emphasis_with_matchers(walker: &ValidatorWalker, got_eof: bool) -> ValidationResult {
+    let mut result = ValidationResult::from_cursors(walker.schema_cursor(), walker.input_cursor());
+
+    let mut schema_cursor = walker.schema_cursor().clone();
+    let mut input_cursor = walker.input_cursor().clone();
+    compare_node_kinds_check!(
+        schema_cursor,
+        input_cursor,
+        walker.schema_str(),
+        walker.input_str(),
+        result
+    );
+
+    let content_result = match (
+        emphasis_content_child(&mut schema_cursor),
+        emphasis_content_child(&mut input_cursor),
+    ) {
+        // Nested emphasis, like `_*x*_`, gets matched one level at a time
+        (Some(true), Some(_)) => TextualVsTextualValidator
+            .validate(&walker.with_cursors(&schema_cursor, &input_cursor), got_eof),
+        _ if is_text_and_code_spans_only(walker.schema_cursor()) => {
+            MultipleMatchersVsTextValidator.validate(walker, got_eof)
+        }
+        // Anything else is compared as is
+        _ => {
+            return validate_textual_vs_textual_direct(
+                walker.schema_cursor(),
+                walker.input_cursor(),
+                walker.schema_str(),
+                walker.input_str(),
+                got_eof,
+                walker.options(),
+                walker.input_line_starts(),
+            );
+        }
+    };
+
+    result.join_data(content_result.data());
+    result
+}
+
+/// Move the cursor to the only child of an emphasis node besides its
+/// delimiters.
+///
+/// Returns whether that child is emphasis itself, or `None` (leaving the
+/// cursor where it was) if there isn't exactly one such child.
+fn emphasis_content_child(cursor: &mut TreeCursor) -> Option<bool> {
+    let node = cursor.node();
+    let mut walk = node.walk();
+    let mut children = node
+        .children(&mut walk)
+        .enumerate()
+        .filter(|(_, child)| !is_emphasis_delimiter_node(child));
+    let (index, child) = children.next()?;
+    if children.next().is_some() {
+        return None;
+    }
+
+    cursor.goto_first_child();
+    for _ in 0..index {
+        cursor.goto_next_sibling();
+    }
+    Some(is_emphasis_node(&child))
+}
+
+/// Whether there is a matcher in a node, at any depth of emphasis.
+pub(super) fn has_matcher_inside(schema_cursor: &TreeCursor, schema_str: &str) -> bool {
+    let mut cursor = schema_cursor.clone();
+    if !cursor.goto_first_child() {
+        return false;
+    }
+
+    loop {
+        let node = cursor.node();
+        let found = if is_inline_code_node(&node) {
+            Matcher::try_from_schema_cursor(&cursor, schema_str).is_ok()
+        } else {
+            is_emphasis_node(&node) && has_matcher_inside(&cursor, schema_str)
+        };
+        if found {
+            return true;
+        }
+        if !cursor.goto_next_sibling() {
+            return false;
+        }
+    }
+}
+
 /// Validate two textual elements directly without checking for matchers.
 ///
 /// This performs the actual node kind and text content comparison without
 /// delegating to matcher validation.
-#[instrument(skip(schema_cursor, input_cursor, schema_str, input_str, got_eof), level = "debug", fields(
+#[instrument(skip(schema_cursor, input_cursor, schema_str, input_str, got_eof, options, input_line_starts), level = "debug", fields(
     s = %schema_cursor.descendant_index(),
     i = %input_cursor.descendant_index(),
 ), ret)]
@@ -76,6 +188,8 @@ pub(super) fn validate_textual_vs_textual_direct(
     schema_str: &str,
     input_str: &str,
     got_eof: bool,
+    options: &ValidateOptions,
+    input_line_starts: Option<&LineStarts>,
 ) -> ValidationResult {
     let mut result = ValidationResult::from_cursors(schema_cursor, input_cursor);
 
@@ -101,6 +215,8 @@ pub(super) fn validate_textual_vs_textual_direct(
         input_cursor,
         is_partial_match,
         false,
+        options,
+        input_line_starts,
     );
     result.join_other_result(&text_result);
     if text_result.has_errors() {
@@ -0,0 +1,511 @@
+//! Unordered list validator.
+//!
+//! Types:
+//! - `validate_unordered_list`: matches the items of an input list against
+//!   the items of a schema list marked with an `<!-- mds:unordered -->`
+//!   directive, in any order.
+//!
+//! For example, this schema
+//!
+//! ```md
+//! <!-- mds:unordered -->
+//! - apple
+//! - banana
+//! - `other:/\w+/`{0,2}
+//! ```
+//!
+//! accepts `banana`, `kiwi` and `apple` in that order, capturing `kiwi`.
+//! Every schema item is paired with an input item of its own that it
+//! matches, and a repeated matcher with as few and as many as its range
+//! allows. The pairs are found with augmenting paths (Kuhn's algorithm)
+//! rather than one item at a time, so an item that matches anything never
+//! takes the only input item that a pickier one matches. Input items left
+//! over are errors, unless the directive is `<!-- mds:unordered+ -->`.
+//! Captures are collected in the order of the input items.
+use std::sync::LazyLock;
+
+use log::trace;
+use regex::Regex;
+use serde_json::Value;
+use tree_sitter::{Node, TreeCursor};
+
+use crate::mdschema::validation::errors::{SchemaError, SchemaViolationError, ValidationError};
+use crate::mdschema::validation::matchers::matcher::Matcher;
+use crate::mdschema::validation::ts_types::*;
+use crate::mdschema::validation::ts_utils::{get_node_text, is_directive_comment};
+use crate::mdschema::validation::validator_walker::ValidatorWalker;
+use crate::mdschema::validation::walkers::ValidationResult;
+use crate::mdschema::validation::walkers::validators::Validator;
+use crate::mdschema::validation::walkers::validators::lists::{
+    ListVsListValidator, ensure_at_first_list_item, extract_repeated_matcher_from_list_item,
+    legacy_list_values, nested_list_cursor, structured_list_values,
+    validate_list_item_contents_vs_list_item_contents,
+};
+
+/// A directive marking the list right after it as unordered, with a `+` if
+/// input items left over are allowed.
+static UNORDERED_DIRECTIVE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^<!--\s*mds:unordered(?P<extra>\+)?\s*-->$").unwrap());
+
+/// What the directive of a schema list marked unordered asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Unordered {
+    /// Whether input items that no schema item is left for are allowed.
+    pub allow_extra_items: bool,
+}
+
+/// A place for an input item in the pairing. Every schema item has one, and
+/// a repeated matcher one for every input item it may take.
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    /// The index of the schema item.
+    item: usize,
+    /// Whether the schema item needs an input item here, rather than only
+    /// allowing one.
+    required: bool,
+}
+
+/// Whether the schema list at a cursor, or whose first item the cursor is
+/// at, comes right after an `<!-- mds:unordered -->` directive.
+pub(super) fn unordered_directive(
+    schema_cursor: &TreeCursor,
+    schema_str: &str,
+) -> Option<Unordered> {
+    let node = schema_cursor.node();
+    let list = if is_list_node(&node) {
+        node
+    } else if is_list_item_node(&node) && node.prev_named_sibling().is_none() {
+        node.parent()?
+    } else {
+        return None;
+    };
+
+    let directive = list.prev_named_sibling()?;
+    if !is_directive_comment(&directive, schema_str) {
+        return None;
+    }
+    let captures = UNORDERED_DIRECTIVE.captures(get_node_text(&directive, schema_str).trim())?;
+    Some(Unordered {
+        allow_extra_items: captures.name("extra").is_some(),
+    })
+}
+
+/// Validate the items of an input list against the items of a schema list
+/// marked unordered, in any order.
+///
+/// Missing and left over items are only reported once we have the whole
+/// input, since the items that would pair with them may still be coming.
+///
+/// # Arguments
+///
+/// * `walker`: The walker, with its cursors at the lists or at their first items.
+/// * `unordered`: What the schema list's directive asks for.
+/// * `got_eof`: Whether we have received the full input document.
+pub(super) fn validate_unordered_list(
+    walker: &ValidatorWalker,
+    unordered: Unordered,
+    got_eof: bool,
+) -> ValidationResult {
+    let mut result = ValidationResult::from_cursors(walker.schema_cursor(), walker.input_cursor());
+
+    let schema_items = list_items(walker.schema_cursor());
+    let input_items = list_items(walker.input_cursor());
+
+    // Every required slot comes before the optional ones, so that they are
+    // filled first
+    let mut slots = Vec::new();
+    let mut optional_slots = Vec::new();
+    let mut matchers: Vec<Option<Matcher>> = Vec::with_capacity(schema_items.len());
+    for (item, schema_item) in schema_items.iter().enumerate() {
+        let (required, optional, matcher) = if let Some(condition) = walker.condition(schema_item) {
+            // Only an item whose condition is met has to be there
+            let is_met = walker.is_condition_met(schema_item, condition, got_eof) == Some(true);
+            (usize::from(is_met), 0, None)
+        } else {
+            match extract_repeated_matcher_from_list_item(
+                schema_item,
+                walker.schema_str(),
+                walker.matcher_cache(),
+                &walker.options().regex_limits,
+            ) {
+                Some(Ok(matcher)) if matcher.is_optional() => (0, 1, None),
+                Some(Ok(matcher)) => {
+                    let extras = matcher.extras();
+                    let min_items = extras.min_items_or(0);
+                    let max_items = extras.max_items().unwrap_or(input_items.len());
+                    (
+                        min_items,
+                        max_items.saturating_sub(min_items),
+                        Some(matcher),
+                    )
+                }
+                Some(Err(error)) => {
+                    result.add_error(ValidationError::SchemaError(SchemaError::MatcherError {
+                        error,
+                        schema_index: schema_item.descendant_index(),
+                    }));
+                    return result;
+                }
+                None => (1, 0, None),
+            }
+        };
+
+        // More slots than input items can't all be filled anyway
+        slots.extend((0..required.min(input_items.len() + 1)).map(|_| Slot {
+            item,
+            required: true,
+        }));
+        optional_slots.extend((0..optional.min(input_items.len())).map(|_| Slot {
+            item,
+            required: false,
+        }));
+        matchers.push(matcher);
+    }
+    slots.extend(optional_slots);
+
+    let pairs: Vec<Vec<ValidationResult>> = schema_items
+        .iter()
+        .map(|schema_item| {
+            input_items
+                .iter()
+                .map(|input_item| validate_item_pair(walker, schema_item, input_item, got_eof))
+                .collect()
+        })
+        .collect();
+    let compatible = |slot: usize, input: usize| !pairs[slots[slot].item][input].has_errors();
+
+    let mut slot_of_input = vec![None; input_items.len()];
+    for slot in 0..slots.len() {
+        let mut visited = vec![false; input_items.len()];
+        augment(slot, &compatible, &mut slot_of_input, &mut visited);
+    }
+    trace!("Paired unordered list items with slots {:?}", slot_of_input);
+
+    if got_eof {
+        let mut filled = vec![false; slots.len()];
+        for slot in slot_of_input.iter().flatten() {
+            filled[*slot] = true;
+        }
+        let mut missing: Vec<usize> = slots
+            .iter()
+            .zip(&filled)
+            .filter(|(slot, filled)| slot.required && !**filled)
+            .map(|(slot, _)| slot.item)
+            .collect();
+        // The required slots of an item are next to each other
+        missing.dedup();
+        let unexpected: Vec<usize> = (0..input_items.len())
+            .filter(|input| !unordered.allow_extra_items && slot_of_input[*input].is_none())
+            .collect();
+
+        if !missing.is_empty() || !unexpected.is_empty() {
+            result.add_error(ValidationError::SchemaViolation(
+                SchemaViolationError::UnorderedItemsUnmatched {
+                    schema_index: list_cursor(walker.schema_cursor()).descendant_index(),
+                    input_index: list_cursor(walker.input_cursor()).descendant_index(),
+                    missing: missing
+                        .iter()
+                        .map(|item| item_text(&schema_items[*item].node(), walker.schema_str()))
+                        .collect(),
+                    unexpected: unexpected
+                        .iter()
+                        .map(|input| item_text(&input_items[*input].node(), walker.input_str()))
+                        .collect(),
+                },
+            ));
+            return result;
+        }
+    }
+
+    // What each repeated matcher captured, in the order of the input
+    let mut repeated_values: Vec<Vec<Value>> = vec![Vec::new(); schema_items.len()];
+    for (input, slot) in slot_of_input.iter().enumerate() {
+        if let Some(slot) = slot {
+            let item = slots[*slot].item;
+            repeated_values[item].push(pairs[item][input].value().clone());
+        }
+    }
+
+    for (input, slot) in slot_of_input.iter().enumerate() {
+        let Some(slot) = slot else {
+            continue;
+        };
+        let item = slots[*slot].item;
+        let pair = &pairs[item][input];
+        let Some(matcher) = &matchers[item] else {
+            result.join_other_result(pair);
+            continue;
+        };
+
+        // A repeated matcher's array goes where its first input item is
+        result.keep_farther_pos(pair.farthest_reached_pos());
+        let values = std::mem::take(&mut repeated_values[item]);
+        if let Some(matcher_id) = matcher.id()
+            && !values.is_empty()
+        {
+            let values = if walker.options().structured_matches {
+                structured_list_values(matcher_id, &values, None)
+            } else {
+                legacy_list_values(matcher_id, &values)
+            };
+            result.set_match(matcher_id, Value::Array(values));
+        }
+    }
+
+    result
+}
+
+/// Validate an input list item against a schema list item, along with the
+/// lists nested in them if they both have one.
+fn validate_item_pair<'a>(
+    walker: &ValidatorWalker<'a>,
+    schema_item_cursor: &TreeCursor<'a>,
+    input_item_cursor: &TreeCursor<'a>,
+    got_eof: bool,
+) -> ValidationResult {
+    let (mut result, early_return) = validate_list_item_contents_vs_list_item_contents(
+        walker,
+        schema_item_cursor,
+        input_item_cursor,
+        got_eof,
+    );
+    if early_return || result.has_errors() {
+        return result;
+    }
+
+    if let (Some(schema_list_cursor), Some(input_list_cursor)) = (
+        nested_list_cursor(schema_item_cursor, |_| false),
+        nested_list_cursor(input_item_cursor, |node| walker.skips_input_node(node)),
+    ) {
+        result.join_other_result(&ListVsListValidator.validate(
+            &walker.with_cursors(&schema_list_cursor, &input_list_cursor),
+            got_eof,
+        ));
+    }
+    result
+}
+
+/// Try to give a slot an input item of its own, moving the input items of
+/// other slots along a path of compatible pairs if need be. Slots that have
+/// an input item keep one.
+///
+/// # Arguments
+///
+/// * `slot`: The slot to give an input item.
+/// * `compatible`: Whether a slot can take an input item.
+/// * `slot_of_input`: The slot each input item is given to, if any.
+/// * `visited`: The input items this search has already tried to move.
+fn augment(
+    slot: usize,
+    compatible: &impl Fn(usize, usize) -> bool,
+    slot_of_input: &mut [Option<usize>],
+    visited: &mut [bool],
+) -> bool {
+    // Most of the time there is an input item that nothing has taken yet
+    if let Some(input) = (0..slot_of_input.len())
+        .find(|input| slot_of_input[*input].is_none() && compatible(slot, *input))
+    {
+        slot_of_input[input] = Some(slot);
+        return true;
+    }
+
+    for input in 0..slot_of_input.len() {
+        if visited[input] || !compatible(slot, input) {
+            continue;
+        }
+        visited[input] = true;
+        let other = slot_of_input[input];
+        if other.is_some_and(|other| augment(other, compatible, slot_of_input, visited)) {
+            slot_of_input[input] = Some(slot);
+            return true;
+        }
+    }
+    false
+}
+
+/// Cursors at the items of the list a cursor is at, or at the first item of.
+fn list_items<'a>(cursor: &TreeCursor<'a>) -> Vec<TreeCursor<'a>> {
+    let mut cursor = cursor.clone();
+    if ensure_at_first_list_item(&mut cursor).is_err() {
+        return Vec::new();
+    }
+
+    let mut items = vec![cursor.clone()];
+    while cursor.goto_next_sibling() {
+        if is_list_item_node(&cursor.node()) {
+            items.push(cursor.clone());
+        }
+    }
+    items
+}
+
+/// A cursor at the list a cursor is at, or at the first item of.
+fn list_cursor<'a>(cursor: &TreeCursor<'a>) -> TreeCursor<'a> {
+    let mut cursor = cursor.clone();
+    if is_list_item_node(&cursor.node()) {
+        cursor.goto_parent();
+    }
+    cursor
+}
+
+/// The first line of a list item after its marker, to name it in errors.
+fn item_text(item: &Node, src: &str) -> String {
+    let start = item
+        .child(0)
+        .filter(|child| is_list_marker_node(child))
+        .map_or(item.start_byte(), |marker| marker.end_byte());
+    src[start..item.end_byte()]
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::super::lists::ListVsListValidator;
+    use super::super::test_utils::ValidatorTester;
+    use crate::mdschema::validation::errors::{SchemaViolationError, ValidationError};
+    use crate::mdschema::validation::ts_types::*;
+    use crate::mdschema::validation::validator::{Validator, ValidatorState};
+    use crate::mdschema::validation::walkers::ValidationResult;
+
+    fn validate_unordered(schema_str: &str, input_str: &str, got_eof: bool) -> ValidationResult {
+        ValidatorTester::<ListVsListValidator>::from_strs(schema_str, input_str)
+            .walk()
+            .goto_first_child_then_unwrap()
+            .goto_next_sibling_for_schema_unwrap()
+            .peek_nodes(|(s, i)| assert!(both_are_list_nodes(s, i)))
+            .validate(got_eof)
+    }
+
+    fn unmatched(result: &ValidationResult) -> (Vec<String>, Vec<String>) {
+        match result.errors() {
+            [
+                ValidationError::SchemaViolation(SchemaViolationError::UnorderedItemsUnmatched {
+                    missing,
+                    unexpected,
+                    ..
+                }),
+            ] => (missing.clone(), unexpected.clone()),
+            errors => panic!("Expected one unordered items error, got {:?}", errors),
+        }
+    }
+
+    #[test]
+    fn test_unordered_items_in_any_order() {
+        let schema = "<!-- mds:unordered -->\n- apple\n- banana\n- cherry\n";
+
+        for input in [
+            "- apple\n- banana\n- cherry\n",
+            "- cherry\n- apple\n- banana\n",
+            "- banana\n- cherry\n- apple\n",
+        ] {
+            let result = validate_unordered(schema, input, true);
+            assert!(
+                result.errors().is_empty(),
+                "{}: {:?}",
+                input,
+                result.errors()
+            );
+            assert_eq!(*result.value(), json!({}));
+        }
+    }
+
+    #[test]
+    fn test_unordered_items_missing_and_unexpected() {
+        let schema = "<!-- mds:unordered -->\n- apple\n- banana\n- cherry\n";
+        let input = "- banana\n- kiwi\n- apple\n";
+
+        let result = validate_unordered(schema, input, true);
+        assert_eq!(
+            unmatched(&result),
+            (vec!["cherry".to_string()], vec!["kiwi".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_unordered_items_with_plus_allow_extra_items() {
+        let schema = "<!-- mds:unordered+ -->\n- apple\n- banana\n";
+
+        let result = validate_unordered(schema, "- kiwi\n- banana\n- apple\n", true);
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+
+        let result = validate_unordered(schema, "- kiwi\n- banana\n", true);
+        assert_eq!(unmatched(&result), (vec!["apple".to_string()], vec![]));
+    }
+
+    #[test]
+    fn test_unordered_duplicate_items_need_an_input_item_each() {
+        let result = validate_unordered("<!-- mds:unordered -->\n- a\n- a\n", "- a\n- b\n", true);
+        assert_eq!(
+            unmatched(&result),
+            (vec!["a".to_string()], vec!["b".to_string()])
+        );
+
+        let result = validate_unordered("<!-- mds:unordered -->\n- a\n- b\n", "- a\n- a\n", true);
+        assert_eq!(
+            unmatched(&result),
+            (vec!["b".to_string()], vec!["a".to_string()])
+        );
+
+        let result = validate_unordered("<!-- mds:unordered -->\n- a\n- a\n", "- a\n- a\n", true);
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+    }
+
+    #[test]
+    fn test_unordered_literals_and_matchers_capture_in_input_order() {
+        let schema = "<!-- mds:unordered -->\n- apple\n- `fruit:/\\w+/`{1,}\n- `size:/\\d+ kg/`\n";
+        let input = "- 3 kg\n- kiwi\n- apple\n- pear\n";
+
+        let result = validate_unordered(schema, input, true);
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+        assert_eq!(
+            *result.value(),
+            json!({"size": "3 kg", "fruit": ["kiwi", "pear"]})
+        );
+    }
+
+    #[test]
+    fn test_unordered_pickier_item_gets_the_only_input_item_it_matches() {
+        let schema = "<!-- mds:unordered -->\n- `any:/\\w+/`\n- apple\n";
+        let input = "- apple\n- kiwi\n";
+
+        let result = validate_unordered(schema, input, true);
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+        assert_eq!(*result.value(), json!({"any": "kiwi"}));
+    }
+
+    #[test]
+    fn test_unordered_items_may_still_come() {
+        let schema = "<!-- mds:unordered -->\n- apple\n- banana\n";
+
+        let result = validate_unordered(schema, "- banana\n", false);
+        assert!(result.errors().is_empty(), "{:?}", result.errors());
+
+        let result = validate_unordered(schema, "- banana\n", true);
+        assert_eq!(unmatched(&result), (vec!["apple".to_string()], vec![]));
+    }
+
+    #[test]
+    fn test_unordered_list_in_document() {
+        let schema = "# Fruits\n\n<!-- mds:unordered -->\n- apple\n- banana\n";
+
+        let mut validator =
+            Validator::new_complete(schema, "# Fruits\n\n- banana\n- apple\n").unwrap();
+        validator.validate();
+        assert_eq!(validator.errors_so_far().count(), 0);
+
+        let mut validator =
+            Validator::new_complete(schema, "# Fruits\n\n- banana\n- kiwi\n").unwrap();
+        validator.validate();
+        let codes: Vec<_> = validator
+            .errors_so_far()
+            .map(ValidationError::code)
+            .collect();
+        assert_eq!(codes, vec!["MDV034"]);
+    }
+}
@@ -1,6 +1,9 @@
 use std::io;
 use std::{fs::File, io::BufReader, path::PathBuf};
 
+/// What `PathOrStdio::filepath` calls stdin and stdout.
+pub const STDIO_FILEPATH: &str = "stdio";
+
 pub enum PathOrStdio {
     Path(PathBuf),
     Stdio,
@@ -20,7 +23,7 @@ impl PathOrStdio {
     pub fn filepath(&self) -> &str {
         match self {
             PathOrStdio::Path(p) => p.to_str().unwrap_or("input"),
-            PathOrStdio::Stdio => "stdio",
+            PathOrStdio::Stdio => STDIO_FILEPATH,
         }
     }
 
@@ -0,0 +1,21 @@
+//! The public API of `mdvalidate`, in one place.
+//!
+//! ```no_run
+//! use mdvalidate::prelude::*;
+//!
+//! let validation = validate_str("# `title:/\\w+/`\n", "# Hello\n").unwrap();
+//! assert!(validation.is_valid);
+//! ```
+//!
+//! Everything here stays where it is within a major version, and the public
+//! enums are `#[non_exhaustive]`, so new errors and options can be added in
+//! minor releases. The modules under `mdschema` have more to offer, like
+//! linting and generating schemas, but may move around between releases.
+pub use crate::mdschema::validation::errors::ValidationError;
+pub use crate::mdschema::validation::json_report::ValidatorReport;
+pub use crate::mdschema::validation::matchers::matcher::Matcher;
+pub use crate::mdschema::validation::validate::{
+    ReadInputError, Validation, validate_reader, validate_reader_with_progress, validate_str,
+    validate_str_with_options,
+};
+pub use crate::mdschema::validation::validator::{ValidateOptions, Validator, ValidatorState};
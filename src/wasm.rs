@@ -0,0 +1,186 @@
+//! A JavaScript interface to validation, for running mdvalidate in the
+//! browser, like to point out mistakes in a document while it's written.
+//!
+//! Only built with the `wasm` feature, for `wasm32-unknown-unknown` (see
+//! `build.rs` for what compiling the grammar for it needs):
+//!
+//! ```sh
+//! cargo rustc --lib --release --target wasm32-unknown-unknown \
+//!     --no-default-features --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg \
+//!     target/wasm32-unknown-unknown/release/mdvalidate.wasm
+//! ```
+//!
+//! The library is only a `cdylib` when built this way, so that other builds
+//! of the crate don't link one they don't need.
+//!
+//! `validate` validates a whole input at once, and a `WasmValidator` is given
+//! the input a chunk at a time, as it streams in. Both return the outcome as a
+//! plain object:
+//!
+//! ```json
+//! { "valid": false, "errors": [...], "matches": { "title": "Hello" } }
+//! ```
+//!
+//! `errors` are like the ones `mdv --error-format json` prints, with their
+//! locations in the input and schema (see `json_report`).
+use serde::Serialize;
+use serde_json::{Value, json};
+use wasm_bindgen::prelude::*;
+
+use crate::mdschema::validation::errors::ValidationError;
+use crate::mdschema::validation::json_report::ValidatorReport;
+use crate::mdschema::validation::validate::{strip_bom, validate_str};
+use crate::mdschema::validation::validator::Validator;
+
+/// Validate an input against a schema.
+///
+/// Anything that stops the input from being validated, like a schema that
+/// can't be parsed, is reported as an error of the outcome.
+#[wasm_bindgen]
+pub fn validate(schema: &str, input: &str) -> JsValue {
+    let outcome = match validate_str(schema, input) {
+        Ok(validation) => validator_outcome(&validation.validator),
+        Err(error) => failed_outcome(&error),
+    };
+    to_js(&outcome)
+}
+
+/// A validator that is given its input a chunk at a time, mapping onto an
+/// incremental `Validator`.
+#[wasm_bindgen]
+pub struct WasmValidator {
+    validator: Validator,
+    /// Whether we have been given any input, so we know to strip a byte
+    /// order mark from the first chunk.
+    got_input: bool,
+    /// Why we stopped validating, if a chunk couldn't be added.
+    error: Option<ValidationError>,
+}
+
+#[wasm_bindgen]
+impl WasmValidator {
+    /// A validator for a schema, without any input yet. Throws if the schema
+    /// can't be parsed.
+    #[wasm_bindgen(constructor)]
+    pub fn new(schema: &str) -> Result<WasmValidator, JsError> {
+        let validator = Validator::new_incomplete(schema, "")
+            .ok_or_else(|| JsError::new(&ValidationError::ValidatorCreationFailed.to_string()))?;
+        Ok(WasmValidator {
+            validator,
+            got_input: false,
+            error: None,
+        })
+    }
+
+    /// Add the next chunk of the input, and validate it.
+    ///
+    /// The outcome is of the input so far, so it may still have errors that
+    /// the rest of the input makes go away, like a list with too few items.
+    pub fn push(&mut self, chunk: &str) -> JsValue {
+        self.append(chunk, false)
+    }
+
+    /// Validate the whole input, once all of it has been pushed.
+    pub fn finish(&mut self) -> JsValue {
+        self.append("", true)
+    }
+}
+
+impl WasmValidator {
+    fn append(&mut self, chunk: &str, got_eof: bool) -> JsValue {
+        if self.error.is_none() {
+            let chunk = if self.got_input {
+                chunk
+            } else {
+                strip_bom(chunk)
+            };
+            self.got_input |= !chunk.is_empty();
+
+            let appended = if got_eof {
+                self.validator.append_final_input(chunk)
+            } else {
+                self.validator.append_more_input(chunk)
+            };
+            match appended {
+                Ok(()) => self.validator.validate(),
+                Err(error) => self.error = Some(error),
+            }
+        }
+
+        let outcome = match &self.error {
+            Some(error) => failed_outcome(error),
+            None => validator_outcome(&self.validator),
+        };
+        to_js(&outcome)
+    }
+}
+
+/// The outcome of validating with a validator, so far.
+fn validator_outcome(validator: &Validator) -> Value {
+    let mut report = ValidatorReport::new(validator).to_json();
+    json!({
+        "valid": validator.errors_so_far().count() == 0,
+        "errors": report["errors"].take(),
+        "matches": validator.matches_so_far(),
+    })
+}
+
+/// The outcome for input we couldn't validate at all.
+fn failed_outcome(error: &ValidationError) -> Value {
+    json!({
+        "valid": false,
+        "errors": [{
+            "code": error.code(),
+            "kind": error.kind(),
+            "severity": "error",
+            "suppressed": false,
+            "message": error.to_string(),
+        }],
+        "matches": {},
+    })
+}
+
+fn to_js(value: &Value) -> JsValue {
+    // Objects become plain objects rather than `Map`s
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .expect("JSON can always be converted to JavaScript")
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn from_js(value: JsValue) -> Value {
+        serde_wasm_bindgen::from_value(value).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_validate() {
+        let outcome = from_js(validate("# `title:/\\w+/`\n", "# Hello\n"));
+        assert_eq!(outcome["valid"], json!(true));
+        assert_eq!(outcome["errors"], json!([]));
+        assert_eq!(outcome["matches"], json!({"title": "Hello"}));
+
+        let outcome = from_js(validate("# Hi\n", "# Bye\n"));
+        assert_eq!(outcome["valid"], json!(false));
+        assert_eq!(outcome["errors"][0]["code"], json!("MDV005"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_validator_streams_input() {
+        let mut validator = WasmValidator::new("# `title:/\\w+/`\n\nSome text\n").unwrap();
+
+        validator.push("# Hel");
+        let outcome = from_js(validator.push("lo\n\nSome "));
+        assert_eq!(outcome["matches"], json!({"title": "Hello"}));
+
+        validator.push("text\n");
+        let outcome = from_js(validator.finish());
+        assert_eq!(outcome["valid"], json!(true), "{}", outcome["errors"]);
+        assert_eq!(outcome["matches"], json!({"title": "Hello"}));
+    }
+}
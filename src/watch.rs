@@ -0,0 +1,133 @@
+//! Re-running validation whenever the schema or input changes on disk.
+//!
+//! We watch the directories the files are in rather than the files
+//! themselves, since many editors save by writing a new file and renaming it
+//! over the old one, which would end a watch on the old file.
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use colored::Colorize;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+/// How long to wait for more changes before re-running. Saving a file often
+/// fires several events in a row, and we only want to validate once.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// The escape codes to clear the terminal and move the cursor to the top.
+const CLEAR_SCREEN: &str = "\x1B[2J\x1B[1;1H";
+
+/// Run `run` now and again every time one of the files changes, until
+/// Ctrl-C is pressed, which exits with 0.
+///
+/// # Arguments
+///
+/// * `files`: The files to watch. They must exist.
+/// * `run`: Does one validation, printing its report.
+pub fn watch_files(files: &[PathBuf], mut run: impl FnMut()) -> notify::Result<()> {
+    ctrlc::set_handler(|| std::process::exit(0))
+        .map_err(|e| notify::Error::generic(&e.to_string()))?;
+
+    let files = files
+        .iter()
+        .map(|file| file.canonicalize())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    for directory in files.iter().filter_map(|file| file.parent()) {
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+    }
+
+    rerun(&files, &mut run);
+    loop {
+        match receiver.recv() {
+            Ok(Ok(event)) if touches_any(&event, &files) => {}
+            Ok(Ok(_)) => continue,
+            Ok(Err(e)) => {
+                eprintln!("{}", format!("Watch error: {}", e).red());
+                continue;
+            }
+            // The watcher is gone, so there will be no more events
+            Err(_) => return Ok(()),
+        }
+
+        // Swallow the rest of a burst of events
+        while receiver.recv_timeout(DEBOUNCE).is_ok() {}
+
+        rerun(&files, &mut run);
+    }
+}
+
+/// Clear the screen, print when and what we're validating, and validate.
+fn rerun(files: &[PathBuf], run: &mut impl FnMut()) {
+    print!("{}", CLEAR_SCREEN);
+    let names = files
+        .iter()
+        .map(|file| file.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!(
+        "{}",
+        format!("[{}] Watching {}", utc_timestamp(SystemTime::now()), names).dimmed()
+    );
+
+    run();
+}
+
+/// Whether an event changed one of the files.
+fn touches_any(event: &Event, files: &[PathBuf]) -> bool {
+    !matches!(event.kind, EventKind::Access(_))
+        && event
+            .paths
+            .iter()
+            .any(|path| files.iter().any(|file| file == path || same_file(file, path)))
+}
+
+/// Whether two paths point at the same file, for event paths that aren't in
+/// the canonical form of the paths we watch.
+fn same_file(file: &Path, path: &Path) -> bool {
+    path.canonicalize().is_ok_and(|path| path == file)
+}
+
+/// A time as `HH:MM:SS UTC`.
+fn utc_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+        % (24 * 60 * 60);
+
+    format!(
+        "{:02}:{:02}:{:02} UTC",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utc_timestamp() {
+        let time = UNIX_EPOCH + Duration::from_secs(3 * 24 * 60 * 60 + 13 * 3600 + 5 * 60 + 9);
+        assert_eq!(utc_timestamp(time), "13:05:09 UTC");
+    }
+
+    #[test]
+    fn test_touches_any() {
+        let file = PathBuf::from("/tmp/schema.mds");
+        let modify = Event::new(EventKind::Modify(notify::event::ModifyKind::Any));
+
+        assert!(touches_any(&modify.clone().add_path(file.clone()), &[file.clone()]));
+        assert!(!touches_any(
+            &modify.add_path(PathBuf::from("/tmp/other.md")),
+            &[file.clone()]
+        ));
+
+        let access =
+            Event::new(EventKind::Access(notify::event::AccessKind::Any)).add_path(file.clone());
+        assert!(!touches_any(&access, &[file]));
+    }
+}
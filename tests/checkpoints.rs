@@ -0,0 +1,127 @@
+//! A validator that is checkpointed and resumed part way through streamed
+//! input, like when a session moves between workers, must end up with the
+//! same errors and matches as one that validated the input in one go.
+
+use mdvalidate::mdschema::validation::checkpoint::ValidatorCheckpoint;
+use mdvalidate::mdschema::validation::errors::ValidationError;
+use mdvalidate::mdschema::validation::validator::{ValidateOptions, Validator, ValidatorState};
+use serde_json::Value;
+
+const SCHEMA: &str = include_str!("fixtures/checkpoint/schema.md");
+const INPUT: &str = include_str!("fixtures/checkpoint/input.md");
+
+/// Points to split the input at, pseudo-randomly but the same on every run.
+fn random_splits(seed: u64, len: usize) -> Vec<usize> {
+    let mut state = seed;
+    let mut splits: Vec<usize> = (0..8)
+        .map(|_| {
+            // A linear congruential generator, with the constants of MMIX
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as usize % len
+        })
+        .collect();
+    splits.sort_unstable();
+    splits.dedup();
+    splits
+}
+
+fn outcome(validator: &Validator) -> (Vec<ValidationError>, Value) {
+    (
+        validator.errors_so_far().cloned().collect(),
+        validator.matches_so_far().clone(),
+    )
+}
+
+fn validate_in_one_go(options: &ValidateOptions) -> (Vec<ValidationError>, Value) {
+    let mut validator = Validator::new_complete(SCHEMA, INPUT)
+        .expect("Failed to create validator")
+        .with_options(options.clone());
+    validator.validate();
+    outcome(&validator)
+}
+
+/// Stream the input in chunks that end at the splits into two validators,
+/// one of which is checkpointed, serialized and resumed after every chunk,
+/// checking that they agree along the way.
+fn validate_with_checkpoints(
+    splits: &[usize],
+    options: &ValidateOptions,
+) -> (Vec<ValidationError>, Value) {
+    let new_validator = || {
+        Validator::new_incomplete(SCHEMA, "")
+            .expect("Failed to create validator")
+            .with_options(options.clone())
+    };
+    let mut uninterrupted = new_validator();
+    let mut resumed = new_validator();
+
+    let mut start = 0;
+    for end in splits.iter().copied().chain([INPUT.len()]) {
+        let chunk = &INPUT[start..end];
+        let got_eof = end == INPUT.len();
+        for validator in [&mut uninterrupted, &mut resumed] {
+            if got_eof {
+                validator.append_final_input(chunk).unwrap();
+            } else {
+                validator.append_more_input(chunk).unwrap();
+            }
+            validator.validate();
+        }
+
+        let saved = serde_json::to_string(&resumed.checkpoint()).unwrap();
+        let checkpoint: ValidatorCheckpoint = serde_json::from_str(&saved).unwrap();
+        resumed = Validator::resume(SCHEMA, &checkpoint)
+            .expect("Failed to resume validator")
+            .with_options(options.clone());
+
+        assert_eq!(
+            resumed.farthest_reached_pos(),
+            uninterrupted.farthest_reached_pos(),
+            "after byte {} of {:?}",
+            end,
+            splits
+        );
+        assert_eq!(
+            outcome(&resumed),
+            outcome(&uninterrupted),
+            "after byte {} of {:?}",
+            end,
+            splits
+        );
+        start = end;
+    }
+
+    outcome(&resumed)
+}
+
+fn check_random_splits(options: &ValidateOptions) {
+    assert!(INPUT.is_ascii(), "Splits could land inside a character");
+
+    let (expected_errors, expected_matches) = validate_in_one_go(options);
+    assert!(
+        !expected_errors.is_empty(),
+        "The fixture should have errors"
+    );
+
+    for seed in 0..32 {
+        let splits = random_splits(seed, INPUT.len());
+        let (errors, matches) = validate_with_checkpoints(&splits, options);
+        assert_eq!(errors, expected_errors, "splits {:?}", splits);
+        assert_eq!(matches, expected_matches, "splits {:?}", splits);
+    }
+}
+
+#[test]
+fn resumed_validators_match_validating_in_one_go() {
+    check_random_splits(&ValidateOptions::default());
+}
+
+#[test]
+fn resumed_validators_keep_dropped_input() {
+    check_random_splits(&ValidateOptions {
+        bounded_memory: true,
+        ..Default::default()
+    });
+}
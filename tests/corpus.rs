@@ -0,0 +1,146 @@
+//! Real-world schema and input pairs, one folder per case under
+//! `tests/corpus/`.
+//!
+//! Each case has a `schema.mds`, an `input.md`, and the `expected_matches.json`
+//! and `expected_errors.json` (`[]` for none) that validating the whole input
+//! should give. To add a case, make its folder with the schema and input, and
+//! run
+//!
+//! ```sh
+//! MDVALIDATE_UPDATE_CORPUS=1 cargo test --test corpus
+//! ```
+//!
+//! to write what it gives now into the expected files, then check that they
+//! are right. Do the same after a change that means to change what cases give.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use mdvalidate::mdschema::validation::validator::{Validator, ValidatorState};
+use mdvalidate_utils::PrettyPrint;
+use serde_json::Value;
+
+const CORPUS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus");
+const UPDATE_ENV_VAR: &str = "MDVALIDATE_UPDATE_CORPUS";
+
+const SCHEMA_FILE: &str = "schema.mds";
+const INPUT_FILE: &str = "input.md";
+const EXPECTED_MATCHES_FILE: &str = "expected_matches.json";
+const EXPECTED_ERRORS_FILE: &str = "expected_errors.json";
+
+/// The folders of the cases, in order of name.
+fn case_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(CORPUS_DIR)
+        .expect("Failed to read the corpus")
+        .map(|entry| entry.expect("Failed to read the corpus").path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn read(dir: &Path, file: &str) -> String {
+    fs::read_to_string(dir.join(file))
+        .unwrap_or_else(|error| panic!("Failed to read {}: {}", dir.join(file).display(), error))
+}
+
+fn read_json(dir: &Path, file: &str) -> Value {
+    serde_json::from_str(&read(dir, file))
+        .unwrap_or_else(|error| panic!("Invalid JSON in {}: {}", dir.join(file).display(), error))
+}
+
+fn write_json(dir: &Path, file: &str, value: &Value) {
+    let json = serde_json::to_string_pretty(value).expect("Failed to serialize");
+    fs::write(dir.join(file), json + "\n")
+        .unwrap_or_else(|error| panic!("Failed to write {}: {}", dir.join(file).display(), error));
+}
+
+/// Validate the input of a case, giving the matches and the errors as JSON.
+fn run_case(validator: &mut Validator) -> (Value, Value) {
+    validator.validate();
+
+    let errors: Vec<_> = validator.errors_so_far().cloned().collect();
+    (
+        validator.matches_so_far().clone(),
+        serde_json::to_value(errors).expect("Failed to serialize the errors"),
+    )
+}
+
+/// What is wrong with a case, with the trees of its schema and input.
+fn mismatch_report(
+    name: &str,
+    validator: &Validator,
+    mismatches: &[(&str, &Value, &Value)],
+) -> String {
+    let mut report = format!("Case '{}':\n", name);
+    for (file, expected, actual) in mismatches {
+        report += &format!(
+            "  {} doesn't match\n  Expected:\n{}\n  Actual:\n{}\n",
+            file,
+            serde_json::to_string_pretty(expected).unwrap(),
+            serde_json::to_string_pretty(actual).unwrap(),
+        );
+    }
+    report += &format!(
+        "  Schema tree:\n{}  Input tree:\n{}",
+        validator
+            .schema_tree()
+            .root_node()
+            .get_pretty_printer()
+            .show_text()
+            .print(validator.schema_str()),
+        validator
+            .input_tree()
+            .root_node()
+            .get_pretty_printer()
+            .show_text()
+            .print(validator.last_input_str()),
+    );
+    report
+}
+
+#[test]
+fn corpus_cases_match_expected_output() {
+    let update = std::env::var(UPDATE_ENV_VAR).is_ok_and(|value| value == "1");
+    let dirs = case_dirs();
+    assert!(!dirs.is_empty(), "No cases in {}", CORPUS_DIR);
+
+    let mut failures = Vec::new();
+    for dir in &dirs {
+        let name = dir.file_name().unwrap().to_string_lossy();
+        let schema = read(dir, SCHEMA_FILE);
+        let input = read(dir, INPUT_FILE);
+
+        let mut validator = Validator::new_complete(&schema, &input)
+            .unwrap_or_else(|| panic!("Case '{}': Failed to create validator", name));
+        let (matches, errors) = run_case(&mut validator);
+
+        if update {
+            write_json(dir, EXPECTED_MATCHES_FILE, &matches);
+            write_json(dir, EXPECTED_ERRORS_FILE, &errors);
+            continue;
+        }
+
+        let expected_matches = read_json(dir, EXPECTED_MATCHES_FILE);
+        let expected_errors = read_json(dir, EXPECTED_ERRORS_FILE);
+        let mismatches: Vec<(&str, &Value, &Value)> = [
+            (EXPECTED_MATCHES_FILE, &expected_matches, &matches),
+            (EXPECTED_ERRORS_FILE, &expected_errors, &errors),
+        ]
+        .into_iter()
+        .filter(|(_, expected, actual)| expected != actual)
+        .collect();
+        if !mismatches.is_empty() {
+            failures.push(mismatch_report(&name, &validator, &mismatches));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} corpus cases failed (run with {}=1 to accept the new output):\n\n{}",
+        failures.len(),
+        dirs.len(),
+        UPDATE_ENV_VAR,
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,90 @@
+//! Drives the C interface the way a C caller would.
+#![cfg(feature = "ffi")]
+
+use std::ffi::{CStr, CString, c_char};
+
+use mdvalidate::ffi::{
+    MdvResult, mdv_result_errors_json, mdv_result_free, mdv_result_is_valid,
+    mdv_result_matches_json, mdv_validate,
+};
+use serde_json::{Value, json};
+
+/// Validate through the C interface, returning whether the input is valid,
+/// the matches and the errors.
+fn validate_via_ffi(schema: *const c_char, input: *const c_char) -> (bool, Value, Value) {
+    unsafe {
+        let result: *mut MdvResult = mdv_validate(schema, input);
+        assert!(!result.is_null());
+
+        let is_valid = mdv_result_is_valid(result);
+        let matches = CStr::from_ptr(mdv_result_matches_json(result)).to_str().unwrap();
+        let errors = CStr::from_ptr(mdv_result_errors_json(result)).to_str().unwrap();
+        let outcome = (
+            is_valid,
+            serde_json::from_str(matches).unwrap(),
+            serde_json::from_str(errors).unwrap(),
+        );
+
+        mdv_result_free(result);
+        outcome
+    }
+}
+
+#[test]
+fn ffi_valid_input() {
+    let schema = CString::new("# Hi `name:/\\w+/`\n").unwrap();
+    let input = CString::new("# Hi Wolf\n").unwrap();
+
+    let (is_valid, matches, errors) = validate_via_ffi(schema.as_ptr(), input.as_ptr());
+
+    assert_eq!(
+        errors,
+        json!({
+            "schema_version": 1,
+            "errors": [],
+            "stats": {
+                "sections": 1,
+                "sections_matched": 1,
+                "sections_with_warnings": 0,
+                "extra_input_blocks": 0,
+                "fields_captured": 1,
+                "fields_expected": 1,
+                "fields": [{"id": "name", "captured": 1, "expected": 1}],
+            },
+        })
+    );
+    assert!(is_valid);
+    assert_eq!(matches, json!({"name": "Wolf"}));
+}
+
+#[test]
+fn ffi_invalid_input() {
+    let schema = CString::new("# Hi `name:/\\d+/`\n").unwrap();
+    let input = CString::new("# Hi Wolf\n").unwrap();
+
+    let (is_valid, matches, errors) = validate_via_ffi(schema.as_ptr(), input.as_ptr());
+
+    assert_eq!(errors["errors"][0]["code"], "MDV004");
+    assert!(!is_valid);
+    assert_eq!(matches, json!({}));
+}
+
+#[test]
+fn ffi_bad_arguments() {
+    let input = CString::new("# Hi\n").unwrap();
+    let (is_valid, _, errors) = validate_via_ffi(std::ptr::null(), input.as_ptr());
+    assert_eq!(errors["errors"][0]["kind"], "invalid_argument");
+    assert!(!is_valid);
+
+    let not_utf8 = [0xffu8, 0xfe, 0];
+    let (is_valid, _, errors) =
+        validate_via_ffi(input.as_ptr(), not_utf8.as_ptr() as *const c_char);
+    assert_eq!(errors["errors"][0]["kind"], "invalid_argument");
+    assert!(!is_valid);
+
+    unsafe {
+        assert!(!mdv_result_is_valid(std::ptr::null()));
+        assert!(mdv_result_matches_json(std::ptr::null()).is_null());
+        mdv_result_free(std::ptr::null_mut());
+    }
+}
@@ -0,0 +1,64 @@
+//! Schemas and inputs the fuzz targets in `fuzz/` found to crash validation.
+//!
+//! Each of these used to panic. Now they must validate, with any problems
+//! reported as errors.
+
+use mdvalidate::mdschema::validation::errors::ValidationError;
+use mdvalidate::{Validation, validate_str};
+
+fn validate_without_panicking(schema: &str, input: &str) -> Validation {
+    validate_str(schema, input).expect("Failed to create validator")
+}
+
+fn has_internal_error(validation: &Validation) -> bool {
+    validation
+        .errors
+        .iter()
+        .any(|error| matches!(error, ValidationError::InternalInvariantViolated(_)))
+}
+
+#[test]
+fn matcher_suffix_longer_than_input() {
+    let validation = validate_without_panicking("`name:/\\w+/` and a suffix", "ab");
+
+    assert!(!validation.is_valid);
+    assert!(!has_internal_error(&validation));
+}
+
+#[test]
+fn matcher_suffix_without_extras() {
+    let validation = validate_without_panicking("`name:/\\w+/`suffix", "abcsuffix");
+
+    assert!(!has_internal_error(&validation));
+}
+
+#[test]
+fn matcher_prefix_ending_inside_multibyte_char() {
+    let validation = validate_without_panicking("préfix `name:/\\w+/`", "pr\u{1F600}");
+
+    assert!(!validation.is_valid);
+    assert!(!has_internal_error(&validation));
+}
+
+#[test]
+fn matcher_next_to_emphasis() {
+    validate_without_panicking("`a:/\\w+/` *b* `c:/\\w+/`", "x *b* y");
+    validate_without_panicking("*b* `a:/\\w+/`", "*b* x");
+    validate_without_panicking("`a:/\\w+/` *b*", "x **b**");
+}
+
+#[test]
+fn multibyte_text_in_table_cells() {
+    let schema = "| `a:/.*/` |\n| --- |\n| `b:/.*/` |\n";
+    let input = "| \u{00e9}\u{0301} |\n| --- |\n| \u{1F600} |\n";
+
+    validate_without_panicking(schema, input);
+}
+
+#[test]
+fn replacement_chars_from_invalid_utf8() {
+    let schema = String::from_utf8_lossy(b"# \xff`a:/\xfe/`\n\n- \xc3`b`{1,}\n");
+    let input = String::from_utf8_lossy(b"# \xff\xff\n\n- \xc3\x28\n");
+
+    validate_without_panicking(&schema, &input);
+}
@@ -0,0 +1,41 @@
+//! Sample documents generated from schemas with fake values must validate
+//! against the schemas they came from.
+
+use mdvalidate::mdschema::validation::generate::generate_sample;
+use mdvalidate::validate_str;
+
+const SCHEMAS: [&str; 10] = [
+    "# `title:/.+/`\n\nSome text.\n",
+    "# Release `version:@semver`\n\nOut on `released:@date` by `author:@email`.\n",
+    "# Items\n\n- `item:/\\w+/`{2,}\n",
+    "# Nested\n\n- `outer:/\\w+/`{1,1}\n    - `inner:/\\d+/`{2,2}\n",
+    "# Unbounded\n\n- `item:/[a-z]+/`{,}\n",
+    "Status: `status:[draft, published]`\n\nLiteral `code`!\n",
+    "# `name:/[A-Z][a-z]+/` `@scope:person`\n\nAge `age:int:/\\d+/` years\n",
+    "# Code\n\n```{lang:/\\w+/}\n{code}\n```\n",
+    "# Notes\n\n`ruler`\n\n`note:/.+/`\n",
+    "# Links\n\nSee `url:@url` or `id:@uuid` for more.\n",
+];
+
+#[test]
+fn fake_samples_validate_against_their_schema() {
+    for schema in SCHEMAS {
+        let sample = generate_sample(schema, true).expect("Failed to generate a sample");
+        let validation = validate_str(schema, &sample).expect("Failed to create validator");
+
+        assert!(
+            validation.errors.is_empty(),
+            "schema:\n{}\nsample:\n{}\nerrors: {:?}",
+            schema,
+            sample,
+            validation.errors
+        );
+    }
+}
+
+#[test]
+fn placeholder_samples_name_each_matcher() {
+    let sample = generate_sample("# `title:/.+/`\n\n- `item:/\\w+/`{,}\n", false).unwrap();
+
+    assert_eq!(sample, "# <title>\n\n- <item>\n- <item>\n");
+}
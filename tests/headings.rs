@@ -29,3 +29,27 @@ test_case!(
         }
     )]
 );
+
+test_case!(
+    heading_matchers_around_literal,
+    r#"# `id:/\d+/`: `title:/.+/`"#,
+    r#"# 42: The answer"#,
+    json!({"id": "42", "title": "The answer"}),
+    vec![]
+);
+
+test_case!(
+    heading_matcher_then_literal,
+    r#"# `name:/\w+/` release notes"#,
+    r#"# Jammy release notes"#,
+    json!({"name": "Jammy"}),
+    vec![]
+);
+
+test_case!(
+    setext_heading_matcher,
+    "Chapter `number:/\\d+/`\n===\n\nSome text",
+    "Chapter 3\n===\n\nSome text",
+    json!({"number": "3"}),
+    vec![]
+);
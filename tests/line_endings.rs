@@ -0,0 +1,39 @@
+//! Documents written on Windows, with `\r\n` line endings and trailing
+//! whitespace, must validate against schemas written elsewhere.
+
+use mdvalidate::mdschema::validation::validator::ValidateOptions;
+use mdvalidate::{validate_str, validate_str_with_options};
+use serde_json::json;
+
+const SCHEMA: &str = include_str!("fixtures/crlf/schema.md");
+const INPUT: &str = include_str!("fixtures/crlf/input.md");
+
+#[test]
+fn crlf_input_validates_with_clean_captures() {
+    assert!(INPUT.contains("\r\n"), "The fixture lost its line endings");
+
+    let validation = validate_str(SCHEMA, INPUT).expect("Failed to create validator");
+
+    assert!(validation.is_valid, "errors: {:?}", validation.errors);
+    assert_eq!(
+        validation.matches,
+        json!({"version": "1.2.0", "author": "Alice Smith"})
+    );
+}
+
+#[test]
+fn crlf_capture_spans_point_into_the_original_input() {
+    let options = ValidateOptions {
+        include_spans: true,
+        ..Default::default()
+    };
+    let validation =
+        validate_str_with_options(SCHEMA, INPUT, &options).expect("Failed to create validator");
+
+    assert!(validation.is_valid, "errors: {:?}", validation.errors);
+    let author = &validation.matches["author"];
+    let start = author["start"]["byte"].as_u64().unwrap() as usize;
+    let end = author["end"]["byte"].as_u64().unwrap() as usize;
+    assert_eq!(&INPUT[start..end], "Alice Smith");
+    assert_eq!(author["start"], json!({"line": 5, "col": 12, "byte": 50}));
+}
@@ -3,7 +3,9 @@ use serde_json::json;
 #[macro_use]
 mod helpers;
 
-use mdvalidate::mdschema::validation::errors::{SchemaViolationError, ValidationError};
+use mdvalidate::mdschema::validation::errors::{
+    NodeContentMismatchKind, SchemaViolationError, ValidationError,
+};
 
 test_case!(
     ordered_list_literal,
@@ -67,3 +69,37 @@ test_case!(
         }
     )]
 );
+
+test_case!(
+    depth_extras_reuse_the_matcher,
+    r#"
+# Items
+
+- `item:/\w+/`+++{1,}
+"#,
+    r#"
+# Items
+
+- a
+    - b
+        - c
+"#,
+    json!({"item": ["a", ["b", ["c"]]]}),
+    vec![]
+);
+
+test_case!(
+    repeated_list_matcher_with_message,
+    r#"- `count:/\d+/|Write each count in digits`{1,}"#,
+    r#"- two"#,
+    json!({}),
+    vec![ValidationError::SchemaViolation(
+        SchemaViolationError::NodeContentMismatch {
+            schema_index: 5,
+            input_index: 5,
+            expected: r"\d+".into(),
+            actual: "two".into(),
+            kind: NodeContentMismatchKind::MatcherMessage("Write each count in digits".into()),
+        }
+    )]
+);
@@ -48,9 +48,107 @@ test_case!(
         SchemaViolationError::NodeContentMismatch {
             schema_index: 2,
             input_index: 2,
-            expected: "^[a-z]+".into(),
+            expected: "[a-z]+".into(),
             actual: "123".into(),
             kind: NodeContentMismatchKind::Matcher,
         }
     )]
 );
+
+test_case!(
+    matcher_mismatch_with_message,
+    r#"`date:/\d{4}-\d{2}-\d{2}/|Use an ISO date, like 2024-01-31!`"#,
+    r#"tomorrow"#,
+    json!({}),
+    vec![ValidationError::SchemaViolation(
+        SchemaViolationError::NodeContentMismatch {
+            schema_index: 2,
+            input_index: 2,
+            expected: r"\d{4}-\d{2}-\d{2}".into(),
+            actual: "tomorrow".into(),
+            kind: NodeContentMismatchKind::MatcherMessage(
+                "Use an ISO date, like 2024-01-31!".into()
+            ),
+        }
+    )]
+);
+
+test_case!(
+    matcher_with_message_matches,
+    r#"Released: `date:/\d{4}-\d{2}-\d{2}/|Use an ISO date` (final)"#,
+    r#"Released: 2024-01-31 (final)"#,
+    json!({"date": "2024-01-31"}),
+    vec![]
+);
+
+test_case!(
+    matcher_with_message_without_id,
+    r#"Count: `/\d+/|Write the count in digits`"#,
+    r#"Count: three"#,
+    json!({}),
+    vec![ValidationError::SchemaViolation(
+        SchemaViolationError::NodeContentMismatch {
+            schema_index: 3,
+            input_index: 2,
+            expected: r"\d+".into(),
+            actual: "three".into(),
+            kind: NodeContentMismatchKind::MatcherMessage("Write the count in digits".into()),
+        }
+    )]
+);
+
+test_case!(
+    optional_matcher_left_out,
+    r#"Env: `env:/\w+/`? (deployed)"#,
+    r#"Env: (deployed)"#,
+    json!({}),
+    vec![]
+);
+
+test_case!(
+    default_matcher_left_out,
+    r#"Env: `env:/\w+/`=prod"#,
+    r#"Env:"#,
+    json!({"env": "prod"}),
+    vec![]
+);
+
+test_case!(
+    default_matcher_present,
+    r#"Env: `env:/\w+/`=prod"#,
+    r#"Env: staging"#,
+    json!({"env": "staging"}),
+    vec![]
+);
+
+test_case!(
+    default_matcher_left_out_between_matchers,
+    r#"From `from:/\d+/`=0 to `to:/\d+/`"#,
+    r#"From to 5"#,
+    json!({"from": "0", "to": "5"}),
+    vec![]
+);
+
+test_case!(
+    double_backtick_code_is_literal,
+    r#"Use ``foo:/bar/`` here"#,
+    r#"Use `foo:/bar/` here"#,
+    json!({}),
+    vec![]
+);
+
+test_case!(
+    escaped_brace_after_matcher_is_text,
+    r#"Limit: `limit:/\d+/`\{1,3} per day"#,
+    r#"Limit: 5{1,3} per day"#,
+    json!({"limit": "5"}),
+    vec![]
+);
+
+test_case!(
+    backslash_before_other_text_after_matcher_stays,
+    r#"Path: `dir:/\w+/`\d"#,
+    r#"Path: src\d"#,
+    json!({"dir": "src"}),
+    vec![]
+);
@@ -3,9 +3,7 @@ use serde_json::json;
 #[macro_use]
 mod helpers;
 
-use mdvalidate::mdschema::validation::errors::{
-    MalformedStructureKind, SchemaViolationError, ValidationError,
-};
+use mdvalidate::mdschema::validation::errors::{SchemaViolationError, ValidationError};
 
 test_case!(
     node_heading_and_paragraph,
@@ -29,10 +27,24 @@ test_case!(
     r#"# Hi"#,
     json!({}),
     vec![ValidationError::SchemaViolation(
-        SchemaViolationError::MalformedNodeStructure {
+        SchemaViolationError::TrailingContent {
             schema_index: 0,
             input_index: 1,
-            kind: MalformedStructureKind::InputHasChildSchemaDoesnt,
+            extra_blocks: 1,
+        }
+    )]
+);
+
+test_case!(
+    trailing_blocks_reported_once,
+    "Hello\n",
+    "Hello\n\nExtra\n\nMore\n",
+    json!({}),
+    vec![ValidationError::SchemaViolation(
+        SchemaViolationError::TrailingContent {
+            schema_index: 1,
+            input_index: 3,
+            extra_blocks: 2,
         }
     )]
 );
@@ -0,0 +1,57 @@
+//! The prelude is the API that minor releases keep stable, so anything that
+//! moves it, or takes something out of it, has to show up here.
+
+use std::any::{type_name, type_name_of_val};
+
+use mdvalidate::prelude::*;
+
+#[test]
+fn prelude_surface_is_unchanged() {
+    let surface = [
+        type_name::<Validator>(),
+        type_name::<dyn ValidatorState>(),
+        type_name::<ValidateOptions>(),
+        type_name::<ValidationError>(),
+        type_name::<ValidatorReport<'static>>(),
+        type_name::<Matcher>(),
+        type_name::<Validation>(),
+        type_name::<ReadInputError>(),
+        type_name_of_val(&validate_str),
+        type_name_of_val(&validate_str_with_options),
+        type_name_of_val(&validate_reader::<&[u8]>),
+        type_name_of_val(&validate_reader_with_progress::<&[u8]>),
+    ];
+
+    assert_eq!(
+        surface,
+        [
+            "mdvalidate::mdschema::validation::validator::Validator",
+            "dyn mdvalidate::mdschema::validation::validator::ValidatorState",
+            "mdvalidate::mdschema::validation::validator::ValidateOptions",
+            "mdvalidate::mdschema::validation::errors::ValidationError",
+            "mdvalidate::mdschema::validation::json_report::ValidatorReport",
+            "mdvalidate::mdschema::validation::matchers::matcher::Matcher",
+            "mdvalidate::mdschema::validation::validate::Validation",
+            "mdvalidate::mdschema::validation::validate::ReadInputError",
+            "mdvalidate::mdschema::validation::validate::validate_str",
+            "mdvalidate::mdschema::validation::validate::validate_str_with_options",
+            "mdvalidate::mdschema::validation::validate::validate_reader<&[u8]>",
+            "mdvalidate::mdschema::validation::validate::validate_reader_with_progress<&[u8]>",
+        ]
+    );
+}
+
+#[test]
+fn prelude_is_enough_to_validate() {
+    let options = ValidateOptions::default();
+    let validation =
+        validate_str_with_options("# `title:/\\w+/`\n", "# Hello\n", &options).unwrap();
+    assert!(validation.is_valid);
+    assert_eq!(validation.matches["title"], "Hello");
+
+    let mut validator = Validator::new_incomplete("# `title:/\\w+/`\n", "# Hel").unwrap();
+    validator.read_final_input("# Hello\n").unwrap();
+    validator.validate();
+    assert_eq!(validator.errors_so_far().count(), 0);
+    assert_eq!(validator.matches_so_far()["title"], "Hello");
+}
@@ -3,9 +3,8 @@ use serde_json::json;
 #[macro_use]
 mod helpers;
 
-use mdvalidate::mdschema::validation::errors::{
-    MalformedStructureKind, SchemaViolationError, ValidationError,
-};
+use mdvalidate::mdschema::validation::errors::{SchemaViolationError, ValidationError};
+use mdvalidate::mdschema::validation::validator::{Validator, ValidatorState};
 
 test_case!(ruler_dashes, r#"---"#, r#"---"#, json!({}), vec![]);
 
@@ -15,12 +14,91 @@ test_case!(
     r#""#,
     json!({}),
     vec![ValidationError::SchemaViolation(
-        SchemaViolationError::MalformedNodeStructure {
+        SchemaViolationError::MissingContent {
             schema_index: 1,
             input_index: 0,
-            kind: MalformedStructureKind::SchemaHasChildInputDoesnt,
+            expected: "---".to_string(),
         }
     )]
 );
 
 test_case!(ruler_stars, r#"***"#, r#"***"#, json!({}), vec![]);
+
+test_case!(
+    ruler_matcher,
+    "# Title\n\n`ruler`\n\n`note:/.+/`\n",
+    "# Title\n\n---\n\nHello\n",
+    json!({"note": "Hello"}),
+    vec![]
+);
+
+test_case!(
+    ruler_matcher_missing,
+    "`ruler`",
+    "Text",
+    json!({}),
+    vec![ValidationError::SchemaViolation(
+        SchemaViolationError::NodeTypeMismatch {
+            schema_index: 1,
+            input_index: 1,
+            expected: "thematic_break".to_string(),
+            actual: "paragraph".to_string(),
+        }
+    )]
+);
+
+test_case!(
+    optional_ruler_absent,
+    "# Title\n\n`ruler`?\n\n`note:/.+/`\n",
+    "# Title\n\nHello\n",
+    json!({"note": "Hello"}),
+    vec![]
+);
+
+test_case!(
+    optional_ruler_present,
+    "# Title\n\n`ruler`?\n\n`note:/.+/`\n",
+    "# Title\n\n***\n\nHello\n",
+    json!({"note": "Hello"}),
+    vec![]
+);
+
+test_case!(
+    optional_ruler_at_end_absent,
+    "`note:/.+/`\n\n`ruler`?\n",
+    "Hello\n",
+    json!({"note": "Hello"}),
+    vec![]
+);
+
+test_case!(
+    multiple_consecutive_rulers,
+    "`first:/.+/`\n\n`ruler`+\n\n`second:/.+/`\n",
+    "One\n\n---\n\n***\n\n___\n\nTwo\n",
+    json!({"first": "One", "second": "Two"}),
+    vec![]
+);
+
+test_case!(
+    one_or_more_rulers_with_one_ruler,
+    "`first:/.+/`\n\n`ruler`+\n\n`second:/.+/`\n",
+    "One\n\n---\n\nTwo\n",
+    json!({"first": "One", "second": "Two"}),
+    vec![]
+);
+
+#[test]
+fn ruler_at_end_of_incomplete_input() {
+    let schema = "`first:/.+/`\n\n`ruler`+\n\n`second:/.+/`\n";
+
+    // More rulers, or the paragraph after them, may still come
+    let mut validator = Validator::new_incomplete(schema, "One\n\n---\n").unwrap();
+    validator.validate();
+    assert_eq!(validator.errors_so_far().count(), 0);
+
+    let mut validator =
+        Validator::new_complete(schema, "One\n\n---\n\n---\n\nTwo\n").unwrap();
+    validator.validate();
+    assert_eq!(validator.errors_so_far().count(), 0);
+    assert_eq!(validator.matches_so_far(), &json!({"first": "One", "second": "Two"}));
+}
@@ -108,7 +108,7 @@ test_case!(
         SchemaViolationError::NodeContentMismatch {
             schema_index: 25,
             input_index: 27,
-            expected: "^\\d+".to_string(),
+            expected: "\\d+".to_string(),
             actual: "not_a_number".to_string(),
             kind: NodeContentMismatchKind::Matcher,
         }
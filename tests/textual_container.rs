@@ -6,6 +6,7 @@ mod helpers;
 use mdvalidate::mdschema::validation::errors::{
     NodeContentMismatchKind, SchemaViolationError, ValidationError,
 };
+use mdvalidate::mdschema::validation::validator::{Validator, ValidatorState};
 
 test_case!(
     paragraph_literal,
@@ -54,3 +55,133 @@ test_case!(
     json!({"url": "https://example.com", "other": "hi"}),
     vec![]
 );
+
+test_case!(
+    emphasis_matcher,
+    r#"Hi *`label:/\w+/`*"#,
+    r#"Hi *there*"#,
+    json!({"label": "there"}),
+    vec![]
+);
+
+test_case!(
+    strong_emphasis_matcher_with_text,
+    r#"**Dear `name:/\w+/`**, hello"#,
+    r#"**Dear Wolf**, hello"#,
+    json!({"name": "Wolf"}),
+    vec![]
+);
+
+test_case!(
+    nested_emphasis_matcher,
+    r#"_*`x:/\w+/`*_"#,
+    r#"_*deep*_"#,
+    json!({"x": "deep"}),
+    vec![]
+);
+
+test_case!(
+    strong_emphasis_matcher_vs_emphasis,
+    r#"**`name:/\w+/`**"#,
+    r#"*Wolf*"#,
+    json!({}),
+    vec![ValidationError::SchemaViolation(
+        SchemaViolationError::NodeTypeMismatch {
+            schema_index: 2,
+            input_index: 2,
+            expected: "strong_emphasis".into(),
+            actual: "emphasis".into(),
+        }
+    )]
+);
+
+test_case!(
+    strong_emphasis_matcher_vs_plain_text,
+    r#"Product: **`product:/\w+/`** is great"#,
+    r#"Product: Widget is great"#,
+    json!({}),
+    vec![ValidationError::SchemaViolation(
+        SchemaViolationError::NodeTypeMismatch {
+            schema_index: 3,
+            input_index: 2,
+            expected: "strong_emphasis".into(),
+            actual: "text".into(),
+        }
+    )]
+);
+
+test_case!(
+    strikethrough_matcher,
+    r#"Was ~~`old:/\w+/`~~ now"#,
+    r#"Was ~~gone~~ now"#,
+    json!({"old": "gone"}),
+    vec![]
+);
+
+test_case!(
+    emphasis_before_matcher,
+    r#"*Status:* `status:/\w+/`"#,
+    r#"*Status:* done"#,
+    json!({"status": "done"}),
+    vec![]
+);
+
+test_case!(
+    strong_emphasis_after_matcher,
+    r#"Status: `status:/\w+/` **now**"#,
+    r#"Status: done **now**"#,
+    json!({"status": "done"}),
+    vec![]
+);
+
+test_case!(
+    emphasis_around_matcher,
+    r#"*Status:* `status:/\w+/` **now**"#,
+    r#"*Status:* done **now**"#,
+    json!({"status": "done"}),
+    vec![]
+);
+
+#[test]
+fn emphasis_after_matcher_is_validated() {
+    for (schema, input) in [
+        (
+            r#"Status: `status:/\w+/` **now**"#,
+            "Status: done **later**",
+        ),
+        (
+            r#"*Status:* `status:/\w+/` **now**"#,
+            "*Status:* done **later**",
+        ),
+        (r#"`status:/\w+/` and **now**"#, "done or **now**"),
+    ] {
+        let (errors, _) = crate::helpers::run_test_case(schema, input);
+        assert!(
+            matches!(
+                errors.as_slice(),
+                [ValidationError::SchemaViolation(
+                    SchemaViolationError::NodeContentMismatch { .. }
+                )]
+            ),
+            "{}: {:?}",
+            input,
+            errors
+        );
+    }
+}
+
+#[test]
+fn emphasis_before_matcher_while_streaming() {
+    let schema = r#"*Status:* `status:/\w+/`"#;
+
+    // The emphasis is complete, but more of the matched text may still come
+    let mut validator = Validator::new_incomplete(schema, "*Status:* do").unwrap();
+    validator.validate();
+    assert_eq!(validator.errors_so_far().count(), 0);
+    assert_eq!(validator.matches_so_far(), &json!({}));
+
+    validator.read_final_input("*Status:* done").unwrap();
+    validator.validate();
+    assert_eq!(validator.errors_so_far().count(), 0);
+    assert_eq!(validator.matches_so_far(), &json!({"status": "done"}));
+}
@@ -1,10 +1,14 @@
+mod mdv_report;
 mod node_print;
+mod tree_pair;
 
 use clap::{Parser, Subcommand};
-use node_print::PrettyPrint;
+use mdv_report::ReportedError;
+use node_print::{PrettyPrint, node_label};
 use std::fs;
 use std::io::{self, Read};
-use tree_sitter::Parser as TSParser;
+use tree_pair::PairPrinter;
+use tree_sitter::{Parser as TSParser, Tree};
 use tree_sitter_markdown::language;
 
 #[derive(Parser)]
@@ -26,6 +30,28 @@ enum Commands {
         #[arg(short, long)]
         show_text: bool,
     },
+    /// Print the schema and input syntax trees side by side, marking where
+    /// they first differ
+    Compare {
+        /// Schema file path
+        schema: String,
+
+        /// Input file path
+        input: String,
+
+        /// Show text content of nodes in the trees
+        #[arg(short, long)]
+        show_text: bool,
+
+        /// Mark the nodes of the Nth error (from 1) that mdv reports, and
+        /// print where it left the schema and input
+        #[arg(long, value_name = "N")]
+        fail_at: Option<usize>,
+
+        /// The mdv binary to run for --fail-at
+        #[arg(long, default_value = "mdv")]
+        mdv: String,
+    },
 }
 
 fn read_input(path: Option<String>) -> io::Result<String> {
@@ -38,6 +64,15 @@ fn read_input(path: Option<String>) -> io::Result<String> {
     }
 }
 
+fn parse_markdown(input: &str) -> Tree {
+    let mut parser = TSParser::new();
+    parser
+        .set_language(&language())
+        .expect("Failed to load Markdown language");
+
+    parser.parse(input, None).expect("Failed to parse")
+}
+
 fn main() {
     let cli = Cli::parse();
 
@@ -45,12 +80,7 @@ fn main() {
         Commands::PrintTree { file, show_text } => {
             let input = read_input(file).expect("Failed to read input");
 
-            let mut parser = TSParser::new();
-            parser
-                .set_language(&language())
-                .expect("Failed to load Markdown language");
-
-            let tree = parser.parse(&input, None).expect("Failed to parse");
+            let tree = parse_markdown(&input);
             let node = tree.root_node();
 
             let mut printer = node.get_pretty_printer();
@@ -59,5 +89,54 @@ fn main() {
             }
             print!("{}", printer.print(&input));
         }
+        Commands::Compare {
+            schema,
+            input,
+            show_text,
+            fail_at,
+            mdv,
+        } => {
+            let schema_str = fs::read_to_string(&schema).expect("Failed to read schema");
+            let input_str = fs::read_to_string(&input).expect("Failed to read input");
+            let schema_tree = parse_markdown(&schema_str);
+            let input_tree = parse_markdown(&input_str);
+
+            let error = fail_at.map(|n| {
+                ReportedError::nth(
+                    &mdv,
+                    (schema.as_str(), input.as_str()),
+                    (&schema_tree, &input_tree),
+                    n,
+                )
+                .unwrap_or_else(|message| {
+                    eprintln!("{}", message);
+                    std::process::exit(1);
+                })
+            });
+
+            let mut printer = PairPrinter::new(schema_tree.root_node(), input_tree.root_node());
+            if show_text {
+                printer = printer.show_text();
+            }
+            if let Some(error) = &error {
+                printer = printer.highlight(error.schema_index, error.input_index);
+            }
+            print!("{}", printer.print(&schema_str, &input_str));
+
+            if let Some(error) = error {
+                println!("\nerror[{}]: {}", error.code, error.message);
+                for (side, tree, index, source) in [
+                    ("schema", &schema_tree, error.schema_index, &schema_str),
+                    ("input", &input_tree, error.input_index, &input_str),
+                ] {
+                    let node = index.map_or("(none)".to_string(), |index| {
+                        let mut cursor = tree.walk();
+                        cursor.goto_descendant(index);
+                        node_label(&cursor.node(), index, Some(source.as_str()))
+                    });
+                    println!("  {} cursor: {}", side, node);
+                }
+            }
+        }
     }
 }
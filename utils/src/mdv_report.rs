@@ -0,0 +1,90 @@
+use serde_json::Value;
+use std::process::Command;
+use tree_sitter::Tree;
+
+/// An error from `mdv`'s JSON report, with the descendant indexes of the
+/// nodes it points at.
+///
+/// mdvalidate depends on these utils, so rather than validating ourselves we
+/// run `mdv --error-format json` and find the nodes of the error's locations
+/// in our own trees.
+pub struct ReportedError {
+    pub code: String,
+    pub message: String,
+    pub schema_index: Option<usize>,
+    pub input_index: Option<usize>,
+}
+
+impl ReportedError {
+    /// The `n`th error (from 1) that `mdv` reports for an input.
+    ///
+    /// # Arguments
+    ///
+    /// * `mdv`: The `mdv` binary to run.
+    /// * `paths`: The paths of the schema and the input.
+    /// * `trees`: The schema and input trees, to find the error's nodes in.
+    /// * `n`: Which error to get.
+    pub fn nth(
+        mdv: &str,
+        (schema_path, input_path): (&str, &str),
+        (schema_tree, input_tree): (&Tree, &Tree),
+        n: usize,
+    ) -> Result<Self, String> {
+        let output = Command::new(mdv)
+            .args([schema_path, input_path, "--error-format", "json"])
+            .output()
+            .map_err(|error| format!("Failed to run {}: {}", mdv, error))?;
+
+        // The report is the first thing written, and the matches may follow
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let report = serde_json::Deserializer::from_str(&stdout)
+            .into_iter::<Value>()
+            .next()
+            .and_then(Result::ok)
+            .ok_or_else(|| {
+                format!(
+                    "{} didn't write a JSON report: {}",
+                    mdv,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )
+            })?;
+
+        let errors = report["errors"].as_array().cloned().unwrap_or_default();
+        let error = n
+            .checked_sub(1)
+            .and_then(|index| errors.get(index))
+            .ok_or_else(|| format!("There is no error {}, only {}", n, errors.len()))?;
+
+        Ok(Self {
+            code: error["code"].as_str().unwrap_or_default().to_string(),
+            message: error["message"].as_str().unwrap_or_default().to_string(),
+            schema_index: find_located_node(schema_tree, &error["schema"]),
+            input_index: find_located_node(input_tree, &error["input"]),
+        })
+    }
+}
+
+/// The descendant index of the first node with the bytes and kind of a
+/// location in the report.
+fn find_located_node(tree: &Tree, location: &Value) -> Option<usize> {
+    let start = location["start"]["byte"].as_u64()? as usize;
+    let end = location["end"]["byte"].as_u64()? as usize;
+    let kind = location["node_kind"].as_str()?;
+
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        if node.byte_range() == (start..end) && node.kind() == kind {
+            return Some(cursor.descendant_index());
+        }
+
+        if cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return None;
+            }
+        }
+    }
+}
@@ -9,6 +9,7 @@ pub struct Printer<'a> {
     node: Node<'a>,
     highlight_indices: Vec<usize>,
     show_text: bool,
+    start_index: usize,
 }
 
 impl<'a> Printer<'a> {
@@ -17,6 +18,7 @@ impl<'a> Printer<'a> {
             node,
             highlight_indices: Vec::new(),
             show_text: false,
+            start_index: 0,
         }
     }
 
@@ -30,8 +32,17 @@ impl<'a> Printer<'a> {
         self
     }
 
+    /// Number nodes starting from this index instead of 0.
+    ///
+    /// Useful when printing a subtree, so that the printed indices (and the
+    /// highlighted ones) match the descendant indices in the full tree.
+    pub fn start_index(mut self, index: usize) -> Self {
+        self.start_index = index;
+        self
+    }
+
     pub fn print(self, source: &str) -> String {
-        let mut next_index = 0;
+        let mut next_index = self.start_index;
         let wrapper = build_tree_with_config(
             self.node,
             &mut next_index,
@@ -68,28 +79,12 @@ impl<'a> TreeItem for NodeWrapper<'a> {
             ""
         };
 
-        let text_display = if let Some(source) = self.source {
-            let text = self.node.utf8_text(source.as_bytes()).unwrap_or("");
-            let text_preview = if text.len() > 50 {
-                format!(" \"{}...\"", &text[..47])
-            } else {
-                format!(" \"{}\"", text)
-            };
-            text_preview.replace('\n', "\\n")
-        } else {
-            String::new()
-        };
-
         write!(
             f,
             "{}",
             style.paint(format!(
-                "({}[{}]{}..{}){}{}",
-                self.node.kind(),
-                self.index,
-                self.node.byte_range().start,
-                self.node.byte_range().end,
-                text_display,
+                "{}{}",
+                node_label(&self.node, self.index, self.source),
                 highlight_marker
             ))
         )
@@ -100,6 +95,31 @@ impl<'a> TreeItem for NodeWrapper<'a> {
     }
 }
 
+/// How a node is shown in a tree, like `(text[3]2..7)`, followed by the start
+/// of its text if we have the source.
+pub fn node_label(node: &Node, index: usize, source: Option<&str>) -> String {
+    let text_display = if let Some(source) = source {
+        let text = node.utf8_text(source.as_bytes()).unwrap_or("");
+        let text_preview = if text.chars().count() > 50 {
+            format!(" \"{}...\"", text.chars().take(47).collect::<String>())
+        } else {
+            format!(" \"{}\"", text)
+        };
+        text_preview.replace('\n', "\\n")
+    } else {
+        String::new()
+    };
+
+    format!(
+        "({}[{}]{}..{}){}",
+        node.kind(),
+        index,
+        node.byte_range().start,
+        node.byte_range().end,
+        text_display
+    )
+}
+
 fn build_tree_with_config<'a>(
     node: Node<'a>,
     next_index: &mut usize,
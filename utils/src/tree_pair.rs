@@ -0,0 +1,192 @@
+use tree_sitter::Node;
+
+use crate::node_print::node_label;
+
+/// A node in one of the two columns, with its descendant index in its tree.
+#[derive(Clone, Copy)]
+pub struct Cell<'a> {
+    pub node: Node<'a>,
+    pub index: usize,
+}
+
+/// A row of the schema and input trees side by side.
+pub struct Row<'a> {
+    pub depth: usize,
+    pub schema: Option<Cell<'a>>,
+    pub input: Option<Cell<'a>>,
+}
+
+impl Row<'_> {
+    /// Whether the trees differ at this row, because one of them has no node
+    /// here or the nodes are of different kinds.
+    pub fn diverges(&self) -> bool {
+        match (&self.schema, &self.input) {
+            (Some(schema), Some(input)) => schema.node.kind() != input.node.kind(),
+            _ => true,
+        }
+    }
+
+    fn has_index(&self, schema_index: Option<usize>, input_index: Option<usize>) -> bool {
+        let schema = self.schema.map(|cell| cell.index);
+        let input = self.input.map(|cell| cell.index);
+        (schema.is_some() && schema == schema_index) || (input.is_some() && input == input_index)
+    }
+}
+
+/// Prints a schema tree and an input tree side by side.
+///
+/// The rows pair up the nodes like the node walker does: the roots, then the
+/// children of each pair in order. A node the other tree has nothing for is
+/// printed against an empty column, and so is everything below it.
+pub struct PairPrinter<'a> {
+    schema: Node<'a>,
+    input: Node<'a>,
+    show_text: bool,
+    highlight: (Option<usize>, Option<usize>),
+}
+
+impl<'a> PairPrinter<'a> {
+    pub fn new(schema: Node<'a>, input: Node<'a>) -> Self {
+        Self {
+            schema,
+            input,
+            show_text: false,
+            highlight: (None, None),
+        }
+    }
+
+    pub fn show_text(mut self) -> Self {
+        self.show_text = true;
+        self
+    }
+
+    /// Mark the rows with these descendant indexes, like the nodes an error
+    /// points at.
+    pub fn highlight(mut self, schema_index: Option<usize>, input_index: Option<usize>) -> Self {
+        self.highlight = (schema_index, input_index);
+        self
+    }
+
+    /// The rows of the two trees, in the order they are printed.
+    pub fn rows(&self) -> Vec<Row<'a>> {
+        let mut rows = Vec::new();
+        let mut next_indexes = (0, 0);
+        align_nodes(
+            Some(self.schema),
+            Some(self.input),
+            0,
+            &mut next_indexes,
+            &mut rows,
+        );
+        rows
+    }
+
+    pub fn print(self, schema_source: &str, input_source: &str) -> String {
+        let rows = self.rows();
+        let first_divergence = rows.iter().position(Row::diverges);
+
+        let label = |cell: Option<Cell>, depth: usize, source: &str| {
+            cell.map_or(String::new(), |cell| {
+                let source = self.show_text.then_some(source);
+                format!(
+                    "{}{}",
+                    "  ".repeat(depth),
+                    node_label(&cell.node, cell.index, source)
+                )
+            })
+        };
+        let lines: Vec<(String, String)> = rows
+            .iter()
+            .map(|row| {
+                (
+                    label(row.schema, row.depth, schema_source),
+                    label(row.input, row.depth, input_source),
+                )
+            })
+            .collect();
+        let width = lines
+            .iter()
+            .map(|(schema, _)| schema.chars().count())
+            .chain(["schema".len()])
+            .max()
+            .unwrap_or_default();
+
+        let mut output = format!("{:<width$} | input\n", "schema");
+        for (row_index, (row, (schema, input))) in rows.iter().zip(&lines).enumerate() {
+            let marker = if row.has_index(self.highlight.0, self.highlight.1) {
+                " <-- error"
+            } else if Some(row_index) == first_divergence {
+                " <-- differs"
+            } else {
+                ""
+            };
+            output.push_str(format!("{:<width$} | {}{}", schema, input, marker).trim_end());
+            output.push('\n');
+        }
+
+        match first_divergence.map(|row_index| &rows[row_index]) {
+            Some(row) => output.push_str(&format!(
+                "\nThe trees first differ at schema node {} and input node {}\n",
+                describe(row.schema),
+                describe(row.input)
+            )),
+            None => output.push_str("\nThe trees have the same shape\n"),
+        }
+        output
+    }
+}
+
+fn describe(cell: Option<Cell>) -> String {
+    cell.map_or("(none)".to_string(), |cell| {
+        format!("{}[{}]", cell.node.kind(), cell.index)
+    })
+}
+
+fn align_nodes<'a>(
+    schema: Option<Node<'a>>,
+    input: Option<Node<'a>>,
+    depth: usize,
+    next_indexes: &mut (usize, usize),
+    rows: &mut Vec<Row<'a>>,
+) {
+    // Descendant indexes count the nodes of a tree in this same order
+    let schema = schema.map(|node| {
+        next_indexes.0 += 1;
+        Cell {
+            node,
+            index: next_indexes.0 - 1,
+        }
+    });
+    let input = input.map(|node| {
+        next_indexes.1 += 1;
+        Cell {
+            node,
+            index: next_indexes.1 - 1,
+        }
+    });
+
+    let schema_children = children(schema);
+    let input_children = children(input);
+    rows.push(Row {
+        depth,
+        schema,
+        input,
+    });
+
+    for child in 0..schema_children.len().max(input_children.len()) {
+        align_nodes(
+            schema_children.get(child).copied(),
+            input_children.get(child).copied(),
+            depth + 1,
+            next_indexes,
+            rows,
+        );
+    }
+}
+
+fn children<'a>(cell: Option<Cell<'a>>) -> Vec<Node<'a>> {
+    match cell {
+        Some(cell) => cell.node.children(&mut cell.node.walk()).collect(),
+        None => Vec::new(),
+    }
+}